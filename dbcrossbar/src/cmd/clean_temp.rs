@@ -0,0 +1,67 @@
+//! The `clean-temp` subcommand.
+
+use common_failures::Result;
+use dbcrossbarlib::{
+    clean_temp::{delete_orphaned_temporary, find_orphaned_temporaries},
+    config::Configuration,
+    Context,
+};
+use failure::format_err;
+use slog::warn;
+use std::time::Duration;
+use structopt::{self, StructOpt};
+
+/// Temporary cleanup arguments.
+#[derive(Debug, StructOpt)]
+pub(crate) struct Opt {
+    /// Temporary directories to clean up, in addition to those in our
+    /// configuration (can be repeated).
+    #[structopt(long = "temporary")]
+    temporaries: Vec<String>,
+
+    /// Only delete temporary directories which haven't been modified for this
+    /// many hours.
+    #[structopt(long = "older-than-hours", default_value = "24")]
+    older_than_hours: u32,
+
+    /// List the directories we would delete, without deleting them.
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+}
+
+/// Delete temporary directories left behind by earlier copies.
+pub(crate) async fn run(
+    ctx: Context,
+    config: Configuration,
+    _enable_unstable: bool,
+    opt: Opt,
+) -> Result<()> {
+    let mut temporaries = opt.temporaries.clone();
+    temporaries.extend(config.temporaries()?);
+    let older_than = Duration::from_secs(u64::from(opt.older_than_hours) * 60 * 60);
+    let orphans = find_orphaned_temporaries(&ctx, &temporaries, older_than).await?;
+
+    let mut failed = 0;
+    for orphan in &orphans {
+        if opt.dry_run {
+            println!("would delete {} (modified {})", orphan.url, orphan.modified);
+        } else {
+            match delete_orphaned_temporary(&ctx, orphan).await {
+                Ok(()) => println!("deleted {}", orphan.url),
+                Err(err) => {
+                    warn!(ctx.log(), "could not delete {}: {}", orphan.url, err);
+                    failed += 1;
+                }
+            }
+        }
+    }
+    if failed > 0 {
+        Err(format_err!(
+            "could not delete {} of {} temporary directories",
+            failed,
+            orphans.len(),
+        ))
+    } else {
+        Ok(())
+    }
+}
@@ -20,7 +20,8 @@ pub(crate) struct Opt {
 /// Shared options that specify a key.
 #[derive(Debug, StructOpt)]
 pub(crate) struct KeyOpt {
-    /// The configuration key to operate on [values: temporary].
+    /// The configuration key to operate on [values: temporary,
+    /// concurrency_limit, bandwidth_limit].
     key: String,
     // We'll probably extend this with options for driver-specific and
     // host-specific keys at some point.
@@ -31,6 +32,8 @@ impl KeyOpt {
     fn to_key(&self) -> Result<Key<'static>> {
         match &self.key[..] {
             "temporary" => Ok(Key::temporary()),
+            "concurrency_limit" => Ok(Key::concurrency_limit()),
+            "bandwidth_limit" => Ok(Key::bandwidth_limit()),
             other => Err(format_err!("unknown configuration key {:?}", other)),
         }
     }
@@ -2,8 +2,8 @@
 
 use common_failures::Result;
 use dbcrossbarlib::{
-    config::Configuration, Context, DriverArguments, SharedArguments, SourceArguments,
-    TemporaryStorage, UnparsedLocator,
+    config::Configuration, Context, DriverArguments, QueryParam, SharedArguments,
+    SourceArguments, TemporaryStorage, UnparsedLocator,
 };
 use failure::{format_err, ResultExt};
 use structopt::{self, StructOpt};
@@ -28,6 +28,11 @@ pub(crate) struct Opt {
     #[structopt(long = "where")]
     where_clause: Option<String>,
 
+    /// A named parameter of the form `name=value`, which can be referred to
+    /// as `@name` in the `--where` clause.
+    #[structopt(long = "query-param")]
+    query_params: Vec<QueryParam>,
+
     /// The locator specifying the records to count.
     locator: UnparsedLocator,
 }
@@ -62,7 +67,11 @@ pub(crate) async fn run(
 
     // Build our source arguments.
     let from_args = DriverArguments::from_cli_args(&opt.from_args)?;
-    let source_args = SourceArguments::new(from_args, opt.where_clause.clone());
+    let source_args = SourceArguments::new(
+        from_args,
+        opt.where_clause.clone(),
+        opt.query_params.clone(),
+    );
 
     let count = locator.count(ctx.clone(), shared_args, source_args).await?;
     println!("{}", count);
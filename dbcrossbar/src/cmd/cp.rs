@@ -2,17 +2,17 @@
 
 use common_failures::Result;
 use dbcrossbarlib::{
-    config::Configuration, rechunk::rechunk_csvs, tokio_glue::try_forward, Context,
-    DestinationArguments, DisplayOutputLocators, DriverArguments, IfExists,
-    SharedArguments, SourceArguments, TemporaryStorage, UnparsedLocator,
+    config::Configuration,
+    copy::{copy, CopyOptions, DestinationCallback},
+    BoxLocator, Context, DestinationArguments, DisplayOutputLocators, DriverArguments,
+    IfExists, QueryParam, Result as DbcrossbarResult, SourceArguments,
+    UnparsedLocator,
 };
-use failure::{format_err, ResultExt};
-use futures::{pin_mut, stream, FutureExt, StreamExt, TryStreamExt};
+use failure::format_err;
 use humanize_rs::bytes::Bytes as HumanizedBytes;
-use slog::{debug, o};
+use slog::debug;
+use std::io::{self, Write};
 use structopt::{self, StructOpt};
-use tokio::io;
-use tokio_util::codec::{FramedWrite, LinesCodec};
 
 /// Schema conversion arguments.
 #[derive(Debug, StructOpt)]
@@ -50,10 +50,20 @@ pub(crate) struct Opt {
     #[structopt(long = "where")]
     where_clause: Option<String>,
 
+    /// A named parameter of the form `name=value`, which can be referred to
+    /// as `@name` in the `--where` clause.
+    #[structopt(long = "query-param")]
+    query_params: Vec<QueryParam>,
+
     /// How many data streams should we attempt to copy in parallel?
     #[structopt(long = "max-streams", short = "J", default_value = "4")]
     max_streams: usize,
 
+    /// Check that every value matches the schema, and fail on the first bad
+    /// value. This forces the data to pass through the local machine.
+    #[structopt(long = "validate")]
+    validate: bool,
+
     /// Display where we wrote our output data.
     #[structopt(long = "display-output-locators")]
     display_output_locators: bool,
@@ -72,91 +82,21 @@ pub(crate) async fn run(
     enable_unstable: bool,
     opt: Opt,
 ) -> Result<()> {
-    let schema_opt = opt.schema.map(|s| s.parse(enable_unstable)).transpose()?;
+    let schema = opt.schema.map(|s| s.parse(enable_unstable)).transpose()?;
     let from_locator = opt.from_locator.parse(enable_unstable)?;
     let to_locator = opt.to_locator.parse(enable_unstable)?;
 
-    // Figure out what table schema to use.
-    let schema = {
-        let schema_locator = schema_opt.as_ref().unwrap_or(&from_locator);
-        schema_locator
-            .schema(ctx.clone())
-            .await
-            .with_context(|_| format!("error reading schema from {}", schema_locator))?
-            .ok_or_else(|| {
-                format_err!("don't know how to read schema from {}", schema_locator)
-            })
-    }?;
-
-    // Build our shared arguments.
-    let temporaries = opt.temporaries.clone();
-    let temporary_storage = TemporaryStorage::with_config(temporaries, &config)?;
-    let shared_args = SharedArguments::new(schema, temporary_storage, opt.max_streams);
-
-    // Build our source arguments.
+    // Build our source and destination arguments.
     let from_args = DriverArguments::from_cli_args(&opt.from_args)?;
-    let source_args = SourceArguments::new(from_args, opt.where_clause.clone());
-
-    // Build our destination arguments.
+    let source_args = SourceArguments::new(
+        from_args,
+        opt.where_clause.clone(),
+        opt.query_params.clone(),
+    );
     let to_args = DriverArguments::from_cli_args(&opt.to_args)?;
     let dest_args = DestinationArguments::new(to_args, opt.if_exists);
 
-    // Can we short-circuit this particular copy using special features of the
-    // the source and destination, or do we need to pull the data down to the
-    // local machine?
-    let should_use_remote = opt.stream_size.is_none()
-        && to_locator.supports_write_remote_data(from_locator.as_ref());
-    let dests = if should_use_remote {
-        // Build a logging context.
-        let ctx = ctx.child(o!(
-            "from_locator" => from_locator.to_string(),
-            "to_locator" => to_locator.to_string(),
-        ));
-
-        // Perform a remote transfer.
-        debug!(ctx.log(), "performing remote data transfer");
-        let dests = to_locator
-            .write_remote_data(ctx, from_locator, shared_args, source_args, dest_args)
-            .await?;
-
-        // Convert our list of output locators into a stream.
-        stream::iter(dests).map(Ok).boxed()
-    } else {
-        // We have to transfer the data via the local machine, so read data from
-        // input.
-        debug!(ctx.log(), "performing local data transfer");
-
-        let input_ctx = ctx.child(o!("from_locator" => from_locator.to_string()));
-        let mut data = from_locator
-            .local_data(input_ctx, shared_args.clone(), source_args)
-            .await?
-            .ok_or_else(|| {
-                format_err!("don't know how to read data from {}", from_locator)
-            })?;
-
-        // Honor --stream-size if passed.
-        if let Some(stream_size) = opt.stream_size {
-            let stream_size = stream_size.size();
-            data = rechunk_csvs(ctx.clone(), stream_size, data)?;
-        }
-
-        // Write data to output.
-        let output_ctx = ctx.child(o!("to_locator" => to_locator.to_string()));
-        let result_stream = to_locator
-            .write_local_data(output_ctx, data, shared_args.clone(), dest_args)
-            .await?;
-
-        // Consume the stream of futures produced by `write_local_data`, allowing a
-        // certain degree of parallelism. This is where all the actual work happens,
-        // and this what controls how many "input driver" -> "output driver"
-        // connections are running at any given time.
-        result_stream
-            // Run up to `parallelism` futures in parallel.
-            .try_buffer_unordered(shared_args.max_streams())
-            .boxed()
-    };
-
-    // Optionally display `dests`, depending on a combination of
+    // Optionally display our destinations, depending on a combination of
     // `--display-output-locators` and the defaults for `to_locator`.
     let display_output_locators = match (
         opt.display_output_locators,
@@ -177,33 +117,62 @@ pub(crate) async fn run(
         // We don't want to display our output locators.
         (false, _) => false,
     };
-
-    // Print our destination
-    if display_output_locators {
-        // Display our output locators incrementally on standard output using
-        // `LinesCodec` to insert newlines.
-        let stdout_sink = FramedWrite::new(io::stdout(), LinesCodec::new());
-        let dest_strings = dests.and_then(|dest| {
-            async move {
-                let dest_str = dest.to_string();
-                if dest_str.contains('\n') || dest_str.contains('\r') {
-                    // If we write out this locator, it would be split between
-                    // lines, causing an ambiguity for any parsing program.
-                    Err(format_err!(
-                        "cannot output locator with newline: {:?}",
-                        dest_str
-                    ))
-                } else {
-                    Ok(dest_str)
-                }
-            }
-        });
-        pin_mut!(dest_strings);
-        try_forward(&ctx, dest_strings, stdout_sink).await?;
+    let on_destination: Option<DestinationCallback> = if display_output_locators {
+        Some(Box::new(display_output_locator))
     } else {
-        // Just collect our results and ignore
-        let dests = dests.try_collect::<Vec<_>>().boxed().await?;
-        debug!(ctx.log(), "destination locators: {:?}", dests);
+        None
+    };
+
+    let options = CopyOptions {
+        schema,
+        temporaries: opt.temporaries.clone(),
+        stream_size: opt.stream_size.as_ref().map(|s| s.size()),
+        max_streams: opt.max_streams,
+        validate: opt.validate,
+        on_destination,
+    };
+    let report = match copy(
+        ctx.clone(),
+        &config,
+        from_locator,
+        to_locator,
+        source_args,
+        dest_args,
+        options,
+    )
+    .await
+    {
+        Ok(report) => report,
+        Err(err) => {
+            debug!(ctx.log(), "copy failed: {:?}", err.report.streams);
+            return Err(err.cause);
+        }
+    };
+    debug!(
+        ctx.log(),
+        "copied {} bytes in {} streams to {} destinations in {:?}",
+        report.total_bytes(),
+        report.streams.len(),
+        report.destinations.len(),
+        report.duration,
+    );
+    Ok(())
+}
+
+/// Print `dest` on standard output, one locator per line.
+fn display_output_locator(dest: &BoxLocator) -> DbcrossbarResult<()> {
+    let dest_str = dest.to_string();
+    if dest_str.contains('\n') || dest_str.contains('\r') {
+        // If we write out this locator, it would be split between lines,
+        // causing an ambiguity for any parsing program.
+        return Err(format_err!(
+            "cannot output locator with newline: {:?}",
+            dest_str
+        ));
     }
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    writeln!(stdout, "{}", dest_str)?;
+    stdout.flush()?;
     Ok(())
 }
@@ -0,0 +1,45 @@
+//! The `doctor` subcommand.
+
+use common_failures::Result;
+use dbcrossbarlib::{
+    config::Configuration,
+    doctor::{run_checks, CheckStatus},
+    Context,
+};
+use failure::format_err;
+use structopt::StructOpt;
+
+/// Diagnostic arguments.
+#[derive(Debug, StructOpt)]
+pub(crate) struct Opt {}
+
+/// Check for common configuration problems.
+pub(crate) async fn run(
+    ctx: Context,
+    config: Configuration,
+    _enable_unstable: bool,
+    _opt: Opt,
+) -> Result<()> {
+    let results = run_checks(&ctx, &config).await;
+    let mut failed = 0;
+    for result in &results {
+        println!("{:<5} {}: {}", result.status, result.name, result.message);
+        if let Some(fix) = &result.fix {
+            for (i, line) in fix.lines().enumerate() {
+                if i == 0 {
+                    println!("      fix: {}", line);
+                } else {
+                    println!("           {}", line);
+                }
+            }
+        }
+        if result.status == CheckStatus::Failed {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        Err(format_err!("{} of {} checks failed", failed, results.len()))
+    } else {
+        Ok(())
+    }
+}
@@ -2,14 +2,17 @@
 
 use dbcrossbarlib::{config::Configuration, tokio_glue::BoxFuture, Context};
 use futures::FutureExt;
+use std::path::PathBuf;
 //use structopt::StructOpt;
 use structopt_derive::StructOpt;
 
 use crate::logging::LogFormat;
 
+pub(crate) mod clean_temp;
 pub(crate) mod config;
 pub(crate) mod count;
 pub(crate) mod cp;
+pub(crate) mod doctor;
 pub(crate) mod features;
 pub(crate) mod license;
 pub(crate) mod schema;
@@ -33,6 +36,21 @@ pub(crate) struct Opt {
     #[structopt(long = "enable-unstable")]
     pub(crate) enable_unstable: bool,
 
+    /// The maximum number of threads to use for CPU-heavy work like CSV
+    /// parsing and transforms, and for local file I/O.
+    #[structopt(long = "max-blocking-threads")]
+    pub(crate) max_blocking_threads: Option<usize>,
+
+    /// Record Google Cloud API calls and data checksums to a file, which can
+    /// be used to reproduce bugs without access to the original data.
+    #[structopt(long = "record", conflicts_with = "replay")]
+    pub(crate) record: Option<PathBuf>,
+
+    /// Replay Google Cloud API calls from a file created with `--record`, and
+    /// verify that our data has the same checksums.
+    #[structopt(long = "replay")]
+    pub(crate) replay: Option<PathBuf>,
+
     /// The command to run.
     #[structopt(subcommand)]
     pub(crate) cmd: Command,
@@ -42,6 +60,13 @@ pub(crate) struct Opt {
 #[derive(Debug, StructOpt)]
 #[allow(clippy::large_enum_variant)]
 pub(crate) enum Command {
+    /// Delete temporary directories left behind by earlier copies.
+    #[structopt(name = "clean-temp")]
+    CleanTemp {
+        #[structopt(flatten)]
+        command: clean_temp::Opt,
+    },
+
     /// Update configuration.
     #[structopt(name = "config")]
     Config {
@@ -71,6 +96,14 @@ pub(crate) enum Command {
         command: cp::Opt,
     },
 
+    /// Check for missing tools, missing credentials and other common
+    /// configuration problems.
+    #[structopt(name = "doctor")]
+    Doctor {
+        #[structopt(flatten)]
+        command: doctor::Opt,
+    },
+
     /// List available drivers and supported features.
     #[structopt(name = "features")]
     Features {
@@ -94,6 +127,9 @@ pub(crate) enum Command {
 
 pub(crate) fn run(ctx: Context, config: Configuration, opt: Opt) -> BoxFuture<()> {
     match opt.cmd {
+        Command::CleanTemp { command } => {
+            clean_temp::run(ctx, config, opt.enable_unstable, command).boxed()
+        }
         Command::Config { command } => config::run(ctx, config, command).boxed(),
 
         Command::Count { command } => {
@@ -102,6 +138,9 @@ pub(crate) fn run(ctx: Context, config: Configuration, opt: Opt) -> BoxFuture<()
         Command::Cp { command } => {
             cp::run(ctx, config, opt.enable_unstable, command).boxed()
         }
+        Command::Doctor { command } => {
+            doctor::run(ctx, config, opt.enable_unstable, command).boxed()
+        }
         Command::Features { command } => {
             features::run(ctx, config, opt.enable_unstable, command).boxed()
         }
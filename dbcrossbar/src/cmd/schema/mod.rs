@@ -13,7 +13,19 @@ pub(crate) enum Opt {
     #[structopt(after_help = r#"EXAMPLE LOCATORS:
     postgres-sql:table.sql
     postgres://localhost:5432/db#table
+    mysql-sql:table.sql
     bigquery-schema:table.json
+    avro-schema:table.avsc
+    parquet-schema:table.parquet
+    spark-schema:table.json
+    json-schema:table.schema.json
+    proto-schema:table.proto
+    ts-schema:table.ts
+    rust-schema:table.rs
+    hive-sql:table.sql?format=parquet&location=s3a://bucket/table/
+    migration:migrations/?layout=sqlx
+    graphql-schema:table.graphql
+    dbt-schema:models/sources.yml?source=raw
 "#)]
     Conv {
         #[structopt(flatten)]
@@ -26,9 +26,13 @@ extern crate openssl;
 extern crate tokio;
 
 use common_failures::{quick_main, Result};
-use dbcrossbarlib::{config::Configuration, run_futures_with_runtime, Context};
+use dbcrossbarlib::{
+    config::Configuration, recording::Recorder, run_futures_with_runtime_options,
+    Context, RuntimeOptions,
+};
 use slog::{debug, Drain};
 use slog_async::{self, OverflowStrategy};
+use std::sync::Arc;
 use structopt::{self, StructOpt};
 
 mod cmd;
@@ -65,7 +69,17 @@ fn run() -> Result<()> {
     // must be passed to all our background operations. The `worker_fut` will
     // return either success when all background workers have finished, or an
     // error as soon as one fails.
-    let (ctx, worker_fut) = Context::create(log);
+    let (mut ctx, worker_fut) = Context::create(log);
+
+    // Record or replay our interactions with cloud services, if requested.
+    let recorder = match (&opt.record, &opt.replay) {
+        (Some(path), _) => Some(Arc::new(Recorder::record(path))),
+        (None, Some(path)) => Some(Arc::new(Recorder::replay(path)?)),
+        (None, None) => None,
+    };
+    if let Some(recorder) = &recorder {
+        ctx = ctx.with_recorder(recorder.clone());
+    }
 
     // Log our command-line options.
     debug!(ctx.log(), "{:?}", opt);
@@ -74,9 +88,22 @@ fn run() -> Result<()> {
     let config = Configuration::try_default()?;
     debug!(ctx.log(), "{:?}", config);
 
+    // Configure our runtime.
+    let runtime_options = RuntimeOptions {
+        max_blocking_threads: opt.max_blocking_threads,
+    };
+
     // Create a future to run our command.
     let cmd_fut = cmd::run(ctx, config, opt);
 
     // Run our futures.
-    run_futures_with_runtime(cmd_fut, worker_fut)
+    let result =
+        run_futures_with_runtime_options(cmd_fut, worker_fut, &runtime_options);
+
+    // Save our recording even if we failed, because that's when it's most
+    // useful. But report our original error first.
+    match recorder {
+        Some(recorder) => result.and(recorder.save()),
+        None => result,
+    }
 }
@@ -79,6 +79,51 @@ fn conv_csv_to_pg_sql() {
     assert!(output.stdout_str().contains("last_name"));
 }
 
+#[test]
+fn conv_csv_to_pg_sql_infers_types() {
+    let testdir = TestDir::new("dbcrossbar", "conv_csv_to_pg_sql_infers_types");
+    testdir.create_file(
+        "data.csv",
+        "id,score,signed_up,zip\n1,2.5,2020-01-31,02134\n2,,2020-02-01,90210\n",
+    );
+    let output = testdir
+        .cmd()
+        .args(&["schema", "conv", "csv:data.csv", "postgres-sql:-"])
+        .output()
+        .expect_success();
+    let sql = output.stdout_str();
+    assert!(sql.contains("\"id\" bigint"));
+    assert!(sql.contains("\"score\" double precision"));
+    assert!(sql.contains("\"signed_up\" date"));
+    assert!(sql.contains("\"zip\" text"));
+
+    // `infer_rows=0` turns off type inference.
+    let output = testdir
+        .cmd()
+        .args(&[
+            "schema",
+            "conv",
+            "csv:data.csv?infer_rows=0",
+            "postgres-sql:-",
+        ])
+        .output()
+        .expect_success();
+    assert!(output.stdout_str().contains("\"id\" text"));
+}
+
+#[test]
+fn conv_csv_with_bom_to_pg_sql() {
+    let testdir = TestDir::new("dbcrossbar", "conv_csv_with_bom_to_pg_sql");
+    testdir.create_file("data.csv", "\u{FEFF}id,name\r\n1,a\r\n");
+    let output = testdir
+        .cmd()
+        .args(&["schema", "conv", "csv:data.csv", "postgres-sql:-"])
+        .output()
+        .expect_success();
+    assert!(output.stdout_str().contains("\"id\" bigint"));
+    assert!(!output.stdout_str().contains('\u{FEFF}'));
+}
+
 #[test]
 fn conv_pg_sql_to_bq_schema() {
     let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_bq_schema");
@@ -132,3 +177,224 @@ fn conv_ts_to_portable() {
         serde_json::from_str::<serde_json::Value>(&expected).unwrap(),
     );
 }
+
+#[test]
+fn conv_pg_sql_to_avro_schema_to_pg_sql() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_avro_schema_to_pg_sql");
+    let output1 = testdir
+        .cmd()
+        .args(&["schema", "conv", "postgres-sql:-", "avro-schema:-"])
+        .output_with_stdin(EXAMPLE_SQL)
+        .expect_success();
+    assert!(output1.stdout_str().contains("\"record\""));
+    let output2 = testdir
+        .cmd()
+        .args(&["schema", "conv", "avro-schema:-", "postgres-sql:-"])
+        .output_with_stdin(output1.stdout_str())
+        .expect_success();
+    assert!(output2.stdout_str().contains("CREATE TABLE"));
+    assert!(output2.stdout_str().contains("\"first_name\" text"));
+}
+
+#[test]
+fn conv_pg_sql_to_parquet_schema_to_pg_sql() {
+    let testdir =
+        TestDir::new("dbcrossbar", "conv_pg_sql_to_parquet_schema_to_pg_sql");
+    let output1 = testdir
+        .cmd()
+        .args(&["schema", "conv", "postgres-sql:-", "parquet-schema:-"])
+        .output_with_stdin(EXAMPLE_SQL)
+        .expect_success();
+    assert!(output1.stdout_str().starts_with("message "));
+    let output2 = testdir
+        .cmd()
+        .args(&["schema", "conv", "parquet-schema:-", "postgres-sql:-"])
+        .output_with_stdin(output1.stdout_str())
+        .expect_success();
+    assert!(output2.stdout_str().contains("CREATE TABLE"));
+    assert!(output2.stdout_str().contains("\"first_name\" text"));
+}
+
+#[test]
+fn conv_table_schema_to_pg_sql() {
+    let testdir = TestDir::new("dbcrossbar", "conv_table_schema_to_pg_sql");
+    let input_json = testdir.src_path("fixtures/datapackage.json");
+    let output = testdir
+        .cmd()
+        .args(&[
+            "schema",
+            "conv",
+            &format!("table-schema:{}", input_json.display()),
+            "postgres-sql:-",
+        ])
+        .expect_success();
+    assert!(output.stdout_str().contains("CREATE TABLE"));
+    assert!(output.stdout_str().contains("\"id\" bigint NOT NULL"));
+}
+
+#[test]
+fn conv_pg_sql_to_proto_schema() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_proto_schema");
+    let output = testdir
+        .cmd()
+        .args(&["schema", "conv", "postgres-sql:-", "proto-schema:-"])
+        .output_with_stdin(INPUT_SQL)
+        .expect_success();
+    assert!(output.stdout_str().contains("syntax = \"proto3\";"));
+    assert!(output.stdout_str().contains("message "));
+}
+
+#[test]
+fn conv_pg_sql_to_ts_schema() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_ts_schema");
+    let output = testdir
+        .cmd()
+        .args(&["schema", "conv", "postgres-sql:-", "ts-schema:-"])
+        .output_with_stdin(EXAMPLE_SQL)
+        .expect_success();
+    assert!(output.stdout_str().contains("export interface "));
+    assert!(output.stdout_str().contains("first_name: string"));
+}
+
+#[test]
+fn conv_pg_sql_to_rust_schema() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_rust_schema");
+    let output = testdir
+        .cmd()
+        .args(&["schema", "conv", "postgres-sql:-", "rust-schema:-"])
+        .output_with_stdin(EXAMPLE_SQL)
+        .expect_success();
+    assert!(output.stdout_str().contains("pub struct Example {"));
+    assert!(output
+        .stdout_str()
+        .contains("pub first_name: Option<String>,"));
+}
+
+#[test]
+fn conv_pg_sql_to_hive_sql() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_hive_sql");
+    let output = testdir
+        .cmd()
+        .args(&[
+            "schema",
+            "conv",
+            "postgres-sql:-",
+            "hive-sql:-?format=parquet&location=s3a://example/t/",
+        ])
+        .output_with_stdin(INPUT_SQL)
+        .expect_success();
+    assert!(output.stdout_str().contains("CREATE EXTERNAL TABLE"));
+    assert!(output.stdout_str().contains("ARRAY<"));
+    assert!(output
+        .stdout_str()
+        .contains("STORED AS PARQUET\nLOCATION 's3a://example/t/';"));
+}
+
+#[test]
+fn conv_pg_sql_to_spark_schema_to_pg_sql() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_spark_schema_to_pg_sql");
+    let output1 = testdir
+        .cmd()
+        .args(&["schema", "conv", "postgres-sql:-", "spark-schema:-"])
+        .output_with_stdin(EXAMPLE_SQL)
+        .expect_success();
+    assert!(output1.stdout_str().contains("\"struct\""));
+    let output2 = testdir
+        .cmd()
+        .args(&["schema", "conv", "spark-schema:-", "postgres-sql:-"])
+        .output_with_stdin(output1.stdout_str())
+        .expect_success();
+    assert!(output2.stdout_str().contains("CREATE TABLE"));
+    assert!(output2.stdout_str().contains("\"first_name\" text"));
+}
+
+#[test]
+fn conv_pg_sql_to_migration() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_migration");
+    testdir
+        .cmd()
+        .args(&[
+            "schema",
+            "conv",
+            "postgres-sql:-",
+            "migration:migrations/?layout=sqlx",
+        ])
+        .output_with_stdin(EXAMPLE_SQL)
+        .expect_success();
+    let mut names = fs::read_dir(testdir.path("migrations"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names.len(), 2);
+    assert!(names[0].ends_with("_create_example.down.sql"));
+    assert!(names[1].ends_with("_create_example.up.sql"));
+}
+
+#[test]
+fn conv_pg_sql_to_graphql_schema() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_graphql_schema");
+    let output = testdir
+        .cmd()
+        .args(&["schema", "conv", "postgres-sql:-", "graphql-schema:-"])
+        .output_with_stdin(EXAMPLE_SQL)
+        .expect_success();
+    assert!(output.stdout_str().contains("type Example {"));
+    assert!(output.stdout_str().contains("  first_name: String\n"));
+}
+
+#[test]
+fn conv_pg_sql_to_json_schema_to_pg_sql() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_json_schema_to_pg_sql");
+    let output1 = testdir
+        .cmd()
+        .args(&["schema", "conv", "postgres-sql:-", "json-schema:-"])
+        .output_with_stdin(EXAMPLE_SQL)
+        .expect_success();
+    assert!(output1.stdout_str().contains("\"properties\""));
+    let output2 = testdir
+        .cmd()
+        .args(&["schema", "conv", "json-schema:-", "postgres-sql:-"])
+        .output_with_stdin(output1.stdout_str())
+        .expect_success();
+    assert!(output2.stdout_str().contains("CREATE TABLE"));
+    assert!(output2.stdout_str().contains("\"first_name\" text"));
+}
+
+#[test]
+fn conv_pg_sql_to_mysql_sql_to_pg_sql() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_mysql_sql_to_pg_sql");
+    let output1 = testdir
+        .cmd()
+        .args(&["schema", "conv", "postgres-sql:-", "mysql-sql:-"])
+        .output_with_stdin(EXAMPLE_SQL)
+        .expect_success();
+    assert!(output1.stdout_str().contains("CREATE TABLE `example` ("));
+    let output2 = testdir
+        .cmd()
+        .args(&["schema", "conv", "mysql-sql:-", "postgres-sql:-"])
+        .output_with_stdin(output1.stdout_str())
+        .expect_success();
+    assert!(output2.stdout_str().contains("CREATE TABLE"));
+    assert!(output2.stdout_str().contains("\"first_name\" text"));
+}
+
+#[test]
+fn conv_pg_sql_to_dbt_schema() {
+    let testdir = TestDir::new("dbcrossbar", "conv_pg_sql_to_dbt_schema");
+    let output = testdir
+        .cmd()
+        .args(&[
+            "schema",
+            "conv",
+            "postgres-sql:-",
+            "dbt-schema:-?source=raw",
+        ])
+        .output_with_stdin(EXAMPLE_SQL)
+        .expect_success();
+    assert!(output.stdout_str().contains("  - name: raw\n"));
+    assert!(output.stdout_str().contains("      - name: example\n"));
+    assert!(output
+        .stdout_str()
+        .contains("          - name: first_name\n"));
+}
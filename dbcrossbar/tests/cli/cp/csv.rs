@@ -56,3 +56,435 @@ fn cp_csv_to_csv_piped() {
         .expect_success();
     assert_eq!(output.stdout_str(), EXAMPLE_CSV);
 }
+
+#[test]
+fn cp_csv_to_csv_piped_without_schema() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_csv_piped_without_schema");
+    // Use enough rows that we read past our type inference sample.
+    let mut input = "id,name\n".to_owned();
+    for i in 0..5000 {
+        input.push_str(&format!("{},name {}\n", i, i));
+    }
+    let output = testdir
+        .cmd()
+        .args(&["cp", "csv:-?infer_rows=10", "csv:-"])
+        .output_with_stdin(&input)
+        .expect_success();
+    assert_eq!(output.stdout_str(), input);
+}
+
+#[test]
+fn cp_csv_to_csv_piped_strips_bom() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_csv_piped_strips_bom");
+    testdir.create_file("schema.sql", "CREATE TABLE t (a TEXT, b TEXT);\r\n");
+    let output = testdir
+        .cmd()
+        .args(&["cp", "--schema=postgres-sql:schema.sql", "csv:-", "csv:-"])
+        .output_with_stdin("\u{FEFF}a,b\r\n1,2\r\n")
+        .expect_success();
+    assert_eq!(output.stdout_str(), "a,b\r\n1,2\r\n");
+}
+
+#[test]
+fn cp_csv_to_csv_with_bom() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_csv_with_bom");
+    testdir.create_file("in.csv", "\u{FEFF}a,b\n1,2\n");
+    testdir
+        .cmd()
+        .args(&["cp", "--to-arg=write_bom=true", "csv:in.csv", "csv:out.csv"])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "\u{FEFF}a,b\n1,2\n");
+}
+
+#[test]
+fn cp_csv_with_column_mismatch_pad_null() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_column_mismatch_pad_null");
+    testdir.create_file("ragged.csv", "a,b,c\n1,2\n1,2,3,4\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--from-arg=on_column_mismatch=pad_null",
+            "csv:ragged.csv",
+            "csv:fixed.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("fixed.csv", "a,b,c\n1,2,\n1,2,3\n");
+}
+
+#[test]
+fn cp_csv_skipping_bad_rows() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_skipping_bad_rows");
+    testdir.create_file("schema.sql", "CREATE TABLE t (a TEXT, b TEXT);\n");
+    testdir.create_file("in.csv", "a,b\n1,2\n3\n4,5\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--from-arg=on_error=skip",
+            "--from-arg=bad_rows=csv:rejects/",
+            "csv:in.csv",
+            "csv:out.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "a,b\n1,2\n4,5\n");
+    testdir.expect_file_contents(
+        "rejects/in.csv",
+        "bad_row_reason,a,b\n\"row 2 has 1 fields, but header has 2\",3\n",
+    );
+}
+
+#[test]
+fn cp_csv_to_csv_dir_split_by_rows() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_csv_dir_split_by_rows");
+    testdir.create_file("schema.sql", "CREATE TABLE t (a TEXT, b TEXT);\n");
+    testdir.create_file("in.csv", "a,b\n1,1\n2,2\n3,3\n4,4\n5,5\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--to-arg=max_rows_per_file=2",
+            "csv:in.csv",
+            "csv:out/",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out/in_0001.csv", "a,b\n1,1\n2,2\n");
+    testdir.expect_file_contents("out/in_0002.csv", "a,b\n3,3\n4,4\n");
+    testdir.expect_file_contents("out/in_0003.csv", "a,b\n5,5\n");
+
+    // We can't split a single output file.
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--to-arg=max_file_size=1MB",
+            "csv:in.csv",
+            "csv:out.csv",
+        ])
+        .expect_failure();
+}
+
+#[test]
+fn cp_csv_to_csv_dir_partitioned() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_csv_dir_partitioned");
+    testdir.create_file("schema.sql", "CREATE TABLE t (id TEXT, day TEXT);\n");
+    testdir.create_file(
+        "in.csv",
+        "id,day\n1,2024-01-01\n2,2024-01-02\n3,2024-01-01\n",
+    );
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--to-arg=partition_by=day",
+            "csv:in.csv",
+            "csv:out/",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out/day=2024-01-01/part-0001.csv", "id\n1\n3\n");
+    testdir.expect_file_contents("out/day=2024-01-02/part-0001.csv", "id\n2\n");
+}
+
+#[test]
+fn cp_csv_to_csv_dir_append() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_csv_dir_append");
+    let src = testdir.src_path("fixtures/example.csv");
+    for if_exists in &["--if-exists=error", "--if-exists=append"] {
+        testdir
+            .cmd()
+            .arg("cp")
+            .arg(if_exists)
+            .arg(&format!("csv:{}", src.display()))
+            .arg("csv:out/")
+            .expect_success();
+    }
+    let file_count = fs::read_dir(testdir.path("out")).unwrap().count();
+    assert_eq!(file_count, 2);
+}
+
+#[test]
+fn cp_tsv_to_pipe_delimited() {
+    let testdir = TestDir::new("dbcrossbar", "cp_tsv_to_pipe_delimited");
+    testdir.create_file("data.tsv", "a\tb\n\"x,y\"\t2\n");
+    testdir
+        .cmd()
+        .args(&["cp", "--to-arg=delimiter=|", "csv:data.tsv", "csv:out.csv"])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "a|b\nx,y|2\n");
+}
+
+#[test]
+fn cp_pipe_delimited_csvs_to_csv() {
+    let testdir = TestDir::new("dbcrossbar", "cp_pipe_delimited_csvs_to_csv");
+    testdir.create_file("schema.sql", "CREATE TABLE t (a TEXT, b TEXT);\n");
+    testdir.create_file("in/1.csv", "a|b\n1|2\n");
+    testdir.create_file("in/2.csv", "a|b\n3|\"4|5\"\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--from-arg=delimiter=|",
+            "csv:in/",
+            "csv:out.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "a,b\n1,2\n3,4|5\n");
+}
+
+#[test]
+fn cp_sniffed_pipe_delimited_csv_to_csv() {
+    let testdir = TestDir::new("dbcrossbar", "cp_sniffed_pipe_delimited_csv_to_csv");
+    testdir.create_file("data.csv", "a|b\n1|\"2,3\"\n");
+    testdir
+        .cmd()
+        .args(&["cp", "csv:data.csv", "csv:out.csv"])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "a,b\n1,\"2,3\"\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--to-arg=delimiter=|",
+            "csv:out.csv",
+            "csv:round_trip.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("round_trip.csv", "a|b\n1|2,3\n");
+}
+
+#[test]
+fn cp_headerless_csv_to_headerless_csv() {
+    let testdir = TestDir::new("dbcrossbar", "cp_headerless_csv_to_headerless_csv");
+    testdir.create_file("schema.sql", "CREATE TABLE t (a TEXT, b TEXT);\n");
+    testdir.create_file("in.csv", "1,2\n3,4\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--from-arg=has_header=false",
+            "csv:in.csv",
+            "csv:with_header.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("with_header.csv", "a,b\n1,2\n3,4\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--to-arg=write_header=false",
+            "csv:with_header.csv",
+            "csv:out.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "1,2\n3,4\n");
+}
+
+#[test]
+fn cp_fixed_width_to_csv() {
+    let testdir = TestDir::new("dbcrossbar", "cp_fixed_width_to_csv");
+    testdir.create_file(
+        "schema.sql",
+        "CREATE TABLE t (id TEXT, name TEXT, amount TEXT);\n",
+    );
+    testdir.create_file("in.txt", "001Alice   12.50\n002Bob\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--from-arg=column_widths=3,8,6",
+            "csv:in.txt",
+            "csv:out.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents(
+        "out.csv",
+        "id,name,amount\n001,Alice,12.50\n002,Bob,\n",
+    );
+}
+
+#[test]
+fn cp_csv_to_csv_selecting_columns() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_csv_selecting_columns");
+    testdir.create_file("in.csv", "a,b,c\n1,2,3\n");
+    testdir
+        .cmd()
+        .args(&["cp", "--to-arg=columns=c,a", "csv:in.csv", "csv:out.csv"])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "c,a\n3,1\n");
+}
+
+#[test]
+fn cp_csv_to_csv_with_crlf() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_csv_with_crlf");
+    testdir.create_file("in.csv", "a,b\n1,2\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--to-arg=delimiter=tab",
+            "--to-arg=line_ending=crlf",
+            "csv:in.csv",
+            "csv:out.tsv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.tsv", "a\tb\r\n1\t2\r\n");
+}
+
+#[test]
+fn cp_csv_to_csv_with_validate() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_csv_with_validate");
+    testdir.create_file(
+        "schema.sql",
+        "CREATE TABLE t (id INT NOT NULL, name TEXT);\n",
+    );
+    testdir.create_file("good.csv", "id,name\n1,a\n2,\n");
+    testdir.create_file("bad.csv", "id,name\n1,a\nabc,b\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--validate",
+            "--schema=postgres-sql:schema.sql",
+            "csv:good.csv",
+            "csv:out.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "id,name\n1,a\n2,\n");
+    let output = testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--validate",
+            "--schema=postgres-sql:schema.sql",
+            "csv:bad.csv",
+            "csv:out2.csv",
+        ])
+        .expect_failure();
+    assert!(output.stderr_str().contains("row 2, column id"));
+}
+
+#[test]
+fn cp_csv_to_csv_dir_in_chunks() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_csv_dir_in_chunks");
+    testdir.create_file("in.csv", "a,b\n1,xx\n2,yy\n3,zz\n4,ww\n");
+    testdir
+        .cmd()
+        .args(&["cp", "--from-arg=chunks=2", "csv:in.csv", "csv:out/"])
+        .expect_success();
+    testdir.expect_file_contents("out/in_0001.csv", "a,b\n1,xx\n2,yy\n");
+    testdir.expect_file_contents("out/in_0002.csv", "a,b\n3,zz\n4,ww\n");
+}
+
+#[test]
+fn cp_csv_with_custom_null_values() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_custom_null_values");
+    testdir.create_file("schema.sql", "CREATE TABLE t (a TEXT, b TEXT);\n");
+    testdir.create_file("in.csv", "a,b\n1,\\N\nNULL,x\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--from-arg=null_value[]=\\N",
+            "--from-arg=null_value[]=NULL",
+            "--to-arg=null_value=<null>",
+            "csv:in.csv",
+            "csv:out.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "a,b\n1,<null>\n<null>,x\n");
+}
+
+#[test]
+fn cp_windows_1252_csv_to_csv() {
+    let testdir = TestDir::new("dbcrossbar", "cp_windows_1252_csv_to_csv");
+    testdir.create_file("schema.sql", "CREATE TABLE t (name TEXT, price TEXT);\n");
+    fs::write(testdir.path("in.csv"), b"name,price\ncaf\xe9,\x805\n").unwrap();
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--from-arg=encoding=windows-1252",
+            "csv:in.csv",
+            "csv:out.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "name,price\ncafé,€5\n");
+}
+
+#[test]
+fn cp_csv_glob_to_csv() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_glob_to_csv");
+    testdir.create_file("schema.sql", "CREATE TABLE t (a TEXT, b TEXT);\n");
+    testdir.create_file("in/2022/sales_2022.csv", "a,b\n1,2\n");
+    testdir.create_file("in/2023/sales_2023.csv", "a,b\n3,4\n");
+    testdir.create_file("in/2023/README.txt", "Not a CSV file.\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "csv:in/**/*_2023*.csv",
+            "csv:out.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "a,b\n3,4\n");
+}
+
+#[test]
+fn cp_csv_to_compressed_csvs_and_back() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_compressed_csvs_and_back");
+    let src = testdir.src_path("fixtures/example.csv");
+    for compression in &["gzip", "zstd", "bzip2", "xz"] {
+        testdir
+            .cmd()
+            .arg("cp")
+            .arg("--if-exists=overwrite")
+            .arg(&format!("--to-arg=compression={}", compression))
+            .arg(&format!("csv:{}", src.display()))
+            .arg(&format!("csv:{}/", compression))
+            .expect_success();
+    }
+    let schema = testdir.src_path("fixtures/example.sql");
+    let expected = fs::read_to_string(&src).unwrap();
+    for (compression, ext) in &[("gzip", "gz"), ("zstd", "zst"), ("bzip2", "bz2")] {
+        let compressed = format!("{}/example.csv.{}", compression, ext);
+        assert!(testdir.path(&compressed).exists());
+        testdir
+            .cmd()
+            .arg("cp")
+            .arg("--if-exists=overwrite")
+            .arg(&format!("--schema=postgres-sql:{}", schema.display()))
+            .arg(&format!("csv:{}", compressed))
+            .arg("csv:out.csv")
+            .expect_success();
+        testdir.expect_file_contents("out.csv", &expected);
+    }
+
+    // Single files are compressed based on their extension, and we can read
+    // schemas from compressed files.
+    testdir
+        .cmd()
+        .args(&["cp", "csv:xz/", "csv:single.csv.xz"])
+        .arg(&format!("--schema=postgres-sql:{}", schema.display()))
+        .expect_success();
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--if-exists=overwrite",
+            "csv:single.csv.xz",
+            "csv:out.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", &expected);
+}
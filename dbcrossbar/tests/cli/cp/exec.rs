@@ -0,0 +1,32 @@
+//! Tests for the `exec:` driver.
+
+use cli_test_dir::*;
+
+#[test]
+fn cp_exec_to_exec() {
+    let testdir = TestDir::new("dbcrossbar", "cp_exec_to_exec");
+    testdir.create_file("in.csv", "a,b\n1,2\n");
+    testdir.create_file("schema.sql", "CREATE TABLE t (a INT, b INT);\n");
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--if-exists=overwrite",
+            "--schema=postgres-sql:schema.sql",
+            "exec:cat in.csv",
+            "exec:cat > out.csv && echo $DBCROSSBAR_IF_EXISTS > if_exists.txt",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "a,b\n1,2\n");
+    testdir.expect_file_contents("if_exists.txt", "overwrite\n");
+}
+
+#[test]
+fn cp_exec_reports_command_failure() {
+    let testdir = TestDir::new("dbcrossbar", "cp_exec_reports_command_failure");
+    testdir.create_file("in.csv", "a,b\n1,2\n");
+    testdir
+        .cmd()
+        .args(&["cp", "csv:in.csv", "exec:cat > /dev/null; exit 1"])
+        .expect_failure();
+}
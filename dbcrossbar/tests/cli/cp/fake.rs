@@ -0,0 +1,26 @@
+//! Tests for the `fake:` driver.
+
+use cli_test_dir::*;
+use std::fs;
+
+#[test]
+fn cp_fake_to_csv_is_repeatable() {
+    let testdir = TestDir::new("dbcrossbar", "cp_fake_to_csv_is_repeatable");
+    let schema = testdir.src_path("fixtures/many_types.sql");
+    for out in &["csv:out1.csv", "csv:out2.csv"] {
+        testdir
+            .cmd()
+            .args(&[
+                "cp",
+                &format!("--schema=postgres-sql:{}", schema.display()),
+                "--from-arg=seed=42",
+                "fake:100",
+                out,
+            ])
+            .expect_success();
+    }
+    let out1 = fs::read_to_string(testdir.path("out1.csv")).unwrap();
+    let out2 = fs::read_to_string(testdir.path("out2.csv")).unwrap();
+    assert_eq!(out1.lines().count(), 101);
+    assert_eq!(out1, out2);
+}
@@ -0,0 +1,41 @@
+//! Tests for the `file:` driver.
+
+use cli_test_dir::*;
+use std::fs;
+
+#[test]
+fn cp_file_dir_to_file_dir() {
+    let testdir = TestDir::new("dbcrossbar", "cp_file_dir_to_file_dir");
+    testdir.create_file("in/model.bin", "\u{0}\u{1}not,a\ncsv");
+    testdir.create_file("in/sub/notes.tar.gz", "opaque");
+    testdir
+        .cmd()
+        .args(&["cp", "--if-exists=overwrite", "file:in/", "file:out/"])
+        .expect_success();
+    testdir.expect_file_contents("out/model.bin", "\u{0}\u{1}not,a\ncsv");
+    testdir.expect_file_contents("out/sub/notes.tar.gz", "opaque");
+}
+
+#[test]
+fn cp_file_to_stdout() {
+    let testdir = TestDir::new("dbcrossbar", "cp_file_to_stdout");
+    testdir.create_file("in.json", "{\"a\": 1}\n");
+    let output = testdir
+        .cmd()
+        .args(&["cp", "file:in.json", "file:-"])
+        .expect_success();
+    assert_eq!(output.stdout_str(), "{\"a\": 1}\n");
+}
+
+#[test]
+fn cp_file_dir_to_single_file_fails() {
+    let testdir = TestDir::new("dbcrossbar", "cp_file_dir_to_single_file_fails");
+    testdir.create_file("in/a.txt", "a");
+    testdir.create_file("in/b.txt", "b");
+    let output = testdir
+        .cmd()
+        .args(&["cp", "--if-exists=overwrite", "file:in/", "file:out.txt"])
+        .expect_failure();
+    assert!(output.stderr_str().contains("cannot copy multiple files"));
+    assert!(fs::metadata(testdir.path("out.txt")).is_err());
+}
@@ -8,11 +8,16 @@ mod bigml;
 mod bigquery;
 mod combined;
 mod csv;
+mod exec;
+mod fake;
+mod file;
 mod gs;
+mod null;
 mod postgres;
 mod redshift;
 mod s3;
 mod shopify;
+mod singer;
 
 /// The URL of our test database.
 pub(crate) fn postgres_test_url() -> String {
@@ -0,0 +1,15 @@
+//! Tests for the `null:` driver.
+
+use cli_test_dir::*;
+
+#[test]
+fn cp_csv_to_null() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_null");
+    let src = testdir.src_path("fixtures/example.csv");
+    testdir
+        .cmd()
+        .arg("cp")
+        .arg(&format!("csv:{}", src.display()))
+        .arg("null:")
+        .expect_success();
+}
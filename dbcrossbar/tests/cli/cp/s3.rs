@@ -52,3 +52,36 @@ fn cp_csv_to_s3_to_csv() {
     let actual = fs::read_to_string(testdir.path("out/many_types.csv")).unwrap();
     assert_diff!(&expected, &actual, ",", 0);
 }
+
+#[test]
+#[ignore]
+fn cp_csv_to_s3_if_exists_error() {
+    let _ = env_logger::try_init();
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_s3_if_exists_error");
+    let src = testdir.src_path("fixtures/many_types.csv");
+    let schema = testdir.src_path("fixtures/many_types.sql");
+    let s3_dir = s3_test_dir_url("cp_csv_to_s3_if_exists_error");
+    let cp_with = |if_exists: &str| {
+        let mut cmd = testdir.cmd();
+        cmd.args(&[
+            "cp",
+            if_exists,
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &s3_dir,
+        ]);
+        cmd
+    };
+
+    // Start with a single file.
+    cp_with("--if-exists=overwrite")
+        .tee_output()
+        .expect_success();
+
+    // We refuse to write to a non-empty directory by default.
+    let output = cp_with("--if-exists=error").tee_output().expect_failure();
+    assert!(output.stderr_str().contains("already contains files"));
+
+    // But we can add new files alongside the existing one.
+    cp_with("--if-exists=append").tee_output().expect_success();
+}
@@ -0,0 +1,51 @@
+//! Tests for the `singer-tap:` and `singer-target:` drivers.
+
+use cli_test_dir::*;
+
+/// Output from a simple Singer tap.
+const TAP_OUTPUT: &str = r#"{"type": "SCHEMA", "stream": "users", "schema": {"type": "object", "properties": {"id": {"type": "integer"}, "name": {"type": ["null", "string"]}}, "required": ["id"]}, "key_properties": ["id"]}
+{"type": "RECORD", "stream": "users", "record": {"id": 1, "name": "Ann"}}
+{"type": "STATE", "value": {"bookmarks": {"users": 1}}}
+{"type": "RECORD", "stream": "users", "record": {"id": 2}}
+"#;
+
+#[test]
+fn cp_singer_tap_to_csv() {
+    let testdir = TestDir::new("dbcrossbar", "cp_singer_tap_to_csv");
+    testdir.create_file("tap.jsonl", TAP_OUTPUT);
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--enable-unstable",
+            "singer-tap:cat tap.jsonl#users",
+            "csv:out.csv",
+        ])
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "id,name\n1,Ann\n2,\n");
+}
+
+#[test]
+fn cp_singer_tap_to_singer_target() {
+    let testdir = TestDir::new("dbcrossbar", "cp_singer_tap_to_singer_target");
+    testdir.create_file("tap.jsonl", TAP_OUTPUT);
+    testdir
+        .cmd()
+        .args(&[
+            "cp",
+            "--enable-unstable",
+            "--to-arg=key_properties[]=id",
+            "singer-tap:cat tap.jsonl",
+            "singer-target:cat > out.jsonl",
+        ])
+        .expect_success();
+    testdir.expect_contains("out.jsonl", r#"{"key_properties":["id"],"schema":{"#);
+    testdir.expect_contains(
+        "out.jsonl",
+        r#"{"record":{"id":1,"name":"Ann"},"stream":"users","type":"RECORD"}"#,
+    );
+    testdir.expect_contains(
+        "out.jsonl",
+        r#"{"record":{"id":2,"name":null},"stream":"users","type":"RECORD"}"#,
+    );
+}
@@ -0,0 +1,15 @@
+//! Diagnosing configuration problems.
+
+use cli_test_dir::*;
+
+#[test]
+fn doctor_reports_configuration() {
+    let testdir = TestDir::new("dbcrossbar", "doctor_reports_configuration");
+    let output = testdir
+        .cmd()
+        .env("DBCROSSBAR_CONFIG_DIR", testdir.path("config"))
+        .arg("doctor")
+        .expect_success();
+    assert!(output.stdout_str().contains("configuration: using"));
+    assert!(output.stdout_str().contains("AWS credentials:"));
+}
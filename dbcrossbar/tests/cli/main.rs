@@ -6,3 +6,5 @@ pub(crate) mod about;
 pub(crate) mod conv;
 pub(crate) mod count;
 pub(crate) mod cp;
+pub(crate) mod doctor;
+pub(crate) mod recording;
@@ -0,0 +1,31 @@
+//! Recording and replaying runs with `--record` and `--replay`.
+
+use cli_test_dir::*;
+
+#[test]
+fn replay_verifies_data_checksums() {
+    let testdir = TestDir::new("dbcrossbar", "replay_verifies_data_checksums");
+    testdir.create_file("in.csv", "a,b\n1,2\n");
+    testdir
+        .cmd()
+        .arg("--record=recording.json")
+        .args(&["cp", "csv:in.csv", "csv:out1.csv"])
+        .expect_success();
+    testdir.expect_contains("recording.json", "\"checksums\"");
+
+    // Replaying with the same data should succeed.
+    testdir
+        .cmd()
+        .arg("--replay=recording.json")
+        .args(&["cp", "csv:in.csv", "csv:out2.csv"])
+        .expect_success();
+
+    // Replaying with different data should fail.
+    testdir.create_file("in.csv", "a,b\n1,3\n");
+    let output = testdir
+        .cmd()
+        .arg("--replay=recording.json")
+        .args(&["cp", "csv:in.csv", "csv:out3.csv"])
+        .expect_failure();
+    assert!(output.stderr_str().contains("but recording has MD5"));
+}
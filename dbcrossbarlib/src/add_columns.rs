@@ -0,0 +1,254 @@
+//! Adding generated "audit" columns to CSV streams.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::str::FromStr;
+
+use crate::common::*;
+use crate::schema::{Column, DataType};
+use crate::transform::spawn_sync_transform;
+
+/// How to compute the value of an added column.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum AddedValue {
+    /// The time at which we started loading data.
+    Now,
+    /// The name of the `CsvStream` containing the row.
+    StreamName,
+    /// The original file path or object URL of the stream, if known.
+    Source,
+    /// The modification time of the original file or object, if known.
+    SourceModified,
+    /// A constant string.
+    Literal(String),
+}
+
+impl AddedValue {
+    /// The portable data type of this value.
+    fn data_type(&self) -> DataType {
+        match self {
+            AddedValue::Now | AddedValue::SourceModified => {
+                DataType::TimestampWithTimeZone
+            }
+            AddedValue::StreamName | AddedValue::Source | AddedValue::Literal(_) => {
+                DataType::Text
+            }
+        }
+    }
+
+    /// Compute this value for a specific stream. Unknown values are returned
+    /// as empty strings, which we treat as `NULL`.
+    fn evaluate(&self, stream: &CsvStream, now: DateTime<Utc>) -> String {
+        let format_timestamp =
+            |ts: DateTime<Utc>| ts.to_rfc3339_opts(SecondsFormat::Micros, true);
+        match self {
+            AddedValue::Now => format_timestamp(now),
+            AddedValue::StreamName => stream.name.clone(),
+            AddedValue::Source => stream.metadata.source.clone().unwrap_or_default(),
+            AddedValue::SourceModified => stream
+                .metadata
+                .modified
+                .map(format_timestamp)
+                .unwrap_or_default(),
+            AddedValue::Literal(s) => s.to_owned(),
+        }
+    }
+}
+
+/// A list of columns to add to each row, parsed from a string like
+/// `loaded_at=now(),source_file=_stream_name,batch='b1'`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct AddColumns(Vec<(String, AddedValue)>);
+
+impl AddColumns {
+    /// Add our columns to the end of `table`.
+    pub(crate) fn add_to_schema(&self, table: &Table) -> Result<Table> {
+        let mut table = table.to_owned();
+        for (name, value) in &self.0 {
+            if table.columns.iter().any(|c| &c.name == name) {
+                return Err(format_err!(
+                    "cannot add column {} because it already exists",
+                    name,
+                ));
+            }
+            table.columns.push(Column {
+                name: name.to_owned(),
+                is_nullable: true,
+                data_type: value.data_type(),
+                comment: None,
+            });
+        }
+        Ok(table)
+    }
+
+    /// Add our columns to the end of every row in `stream`. Any uses of `now()`
+    /// will be replaced with `now`.
+    pub(crate) fn add_to_csv_stream(
+        &self,
+        ctx: &Context,
+        stream: CsvStream,
+        now: DateTime<Utc>,
+    ) -> Result<CsvStream> {
+        let names = self
+            .0
+            .iter()
+            .map(|(name, _)| name.to_owned())
+            .collect::<Vec<_>>();
+        let values = self
+            .0
+            .iter()
+            .map(|(_, value)| value.evaluate(&stream, now))
+            .collect::<Vec<_>>();
+        let data = spawn_sync_transform(
+            ctx.clone(),
+            "add_columns".to_owned(),
+            stream.data,
+            move |_ctx, rdr, wtr| append_csv_columns(&names, &values, rdr, wtr),
+        )?;
+        Ok(CsvStream {
+            name: stream.name,
+            metadata: stream.metadata,
+            data,
+        })
+    }
+}
+
+impl FromStr for AddColumns {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(grammar::add_columns(s).map_err(|err| {
+            format_err!(
+                "error parsing add_columns {:?} at {}: expected {}",
+                s,
+                err.location.offset,
+                err.expected,
+            )
+        })?)
+    }
+}
+
+peg::parser! {
+    /// A grammar for parsing `add_columns` specs.
+    grammar grammar() for str {
+        /// A comma-separated list of columns.
+        pub(super) rule add_columns() -> AddColumns
+            = cols:(column() ++ ",") { AddColumns(cols) }
+
+        /// A single `name=value` pair.
+        rule column() -> (String, AddedValue)
+            = name:name() "=" value:value() { (name, value) }
+
+        /// A column name.
+        rule name() -> String
+            = quiet! {
+                s:$(['A'..='Z' | 'a'..='z' | '_'] ['A'..='Z' | 'a'..='z' | '_' | '0'..='9']*)
+                { s.to_owned() }
+            }
+            / expected!("column name")
+
+        /// A value expression.
+        rule value() -> AddedValue
+            = "now()" { AddedValue::Now }
+            / "_stream_name" { AddedValue::StreamName }
+            / "_source_modified" { AddedValue::SourceModified }
+            / "_source" { AddedValue::Source }
+            / "'" chars:(quoted_char()*) "'" { AddedValue::Literal(chars.into_iter().collect()) }
+
+        /// A character inside a single-quoted string, where `''` represents
+        /// a single quote.
+        rule quoted_char() -> char
+            = "''" { '\'' }
+            / !"'" c:$([_]) { c.chars().next().expect("should have char") }
+    }
+}
+
+#[test]
+fn parse_add_columns() {
+    let parsed = "loaded_at=now(),source_file=_stream_name,src=_source,\
+                  src_modified=_source_modified,batch='it''s'"
+        .parse::<AddColumns>()
+        .unwrap();
+    assert_eq!(
+        parsed,
+        AddColumns(vec![
+            ("loaded_at".to_owned(), AddedValue::Now),
+            ("source_file".to_owned(), AddedValue::StreamName),
+            ("src".to_owned(), AddedValue::Source),
+            ("src_modified".to_owned(), AddedValue::SourceModified),
+            ("batch".to_owned(), AddedValue::Literal("it's".to_owned())),
+        ]),
+    );
+    assert!("".parse::<AddColumns>().is_err());
+    assert!("x=today()".parse::<AddColumns>().is_err());
+}
+
+#[test]
+fn evaluate_uses_stream_metadata() {
+    let now = Utc::now();
+    let mut stream = CsvStream {
+        name: "file1".to_owned(),
+        metadata: StreamMetadata::default(),
+        data: stream::empty().boxed(),
+    };
+    assert_eq!(AddedValue::StreamName.evaluate(&stream, now), "file1");
+    assert_eq!(AddedValue::Source.evaluate(&stream, now), "");
+    assert_eq!(AddedValue::SourceModified.evaluate(&stream, now), "");
+
+    stream.metadata = StreamMetadata {
+        source: Some("s3://bucket/dir/file1.csv".to_owned()),
+        modified: Some("2020-01-02T03:04:05Z".parse().unwrap()),
+    };
+    assert_eq!(
+        AddedValue::Source.evaluate(&stream, now),
+        "s3://bucket/dir/file1.csv",
+    );
+    assert_eq!(
+        AddedValue::SourceModified.evaluate(&stream, now),
+        "2020-01-02T03:04:05.000000Z",
+    );
+}
+
+/// Copy CSV data from `rdr` to `wtr`, adding `names` to the header and `values`
+/// to every row.
+fn append_csv_columns<R, W>(
+    names: &[String],
+    values: &[String],
+    rdr: R,
+    wtr: W,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+
+    let mut headers = rdr
+        .byte_headers()
+        .context("cannot read CSV header")?
+        .to_owned();
+    for name in names {
+        headers.push_field(name.as_bytes());
+    }
+    wtr.write_byte_record(&headers)
+        .context("cannot write CSV header")?;
+
+    let mut row = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut row).context("cannot read row")? {
+        for value in values {
+            row.push_field(value.as_bytes());
+        }
+        wtr.write_byte_record(&row).context("cannot write row")?;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn append_csv_columns_adds_values() {
+    let names = vec!["source_file".to_owned()];
+    let values = vec!["data".to_owned()];
+    let mut out = vec![];
+    append_csv_columns(&names, &values, &b"a,b\n1,2\n"[..], &mut out).unwrap();
+    assert_eq!(out, b"a,b,source_file\n1,2,data\n");
+}
@@ -128,6 +128,7 @@ impl SharedArguments<Verified> {
 pub enum SourceArgumentsFeatures {
     DriverArgs,
     WhereClause,
+    QueryParams,
 }
 
 impl fmt::Display for DisplayEnumSet<SourceArgumentsFeatures> {
@@ -139,6 +140,9 @@ impl fmt::Display for DisplayEnumSet<SourceArgumentsFeatures> {
         if self.0.contains(SourceArgumentsFeatures::WhereClause) {
             write!(f, "{}--where=$SQL_EXPR", sep.display())?;
         }
+        if self.0.contains(SourceArgumentsFeatures::QueryParams) {
+            write!(f, "{}--query-param=$NAME=$VALUE", sep.display())?;
+        }
         Ok(())
     }
 }
@@ -152,6 +156,9 @@ pub struct SourceArguments<ArgumentState> {
     /// A `WHERE` clause for this query.
     where_clause: Option<String>,
 
+    /// Named parameters to bind to our `WHERE` clause.
+    query_params: Vec<QueryParam>,
+
     /// We need to include a reference to `ArgumentState` somewhere, so use a
     /// 0-byte phantom value.
     _phantom: PhantomData<ArgumentState>,
@@ -160,10 +167,15 @@ pub struct SourceArguments<ArgumentState> {
 // These methods are only available in the `Unverified` state.
 impl SourceArguments<Unverified> {
     /// Construct a new `SourceArguments`.
-    pub fn new(driver_args: DriverArguments, where_clause: Option<String>) -> Self {
+    pub fn new(
+        driver_args: DriverArguments,
+        where_clause: Option<String>,
+        query_params: Vec<QueryParam>,
+    ) -> Self {
         Self {
             driver_args,
             where_clause,
+            query_params,
             _phantom: PhantomData,
         }
     }
@@ -171,7 +183,7 @@ impl SourceArguments<Unverified> {
     /// Construct a new `SourceArguments` with typical values for a temporary
     /// storage location.
     pub fn for_temporary() -> Self {
-        Self::new(DriverArguments::default(), None)
+        Self::new(DriverArguments::default(), None, vec![])
     }
 
     /// Verify that this structure only contains supported arguments. This uses
@@ -194,9 +206,24 @@ impl SourceArguments<Unverified> {
         {
             return Err(format_err!("this data source does not support --where"));
         }
+        if !self.query_params.is_empty() {
+            if !features
+                .source_args
+                .contains(SourceArgumentsFeatures::QueryParams)
+            {
+                return Err(format_err!(
+                    "this data source does not support --query-param"
+                ));
+            }
+            if self.where_clause.is_none() {
+                return Err(format_err!("--query-param requires --where"));
+            }
+            QueryParam::check_unique(&self.query_params)?;
+        }
         Ok(SourceArguments {
             driver_args: self.driver_args,
             where_clause: self.where_clause,
+            query_params: self.query_params,
             _phantom: PhantomData,
         })
     }
@@ -213,6 +240,11 @@ impl SourceArguments<Verified> {
     pub fn where_clause(&self) -> Option<&str> {
         self.where_clause.as_ref().map(|s| &s[..])
     }
+
+    /// Named parameters to bind to our `WHERE` clause.
+    pub fn query_params(&self) -> &[QueryParam] {
+        &self.query_params
+    }
 }
 
 /// What `DestinationArguments` features are supported by a given driver?
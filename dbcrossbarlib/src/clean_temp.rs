@@ -0,0 +1,233 @@
+//! Finding and deleting temporary directories left behind by earlier copies.
+//!
+//! `copy` deletes its own temporary directories when it finishes, but if
+//! `dbcrossbar` crashes or is killed, they'll stay around forever. Each copy
+//! uses a directory with a distinctive name, like
+//! `s3://bucket/tmp/dbcrossbar-tmp-a1B2c3D4e5/`, so we look for directories like
+//! that which haven't been modified recently. We never touch anything else,
+//! because other programs may share the same temporary storage.
+
+use chrono::{DateTime, Utc};
+use std::{collections::BTreeMap, time::Duration};
+
+#[cfg(feature = "s3")]
+use crate::clouds::aws::s3::{self, S3Connection};
+#[cfg(feature = "gs")]
+use crate::clouds::gcloud::storage;
+use crate::common::*;
+
+/// A temporary directory which appears to have been left behind.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OrphanedTemporary {
+    /// The URL of the directory.
+    pub url: Url,
+    /// When any file in this directory was last modified.
+    pub modified: DateTime<Utc>,
+}
+
+/// Find temporary directories under `temporaries` which were last modified
+/// more than `older_than` ago. We only look at `s3://` and `gs://`
+/// temporaries, and ignore anything which doesn't look like it was created by
+/// `dbcrossbar`.
+pub async fn find_orphaned_temporaries(
+    ctx: &Context,
+    temporaries: &[String],
+    older_than: Duration,
+) -> Result<Vec<OrphanedTemporary>> {
+    let cutoff = Utc::now()
+        - chrono::Duration::from_std(older_than).context("duration too long")?;
+    let mut orphans = vec![];
+    for temporary in temporaries {
+        let url = temp_root_url(temporary)?;
+        let objects = match url.scheme() {
+            "s3" => list_s3_temporary(ctx, &url).await?,
+            "gs" => list_gs_temporary(ctx, &url).await?,
+            _ => {
+                debug!(ctx.log(), "not checking {} for orphans", temporary);
+                continue;
+            }
+        };
+        orphans.extend(orphaned_dirs(&url, objects, cutoff)?);
+    }
+    Ok(orphans)
+}
+
+/// Delete a temporary directory found by `find_orphaned_temporaries`.
+pub async fn delete_orphaned_temporary(
+    ctx: &Context,
+    orphan: &OrphanedTemporary,
+) -> Result<()> {
+    match orphan.url.scheme() {
+        "s3" => delete_s3_temporary(ctx, &orphan.url).await,
+        "gs" => delete_gs_temporary(ctx, &orphan.url).await,
+        _ => Err(format_err!("don't know how to delete {}", orphan.url)),
+    }
+}
+
+/// Parse a `--temporary` value into a directory URL, removing any options like
+/// `?sse=aws:kms`.
+fn temp_root_url(temporary: &str) -> Result<Url> {
+    let mut url = temporary
+        .parse::<Url>()
+        .with_context(|_| format!("cannot parse {}", temporary))?;
+    url.set_query(None);
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    Ok(url)
+}
+
+/// Group `objects` under `root` by temporary directory, and return the
+/// directories where every object was last modified before `cutoff`.
+///
+/// We skip objects directly under `root`, directories which weren't named by
+/// `TemporaryStorage::temp_dir_name`, and directories containing objects with
+/// unknown modification times.
+fn orphaned_dirs<I>(
+    root: &Url,
+    objects: I,
+    cutoff: DateTime<Utc>,
+) -> Result<Vec<OrphanedTemporary>>
+where
+    I: IntoIterator<Item = (Url, Option<DateTime<Utc>>)>,
+{
+    // Map each directory to its newest modification time, or `None` if we
+    // can't tell.
+    let mut dirs = BTreeMap::<String, Option<DateTime<Utc>>>::new();
+    for (url, modified) in objects {
+        if !url.as_str().starts_with(root.as_str()) {
+            continue;
+        }
+        let rel_path = &url.as_str()[root.as_str().len()..];
+        let mut components = rel_path.splitn(2, '/');
+        let tag = components.next().unwrap_or("");
+        if components.next().is_none() || !TemporaryStorage::is_temp_dir_name(tag) {
+            continue;
+        }
+        let newest = dirs.entry(tag.to_owned()).or_insert(modified);
+        *newest = match (*newest, modified) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            _ => None,
+        };
+    }
+
+    let mut orphans = vec![];
+    for (tag, newest) in dirs {
+        if let Some(modified) = newest.filter(|&modified| modified < cutoff) {
+            orphans.push(OrphanedTemporary {
+                url: root.join(&format!("{}/", tag))?,
+                modified,
+            });
+        }
+    }
+    Ok(orphans)
+}
+
+#[test]
+fn orphaned_dirs_are_old_temp_dirs() {
+    use chrono::TimeZone;
+
+    let root = "s3://example/tmp/".parse::<Url>().unwrap();
+    let old = Utc.ymd(2024, 1, 1).and_hms(0, 0, 0);
+    let new = Utc.ymd(2024, 1, 3).and_hms(0, 0, 0);
+    let cutoff = Utc.ymd(2024, 1, 2).and_hms(0, 0, 0);
+    let object = |path: &str, modified| (root.join(path).unwrap(), modified);
+    let objects = vec![
+        object("dbcrossbar-tmp-a1B2c3D4e5/x.csv", Some(old)),
+        object("dbcrossbar-tmp-a1B2c3D4e5/y.csv", Some(old)),
+        object("dbcrossbar-tmp-f6G7h8I9j0/x.csv", Some(old)),
+        object("dbcrossbar-tmp-f6G7h8I9j0/y.csv", Some(new)),
+        object("dbcrossbar-tmp-k1L2m3N4o5/x.csv", None),
+        object("dbcrossbar-tmp-not-a-tag/x.csv", Some(old)),
+        object("dbcrossbar-tmp-p6Q7r8S9t0", Some(old)),
+        // Other programs' directories may have names which look random.
+        object("u1V2w3X4y5/x.csv", Some(old)),
+    ];
+    assert_eq!(
+        orphaned_dirs(&root, objects, cutoff).unwrap(),
+        vec![OrphanedTemporary {
+            url: "s3://example/tmp/dbcrossbar-tmp-a1B2c3D4e5/"
+                .parse()
+                .unwrap(),
+            modified: old,
+        }],
+    );
+}
+
+/// List the objects in an `s3://` temporary directory.
+#[cfg(feature = "s3")]
+async fn list_s3_temporary(
+    ctx: &Context,
+    url: &Url,
+) -> Result<Vec<(Url, Option<DateTime<Utc>>)>> {
+    // `s3::ls` treats an empty listing as an error.
+    let conn = S3Connection::default();
+    if s3::is_empty(ctx, url, &conn).await? {
+        return Ok(vec![]);
+    }
+    s3::ls(ctx, url, None, &conn)
+        .await?
+        .map_ok(|obj| (obj.url, obj.modified))
+        .try_collect()
+        .await
+}
+
+/// We were built without `s3:` support, so we can't list this.
+#[cfg(not(feature = "s3"))]
+async fn list_s3_temporary(
+    _ctx: &Context,
+    url: &Url,
+) -> Result<Vec<(Url, Option<DateTime<Utc>>)>> {
+    Err(format_err!("cannot list {} without s3 support", url))
+}
+
+/// Delete an `s3://` temporary directory.
+#[cfg(feature = "s3")]
+async fn delete_s3_temporary(ctx: &Context, url: &Url) -> Result<()> {
+    s3::rmdir(ctx, url, &S3Connection::default()).await
+}
+
+/// We were built without `s3:` support, so we can't delete this.
+#[cfg(not(feature = "s3"))]
+async fn delete_s3_temporary(_ctx: &Context, url: &Url) -> Result<()> {
+    Err(format_err!("cannot delete {} without s3 support", url))
+}
+
+/// List the objects in a `gs://` temporary directory.
+#[cfg(feature = "gs")]
+async fn list_gs_temporary(
+    ctx: &Context,
+    url: &Url,
+) -> Result<Vec<(Url, Option<DateTime<Utc>>)>> {
+    // We read the entire listing, because `storage::ls` uses a background
+    // worker which will report an error if we stop reading early.
+    storage::ls(ctx, url, None)
+        .await?
+        .and_then(|obj| async move {
+            let url = obj.to_url_string().parse::<Url>()?;
+            Ok((url, obj.updated()?))
+        })
+        .try_collect()
+        .await
+}
+
+/// We were built without `gs:` support, so we can't list this.
+#[cfg(not(feature = "gs"))]
+async fn list_gs_temporary(
+    _ctx: &Context,
+    url: &Url,
+) -> Result<Vec<(Url, Option<DateTime<Utc>>)>> {
+    Err(format_err!("cannot list {} without gs support", url))
+}
+
+/// Delete a `gs://` temporary directory.
+#[cfg(feature = "gs")]
+async fn delete_gs_temporary(ctx: &Context, url: &Url) -> Result<()> {
+    storage::rmdir(ctx, url).await
+}
+
+/// We were built without `gs:` support, so we can't delete this.
+#[cfg(not(feature = "gs"))]
+async fn delete_gs_temporary(_ctx: &Context, url: &Url) -> Result<()> {
+    Err(format_err!("cannot delete {} without gs support", url))
+}
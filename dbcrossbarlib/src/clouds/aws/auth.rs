@@ -1,9 +1,17 @@
 //! AWS authentication.
 
+use chrono::{DateTime, Duration, Utc};
+use lazy_static::lazy_static;
+use reqwest::Method;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+use super::{aws_query_string, xml, AwsClient, AwsRequest};
 use crate::common::*;
-use crate::credentials::CredentialsManager;
+use crate::credentials::{read_aws_profile, try_var, CredentialsManager};
 
 /// Credentials used to access S3.
+#[derive(Clone)]
 pub(crate) struct AwsCredentials {
     /// The value of `AWS_ACCESS_KEY_ID`.
     pub(crate) access_key_id: String,
@@ -27,3 +35,150 @@ impl AwsCredentials {
         })
     }
 }
+
+/// Look up our default AWS region, using `AWS_DEFAULT_REGION`, `AWS_REGION`
+/// or the `region` in our profile.
+///
+/// We do this separately from looking up credentials, because the region may be
+/// configured even when our credentials come from an ECS task or EC2 instance.
+pub(crate) async fn aws_default_region() -> Result<Option<String>> {
+    for name in &["AWS_DEFAULT_REGION", "AWS_REGION"] {
+        if let Some(region) = try_var(name)? {
+            return Ok(Some(region));
+        }
+    }
+    let explicit_profile = try_var("AWS_PROFILE")?;
+    let profile = explicit_profile.as_deref().unwrap_or("default");
+    Ok(read_aws_profile(profile).await?.remove("region"))
+}
+
+/// Temporary credentials for an assumed role, and when they expire.
+type CachedCredentials = (AwsCredentials, DateTime<Utc>);
+
+/// An IAM role which we assume using STS, typically to access resources in
+/// another AWS account.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct AwsRole {
+    /// The ARN of the role, like `arn:aws:iam::123456789012:role/etl`.
+    role_arn: String,
+    /// The external ID required by the role's trust policy, if any.
+    external_id: Option<String>,
+}
+
+impl AwsRole {
+    /// Build an `AwsRole` from the `role_arn` and `external_id` driver
+    /// arguments, if a role was specified.
+    pub(crate) fn from_driver_args(
+        role_arn: Option<&str>,
+        external_id: Option<&str>,
+    ) -> Result<Option<AwsRole>> {
+        match (role_arn, external_id) {
+            (Some(role_arn), external_id) => Ok(Some(AwsRole {
+                role_arn: role_arn.to_owned(),
+                external_id: external_id.map(|id| id.to_owned()),
+            })),
+            (None, Some(_)) => Err(format_err!("external_id requires role_arn")),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Assume this role, returning temporary credentials. We cache these
+    /// credentials until shortly before they expire.
+    pub(crate) async fn assume(&self, ctx: &Context) -> Result<AwsCredentials> {
+        lazy_static! {
+            static ref CACHE: Mutex<HashMap<AwsRole, CachedCredentials>> =
+                Mutex::new(HashMap::new());
+        }
+
+        // Hold our lock while calling STS, so that we only assume each role
+        // once, even if many streams need it at the same time.
+        let mut cache = CACHE.lock().await;
+        if let Some((creds, expiration)) = cache.get(self) {
+            if *expiration > Utc::now() + Duration::minutes(5) {
+                return Ok(creds.to_owned());
+            }
+        }
+
+        // Call `AssumeRole` using our default credentials. The global STS
+        // endpoint always uses `us-east-1` for signing.
+        let mut query = vec![
+            ("Action", "AssumeRole"),
+            ("RoleArn", &self.role_arn[..]),
+            ("RoleSessionName", "dbcrossbar"),
+            ("Version", "2011-06-15"),
+        ];
+        if let Some(external_id) = &self.external_id {
+            query.push(("ExternalId", &external_id[..]));
+        }
+        let mut url = "https://sts.amazonaws.com/".parse::<Url>()?;
+        url.set_query(Some(&aws_query_string(&query)));
+        let client = AwsClient::new(
+            AwsCredentials::try_default().await?,
+            "us-east-1".to_owned(),
+            "sts",
+        );
+        let (creds, expiration) = async {
+            let resp = client.send(ctx, AwsRequest::new(Method::GET, url)).await?;
+            let body = resp.text().await.context("cannot read STS response")?;
+            parse_assume_role_output(&body)
+        }
+        .await
+        .with_context(|_| format!("cannot assume role {}", self.role_arn))?;
+        cache.insert(self.to_owned(), (creds.clone(), expiration));
+        Ok(creds)
+    }
+}
+
+/// Parse an STS `AssumeRole` response, returning credentials and when they
+/// expire.
+fn parse_assume_role_output(body: &str) -> Result<(AwsCredentials, DateTime<Utc>)> {
+    let creds = xml::element(body, "Credentials")
+        .ok_or_else(|| format_err!("could not find <Credentials> in STS response"))?;
+    let expiration = xml::required_element_text(creds, "Expiration")?;
+    let expiration = DateTime::parse_from_rfc3339(&expiration)
+        .with_context(|_| format!("cannot parse expiration {:?}", expiration))?
+        .with_timezone(&Utc);
+    Ok((
+        AwsCredentials {
+            access_key_id: xml::required_element_text(creds, "AccessKeyId")?,
+            secret_access_key: xml::required_element_text(creds, "SecretAccessKey")?,
+            session_token: Some(xml::required_element_text(creds, "SessionToken")?),
+        },
+        expiration,
+    ))
+}
+
+#[test]
+fn parse_assume_role_xml() {
+    let body = r#"<AssumeRoleResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <AssumeRoleResult>
+    <AssumedRoleUser>
+      <Arn>arn:aws:sts::123456789012:assumed-role/etl/dbcrossbar</Arn>
+      <AssumedRoleId>AROAEXAMPLE:dbcrossbar</AssumedRoleId>
+    </AssumedRoleUser>
+    <Credentials>
+      <AccessKeyId>ASIAEXAMPLE</AccessKeyId>
+      <SecretAccessKey>secret</SecretAccessKey>
+      <SessionToken>token+/=</SessionToken>
+      <Expiration>2024-01-01T12:00:00Z</Expiration>
+    </Credentials>
+  </AssumeRoleResult>
+</AssumeRoleResponse>"#;
+    let (creds, expiration) = parse_assume_role_output(body).unwrap();
+    assert_eq!(creds.access_key_id, "ASIAEXAMPLE");
+    assert_eq!(creds.secret_access_key, "secret");
+    assert_eq!(creds.session_token.as_deref(), Some("token+/="));
+    assert_eq!(expiration.to_rfc3339(), "2024-01-01T12:00:00+00:00");
+    assert!(parse_assume_role_output("<AssumeRoleResponse/>").is_err());
+}
+
+#[test]
+fn aws_role_from_driver_args() {
+    let role = AwsRole::from_driver_args(Some("arn:aws:iam::1:role/etl"), Some("x"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(role.role_arn, "arn:aws:iam::1:role/etl");
+    assert_eq!(role.external_id.as_deref(), Some("x"));
+    assert_eq!(AwsRole::from_driver_args(None, None).unwrap(), None);
+    assert!(AwsRole::from_driver_args(None, Some("x")).is_err());
+}
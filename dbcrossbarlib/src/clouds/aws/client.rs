@@ -0,0 +1,259 @@
+//! A minimal client for AWS REST APIs.
+
+use bytes::Bytes;
+use chrono::Utc;
+use reqwest::{self, Method};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use tokio::time::delay_for;
+
+use super::{
+    retry::{is_temporary_status, max_retries},
+    sign_request_v4, xml, AwsCredentials, AwsService,
+};
+use crate::common::*;
+use crate::retry::delay_before_retry;
+
+/// A request to an AWS service.
+pub(crate) struct AwsRequest {
+    /// The HTTP method to use.
+    method: Method,
+    /// The URL to request. This must be encoded using `aws_uri_encode`.
+    url: Url,
+    /// Extra headers to send, with lower-case names.
+    headers: Vec<(String, String)>,
+    /// The body of our request.
+    body: Bytes,
+}
+
+impl AwsRequest {
+    /// Create a new request with no body.
+    pub(crate) fn new(method: Method, url: Url) -> AwsRequest {
+        AwsRequest {
+            method,
+            url,
+            headers: vec![],
+            body: Bytes::new(),
+        }
+    }
+
+    /// Add a header to this request. `name` must be lower-case.
+    pub(crate) fn header<V: Into<String>>(
+        mut self,
+        name: &'static str,
+        value: V,
+    ) -> Self {
+        self.headers.push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Add several headers to this request. Names must be lower-case.
+    pub(crate) fn headers<N: Into<String>>(
+        mut self,
+        headers: Vec<(N, String)>,
+    ) -> Self {
+        self.headers.extend(
+            headers
+                .into_iter()
+                .map(|(name, value)| (name.into(), value)),
+        );
+        self
+    }
+
+    /// Set the body of this request.
+    pub(crate) fn body(mut self, body: Bytes) -> Self {
+        self.body = body;
+        self
+    }
+}
+
+/// A client for a single AWS service in a single region.
+pub(crate) struct AwsClient {
+    /// Our HTTP client.
+    client: reqwest::Client,
+    /// The credentials we use to sign requests.
+    credentials: AwsCredentials,
+    /// The region we talk to.
+    region: String,
+    /// The name of the service we talk to, like `s3`.
+    service: &'static str,
+}
+
+impl AwsClient {
+    /// Create a new client for `service` in `region`.
+    pub(crate) fn new(
+        credentials: AwsCredentials,
+        region: String,
+        service: &'static str,
+    ) -> AwsClient {
+        AwsClient {
+            client: reqwest::Client::new(),
+            credentials,
+            region,
+            service,
+        }
+    }
+
+    /// The region we talk to.
+    pub(crate) fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// Sign and send `req`. Returns an error if the server does not report
+    /// success.
+    ///
+    /// If the request fails with an error that might be temporary, like S3's
+    /// `503 SlowDown`, we log a warning and retry with exponential backoff.
+    pub(crate) async fn send(
+        &self,
+        ctx: &Context,
+        req: AwsRequest,
+    ) -> Result<reqwest::Response> {
+        let max_retries = max_retries()?;
+        let mut retry = 0;
+        loop {
+            match self.send_once(ctx, &req).await {
+                Ok(resp) => return Ok(resp),
+                Err(SendError::Temporary(err)) if retry < max_retries => {
+                    retry += 1;
+                    let delay = delay_before_retry(retry);
+                    warn!(
+                        ctx.log(),
+                        "retrying in {:.1}s ({} of {}): {}",
+                        delay.as_secs_f64(),
+                        retry,
+                        max_retries,
+                        err,
+                    );
+                    delay_for(delay).await;
+                }
+                Err(SendError::Temporary(err)) | Err(SendError::Permanent(err)) => {
+                    return Err(err)
+                }
+            }
+        }
+    }
+
+    /// Sign and send `req` once, and return the response even if the server
+    /// reports an error. This is useful when an error response contains
+    /// information we need.
+    pub(crate) async fn send_unchecked(
+        &self,
+        ctx: &Context,
+        req: AwsRequest,
+    ) -> Result<reqwest::Response> {
+        match self.sign_and_send(ctx, &req).await {
+            Ok(resp) => Ok(resp),
+            Err(SendError::Temporary(err)) | Err(SendError::Permanent(err)) => {
+                Err(err)
+            }
+        }
+    }
+
+    /// Sign and send `req` once, returning an error if the server does not
+    /// report success.
+    async fn send_once(
+        &self,
+        ctx: &Context,
+        req: &AwsRequest,
+    ) -> Result<reqwest::Response, SendError> {
+        let resp = self.sign_and_send(ctx, req).await?;
+        let status = resp.status();
+        if status.is_success() {
+            Ok(resp)
+        } else {
+            // If we're talking to the wrong region, S3 tells us which region
+            // we should be using.
+            let bucket_region = resp
+                .headers()
+                .get("x-amz-bucket-region")
+                .and_then(|region| region.to_str().ok())
+                .filter(|&region| region != self.region)
+                .map(|region| region.to_owned());
+            let body = resp.text().await.unwrap_or_default();
+            trace!(ctx.log(), "error body: {}", body);
+            let mut message =
+                xml::error_message(&body).unwrap_or_else(|| status.to_string());
+            if let Some(bucket_region) = bucket_region {
+                message = format!(
+                    "{} (bucket is in region {}, not {})",
+                    message, bucket_region, self.region,
+                );
+            }
+            let err = format_err!("could not {} {}: {}", req.method, req.url, message);
+            if is_temporary_status(status) {
+                Err(SendError::Temporary(err))
+            } else {
+                Err(SendError::Permanent(err))
+            }
+        }
+    }
+
+    /// Sign and send `req` once, without checking the response status.
+    async fn sign_and_send(
+        &self,
+        ctx: &Context,
+        req: &AwsRequest,
+    ) -> Result<reqwest::Response, SendError> {
+        let AwsRequest {
+            method,
+            url,
+            headers: extra_headers,
+            body,
+        } = req;
+        trace!(ctx.log(), "{} {}", method, url);
+
+        // Sign our request. We do this each time we send it, because
+        // signatures expire.
+        let payload_sha256 = hex::encode(Sha256::digest(body));
+        let mut headers = BTreeMap::new();
+        for (name, value) in extra_headers {
+            headers.insert(name.to_owned(), value.to_owned());
+        }
+        headers.insert("x-amz-content-sha256".to_owned(), payload_sha256.clone());
+        let service = AwsService {
+            region: &self.region,
+            name: self.service,
+        };
+        sign_request_v4(
+            &self.credentials,
+            &service,
+            Utc::now(),
+            method.as_str(),
+            url,
+            &mut headers,
+            &payload_sha256,
+        )
+        .map_err(SendError::Permanent)?;
+
+        // Send it. `reqwest` will fill in the `host` header for us.
+        let mut builder = self.client.request(method.clone(), url.as_str());
+        for (name, value) in &headers {
+            if name != "host" {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+        }
+        match builder.body(body.clone()).send().await {
+            Ok(resp) => Ok(resp),
+            Err(err) => {
+                // Errors sending the request or timeouts are worth retrying.
+                let temporary = err.is_request() || err.is_timeout();
+                let err: Error = err.into();
+                let err = err.context(format!("could not {} {}", method, url)).into();
+                if temporary {
+                    Err(SendError::Temporary(err))
+                } else {
+                    Err(SendError::Permanent(err))
+                }
+            }
+        }
+    }
+}
+
+/// An error from `AwsClient::send_once`.
+enum SendError {
+    /// An error which might go away if we try again.
+    Temporary(Error),
+    /// An error which will probably happen again.
+    Permanent(Error),
+}
@@ -1,8 +1,14 @@
 //! Interfaces to AWS.
 
 mod auth;
+mod client;
+#[cfg(feature = "bigml")]
+pub(crate) mod presign;
+mod retry;
 pub(crate) mod s3;
 mod signing;
+mod xml;
 
 pub(crate) use auth::*;
+pub(crate) use client::*;
 pub(crate) use signing::*;
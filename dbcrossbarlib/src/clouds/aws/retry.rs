@@ -0,0 +1,38 @@
+//! Retrying AWS requests which fail temporarily.
+
+use reqwest::StatusCode;
+
+use crate::common::*;
+use crate::retry::max_retries_from_env;
+
+/// How many times we retry a failed request, unless overridden by
+/// `DBCROSSBAR_AWS_MAX_RETRIES`.
+const DEFAULT_MAX_RETRIES: u32 = 8;
+
+/// How many times should we retry a request which fails temporarily?
+///
+/// This can be set using `DBCROSSBAR_AWS_MAX_RETRIES`. A value of `0` disables
+/// retries.
+pub(crate) fn max_retries() -> Result<u32> {
+    max_retries_from_env("DBCROSSBAR_AWS_MAX_RETRIES", DEFAULT_MAX_RETRIES)
+}
+
+/// Might a request which failed with `status` succeed if we try again? This
+/// includes the `503 SlowDown` errors that S3 returns when we send too many
+/// requests.
+pub(crate) fn is_temporary_status(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::INTERNAL_SERVER_ERROR
+        || status == StatusCode::BAD_GATEWAY
+        || status == StatusCode::SERVICE_UNAVAILABLE
+        || status == StatusCode::GATEWAY_TIMEOUT
+}
+
+#[test]
+fn temporary_statuses() {
+    assert!(is_temporary_status(StatusCode::SERVICE_UNAVAILABLE));
+    assert!(is_temporary_status(StatusCode::INTERNAL_SERVER_ERROR));
+    assert!(!is_temporary_status(StatusCode::FORBIDDEN));
+    assert!(!is_temporary_status(StatusCode::NOT_FOUND));
+}
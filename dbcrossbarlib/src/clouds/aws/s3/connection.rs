@@ -0,0 +1,64 @@
+//! How we connect to S3.
+
+use crate::clouds::aws::AwsRole;
+use crate::common::*;
+
+/// How should we connect to S3? This includes the credentials we use, and
+/// which endpoint we talk to.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct S3Connection {
+    /// An IAM role to assume, or `None` to use our default credentials.
+    role: Option<AwsRole>,
+    /// The region of our bucket, or `None` to ask S3 where it is.
+    region: Option<String>,
+    /// Should we use S3 Transfer Acceleration?
+    accelerate: bool,
+}
+
+impl S3Connection {
+    /// Create a new `S3Connection`, checking that `region` looks reasonable.
+    pub(crate) fn new(
+        role: Option<AwsRole>,
+        region: Option<String>,
+        accelerate: bool,
+    ) -> Result<S3Connection> {
+        if let Some(region) = &region {
+            let valid = !region.is_empty()
+                && region
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if !valid {
+                return Err(format_err!("invalid AWS region {:?}", region));
+            }
+        }
+        Ok(S3Connection {
+            role,
+            region,
+            accelerate,
+        })
+    }
+
+    /// The IAM role to assume, if any.
+    pub(crate) fn role(&self) -> Option<&AwsRole> {
+        self.role.as_ref()
+    }
+
+    /// The region to use instead of asking S3, if any.
+    pub(crate) fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// Should we use S3 Transfer Acceleration?
+    pub(crate) fn accelerate(&self) -> bool {
+        self.accelerate
+    }
+}
+
+#[test]
+fn regions_are_validated() {
+    let conn = S3Connection::new(None, Some("eu-west-1".to_owned()), true).unwrap();
+    assert_eq!(conn.region(), Some("eu-west-1"));
+    assert!(conn.accelerate());
+    assert!(S3Connection::new(None, Some("".to_owned()), false).is_err());
+    assert!(S3Connection::new(None, Some("evil.com/x".to_owned()), false).is_err());
+}
@@ -1,27 +1,27 @@
 //! Download files from S3.
 
-use std::process::Stdio;
-use tokio::io::BufReader;
+use reqwest::Method;
 
-use super::aws_s3_command;
+use super::{https_url, parse_s3_url, s3_client, RequestPayer, S3Connection};
+use crate::clouds::aws::AwsRequest;
 use crate::common::*;
-use crate::tokio_glue::copy_reader_to_stream;
+use crate::http_response::http_response_stream;
 
-/// Download the file at the specified URL as a stream.
+/// Download the file at the specified URL as a stream. Pass `request_payer` to
+/// download from requester-pays buckets. We connect to S3 using `conn`.
 pub(crate) async fn download_file(
     ctx: &Context,
     file_url: &Url,
+    request_payer: Option<RequestPayer>,
+    conn: &S3Connection,
 ) -> Result<BoxStream<BytesMut>> {
-    debug!(ctx.log(), "streaming from {} using `aws s3 cp`", file_url);
-    let mut child = aws_s3_command()
-        .await?
-        .args(&["cp", file_url.as_str(), "-"])
-        .stdout(Stdio::piped())
-        .spawn()
-        .context("error running `aws s3 cp`")?;
-    let child_stdout = child.stdout.take().expect("child should have stdout");
-    let child_stdout = BufReader::with_capacity(BUFFER_SIZE, child_stdout);
-    let data = copy_reader_to_stream(ctx.clone(), child_stdout)?;
-    ctx.spawn_process(format!("aws s3 cp {} -", file_url), child);
-    Ok(data.boxed())
+    debug!(ctx.log(), "streaming from {}", file_url);
+    let (bucket, key) = parse_s3_url(file_url)?;
+    let client = s3_client(ctx, conn, &bucket).await?;
+    let url = https_url(&client, &bucket, &key, &[])?;
+    let headers = request_payer.map(|p| p.headers()).unwrap_or_default();
+    let resp = client
+        .send(ctx, AwsRequest::new(Method::GET, url).headers(headers))
+        .await?;
+    Ok(http_response_stream(resp))
 }
@@ -0,0 +1,146 @@
+//! Server-side encryption for the objects we write to S3.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+
+/// The kinds of server-side encryption supported by S3.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum SseAlgorithm {
+    /// Encrypt using keys managed by S3.
+    Aes256,
+    /// Encrypt using a key managed by AWS KMS.
+    AwsKms,
+}
+
+impl fmt::Display for SseAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SseAlgorithm::Aes256 => write!(f, "AES256"),
+            SseAlgorithm::AwsKms => write!(f, "aws:kms"),
+        }
+    }
+}
+
+impl FromStr for SseAlgorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "AES256" => Ok(SseAlgorithm::Aes256),
+            "aws:kms" => Ok(SseAlgorithm::AwsKms),
+            _ => Err(format_err!(
+                "unknown server-side encryption {:?} (expected AES256 or aws:kms)",
+                s
+            )),
+        }
+    }
+}
+
+/// How should S3 encrypt the objects we write?
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ServerSideEncryption {
+    /// The encryption to use, or `None` to use the bucket's default.
+    algorithm: Option<SseAlgorithm>,
+    /// The KMS key to use with `SseAlgorithm::AwsKms`, or `None` to use the
+    /// default KMS key for S3.
+    kms_key_id: Option<String>,
+}
+
+impl ServerSideEncryption {
+    /// Create a new `ServerSideEncryption`, checking that our arguments make
+    /// sense together.
+    pub(crate) fn new(
+        algorithm: Option<SseAlgorithm>,
+        kms_key_id: Option<String>,
+    ) -> Result<Self> {
+        if kms_key_id.is_some() && algorithm != Some(SseAlgorithm::AwsKms) {
+            return Err(format_err!("sse_kms_key_id requires sse=aws:kms"));
+        }
+        Ok(ServerSideEncryption {
+            algorithm,
+            kms_key_id,
+        })
+    }
+
+    /// Parse the query string of a temporary storage URL, like
+    /// `s3://bucket/tmp/?sse=aws:kms&sse_kms_key_id=...`.
+    pub(crate) fn from_url_query(url: &Url) -> Result<Self> {
+        let mut algorithm = None;
+        let mut kms_key_id = None;
+        for (key, value) in url.query_pairs() {
+            match &key[..] {
+                "sse" => algorithm = Some(value.parse::<SseAlgorithm>()?),
+                "sse_kms_key_id" => kms_key_id = Some(value.into_owned()),
+                _ => {
+                    return Err(format_err!(
+                        "unknown option {:?} in {} (expected sse or sse_kms_key_id)",
+                        key,
+                        url,
+                    ))
+                }
+            }
+        }
+        Self::new(algorithm, kms_key_id)
+    }
+
+    /// The encryption to use, if any.
+    pub(crate) fn algorithm(&self) -> Option<SseAlgorithm> {
+        self.algorithm
+    }
+
+    /// The KMS key to use, if any.
+    pub(crate) fn kms_key_id(&self) -> Option<&str> {
+        self.kms_key_id.as_deref()
+    }
+
+    /// Extra headers to send when creating objects.
+    pub(crate) fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![];
+        if let Some(algorithm) = self.algorithm() {
+            headers.push(("x-amz-server-side-encryption", algorithm.to_string()));
+        }
+        if let Some(kms_key_id) = self.kms_key_id() {
+            headers.push((
+                "x-amz-server-side-encryption-aws-kms-key-id",
+                kms_key_id.to_owned(),
+            ));
+        }
+        headers
+    }
+}
+
+#[test]
+fn parse_server_side_encryption() {
+    let url = "s3://example/tmp/?sse=aws:kms&sse_kms_key_id=alias/etl"
+        .parse::<Url>()
+        .unwrap();
+    let sse = ServerSideEncryption::from_url_query(&url).unwrap();
+    assert_eq!(sse.algorithm(), Some(SseAlgorithm::AwsKms));
+    assert_eq!(sse.kms_key_id(), Some("alias/etl"));
+    assert_eq!(
+        sse.headers(),
+        vec![
+            ("x-amz-server-side-encryption", "aws:kms".to_owned()),
+            (
+                "x-amz-server-side-encryption-aws-kms-key-id",
+                "alias/etl".to_owned(),
+            ),
+        ],
+    );
+
+    let url = "s3://example/tmp/".parse::<Url>().unwrap();
+    let sse = ServerSideEncryption::from_url_query(&url).unwrap();
+    assert_eq!(sse, ServerSideEncryption::default());
+    assert!(sse.headers().is_empty());
+
+    for bad in &[
+        "s3://example/tmp/?sse=aes256",
+        "s3://example/tmp/?sse_kms_key_id=k",
+        "s3://example/tmp/?sse=AES256&sse_kms_key_id=k",
+        "s3://example/tmp/?encrypt=yes",
+    ] {
+        let url = bad.parse::<Url>().unwrap();
+        assert!(ServerSideEncryption::from_url_query(&url).is_err());
+    }
+}
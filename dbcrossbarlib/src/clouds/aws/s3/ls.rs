@@ -1,51 +1,242 @@
 //! Listing S3 files.
 
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::process::Stdio;
-use tokio::io::BufReader;
+use chrono::{DateTime, Utc};
+use futures::stream;
+use reqwest::Method;
+use std::sync::Arc;
 
-use super::aws_s3_command;
+use super::{
+    https_url, parse_s3_url, s3_client, RequestPayer, S3Client, S3Connection,
+};
+use crate::clouds::aws::{xml, AwsRequest};
 use crate::common::*;
 
-/// List all the files at the specified `s2://` URL, recursively.
+/// A file listed by `ListObjectsV2`.
+#[derive(Debug)]
+pub(crate) struct S3Object {
+    /// The `s3://` URL of this file.
+    pub(crate) url: Url,
+    /// The key of this file in its bucket.
+    pub(crate) key: String,
+    /// When this file was last modified.
+    pub(crate) modified: Option<DateTime<Utc>>,
+    /// The size of this file, in bytes.
+    pub(crate) size: u64,
+}
+
+/// A single page of `ListObjectsV2` results.
+pub(super) struct ListPage {
+    /// The objects on this page.
+    pub(super) objects: Vec<S3Object>,
+    /// A token for fetching the next page, if there is one.
+    pub(super) next_token: Option<String>,
+}
+
+/// Lists all the objects under an `s3://` URL, one page at a time.
+pub(super) struct Lister {
+    /// Our S3 client.
+    client: S3Client,
+    /// The bucket we're listing.
+    bucket: String,
+    /// The key prefix we're listing.
+    prefix: String,
+    /// The URL of our bucket, used to build object URLs.
+    bucket_url: Url,
+    /// Who pays for our requests?
+    request_payer: Option<RequestPayer>,
+}
+
+impl Lister {
+    /// Prepare to list the objects under `url`.
+    pub(super) async fn new(
+        ctx: &Context,
+        url: &Url,
+        request_payer: Option<RequestPayer>,
+        conn: &S3Connection,
+    ) -> Result<Lister> {
+        let (bucket, prefix) = parse_s3_url(url)?;
+        Ok(Lister {
+            client: s3_client(ctx, conn, &bucket).await?,
+            bucket,
+            prefix,
+            bucket_url: bucket_url(url)?,
+            request_payer,
+        })
+    }
+
+    /// Our S3 client.
+    pub(super) fn client(&self) -> &S3Client {
+        &self.client
+    }
+
+    /// Fetch the page of results starting at `token`, or the first page if
+    /// `token` is `None`.
+    pub(super) async fn page(
+        &self,
+        ctx: &Context,
+        token: Option<&str>,
+    ) -> Result<ListPage> {
+        let mut query = vec![("list-type", "2"), ("prefix", &self.prefix[..])];
+        if let Some(token) = token {
+            query.push(("continuation-token", token));
+        }
+        let url = https_url(&self.client, &self.bucket, "", &query)?;
+        let headers = self.request_payer.map(|p| p.headers()).unwrap_or_default();
+        let resp = self
+            .client
+            .send(ctx, AwsRequest::new(Method::GET, url).headers(headers))
+            .await?;
+        let body = resp.text().await.context("cannot read S3 listing")?;
+        parse_list_page(&self.bucket_url, &body)
+    }
+}
+
+/// List all the files at the specified `s3://` URL, recursively. Pass
+/// `request_payer` to list requester-pays buckets. We connect to S3 using
+/// `conn`.
+///
+/// As with `aws s3 ls`, it's an error if no files are found.
 pub(crate) async fn ls(
     ctx: &Context,
     url: &Url,
-) -> Result<impl Stream<Item = Result<Url>> + Send + Unpin + 'static> {
-    // Start a child process to list files at that URL.
+    request_payer: Option<RequestPayer>,
+    conn: &S3Connection,
+) -> Result<BoxStream<S3Object>> {
     debug!(ctx.log(), "listing {}", url);
-    let mut child = aws_s3_command()
-        .await?
-        .args(&["ls", "--recursive", url.as_str()])
-        .stdout(Stdio::piped())
-        .spawn()
-        .context("error running `aws s3 ls`")?;
-    let child_stdout = child.stdout.take().expect("child should have stdout");
-    ctx.spawn_process(format!("aws s3 ls {}", url), child);
-
-    // Parse `ls` output into lines, and convert into `Url`s.
-    //
-    // XXX - This will fail (either silently or noisily, I'm not sure) if there
-    // are 1000+ files in the S3 directory, and we can't fix this without
-    // switching from `aws s3` to native S3 API calls from Rust.
+    let lister = Arc::new(Lister::new(ctx, url, request_payer, conn).await?);
+    let first = lister.page(ctx, None).await?;
+    if first.objects.is_empty() && first.next_token.is_none() {
+        return Err(format_err!("no files found at {}", url));
+    }
+
+    // Fetch any remaining pages lazily, in case there are a lot of them.
     let ctx = ctx.to_owned();
-    let url = url.to_owned();
-    let lines = BufReader::with_capacity(BUFFER_SIZE, child_stdout)
-        .lines()
-        .map_err(|e| format_err!("error reading `aws s3 ls` output: {}", e))
-        .and_then(move |line| {
-            let ctx = ctx.clone();
-            let url = url.clone();
-            async move {
-                trace!(ctx.log(), "`aws s3 ls` line: {}", line);
-                let bucket_url = bucket_url(&url)?;
-                let path = path_from_line(&line)?;
-                Ok(bucket_url.join(&path)?)
+    let rest = stream::try_unfold(first.next_token, move |token| {
+        let ctx = ctx.clone();
+        let lister = lister.clone();
+        async move {
+            match token {
+                None => Ok::<_, Error>(None),
+                Some(token) => {
+                    let page = lister.page(&ctx, Some(&token)).await?;
+                    let objects = stream::iter(page.objects.into_iter().map(Ok));
+                    Ok(Some((objects, page.next_token)))
+                }
             }
+        }
+    })
+    .try_flatten();
+    Ok(stream::iter(first.objects.into_iter().map(Ok))
+        .chain(rest)
+        .boxed())
+}
+
+/// Make sure we can list the specified `s3://` URL, even if it's empty.
+pub(crate) async fn check_access(ctx: &Context, url: &Url) -> Result<()> {
+    let lister = Lister::new(ctx, url, None, &S3Connection::default()).await?;
+    lister.page(ctx, None).await?;
+    Ok(())
+}
+
+/// Is the specified `s3://` URL empty? We connect to S3 using `conn`.
+pub(crate) async fn is_empty(
+    ctx: &Context,
+    url: &Url,
+    conn: &S3Connection,
+) -> Result<bool> {
+    let lister = Lister::new(ctx, url, None, conn).await?;
+    let first = lister.page(ctx, None).await?;
+    Ok(first.objects.is_empty() && first.next_token.is_none())
+}
+
+/// Parse a `ListObjectsV2` response.
+fn parse_list_page(bucket_url: &Url, body: &str) -> Result<ListPage> {
+    let mut objects = vec![];
+    for contents in xml::elements(body, "Contents") {
+        let key = xml::required_element_text(contents, "Key")?;
+        // Skip empty "directory" placeholders created by the S3 console.
+        if key.ends_with('/') {
+            continue;
+        }
+        let size = xml::required_element_text(contents, "Size")?
+            .parse::<u64>()
+            .context("cannot parse S3 object size")?;
+        let modified = xml::element_text(contents, "LastModified")?
+            .and_then(|modified| DateTime::parse_from_rfc3339(&modified).ok())
+            .map(|modified| modified.with_timezone(&Utc));
+        objects.push(S3Object {
+            url: object_url(bucket_url, &key)?,
+            key,
+            modified,
+            size,
         });
+    }
+    let next_token = if xml::element(body, "IsTruncated") == Some("true") {
+        Some(xml::required_element_text(body, "NextContinuationToken")?)
+    } else {
+        None
+    };
+    Ok(ListPage {
+        objects,
+        next_token,
+    })
+}
 
-    Ok(lines.boxed())
+#[test]
+fn parse_list_objects_v2() {
+    let bucket_url = "s3://bucket/".parse::<Url>().unwrap();
+    let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>bucket</Name>
+  <Prefix>dir/</Prefix>
+  <KeyCount>3</KeyCount>
+  <MaxKeys>1000</MaxKeys>
+  <IsTruncated>true</IsTruncated>
+  <NextContinuationToken>1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=</NextContinuationToken>
+  <Contents>
+    <Key>dir/</Key>
+    <LastModified>2013-09-02T21:32:57.000Z</LastModified>
+    <Size>0</Size>
+  </Contents>
+  <Contents>
+    <Key>dir/a.csv</Key>
+    <LastModified>2013-09-02T21:37:53.000Z</LastModified>
+    <ETag>&quot;599bab3ed2c697f1d26842727561fd94&quot;</ETag>
+    <Size>10</Size>
+    <StorageClass>STANDARD</StorageClass>
+  </Contents>
+  <Contents>
+    <Key>dir/b &amp; c#1.csv</Key>
+    <LastModified>2013-09-02T21:37:53.000Z</LastModified>
+    <Size>2863288</Size>
+  </Contents>
+</ListBucketResult>"#;
+    let page = parse_list_page(&bucket_url, body).unwrap();
+    assert_eq!(
+        page.next_token.as_deref(),
+        Some("1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM="),
+    );
+    assert_eq!(page.objects.len(), 2);
+    assert_eq!(page.objects[0].url.as_str(), "s3://bucket/dir/a.csv");
+    assert_eq!(page.objects[0].size, 10);
+    assert_eq!(
+        page.objects[0].modified.unwrap().to_rfc3339(),
+        "2013-09-02T21:37:53+00:00",
+    );
+    assert_eq!(page.objects[1].key, "dir/b & c#1.csv");
+    assert_eq!(
+        page.objects[1].url.as_str(),
+        "s3://bucket/dir/b%20&%20c%231.csv"
+    );
+    assert_eq!(
+        parse_s3_url(&page.objects[1].url).unwrap().1,
+        "dir/b & c#1.csv",
+    );
+
+    let body = "<ListBucketResult><IsTruncated>false</IsTruncated></ListBucketResult>";
+    let page = parse_list_page(&bucket_url, body).unwrap();
+    assert!(page.objects.is_empty());
+    assert!(page.next_token.is_none());
 }
 
 /// Given an S3 URL, get the URL for just the bucket itself.
@@ -75,29 +266,13 @@ fn bucket_url_extracts_bucket() {
     }
 }
 
-/// Given a line of `aws s3 ls` output, extract the path.
-fn path_from_line(line: &str) -> Result<String> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r#"^[-0-9]+ [:0-9]+ +[0-9]+ ([^\r\n]+)"#)
-            .expect("invalid regex in source");
-    }
-    let cap = RE
-        .captures(line)
-        .ok_or_else(|| format_err!("cannot parse S3 ls output: {:?}", line))?;
-    Ok(cap[1].to_owned())
-}
-
-#[test]
-fn path_from_line_returns_entire_path() {
-    let examples = &[
-        ("2013-09-02 21:37:53         10 a.txt", "a.txt"),
-        ("2013-09-02 21:37:53    2863288 foo.zip", "foo.zip"),
-        (
-            "2013-09-02 21:32:57         23 foo/bar/.baz/a",
-            "foo/bar/.baz/a",
-        ),
-    ];
-    for &(line, rel_path) in examples {
-        assert_eq!(path_from_line(line).unwrap(), rel_path);
-    }
+/// Build the `s3://` URL for `key`, relative to `bucket_url`.
+fn object_url(bucket_url: &Url, key: &str) -> Result<Url> {
+    // `Url::join` escapes most characters for us, but it would treat these
+    // characters as part of the URL syntax.
+    let path = key
+        .replace('%', "%25")
+        .replace('#', "%23")
+        .replace('?', "%3F");
+    Ok(bucket_url.join(&path)?)
 }
@@ -1,44 +1,247 @@
-//! Wrappers for `aws` CLI commands.
-//!
-//! We'll probably replace these with a native implementation at some point.
+//! A native S3 client, built on top of the S3 REST API.
 
-use tokio::process::Command;
+use lazy_static::lazy_static;
+use percent_encoding::percent_decode_str;
+use reqwest::Method;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
 
+use super::{
+    aws_default_region, aws_query_string, aws_uri_encode, AwsClient, AwsCredentials,
+    AwsRequest,
+};
 use crate::common::*;
-use crate::credentials::CredentialsManager;
 
+mod connection;
 mod download_file;
+mod encryption;
 mod ls;
+mod request_payer;
 mod rmdir;
+mod storage_class;
+mod tags;
 mod upload_file;
 
+pub(crate) use connection::S3Connection;
 pub(crate) use download_file::download_file;
-pub(crate) use ls::ls;
+pub(crate) use encryption::{ServerSideEncryption, SseAlgorithm};
+pub(crate) use ls::{check_access, is_empty, ls, S3Object};
+pub(crate) use request_payer::RequestPayer;
 pub(crate) use rmdir::rmdir;
-pub(crate) use upload_file::upload_file;
+pub(crate) use storage_class::StorageClass;
+pub(crate) use tags::ObjectTags;
+pub(crate) use upload_file::{upload_file, UploadOptions};
 
-/// Create a new `tokio::process::Command` that invokes `aws s3` with the
-/// necessary `AWS` variables set.
+/// A client for S3.
+pub(crate) struct S3Client {
+    /// Our underlying AWS client.
+    client: AwsClient,
+    /// Should we use S3 Transfer Acceleration endpoints?
+    accelerate: bool,
+}
+
+impl S3Client {
+    /// Sign and send `req`, retrying temporary errors.
+    pub(crate) async fn send(
+        &self,
+        ctx: &Context,
+        req: AwsRequest,
+    ) -> Result<reqwest::Response> {
+        self.client.send(ctx, req).await
+    }
+}
+
+/// Create a new S3 client for `bucket` using the settings in `conn`. If `conn`
+/// specifies a role, we assume it and use its temporary credentials instead of
+/// our default credentials. If `conn` doesn't specify a region, we ask S3
+/// where `bucket` is.
 ///
-/// The plan is for this to someday take a `bucket` argument that looks up
-/// bucket-specific credentials, once [`CredentialsManager`] supports per-host
-/// credentials. For now, this basically exists to (try to) ensure that we're
-/// not relying on `aws`'s built-in authentication.
-pub(self) async fn aws_s3_command() -> Result<Command> {
-    let creds = CredentialsManager::singleton().get("aws").await?;
-
-    let mut command = Command::new("aws");
-    command.env("AWS_ACCESS_KEY_ID", creds.get_required("access_key_id")?);
-    command.env(
-        "AWS_SECRET_ACCESS_KEY",
-        creds.get_required("secret_access_key")?,
-    );
-    if let Some(session_token) = creds.get_optional("session_token") {
-        command.env("AWS_SESSION_TOKEN", session_token);
+/// The plan is for this to someday look up bucket-specific credentials, once
+/// `CredentialsManager` supports per-host credentials.
+pub(crate) async fn s3_client(
+    ctx: &Context,
+    conn: &S3Connection,
+    bucket: &str,
+) -> Result<S3Client> {
+    let credentials = match conn.role() {
+        Some(role) => role.assume(ctx).await?,
+        None => AwsCredentials::try_default().await?,
+    };
+    let region = match conn.region() {
+        Some(region) => region.to_owned(),
+        None => bucket_region(ctx, &credentials, bucket).await?,
+    };
+    Ok(S3Client {
+        client: AwsClient::new(credentials, region, "s3"),
+        accelerate: conn.accelerate(),
+    })
+}
+
+/// Look up the region containing `bucket`. We cache the results, because we
+/// create a new client for every file we read or write.
+async fn bucket_region(
+    ctx: &Context,
+    credentials: &AwsCredentials,
+    bucket: &str,
+) -> Result<String> {
+    lazy_static! {
+        static ref CACHE: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    }
+
+    // Hold our lock while calling S3, so that we only look up each bucket
+    // once, even if many streams need it at the same time.
+    let mut cache = CACHE.lock().await;
+    if let Some(region) = cache.get(bucket) {
+        return Ok(region.to_owned());
+    }
+
+    // Call `HeadBucket` on the global endpoint. S3 includes the bucket's
+    // region in the `x-amz-bucket-region` header, even if it redirects us or
+    // denies access. We use a path-style URL, because it also works for bucket
+    // names containing `.`.
+    let url = format!("https://s3.amazonaws.com/{}", aws_uri_encode(bucket, true))
+        .parse::<Url>()
+        .with_context(|_| format!("cannot build URL for s3://{}/", bucket))?;
+    let client = AwsClient::new(credentials.to_owned(), "us-east-1".to_owned(), "s3");
+    let resp = client
+        .send_unchecked(ctx, AwsRequest::new(Method::HEAD, url))
+        .await
+        .with_context(|_| format!("could not find region of s3://{}/", bucket))?;
+    let region = match resp.headers().get("x-amz-bucket-region") {
+        Some(region) => region
+            .to_str()
+            .context("could not parse x-amz-bucket-region header")?
+            .to_owned(),
+        // This usually means that the bucket doesn't exist. Fall back to our
+        // default region, and let our real request report any errors.
+        None => aws_default_region().await?.ok_or_else(|| {
+            format_err!(
+                "could not find region of s3://{}/ ({}), and AWS_DEFAULT_REGION \
+                 is not set",
+                bucket,
+                resp.status(),
+            )
+        })?,
+    };
+    debug!(ctx.log(), "s3://{}/ is in region {}", bucket, region);
+    cache.insert(bucket.to_owned(), region.clone());
+    Ok(region)
+}
+
+/// Split an `s3://` URL into a bucket name and an object key (or key prefix).
+pub(crate) fn parse_s3_url(url: &Url) -> Result<(String, String)> {
+    if url.scheme() != "s3" {
+        return Err(format_err!("expected s3:// URL, found {}", url));
+    }
+    let bucket = url
+        .host_str()
+        .ok_or_else(|| format_err!("could not find bucket name in {}", url))?
+        .to_owned();
+    let key = percent_decode_str(url.path().trim_start_matches('/'))
+        .decode_utf8()
+        .with_context(|_| format!("invalid UTF-8 in {}", url))?
+        .into_owned();
+    Ok((bucket, key))
+}
+
+/// Build the HTTPS URL for `key` in `bucket`, with the specified `query`
+/// parameters.
+///
+/// We use virtual-hosted-style URLs unless the bucket name contains a `.`,
+/// which would break TLS certificate validation. Transfer Acceleration
+/// requires virtual-hosted-style URLs, so it doesn't work with these buckets.
+pub(crate) fn https_url(
+    client: &S3Client,
+    bucket: &str,
+    key: &str,
+    query: &[(&str, &str)],
+) -> Result<Url> {
+    let region = client.client.region();
+    let key = aws_uri_encode(key, false);
+    let mut url = if client.accelerate {
+        if bucket.contains('.') {
+            return Err(format_err!(
+                "cannot use accelerate=true with bucket {}, because its name \
+                 contains `.`",
+                bucket,
+            ));
+        }
+        format!("https://{}.s3-accelerate.amazonaws.com/{}", bucket, key)
+    } else if bucket.contains('.') {
+        format!("https://s3.{}.amazonaws.com/{}/{}", region, bucket, key)
     } else {
-        command.env_remove("AWS_SESSION_TOKEN");
+        format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, key)
+    }
+    .parse::<Url>()
+    .with_context(|_| format!("cannot build URL for s3://{}/{}", bucket, key))?;
+    if !query.is_empty() {
+        url.set_query(Some(&aws_query_string(query)));
     }
-    command.env("AWS_DEFAULT_REGION", creds.get_required("default_region")?);
-    command.arg("s3");
-    Ok(command)
+    Ok(url)
+}
+
+#[test]
+fn parse_s3_urls() {
+    let url = "s3://bucket/dir/a%20b.csv".parse::<Url>().unwrap();
+    assert_eq!(
+        parse_s3_url(&url).unwrap(),
+        ("bucket".to_owned(), "dir/a b.csv".to_owned()),
+    );
+    let url = "s3://bucket/".parse::<Url>().unwrap();
+    assert_eq!(
+        parse_s3_url(&url).unwrap(),
+        ("bucket".to_owned(), "".to_owned()),
+    );
+    let url = "gs://bucket/".parse::<Url>().unwrap();
+    assert!(parse_s3_url(&url).is_err());
+}
+
+#[test]
+fn https_urls_are_encoded() {
+    let creds = AwsCredentials {
+        access_key_id: "id".to_owned(),
+        secret_access_key: "secret".to_owned(),
+        session_token: None,
+    };
+    let client = S3Client {
+        client: AwsClient::new(creds, "us-east-2".to_owned(), "s3"),
+        accelerate: false,
+    };
+    assert_eq!(
+        https_url(&client, "bucket", "dir/a b+c.csv", &[])
+            .unwrap()
+            .as_str(),
+        "https://bucket.s3.us-east-2.amazonaws.com/dir/a%20b%2Bc.csv",
+    );
+    assert_eq!(
+        https_url(
+            &client,
+            "my.bucket",
+            "",
+            &[("list-type", "2"), ("prefix", "a/")]
+        )
+        .unwrap()
+        .as_str(),
+        "https://s3.us-east-2.amazonaws.com/my.bucket/?list-type=2&prefix=a%2F",
+    );
+}
+
+#[test]
+fn https_urls_use_transfer_acceleration() {
+    let creds = AwsCredentials {
+        access_key_id: "id".to_owned(),
+        secret_access_key: "secret".to_owned(),
+        session_token: None,
+    };
+    let client = S3Client {
+        client: AwsClient::new(creds, "eu-west-1".to_owned(), "s3"),
+        accelerate: true,
+    };
+    assert_eq!(
+        https_url(&client, "bucket", "dir/a.csv", &[])
+            .unwrap()
+            .as_str(),
+        "https://bucket.s3-accelerate.amazonaws.com/dir/a.csv",
+    );
+    assert!(https_url(&client, "my.bucket", "a.csv", &[]).is_err());
 }
@@ -0,0 +1,23 @@
+//! Reading from requester-pays buckets.
+
+use serde::Deserialize;
+
+/// Who pays for requests and data transfer? Specifying this acknowledges
+/// that we'll be charged for reading from a requester-pays bucket.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RequestPayer {
+    /// We pay.
+    Requester,
+}
+
+impl RequestPayer {
+    /// Extra headers to send with our requests.
+    pub(crate) fn headers(self) -> Vec<(&'static str, String)> {
+        match self {
+            RequestPayer::Requester => {
+                vec![("x-amz-request-payer", "requester".to_owned())]
+            }
+        }
+    }
+}
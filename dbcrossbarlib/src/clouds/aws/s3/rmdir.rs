@@ -1,12 +1,21 @@
 //! Deleting data from S3.
 
-use std::process::Stdio;
+use bytes::Bytes;
+use reqwest::Method;
 
-use super::aws_s3_command;
+use super::{
+    https_url, ls::Lister, parse_s3_url, upload_file::content_md5, S3Connection,
+};
+use crate::clouds::aws::{xml, AwsRequest};
 use crate::common::*;
 
-/// Recursively delete a `s3://` directory without deleting the bucket.
-pub(crate) async fn rmdir(ctx: &Context, url: &Url) -> Result<()> {
+/// Recursively delete a `s3://` directory without deleting the bucket. We
+/// connect to S3 using `conn`.
+pub(crate) async fn rmdir(
+    ctx: &Context,
+    url: &Url,
+    conn: &S3Connection,
+) -> Result<()> {
     // Delete all the files under `url`.
     debug!(ctx.log(), "deleting existing {}", url);
     if !url.path().ends_with('/') {
@@ -15,19 +24,61 @@ pub(crate) async fn rmdir(ctx: &Context, url: &Url) -> Result<()> {
             url,
         ));
     }
-    let status = aws_s3_command()
-        .await?
-        .args(&["rm", "--recursive", url.as_str()])
-        // Throw away stdout so it doesn't corrupt our output.
-        .stdout(Stdio::null())
-        .status()
-        .await
-        .context("error running `aws s3`")?;
-    if !status.success() {
-        warn!(
-            ctx.log(),
-            "can't delete contents of {}, possibly because it doesn't exist", url,
-        );
+    let (bucket, _) = parse_s3_url(url)?;
+    let lister = Lister::new(ctx, url, None, conn).await?;
+
+    // Each page of results contains at most 1,000 objects, which is also the
+    // most we can delete with a single `DeleteObjects` request.
+    let mut token = None;
+    loop {
+        let page = lister.page(ctx, token.as_deref()).await?;
+        if !page.objects.is_empty() {
+            let keys = page
+                .objects
+                .iter()
+                .map(|obj| &obj.key[..])
+                .collect::<Vec<_>>();
+            let body = Bytes::from(delete_objects_xml(&keys));
+            let req = AwsRequest::new(
+                Method::POST,
+                https_url(lister.client(), &bucket, "", &[("delete", "")])?,
+            )
+            .header("content-md5", content_md5(&body))
+            .body(body);
+            let resp = lister.client().send(ctx, req).await?;
+
+            // `DeleteObjects` reports errors for individual keys in the
+            // response body.
+            let body = resp.text().await.context("cannot read S3 response")?;
+            if let Some(message) = xml::error_message(&body) {
+                return Err(format_err!("could not delete from {}: {}", url, message));
+            }
+        }
+        match page.next_token {
+            Some(next_token) => token = Some(next_token),
+            None => break,
+        }
     }
     Ok(())
 }
+
+/// Build the body of a `DeleteObjects` request.
+fn delete_objects_xml(keys: &[&str]) -> String {
+    let mut out = "<Delete><Quiet>true</Quiet>".to_owned();
+    for key in keys {
+        out.push_str(&format!("<Object><Key>{}</Key></Object>", xml::escape(key)));
+    }
+    out.push_str("</Delete>");
+    out
+}
+
+#[test]
+fn build_delete_objects_xml() {
+    assert_eq!(
+        delete_objects_xml(&["dir/a.csv", "dir/b&c.csv"]),
+        "<Delete><Quiet>true</Quiet>\
+         <Object><Key>dir/a.csv</Key></Object>\
+         <Object><Key>dir/b&amp;c.csv</Key></Object>\
+         </Delete>",
+    );
+}
@@ -0,0 +1,119 @@
+//! Tags for the objects we write to S3.
+
+use std::{collections::HashSet, str::FromStr};
+
+use crate::clouds::aws::aws_uri_encode;
+use crate::common::*;
+
+/// The maximum number of tags S3 allows on a single object.
+const MAX_TAGS: usize = 10;
+
+/// The maximum length of a tag key, in characters.
+const MAX_KEY_LEN: usize = 128;
+
+/// The maximum length of a tag value, in characters.
+const MAX_VALUE_LEN: usize = 256;
+
+/// Tags to attach to every object we write, parsed from a string like
+/// `team=data,cost-center=1234`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ObjectTags {
+    /// Our tags, in the order they were specified.
+    tags: Vec<(String, String)>,
+}
+
+impl ObjectTags {
+    /// Extra headers to send when creating an object. S3 expects our tags to
+    /// be encoded like a query string.
+    pub(crate) fn headers(&self) -> Vec<(&'static str, String)> {
+        let tagging = self
+            .tags
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "{}={}",
+                    aws_uri_encode(key, true),
+                    aws_uri_encode(value, true)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        vec![("x-amz-tagging", tagging)]
+    }
+}
+
+impl FromStr for ObjectTags {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut tags = vec![];
+        let mut seen = HashSet::new();
+        for tag in s.split(',') {
+            let mut parts = tag.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| {
+                    format_err!("expected tag {:?} to look like KEY=VALUE", tag)
+                })?
+                .trim();
+            if key.is_empty() || key.chars().count() > MAX_KEY_LEN {
+                return Err(format_err!(
+                    "tag key {:?} must contain 1 to {} characters",
+                    key,
+                    MAX_KEY_LEN,
+                ));
+            }
+            if key.starts_with("aws:") {
+                return Err(format_err!("tag key {:?} may not start with aws:", key));
+            }
+            if value.chars().count() > MAX_VALUE_LEN {
+                return Err(format_err!(
+                    "tag value for {:?} may contain at most {} characters",
+                    key,
+                    MAX_VALUE_LEN,
+                ));
+            }
+            if !seen.insert(key.to_owned()) {
+                return Err(format_err!("tag {:?} specified more than once", key));
+            }
+            tags.push((key.to_owned(), value.to_owned()));
+        }
+        if tags.len() > MAX_TAGS {
+            return Err(format_err!(
+                "S3 allows at most {} tags per object, found {}",
+                MAX_TAGS,
+                tags.len(),
+            ));
+        }
+        Ok(ObjectTags { tags })
+    }
+}
+
+#[test]
+fn parse_object_tags() {
+    let tags = "team=data, cost-center=1234,note=a b&c"
+        .parse::<ObjectTags>()
+        .unwrap();
+    assert_eq!(
+        tags.headers(),
+        vec![(
+            "x-amz-tagging",
+            "team=data&cost-center=1234&note=a%20b%26c".to_owned()
+        )],
+    );
+    let tags = "empty=".parse::<ObjectTags>().unwrap();
+    assert_eq!(tags.headers()[0].1, "empty=");
+
+    let invalid = &[
+        "",
+        "team",
+        "=data",
+        "team=a,team=b",
+        "aws:team=data",
+        "a=1,b=2,c=3,d=4,e=5,f=6,g=7,h=8,i=9,j=10,k=11",
+    ];
+    for &s in invalid {
+        assert!(s.parse::<ObjectTags>().is_err(), "should not parse {:?}", s);
+    }
+}
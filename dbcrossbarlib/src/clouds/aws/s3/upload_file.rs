@@ -1,41 +1,356 @@
 //! Upload files to S3.
 
-use std::process::Stdio;
+use bytes::Bytes;
+use reqwest::Method;
 
-use super::aws_s3_command;
+use super::{
+    https_url, parse_s3_url, s3_client, ObjectTags, S3Client, S3Connection,
+    ServerSideEncryption, StorageClass,
+};
+use crate::clouds::aws::{xml, AwsRequest};
 use crate::common::*;
-use crate::tokio_glue::copy_stream_to_writer;
+use crate::object_metadata::ObjectMetadata;
 
-/// Upload `data` as a file at `url`.
+/// The size of each part of a multipart upload. S3 allows at most 10,000
+/// parts, so this limits us to uploading about 156 GiB per file.
+const PART_SIZE: usize = 16 * 1024 * 1024;
+
+/// The maximum number of parts allowed by S3.
+const MAX_PARTS: usize = 10_000;
+
+/// Options for `upload_file`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct UploadOptions {
+    /// How should we encrypt our object?
+    pub(crate) sse: ServerSideEncryption,
+    /// The storage class to use, or `None` for the bucket's default.
+    pub(crate) storage_class: Option<StorageClass>,
+    /// Tags to attach to our object.
+    pub(crate) tags: Option<ObjectTags>,
+    /// Content type, content encoding and custom metadata for our object.
+    pub(crate) metadata: ObjectMetadata,
+}
+
+impl UploadOptions {
+    /// The headers to send when creating an object.
+    fn headers(&self) -> Vec<(String, String)> {
+        let mut headers = self
+            .sse
+            .headers()
+            .into_iter()
+            .map(|(name, value)| (name.to_owned(), value))
+            .collect::<Vec<_>>();
+        if let Some(storage_class) = self.storage_class {
+            headers.push((
+                "x-amz-storage-class".to_owned(),
+                storage_class.as_str().to_owned(),
+            ));
+        }
+        if let Some(tags) = &self.tags {
+            headers.extend(
+                tags.headers()
+                    .into_iter()
+                    .map(|(name, value)| (name.to_owned(), value)),
+            );
+        }
+        if let Some(content_type) = self.metadata.content_type() {
+            headers.push(("content-type".to_owned(), content_type.to_owned()));
+        }
+        if let Some(content_encoding) = self.metadata.content_encoding() {
+            headers.push(("content-encoding".to_owned(), content_encoding.to_owned()));
+        }
+        for (key, value) in self.metadata.custom() {
+            // S3 stores metadata keys in lower case anyway.
+            let name = format!("x-amz-meta-{}", key.to_ascii_lowercase());
+            headers.push((name, value.to_owned()));
+        }
+        headers
+    }
+}
+
+#[test]
+fn upload_headers_include_metadata() {
+    use crate::object_metadata::CustomMetadata;
+
+    let metadata = ObjectMetadata::new(
+        Some("text/csv".to_owned()),
+        Some("gzip".to_owned()),
+        Some("Source=etl".parse::<CustomMetadata>().unwrap()),
+    )
+    .unwrap();
+    let opt = UploadOptions {
+        storage_class: Some(StorageClass::IntelligentTiering),
+        metadata,
+        ..UploadOptions::default()
+    };
+    assert_eq!(
+        opt.headers(),
+        vec![
+            (
+                "x-amz-storage-class".to_owned(),
+                "INTELLIGENT_TIERING".to_owned(),
+            ),
+            ("content-type".to_owned(), "text/csv".to_owned()),
+            ("content-encoding".to_owned(), "gzip".to_owned()),
+            ("x-amz-meta-source".to_owned(), "etl".to_owned()),
+        ],
+    );
+}
+
+/// Upload `data` as a file at `url`, using the encryption, storage class,
+/// tags and metadata specified by `opt`. We connect to S3 using `conn`.
+///
+/// Small files are uploaded with a single `PUT`. Larger files are streamed
+/// using a multipart upload, so we never need to hold more than one part in
+/// memory.
 pub(crate) async fn upload_file<'a>(
     ctx: &'a Context,
     data: BoxStream<BytesMut>,
     file_url: &'a Url,
+    opt: &'a UploadOptions,
+    conn: &'a S3Connection,
 ) -> Result<()> {
-    // Run `aws cp - $URL` as a background process.
-    debug!(ctx.log(), "uploading stream to `aws s3`");
-    let mut child = aws_s3_command()
-        .await?
-        .args(&["cp", "-", file_url.as_str()])
-        .stdin(Stdio::piped())
-        // Throw away stdout so it doesn't corrupt our output.
-        .stdout(Stdio::null())
-        .spawn()
-        .context("error running `aws s3`")?;
-    let child_stdin = child.stdin.take().expect("child should have stdin");
-
-    // Copy data to our child process.
-    copy_stream_to_writer(ctx.clone(), data, child_stdin)
-        .await
-        .context("error copying data to `aws s3`")?;
+    debug!(ctx.log(), "streaming to {}", file_url);
+    let (bucket, key) = parse_s3_url(file_url)?;
+    let client = s3_client(ctx, conn, &bucket).await?;
+    let headers = opt.headers();
+    let upload = Upload {
+        client: &client,
+        bucket: &bucket,
+        key: &key,
+    };
+
+    // If our data fits in a single part, just `PUT` it.
+    let mut parts = PartReader::new(data, PART_SIZE);
+    let first = parts.next_part().await?.unwrap_or_else(Bytes::new);
+    if first.len() < PART_SIZE {
+        upload
+            .put(ctx, headers, first)
+            .await
+            .with_context(|_| format!("error uploading {}", file_url))?;
+        return Ok(());
+    }
 
-    // Wait for `aws s3` to finish.
-    let status = child
+    // Otherwise, start a multipart upload, and make sure we abort it if
+    // anything goes wrong, so that the parts don't keep costing money.
+    let upload_id = upload
+        .create_multipart(ctx, headers)
         .await
-        .with_context(|_| format!("error finishing upload to {}", file_url))?;
-    if status.success() {
+        .with_context(|_| format!("error starting upload to {}", file_url))?;
+    let result = async {
+        let mut etags = vec![upload.part(ctx, &upload_id, 1, first).await?];
+        while let Some(part) = parts.next_part().await? {
+            if etags.len() >= MAX_PARTS {
+                return Err(format_err!("file is too large to upload to S3"));
+            }
+            let part_number = etags.len() + 1;
+            etags.push(upload.part(ctx, &upload_id, part_number, part).await?);
+        }
+        upload.complete_multipart(ctx, &upload_id, &etags).await
+    }
+    .await;
+    if let Err(err) = &result {
+        debug!(ctx.log(), "aborting upload to {}: {}", file_url, err);
+        if let Err(abort_err) = upload.abort_multipart(ctx, &upload_id).await {
+            warn!(
+                ctx.log(),
+                "could not abort upload to {}: {}", file_url, abort_err,
+            );
+        }
+    }
+    Ok(result.with_context(|_| format!("error uploading {}", file_url))?)
+}
+
+/// An object that we're uploading.
+struct Upload<'a> {
+    /// Our S3 client.
+    client: &'a S3Client,
+    /// The bucket we're uploading to.
+    bucket: &'a str,
+    /// The key of the object we're creating.
+    key: &'a str,
+}
+
+impl<'a> Upload<'a> {
+    /// Build a request for this object.
+    fn request(&self, method: Method, query: &[(&str, &str)]) -> Result<AwsRequest> {
+        let url = https_url(self.client, self.bucket, self.key, query)?;
+        Ok(AwsRequest::new(method, url))
+    }
+
+    /// Upload `body` using a single `PutObject` request.
+    async fn put(
+        &self,
+        ctx: &Context,
+        headers: Vec<(String, String)>,
+        body: Bytes,
+    ) -> Result<()> {
+        let req = self
+            .request(Method::PUT, &[])?
+            .headers(headers)
+            .header("content-md5", content_md5(&body))
+            .body(body);
+        self.client.send(ctx, req).await?;
+        Ok(())
+    }
+
+    /// Start a multipart upload, and return the upload ID.
+    async fn create_multipart(
+        &self,
+        ctx: &Context,
+        headers: Vec<(String, String)>,
+    ) -> Result<String> {
+        let req = self
+            .request(Method::POST, &[("uploads", "")])?
+            .headers(headers);
+        let resp = self.client.send(ctx, req).await?;
+        let body = resp.text().await.context("cannot read S3 response")?;
+        xml::required_element_text(&body, "UploadId")
+    }
+
+    /// Upload a single part, returning its ETag.
+    async fn part(
+        &self,
+        ctx: &Context,
+        upload_id: &str,
+        part_number: usize,
+        body: Bytes,
+    ) -> Result<String> {
+        trace!(ctx.log(), "uploading part {} of {}", part_number, self.key);
+        let part_number = part_number.to_string();
+        let req = self
+            .request(
+                Method::PUT,
+                &[("partNumber", &part_number[..]), ("uploadId", upload_id)],
+            )?
+            .header("content-md5", content_md5(&body))
+            .body(body);
+        let resp = self.client.send(ctx, req).await?;
+        let etag = resp
+            .headers()
+            .get("etag")
+            .ok_or_else(|| format_err!("S3 did not return an ETag for part"))?
+            .to_str()
+            .context("invalid ETag for part")?;
+        Ok(etag.to_owned())
+    }
+
+    /// Finish a multipart upload.
+    async fn complete_multipart(
+        &self,
+        ctx: &Context,
+        upload_id: &str,
+        etags: &[String],
+    ) -> Result<()> {
+        let req = self
+            .request(Method::POST, &[("uploadId", upload_id)])?
+            .body(Bytes::from(complete_multipart_xml(etags)));
+        let resp = self.client.send(ctx, req).await?;
+        // S3 may report an error after sending a successful status code.
+        let body = resp.text().await.context("cannot read S3 response")?;
+        match xml::error_message(&body) {
+            Some(message) => {
+                Err(format_err!("could not complete upload: {}", message))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Abort a multipart upload, deleting any parts we've uploaded.
+    async fn abort_multipart(&self, ctx: &Context, upload_id: &str) -> Result<()> {
+        let req = self.request(Method::DELETE, &[("uploadId", upload_id)])?;
+        self.client.send(ctx, req).await?;
         Ok(())
-    } else {
-        Err(format_err!("`aws s3` returned error: {}", status))
     }
 }
+
+/// Build the body of a `CompleteMultipartUpload` request.
+fn complete_multipart_xml(etags: &[String]) -> String {
+    let mut out = "<CompleteMultipartUpload>".to_owned();
+    for (i, etag) in etags.iter().enumerate() {
+        out.push_str(&format!(
+            "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+            i + 1,
+            xml::escape(etag),
+        ));
+    }
+    out.push_str("</CompleteMultipartUpload>");
+    out
+}
+
+#[test]
+fn build_complete_multipart_xml() {
+    assert_eq!(
+        complete_multipart_xml(&["\"a\"".to_owned(), "\"b\"".to_owned()]),
+        "<CompleteMultipartUpload>\
+         <Part><PartNumber>1</PartNumber><ETag>&quot;a&quot;</ETag></Part>\
+         <Part><PartNumber>2</PartNumber><ETag>&quot;b&quot;</ETag></Part>\
+         </CompleteMultipartUpload>",
+    );
+}
+
+/// Compute the `Content-MD5` header for `body`, so that S3 can detect any
+/// corruption in transit.
+pub(super) fn content_md5(body: &[u8]) -> String {
+    base64::encode(&md5::compute(body).0)
+}
+
+/// Splits a stream of data into parts of a fixed size.
+struct PartReader {
+    /// Our input data.
+    data: BoxStream<BytesMut>,
+    /// Data we've read but not yet returned.
+    buffer: BytesMut,
+    /// The size of each part, except the last.
+    part_size: usize,
+    /// Have we reached the end of `data`?
+    done: bool,
+}
+
+impl PartReader {
+    /// Create a new `PartReader`.
+    fn new(data: BoxStream<BytesMut>, part_size: usize) -> PartReader {
+        PartReader {
+            data,
+            buffer: BytesMut::new(),
+            part_size,
+            done: false,
+        }
+    }
+
+    /// Return the next part, or `None` if there's no more data. Every part
+    /// except the last will contain exactly `part_size` bytes.
+    async fn next_part(&mut self) -> Result<Option<Bytes>> {
+        while !self.done && self.buffer.len() < self.part_size {
+            match self.data.next().await {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk?),
+                None => self.done = true,
+            }
+        }
+        if self.buffer.is_empty() {
+            Ok(None)
+        } else {
+            let len = self.buffer.len().min(self.part_size);
+            Ok(Some(self.buffer.split_to(len).freeze()))
+        }
+    }
+}
+
+#[test]
+fn part_reader_returns_fixed_size_parts() {
+    use futures::executor::block_on;
+
+    let chunks = vec!["abc", "defgh", "", "ij"]
+        .into_iter()
+        .map(|s| Ok(BytesMut::from(s)))
+        .collect::<Vec<_>>();
+    let mut parts = PartReader::new(futures::stream::iter(chunks).boxed(), 4);
+    let mut found = vec![];
+    while let Some(part) = block_on(parts.next_part()).unwrap() {
+        found.push(String::from_utf8(part.to_vec()).unwrap());
+    }
+    assert_eq!(found, vec!["abcd", "efgh", "ij"]);
+
+    let mut parts = PartReader::new(futures::stream::iter(vec![]).boxed(), 4);
+    assert!(block_on(parts.next_part()).unwrap().is_none());
+}
@@ -1,61 +1,205 @@
-//! S3 URL signing.
+//! Signing AWS requests.
 
 use chrono::{DateTime, Utc};
 use hmac::{Hmac, Mac, NewMac};
-use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 
 use super::AwsCredentials;
 use crate::common::*;
 
-/// Sign an `s3://` URL for use with AWS. Returns the signed URL and an optional
-/// value for the `x-amz-security-token` header.
-pub(crate) fn sign_s3_url<'creds>(
-    credentials: &'creds AwsCredentials,
+/// An AWS service in a specific region. This is used to scope our signatures.
+pub(crate) struct AwsService<'a> {
+    /// The AWS region, like `us-east-1`.
+    pub(crate) region: &'a str,
+    /// The name of the service, like `s3` or `sts`.
+    pub(crate) name: &'a str,
+}
+
+/// Sign an HTTP request using [AWS Signature Version 4][sigv4].
+///
+/// `headers` must use lower-case names. We add `host`, `x-amz-date`,
+/// `x-amz-security-token` (if needed) and `authorization`. `url` must already be
+/// encoded using `aws_uri_encode`, and `payload_sha256` is the hex-encoded
+/// SHA-256 hash of the request body.
+///
+/// [sigv4]: https://docs.aws.amazon.com/general/latest/gr/sigv4_signing.html
+pub(crate) fn sign_request_v4(
+    credentials: &AwsCredentials,
+    service: &AwsService<'_>,
+    now: DateTime<Utc>,
     method: &str,
-    expires: DateTime<Utc>,
     url: &Url,
-) -> Result<(Url, &'creds Option<String>)> {
-    if url.scheme() != "s3" {
-        return Err(format_err!("can't sign non-S3 URL {}", url));
-    }
+    headers: &mut BTreeMap<String, String>,
+    payload_sha256: &str,
+) -> Result<()> {
     let host = url
-        .host()
+        .host_str()
         .ok_or_else(|| format_err!("no host in URL {}", url))?;
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    };
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    headers.insert("host".to_owned(), host);
+    headers.insert("x-amz-date".to_owned(), amz_date.clone());
+    if let Some(session_token) = &credentials.session_token {
+        headers.insert("x-amz-security-token".to_owned(), session_token.to_owned());
+    }
+
+    // Build our canonical request.
+    let canonical_headers = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect::<String>();
+    let signed_headers = headers.keys().map(|k| &k[..]).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        url.path(),
+        canonical_query(url.query().unwrap_or("")),
+        canonical_headers,
+        signed_headers,
+        payload_sha256,
+    );
 
-    let mut mac = Hmac::<Sha1>::new_varkey(credentials.secret_access_key.as_bytes())
+    // Sign it.
+    let scope = format!("{}/{}/{}/aws4_request", date, service.region, service.name);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes())),
+    );
+    let mut key = hmac_sha256(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    )?;
+    for part in &[service.region, service.name, "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes())?;
+    }
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes())?);
+    headers.insert(
+        "authorization".to_owned(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature,
+        ),
+    );
+    Ok(())
+}
+
+/// Compute the HMAC-SHA256 of `data` using `key`.
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key)
         .map_err(|err| format_err!("cannot compute signature: {}", err))?;
-    let full_path = format!("/{}{}", host, url.path());
-    let payload = format!("{}\n\n\n{}\n{}", method, expires.timestamp(), full_path,);
-    mac.update(payload.as_bytes());
-    let signature = base64::encode(&mac.finalize().into_bytes());
-    let mut signed: Url = format!("https://s3.amazonaws.com{}", full_path).parse()?;
-    signed
-        .query_pairs_mut()
-        .append_pair("AWSAccessKeyId", &credentials.access_key_id)
-        .append_pair("Expires", &format!("{}", expires.timestamp()))
-        .append_pair("Signature", &signature);
-    Ok((signed, &credentials.session_token))
+    mac.update(data);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+/// Sort an encoded query string into canonical order, making sure every key has
+/// a value.
+fn canonical_query(query: &str) -> String {
+    let mut pairs = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            if pair.contains('=') {
+                pair.to_owned()
+            } else {
+                format!("{}=", pair)
+            }
+        })
+        .collect::<Vec<_>>();
+    pairs.sort();
+    pairs.join("&")
+}
+
+/// Percent-encode `s` the way AWS expects. If `encode_slash` is false, we leave
+/// `/` alone, which is what we want for S3 object keys.
+pub(crate) fn aws_uri_encode(s: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(char::from(b))
+            }
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Build a query string from `pairs`, encoding it the way AWS expects. Keys with
+/// empty values are written without an `=`, like `?uploads`.
+pub(crate) fn aws_query_string(pairs: &[(&str, &str)]) -> String {
+    pairs
+        .iter()
+        .map(|(key, value)| {
+            if value.is_empty() {
+                aws_uri_encode(key, true)
+            } else {
+                format!(
+                    "{}={}",
+                    aws_uri_encode(key, true),
+                    aws_uri_encode(value, true),
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&")
 }
 
 #[test]
-fn signatures_are_valid() {
-    use chrono::NaiveDateTime;
+fn v4_signatures_are_valid() {
+    use chrono::TimeZone;
 
-    // Example is taken from
-    // https://s3.amazonaws.com/doc/s3-developer-guide/RESTAuthentication.html.
+    // Example is `get-vanilla` from the AWS Signature Version 4 test suite.
     let creds = AwsCredentials {
-        access_key_id: "44CF9590006BF252F707".to_owned(),
-        secret_access_key: "OtxrzxIsfpFjA7SwPzILwy8Bw21TLhquhboDYROV".to_owned(),
+        access_key_id: "AKIDEXAMPLE".to_owned(),
+        secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_owned(),
         session_token: None,
     };
-    let (signed_url, _x_amz_security_token) = sign_s3_url(
+    let service = AwsService {
+        region: "us-east-1",
+        name: "service",
+    };
+    let mut headers = BTreeMap::new();
+    sign_request_v4(
         &creds,
+        &service,
+        Utc.ymd(2015, 8, 30).and_hms(12, 36, 0),
         "GET",
-        DateTime::from_utc(NaiveDateTime::from_timestamp(1_141_889_120, 0), Utc),
-        &"s3://quotes/nelson".parse().unwrap(),
+        &"https://example.amazonaws.com/".parse().unwrap(),
+        &mut headers,
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
     )
     .unwrap();
-    let expected: Url =
-        "https://s3.amazonaws.com/quotes/nelson?AWSAccessKeyId=44CF9590006BF252F707&Expires=1141889120&Signature=vjbyPxybdZaNmGa%2ByT272YEAiv4%3D".parse().unwrap();
-    assert_eq!(signed_url, expected);
+    assert_eq!(headers["x-amz-date"], "20150830T123600Z");
+    assert_eq!(
+        headers["authorization"],
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31",
+    );
+}
+
+#[test]
+fn canonical_queries_are_sorted() {
+    assert_eq!(canonical_query(""), "");
+    assert_eq!(canonical_query("uploads"), "uploads=");
+    assert_eq!(
+        canonical_query("prefix=a%2Fb&list-type=2"),
+        "list-type=2&prefix=a%2Fb",
+    );
+}
+
+#[test]
+fn aws_uri_encode_escapes_reserved_characters() {
+    assert_eq!(aws_uri_encode("a b/c+d~é", false), "a%20b/c%2Bd~%C3%A9");
+    assert_eq!(aws_uri_encode("a/b", true), "a%2Fb");
+    assert_eq!(
+        aws_query_string(&[("uploadId", "x/y"), ("uploads", "")]),
+        "uploadId=x%2Fy&uploads",
+    );
 }
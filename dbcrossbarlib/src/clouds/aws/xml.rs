@@ -0,0 +1,144 @@
+//! Reading and writing the simple XML documents used by AWS REST APIs.
+//!
+//! We only need a few elements from each response, so we search for them
+//! directly instead of using a full XML parser. AWS responses don't use
+//! namespace prefixes, attributes on the elements we care about, or `CDATA`.
+
+use crate::common::*;
+
+/// Return the contents of each `<name>...</name>` element in `xml`, in order.
+/// Nested elements with the same name are not supported.
+pub(crate) fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let mut found = vec![];
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after_open = &rest[start + open.len()..];
+        match after_open.find(&close) {
+            Some(end) => {
+                found.push(&after_open[..end]);
+                rest = &after_open[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    found
+}
+
+/// Return the contents of the first `<name>...</name>` element in `xml`.
+pub(crate) fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    elements(xml, name).into_iter().next()
+}
+
+/// Return the text of the first `<name>...</name>` element in `xml`, with any
+/// entities decoded.
+pub(crate) fn element_text(xml: &str, name: &str) -> Result<Option<String>> {
+    element(xml, name).map(unescape).transpose()
+}
+
+/// Like `element_text`, but fail if `name` is missing.
+pub(crate) fn required_element_text(xml: &str, name: &str) -> Result<String> {
+    element_text(xml, name)?
+        .ok_or_else(|| format_err!("could not find <{}> in AWS response", name))
+}
+
+/// If `xml` is an AWS error response, return a description of the error.
+pub(crate) fn error_message(xml: &str) -> Option<String> {
+    let error = element(xml, "Error")?;
+    let code = element_text(error, "Code").ok()??;
+    match element_text(error, "Message").ok()? {
+        Some(message) => Some(format!("{}: {}", code, message)),
+        None => Some(code),
+    }
+}
+
+/// Decode XML entities in `text`.
+pub(crate) fn unescape(text: &str) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let semi = rest[amp..]
+            .find(';')
+            .ok_or_else(|| format_err!("unterminated XML entity in {:?}", text))?;
+        let entity = &rest[amp + 1..amp + semi];
+        let c = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16)
+                .ok()
+                .and_then(std::char::from_u32)
+                .ok_or_else(|| format_err!("invalid XML entity &{};", entity))?,
+            _ if entity.starts_with('#') => entity[1..]
+                .parse::<u32>()
+                .ok()
+                .and_then(std::char::from_u32)
+                .ok_or_else(|| format_err!("invalid XML entity &{};", entity))?,
+            _ => return Err(format_err!("unknown XML entity &{};", entity)),
+        };
+        out.push(c);
+        rest = &rest[amp + semi + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Escape `text` for use in an XML element.
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Carriage returns would be normalized to newlines by the parser.
+            '\r' => out.push_str("&#13;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[test]
+fn find_elements() {
+    let xml = "<R><C><Key>a</Key></C><C><Key>b &amp; c</Key></C><Next>t</Next></R>";
+    assert_eq!(
+        elements(xml, "C"),
+        vec!["<Key>a</Key>", "<Key>b &amp; c</Key>"]
+    );
+    assert_eq!(element(xml, "Next"), Some("t"));
+    assert_eq!(element(xml, "Missing"), None);
+    assert_eq!(
+        element_text(elements(xml, "C")[1], "Key").unwrap(),
+        Some("b & c".to_owned()),
+    );
+    assert!(required_element_text(xml, "Missing").is_err());
+}
+
+#[test]
+fn parse_error_messages() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Error><Code>NoSuchBucket</Code><Message>The specified bucket does not exist</Message></Error>"#;
+    assert_eq!(
+        error_message(xml).unwrap(),
+        "NoSuchBucket: The specified bucket does not exist",
+    );
+    assert_eq!(error_message("<Other/>"), None);
+}
+
+#[test]
+fn escape_and_unescape_round_trip() {
+    let examples = &["", "plain", "a&b<c>d\"e'f", "line\r\nbreak", "ünïcødé"];
+    for &text in examples {
+        assert_eq!(unescape(&escape(text)).unwrap(), text);
+    }
+    assert_eq!(unescape("&#65;&#x42;").unwrap(), "AB");
+    assert!(unescape("&bogus;").is_err());
+    assert!(unescape("&amp").is_err());
+}
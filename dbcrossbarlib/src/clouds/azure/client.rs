@@ -0,0 +1,120 @@
+//! An Azure Data Lake Storage Gen2 REST client.
+
+use bytes::Bytes;
+use reqwest::{
+    self,
+    header::{HeaderValue, CONTENT_LENGTH},
+    Method,
+};
+use serde::Deserialize;
+
+use crate::common::*;
+use crate::credentials::CredentialsManager;
+
+/// The version of the storage REST API that we use.
+const API_VERSION: &str = "2019-12-12";
+
+/// An Azure Storage REST client using OAuth2 bearer tokens.
+pub(crate) struct Client {
+    /// Our access token.
+    token: String,
+
+    /// Our HTTP client.
+    client: reqwest::Client,
+}
+
+impl Client {
+    /// Create a new Azure Storage client.
+    pub(crate) async fn new(_ctx: &Context) -> Result<Client> {
+        let creds = CredentialsManager::singleton().get("azure_storage").await?;
+        let token = creds.get_required("access_token")?.trim().to_owned();
+        let client = reqwest::Client::new();
+        Ok(Client { token, client })
+    }
+
+    /// Make an HTTP request, and return the response, even if it indicates
+    /// an error.
+    pub(crate) async fn request(
+        &self,
+        ctx: &Context,
+        method: Method,
+        url: &Url,
+        body: Option<Bytes>,
+    ) -> Result<reqwest::Response> {
+        trace!(ctx.log(), "{} {}", method, url);
+        let mut req = self
+            .client
+            .request(method.clone(), url.as_str())
+            .bearer_auth(&self.token)
+            .header("x-ms-version", API_VERSION);
+        if let Some(body) = body {
+            req = req
+                .header(CONTENT_LENGTH, HeaderValue::from(body.len()))
+                .body(body);
+        }
+        Ok(req
+            .send()
+            .await
+            .with_context(|_| format!("could not {} {}", method, url))?)
+    }
+
+    /// Make an HTTP request, and return an error unless it succeeds.
+    pub(crate) async fn request_ok(
+        &self,
+        ctx: &Context,
+        method: Method,
+        url: &Url,
+        body: Option<Bytes>,
+    ) -> Result<reqwest::Response> {
+        let resp = self.request(ctx, method.clone(), url, body).await?;
+        if resp.status().is_success() {
+            Ok(resp)
+        } else {
+            Err(error_from_response(ctx, &method, url, resp).await)
+        }
+    }
+}
+
+/// An Azure Storage error response.
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    /// The actual error.
+    error: AzureError,
+}
+
+/// Information about an Azure Storage error.
+#[derive(Debug, Deserialize)]
+struct AzureError {
+    code: String,
+    message: String,
+}
+
+/// Convert an unsuccessful HTTP response into an error.
+pub(crate) async fn error_from_response(
+    ctx: &Context,
+    method: &Method,
+    url: &Url,
+    resp: reqwest::Response,
+) -> Error {
+    let status = resp.status();
+    let body = resp.text().await.unwrap_or_default();
+    trace!(
+        ctx.log(),
+        "{} {} failed: {} {:?}",
+        method,
+        url,
+        status,
+        body
+    );
+    match serde_json::from_str::<ErrorResponse>(&body) {
+        Ok(resp) => format_err!(
+            "{} {} failed: {} {}",
+            method,
+            url,
+            resp.error.code,
+            // Azure appends request IDs and timestamps on separate lines.
+            resp.error.message.lines().next().unwrap_or(""),
+        ),
+        Err(_) => format_err!("{} {} failed: {}", method, url, status),
+    }
+}
@@ -0,0 +1,6 @@
+//! Interfaces to Microsoft Azure.
+
+mod client;
+pub(crate) mod storage;
+
+pub(crate) use client::*;
@@ -0,0 +1,19 @@
+//! Downloading files from Azure Data Lake Storage.
+
+use reqwest::Method;
+
+use super::{super::Client, parse_abfss_url};
+use crate::common::*;
+use crate::http_response::http_response_stream;
+
+/// Download the file at `file_url` as a stream.
+pub(crate) async fn download_file(
+    ctx: &Context,
+    file_url: &Url,
+) -> Result<BoxStream<BytesMut>> {
+    debug!(ctx.log(), "streaming from {}", file_url);
+    let req_url = parse_abfss_url(file_url)?.rest_url()?;
+    let client = Client::new(ctx).await?;
+    let resp = client.request_ok(ctx, Method::GET, &req_url, None).await?;
+    Ok(http_response_stream(resp))
+}
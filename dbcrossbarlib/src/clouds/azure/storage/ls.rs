@@ -0,0 +1,177 @@
+//! Listing files in Azure Data Lake Storage.
+
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use super::{super::Client, parse_abfss_url, AbfssObject, AbfssPath};
+use crate::common::*;
+
+/// Response body.
+#[derive(Debug, Deserialize)]
+struct ListResponse {
+    #[serde(default)]
+    paths: Vec<PathItem>,
+}
+
+/// An entry in a directory listing.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PathItem {
+    /// The full path of this entry, relative to the file system.
+    name: String,
+    /// Is this a directory? Azure returns this as the string `"true"`, and
+    /// omits it for files.
+    #[serde(default)]
+    is_directory: Option<Value>,
+    /// When this entry was last modified, in RFC 1123 format.
+    #[serde(default)]
+    last_modified: Option<String>,
+    /// The size of this entry, in bytes. Azure returns this as a string.
+    #[serde(default)]
+    content_length: Option<Value>,
+}
+
+impl PathItem {
+    /// Is this item a directory?
+    fn is_directory(&self) -> bool {
+        match &self.is_directory {
+            Some(Value::Bool(b)) => *b,
+            Some(Value::String(s)) => s == "true",
+            _ => false,
+        }
+    }
+
+    /// How big is this item?
+    fn size(&self) -> Result<u64> {
+        match &self.content_length {
+            None => Ok(0),
+            Some(Value::Number(n)) => n
+                .as_u64()
+                .ok_or_else(|| format_err!("invalid contentLength for {}", self.name)),
+            Some(Value::String(s)) => Ok(s.parse::<u64>().with_context(|_| {
+                format!("invalid contentLength for {}", self.name)
+            })?),
+            Some(_) => Err(format_err!("invalid contentLength for {}", self.name)),
+        }
+    }
+
+    /// When was this item last modified?
+    fn modified(&self) -> Option<DateTime<Utc>> {
+        let last_modified = self.last_modified.as_ref()?;
+        DateTime::parse_from_rfc2822(last_modified)
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc))
+    }
+}
+
+/// List all the CSV files under the specified `abfss://` directory,
+/// recursively.
+///
+/// Because accounts with a hierarchical namespace have real directories, we
+/// ask the server for the contents of our directory, instead of listing
+/// everything with a matching prefix the way we do for S3 and Google Cloud
+/// Storage.
+///
+/// See the [documentation][list].
+///
+/// [list]: https://docs.microsoft.com/en-us/rest/api/storageservices/datalakestoragegen2/path/list
+pub(crate) async fn ls(
+    ctx: &Context,
+    url: &Url,
+) -> Result<impl Stream<Item = Result<AbfssObject>> + Send + Unpin + 'static> {
+    debug!(ctx.log(), "listing {}", url);
+    let path = parse_abfss_url(url)?;
+
+    // Set up a background worker which forwards list output to `sender`.
+    let (mut sender, receiver) = mpsc::channel::<Result<AbfssObject>>(1);
+    let worker_ctx = ctx.child(o!("worker" => "abfss ls"));
+    let worker: BoxFuture<()> = async move {
+        let result = ls_worker(&worker_ctx, &path, &mut sender).await;
+        if let Err(err) = result {
+            error!(worker_ctx.log(), "error in abfss worker: {}", err);
+            sender.send(Err(err)).await.map_err(|_| {
+                format_err!("error sending data to stream (perhaps it was closed)")
+            })?;
+        }
+        Ok(())
+    }
+    .boxed();
+    ctx.spawn_worker(worker);
+    Ok(receiver)
+}
+
+/// Page through a directory listing, sending the files we find to `sender`.
+async fn ls_worker(
+    ctx: &Context,
+    path: &AbfssPath,
+    sender: &mut mpsc::Sender<Result<AbfssObject>>,
+) -> Result<()> {
+    let client = Client::new(ctx).await?;
+    let directory = path.decoded_path()?;
+    let directory = directory.trim_end_matches('/');
+
+    let mut continuation: Option<String> = None;
+    loop {
+        // Set up our request.
+        let mut req_url = path.filesystem_url()?;
+        {
+            let mut query = req_url.query_pairs_mut();
+            query
+                .append_pair("resource", "filesystem")
+                .append_pair("recursive", "true");
+            if !directory.is_empty() {
+                query.append_pair("directory", directory);
+            }
+            if let Some(continuation) = &continuation {
+                query.append_pair("continuation", continuation);
+            }
+        }
+
+        // Make our request.
+        let resp = client.request_ok(ctx, Method::GET, &req_url, None).await?;
+        let next_continuation = resp
+            .headers()
+            .get("x-ms-continuation")
+            .map(|v| v.to_str().map(|v| v.to_owned()))
+            .transpose()
+            .context("invalid x-ms-continuation header")?
+            .filter(|v| !v.is_empty());
+        let list = resp
+            .json::<ListResponse>()
+            .await
+            .with_context(|_| format!("error listing {}", req_url))?;
+
+        // Forward the files we found.
+        for item in list.paths {
+            if item.is_directory() || !item.name.to_ascii_lowercase().ends_with(".csv")
+            {
+                trace!(ctx.log(), "skipping {:?}", item.name);
+                continue;
+            }
+            let obj = AbfssObject {
+                url: path.abfss_url_for(&item.name)?,
+                modified: item.modified(),
+                size: item.size()?,
+            };
+            sender.send(Ok(obj)).await.map_err(|_| {
+                format_err!("error sending data to stream (perhaps it was closed)")
+            })?;
+        }
+
+        // Exit if this is the last page of results.
+        if next_continuation.is_none() {
+            break;
+        }
+        if next_continuation == continuation {
+            return Err(format_err!(
+                "tried to list page {:?} of files twice",
+                continuation,
+            ));
+        }
+        continuation = next_continuation;
+    }
+    Ok(())
+}
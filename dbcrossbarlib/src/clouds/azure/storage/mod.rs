@@ -0,0 +1,145 @@
+//! Interfaces to Azure Data Lake Storage Gen2.
+//!
+//! We talk to the `dfs.core.windows.net` endpoint, which understands real
+//! directories on accounts with a hierarchical namespace.
+
+use chrono::{DateTime, Utc};
+use percent_encoding::percent_decode_str;
+
+use crate::common::*;
+
+mod download_file;
+mod ls;
+mod rmdir;
+mod upload_file;
+
+pub(crate) use download_file::download_file;
+pub(crate) use ls::ls;
+pub(crate) use rmdir::rmdir;
+pub(crate) use upload_file::upload_file;
+
+/// Chunk size to use when uploading files.
+///
+/// Each chunk is sent as a separate `append` request, so this should be large
+/// enough to keep the number of requests reasonable.
+#[cfg(not(debug_assertions))]
+pub(crate) const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+// Use a much smaller chunk size when testing to force our chunking code to be
+// used.
+#[cfg(debug_assertions)]
+pub(crate) const CHUNK_SIZE: usize = 128;
+
+/// A file in Azure Data Lake Storage.
+#[derive(Debug)]
+pub(crate) struct AbfssObject {
+    /// The `abfss://` URL of this file.
+    pub(crate) url: Url,
+    /// When this file was last modified.
+    pub(crate) modified: Option<DateTime<Utc>>,
+    /// The size of this file, in bytes.
+    pub(crate) size: u64,
+}
+
+/// The parts of an `abfss://filesystem@account.dfs.core.windows.net/path` URL.
+#[derive(Debug, PartialEq)]
+pub(crate) struct AbfssPath {
+    /// The host name of the storage account.
+    pub(crate) host: String,
+    /// The file system (or container) name.
+    pub(crate) filesystem: String,
+    /// The path within the file system, without a leading `/`, and still
+    /// percent-encoded.
+    pub(crate) path: String,
+}
+
+impl AbfssPath {
+    /// The decoded path within the file system.
+    pub(crate) fn decoded_path(&self) -> Result<String> {
+        Ok(percent_decode_str(&self.path)
+            .decode_utf8()
+            .with_context(|_| format!("cannot decode path {:?}", self.path))?
+            .into_owned())
+    }
+
+    /// The URL of the file system itself on the `https://` REST endpoint.
+    pub(crate) fn filesystem_url(&self) -> Result<Url> {
+        Ok(format!("https://{}/{}", self.host, self.filesystem).parse::<Url>()?)
+    }
+
+    /// The URL of our path on the `https://` REST endpoint. Any trailing `/`
+    /// is removed, because directories are real objects with their own names.
+    pub(crate) fn rest_url(&self) -> Result<Url> {
+        Ok(format!(
+            "https://{}/{}/{}",
+            self.host,
+            self.filesystem,
+            self.path.trim_end_matches('/'),
+        )
+        .parse::<Url>()?)
+    }
+
+    /// Build an `abfss://` URL for `name`, which is relative to the root of our
+    /// file system.
+    pub(crate) fn abfss_url_for(&self, name: &str) -> Result<Url> {
+        let mut url =
+            format!("abfss://{}@{}/", self.filesystem, self.host).parse::<Url>()?;
+        url.path_segments_mut()
+            .map_err(|_| format_err!("cannot build URL for {:?}", name))?
+            .pop_if_empty()
+            .extend(name.split('/'));
+        Ok(url)
+    }
+}
+
+/// Split an `abfss://` URL into its parts.
+pub(crate) fn parse_abfss_url(url: &Url) -> Result<AbfssPath> {
+    if url.scheme() != "abfss" {
+        return Err(format_err!("expected an abfss:// URL, found {}", url));
+    }
+    let filesystem = url.username();
+    if filesystem.is_empty() {
+        return Err(format_err!(
+            "expected abfss://filesystem@account.dfs.core.windows.net/, found {}",
+            url,
+        ));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| format_err!("could not get storage account from {}", url))?;
+    Ok(AbfssPath {
+        host: host.to_owned(),
+        filesystem: filesystem.to_owned(),
+        path: url.path().trim_start_matches('/').to_owned(),
+    })
+}
+
+#[test]
+fn parse_abfss_url_and_build_urls() {
+    let url = "abfss://data@example.dfs.core.windows.net/dir/my%20file.csv"
+        .parse::<Url>()
+        .unwrap();
+    let path = parse_abfss_url(&url).unwrap();
+    assert_eq!(
+        path,
+        AbfssPath {
+            host: "example.dfs.core.windows.net".to_owned(),
+            filesystem: "data".to_owned(),
+            path: "dir/my%20file.csv".to_owned(),
+        }
+    );
+    assert_eq!(path.decoded_path().unwrap(), "dir/my file.csv");
+    assert_eq!(
+        path.rest_url().unwrap().as_str(),
+        "https://example.dfs.core.windows.net/data/dir/my%20file.csv",
+    );
+    assert_eq!(
+        path.abfss_url_for("dir/a#b.csv").unwrap().as_str(),
+        "abfss://data@example.dfs.core.windows.net/dir/a%23b.csv",
+    );
+
+    let no_fs = "abfss://example.dfs.core.windows.net/dir/"
+        .parse::<Url>()
+        .unwrap();
+    assert!(parse_abfss_url(&no_fs).is_err());
+}
@@ -0,0 +1,65 @@
+//! Deleting directories from Azure Data Lake Storage.
+
+use reqwest::{Method, StatusCode};
+
+use super::{
+    super::{error_from_response, Client},
+    parse_abfss_url,
+};
+use crate::common::*;
+
+/// Recursively delete an `abfss://` directory.
+///
+/// With a hierarchical namespace, this is a single operation on the directory
+/// itself, instead of one deletion per file. Deleting a directory which
+/// doesn't exist succeeds.
+pub(crate) async fn rmdir(ctx: &Context, url: &Url) -> Result<()> {
+    debug!(ctx.log(), "deleting existing {}", url);
+
+    if !url.path().ends_with('/') {
+        return Err(format_err!(
+            "can only delete abfss:// URL ending in '/', got {}",
+            url,
+        ));
+    }
+    let path = parse_abfss_url(url)?;
+    if path.path.trim_end_matches('/').is_empty() {
+        return Err(format_err!(
+            "cannot delete the root of {}, use a subdirectory instead",
+            url,
+        ));
+    }
+
+    let client = Client::new(ctx).await?;
+    let mut continuation: Option<String> = None;
+    loop {
+        let mut req_url = path.rest_url()?;
+        {
+            let mut query = req_url.query_pairs_mut();
+            query.append_pair("recursive", "true");
+            if let Some(continuation) = &continuation {
+                query.append_pair("continuation", continuation);
+            }
+        }
+        let resp = client.request(ctx, Method::DELETE, &req_url, None).await?;
+        if resp.status() == StatusCode::NOT_FOUND && continuation.is_none() {
+            trace!(ctx.log(), "{} does not exist", url);
+            return Ok(());
+        } else if !resp.status().is_success() {
+            return Err(
+                error_from_response(ctx, &Method::DELETE, &req_url, resp).await
+            );
+        }
+
+        // Large directories may take more than one request to delete.
+        continuation = resp
+            .headers()
+            .get("x-ms-continuation")
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_owned());
+        if continuation.is_none() {
+            return Ok(());
+        }
+    }
+}
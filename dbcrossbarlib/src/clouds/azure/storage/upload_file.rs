@@ -0,0 +1,71 @@
+//! Uploading files to Azure Data Lake Storage.
+
+use bytes::Bytes;
+use reqwest::Method;
+
+use super::{super::Client, parse_abfss_url, CHUNK_SIZE};
+use crate::common::*;
+
+/// Upload `data` as a file at `file_url`, replacing any existing file.
+///
+/// This uses the three-step ADLS Gen2 protocol: we [create][create] an empty
+/// file, [append][update] our data in chunks, and then flush the file to make
+/// the data visible. Any missing parent directories are created automatically.
+///
+/// [create]: https://docs.microsoft.com/en-us/rest/api/storageservices/datalakestoragegen2/path/create
+/// [update]: https://docs.microsoft.com/en-us/rest/api/storageservices/datalakestoragegen2/path/update
+pub(crate) async fn upload_file(
+    ctx: &Context,
+    mut data: BoxStream<BytesMut>,
+    file_url: &Url,
+) -> Result<()> {
+    debug!(ctx.log(), "streaming to {}", file_url);
+    let req_url = parse_abfss_url(file_url)?.rest_url()?;
+    let client = Client::new(ctx).await?;
+
+    // Create our file.
+    let mut create_url = req_url.clone();
+    create_url.query_pairs_mut().append_pair("resource", "file");
+    client
+        .request_ok(ctx, Method::PUT, &create_url, Some(Bytes::new()))
+        .await?;
+
+    // Append our data in chunks.
+    let mut position = 0;
+    let mut buffer = BytesMut::with_capacity(CHUNK_SIZE);
+    loop {
+        let next = data.next().await.transpose()?;
+        let at_end = next.is_none();
+        if let Some(bytes) = next {
+            buffer.extend_from_slice(&bytes);
+        }
+        if buffer.len() >= CHUNK_SIZE || (at_end && !buffer.is_empty()) {
+            let chunk = buffer.split().freeze();
+            let len = chunk.len();
+            let mut append_url = req_url.clone();
+            append_url
+                .query_pairs_mut()
+                .append_pair("action", "append")
+                .append_pair("position", &position.to_string());
+            trace!(ctx.log(), "appending {} bytes at {}", len, position);
+            client
+                .request_ok(ctx, Method::PATCH, &append_url, Some(chunk))
+                .await?;
+            position += len;
+        }
+        if at_end {
+            break;
+        }
+    }
+
+    // Commit everything we've appended.
+    let mut flush_url = req_url;
+    flush_url
+        .query_pairs_mut()
+        .append_pair("action", "flush")
+        .append_pair("position", &position.to_string());
+    client
+        .request_ok(ctx, Method::PATCH, &flush_url, Some(Bytes::new()))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! Looking up information about BigQuery datasets.
+
+use serde::Deserialize;
+
+use super::super::{percent_encode, Client, NoQuery};
+use crate::common::*;
+use crate::drivers::bigquery_shared::TableName;
+
+/// Information about a dataset.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Dataset {
+    /// Where this dataset's data is stored, like `US`, `EU` or `europe-west2`.
+    location: String,
+}
+
+/// Look up the location of the dataset containing `table_name`.
+pub(crate) async fn dataset_location(
+    ctx: &Context,
+    table_name: &TableName,
+) -> Result<String> {
+    trace!(ctx.log(), "fetching location of dataset for {}", table_name);
+
+    // Build our URL.
+    let url = format!(
+        "https://bigquery.googleapis.com/bigquery/v2/projects/{}/datasets/{}",
+        percent_encode(table_name.project()),
+        percent_encode(table_name.dataset()),
+    );
+
+    // Look up our dataset.
+    let client = Client::new(ctx).await?;
+    let dataset = client
+        .get::<Dataset, _, _>(ctx, &url, NoQuery)
+        .await
+        .with_context(|_| {
+            format!(
+                "could not look up location of dataset {}:{}",
+                table_name.project(),
+                table_name.dataset(),
+            )
+        })?;
+    Ok(dataset.location)
+}
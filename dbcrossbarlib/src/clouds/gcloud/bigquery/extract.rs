@@ -2,16 +2,20 @@
 
 use super::{
     super::Client,
+    explain_bucket_location,
     jobs::{run_job, Job, JobConfigurationExtract, Labels, TableReference},
 };
 
 use crate::common::*;
 use crate::drivers::bigquery_shared::TableName;
 
-/// Extract a table from BigQuery to Google Cloud Storage.
+/// Extract a table from BigQuery to Google Cloud Storage, using a job which
+/// runs in `project` and `location`.
 pub(crate) async fn extract(
     ctx: &Context,
+    project: &str,
     source_table: &TableName,
+    location: &str,
     dest_gs_url: &Url,
     labels: &Labels,
 ) -> Result<()> {
@@ -25,12 +29,18 @@ pub(crate) async fn extract(
 
     // Run our job.
     let client = Client::new(ctx).await?;
-    run_job(
+    let result = run_job(
         ctx,
         &client,
-        source_table.project(),
+        project,
+        location,
         Job::new_extract(config, labels.to_owned()),
     )
-    .await?;
-    Ok(())
+    .await;
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            Err(explain_bucket_location(ctx, dest_gs_url, location, err).await)
+        }
+    }
 }
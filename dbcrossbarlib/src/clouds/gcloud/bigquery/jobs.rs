@@ -7,11 +7,11 @@ use std::{collections::HashMap, convert::TryFrom};
 use tokio::time::{delay_for, Duration};
 
 use super::{
-    super::{Client, NoQuery},
+    super::{percent_encode, Client, NoQuery},
     BigQueryError, TableSchema,
 };
 use crate::common::*;
-use crate::drivers::bigquery_shared::TableName;
+use crate::drivers::bigquery_shared::{TableName, TimePartitioning};
 
 /// Key/value pairs. See [JobConfiguration][config].
 ///
@@ -160,6 +160,14 @@ pub(crate) struct JobConfigurationQuery {
 
     /// Should be use "legacy SQL" mode? Hint: No, we don't. Defaults to true.
     pub(crate) use_legacy_sql: Option<bool>,
+
+    /// How are our query parameters referred to? We only use `NAMED`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) parameter_mode: Option<String>,
+
+    /// Parameters to bind to `@name` placeholders in our query.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) query_parameters: Vec<QueryParameter>,
 }
 
 impl JobConfigurationQuery {
@@ -171,10 +179,64 @@ impl JobConfigurationQuery {
             write_disposition: None,
             query: query.into(),
             use_legacy_sql: Some(false),
+            parameter_mode: None,
+            query_parameters: vec![],
+        }
+    }
+
+    /// Bind `params` to the `@name` placeholders in our query. Values are
+    /// always passed as `STRING`, and queries should cast them as needed.
+    pub(crate) fn set_query_params(&mut self, params: &[QueryParam]) {
+        if params.is_empty() {
+            return;
         }
+        self.parameter_mode = Some("NAMED".to_owned());
+        self.query_parameters = params
+            .iter()
+            .map(|param| QueryParameter {
+                name: param.name().to_owned(),
+                parameter_type: QueryParameterType {
+                    ty: "STRING".to_owned(),
+                },
+                parameter_value: QueryParameterValue {
+                    value: param.value().to_owned(),
+                },
+            })
+            .collect();
     }
 }
 
+/// A parameter bound to a query.
+///
+/// See the [documentation][docs] for more details.
+///
+/// [docs]: https://cloud.google.com/bigquery/docs/reference/rest/v2/QueryParameter
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueryParameter {
+    /// The name of this parameter, without the leading `@`.
+    pub(crate) name: String,
+    /// The type of this parameter.
+    pub(crate) parameter_type: QueryParameterType,
+    /// The value of this parameter.
+    pub(crate) parameter_value: QueryParameterValue,
+}
+
+/// The type of a `QueryParameter`.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct QueryParameterType {
+    /// A BigQuery type name, like `STRING`.
+    #[serde(rename = "type")]
+    pub(crate) ty: String,
+}
+
+/// The value of a `QueryParameter`.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct QueryParameterValue {
+    /// The value, represented as a string.
+    pub(crate) value: String,
+}
+
 /// Configuration for data load jobs.
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -186,6 +248,20 @@ pub(crate) struct JobConfigurationLoad {
     pub(crate) write_disposition: Option<WriteDisposition>,
     pub(crate) skip_leading_rows: Option<i32>,
     pub(crate) allow_quoted_newlines: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) time_partitioning: Option<TimePartitioning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) destination_table_properties: Option<DestinationTableProperties>,
+}
+
+/// Properties to set on the destination table of a load job, if the job
+/// creates it.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DestinationTableProperties {
+    /// Labels to attach to the table.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) labels: Labels,
 }
 
 /// Configuration for data extraction jobs.
@@ -292,24 +368,35 @@ impl TryFrom<&IfExists> for WriteDisposition {
     }
 }
 
-/// Run a BigQuery job.
+/// Run a BigQuery job in `location`, which should be the location of the
+/// datasets it uses, like `US`, `EU` or `europe-west2`.
 pub(crate) async fn run_job(
     ctx: &Context,
     client: &Client,
     project_id: &str,
+    location: &str,
     mut job: Job,
 ) -> Result<Job> {
     trace!(
         ctx.log(),
-        "starting BigQuery job on {} {:?}",
+        "starting BigQuery job on {} in {} {:?}",
         project_id,
+        location,
         job,
     );
 
+    // Choose our own job ID, because BigQuery requires one when we specify a
+    // location.
+    job.job_reference = Some(JobReference {
+        project_id: project_id.to_owned(),
+        job_id: format!("dbcrossbar_{}", TemporaryStorage::random_tag()),
+        location: location.to_owned(),
+    });
+
     // Create our job.
     let insert_url = format!(
         "https://bigquery.googleapis.com/bigquery/v2/projects/{}/jobs",
-        project_id,
+        percent_encode(project_id),
     );
     job = client
         .post::<Job, _, _, _>(ctx, &insert_url, NoQuery, job)
@@ -346,3 +433,23 @@ pub(crate) async fn run_job(
         .check_for_error()?;
     Ok(job)
 }
+
+#[test]
+fn query_params_are_serialized() {
+    let mut config = JobConfigurationQuery::new("SELECT @day");
+    let json = serde_json::to_value(&config).unwrap();
+    assert!(json.get("parameterMode").is_none());
+    assert!(json.get("queryParameters").is_none());
+    let params = vec!["day=2024-06-01".parse::<QueryParam>().unwrap()];
+    config.set_query_params(&params);
+    let json = serde_json::to_value(&config).unwrap();
+    assert_eq!(json["parameterMode"], "NAMED");
+    assert_eq!(
+        json["queryParameters"],
+        serde_json::json!([{
+            "name": "day",
+            "parameterType": { "type": "STRING" },
+            "parameterValue": { "value": "2024-06-01" },
+        }]),
+    );
+}
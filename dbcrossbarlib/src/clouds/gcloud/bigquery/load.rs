@@ -2,27 +2,42 @@
 
 use super::{
     super::Client,
+    explain_bucket_location,
     jobs::{
-        run_job, CreateDisposition, Job, JobConfigurationLoad, Labels, TableReference,
-        WriteDisposition,
+        run_job, CreateDisposition, DestinationTableProperties, Job,
+        JobConfigurationLoad, Labels, TableReference, WriteDisposition,
     },
     TableSchema,
 };
 use crate::common::*;
-use crate::drivers::bigquery_shared::BqTable;
+use crate::drivers::bigquery_shared::{BqTable, NewTableOptions};
 use std::convert::TryFrom;
 
-/// Load data from `gs_url` into `dest_table`.
+/// Load data from `gs_url` into `dest_table`, using a job which runs in
+/// `project` and `location`. If we need to create `dest_table`, we use
+/// `options`.
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn load(
     ctx: &Context,
+    project: &str,
     gs_url: &Url,
     dest_table: &BqTable,
+    location: &str,
     if_exists: &IfExists,
+    options: &NewTableOptions,
     labels: &Labels,
 ) -> Result<()> {
     trace!(ctx.log(), "loading {} into {}", gs_url, dest_table.name);
 
     // Configure our job.
+    let table_labels = options.labels.labels();
+    let destination_table_properties = if table_labels.is_empty() {
+        None
+    } else {
+        Some(DestinationTableProperties {
+            labels: table_labels.to_owned(),
+        })
+    };
     let config = JobConfigurationLoad {
         source_uris: vec![gs_url.to_string()],
         schema: Some(TableSchema {
@@ -33,16 +48,22 @@ pub(crate) async fn load(
         write_disposition: Some(WriteDisposition::try_from(if_exists)?),
         skip_leading_rows: Some(1),
         allow_quoted_newlines: Some(true),
+        time_partitioning: options.time_partitioning.clone(),
+        destination_table_properties,
     };
 
     // Run our job.
     let client = Client::new(ctx).await?;
-    run_job(
+    let result = run_job(
         ctx,
         &client,
-        dest_table.name.project(),
+        project,
+        location,
         Job::new_load(config, labels.to_owned()),
     )
-    .await?;
-    Ok(())
+    .await;
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) => Err(explain_bucket_location(ctx, gs_url, location, err).await),
+    }
 }
@@ -3,15 +3,18 @@
 use serde::{Deserialize, Serialize};
 use std::{error, fmt};
 
+use super::storage::bucket_location;
 use crate::common::*;
 use crate::drivers::bigquery_shared::{BqColumn, TableName};
 
+mod datasets;
 mod extract;
 pub(crate) mod jobs;
 mod load;
 mod queries;
 mod schema;
 
+pub(crate) use datasets::*;
 pub(crate) use extract::*;
 pub(crate) use jobs::Labels;
 pub(crate) use load::*;
@@ -55,14 +58,38 @@ pub(crate) struct TableSchema {
     fields: Vec<BqColumn>,
 }
 
-/// Drop a table from BigQuery.
+/// Drop a table from BigQuery, using a job which runs in `project`.
 pub(crate) async fn drop_table(
     ctx: &Context,
+    project: &str,
     table_name: &TableName,
+    location: &str,
     labels: &Labels,
 ) -> Result<()> {
     // Delete temp table.
     debug!(ctx.log(), "deleting table: {}", table_name);
     let sql = format!("DROP TABLE {};\n", table_name.dotted_and_quoted());
-    execute_sql(ctx, table_name.project(), &sql, labels).await
+    execute_sql(ctx, project, location, &sql, labels).await
+}
+
+/// Given an error from a job which ran in `location` and used `gs_url`, check
+/// whether the bucket is somewhere else, and if so, explain that in the error.
+/// BigQuery's own errors about this can be hard to understand.
+async fn explain_bucket_location(
+    ctx: &Context,
+    gs_url: &Url,
+    location: &str,
+    err: Error,
+) -> Error {
+    match bucket_location(ctx, gs_url).await {
+        Ok(bucket_location) if !bucket_location.eq_ignore_ascii_case(location) => err
+            .context(format!(
+                "BigQuery job ran in {}, but the bucket for {} is in {}; you may \
+                 need to use a bucket in {}",
+                location, gs_url, bucket_location, location,
+            ))
+            .into(),
+        // Either our locations match, or we couldn't find out.
+        _ => err,
+    }
 }
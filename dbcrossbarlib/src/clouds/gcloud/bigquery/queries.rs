@@ -18,6 +18,7 @@ use crate::drivers::bigquery_shared::{BqColumn, TableName};
 pub(crate) async fn execute_sql(
     ctx: &Context,
     project: &str,
+    location: &str,
     sql: &str,
     labels: &Labels,
 ) -> Result<()> {
@@ -28,17 +29,22 @@ pub(crate) async fn execute_sql(
         ctx,
         &client,
         project,
+        location,
         Job::new_query(config, labels.to_owned()),
     )
     .await?;
     Ok(())
 }
 
-/// Run an SQL query and save the results to a table.
+/// Run an SQL query and save the results to a table, binding `params` to any
+/// `@name` placeholders.
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn query_to_table(
     ctx: &Context,
     project: &str,
+    location: &str,
     sql: &str,
+    params: &[QueryParam],
     dest_table: &TableName,
     if_exists: &IfExists,
     labels: &Labels,
@@ -50,6 +56,7 @@ pub(crate) async fn query_to_table(
     config.destination_table = Some(TableReference::from(dest_table));
     config.create_disposition = Some(CreateDisposition::CreateIfNeeded);
     config.write_disposition = Some(WriteDisposition::try_from(if_exists)?);
+    config.set_query_params(params);
 
     // Run our query.
     let client = Client::new(ctx).await?;
@@ -57,6 +64,7 @@ pub(crate) async fn query_to_table(
         ctx,
         &client,
         project,
+        location,
         Job::new_query(config, labels.to_owned()),
     )
     .await?;
@@ -160,18 +168,22 @@ impl Value {
 async fn query_all_json(
     ctx: &Context,
     project: &str,
+    location: &str,
     sql: &str,
+    params: &[QueryParam],
     labels: &Labels,
 ) -> Result<Vec<serde_json::Value>> {
     trace!(ctx.log(), "executing SQL: {}", sql);
 
     // Run our query.
-    let config = JobConfigurationQuery::new(sql);
+    let mut config = JobConfigurationQuery::new(sql);
+    config.set_query_params(params);
     let client = Client::new(ctx).await?;
     let job = run_job(
         ctx,
         &client,
         project,
+        location,
         Job::new_query(config, labels.to_owned()),
     )
     .await?;
@@ -200,13 +212,15 @@ async fn query_all_json(
 pub(crate) async fn query_all<T>(
     ctx: &Context,
     project: &str,
+    location: &str,
     sql: &str,
+    params: &[QueryParam],
     labels: &Labels,
 ) -> Result<Vec<T>>
 where
     T: DeserializeOwned,
 {
-    let output = query_all_json(ctx, project, sql, labels).await?;
+    let output = query_all_json(ctx, project, location, sql, params, labels).await?;
     let rows = output
         .into_iter()
         .map(serde_json::from_value::<T>)
@@ -219,13 +233,15 @@ where
 pub(crate) async fn query_one<T>(
     ctx: &Context,
     project: &str,
+    location: &str,
     sql: &str,
+    params: &[QueryParam],
     labels: &Labels,
 ) -> Result<T>
 where
     T: DeserializeOwned,
 {
-    let mut rows = query_all(ctx, project, sql, labels).await?;
+    let mut rows = query_all(ctx, project, location, sql, params, labels).await?;
     if rows.len() == 1 {
         Ok(rows.remove(0))
     } else {
@@ -1,20 +1,24 @@
 //! A Google Cloud REST client.
 
 use bigml::wait::{wait, BackoffType, WaitOptions, WaitStatus};
+use bytes::Bytes;
 use failure::ResultExt;
 use mime::{self, Mime};
 use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use reqwest::{
     self,
-    header::{HeaderMap, CONTENT_TYPE},
-    IntoUrl,
+    header::{HeaderMap, CONTENT_RANGE, CONTENT_TYPE, LOCATION, RANGE},
+    IntoUrl, StatusCode,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{error, fmt, time::Duration};
 
 use super::auth::{authenticator, AccessToken, Authenticator};
 use crate::common::*;
-use crate::tokio_glue::IdiomaticBytesStream;
+use crate::recording::{Interaction, RecorderMode};
+
+#[cfg(feature = "bigquery")]
+mod post;
 
 /// The OAuth2 scopes that we'll need.
 ///
@@ -41,10 +45,54 @@ pub(crate) enum Alt {
     Proto,
 }
 
+/// The state of a resumable upload after we send it a chunk.
+#[derive(Debug)]
+pub(crate) enum UploadStatus<Output> {
+    /// The server has saved the first `committed` bytes, and it wants more.
+    Incomplete { committed: u64 },
+    /// The upload is finished, and the server returned `Output`.
+    Complete(Output),
+    /// The request failed, but trying again might work.
+    FailedTemporarily(Error),
+}
+
+/// The parts of an HTTP response that we use, which may have come from either
+/// the network or a recording.
+struct RawResponse {
+    /// The HTTP status.
+    status: StatusCode,
+    /// Did the server claim that `body` was JSON?
+    is_json: bool,
+    /// The response body.
+    body: String,
+}
+
+impl RawResponse {
+    /// Read the body of `http_resp`.
+    async fn read(
+        ctx: &Context,
+        url: &Url,
+        http_resp: reqwest::Response,
+    ) -> Result<RawResponse> {
+        let status = http_resp.status();
+        let is_json = response_claims_to_be_json(ctx, &http_resp);
+        let body = http_resp
+            .text()
+            .await
+            .with_context(|_| format!("error fetching response from {}", url))?;
+        Ok(RawResponse {
+            status,
+            is_json,
+            body,
+        })
+    }
+}
+
 /// A Google Cloud REST client using OAuth2.
 pub(crate) struct Client {
-    /// An authenticator that provides OAuth2 tokens.
-    authenticator: Authenticator,
+    /// An authenticator that provides OAuth2 tokens. This will be `None` if
+    /// we're replaying a recording.
+    authenticator: Option<Authenticator>,
 
     /// Our HTTP client.
     client: reqwest::Client,
@@ -53,7 +101,10 @@ pub(crate) struct Client {
 impl Client {
     /// Create a new Google Cloud client.
     pub(crate) async fn new(ctx: &Context) -> Result<Client> {
-        let authenticator = authenticator(ctx).await?;
+        let authenticator = match ctx.recorder() {
+            Some(recorder) if recorder.mode() == RecorderMode::Replay => None,
+            _ => Some(authenticator(ctx).await?),
+        };
         let client = reqwest::Client::new();
         Ok(Client {
             authenticator,
@@ -133,9 +184,12 @@ impl Client {
         Query: fmt::Debug + Serialize,
     {
         let url = build_url(url, query)?;
-        let headers = HeaderMap::default();
-        let http_resp = self.get_helper(ctx, &url, headers).await?;
-        self.handle_response(ctx, "GET", &url, http_resp).await
+        let send = async {
+            let headers = HeaderMap::default();
+            self.get_helper(ctx, &url, headers).await
+        };
+        let raw = self.send_or_replay(ctx, "GET", &url, None, send).await?;
+        self.handle_response(ctx, "GET", &url, raw)
     }
 
     /// Make an HTTP GET request with the specified URL and query parameters,
@@ -160,23 +214,29 @@ impl Client {
         }
     }
 
-    /// Make an HTTP POST request with the specified URL and body.
-    pub(crate) async fn post<Output, U, Query, Body>(
+    /// Start a resumable upload, and return the session URL to which we should
+    /// send our data. `body` contains the metadata for the new object.
+    ///
+    /// Docs: https://cloud.google.com/storage/docs/performing-resumable-uploads
+    pub(crate) async fn start_resumable_upload<U, Query, Body>(
         &self,
         ctx: &Context,
         url: U,
         query: Query,
         body: Body,
-    ) -> Result<Output>
+    ) -> Result<Url>
     where
-        Output: fmt::Debug + DeserializeOwned,
         U: IntoUrl,
         Query: fmt::Debug + Serialize,
         Body: fmt::Debug + Serialize,
     {
         let url = build_url(url, query)?;
-        trace!(ctx.log(), "POST {} {:?}", url, body);
-        trace!(ctx.log(), "serialied {}", serde_json::to_string(&body)?);
+        trace!(
+            ctx.log(),
+            "POST {} {:?} to start resumable upload",
+            url,
+            body
+        );
         let token = self.token().await?;
         let http_resp = self
             .client
@@ -186,38 +246,79 @@ impl Client {
             .send()
             .await
             .with_context(|_| format!("could not POST {}", url))?;
-        self.handle_response(ctx, "POST", &url, http_resp).await
+        if !http_resp.status().is_success() {
+            return Err(self.handle_error(ctx, "POST", &url, http_resp).await);
+        }
+        let location = http_resp
+            .headers()
+            .get(LOCATION)
+            .ok_or_else(|| format_err!("no Location header in response from {}", url))?
+            .to_str()
+            .context("could not parse Location header")?;
+        Ok(location
+            .parse::<Url>()
+            .with_context(|_| format!("could not parse upload URL {:?}", location))?)
     }
 
-    /// Post a stream of data to the specified URL.
-    pub(crate) async fn post_stream<U, Query>(
+    /// Send `chunk` to a resumable upload session. `content_range` should be
+    /// a `Content-Range` header value describing `chunk`. To ask how much data
+    /// the server has saved, pass an empty `chunk` and `bytes */*`.
+    pub(crate) async fn put_upload_chunk<Output>(
         &self,
-        // Pass `ctx` by value, not reference, because of a weird async lifetime error.
-        ctx: Context,
-        url: U,
-        query: Query,
-        stream: IdiomaticBytesStream,
-    ) -> Result<()>
+        ctx: &Context,
+        session_url: &Url,
+        content_range: &str,
+        chunk: Bytes,
+    ) -> Result<UploadStatus<Output>>
     where
-        U: IntoUrl,
-        Query: fmt::Debug + Serialize,
+        Output: fmt::Debug + DeserializeOwned,
     {
-        let url = build_url(url, query)?;
-        trace!(ctx.log(), "POST {} with stream", url);
-        let body = reqwest::Body::wrap_stream(stream);
+        trace!(ctx.log(), "PUT upload chunk {}", content_range);
         let token = self.token().await?;
-        let http_resp = self
+        let resp_result = self
             .client
-            .post(url.as_str())
+            .put(session_url.as_str())
             .bearer_auth(token.as_str())
-            .body(body)
+            .header(CONTENT_RANGE, content_range)
+            .body(chunk)
             .send()
-            .await
-            .with_context(|_| format!("could not POST {}", url))?;
-        if http_resp.status().is_success() {
-            Ok(())
-        } else {
-            Err(self.handle_error(&ctx, "POST", &url, http_resp).await)
+            .await;
+        match resp_result {
+            // As in `get_helper`, we guess that these errors are temporary.
+            Err(err) if err.is_request() || err.is_timeout() => {
+                let err: Error = err.into();
+                Ok(UploadStatus::FailedTemporarily(
+                    err.context("could not PUT upload chunk").into(),
+                ))
+            }
+            Err(err) => Err(err.into()),
+            // Google uses 308 to mean "Resume Incomplete", not "Permanent
+            // Redirect".
+            Ok(resp) if resp.status() == StatusCode::PERMANENT_REDIRECT => {
+                let range = match resp.headers().get(RANGE) {
+                    Some(range) => {
+                        Some(range.to_str().context("could not parse Range header")?)
+                    }
+                    None => None,
+                };
+                Ok(UploadStatus::Incomplete {
+                    committed: committed_bytes(range)?,
+                })
+            }
+            Ok(resp) if resp.status().is_server_error() => {
+                Ok(UploadStatus::FailedTemporarily(
+                    self.handle_error(ctx, "PUT", session_url, resp).await,
+                ))
+            }
+            Ok(resp) => {
+                let raw = RawResponse::read(ctx, session_url, resp).await?;
+                Ok(UploadStatus::Complete(self.handle_response(
+                    ctx,
+                    "PUT",
+                    session_url,
+                    raw,
+                )?))
+            }
         }
     }
 
@@ -234,53 +335,102 @@ impl Client {
     {
         let url = build_url(url, query)?;
         trace!(ctx.log(), "DELETE {}", url);
-        let token = self.token().await?;
-        let http_resp = self
-            .client
-            .delete(url.as_str())
-            .bearer_auth(token.as_str())
-            .send()
-            .await
-            .with_context(|_| format!("error deleting {}", url))?;
-        if http_resp.status().is_success() {
+        let send = async {
+            let token = self.token().await?;
+            Ok(self
+                .client
+                .delete(url.as_str())
+                .bearer_auth(token.as_str())
+                .send()
+                .await
+                .with_context(|_| format!("error deleting {}", url))?)
+        };
+        let raw = self.send_or_replay(ctx, "DELETE", &url, None, send).await?;
+        if raw.status.is_success() {
             Ok(())
         } else {
-            Err(format_err!(
-                "error deleting {}: {}",
-                url,
-                http_resp.status(),
-            ))
+            Err(format_err!("error deleting {}: {}", url, raw.status))
+        }
+    }
+
+    /// Run `send` to make an HTTP request and read the response. If we're
+    /// recording, record the request and response. If we're replaying, return
+    /// the recorded response without running `send`.
+    async fn send_or_replay<F>(
+        &self,
+        ctx: &Context,
+        method: &str,
+        url: &Url,
+        request: Option<serde_json::Value>,
+        send: F,
+    ) -> Result<RawResponse>
+    where
+        F: Future<Output = Result<reqwest::Response>>,
+    {
+        match ctx.recorder() {
+            Some(recorder) if recorder.mode() == RecorderMode::Replay => {
+                let interaction = recorder.replay_interaction(
+                    ctx,
+                    method,
+                    url.as_str(),
+                    request.as_ref(),
+                )?;
+                Ok(RawResponse {
+                    status: StatusCode::from_u16(interaction.status)?,
+                    is_json: interaction.is_json,
+                    body: interaction.response,
+                })
+            }
+            recorder => {
+                let raw = RawResponse::read(ctx, url, send.await?).await?;
+                if let Some(recorder) = recorder {
+                    recorder.record_interaction(Interaction::new(
+                        method,
+                        url.as_str(),
+                        request,
+                        raw.status.as_u16(),
+                        raw.is_json,
+                        raw.body.clone(),
+                    ));
+                }
+                Ok(raw)
+            }
         }
     }
 
     /// Get an access token.
     async fn token(&self) -> Result<AccessToken> {
-        Ok(self
-            .authenticator
+        let authenticator = self.authenticator.as_ref().ok_or_else(|| {
+            format_err!(
+                "cannot transfer Google Cloud data while replaying a recording"
+            )
+        })?;
+        Ok(authenticator
             .token(SCOPES)
             .await
             .context("could not get Google Cloud OAuth2 token")?)
     }
 
     /// Handle an HTTP response.
-    async fn handle_response<Output>(
+    fn handle_response<Output>(
         &self,
         ctx: &Context,
         method: &str,
         url: &Url,
-        http_resp: reqwest::Response,
+        raw: RawResponse,
     ) -> Result<Output>
     where
         Output: fmt::Debug + DeserializeOwned,
     {
-        if http_resp.status().is_success() {
-            let resp = http_resp.json::<Output>().await.with_context(|_| {
-                format!("error fetching JSON response from {}", url)
-            })?;
+        if raw.status.is_success() {
+            let resp =
+                serde_json::from_str::<Output>(&raw.body).with_context(|_| {
+                    format!("error fetching JSON response from {}", url)
+                })?;
             trace!(ctx.log(), "{} returned {:?}", method, resp);
             Ok(resp)
         } else {
-            Err(self.handle_error(ctx, method, url, http_resp).await)
+            Err(self.handle_raw_error(ctx, method, url, raw))
         }
     }
 
@@ -292,23 +442,23 @@ impl Client {
         url: &Url,
         http_resp: reqwest::Response,
     ) -> Error {
-        // Decide if we should even try to parse this response as JSON before we
-        // consume our http_resp.
-        let should_parse_as_json = response_claims_to_be_json(ctx, &http_resp);
-
-        // Fetch the error body.
-        let err_body_result = http_resp
-            .bytes()
-            .await
-            .with_context(|_| format!("error fetching error response from {}", url));
-        let err_body = match err_body_result {
-            Ok(err_body) => err_body,
-            Err(err) => return err.into(),
-        };
+        match RawResponse::read(ctx, url, http_resp).await {
+            Ok(raw) => self.handle_raw_error(ctx, method, url, raw),
+            Err(err) => err,
+        }
+    }
 
+    /// Handle an HTTP error response that we've already read.
+    fn handle_raw_error(
+        &self,
+        ctx: &Context,
+        method: &str,
+        url: &Url,
+        raw: RawResponse,
+    ) -> Error {
         // Try to return a nice JSON error.
-        if should_parse_as_json {
-            if let Ok(resp) = serde_json::from_slice::<ErrorResponse>(&err_body) {
+        if raw.is_json {
+            if let Ok(resp) = serde_json::from_str::<ErrorResponse>(&raw.body) {
                 trace!(ctx.log(), "{} error {:?}", method, resp);
                 let err: Error = resp.error.into();
                 return err.context(format!("{} error {}", method, url)).into();
@@ -318,15 +468,15 @@ impl Client {
         // We've run afoul of
         // https://github.com/googleapis/google-cloud-ruby/issues/5180 or
         // something equally terrible, so just report whatever we have.
-        let raw_err = String::from_utf8_lossy(&err_body);
         trace!(
             ctx.log(),
             "{} {}: expected JSON describing error, but got {:?}",
             method,
             url,
-            raw_err,
+            raw.body,
         );
-        let err = format_err!("expected JSON describing error, but got {:?}", raw_err);
+        let err =
+            format_err!("expected JSON describing error, but got {:?}", raw.body);
         err.context(format!("{} error {}", method, url)).into()
     }
 }
@@ -380,6 +530,32 @@ pub(crate) struct ErrorDetail {
     pub(crate) location: Option<String>,
 }
 
+/// Given the `Range` header from a `308 Resume Incomplete` response, return how
+/// many bytes the server has saved. A missing header means none.
+fn committed_bytes(range: Option<&str>) -> Result<u64> {
+    let range = match range {
+        Some(range) => range,
+        None => return Ok(0),
+    };
+    let prefix = "bytes=0-";
+    if !range.starts_with(prefix) {
+        return Err(format_err!("unexpected Range header {:?}", range));
+    }
+    let end = range[prefix.len()..]
+        .parse::<u64>()
+        .with_context(|_| format!("unexpected Range header {:?}", range))?;
+    Ok(end + 1)
+}
+
+#[test]
+fn committed_bytes_parses_range() {
+    assert_eq!(committed_bytes(None).unwrap(), 0);
+    assert_eq!(committed_bytes(Some("bytes=0-0")).unwrap(), 1);
+    assert_eq!(committed_bytes(Some("bytes=0-262143")).unwrap(), 262_144);
+    assert!(committed_bytes(Some("bytes=10-20")).is_err());
+    assert!(committed_bytes(Some("bytes=0-x")).is_err());
+}
+
 /// Percent-encode a string for use as a URL path component.
 pub(crate) fn percent_encode<'a>(s: &'a str) -> impl fmt::Display + 'a {
     utf8_percent_encode(s, NON_ALPHANUMERIC)
@@ -0,0 +1,45 @@
+//! JSON `POST` requests, which we only need to create BigQuery jobs.
+
+use reqwest::IntoUrl;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+
+use super::{build_url, Client};
+use crate::common::*;
+
+impl Client {
+    /// Make an HTTP POST request with the specified URL and body.
+    pub(crate) async fn post<Output, U, Query, Body>(
+        &self,
+        ctx: &Context,
+        url: U,
+        query: Query,
+        body: Body,
+    ) -> Result<Output>
+    where
+        Output: fmt::Debug + DeserializeOwned,
+        U: IntoUrl,
+        Query: fmt::Debug + Serialize,
+        Body: fmt::Debug + Serialize,
+    {
+        let url = build_url(url, query)?;
+        trace!(ctx.log(), "POST {} {:?}", url, body);
+        trace!(ctx.log(), "serialied {}", serde_json::to_string(&body)?);
+        let request = serde_json::to_value(&body)?;
+        let send = async {
+            let token = self.token().await?;
+            Ok(self
+                .client
+                .post(url.as_str())
+                .bearer_auth(token.as_str())
+                .json(&request)
+                .send()
+                .await
+                .with_context(|_| format!("could not POST {}", url))?)
+        };
+        let raw = self
+            .send_or_replay(ctx, "POST", &url, Some(request.clone()), send)
+            .await?;
+        self.handle_response(ctx, "POST", &url, raw)
+    }
+}
@@ -1,6 +1,7 @@
 //! Interfaces to Google Cloud.
 
 pub(crate) mod auth;
+#[cfg(feature = "bigquery")]
 pub(crate) mod bigquery;
 mod client;
 pub(crate) mod crc32c_stream;
@@ -0,0 +1,29 @@
+//! Looking up information about Google Cloud Storage buckets.
+
+use serde::Deserialize;
+
+use super::{
+    super::{percent_encode, Client, NoQuery},
+    parse_gs_url,
+};
+use crate::common::*;
+
+/// Information about a bucket.
+#[derive(Debug, Deserialize)]
+struct Bucket {
+    /// Where this bucket's data is stored, like `US`, `EU` or `EUROPE-WEST2`.
+    location: String,
+}
+
+/// Look up the location of the bucket containing `url`.
+pub(crate) async fn bucket_location(ctx: &Context, url: &Url) -> Result<String> {
+    let (bucket, _) = parse_gs_url(url)?;
+    trace!(ctx.log(), "fetching location of bucket {}", bucket);
+    let req_url = format!(
+        "https://storage.googleapis.com/storage/v1/b/{}",
+        percent_encode(&bucket),
+    );
+    let client = Client::new(ctx).await?;
+    let bucket = client.get::<Bucket, _, _>(ctx, &req_url, NoQuery).await?;
+    Ok(bucket.location)
+}
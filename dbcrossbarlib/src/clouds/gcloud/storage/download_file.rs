@@ -3,16 +3,17 @@
 use bytes::BufMut;
 use futures::stream;
 use headers::{ContentRange, Header, HeaderMapExt, Range};
-use reqwest::header::{HeaderMap, HeaderValue, IF_MATCH};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, IF_MATCH};
 use serde::Serialize;
 use std::{cmp::min, convert::TryFrom, ops};
-use tokio::spawn;
+use tokio::{spawn, time::delay_for};
 
 use super::{
     super::{percent_encode, Alt, Client},
-    parse_gs_url, StorageObject, CHUNK_SIZE,
+    max_retries, parse_gs_url, StorageObject, CHUNK_SIZE,
 };
 use crate::common::*;
+use crate::retry::delay_before_retry;
 
 /// Maximum number of parallel downloads.
 const PARALLEL_DOWNLOADS: usize = 5;
@@ -25,12 +26,18 @@ struct DownloadQuery {
 
     /// What object generation do we expect to download?
     if_generation_match: i64,
+
+    /// The project to bill for this request, for requester-pays buckets.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_project: Option<String>,
 }
 
-/// Download the file at the specified URL as a stream.
+/// Download the file at the specified URL as a stream. If `user_project` is
+/// specified, bill the download to that project.
 pub(crate) async fn download_file(
     ctx: &Context,
     item: &StorageObject,
+    user_project: Option<&str>,
 ) -> Result<BoxStream<BytesMut>> {
     let file_url = item.to_url_string().parse::<Url>()?;
     debug!(ctx.log(), "streaming from {}", file_url);
@@ -44,16 +51,21 @@ pub(crate) async fn download_file(
     );
     let mut common_headers = HeaderMap::default();
     common_headers.insert(IF_MATCH, HeaderValue::from_str(&item.etag)?);
+    // Always ask for the bytes as stored. Otherwise, Cloud Storage would
+    // decompress objects with `Content-Encoding: gzip` and ignore our ranges.
+    common_headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
 
     // Build a stream of download tasks.
     let ctx = ctx.to_owned();
     let generation = item.generation;
+    let user_project = user_project.map(|p| p.to_owned());
     let stream = stream::iter(chunk_ranges(CHUNK_SIZE, item.size))
         .map(move |range| {
             download_range(
                 ctx.clone(),
                 url.clone(),
                 generation,
+                user_project.clone(),
                 common_headers.clone(),
                 range,
             )
@@ -75,7 +87,8 @@ async fn download_range(
     ctx: Context,
     url: String,
     generation: i64,
-    mut headers: HeaderMap,
+    user_project: Option<String>,
+    headers: HeaderMap,
     range: ops::Range<u64>,
 ) -> Result<BytesMut> {
     trace!(
@@ -90,39 +103,76 @@ async fn download_range(
     // backpressure. This is reasonable because we're downloading a chunk of
     // predictable size.
     let task_fut = async move {
-        // Make our request.
         let client = Client::new(&ctx).await?;
-        headers.typed_insert(Range::bytes(range.clone())?);
-        let query = DownloadQuery {
-            alt: Alt::Media,
-            if_generation_match: generation,
-        };
-        let response = client.get_response(&ctx, &url, query, headers).await?;
-
-        // Make sure we're downloading the range we expect.
-        let content_range = get_header::<ContentRange>(&response)?;
-        let (start, end_inclusive) = content_range
-            .bytes_range()
-            .ok_or_else(|| format_err!("could not get range from Content-Range"))?;
-        if start != range.start || end_inclusive + 1 != range.end {
-            return Err(format_err!(
-                "expected to download range [{}, {}), but server offered [{}, {})",
-                range.start,
-                range.end,
-                start,
-                end_inclusive + 1,
-            ));
-        }
-
-        // Download the data to a buffer.
         let bytes_to_download = usize::try_from(range.end - range.start)
             .with_context(|_| {
                 format!("range {:?} is to big to fit in memory", range)
             })?;
         let mut buffer = BytesMut::with_capacity(bytes_to_download);
-        let mut stream = response.bytes_stream();
-        while let Some(chunk) = stream.next().await {
-            buffer.put(chunk?);
+        let max_retries = max_retries()?;
+        let mut retries = 0;
+        while buffer.len() < bytes_to_download {
+            // Make our request, asking only for the part of our range that we
+            // haven't received yet. `get_response` retries its own errors.
+            let part = range.start + u64::try_from(buffer.len())?..range.end;
+            let mut part_headers = headers.clone();
+            part_headers.typed_insert(Range::bytes(part.clone())?);
+            let query = DownloadQuery {
+                alt: Alt::Media,
+                if_generation_match: generation,
+                user_project: user_project.clone(),
+            };
+            let response =
+                client.get_response(&ctx, &url, query, part_headers).await?;
+
+            // Make sure we're downloading the range we expect.
+            let content_range = get_header::<ContentRange>(&response)?;
+            let (start, end_inclusive) =
+                content_range.bytes_range().ok_or_else(|| {
+                    format_err!("could not get range from Content-Range")
+                })?;
+            if start != part.start || end_inclusive + 1 != part.end {
+                return Err(format_err!(
+                    "expected to download range [{}, {}), but server offered [{}, {})",
+                    part.start,
+                    part.end,
+                    start,
+                    end_inclusive + 1,
+                ));
+            }
+
+            // Download the data to our buffer. If the connection fails partway
+            // through, keep what we have, and resume from there.
+            let mut stream = response.bytes_stream();
+            let mut body_result = Ok(());
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(chunk) => buffer.put(chunk),
+                    Err(err) => {
+                        body_result = Err(Error::from(err));
+                        break;
+                    }
+                }
+            }
+            let err = match body_result {
+                Ok(()) if buffer.len() >= bytes_to_download => break,
+                Ok(()) => format_err!("connection closed before range was complete"),
+                Err(err) => err,
+            };
+            if retries >= max_retries {
+                return Err(err);
+            }
+            retries += 1;
+            warn!(
+                ctx.log(),
+                "resuming download of {} at byte {} after error (retry {}/{}): {}",
+                url,
+                range.start + u64::try_from(buffer.len())?,
+                retries,
+                max_retries,
+                err,
+            );
+            delay_for(delay_before_retry(retries)).await;
         }
 
         // Did we download the number of bytes the `Content-Range` header promised?
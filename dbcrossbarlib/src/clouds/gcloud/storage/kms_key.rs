@@ -0,0 +1,96 @@
+//! Customer-managed encryption keys for the objects we write to Google Cloud
+//! Storage.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+
+/// What a KMS key name should look like, for error messages.
+const KEY_FORMAT: &str = "projects/P/locations/L/keyRings/R/cryptoKeys/K";
+
+/// The name of a Cloud KMS key, like
+/// `projects/p/locations/us/keyRings/r/cryptoKeys/k`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct KmsKeyName(String);
+
+impl KmsKeyName {
+    /// Parse the query string of a temporary storage URL, like
+    /// `gs://bucket/tmp/?kms_key=projects/...`.
+    pub(crate) fn from_url_query(url: &Url) -> Result<Option<Self>> {
+        let mut kms_key = None;
+        for (key, value) in url.query_pairs() {
+            match &key[..] {
+                "kms_key" => kms_key = Some(value.parse::<KmsKeyName>()?),
+                _ => {
+                    return Err(format_err!(
+                        "unknown option {:?} in {} (expected kms_key)",
+                        key,
+                        url,
+                    ))
+                }
+            }
+        }
+        Ok(kms_key)
+    }
+
+    /// The full resource name of this key.
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for KmsKeyName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for KmsKeyName {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = s.split('/').collect::<Vec<_>>();
+        let labels = ["projects", "locations", "keyRings", "cryptoKeys"];
+        let valid = parts.len() == 2 * labels.len()
+            && parts
+                .chunks(2)
+                .zip(labels.iter())
+                .all(|(pair, &label)| pair[0] == label && !pair[1].is_empty());
+        if valid {
+            Ok(KmsKeyName(s.to_owned()))
+        } else {
+            Err(format_err!(
+                "expected KMS key {:?} to look like {}",
+                s,
+                KEY_FORMAT,
+            ))
+        }
+    }
+}
+
+#[test]
+fn parse_kms_key_names() {
+    let name = "projects/p/locations/us/keyRings/r/cryptoKeys/etl";
+    assert_eq!(name.parse::<KmsKeyName>().unwrap().as_str(), name);
+
+    let invalid = &[
+        "",
+        "etl",
+        "projects/p/locations/us/keyRings/r/cryptoKeys/",
+        "projects/p/locations/us/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1",
+        "projects/p/locations/us/keyRings/r/keys/k",
+    ];
+    for &s in invalid {
+        assert!(s.parse::<KmsKeyName>().is_err(), "should not parse {:?}", s);
+    }
+
+    let url = format!("gs://example/tmp/?kms_key={}", name)
+        .parse::<Url>()
+        .unwrap();
+    assert_eq!(
+        KmsKeyName::from_url_query(&url).unwrap().unwrap().as_str(),
+        name,
+    );
+    let url = "gs://example/tmp/?sse=aws:kms".parse::<Url>().unwrap();
+    assert!(KmsKeyName::from_url_query(&url).is_err());
+}
@@ -18,6 +18,10 @@ struct ListQuery<'a> {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     page_token: Option<String>,
+
+    /// The project to bill for this request, for requester-pays buckets.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_project: Option<&'a str>,
 }
 
 /// Response body.
@@ -50,7 +54,9 @@ macro_rules! try_and_forward_errors {
     };
 }
 
-/// List all the files at the specified `gs://` URL, recursively.
+/// List all the files at the specified `gs://` URL, recursively. If
+/// `user_project` is specified, bill the listing to that project, which is
+/// required for requester-pays buckets.
 ///
 /// See the [documentation][list]. We treat "/" a directory separate, and try to
 /// handle prefix matches using ordinary file-system behavior.
@@ -59,9 +65,11 @@ macro_rules! try_and_forward_errors {
 pub(crate) async fn ls(
     ctx: &Context,
     url: &Url,
+    user_project: Option<&str>,
 ) -> Result<impl Stream<Item = Result<StorageObject>> + Send + Unpin + 'static> {
     debug!(ctx.log(), "listing {}", url);
     let (bucket, object) = parse_gs_url(url)?;
+    let user_project = user_project.map(|p| p.to_owned());
 
     // We were asked to list `object`, so everything we return should either be
     // `object` itself, or something in a subdirectory.
@@ -98,6 +106,7 @@ pub(crate) async fn ls(
             let query = ListQuery {
                 prefix: &object,
                 page_token: page_token.clone(),
+                user_project: user_project.as_deref(),
             };
 
             // Make our request.
@@ -122,11 +131,6 @@ pub(crate) async fn ls(
                     continue;
                 }
 
-                // Filter out non-CSV files.
-                if !item.name.to_ascii_lowercase().ends_with(".csv") {
-                    continue;
-                }
-
                 // Make sure that we either return the file that we were asked
                 // for, or something in a subdirectory. We don't want to accidentally
                 // return `object + "_trailing"`, but since cloud bucket stores don't
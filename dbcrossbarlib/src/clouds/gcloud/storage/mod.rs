@@ -1,5 +1,6 @@
 //! Interfaces to Google Cloud Storage.
 
+use chrono::{DateTime, Utc};
 use serde::{
     de::{self, Deserializer, Visitor},
     Deserialize,
@@ -7,16 +8,23 @@ use serde::{
 use std::{fmt, marker::PhantomData, str::FromStr};
 
 use crate::common::*;
+use crate::retry::max_retries_from_env;
 
+#[cfg(feature = "bigquery")]
+mod bucket;
 mod download_file;
+mod kms_key;
 mod ls;
 mod rmdir;
 mod upload_file;
 
+#[cfg(feature = "bigquery")]
+pub(crate) use bucket::bucket_location;
 pub(crate) use download_file::download_file;
+pub(crate) use kms_key::KmsKeyName;
 pub(crate) use ls::ls;
 pub(crate) use rmdir::rmdir;
-pub(crate) use upload_file::upload_file;
+pub(crate) use upload_file::{upload_file, UploadOptions};
 
 /// Chunk size to use when working with Google Cloud Storage.
 ///
@@ -39,6 +47,16 @@ pub(crate) const CHUNK_SIZE: u64 = 1024 * 1024;
 #[cfg(debug_assertions)]
 pub(crate) const CHUNK_SIZE: u64 = 128;
 
+/// How many times we retry part of a transfer which fails temporarily, unless
+/// overridden by `DBCROSSBAR_GCS_MAX_RETRIES`.
+const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// How many times should we retry an upload chunk or download range which
+/// fails temporarily? A value of `0` disables retries.
+pub(crate) fn max_retries() -> Result<u32> {
+    max_retries_from_env("DBCROSSBAR_GCS_MAX_RETRIES", DEFAULT_MAX_RETRIES)
+}
+
 /// Split a `gs://` URL into a bucket and an object name.
 pub(crate) fn parse_gs_url(url: &Url) -> Result<(String, String)> {
     if url.scheme() != "gs" {
@@ -74,6 +92,10 @@ pub(crate) struct StorageObject {
     /// The generation number for this object's metadata.
     #[serde(deserialize_with = "deserialize_int::<'_, i64, _>")]
     pub(crate) metageneration: i64,
+    /// When this object's data or metadata was last updated, in RFC 3339
+    /// format.
+    #[serde(default)]
+    pub(crate) updated: Option<String>,
 }
 
 impl StorageObject {
@@ -81,6 +103,20 @@ impl StorageObject {
     pub(crate) fn to_url_string(&self) -> String {
         format!("gs://{}/{}", self.bucket, self.name)
     }
+
+    /// When was this object last modified?
+    pub(crate) fn updated(&self) -> Result<Option<DateTime<Utc>>> {
+        self.updated
+            .as_ref()
+            .map(|updated| -> Result<DateTime<Utc>> {
+                Ok(DateTime::parse_from_rfc3339(updated)
+                    .with_context(|_| {
+                        format!("cannot parse {:?} as a timestamp", updated)
+                    })?
+                    .with_timezone(&Utc))
+            })
+            .transpose()
+    }
 }
 
 /// A helper function which can deserialize integers represented as either
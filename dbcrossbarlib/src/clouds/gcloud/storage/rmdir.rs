@@ -22,7 +22,7 @@ pub(crate) async fn rmdir(ctx: &Context, url: &Url) -> Result<()> {
     }
 
     // TODO: Used batched commands to delete 100 URLs at a time.
-    let url_stream = ls(ctx, url).await?;
+    let url_stream = ls(ctx, url, None).await?;
     let ctx = ctx.clone();
     let del_fut_stream: BoxStream<BoxFuture<()>> = url_stream
         .map_ok(move |item| {
@@ -1,13 +1,102 @@
-//! Download a file from Google Cloud storage.
+//! Upload a file to Google Cloud storage.
 
+use bytes::Bytes;
+use humanize_rs::bytes::Bytes as HumanizedBytes;
 use serde::Serialize;
+use std::{collections::BTreeMap, convert::TryFrom};
+use tokio::time::delay_for;
 
 use super::{
-    super::{crc32c_stream::Crc32cStream, percent_encode, Client, NoQuery},
-    parse_gs_url, StorageObject,
+    super::{crc32c_stream::Crc32cStream, percent_encode, Client, UploadStatus},
+    max_retries, parse_gs_url, KmsKeyName, StorageObject,
 };
 use crate::common::*;
-use crate::tokio_glue::idiomatic_bytes_stream;
+use crate::object_metadata::ObjectMetadata;
+use crate::retry::delay_before_retry;
+
+/// How much data we send in each request of a resumable upload, unless told
+/// otherwise. Google recommends at least 8 MiB.
+const DEFAULT_UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Google requires upload chunks to be a multiple of this size.
+const UPLOAD_CHUNK_ALIGNMENT: usize = 256 * 1024;
+
+/// Options for `upload_file`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct UploadOptions {
+    /// The Cloud KMS key to encrypt our object with, if any.
+    kms_key: Option<KmsKeyName>,
+    /// How much data we send in each request.
+    chunk_size: usize,
+    /// Content type, content encoding and custom metadata for our object.
+    metadata: ObjectMetadata,
+}
+
+impl UploadOptions {
+    /// Create new upload options. `chunk_size` may be written as "16MiB",
+    /// etc., and must be a multiple of 256 KiB.
+    pub(crate) fn new(
+        kms_key: Option<KmsKeyName>,
+        chunk_size: Option<&str>,
+    ) -> Result<UploadOptions> {
+        let chunk_size = match chunk_size {
+            Some(s) => {
+                let size =
+                    s.parse::<HumanizedBytes>().map(|b| b.size()).map_err(|e| {
+                        format_err!("cannot parse upload_chunk_size {:?}: {}", s, e)
+                    })?;
+                if size == 0 || size % UPLOAD_CHUNK_ALIGNMENT != 0 {
+                    return Err(format_err!(
+                        "upload_chunk_size {:?} must be a multiple of 256KiB",
+                        s,
+                    ));
+                }
+                size
+            }
+            None => DEFAULT_UPLOAD_CHUNK_SIZE,
+        };
+        Ok(UploadOptions {
+            kms_key,
+            chunk_size,
+            metadata: ObjectMetadata::default(),
+        })
+    }
+
+    /// Set the content type, content encoding and custom metadata for our
+    /// object.
+    pub(crate) fn with_metadata(mut self, metadata: ObjectMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// The Cloud KMS key to encrypt our object with, if any.
+    pub(crate) fn kms_key(&self) -> Option<&KmsKeyName> {
+        self.kms_key.as_ref()
+    }
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        UploadOptions {
+            kms_key: None,
+            chunk_size: DEFAULT_UPLOAD_CHUNK_SIZE,
+            metadata: ObjectMetadata::default(),
+        }
+    }
+}
+
+#[test]
+fn upload_chunk_sizes_are_validated() {
+    let opt = UploadOptions::new(None, Some("16MiB")).unwrap();
+    assert_eq!(opt.chunk_size, 16 * 1024 * 1024);
+    assert_eq!(
+        UploadOptions::new(None, None).unwrap(),
+        UploadOptions::default(),
+    );
+    assert!(UploadOptions::new(None, Some("0")).is_err());
+    assert!(UploadOptions::new(None, Some("1MB")).is_err());
+    assert!(UploadOptions::new(None, Some("lots")).is_err());
+}
 
 /// Parameters for an upload query.
 #[derive(Debug, Serialize)]
@@ -22,44 +111,136 @@ struct UploadQuery {
 
     /// The name of the object we're creating.
     name: String,
+
+    /// The Cloud KMS key to encrypt our object with, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kms_key_name: Option<String>,
+}
+
+/// Metadata for the object we're creating, sent when we start an upload.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadBody<'a> {
+    /// The `Content-Type` of our object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<&'a str>,
+
+    /// The `Content-Encoding` of our object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_encoding: Option<&'a str>,
+
+    /// Custom metadata for our object.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<&'a str, &'a str>,
+}
+
+impl<'a> UploadBody<'a> {
+    /// Build an upload body from `metadata`.
+    fn new(metadata: &'a ObjectMetadata) -> Self {
+        UploadBody {
+            content_type: metadata.content_type(),
+            content_encoding: metadata.content_encoding(),
+            metadata: metadata
+                .custom()
+                .iter()
+                .map(|(key, value)| (&key[..], &value[..]))
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn upload_body_includes_metadata() {
+    use crate::object_metadata::CustomMetadata;
+
+    let metadata = ObjectMetadata::new(
+        Some("text/csv".to_owned()),
+        None,
+        Some("source=etl".parse::<CustomMetadata>().unwrap()),
+    )
+    .unwrap();
+    assert_eq!(
+        serde_json::to_value(UploadBody::new(&metadata)).unwrap(),
+        serde_json::json!({
+            "contentType": "text/csv",
+            "metadata": { "source": "etl" },
+        }),
+    );
+    let metadata = ObjectMetadata::default();
+    assert_eq!(
+        serde_json::to_value(UploadBody::new(&metadata)).unwrap(),
+        serde_json::json!({}),
+    );
 }
 
 /// Upload `data` as a file at `url`.
 ///
-/// Docs: https://cloud.google.com/storage/docs/json_api/v1/objects/insert
+/// We use a resumable upload, sending `opt.chunk_size` bytes at a time, so
+/// that a network error only forces us to resend the current chunk, and not
+/// the whole file.
 ///
-/// TODO: Support https://cloud.google.com/storage/docs/performing-resumable-uploads.
+/// Docs: https://cloud.google.com/storage/docs/performing-resumable-uploads
 pub(crate) async fn upload_file<'a>(
     ctx: &'a Context,
     data: BoxStream<BytesMut>,
     file_url: &'a Url,
+    opt: &'a UploadOptions,
 ) -> Result<StorageObject> {
     debug!(ctx.log(), "streaming to {}", file_url);
     let (bucket, object) = parse_gs_url(file_url)?;
 
     // Compute a running CRC32 sum.
     let (stream, crc32c_reciever) = Crc32cStream::new(data);
+    let mut stream = stream.boxed();
 
-    // Post our data.
+    // Start our upload session.
     let url = format!(
         "https://storage.googleapis.com/upload/storage/v1/b/{}/o",
         percent_encode(&bucket),
     );
     let query = UploadQuery {
-        upload_type: "media",
+        upload_type: "resumable",
         if_generation_match: 0,
         name: object.clone(),
+        kms_key_name: opt.kms_key().map(|key| key.as_str().to_owned()),
     };
     let client = Client::new(&ctx).await?;
-    client
-        .post_stream(
-            ctx.clone(),
-            &url,
-            query,
-            idiomatic_bytes_stream(&ctx, stream.boxed()),
-        )
+    let body = UploadBody::new(&opt.metadata);
+    let session_url = client
+        .start_resumable_upload(ctx, &url, query, body)
         .await?;
 
+    // Send our data one chunk at a time. Every chunk except the last must be
+    // exactly `opt.chunk_size` bytes.
+    let mut buffer = BytesMut::new();
+    let mut offset = 0;
+    let mut finished = false;
+    let obj = loop {
+        while !finished && buffer.len() < opt.chunk_size {
+            match stream.next().await {
+                Some(bytes) => buffer.extend_from_slice(&bytes?),
+                None => finished = true,
+            }
+        }
+        let chunk = if finished {
+            buffer.split().freeze()
+        } else {
+            buffer.split_to(opt.chunk_size).freeze()
+        };
+        let chunk_len = u64::try_from(chunk.len())?;
+        let total = if finished {
+            Some(offset + chunk_len)
+        } else {
+            None
+        };
+        let result =
+            upload_chunk(ctx, &client, &session_url, offset, chunk, total).await?;
+        if let Some(obj) = result {
+            break obj;
+        }
+        offset += chunk_len;
+    };
+
     // Wait for our computed hash code.
     let hasher = crc32c_reciever
         .await
@@ -67,12 +248,6 @@ pub(crate) async fn upload_file<'a>(
     let crc32c = hasher.finish_encoded();
 
     // Verify that our uploaded file has the right checksum.
-    let obj_url = format!(
-        "https://storage.googleapis.com/storage/v1/b/{}/o/{}",
-        percent_encode(&bucket),
-        percent_encode(&object),
-    );
-    let obj: StorageObject = client.get(ctx, &obj_url, NoQuery).await?;
     if obj.crc32c == crc32c {
         Ok(obj)
     } else {
@@ -82,3 +257,119 @@ pub(crate) async fn upload_file<'a>(
         ))
     }
 }
+
+/// Upload `chunk`, which starts at `offset` in our file. If we know the `total`
+/// size of the file, this is the last chunk, and we return the finished
+/// object.
+///
+/// If a request fails temporarily, or the server only saves part of our chunk,
+/// we resend whatever the server is missing.
+async fn upload_chunk(
+    ctx: &Context,
+    client: &Client,
+    session_url: &Url,
+    offset: u64,
+    chunk: Bytes,
+    total: Option<u64>,
+) -> Result<Option<StorageObject>> {
+    let end = offset + u64::try_from(chunk.len())?;
+    let max_retries = max_retries()?;
+    let mut committed = offset;
+    let mut retries = 0;
+    loop {
+        let remaining = chunk.slice(usize::try_from(committed - offset)?..);
+        let range = content_range(committed, u64::try_from(remaining.len())?, total);
+        let status = client
+            .put_upload_chunk(ctx, session_url, &range, remaining)
+            .await?;
+        match status {
+            UploadStatus::Complete(obj) if total.is_some() => return Ok(Some(obj)),
+            UploadStatus::Complete(_) => {
+                return Err(format_err!(
+                    "upload finished before we sent all our data"
+                ));
+            }
+            UploadStatus::Incomplete { committed: saved }
+                if saved == end && total.is_none() =>
+            {
+                return Ok(None);
+            }
+            UploadStatus::Incomplete { committed: saved }
+                if saved > committed && saved < end =>
+            {
+                trace!(ctx.log(), "server saved {} of {} bytes", saved, end);
+                committed = saved;
+            }
+            UploadStatus::Incomplete { committed: saved } => {
+                return Err(format_err!(
+                    "expected server to save {} bytes, but it saved {}",
+                    end,
+                    saved,
+                ));
+            }
+            UploadStatus::FailedTemporarily(err) => {
+                if retries >= max_retries {
+                    return Err(err);
+                }
+                retries += 1;
+                warn!(
+                    ctx.log(),
+                    "retrying upload chunk after error (retry {}/{}): {}",
+                    retries,
+                    max_retries,
+                    err,
+                );
+                delay_for(delay_before_retry(retries)).await;
+
+                // Ask the server how much it has saved, so we know where to
+                // start again.
+                let status = client
+                    .put_upload_chunk::<StorageObject>(
+                        ctx,
+                        session_url,
+                        "bytes */*",
+                        Bytes::new(),
+                    )
+                    .await?;
+                match status {
+                    UploadStatus::Complete(obj) if total.is_some() => {
+                        return Ok(Some(obj))
+                    }
+                    UploadStatus::Incomplete { committed: saved }
+                        if saved >= offset && saved <= end =>
+                    {
+                        committed = saved;
+                        if saved == end && total.is_none() {
+                            return Ok(None);
+                        }
+                    }
+                    // Either this is a temporary failure and we'll retry, or
+                    // the server is confused and our next request will fail.
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Build a `Content-Range` header for `len` bytes starting at `start`. If we
+/// know the `total` size of the file, include it, which tells the server that
+/// this is the last chunk.
+fn content_range(start: u64, len: u64, total: Option<u64>) -> String {
+    let total = match total {
+        Some(total) => total.to_string(),
+        None => "*".to_owned(),
+    };
+    if len == 0 {
+        format!("bytes */{}", total)
+    } else {
+        format!("bytes {}-{}/{}", start, start + len - 1, total)
+    }
+}
+
+#[test]
+fn content_ranges_describe_chunks() {
+    assert_eq!(content_range(0, 10, None), "bytes 0-9/*");
+    assert_eq!(content_range(10, 5, Some(15)), "bytes 10-14/15");
+    assert_eq!(content_range(15, 0, Some(15)), "bytes */15");
+}
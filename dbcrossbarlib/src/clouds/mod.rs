@@ -1,4 +1,8 @@
 //! Interfaces to various clouds.
 
+#[cfg(feature = "s3")]
 pub(crate) mod aws;
+#[cfg(feature = "abfss")]
+pub(crate) mod azure;
+#[cfg(feature = "gs")]
 pub(crate) mod gcloud;
@@ -0,0 +1,235 @@
+//! Compressing and decompressing data streams on the fly.
+//!
+//! Drivers which read and write files can use this to handle `*.csv.gz` and
+//! similar files. We detect compressed input files using their extensions, and
+//! we compress output files when asked to using `--to-arg=compression=$CODEC`.
+
+use serde::Deserialize;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::common::*;
+use crate::transform::spawn_sync_transform;
+
+/// The compression formats we support.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Compression {
+    /// `gzip` compression, with the extension `.gz`.
+    Gzip,
+    /// `zstd` compression, with the extension `.zst`.
+    Zstd,
+    /// `bzip2` compression, with the extension `.bz2`.
+    Bzip2,
+    /// `xz` compression, with the extension `.xz`.
+    Xz,
+}
+
+impl Compression {
+    /// All the compression formats we support.
+    const ALL: &'static [Compression] = &[
+        Compression::Gzip,
+        Compression::Zstd,
+        Compression::Bzip2,
+        Compression::Xz,
+    ];
+
+    /// The file extension used by this compression format, including the
+    /// leading `.`.
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+            Compression::Bzip2 => ".bz2",
+            Compression::Xz => ".xz",
+        }
+    }
+
+    /// Detect the compression used by a file, based on its name or URL.
+    pub(crate) fn for_file_name(file_name: &str) -> Option<Compression> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|c| file_name.ends_with(c.extension()))
+    }
+
+    /// Detect the compression used by a local file, based on its name.
+    pub(crate) fn for_path(path: &Path) -> Option<Compression> {
+        Self::for_file_name(&path.to_string_lossy())
+    }
+
+    /// Remove any compression extension from `path`, so that `data.csv.gz`
+    /// becomes `data.csv`.
+    pub(crate) fn strip_extension(path: &Path) -> PathBuf {
+        match Self::for_path(path) {
+            Some(_) => path.with_extension(""),
+            None => path.to_owned(),
+        }
+    }
+
+    /// Add our extension to `file_name`.
+    pub(crate) fn add_extension(self, file_name: &str) -> String {
+        format!("{}{}", file_name, self.extension())
+    }
+
+    /// Wrap `rdr` in a synchronous decoder for this compression format.
+    pub(crate) fn decoder<'a, R>(self, rdr: R) -> Result<Box<dyn Read + Send + 'a>>
+    where
+        R: Read + Send + 'a,
+    {
+        Ok(match self {
+            // Handle files made by concatenating multiple compressed files,
+            // which is easy to do and fairly common.
+            Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(rdr)),
+            Compression::Zstd => Box::new(
+                zstd::stream::read::Decoder::new(rdr)
+                    .context("cannot create zstd decoder")?,
+            ),
+            Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(rdr)),
+            Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(rdr)),
+        })
+    }
+
+    /// Decompress all the data in `rdr`, and write it to `wtr`.
+    pub(crate) fn decompress<R, W>(self, rdr: R, mut wtr: W) -> Result<()>
+    where
+        R: Read + Send,
+        W: Write,
+    {
+        let mut decoder = self.decoder(rdr)?;
+        io::copy(&mut decoder, &mut wtr)
+            .with_context(|_| format!("error decompressing {:?} data", self))?;
+        wtr.flush().context("error flushing output")?;
+        Ok(())
+    }
+
+    /// Compress all the data in `rdr`, and write it to `wtr`.
+    pub(crate) fn compress<R, W>(self, mut rdr: R, wtr: W) -> Result<()>
+    where
+        R: Read,
+        W: Write,
+    {
+        let mut wtr = match self {
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(wtr, flate2::Compression::default());
+                io::copy(&mut rdr, &mut encoder)?;
+                encoder.finish()?
+            }
+            Compression::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(wtr, 0)?;
+                io::copy(&mut rdr, &mut encoder)?;
+                encoder.finish()?
+            }
+            Compression::Bzip2 => {
+                let mut encoder =
+                    bzip2::write::BzEncoder::new(wtr, bzip2::Compression::default());
+                io::copy(&mut rdr, &mut encoder)?;
+                encoder.finish()?
+            }
+            Compression::Xz => {
+                let mut encoder = xz2::write::XzEncoder::new(wtr, 6);
+                io::copy(&mut rdr, &mut encoder)?;
+                encoder.finish()?
+            }
+        };
+        wtr.flush().context("error flushing output")?;
+        Ok(())
+    }
+
+    /// Decompress `data` in a background thread.
+    pub(crate) fn decompress_stream(
+        self,
+        ctx: &Context,
+        data: BoxStream<BytesMut>,
+    ) -> Result<BoxStream<BytesMut>> {
+        spawn_sync_transform(
+            ctx.clone(),
+            format!("decompress_{:?}", self).to_lowercase(),
+            data,
+            move |_ctx, rdr, wtr| self.decompress(rdr, wtr),
+        )
+    }
+
+    /// Compress `data` in a background thread.
+    pub(crate) fn compress_stream(
+        self,
+        ctx: &Context,
+        data: BoxStream<BytesMut>,
+    ) -> Result<BoxStream<BytesMut>> {
+        spawn_sync_transform(
+            ctx.clone(),
+            format!("compress_{:?}", self).to_lowercase(),
+            data,
+            move |_ctx, rdr, wtr| {
+                self.compress(rdr, wtr)
+                    .with_context(|_| format!("error compressing {:?} data", self))?;
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Decompress `data` if `file_name` has the extension of a compression format
+/// we support. Otherwise, return it unchanged.
+pub(crate) fn decompress_stream_for_file_name(
+    ctx: &Context,
+    file_name: &str,
+    data: BoxStream<BytesMut>,
+) -> Result<BoxStream<BytesMut>> {
+    match Compression::for_file_name(file_name) {
+        Some(compression) => {
+            debug!(
+                ctx.log(),
+                "decompressing {} as {:?}", file_name, compression
+            );
+            compression.decompress_stream(ctx, data)
+        }
+        None => Ok(data),
+    }
+}
+
+#[test]
+fn detect_compression_from_file_names() {
+    let examples = &[
+        ("dir/data.csv.gz", Some(Compression::Gzip)),
+        ("s3://b/data.csv.zst", Some(Compression::Zstd)),
+        ("gs://b/data.csv.bz2", Some(Compression::Bzip2)),
+        ("data.tsv.xz", Some(Compression::Xz)),
+        ("data.csv", None),
+        ("gzip.csv", None),
+    ];
+    for &(file_name, expected) in examples {
+        assert_eq!(Compression::for_file_name(file_name), expected);
+    }
+    assert_eq!(Compression::Zstd.add_extension("a.csv"), "a.csv.zst");
+    assert_eq!(
+        Compression::strip_extension(Path::new("dir/a.tsv.xz")),
+        Path::new("dir/a.tsv"),
+    );
+    assert_eq!(
+        Compression::strip_extension(Path::new("dir/a.csv")),
+        Path::new("dir/a.csv"),
+    );
+}
+
+#[test]
+fn compression_roundtrip() {
+    let input = "a,b\n1,2\n".repeat(1000);
+    for &compression in Compression::ALL {
+        let mut compressed = vec![];
+        compression
+            .compress(input.as_bytes(), &mut compressed)
+            .unwrap();
+        assert!(compressed.len() < input.len());
+
+        // Concatenated files should decompress as a single file.
+        let mut doubled = compressed.clone();
+        doubled.extend_from_slice(&compressed);
+        let mut output = vec![];
+        compression.decompress(&doubled[..], &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), input.repeat(2));
+    }
+}
@@ -51,6 +51,7 @@ pub(crate) fn concatenate_csv_streams(
     // Build our combined `CsvStream`.
     let new_csv_stream = CsvStream {
         name: "combined".to_owned(),
+        metadata: StreamMetadata::default(),
         data: receiver.boxed(),
     };
 
@@ -208,6 +209,10 @@ fn csv_header_length_handles_corner_cases() {
     assert_eq!(csv_header_length(b"a,b,c\nd,e,f\n").unwrap(), Some(6));
     assert_eq!(csv_header_length(b"a,b,c\r\n").unwrap(), Some(7));
 
+    // We run after the CSV driver has converted any other delimiters to
+    // commas, but tab-delimited headers should work fine, too.
+    assert_eq!(csv_header_length(b"a\tb\nc\td\n").unwrap(), Some(4));
+
     // If we wanted to be more clever, we could handle quoted headers with
     // embedded newlines, and other such complications.
     assert!(csv_header_length(b"a,\"\n\",c\n").is_err());
@@ -87,6 +87,16 @@ impl Key<'_> {
         Self::global("temporary")
     }
 
+    /// A key for accessing `concurrency_limit`.
+    pub fn concurrency_limit() -> Key<'static> {
+        Self::global("concurrency_limit")
+    }
+
+    /// A key for accessing `bandwidth_limit`.
+    pub fn bandwidth_limit() -> Key<'static> {
+        Self::global("bandwidth_limit")
+    }
+
     /// A top-level configuration key.
     pub(crate) fn global(key: &str) -> Key<'_> {
         Key { key }
@@ -167,11 +177,37 @@ impl Configuration {
         Ok(())
     }
 
+    /// The path to our configuration file. This file may not exist.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     /// Return a list of places to store temporary data.
     pub fn temporaries(&self) -> Result<Vec<String>> {
         self.string_array(&Key::global("temporary"))
     }
 
+    /// Return a list of `PREFIX=N` limits on how many `dbcrossbar` processes
+    /// on this host may write to destinations starting with `PREFIX` at once.
+    pub fn concurrency_limits(&self) -> Result<Vec<String>> {
+        self.string_array(&Key::concurrency_limit())
+    }
+
+    /// Return the maximum bandwidth, like `50MB`, to be shared by all
+    /// `dbcrossbar` processes on this host, if any.
+    pub fn bandwidth_limit(&self) -> Result<Option<String>> {
+        let mut limits = self.string_array(&Key::bandwidth_limit())?;
+        match limits.len() {
+            0 => Ok(None),
+            1 => Ok(limits.pop()),
+            _ => Err(format_err!(
+                "expected at most one {} in {}",
+                Key::bandwidth_limit(),
+                self.path.display(),
+            )),
+        }
+    }
+
     /// Get an array of strings from our config file.
     fn string_array(&self, key: &Key<'_>) -> Result<Vec<String>> {
         let mut temps = vec![];
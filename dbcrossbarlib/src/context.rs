@@ -1,9 +1,12 @@
 //! Logging and error-handling context.
 
 use slog::{OwnedKV, SendSyncRefUnwindSafeKV};
+use std::sync::Arc;
 use tokio::process::Child;
 
 use crate::common::*;
+use crate::process::wait_for_process;
+use crate::recording::Recorder;
 
 /// Context shared by our various asynchronous operations.
 #[derive(Debug, Clone)]
@@ -13,6 +16,8 @@ pub struct Context {
     /// To report asynchronous errors anywhere in the application, send them to
     /// this channel.
     error_sender: mpsc::Sender<Error>,
+    /// If present, record or replay our interactions with external services.
+    recorder: Option<Arc<Recorder>>,
 }
 
 impl Context {
@@ -21,7 +26,11 @@ impl Context {
     /// fails.
     pub fn create(log: Logger) -> (Self, BoxFuture<()>) {
         let (error_sender, mut receiver) = mpsc::channel(1);
-        let context = Context { log, error_sender };
+        let context = Context {
+            log,
+            error_sender,
+            recorder: None,
+        };
         let worker_future = async move {
             match receiver.next().await {
                 // All senders have shut down correctly.
@@ -53,6 +62,18 @@ impl Context {
         Self::create(log)
     }
 
+    /// Record or replay our interactions with external services using
+    /// `recorder`. This should be called before cloning this context.
+    pub fn with_recorder(mut self, recorder: Arc<Recorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Get the recorder associated with this context, if any.
+    pub fn recorder(&self) -> Option<&Arc<Recorder>> {
+        self.recorder.as_ref()
+    }
+
     /// Get the logger associated with this context.
     pub fn log(&self) -> &Logger {
         &self.log
@@ -67,6 +88,7 @@ impl Context {
         Context {
             log: self.log.new(log_kv),
             error_sender: self.error_sender.clone(),
+            recorder: self.recorder.clone(),
         }
     }
 
@@ -92,15 +114,11 @@ impl Context {
     }
 
     /// Monitor an asynchrnous child process, and report any errors or non-zero
-    /// exit codes that occur.
+    /// exit codes that occur. If the child's standard error is piped, we copy
+    /// it to our log. If we exit while the child is still running, we kill it.
     pub fn spawn_process(&self, name: String, child: Child) {
-        let worker = async move {
-            match child.await {
-                Ok(ref status) if status.success() => Ok(()),
-                Ok(status) => Err(format_err!("{} failed with {}", name, status)),
-                Err(err) => Err(format_err!("{} failed with error: {}", name, err)),
-            }
-        };
+        let ctx = self.clone();
+        let worker = async move { wait_for_process(&ctx, &name, child, None).await };
         self.spawn_worker(worker.boxed());
     }
 }
@@ -0,0 +1,463 @@
+//! Sharing concurrency and bandwidth limits between `dbcrossbar` processes.
+//!
+//! When several `dbcrossbar` processes run on the same host (for example, from
+//! `cron`), they may collectively exceed a destination's quotas, or saturate
+//! the network. We coordinate using files in a shared directory. Each process
+//! registers itself, and claims a numbered slot file for each concurrency limit
+//! that applies to its destination. Processes rewrite their files regularly, so
+//! that files left behind by crashed processes eventually expire.
+
+use failure::Fail;
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+    sync::{Arc, Mutex as SyncMutex, Weak},
+    time::{Duration, SystemTime},
+};
+use tokio::{sync::Mutex, time::delay_for};
+
+use crate::common::*;
+use crate::config::{config_dir, Configuration};
+use crate::token_bucket::TokenBucket;
+
+/// How often we rewrite our files to show that we're still alive.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How old a file needs to be before we assume its owner has crashed.
+const STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// How often we check for a free slot while waiting.
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Find the directory we use to coordinate with other processes.
+fn coordination_dir() -> Result<PathBuf> {
+    match env::var_os("DBCROSSBAR_COORDINATION_DIR") {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(config_dir()?.join("coordination")),
+    }
+}
+
+/// A limit on how many processes may write to matching destinations at once.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct ConcurrencyLimit {
+    /// Destination locators starting with this prefix are subject to this
+    /// limit.
+    prefix: String,
+    /// The maximum number of processes which may write to matching
+    /// destinations at once.
+    max: usize,
+}
+
+impl FromStr for ConcurrencyLimit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let eq = s.rfind('=').ok_or_else(|| {
+            format_err!("expected concurrency limit {:?} to look like PREFIX=N", s)
+        })?;
+        let max = s[eq + 1..]
+            .parse::<usize>()
+            .with_context(|_| format!("could not parse concurrency limit {:?}", s))?;
+        if max == 0 {
+            return Err(format_err!("concurrency limit {:?} must be at least 1", s));
+        }
+        Ok(ConcurrencyLimit {
+            prefix: s[..eq].to_owned(),
+            max,
+        })
+    }
+}
+
+#[test]
+fn parse_concurrency_limit() {
+    assert_eq!(
+        "bigquery:my-project:=2"
+            .parse::<ConcurrencyLimit>()
+            .unwrap(),
+        ConcurrencyLimit {
+            prefix: "bigquery:my-project:".to_owned(),
+            max: 2,
+        },
+    );
+    for bad in &["bigquery:", "bigquery:=x", "bigquery:=0"] {
+        assert!(bad.parse::<ConcurrencyLimit>().is_err());
+    }
+}
+
+/// Parse a bandwidth like `50MB` or `1.5GiB`, and return bytes per second.
+fn parse_bandwidth(s: &str) -> Result<f64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or_else(|| s.len());
+    let (number, unit) = s.split_at(split);
+    let number = number
+        .parse::<f64>()
+        .with_context(|_| format!("could not parse bandwidth {:?}", s))?;
+    let multiplier = match unit.trim().trim_end_matches("/s") {
+        "" | "B" => 1.0,
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(format_err!("unknown unit in bandwidth {:?}", s)),
+    };
+    let bandwidth = number * multiplier;
+    if bandwidth > 0.0 {
+        Ok(bandwidth)
+    } else {
+        Err(format_err!("bandwidth {:?} must be positive", s))
+    }
+}
+
+#[test]
+fn parse_bandwidths() {
+    assert!((parse_bandwidth("50MB").unwrap() - 50e6).abs() < 1e-6);
+    assert!((parse_bandwidth("1.5 KiB/s").unwrap() - 1536.0).abs() < 1e-6);
+    assert!((parse_bandwidth("100").unwrap() - 100.0).abs() < 1e-6);
+    for bad in &["", "MB", "0MB", "10 furlongs"] {
+        assert!(parse_bandwidth(bad).is_err());
+    }
+}
+
+/// Coordinates with other `dbcrossbar` processes on this host.
+#[derive(Debug)]
+pub struct Coordinator {
+    /// The directory containing our coordination files.
+    dir: PathBuf,
+    /// Limits on how many processes may write to a destination.
+    concurrency_limits: Vec<ConcurrencyLimit>,
+    /// The total bandwidth that may be used by all processes, in bytes per
+    /// second.
+    bandwidth_limit: Option<f64>,
+}
+
+impl Coordinator {
+    /// Create a coordinator using the limits in `config`, or return `None` if
+    /// no limits are configured.
+    pub fn from_config(config: &Configuration) -> Result<Option<Self>> {
+        let concurrency_limits = config
+            .concurrency_limits()?
+            .iter()
+            .map(|limit| limit.parse::<ConcurrencyLimit>())
+            .collect::<Result<Vec<_>>>()?;
+        let bandwidth_limit = match config.bandwidth_limit()? {
+            Some(limit) => Some(parse_bandwidth(&limit)?),
+            None => None,
+        };
+        if concurrency_limits.is_empty() && bandwidth_limit.is_none() {
+            Ok(None)
+        } else {
+            Ok(Some(Coordinator {
+                dir: coordination_dir()?,
+                concurrency_limits,
+                bandwidth_limit,
+            }))
+        }
+    }
+
+    /// Wait until we're allowed to write to `dest`, and return a lease which
+    /// holds our place until it's dropped.
+    pub async fn acquire(&self, ctx: &Context, dest: &str) -> Result<Lease> {
+        let tag = format!("{}-{}", process::id(), TemporaryStorage::random_tag());
+        let inner = Arc::new(LeaseInner {
+            tag: tag.clone(),
+            files: SyncMutex::new(vec![]),
+        });
+
+        // Register this process, so that other processes know how many ways
+        // to divide our bandwidth.
+        let processes_dir = self.dir.join("processes");
+        create_dir(&processes_dir)?;
+        let process_file = processes_dir.join(&tag);
+        write_heartbeat(&process_file, &tag)?;
+        inner.add_file(process_file);
+        let bandwidth = match self.bandwidth_limit {
+            Some(total) => {
+                let share = total / f64::from(count_live_files(&processes_dir)?);
+                debug!(ctx.log(), "using {} bytes/second of {}", share, total);
+                let bucket = TokenBucket::new(share, BUFFER_SIZE);
+                Some((total, Arc::new(Mutex::new(bucket))))
+            }
+            None => None,
+        };
+
+        // Keep our files alive in the background, including while we wait for
+        // slots below.
+        // We don't use `ctx.spawn_worker`, because this runs until our lease is
+        // dropped, and it shouldn't keep our background workers alive.
+        tokio::spawn(heartbeat(
+            ctx.log().clone(),
+            Arc::downgrade(&inner),
+            processes_dir,
+            bandwidth.clone(),
+        ));
+
+        // Claim a slot for each concurrency limit that applies to `dest`. We
+        // always claim slots in the same order to avoid deadlocks.
+        let mut limits = self
+            .concurrency_limits
+            .iter()
+            .filter(|limit| dest.starts_with(&limit.prefix))
+            .collect::<Vec<_>>();
+        limits.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+        for limit in limits {
+            let slots_dir = self
+                .dir
+                .join("slots")
+                .join(format!("{:x}", md5::compute(limit.prefix.as_bytes())));
+            create_dir(&slots_dir)?;
+            let slot = claim_slot(ctx, &slots_dir, limit, &tag).await?;
+            inner.add_file(slot);
+        }
+
+        Ok(Lease {
+            _inner: inner,
+            bandwidth: bandwidth.map(|(_, bucket)| bucket),
+        })
+    }
+}
+
+/// Permission to write to a destination, and our share of the bandwidth. This
+/// is released when dropped.
+pub struct Lease {
+    /// Our coordination files.
+    _inner: Arc<LeaseInner>,
+    /// Our share of the bandwidth limit, if any.
+    bandwidth: Option<Arc<Mutex<TokenBucket>>>,
+}
+
+impl Lease {
+    /// Limit the bandwidth used by `streams` to our share of the bandwidth
+    /// limit, if any.
+    pub fn throttle_csv_streams(
+        &self,
+        streams: BoxStream<CsvStream>,
+    ) -> BoxStream<CsvStream> {
+        let bucket = match &self.bandwidth {
+            Some(bucket) => bucket.clone(),
+            None => return streams,
+        };
+        streams
+            .map_ok(move |stream| {
+                let bucket = bucket.clone();
+                let data = stream.data.and_then(move |bytes| {
+                    let bucket = bucket.clone();
+                    async move {
+                        bucket.lock().await.take(bytes.len()).await;
+                        Ok(bytes)
+                    }
+                });
+                CsvStream {
+                    name: stream.name,
+                    metadata: stream.metadata,
+                    data: data.boxed(),
+                }
+            })
+            .boxed()
+    }
+}
+
+/// The files that we need to keep alive, and to delete when we're done.
+struct LeaseInner {
+    /// A unique tag for this lease, which we write into our files.
+    tag: String,
+    /// Our coordination files.
+    files: SyncMutex<Vec<PathBuf>>,
+}
+
+impl LeaseInner {
+    /// Add a file to keep alive.
+    fn add_file(&self, path: PathBuf) {
+        self.files.lock().expect("lock poisoned").push(path);
+    }
+
+    /// Rewrite all our files to show that we're still alive.
+    fn refresh(&self, log: &Logger) {
+        for path in self.files.lock().expect("lock poisoned").iter() {
+            if let Err(err) = write_heartbeat(path, &self.tag) {
+                warn!(log, "could not update {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+impl Drop for LeaseInner {
+    fn drop(&mut self) {
+        for path in self.files.lock().expect("lock poisoned").iter() {
+            // There's nothing useful we can do about errors here, and stale
+            // files will expire eventually.
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Keep the files in `inner` alive until it's dropped, and adjust our share of
+/// the bandwidth as other processes come and go.
+async fn heartbeat(
+    log: Logger,
+    inner: Weak<LeaseInner>,
+    processes_dir: PathBuf,
+    bandwidth: Option<(f64, Arc<Mutex<TokenBucket>>)>,
+) {
+    loop {
+        delay_for(HEARTBEAT_INTERVAL).await;
+        match inner.upgrade() {
+            Some(inner) => inner.refresh(&log),
+            None => return,
+        }
+        if let Some((total, bucket)) = &bandwidth {
+            match count_live_files(&processes_dir) {
+                Ok(count) => {
+                    let share = total / f64::from(count);
+                    trace!(log, "using {} bytes/second of {}", share, total);
+                    bucket.lock().await.set_rate(share);
+                }
+                Err(err) => {
+                    warn!(log, "could not count dbcrossbar processes: {}", err)
+                }
+            }
+        }
+    }
+}
+
+/// Wait until we can claim one of the slots for `limit` in `dir`.
+async fn claim_slot(
+    ctx: &Context,
+    dir: &Path,
+    limit: &ConcurrencyLimit,
+    tag: &str,
+) -> Result<PathBuf> {
+    let mut logged = false;
+    loop {
+        for i in 0..limit.max {
+            let path = dir.join(format!("slot-{}", i));
+            remove_if_stale(&path)?;
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut f) => {
+                    f.write_all(tag.as_bytes()).with_context(|_| {
+                        format!("could not write {}", path.display())
+                    })?;
+                    debug!(
+                        ctx.log(),
+                        "claimed {} for {}",
+                        path.display(),
+                        limit.prefix
+                    );
+                    return Ok(path);
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => {
+                    return Err(err
+                        .context(format!("could not create {}", path.display()))
+                        .into())
+                }
+            }
+        }
+        if !logged {
+            info!(
+                ctx.log(),
+                "waiting for one of {} slots for {}", limit.max, limit.prefix,
+            );
+            logged = true;
+        }
+        delay_for(RETRY_INTERVAL).await;
+    }
+}
+
+/// Create `dir` if it doesn't exist.
+fn create_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|_| format!("could not create {}", dir.display()))?;
+    Ok(())
+}
+
+/// Write `tag` to `path`, updating its modification time.
+fn write_heartbeat(path: &Path, tag: &str) -> Result<()> {
+    fs::write(path, tag)
+        .with_context(|_| format!("could not write {}", path.display()))?;
+    Ok(())
+}
+
+/// Delete `path` if it hasn't been updated recently, because its owner has
+/// probably crashed.
+fn remove_if_stale(path: &Path) -> Result<bool> {
+    let modified = match fs::metadata(path).and_then(|m| m.modified()) {
+        Ok(modified) => modified,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => {
+            return Err(err
+                .context(format!("could not check {}", path.display()))
+                .into())
+        }
+    };
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_else(|_| Duration::from_secs(0));
+    if age > STALE_AFTER {
+        match fs::remove_file(path) {
+            Ok(()) => Ok(true),
+            // Somebody else removed it first.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(true),
+            Err(err) => Err(err
+                .context(format!("could not remove stale {}", path.display()))
+                .into()),
+        }
+    } else {
+        Ok(false)
+    }
+}
+
+/// Count the files in `dir` which aren't stale. This is always at least 1,
+/// because it's only called by registered processes.
+fn count_live_files(dir: &Path) -> Result<u32> {
+    let mut count = 0;
+    let entries = fs::read_dir(dir)
+        .with_context(|_| format!("could not list {}", dir.display()))?;
+    for entry in entries {
+        let entry =
+            entry.with_context(|_| format!("could not list {}", dir.display()))?;
+        if !remove_if_stale(&entry.path())? {
+            count += 1;
+        }
+    }
+    Ok(count.max(1))
+}
+
+#[test]
+fn concurrency_limits_are_shared() {
+    use tokio::time::timeout;
+
+    let dir = tempfile::tempdir().unwrap();
+    let coordinator = Coordinator {
+        dir: dir.path().to_owned(),
+        concurrency_limits: vec!["bigquery:=1".parse().unwrap()],
+        bandwidth_limit: None,
+    };
+    let (ctx, worker_fut) = Context::create_for_test("concurrency_limits");
+    let cmd_fut = async move {
+        let lease = coordinator.acquire(&ctx, "bigquery:p:d.t").await?;
+
+        // Destinations without a limit don't need to wait.
+        let other = coordinator.acquire(&ctx, "csv:out.csv").await?;
+        drop(other);
+
+        // But a second `bigquery:` lease must wait for the first.
+        let waiting = timeout(
+            Duration::from_millis(100),
+            coordinator.acquire(&ctx, "bigquery:p:d.t2"),
+        )
+        .await;
+        assert!(waiting.is_err());
+        drop(lease);
+        let _second = coordinator.acquire(&ctx, "bigquery:p:d.t2").await?;
+        Ok(())
+    };
+    run_futures_with_runtime(cmd_fut.boxed(), worker_fut).unwrap();
+}
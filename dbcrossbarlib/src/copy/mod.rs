@@ -0,0 +1,307 @@
+//! Copying data from one locator to another.
+//!
+//! This is the engine behind `dbcrossbar cp`, exposed so that other programs
+//! can run copies and inspect the results.
+
+use std::time::{Duration, Instant};
+
+use crate::common::*;
+use crate::config::Configuration;
+use crate::coordination::Coordinator;
+use crate::rechunk::rechunk_csvs;
+use crate::recording::checksum_csv_streams;
+use crate::validate::validate_csv_streams;
+
+mod report;
+
+use self::report::StreamRecorder;
+pub use self::report::{CopyError, CopyReport, StreamOutcome, StreamReport};
+
+/// A function which will be called with each destination locator as soon as
+/// the destination driver reports it.
+pub type DestinationCallback = Box<dyn FnMut(&BoxLocator) -> Result<()> + Send>;
+
+/// Options for `copy`.
+pub struct CopyOptions {
+    /// The schema to use. If this is `None`, we use the schema of the source.
+    pub schema: Option<BoxLocator>,
+
+    /// Temporary directories, cloud storage buckets and datasets to use during
+    /// the transfer, in addition to any in our configuration.
+    pub temporaries: Vec<String>,
+
+    /// The approximate size of the CSV streams we should produce, in bytes.
+    pub stream_size: Option<usize>,
+
+    /// How many data streams should we attempt to copy in parallel?
+    pub max_streams: usize,
+
+    /// Should we check every value against our schema as we copy it? This
+    /// forces the data to pass through the local machine.
+    pub validate: bool,
+
+    /// Called with each destination locator as soon as it's available.
+    pub on_destination: Option<DestinationCallback>,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        CopyOptions {
+            schema: None,
+            temporaries: vec![],
+            stream_size: None,
+            max_streams: 4,
+            validate: false,
+            on_destination: None,
+        }
+    }
+}
+
+/// Copy data from `from_locator` to `to_locator`, and return a report
+/// describing what we did. If the copy fails, the returned `CopyError` will
+/// include a report describing what we did before the failure.
+pub async fn copy(
+    ctx: Context,
+    config: &Configuration,
+    from_locator: BoxLocator,
+    to_locator: BoxLocator,
+    source_args: SourceArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+    options: CopyOptions,
+) -> Result<CopyReport, CopyError> {
+    let started = Instant::now();
+    let mut report = CopyReport {
+        from_locator: from_locator.to_string(),
+        to_locator: to_locator.to_string(),
+        remote: false,
+        streams: vec![],
+        destinations: vec![],
+        warnings: vec![],
+        duration: Duration::default(),
+    };
+    let recorder = StreamRecorder::default();
+    let result = copy_helper(
+        ctx,
+        config,
+        from_locator,
+        to_locator,
+        source_args,
+        dest_args,
+        options,
+        &recorder,
+        &mut report,
+    )
+    .await;
+    report.streams = recorder.reports();
+    report.duration = started.elapsed();
+    match result {
+        Ok(()) => Ok(report),
+        Err(cause) => Err(CopyError { report, cause }),
+    }
+}
+
+/// Perform the actual work of `copy`, filling in `report` as we go.
+#[allow(clippy::too_many_arguments)]
+async fn copy_helper(
+    ctx: Context,
+    config: &Configuration,
+    from_locator: BoxLocator,
+    to_locator: BoxLocator,
+    source_args: SourceArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+    options: CopyOptions,
+    recorder: &StreamRecorder,
+    report: &mut CopyReport,
+) -> Result<()> {
+    let CopyOptions {
+        schema: schema_opt,
+        temporaries,
+        stream_size,
+        max_streams,
+        validate,
+        mut on_destination,
+    } = options;
+
+    // Figure out what table schema to use.
+    let schema = {
+        let schema_locator = schema_opt.as_ref().unwrap_or(&from_locator);
+        schema_locator
+            .schema(ctx.clone())
+            .await
+            .with_context(|_| format!("error reading schema from {}", schema_locator))?
+            .ok_or_else(|| {
+                format_err!("don't know how to read schema from {}", schema_locator)
+            })
+    }?;
+
+    // Can we short-circuit this particular copy using special features of the
+    // the source and destination, or do we need to pull the data down to the
+    // local machine? If the user passed `--stream-size`, we can only use a
+    // remote transfer if the source promises that it's already small enough.
+    // And if the user passed `--validate`, we need to see the data ourselves.
+    let supports_remote =
+        !validate && to_locator.supports_write_remote_data(from_locator.as_ref());
+    let size_hint = if supports_remote && stream_size.is_none() {
+        SizeHint::default()
+    } else {
+        size_hint(
+            &ctx,
+            from_locator.as_ref(),
+            source_args.clone(),
+            &mut report.warnings,
+        )
+        .await
+    };
+    let should_use_remote = supports_remote
+        && match (stream_size, size_hint.total_bytes) {
+            (None, _) => true,
+            (Some(stream_size), Some(total_bytes)) => {
+                total_bytes <= stream_size as u64
+            }
+            (Some(_), None) => false,
+        };
+    report.remote = should_use_remote;
+
+    // Don't run more parallel copies than we expect to have streams.
+    let expected_streams = match stream_size {
+        Some(stream_size) => size_hint.stream_count_after_rechunking(stream_size),
+        None => size_hint.stream_count,
+    };
+    let max_streams = match expected_streams {
+        Some(count) => max_streams.min(count.max(1)),
+        None => max_streams,
+    };
+
+    // Build our shared arguments.
+    let temporary_storage = TemporaryStorage::with_config(temporaries, config)?;
+    let shared_args =
+        SharedArguments::new(schema.clone(), temporary_storage.clone(), max_streams);
+
+    // Wait until other `dbcrossbar` processes on this host leave us room to
+    // write to our destination. We hold `lease` until we're done.
+    let lease = match Coordinator::from_config(config)? {
+        Some(coordinator) => {
+            Some(coordinator.acquire(&ctx, &to_locator.to_string()).await?)
+        }
+        None => None,
+    };
+
+    let from_locator_str = from_locator.to_string();
+    let to_locator_str = to_locator.to_string();
+    let destinations = &mut report.destinations;
+    let result: Result<()> = async {
+        let mut dests = if should_use_remote {
+            // Build a logging context.
+            let ctx = ctx.child(o!(
+                "from_locator" => from_locator_str.clone(),
+                "to_locator" => to_locator_str.clone(),
+            ));
+
+            // Perform a remote transfer.
+            debug!(ctx.log(), "performing remote data transfer");
+            let dests = to_locator
+                .write_remote_data(
+                    ctx,
+                    from_locator,
+                    shared_args,
+                    source_args,
+                    dest_args,
+                )
+                .await?;
+
+            // Convert our list of output locators into a stream.
+            stream::iter(dests).map(Ok).boxed()
+        } else {
+            // We have to transfer the data via the local machine, so read data
+            // from input.
+            debug!(ctx.log(), "performing local data transfer");
+
+            let input_ctx = ctx.child(o!("from_locator" => from_locator_str.clone()));
+            let mut data = from_locator
+                .local_data(input_ctx, shared_args.clone(), source_args)
+                .await?
+                .ok_or_else(|| {
+                    format_err!("don't know how to read data from {}", from_locator)
+                })?;
+
+            // Checksum our data if we're recording or replaying.
+            data = checksum_csv_streams(&ctx, data);
+
+            // Use no more than our share of any bandwidth limit.
+            if let Some(lease) = &lease {
+                data = lease.throttle_csv_streams(data);
+            }
+
+            // Check our data against the schema if asked to.
+            if validate {
+                data = validate_csv_streams(&ctx, &schema, data);
+            }
+
+            // Honor --stream-size if passed.
+            if let Some(stream_size) = stream_size {
+                data = rechunk_csvs(ctx.clone(), stream_size, data)?;
+            }
+
+            // Keep track of the streams we actually write.
+            data = recorder.record_csv_streams(data);
+
+            // Write data to output.
+            let output_ctx = ctx.child(o!("to_locator" => to_locator_str.clone()));
+            let result_stream = to_locator
+                .write_local_data(output_ctx, data, shared_args.clone(), dest_args)
+                .await?;
+
+            // Consume the stream of futures produced by `write_local_data`,
+            // allowing a certain degree of parallelism. This is where all the
+            // actual work happens, and this what controls how many "input
+            // driver" -> "output driver" connections are running at any given
+            // time.
+            result_stream
+                // Run up to `parallelism` futures in parallel.
+                .try_buffer_unordered(shared_args.max_streams())
+                .boxed()
+        };
+
+        // Collect our destinations as they finish, passing them to our
+        // callback.
+        while let Some(dest) = dests.next().await {
+            let dest = dest?;
+            if let Some(on_destination) = &mut on_destination {
+                on_destination(&dest)?;
+            }
+            destinations.push(dest.to_string());
+        }
+        Ok(())
+    }
+    .await;
+
+    // Delete any temporaries that our drivers asked us to clean up, whether or
+    // not the copy succeeded.
+    temporary_storage.delete_tracked(&ctx).await;
+    result?;
+    debug!(ctx.log(), "destination locators: {:?}", report.destinations);
+    drop(lease);
+    Ok(())
+}
+
+/// Ask `locator` how much data it expects to produce. Since this is only a
+/// hint, we log any errors and carry on without it.
+async fn size_hint(
+    ctx: &Context,
+    locator: &dyn Locator,
+    source_args: SourceArguments<Unverified>,
+    warnings: &mut Vec<String>,
+) -> SizeHint {
+    match locator.size_hint(ctx.clone(), source_args).await {
+        Ok(hint) => {
+            debug!(ctx.log(), "size hint for {}: {}", locator, hint);
+            hint
+        }
+        Err(err) => {
+            let warning = format!("could not get size hint for {}: {}", locator, err);
+            warn!(ctx.log(), "{}", warning);
+            warnings.push(warning);
+            SizeHint::default()
+        }
+    }
+}
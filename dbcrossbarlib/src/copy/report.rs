@@ -0,0 +1,272 @@
+//! Structured reports describing the results of a copy.
+
+use failure::Fail;
+use futures::task::{self, Poll};
+use serde_derive::Serialize;
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::common::*;
+
+/// A report describing a completed copy, suitable for displaying to users or
+/// serializing as JSON.
+#[derive(Clone, Debug, Serialize)]
+pub struct CopyReport {
+    /// The locator we copied from.
+    pub from_locator: String,
+
+    /// The locator we copied to.
+    pub to_locator: String,
+
+    /// Did we copy the data directly between remote systems? If so, the data
+    /// never passed through this machine, and `streams` will be empty.
+    pub remote: bool,
+
+    /// The CSV streams we copied.
+    pub streams: Vec<StreamReport>,
+
+    /// The destination locators reported by the destination driver.
+    pub destinations: Vec<String>,
+
+    /// Non-fatal problems we noticed during the copy.
+    pub warnings: Vec<String>,
+
+    /// How long the entire copy took.
+    pub duration: Duration,
+}
+
+impl CopyReport {
+    /// The total number of bytes we copied through this machine.
+    pub fn total_bytes(&self) -> u64 {
+        self.streams.iter().map(|s| s.bytes).sum()
+    }
+}
+
+/// An error which stopped a copy, with a report describing what we did before
+/// it failed.
+#[derive(Debug)]
+pub struct CopyError {
+    /// What we did before the copy failed. Streams which were still being
+    /// copied will be marked as `Failed` or `Abandoned`.
+    pub report: CopyReport,
+
+    /// The error which stopped the copy.
+    pub cause: Error,
+}
+
+impl fmt::Display for CopyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error copying {} to {}",
+            self.report.from_locator, self.report.to_locator,
+        )
+    }
+}
+
+impl Fail for CopyError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        Some(self.cause.as_fail())
+    }
+}
+
+/// Information about an individual CSV stream.
+#[derive(Clone, Debug, Serialize)]
+pub struct StreamReport {
+    /// The name of the stream.
+    pub name: String,
+
+    /// The original file path or object URL of this stream, if known.
+    pub source: Option<String>,
+
+    /// How many bytes of CSV data we read from this stream.
+    pub bytes: u64,
+
+    /// How long it took from when the stream was created until it was
+    /// finished.
+    pub duration: Duration,
+
+    /// What happened to this stream?
+    pub outcome: StreamOutcome,
+}
+
+/// What happened to a CSV stream.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum StreamOutcome {
+    /// We read the entire stream.
+    Completed,
+    /// Reading the stream failed with an error.
+    Failed {
+        /// The error message.
+        message: String,
+    },
+    /// The destination stopped reading this stream before it was finished.
+    Abandoned,
+}
+
+/// Collects `StreamReport` values for each CSV stream that passes through it.
+#[derive(Clone, Default)]
+pub(crate) struct StreamRecorder {
+    reports: Arc<Mutex<Vec<StreamReport>>>,
+}
+
+impl StreamRecorder {
+    /// Wrap `streams` so that we record what happens to each one.
+    pub(crate) fn record_csv_streams(
+        &self,
+        streams: BoxStream<CsvStream>,
+    ) -> BoxStream<CsvStream> {
+        let recorder = self.clone();
+        streams
+            .map_ok(move |stream| {
+                // Reserve a place for this stream's report, so that our reports
+                // appear in the order that the streams were created.
+                let index = {
+                    let mut reports = recorder.reports.lock().expect("lock poisoned");
+                    reports.push(StreamReport {
+                        name: stream.name.clone(),
+                        source: stream.metadata.source.clone(),
+                        bytes: 0,
+                        duration: Duration::default(),
+                        outcome: StreamOutcome::Abandoned,
+                    });
+                    reports.len() - 1
+                };
+                let data = RecordedStream {
+                    inner: stream.data,
+                    reports: recorder.reports.clone(),
+                    index,
+                    bytes: 0,
+                    started: Instant::now(),
+                    finished: false,
+                };
+                CsvStream {
+                    name: stream.name,
+                    metadata: stream.metadata,
+                    data: data.boxed(),
+                }
+            })
+            .boxed()
+    }
+
+    /// Return the reports we've collected so far.
+    pub(crate) fn reports(&self) -> Vec<StreamReport> {
+        self.reports.lock().expect("lock poisoned").clone()
+    }
+}
+
+/// A stream of CSV data which records what happened to it.
+struct RecordedStream {
+    inner: BoxStream<BytesMut>,
+    reports: Arc<Mutex<Vec<StreamReport>>>,
+    index: usize,
+    bytes: u64,
+    started: Instant,
+    finished: bool,
+}
+
+impl RecordedStream {
+    /// Record the final state of this stream.
+    fn finish(&mut self, outcome: StreamOutcome) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        // Don't panic if we're being dropped while some other thread panics.
+        if let Ok(mut reports) = self.reports.lock() {
+            let report = &mut reports[self.index];
+            report.bytes = self.bytes;
+            report.duration = self.started.elapsed();
+            report.outcome = outcome;
+        }
+    }
+}
+
+impl Stream for RecordedStream {
+    type Item = Result<BytesMut>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let result = self.inner.poll_next_unpin(cx);
+        match &result {
+            Poll::Ready(Some(Ok(bytes))) => {
+                self.bytes += bytes.len() as u64;
+            }
+            Poll::Ready(Some(Err(err))) => {
+                let message = err.to_string();
+                self.finish(StreamOutcome::Failed { message });
+            }
+            Poll::Ready(None) => self.finish(StreamOutcome::Completed),
+            Poll::Pending => {}
+        }
+        result
+    }
+}
+
+impl Drop for RecordedStream {
+    fn drop(&mut self) {
+        self.finish(StreamOutcome::Abandoned);
+    }
+}
+
+#[test]
+fn record_csv_streams_reports_outcomes() {
+    let (ctx, worker_fut) = Context::create_for_test("record_csv_streams");
+    let cmd_fut = async move {
+        debug!(ctx.log(), "testing record_csv_streams");
+        let csv_stream = |chunks: Vec<&'static [u8]>| CsvStream {
+            name: "data".to_owned(),
+            metadata: StreamMetadata::default(),
+            data: stream::iter(chunks.into_iter().map(|c| Ok(BytesMut::from(c))))
+                .boxed(),
+        };
+        let recorder = StreamRecorder::default();
+        let streams = stream::iter(vec![
+            Ok(csv_stream(vec![&b"a\n"[..], &b"1\n"[..]])),
+            Ok(csv_stream(vec![&b"a\n"[..], &b"2\n"[..]])),
+        ])
+        .boxed();
+        let mut streams = recorder.record_csv_streams(streams);
+
+        // Read all of our first stream, and only part of our second.
+        let first = streams.next().await.unwrap()?;
+        first.data.try_collect::<Vec<_>>().await?;
+        let mut second = streams.next().await.unwrap()?;
+        second.data.next().await;
+        drop(second);
+
+        let reports = recorder.reports();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].bytes, 4);
+        assert_eq!(reports[0].outcome, StreamOutcome::Completed);
+        assert_eq!(reports[1].bytes, 2);
+        assert_eq!(reports[1].outcome, StreamOutcome::Abandoned);
+        Ok(())
+    };
+    run_futures_with_runtime(cmd_fut.boxed(), worker_fut).unwrap();
+}
+
+#[test]
+fn copy_error_displays_locators_and_cause() {
+    let err = CopyError {
+        report: CopyReport {
+            from_locator: "csv:in.csv".to_owned(),
+            to_locator: "csv:out.csv".to_owned(),
+            remote: false,
+            streams: vec![],
+            destinations: vec![],
+            warnings: vec![],
+            duration: Duration::default(),
+        },
+        cause: format_err!("disk full"),
+    };
+    assert_eq!(err.to_string(), "error copying csv:in.csv to csv:out.csv");
+    assert_eq!(Fail::cause(&err).unwrap().to_string(), "disk full");
+}
@@ -1,15 +1,18 @@
 //! Support for looking up credentials.
 
 use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use serde::Deserialize;
 use std::{
     collections::HashMap,
     env, fmt,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::Stdio,
     time::{Duration, Instant},
 };
-use tokio::{fs, sync::Mutex};
+use tokio::{fs, process::Command, sync::Mutex};
 
 use crate::common::*;
 use crate::config::config_dir;
@@ -83,12 +86,18 @@ impl CredentialsManager {
         let mut sources = HashMap::new();
         let config_dir = config_dir()?;
 
-        // Specify how to connect to AWS.
-        let aws = EnvCredentialsSource::new(vec![
-            EnvMapping::required("access_key_id", "AWS_ACCESS_KEY_ID"),
-            EnvMapping::required("secret_access_key", "AWS_SECRET_ACCESS_KEY"),
-            EnvMapping::optional("session_token", "AWS_SESSION_TOKEN"),
-            EnvMapping::required("default_region", "AWS_DEFAULT_REGION"),
+        // Specify how to connect to AWS. We search the same places as the
+        // `aws` CLI, in the same order.
+        let aws = CredentialsSources::new(vec![
+            EnvCredentialsSource::new(vec![
+                EnvMapping::required("access_key_id", "AWS_ACCESS_KEY_ID"),
+                EnvMapping::required("secret_access_key", "AWS_SECRET_ACCESS_KEY"),
+                EnvMapping::optional("session_token", "AWS_SESSION_TOKEN"),
+            ])
+            .boxed(),
+            AwsProfileCredentialsSource.boxed(),
+            AwsContainerCredentialsSource.boxed(),
+            AwsInstanceCredentialsSource.boxed(),
         ]);
         sources.insert("aws".to_owned(), Mutex::new(aws.boxed()));
 
@@ -135,12 +144,54 @@ impl CredentialsManager {
         )]);
         sources.insert("shopify".to_owned(), Mutex::new(shopify_secret.boxed()));
 
+        // Specify how to get an OAuth2 token for Azure Storage.
+        let azure_storage = CredentialsSources::new(vec![
+            EnvCredentialsSource::new(vec![EnvMapping::required(
+                "access_token",
+                "AZURE_STORAGE_ACCESS_TOKEN",
+            )])
+            .boxed(),
+            AzureCliCredentialsSource.boxed(),
+        ]);
+        sources.insert(
+            "azure_storage".to_owned(),
+            Mutex::new(azure_storage.boxed()),
+        );
+
         let cache = Mutex::new(HashMap::new());
         Ok(CredentialsManager { sources, cache })
     }
 
     /// Look up the credential `name` and return it.
     pub(crate) async fn get(&self, name: &str) -> Result<Credentials> {
+        if let Some(c) = self.try_get(name).await? {
+            Ok(c)
+        } else {
+            // Explain to the user how they could have specified this
+            // credential.
+            Err(format_err!(
+                "could not find credentials for {} in any of:\n{}",
+                name,
+                self.describe_sources(name).await?,
+            ))
+        }
+    }
+
+    /// Explain where we look for the credential `name`. The `Display` method
+    /// on `CredentialsSource` is responsible for explaining how to set
+    /// credentials.
+    pub(crate) async fn describe_sources(&self, name: &str) -> Result<String> {
+        let source = self
+            .sources
+            .get(name)
+            .ok_or_else(|| format_err!("unknown credential {:?}", name))?;
+        let source = source.lock().await;
+        Ok(source.to_string())
+    }
+
+    /// Look up the credential `name` and return it, or return `None` if it has
+    /// not been configured.
+    pub(crate) async fn try_get(&self, name: &str) -> Result<Option<Credentials>> {
         // Look up our source and lock it. We _must_ do this before locking
         // `cache`.
         let source = self
@@ -154,7 +205,7 @@ impl CredentialsManager {
         let credentials: Option<Credentials> =
             self.cache.lock().await.get(name).map(|c| c.to_owned());
         match credentials {
-            Some(c) if !c.needs_refresh() => return Ok(c),
+            Some(c) if !c.needs_refresh() => return Ok(Some(c)),
             _ => {}
         }
 
@@ -162,16 +213,9 @@ impl CredentialsManager {
         if let Some(c) = source.get_credentials().await? {
             // Cache it and return it.
             self.cache.lock().await.insert(name.to_owned(), c.clone());
-            Ok(c)
+            Ok(Some(c))
         } else {
-            // Explain to the user how they could have specified this
-            // credential. The `Display` method on `CredentialsSource` is
-            // responsible for explaining how to set credentials.
-            Err(format_err!(
-                "could not find credentials for {} in any of:\n{}",
-                name,
-                source,
-            ))
+            Ok(None)
         }
     }
 }
@@ -300,7 +344,7 @@ impl CredentialsSource for EnvCredentialsSource {
 
 /// Look up an environment variable by name, returning `Ok(None)` if it does not
 /// exist.
-fn try_var(name: &str) -> Result<Option<String>> {
+pub(crate) fn try_var(name: &str) -> Result<Option<String>> {
     match env::var(name) {
         Ok(value) => Ok(Some(value)),
         Err(env::VarError::NotPresent) => Ok(None),
@@ -365,6 +409,439 @@ impl CredentialsSource for FileCredentialsSource {
     }
 }
 
+/// Ask the `az` CLI for an Azure Storage access token, using whatever account
+/// the user logged in with using `az login`.
+#[derive(Debug)]
+struct AzureCliCredentialsSource;
+
+/// The parts of `az account get-access-token` output that we use.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzureCliAccessToken {
+    access_token: String,
+    /// When the token expires, in local time.
+    expires_on: Option<String>,
+}
+
+impl fmt::Display for AzureCliCredentialsSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "- The Azure CLI (run `az login` first)")
+    }
+}
+
+#[async_trait]
+impl CredentialsSource for AzureCliCredentialsSource {
+    async fn get_credentials(&self) -> Result<Option<Credentials>> {
+        let output = Command::new("az")
+            .args(&[
+                "account",
+                "get-access-token",
+                "--resource",
+                "https://storage.azure.com/",
+                "--output",
+                "json",
+            ])
+            .stderr(Stdio::null())
+            .output()
+            .await;
+        let output = match output {
+            // `az` is installed and the user is logged in.
+            Ok(output) if output.status.success() => output,
+            // `az` is not installed, or the user isn't logged in, so keep
+            // looking.
+            Ok(_) => return Ok(None),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(format_err!("error running `az`: {}", err)),
+        };
+        let token = serde_json::from_slice::<AzureCliAccessToken>(&output.stdout)
+            .context("could not parse `az account get-access-token` output")?;
+
+        // Work out when this token expires, if we can.
+        let expires = token
+            .expires_on
+            .as_ref()
+            .and_then(|expires_on| {
+                NaiveDateTime::parse_from_str(expires_on, "%Y-%m-%d %H:%M:%S%.f").ok()
+            })
+            .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+            .map(|expires_on| {
+                let remaining = (expires_on.with_timezone(&Utc) - Utc::now())
+                    .to_std()
+                    .unwrap_or_else(|_| Duration::from_secs(0));
+                Instant::now() + remaining
+            });
+
+        let mut data = HashMap::new();
+        data.insert("access_token".to_owned(), token.access_token);
+        Ok(Some(Credentials { data, expires }))
+    }
+}
+
+/// Look up AWS credentials in a profile in `~/.aws/credentials` or
+/// `~/.aws/config`, the files used by the `aws` CLI.
+///
+/// We only support profiles containing access keys. Profiles which use
+/// `role_arn`, `sso_*` or `credential_process` are not supported.
+#[derive(Debug)]
+struct AwsProfileCredentialsSource;
+
+impl fmt::Display for AwsProfileCredentialsSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "- The profile AWS_PROFILE (or `default`) in ~/.aws/credentials or \
+             ~/.aws/config",
+        )
+    }
+}
+
+#[async_trait]
+impl CredentialsSource for AwsProfileCredentialsSource {
+    async fn get_credentials(&self) -> Result<Option<Credentials>> {
+        let explicit_profile = try_var("AWS_PROFILE")?;
+        let profile = explicit_profile.as_deref().unwrap_or("default");
+        let mut values = read_aws_profile(profile).await?;
+        let mut data = HashMap::new();
+        for &(key, name) in &[
+            ("access_key_id", "aws_access_key_id"),
+            ("secret_access_key", "aws_secret_access_key"),
+            ("session_token", "aws_session_token"),
+        ] {
+            if let Some(value) = values.remove(name) {
+                data.insert(key.to_owned(), value);
+            }
+        }
+        if data.contains_key("access_key_id") && data.contains_key("secret_access_key")
+        {
+            Ok(Some(Credentials {
+                data,
+                expires: None,
+            }))
+        } else if explicit_profile.is_some() {
+            Err(format_err!(
+                "AWS_PROFILE is {:?}, but could not find aws_access_key_id and \
+                 aws_secret_access_key for that profile in ~/.aws/credentials or \
+                 ~/.aws/config",
+                profile,
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Read the keys and values for `profile` from `~/.aws/credentials` and
+/// `~/.aws/config`.
+pub(crate) async fn read_aws_profile(
+    profile: &str,
+) -> Result<HashMap<String, String>> {
+    let credentials =
+        match aws_file_path("AWS_SHARED_CREDENTIALS_FILE", "credentials")? {
+            Some(path) => read_aws_file(&path).await?,
+            None => None,
+        };
+    let config = match aws_file_path("AWS_CONFIG_FILE", "config")? {
+        Some(path) => read_aws_file(&path).await?,
+        None => None,
+    };
+    Ok(aws_profile_values(
+        profile,
+        credentials.as_deref(),
+        config.as_deref(),
+    ))
+}
+
+/// Find `profile` in the text of `~/.aws/credentials` and `~/.aws/config`, and
+/// merge the keys and values we find.
+fn aws_profile_values(
+    profile: &str,
+    credentials: Option<&str>,
+    config: Option<&str>,
+) -> HashMap<String, String> {
+    // In `config`, profiles other than `default` are named `[profile NAME]`.
+    let config_section_name = if profile == "default" {
+        profile.to_owned()
+    } else {
+        format!("profile {}", profile)
+    };
+    let credentials_section =
+        credentials.and_then(|text| aws_file_section(text, profile));
+    let config_section =
+        config.and_then(|text| aws_file_section(text, &config_section_name));
+
+    // Values in `credentials` take precedence over values in `config`.
+    let mut values = config_section.unwrap_or_default();
+    values.extend(credentials_section.unwrap_or_default());
+    values
+}
+
+/// Find the AWS configuration file named in the environment variable `var`, or
+/// `file_name` in `~/.aws`.
+fn aws_file_path(var: &str, file_name: &str) -> Result<Option<PathBuf>> {
+    match try_var(var)? {
+        Some(path) => Ok(Some(PathBuf::from(path))),
+        None => Ok(dirs::home_dir().map(|home| home.join(".aws").join(file_name))),
+    }
+}
+
+/// Read an AWS configuration file, returning `None` if it doesn't exist.
+async fn read_aws_file(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path).await {
+        Ok(text) => Ok(Some(text)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format_err!("error reading {}: {}", path.display(), err)),
+    }
+}
+
+/// Find `[section]` in an INI-style AWS configuration file, and return the keys
+/// and values it contains.
+fn aws_file_section(text: &str, section: &str) -> Option<HashMap<String, String>> {
+    let mut values: Option<HashMap<String, String>> = None;
+    let mut in_section = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        } else if line.starts_with('[') && line.ends_with(']') {
+            in_section = line[1..line.len() - 1].trim() == section;
+            if in_section {
+                values.get_or_insert_with(HashMap::new);
+            }
+        } else if let (true, Some(values)) = (in_section, values.as_mut()) {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            if let Some(value) = parts.next() {
+                values.insert(key.to_owned(), value.trim().to_owned());
+            }
+        }
+    }
+    values
+}
+
+#[test]
+fn parse_aws_file_sections() {
+    let text = "\
+[default]
+aws_access_key_id = AKIADEFAULT
+aws_secret_access_key=secret=
+
+# A comment.
+[profile etl]
+region = eu-west-1
+s3 =
+  max_concurrent_requests = 10
+";
+    let default = aws_file_section(text, "default").unwrap();
+    assert_eq!(default["aws_access_key_id"], "AKIADEFAULT");
+    assert_eq!(default["aws_secret_access_key"], "secret=");
+    assert_eq!(default.len(), 2);
+    let etl = aws_file_section(text, "profile etl").unwrap();
+    assert_eq!(etl["region"], "eu-west-1");
+    assert!(aws_file_section(text, "etl").is_none());
+}
+
+#[test]
+fn merge_aws_profile_values() {
+    let credentials = "\
+[etl]
+aws_access_key_id = AKIAETL
+region = us-east-1
+";
+    let config = "\
+[default]
+region = us-west-2
+
+[profile etl]
+aws_access_key_id = AKIAIGNORED
+region = eu-west-1
+output = json
+";
+    let etl = aws_profile_values("etl", Some(credentials), Some(config));
+    assert_eq!(etl["aws_access_key_id"], "AKIAETL");
+    assert_eq!(etl["region"], "us-east-1");
+    assert_eq!(etl["output"], "json");
+    let default = aws_profile_values("default", Some(credentials), Some(config));
+    assert_eq!(default["region"], "us-west-2");
+    assert!(aws_profile_values("missing", None, Some(config)).is_empty());
+}
+
+/// Fetch temporary credentials for an Amazon ECS task role, using the
+/// endpoint that ECS passes to each container.
+#[derive(Debug)]
+struct AwsContainerCredentialsSource;
+
+impl fmt::Display for AwsContainerCredentialsSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "- An ECS task role, using AWS_CONTAINER_CREDENTIALS_RELATIVE_URI or \
+             AWS_CONTAINER_CREDENTIALS_FULL_URI",
+        )
+    }
+}
+
+#[async_trait]
+impl CredentialsSource for AwsContainerCredentialsSource {
+    async fn get_credentials(&self) -> Result<Option<Credentials>> {
+        let url = if let Some(uri) = try_var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI")?
+        {
+            format!("http://169.254.170.2{}", uri)
+        } else if let Some(uri) = try_var("AWS_CONTAINER_CREDENTIALS_FULL_URI")? {
+            uri
+        } else {
+            return Ok(None);
+        };
+        let mut req = aws_metadata_client()?.get(&url);
+        if let Some(token) = try_var("AWS_CONTAINER_AUTHORIZATION_TOKEN")? {
+            req = req.header("Authorization", token);
+        }
+        let body = async {
+            let resp = req.send().await?.error_for_status()?;
+            resp.text().await
+        }
+        .await
+        .with_context(|_| format!("could not fetch ECS credentials from {}", url))?;
+        Ok(Some(parse_aws_metadata_credentials(&body)?))
+    }
+}
+
+/// Fetch temporary credentials for an EC2 instance role, using version 2 of
+/// the instance metadata service.
+#[derive(Debug)]
+struct AwsInstanceCredentialsSource;
+
+impl fmt::Display for AwsInstanceCredentialsSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "- An EC2 instance role (unless AWS_EC2_METADATA_DISABLED is true)",
+        )
+    }
+}
+
+#[async_trait]
+impl CredentialsSource for AwsInstanceCredentialsSource {
+    async fn get_credentials(&self) -> Result<Option<Credentials>> {
+        let disabled = try_var("AWS_EC2_METADATA_DISABLED")?
+            .map_or(false, |value| value.eq_ignore_ascii_case("true"));
+        if disabled {
+            return Ok(None);
+        }
+
+        // Get a session token. If this fails, we're probably not running on
+        // EC2, so keep looking.
+        let client = aws_metadata_client()?;
+        let base_url = "http://169.254.169.254/latest";
+        let token = match client
+            .put(&format!("{}/api/token", base_url))
+            .header("X-aws-ec2-metadata-token-ttl-seconds", "21600")
+            .send()
+            .await
+        {
+            Ok(resp) if resp.status().is_success() => resp
+                .text()
+                .await
+                .context("could not read EC2 metadata token")?,
+            _ => return Ok(None),
+        };
+
+        // Find the name of our instance role, if we have one.
+        let roles_url = format!("{}/meta-data/iam/security-credentials/", base_url);
+        let resp = client
+            .get(&roles_url)
+            .header("X-aws-ec2-metadata-token", &token[..])
+            .send()
+            .await
+            .with_context(|_| format!("could not fetch {}", roles_url))?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let roles = async { resp.error_for_status()?.text().await }
+            .await
+            .with_context(|_| format!("could not fetch {}", roles_url))?;
+        let role = match roles.lines().next() {
+            Some(role) if !role.trim().is_empty() => role.trim().to_owned(),
+            _ => return Ok(None),
+        };
+
+        // Fetch the credentials for our role.
+        let creds_url = format!("{}{}", roles_url, role);
+        let body = async {
+            let resp = client
+                .get(&creds_url)
+                .header("X-aws-ec2-metadata-token", &token[..])
+                .send()
+                .await?
+                .error_for_status()?;
+            resp.text().await
+        }
+        .await
+        .with_context(|_| format!("could not fetch EC2 credentials for {}", role))?;
+        Ok(Some(parse_aws_metadata_credentials(&body)?))
+    }
+}
+
+/// An HTTP client for talking to AWS metadata endpoints. These are local, so we
+/// use a short timeout to avoid hanging when they don't exist.
+fn aws_metadata_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .context("could not create HTTP client")?)
+}
+
+/// Temporary credentials returned by the ECS and EC2 metadata endpoints.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AwsMetadataCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    token: String,
+    /// When these credentials expire, in RFC 3339 format.
+    expiration: String,
+}
+
+/// Parse credentials returned by an AWS metadata endpoint.
+fn parse_aws_metadata_credentials(body: &str) -> Result<Credentials> {
+    let creds = serde_json::from_str::<AwsMetadataCredentials>(body)
+        .context("could not parse AWS metadata credentials")?;
+    let expiration = DateTime::parse_from_rfc3339(&creds.expiration)
+        .with_context(|_| format!("cannot parse expiration {:?}", creds.expiration))?
+        .with_timezone(&Utc);
+    let remaining = (expiration - Utc::now())
+        .to_std()
+        .unwrap_or_else(|_| Duration::from_secs(0));
+
+    let mut data = HashMap::new();
+    data.insert("access_key_id".to_owned(), creds.access_key_id);
+    data.insert("secret_access_key".to_owned(), creds.secret_access_key);
+    data.insert("session_token".to_owned(), creds.token);
+    Ok(Credentials {
+        data,
+        expires: Some(Instant::now() + remaining),
+    })
+}
+
+#[test]
+fn parse_aws_metadata_credentials_json() {
+    let body = r#"{
+  "Code": "Success",
+  "LastUpdated": "2024-01-01T06:00:00Z",
+  "Type": "AWS-HMAC",
+  "AccessKeyId": "ASIAEXAMPLE",
+  "SecretAccessKey": "secret",
+  "Token": "token",
+  "Expiration": "2024-01-01T12:00:00Z"
+}"#;
+    let creds = parse_aws_metadata_credentials(body).unwrap();
+    assert_eq!(creds.data["access_key_id"], "ASIAEXAMPLE");
+    assert_eq!(creds.data["secret_access_key"], "secret");
+    assert_eq!(creds.data["session_token"], "token");
+    // These credentials have already expired.
+    assert!(creds.needs_refresh());
+    assert!(parse_aws_metadata_credentials("{}").is_err());
+}
+
 /// Look in multiple places for credentials.
 #[derive(Debug)]
 struct CredentialsSources {
@@ -1,20 +1,33 @@
 //! Our basic data representation.
 
-use reqwest::{self, Response};
+use chrono::{DateTime, Utc};
 
 use crate::common::*;
-use crate::tokio_glue::{
-    http_response_stream, idiomatic_bytes_stream, IdiomaticBytesStream,
-};
+use crate::tokio_glue::{idiomatic_bytes_stream, IdiomaticBytesStream};
 
 /// A stream of CSV data, with a unique name.
 pub struct CsvStream {
     /// The name of this stream.
     pub name: String,
+    /// Where this stream came from, if known.
+    pub metadata: StreamMetadata,
     /// Our data.
     pub data: BoxStream<BytesMut>,
 }
 
+/// Information about where a `CsvStream` came from. Destinations may use this
+/// to record the provenance of each row.
+///
+/// Streams which were generated by `dbcrossbar` itself (for example, by
+/// concatenating or rechunking other streams) will have no metadata.
+#[derive(Clone, Debug, Default)]
+pub struct StreamMetadata {
+    /// The original file path or object URL of this stream.
+    pub source: Option<String>,
+    /// When the original file or object was last modified.
+    pub modified: Option<DateTime<Utc>>,
+}
+
 impl CsvStream {
     /// Construct a CSV stream from bytes.
     #[cfg(test)]
@@ -30,6 +43,7 @@ impl CsvStream {
             .expect("could not send bytes to channel");
         CsvStream {
             name: "bytes".to_owned(),
+            metadata: StreamMetadata::default(),
             data: receiver.boxed(),
         }
     }
@@ -56,17 +70,6 @@ impl CsvStream {
         Ok(bytes)
     }
 
-    /// Convert an HTTP `Body` into a `CsvStream`.
-    pub(crate) fn from_http_response(
-        name: String,
-        response: Response,
-    ) -> Result<CsvStream> {
-        Ok(CsvStream {
-            name,
-            data: http_response_stream(response),
-        })
-    }
-
     /// Convert this `CsvStream` into a `Stream` that can be used with
     /// `hyper`, `reqwest`, and possibly other Rust libraries. Returns
     /// the stream name and the stream.
@@ -79,6 +82,31 @@ impl CsvStream {
     }
 }
 
+/// Choose a file name to use when writing the stream `stream_name` to a
+/// directory or bucket. When appending, we add a unique suffix so that we never
+/// replace any existing files.
+pub(crate) fn csv_stream_file_name(stream_name: &str, if_exists: &IfExists) -> String {
+    match if_exists {
+        IfExists::Append => {
+            format!("{}_{}.csv", stream_name, TemporaryStorage::random_tag())
+        }
+        _ => format!("{}.csv", stream_name),
+    }
+}
+
+#[test]
+fn csv_stream_file_name_is_unique_when_appending() {
+    assert_eq!(
+        csv_stream_file_name("data", &IfExists::Overwrite),
+        "data.csv"
+    );
+    let appended1 = csv_stream_file_name("data", &IfExists::Append);
+    let appended2 = csv_stream_file_name("data", &IfExists::Append);
+    assert!(appended1.starts_with("data_"));
+    assert!(appended1.ends_with(".csv"));
+    assert_ne!(appended1, appended2);
+}
+
 /// Given a `base_path` refering to one of more CSV files, and a `file_path`
 /// refering to a single CSV file, figure out the best name to use for a
 /// `CsvStream` for that CSV file.
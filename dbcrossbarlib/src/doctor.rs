@@ -0,0 +1,204 @@
+//! Diagnosing common configuration problems.
+//!
+//! Most `dbcrossbar` problems are caused by missing credentials or inaccessible
+//! temporary storage. We check for all of these up front, and explain how to
+//! fix anything we find.
+
+use std::fmt;
+
+#[cfg(feature = "s3")]
+use crate::clouds::aws::s3::{self, ServerSideEncryption};
+#[cfg(feature = "gs")]
+use crate::clouds::gcloud::storage::{self, KmsKeyName};
+use crate::common::*;
+use crate::config::Configuration;
+
+#[cfg(any(feature = "abfss", feature = "gs", feature = "s3", feature = "shopify"))]
+mod credentials;
+
+/// How serious is the outcome of a check?
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CheckStatus {
+    /// Everything is fine.
+    Ok,
+    /// Something is missing, but it's only needed by some drivers.
+    Warning,
+    /// Something is broken.
+    Failed,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckStatus::Ok => "ok".fmt(f),
+            CheckStatus::Warning => "warn".fmt(f),
+            CheckStatus::Failed => "FAIL".fmt(f),
+        }
+    }
+}
+
+/// The outcome of a single check.
+#[derive(Clone, Debug)]
+pub struct CheckResult {
+    /// What we checked.
+    pub name: String,
+    /// Did the check succeed?
+    pub status: CheckStatus,
+    /// What we found.
+    pub message: String,
+    /// How to fix any problem we found.
+    pub fix: Option<String>,
+}
+
+impl CheckResult {
+    /// A successful check.
+    fn ok<N, M>(name: N, message: M) -> Self
+    where
+        N: Into<String>,
+        M: Into<String>,
+    {
+        CheckResult {
+            name: name.into(),
+            status: CheckStatus::Ok,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    /// A check which found a problem.
+    fn problem<N, M, F>(name: N, status: CheckStatus, message: M, fix: F) -> Self
+    where
+        N: Into<String>,
+        M: Into<String>,
+        F: Into<String>,
+    {
+        CheckResult {
+            name: name.into(),
+            status,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Run all our checks, and return the results.
+pub async fn run_checks(ctx: &Context, config: &Configuration) -> Vec<CheckResult> {
+    let mut results = vec![check_config(config)];
+    #[cfg(any(
+        feature = "abfss",
+        feature = "gs",
+        feature = "s3",
+        feature = "shopify"
+    ))]
+    results.extend(credentials::check_all_credentials().await);
+    results.extend(check_temporaries(ctx, config).await);
+    results
+}
+
+/// Check that we could read our configuration file.
+fn check_config(config: &Configuration) -> CheckResult {
+    // If we couldn't parse our config file, we would have failed before
+    // getting here, so we just report where it is.
+    CheckResult::ok(
+        "configuration",
+        format!("using {}", config.path().display()),
+    )
+}
+
+/// Check that we can list each of our configured temporary directories.
+async fn check_temporaries(ctx: &Context, config: &Configuration) -> Vec<CheckResult> {
+    let temporaries = match config.temporaries() {
+        Ok(temporaries) => temporaries,
+        Err(err) => {
+            return vec![CheckResult::problem(
+                "temporary",
+                CheckStatus::Failed,
+                err.to_string(),
+                "fix the `temporary` key in your configuration file",
+            )]
+        }
+    };
+
+    let mut results = vec![];
+    for temporary in temporaries {
+        let name = format!("temporary {}", temporary);
+        let result = match check_temporary(ctx, &temporary).await {
+            Ok(Some(message)) => CheckResult::ok(name, message),
+            Ok(None) => CheckResult::ok(name, "not checked"),
+            Err(err) => CheckResult::problem(
+                name,
+                CheckStatus::Failed,
+                format!("cannot list: {}", err),
+                "check that it exists, and that your credentials can access it",
+            ),
+        };
+        results.push(result);
+    }
+    results
+}
+
+/// Try to list a temporary directory. Returns a description of what we found,
+/// or `None` if we don't know how to check this kind of temporary.
+async fn check_temporary(ctx: &Context, temporary: &str) -> Result<Option<String>> {
+    if temporary.starts_with("s3://") {
+        check_s3_temporary(ctx, temporary).await
+    } else if temporary.starts_with("gs://") {
+        check_gs_temporary(ctx, temporary).await
+    } else {
+        Ok(None)
+    }
+}
+
+/// Try to list an `s3://` temporary directory.
+#[cfg(feature = "s3")]
+async fn check_s3_temporary(ctx: &Context, temporary: &str) -> Result<Option<String>> {
+    // Check any options like `?sse=aws:kms`, and then remove them, because
+    // they aren't part of the path.
+    let mut url = temporary.parse::<Url>()?;
+    ServerSideEncryption::from_url_query(&url)?;
+    url.set_query(None);
+
+    // We don't use `s3::ls` here, because it treats an empty listing as an
+    // error.
+    s3::check_access(ctx, &url).await?;
+    Ok(Some("accessible".to_owned()))
+}
+
+/// We were built without `s3:` support, so we can't check this.
+#[cfg(not(feature = "s3"))]
+async fn check_s3_temporary(
+    _ctx: &Context,
+    _temporary: &str,
+) -> Result<Option<String>> {
+    Ok(None)
+}
+
+/// Try to list a `gs://` temporary directory.
+#[cfg(feature = "gs")]
+async fn check_gs_temporary(ctx: &Context, temporary: &str) -> Result<Option<String>> {
+    // Check any options like `?kms_key=...`, and then remove them, because
+    // they aren't part of the path.
+    let mut url = temporary.parse::<Url>()?;
+    KmsKeyName::from_url_query(&url)?;
+    url.set_query(None);
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    // We read the entire listing, because `storage::ls` uses a background
+    // worker which will report an error if we stop reading early. Temporary
+    // directories are normally small.
+    let count = storage::ls(ctx, &url, None)
+        .await?
+        .try_fold(0, |count, _| async move { Ok(count + 1) })
+        .await?;
+    Ok(Some(format!("accessible, {} files", count)))
+}
+
+/// We were built without `gs:` support, so we can't check this.
+#[cfg(not(feature = "gs"))]
+async fn check_gs_temporary(
+    _ctx: &Context,
+    _temporary: &str,
+) -> Result<Option<String>> {
+    Ok(None)
+}
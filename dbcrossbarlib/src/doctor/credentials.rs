@@ -0,0 +1,81 @@
+//! Checking for the credentials used by our cloud drivers.
+
+use super::{CheckResult, CheckStatus};
+use crate::credentials::CredentialsManager;
+
+/// Check for each kind of credential we know about.
+pub(super) async fn check_all_credentials() -> Vec<CheckResult> {
+    vec![
+        check_aws_credentials().await,
+        check_azure_credentials().await,
+        check_gcloud_credentials().await,
+        check_shopify_credentials().await,
+    ]
+}
+
+/// Check whether we can find any of the credentials in `credential_names`.
+async fn check_credentials(
+    name: &str,
+    credential_names: &[&str],
+    needed_for: &str,
+) -> CheckResult {
+    let manager = CredentialsManager::singleton();
+    let mut fix = "specify credentials using any of:\n".to_owned();
+    for &credential_name in credential_names {
+        match manager.try_get(credential_name).await {
+            Ok(Some(_)) => {
+                return CheckResult::ok(name, format!("found {}", credential_name))
+            }
+            Ok(None) => match manager.describe_sources(credential_name).await {
+                Ok(description) => fix.push_str(&description),
+                Err(err) => {
+                    return CheckResult::problem(
+                        name,
+                        CheckStatus::Failed,
+                        err.to_string(),
+                        "please report this as a bug",
+                    )
+                }
+            },
+            Err(err) => {
+                return CheckResult::problem(
+                    name,
+                    CheckStatus::Failed,
+                    format!("error reading {}: {}", credential_name, err),
+                    "check that your credentials are correctly formatted",
+                )
+            }
+        }
+    }
+    CheckResult::problem(
+        name,
+        CheckStatus::Warning,
+        format!("not found (only needed for {})", needed_for),
+        fix.trim_end(),
+    )
+}
+
+/// Check for AWS credentials.
+async fn check_aws_credentials() -> CheckResult {
+    check_credentials("AWS credentials", &["aws"], "s3: and redshift:").await
+}
+
+/// Check for Azure Storage credentials.
+async fn check_azure_credentials() -> CheckResult {
+    check_credentials("Azure Storage credentials", &["azure_storage"], "abfss:").await
+}
+
+/// Check for Google Cloud credentials.
+async fn check_gcloud_credentials() -> CheckResult {
+    check_credentials(
+        "Google Cloud credentials",
+        &["gcloud_service_account_key", "gcloud_client_secret"],
+        "gs: and bigquery:",
+    )
+    .await
+}
+
+/// Check for Shopify credentials.
+async fn check_shopify_credentials() -> CheckResult {
+    check_credentials("Shopify credentials", &["shopify"], "shopify:").await
+}
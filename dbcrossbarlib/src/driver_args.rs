@@ -1,6 +1,9 @@
-use serde::de::DeserializeOwned;
+use serde::{
+    de::{self, DeserializeOwned, Deserializer},
+    Deserialize,
+};
 use serde_json::{Map, Value};
-use std::{ops::Range, str::FromStr, sync::Arc};
+use std::{fmt, ops::Range, str::FromStr, sync::Arc};
 
 use crate::common::*;
 use crate::parse_error::{Annotation, FileInfo, ParseError};
@@ -74,6 +77,41 @@ fn to_json_detects_conflicts() {
     }
 }
 
+/// Driver argument values are always strings, so this can be used with
+/// `#[serde(default, deserialize_with = "deserialize_opt_from_str")]` to parse
+/// optional numbers and other `FromStr` types.
+pub(crate) fn deserialize_opt_from_str<'de, T, D>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error>
+where
+    T: FromStr,
+    <T as FromStr>::Err: fmt::Display,
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| {
+            s.parse::<T>()
+                .map_err(|err| de::Error::custom(format!("{:?}: {}", s, err)))
+        })
+        .transpose()
+}
+
+#[test]
+fn deserialize_opt_from_str_parses_values() {
+    #[derive(Deserialize)]
+    struct Example {
+        #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+        size: Option<usize>,
+    }
+
+    let args = DriverArguments::from_cli_args(&["size=10"]).unwrap();
+    assert_eq!(args.deserialize::<Example>().unwrap().size, Some(10));
+    let args = DriverArguments::default();
+    assert_eq!(args.deserialize::<Example>().unwrap().size, None);
+    let args = DriverArguments::from_cli_args(&["size=ten"]).unwrap();
+    assert!(args.deserialize::<Example>().is_err());
+}
+
 /// The name of a driver argument.
 #[derive(Clone, Debug)]
 pub(self) struct Arg {
@@ -0,0 +1,62 @@
+//! Reading data from Azure Data Lake Storage.
+
+use super::AbfssLocator;
+use crate::clouds::azure::storage;
+use crate::common::*;
+use crate::csv_stream::csv_stream_name;
+
+/// Implementation of `local_data`, but as a real `async` function.
+pub(crate) async fn local_data_helper(
+    ctx: Context,
+    url: Url,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Option<BoxStream<CsvStream>>> {
+    let _shared_args = shared_args.verify(AbfssLocator::features())?;
+    let _source_args = source_args.verify(AbfssLocator::features())?;
+
+    debug!(ctx.log(), "getting CSV files from {}", url);
+
+    // List the files at our URL.
+    let files = storage::ls(&ctx, &url).await?;
+
+    // Convert into `CsvStream` values lazily in case there are a lot of CSV
+    // files we need to read.
+    let csv_streams = files.and_then(move |item| {
+        let ctx = ctx.clone();
+        let url = url.clone();
+        async move {
+            // Stream the file from the cloud.
+            let file_url = item.url;
+            let name = csv_stream_name(url.as_str(), file_url.as_str())?.to_owned();
+            let ctx = ctx.child(
+                o!("stream" => name.clone(), "url" => file_url.as_str().to_owned()),
+            );
+            let metadata = StreamMetadata {
+                source: Some(file_url.as_str().to_owned()),
+                modified: item.modified,
+            };
+            let data = storage::download_file(&ctx, &file_url).await?;
+
+            // Assemble everything into a CSV stream.
+            Ok(CsvStream {
+                name,
+                metadata,
+                data,
+            })
+        }
+        .boxed()
+    });
+
+    Ok(Some(csv_streams.boxed()))
+}
+
+/// Implementation of `size_hint`, using a directory listing.
+pub(crate) async fn size_hint_helper(ctx: Context, url: Url) -> Result<SizeHint> {
+    let sizes = storage::ls(&ctx, &url)
+        .await?
+        .map_ok(|item| item.size)
+        .try_collect::<Vec<_>>()
+        .await?;
+    Ok(SizeHint::from_sizes(sizes))
+}
@@ -0,0 +1,111 @@
+//! Support for Azure Data Lake Storage Gen2.
+
+use std::{fmt, str::FromStr};
+
+use crate::clouds::azure::storage::parse_abfss_url;
+use crate::common::*;
+
+mod local_data;
+mod prepare_as_destination;
+mod write_local_data;
+
+use local_data::{local_data_helper, size_hint_helper};
+use prepare_as_destination::prepare_as_destination_helper;
+use write_local_data::write_local_data_helper;
+
+/// A directory in Azure Data Lake Storage Gen2, such as
+/// `abfss://filesystem@account.dfs.core.windows.net/dir/`.
+#[derive(Clone, Debug)]
+pub(crate) struct AbfssLocator {
+    url: Url,
+}
+
+impl fmt::Display for AbfssLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.url.fmt(f)
+    }
+}
+
+impl FromStr for AbfssLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with(Self::scheme()) {
+            let url = s
+                .parse::<Url>()
+                .with_context(|_| format!("cannot parse {}", s))?;
+            parse_abfss_url(&url)?;
+            if !url.path().ends_with('/') {
+                Err(format_err!("{} must end with a '/'", url))
+            } else {
+                Ok(AbfssLocator { url })
+            }
+        } else {
+            Err(format_err!("expected {} to begin with abfss://", s))
+        }
+    }
+}
+
+#[test]
+fn from_str_requires_filesystem_and_directory() {
+    assert!(
+        AbfssLocator::from_str("abfss://fs@acct.dfs.core.windows.net/dir/").is_ok()
+    );
+    assert!(AbfssLocator::from_str("abfss://fs@acct.dfs.core.windows.net/").is_ok());
+    assert!(AbfssLocator::from_str("abfss://acct.dfs.core.windows.net/dir/").is_err());
+    assert!(
+        AbfssLocator::from_str("abfss://fs@acct.dfs.core.windows.net/file.csv")
+            .is_err()
+    );
+}
+
+impl Locator for AbfssLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn local_data(
+        &self,
+        ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Option<BoxStream<CsvStream>>> {
+        local_data_helper(ctx, self.url.clone(), shared_args, source_args).boxed()
+    }
+
+    fn size_hint(
+        &self,
+        ctx: Context,
+        _source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<SizeHint> {
+        size_hint_helper(ctx, self.url.clone()).boxed()
+    }
+
+    fn write_local_data(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+        shared_args: SharedArguments<Unverified>,
+        dest_args: DestinationArguments<Unverified>,
+    ) -> BoxFuture<BoxStream<BoxFuture<BoxLocator>>> {
+        write_local_data_helper(ctx, self.url.clone(), data, shared_args, dest_args)
+            .boxed()
+    }
+}
+
+impl LocatorStatic for AbfssLocator {
+    fn scheme() -> &'static str {
+        "abfss:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::LocalData | LocatorFeatures::WriteLocalData,
+            write_schema_if_exists: EnumSet::empty(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: IfExistsFeatures::Overwrite | IfExistsFeatures::Append,
+            _placeholder: (),
+        }
+    }
+}
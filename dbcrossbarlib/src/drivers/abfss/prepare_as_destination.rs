@@ -0,0 +1,23 @@
+//! Preparing ADLS directories as output destinations.
+
+use crate::clouds::azure::storage;
+use crate::common::*;
+
+/// Prepare the target of this locator for use as a destination.
+pub(crate) async fn prepare_as_destination_helper(
+    ctx: Context,
+    abfss_url: Url,
+    if_exists: IfExists,
+) -> Result<()> {
+    match if_exists {
+        // Delete our directory and everything in it.
+        IfExists::Overwrite => storage::rmdir(&ctx, &abfss_url).await,
+        // Leave existing files alone. Our caller is responsible for choosing
+        // new file names.
+        IfExists::Append => Ok(()),
+        _ => Err(format_err!(
+            "must specify `overwrite` or `append` for {} destination",
+            abfss_url,
+        )),
+    }
+}
@@ -0,0 +1,41 @@
+//! Writing data to Azure Data Lake Storage.
+
+use super::{prepare_as_destination_helper, AbfssLocator};
+use crate::clouds::azure::storage;
+use crate::common::*;
+use crate::csv_stream::csv_stream_file_name;
+
+/// Implementation of `write_local_data`, but as a real `async` function.
+pub(crate) async fn write_local_data_helper(
+    ctx: Context,
+    url: Url,
+    data: BoxStream<CsvStream>,
+    shared_args: SharedArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
+    let _shared_args = shared_args.verify(AbfssLocator::features())?;
+    let dest_args = dest_args.verify(AbfssLocator::features())?;
+
+    // Look up our arguments.
+    let if_exists = dest_args.if_exists().to_owned();
+
+    // Delete the existing output, if it exists and we're not appending.
+    prepare_as_destination_helper(ctx.clone(), url.clone(), if_exists.clone()).await?;
+
+    // Spawn our uploader threads.
+    let written = data.map_ok(move |stream| {
+        let url = url.clone();
+        let ctx = ctx.clone();
+        let if_exists = if_exists.clone();
+        async move {
+            let url = url.join(&csv_stream_file_name(&stream.name, &if_exists))?;
+            let ctx = ctx
+                .child(o!("stream" => stream.name.clone(), "url" => url.to_string()));
+            storage::upload_file(&ctx, stream.data, &url).await?;
+            Ok(AbfssLocator { url }.boxed())
+        }
+        .boxed()
+    });
+
+    Ok(written.boxed())
+}
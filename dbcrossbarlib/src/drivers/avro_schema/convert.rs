@@ -0,0 +1,559 @@
+//! Converting between Avro schemas and our portable schemas.
+
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+use crate::common::*;
+use crate::schema::{Column, DataType, StructField};
+
+/// The precision we use when writing `decimal` types. We don't track precision
+/// in our portable schema, so we use the same values as BigQuery's `NUMERIC`.
+const DECIMAL_PRECISION: u32 = 38;
+
+/// The scale we use when writing `decimal` types.
+const DECIMAL_SCALE: u32 = 9;
+
+/// Convert an Avro record schema into a table.
+pub(crate) fn table_from_avro_schema(schema: &Value) -> Result<Table> {
+    if schema.get("type").and_then(|t| t.as_str()) != Some("record") {
+        return Err(format_err!("expected Avro schema to be a record"));
+    }
+    let mut reader = SchemaReader::default();
+    let name = get_str(schema, "name")?.to_owned();
+    let fields = reader.record_fields(schema, None)?;
+    let columns = fields
+        .into_iter()
+        .map(|(field, comment)| Column {
+            name: field.name,
+            is_nullable: field.is_nullable,
+            data_type: field.data_type,
+            comment,
+        })
+        .collect();
+    Ok(Table { name, columns })
+}
+
+/// Get the string property `key` from an Avro object.
+fn get_str<'a>(schema: &'a Value, key: &str) -> Result<&'a str> {
+    schema
+        .get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| format_err!("expected {:?} in Avro schema {}", key, schema))
+}
+
+/// State we need while reading an Avro schema.
+#[derive(Default)]
+struct SchemaReader {
+    /// Named types we've already seen, indexed by both short and full names.
+    named_types: HashMap<String, DataType>,
+}
+
+impl SchemaReader {
+    /// Convert the fields of an Avro record, returning each field's comment.
+    fn record_fields(
+        &mut self,
+        schema: &Value,
+        namespace: Option<&str>,
+    ) -> Result<Vec<(StructField, Option<String>)>> {
+        let name = get_str(schema, "name")?;
+        let namespace = schema
+            .get("namespace")
+            .and_then(|ns| ns.as_str())
+            .or(namespace);
+        let fields = schema
+            .get("fields")
+            .and_then(|fields| fields.as_array())
+            .ok_or_else(|| format_err!("expected fields in Avro record {}", name))?;
+        let mut result = vec![];
+        for field in fields {
+            let field_name = get_str(field, "name")?;
+            let field_type = field.get("type").ok_or_else(|| {
+                format_err!("expected type for Avro field {}.{}", name, field_name)
+            })?;
+            let (is_nullable, data_type) =
+                self.data_type(field_type, namespace).with_context(|_| {
+                    format!("error in Avro field {}.{}", name, field_name)
+                })?;
+            let comment = field
+                .get("doc")
+                .and_then(|d| d.as_str())
+                .map(|d| d.to_owned());
+            result.push((
+                StructField {
+                    name: field_name.to_owned(),
+                    is_nullable,
+                    data_type,
+                },
+                comment,
+            ));
+        }
+        Ok(result)
+    }
+
+    /// Remember a named type so that later parts of the schema can refer to it.
+    fn define(
+        &mut self,
+        schema: &Value,
+        namespace: Option<&str>,
+        data_type: &DataType,
+    ) -> Result<()> {
+        let name = get_str(schema, "name")?;
+        let namespace = schema
+            .get("namespace")
+            .and_then(|ns| ns.as_str())
+            .or(namespace);
+        self.named_types.insert(name.to_owned(), data_type.clone());
+        if let Some(namespace) = namespace {
+            if !name.contains('.') {
+                self.named_types
+                    .insert(format!("{}.{}", namespace, name), data_type.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert an Avro schema into a data type, returning whether `null` is
+    /// allowed.
+    fn data_type(
+        &mut self,
+        schema: &Value,
+        namespace: Option<&str>,
+    ) -> Result<(bool, DataType)> {
+        match schema {
+            // Primitive types and references to named types.
+            Value::String(name) => match &name[..] {
+                "null" => Err(format_err!("cannot convert Avro type \"null\"")),
+                "boolean" => Ok((false, DataType::Bool)),
+                "int" => Ok((false, DataType::Int32)),
+                "long" => Ok((false, DataType::Int64)),
+                "float" => Ok((false, DataType::Float32)),
+                "double" => Ok((false, DataType::Float64)),
+                "string" => Ok((false, DataType::Text)),
+                "bytes" => Err(format_err!("cannot convert Avro type \"bytes\"")),
+                _ => {
+                    let full_name =
+                        namespace.map(|namespace| format!("{}.{}", namespace, name));
+                    let data_type = full_name
+                        .and_then(|full_name| self.named_types.get(&full_name))
+                        .or_else(|| self.named_types.get(name))
+                        .ok_or_else(|| {
+                            format_err!("unknown or recursive Avro type {:?}", name)
+                        })?;
+                    Ok((false, data_type.clone()))
+                }
+            },
+
+            // Unions, which are normally used to make a type nullable.
+            Value::Array(variants) => {
+                let is_nullable = variants.iter().any(|v| v == &json!("null"));
+                let non_null = variants
+                    .iter()
+                    .filter(|&v| v != &json!("null"))
+                    .collect::<Vec<_>>();
+                match &non_null[..] {
+                    [] => Err(format_err!("cannot convert Avro type [\"null\"]")),
+                    [variant] => {
+                        let (_, data_type) = self.data_type(variant, namespace)?;
+                        Ok((is_nullable, data_type))
+                    }
+                    // Other unions have no portable equivalent.
+                    _ => Ok((is_nullable, DataType::Json)),
+                }
+            }
+
+            // Complex types and primitives with logical types.
+            Value::Object(_) => {
+                let ty = match schema.get("type") {
+                    Some(Value::String(ty)) => &ty[..],
+                    Some(other) => return self.data_type(other, namespace),
+                    None => {
+                        return Err(format_err!("expected type in Avro {}", schema))
+                    }
+                };
+                let logical_type =
+                    schema.get("logicalType").and_then(|lt| lt.as_str());
+                let data_type = match (ty, logical_type) {
+                    ("int", Some("date")) => DataType::Date,
+                    ("long", Some("timestamp-millis"))
+                    | ("long", Some("timestamp-micros")) => {
+                        DataType::TimestampWithTimeZone
+                    }
+                    ("long", Some("local-timestamp-millis"))
+                    | ("long", Some("local-timestamp-micros")) => {
+                        DataType::TimestampWithoutTimeZone
+                    }
+                    // We don't have a portable `TIME` type, so we treat it as text.
+                    ("int", Some("time-millis")) | ("long", Some("time-micros")) => {
+                        DataType::Text
+                    }
+                    ("bytes", Some("decimal")) | ("fixed", Some("decimal")) => {
+                        DataType::Decimal
+                    }
+                    ("string", Some("uuid")) => DataType::Uuid,
+                    ("array", _) => {
+                        let items = schema.get("items").ok_or_else(|| {
+                            format_err!("expected items in Avro {}", schema)
+                        })?;
+                        let (_, item_type) = self.data_type(items, namespace)?;
+                        DataType::Array(Box::new(item_type))
+                    }
+                    // Maps have arbitrary keys, so we can only represent them as
+                    // JSON objects.
+                    ("map", _) => DataType::Json,
+                    ("enum", _) => {
+                        self.define(schema, namespace, &DataType::Text)?;
+                        DataType::Text
+                    }
+                    ("fixed", _) => {
+                        return Err(format_err!("cannot convert Avro type \"fixed\""))
+                    }
+                    ("record", _) | ("error", _) => {
+                        let fields = self
+                            .record_fields(schema, namespace)?
+                            .into_iter()
+                            .map(|(field, _comment)| field)
+                            .collect();
+                        let data_type = DataType::Struct(fields);
+                        self.define(schema, namespace, &data_type)?;
+                        data_type
+                    }
+                    // Unknown logical types must be ignored, according to the
+                    // Avro spec.
+                    (ty, _) => self.data_type(&json!(ty), namespace)?.1,
+                };
+                Ok((false, data_type))
+            }
+
+            other => Err(format_err!("unexpected Avro schema {}", other)),
+        }
+    }
+}
+
+/// Build an Avro record schema describing rows of `table`.
+pub(crate) fn avro_schema_for_table(table: &Table) -> Result<Value> {
+    let mut writer = SchemaWriter::default();
+    let name = writer.record_name(&table.name);
+    let fields = table
+        .columns
+        .iter()
+        .map(|col| {
+            writer.field(
+                &name,
+                &col.name,
+                col.is_nullable,
+                &col.data_type,
+                col.comment.as_deref(),
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({
+        "type": "record",
+        "name": name,
+        "fields": fields,
+    }))
+}
+
+/// Is `name` a valid Avro name?
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// State we need while writing an Avro schema.
+#[derive(Default)]
+struct SchemaWriter {
+    /// Record names we've already used. Avro requires these to be unique.
+    record_names: HashSet<String>,
+}
+
+impl SchemaWriter {
+    /// Generate a unique, valid Avro record name based on `name`.
+    fn record_name(&mut self, name: &str) -> String {
+        let mut base = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        if !is_valid_name(&base) {
+            base = format!("_{}", base);
+        }
+        let mut name = base.clone();
+        let mut counter = 2;
+        while self.record_names.contains(&name) {
+            name = format!("{}_{}", base, counter);
+            counter += 1;
+        }
+        self.record_names.insert(name.clone());
+        name
+    }
+
+    /// Build an Avro field. Nested records are named after `record_name`.
+    fn field(
+        &mut self,
+        record_name: &str,
+        name: &str,
+        is_nullable: bool,
+        data_type: &DataType,
+        comment: Option<&str>,
+    ) -> Result<Value> {
+        if !is_valid_name(name) {
+            return Err(format_err!(
+                "cannot use {:?} as an Avro field name (names must match [A-Za-z_][A-Za-z0-9_]*)",
+                name,
+            ));
+        }
+        let ty = self.avro_type(&format!("{}_{}", record_name, name), data_type)?;
+        let mut field = Map::new();
+        field.insert("name".to_owned(), json!(name));
+        if is_nullable {
+            field.insert("type".to_owned(), json!(["null", ty]));
+            field.insert("default".to_owned(), Value::Null);
+        } else {
+            field.insert("type".to_owned(), ty);
+        }
+        if let Some(comment) = comment {
+            field.insert("doc".to_owned(), json!(comment));
+        }
+        Ok(Value::Object(field))
+    }
+
+    /// Build an Avro type describing `data_type`. If we need to create any
+    /// records, we name them after `name_hint`.
+    fn avro_type(&mut self, name_hint: &str, data_type: &DataType) -> Result<Value> {
+        Ok(match data_type {
+            DataType::Array(elem) => json!({
+                "type": "array",
+                "items": self.avro_type(name_hint, elem)?,
+            }),
+            DataType::Bool => json!("boolean"),
+            DataType::Date => json!({ "type": "int", "logicalType": "date" }),
+            DataType::Decimal => json!({
+                "type": "bytes",
+                "logicalType": "decimal",
+                "precision": DECIMAL_PRECISION,
+                "scale": DECIMAL_SCALE,
+            }),
+            DataType::FixedDecimal { precision, scale } => json!({
+                "type": "bytes",
+                "logicalType": "decimal",
+                "precision": precision,
+                "scale": scale,
+            }),
+            DataType::Float32 => json!("float"),
+            DataType::Float64 => json!("double"),
+            // Avro has no 16-bit integers.
+            DataType::Int16 | DataType::Int32 => json!("int"),
+            DataType::Int64 => json!("long"),
+            // Avro has no JSON type, so we store serialized JSON as a string.
+            DataType::GeoJson(_) | DataType::Json | DataType::Text => {
+                json!("string")
+            }
+            DataType::Struct(fields) => {
+                let name = self.record_name(name_hint);
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        self.field(
+                            &name,
+                            &field.name,
+                            field.is_nullable,
+                            &field.data_type,
+                            None,
+                        )
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                json!({
+                    "type": "record",
+                    "name": name,
+                    "fields": fields,
+                })
+            }
+            DataType::TimestampWithoutTimeZone => json!({
+                "type": "long",
+                "logicalType": "local-timestamp-micros",
+            }),
+            DataType::TimestampWithTimeZone => json!({
+                "type": "long",
+                "logicalType": "timestamp-micros",
+            }),
+            DataType::Uuid => json!({ "type": "string", "logicalType": "uuid" }),
+        })
+    }
+}
+
+#[test]
+fn table_from_avro_schema_handles_logical_types() {
+    let schema = json!({
+        "type": "record",
+        "name": "orders",
+        "namespace": "com.example",
+        "fields": [
+            { "name": "id", "type": { "type": "string", "logicalType": "uuid" } },
+            {
+                "name": "total",
+                "type": {
+                    "type": "fixed",
+                    "name": "money",
+                    "size": 16,
+                    "logicalType": "decimal",
+                    "precision": 20,
+                    "scale": 2,
+                },
+            },
+            {
+                "name": "placed_at",
+                "type": ["null", { "type": "long", "logicalType": "timestamp-millis" }],
+                "default": null,
+                "doc": "When the order was placed",
+            },
+            {
+                "name": "status",
+                "type": { "type": "enum", "name": "status", "symbols": ["NEW", "PAID"] },
+            },
+            { "name": "previous_status", "type": ["null", "com.example.status"] },
+            { "name": "attributes", "type": { "type": "map", "values": "string" } },
+            { "name": "either", "type": ["int", "string"] },
+            {
+                "name": "lines",
+                "type": {
+                    "type": "array",
+                    "items": {
+                        "type": "record",
+                        "name": "line",
+                        "fields": [{ "name": "qty", "type": "int" }],
+                    },
+                },
+            },
+            { "name": "favorite_line", "type": ["null", "line"] },
+            { "name": "odd", "type": { "type": "int", "logicalType": "mystery" } },
+        ],
+    });
+    let table = table_from_avro_schema(&schema).unwrap();
+    assert_eq!(table.name, "orders");
+    let line = DataType::Struct(vec![StructField {
+        name: "qty".to_owned(),
+        is_nullable: false,
+        data_type: DataType::Int32,
+    }]);
+    let types = table
+        .columns
+        .iter()
+        .map(|c| (&c.name[..], c.is_nullable, c.data_type.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            ("id", false, DataType::Uuid),
+            ("total", false, DataType::Decimal),
+            ("placed_at", true, DataType::TimestampWithTimeZone),
+            ("status", false, DataType::Text),
+            ("previous_status", true, DataType::Text),
+            ("attributes", false, DataType::Json),
+            ("either", false, DataType::Json),
+            ("lines", false, DataType::Array(Box::new(line.clone()))),
+            ("favorite_line", true, line),
+            ("odd", false, DataType::Int32),
+        ]
+    );
+    assert_eq!(
+        table.columns[2].comment.as_deref(),
+        Some("When the order was placed"),
+    );
+}
+
+#[test]
+fn table_from_avro_schema_rejects_unsupported_schemas() {
+    let bad_schemas = &[
+        json!("string"),
+        json!({ "type": "record", "name": "t", "fields": [
+            { "name": "b", "type": "bytes" },
+        ] }),
+        json!({ "type": "record", "name": "node", "fields": [
+            { "name": "next", "type": ["null", "node"] },
+        ] }),
+    ];
+    for schema in bad_schemas {
+        assert!(table_from_avro_schema(schema).is_err());
+    }
+}
+
+#[test]
+fn avro_schema_roundtrip() {
+    let point = DataType::Struct(vec![
+        StructField {
+            name: "x".to_owned(),
+            is_nullable: false,
+            data_type: DataType::Float64,
+        },
+        StructField {
+            name: "label".to_owned(),
+            is_nullable: true,
+            data_type: DataType::Text,
+        },
+    ]);
+    let column = |name: &str, is_nullable: bool, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let table = Table {
+        name: "example".to_owned(),
+        columns: vec![
+            Column {
+                comment: Some("Primary key".to_owned()),
+                ..column("id", false, DataType::Uuid)
+            },
+            column("active", true, DataType::Bool),
+            column("born_on", true, DataType::Date),
+            column("price", false, DataType::Decimal),
+            column("ratio", false, DataType::Float32),
+            column("score", false, DataType::Float64),
+            column("count", false, DataType::Int32),
+            column("big_count", false, DataType::Int64),
+            column("name", true, DataType::Text),
+            column("created_at", false, DataType::TimestampWithTimeZone),
+            column("updated_at", true, DataType::TimestampWithoutTimeZone),
+            column("tags", true, DataType::Array(Box::new(DataType::Text))),
+            column("point", true, point.clone()),
+            column("points", false, DataType::Array(Box::new(point))),
+        ],
+    };
+    let schema = avro_schema_for_table(&table).unwrap();
+    assert_eq!(table_from_avro_schema(&schema).unwrap(), table);
+
+    // Each nested record needs a unique name.
+    assert_eq!(
+        schema["fields"][12]["type"][1]["name"],
+        json!("example_point")
+    );
+    assert_eq!(
+        schema["fields"][13]["type"]["items"]["name"],
+        json!("example_points"),
+    );
+}
+
+#[test]
+fn avro_schema_for_table_checks_names() {
+    let column = |name: &str| Column {
+        name: name.to_owned(),
+        is_nullable: false,
+        data_type: DataType::Int64,
+        comment: None,
+    };
+    let table = Table {
+        name: "2020 sales".to_owned(),
+        columns: vec![column("id")],
+    };
+    let schema = avro_schema_for_table(&table).unwrap();
+    assert_eq!(schema["name"], json!("_2020_sales"));
+
+    let table = Table {
+        name: "t".to_owned(),
+        columns: vec![column("bad name")],
+    };
+    assert!(avro_schema_for_table(&table).is_err());
+}
@@ -0,0 +1,106 @@
+//! Support for `avro-schema` locators.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+
+mod convert;
+
+use self::convert::{avro_schema_for_table, table_from_avro_schema};
+
+/// An Avro `.avsc` file containing a record schema.
+#[derive(Clone, Debug)]
+pub struct AvroSchemaLocator {
+    path: PathOrStdio,
+}
+
+impl fmt::Display for AvroSchemaLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.path.fmt_locator_helper(Self::scheme(), f)
+    }
+}
+
+impl FromStr for AvroSchemaLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), s)?;
+        Ok(AvroSchemaLocator { path })
+    }
+}
+
+impl Locator for AvroSchemaLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self, ctx: Context) -> BoxFuture<Option<Table>> {
+        schema_helper(ctx, self.to_owned()).boxed()
+    }
+
+    fn write_schema(
+        &self,
+        ctx: Context,
+        table: Table,
+        if_exists: IfExists,
+    ) -> BoxFuture<()> {
+        write_schema_helper(ctx, self.to_owned(), table, if_exists).boxed()
+    }
+}
+
+impl LocatorStatic for AvroSchemaLocator {
+    fn scheme() -> &'static str {
+        "avro-schema:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::Schema | LocatorFeatures::WriteSchema,
+            write_schema_if_exists: IfExistsFeatures::no_append(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
+
+/// Implementation of `schema`, but as a real `async` function.
+async fn schema_helper(
+    _ctx: Context,
+    source: AvroSchemaLocator,
+) -> Result<Option<Table>> {
+    // Read our input.
+    let input = source.path.open_async().await?;
+    let data = async_read_to_end(input)
+        .await
+        .with_context(|_| format!("error reading {}", source.path))?;
+
+    // Parse our input as JSON, and convert it.
+    let schema: serde_json::Value = serde_json::from_slice(&data)
+        .with_context(|_| format!("error parsing {}", source.path))?;
+    let table = table_from_avro_schema(&schema)
+        .with_context(|_| format!("error converting {}", source.path))?;
+    Ok(Some(table))
+}
+
+/// Implementation of `write_schema`, but as a real `async` function.
+async fn write_schema_helper(
+    ctx: Context,
+    dest: AvroSchemaLocator,
+    table: Table,
+    if_exists: IfExists,
+) -> Result<()> {
+    // Convert our schema before we create our output file.
+    let schema = avro_schema_for_table(&table)?;
+
+    // Output our schema to our destination.
+    let mut f = dest.path.create_async(ctx, if_exists).await?;
+    buffer_sync_write_and_copy_to_async(&mut f, |buff| {
+        serde_json::to_writer_pretty(buff, &schema)
+    })
+    .await
+    .with_context(|_| format!("error writing to {}", dest.path))?;
+    f.flush().await?;
+    Ok(())
+}
@@ -21,6 +21,7 @@ impl OptypeExt for Optype {
             DataType::Bool => Ok(Optype::Categorical),
             DataType::Date => Ok(Optype::DateTime),
             DataType::Decimal => Ok(Optype::Numeric),
+            DataType::FixedDecimal { .. } => Ok(Optype::Numeric),
             DataType::Float32 => Ok(Optype::Numeric),
             DataType::Float64 => Ok(Optype::Numeric),
             DataType::GeoJson(_) => Ok(Optype::Text),
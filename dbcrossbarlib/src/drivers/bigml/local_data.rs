@@ -4,9 +4,11 @@ use bigml::{
     self,
     resource::{Id, Resource},
 };
+use reqwest::Response;
 
 use super::{BigMlAction, BigMlLocator};
 use crate::common::*;
+use crate::http_response::http_response_stream;
 
 /// Implementation of `local_data`, but as a real `async` function.
 pub(crate) async fn local_data_helper(
@@ -27,11 +29,20 @@ pub(crate) async fn local_data_helper(
     let client = bigml::Client::new_from_env()?;
     let response = client.download(&id).await?;
     let csv_stream =
-        CsvStream::from_http_response(strip_id_prefix(&id).to_owned(), response)?;
+        csv_stream_from_http_response(strip_id_prefix(&id).to_owned(), response);
 
     Ok(Some(box_stream_once(Ok(csv_stream))))
 }
 
+/// Convert an HTTP `Body` into a `CsvStream`.
+fn csv_stream_from_http_response(name: String, response: Response) -> CsvStream {
+    CsvStream {
+        name,
+        metadata: StreamMetadata::default(),
+        data: http_response_stream(response),
+    }
+}
+
 /// Remove the "dataset/" prefix from `id`.
 fn strip_id_prefix<R: Resource>(id: &Id<R>) -> &str {
     // For any given `Resource` type `R`, we know the actual ID prefix, so we
@@ -8,7 +8,7 @@ use chrono::{Duration, Utc};
 use serde::Deserialize;
 
 use super::{source::SourceExt, BigMlLocator, CreateOptions};
-use crate::clouds::aws::{sign_s3_url, AwsCredentials};
+use crate::clouds::aws::{presign::sign_s3_url, AwsCredentials};
 use crate::common::*;
 use crate::concat::concatenate_csv_streams;
 use crate::drivers::s3::find_s3_temp_dir;
@@ -62,8 +62,12 @@ pub(crate) async fn write_local_data_helper(
     }
 
     // See if we have an S3 temporary directory, and transform `data` into a
-    // list of BigML source IDs.
+    // list of BigML source IDs. We wait for each source to be ready below, so
+    // BigML will have finished reading this directory by the time we delete it.
     let s3_temp = find_s3_temp_dir(shared_args_v.temporary_storage()).ok();
+    if let Some(s3_temp) = &s3_temp {
+        s3_temp.delete_when_done(shared_args_v.temporary_storage());
+    }
     let sources: BoxStream<BoxFuture<(Context, Source)>> =
         if let Some(s3_temp) = s3_temp {
             // We have S3 temporary storage, so let's copy everything there.
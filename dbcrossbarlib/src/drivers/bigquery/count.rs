@@ -20,18 +20,23 @@ pub(crate) async fn count_helper(
     let source_args = source_args.verify(BigQueryLocator::features())?;
 
     // Get our billing labels.
-    let job_labels = source_args
+    let args = source_args
         .driver_args()
         .deserialize::<GCloudDriverArguments>()
-        .context("error parsing --from-args")?
-        .job_labels
-        .to_owned();
+        .context("error parsing --from-args")?;
+    let job_labels = args.job_labels.to_owned();
 
     // Look up the arguments we need.
     let schema = shared_args.schema();
 
-    // Construct a `BqTable` describing our source table.
+    // Figure out where to run our query.
     let table_name = locator.as_table_name().to_owned();
+    let location = match &args.location {
+        Some(location) => location.to_owned(),
+        None => bigquery::dataset_location(&ctx, &table_name).await?,
+    };
+
+    // Construct a `BqTable` describing our source table.
     let table = BqTable::for_table_name_and_columns(
         table_name,
         &schema.columns,
@@ -51,8 +56,10 @@ pub(crate) async fn count_helper(
     }
     let count_str = bigquery::query_one::<CountRow>(
         &ctx,
-        locator.project(),
+        args.job_project(locator.project()),
+        &location,
         &count_sql,
+        source_args.query_params(),
         &job_labels,
     )
     .await?
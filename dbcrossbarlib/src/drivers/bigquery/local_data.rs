@@ -13,6 +13,7 @@ pub(crate) async fn local_data_helper(
     // Build a temporary location.
     let shared_args_v = shared_args.clone().verify(BigQueryLocator::features())?;
     let gs_temp = find_gs_temp_dir(shared_args_v.temporary_storage())?;
+    gs_temp.delete_when_done(shared_args_v.temporary_storage());
     let gs_dest_args = DestinationArguments::for_temporary();
     let gs_source_args = SourceArguments::for_temporary();
 
@@ -131,7 +131,8 @@ impl LocatorStatic for BigQueryLocator {
                 | LocatorFeatures::Count,
             write_schema_if_exists: EnumSet::empty(),
             source_args: SourceArgumentsFeatures::DriverArgs
-                | SourceArgumentsFeatures::WhereClause,
+                | SourceArgumentsFeatures::WhereClause
+                | SourceArgumentsFeatures::QueryParams,
             dest_args: DestinationArgumentsFeatures::DriverArgs.into(),
             dest_if_exists: IfExistsFeatures::Error
                 | IfExistsFeatures::Overwrite
@@ -1,5 +1,6 @@
 //! Implementation of `write_local_data` for BigQuery.
 
+use super::write_remote_data::BigQueryDestinationArguments;
 use crate::common::*;
 use crate::drivers::{bigquery::BigQueryLocator, gs::find_gs_temp_dir};
 use crate::tokio_glue::ConsumeWithParallelism;
@@ -12,10 +13,36 @@ pub(crate) async fn write_local_data_helper(
     shared_args: SharedArguments<Unverified>,
     dest_args: DestinationArguments<Unverified>,
 ) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
-    // Build a temporary location.
     let shared_args_v = shared_args.clone().verify(BigQueryLocator::features())?;
-    let gs_temp = find_gs_temp_dir(shared_args_v.temporary_storage())?;
-    let gs_dest_args = DestinationArguments::for_temporary();
+    let dest_args_v = dest_args.clone().verify(BigQueryLocator::features())?;
+    let args = dest_args_v
+        .driver_args()
+        .deserialize::<BigQueryDestinationArguments>()
+        .context("error parsing --to-args")?;
+
+    // Build a temporary location. External tables need their files to stick
+    // around, so we write those to `external_uri` instead.
+    let (gs_temp, gs_dest_args) = if args.is_external()? {
+        // Check this now, before we write any files. We use the same
+        // `--if-exists` behavior for our files and our table.
+        let if_exists = dest_args_v.if_exists().to_owned();
+        match if_exists {
+            IfExists::Error | IfExists::Overwrite => {}
+            IfExists::Append | IfExists::Upsert(_) => {
+                return Err(format_err!(
+                    "external tables only support --if-exists=error or \
+                     --if-exists=overwrite",
+                ));
+            }
+        }
+        let gs_dest_args =
+            DestinationArguments::new(DriverArguments::default(), if_exists);
+        (args.external_uri()?.to_owned(), gs_dest_args)
+    } else {
+        let gs_temp = find_gs_temp_dir(shared_args_v.temporary_storage())?;
+        gs_temp.delete_when_done(shared_args_v.temporary_storage());
+        (gs_temp, DestinationArguments::for_temporary())
+    };
     let gs_source_args = SourceArguments::for_temporary();
 
     // Copy to a temporary gs:// location.
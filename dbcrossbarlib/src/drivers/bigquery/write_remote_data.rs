@@ -1,13 +1,128 @@
 //! Implementation of `BigQueryLocator::write_remote_data`.
 
+use serde::Deserialize;
+
 use super::BigQueryLocator;
-use crate::clouds::gcloud::bigquery;
+use crate::clouds::gcloud::bigquery::{self, Labels};
 use crate::common::*;
+use crate::driver_args::deserialize_opt_from_str;
 use crate::drivers::{
-    bigquery_shared::{BqTable, GCloudDriverArguments, TableBigQueryExt, Usage},
+    bigquery_shared::{
+        BqTable, ConnectionId, NewTableOptions, PartitionType, TableBigQueryExt,
+        TableExpiration, TableLabels, TimePartitioning, Usage,
+    },
     gs::GsLocator,
 };
 
+/// Parsed version of `--to-arg` values.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct BigQueryDestinationArguments {
+    /// Billing labels to apply to jobs.
+    #[serde(default)]
+    job_labels: Labels,
+
+    /// The location of our dataset, like `EU`. If this isn't specified, we look
+    /// it up.
+    location: Option<String>,
+
+    /// The project in which to run and bill our jobs, if it's different from
+    /// the project containing our table.
+    billing_project: Option<String>,
+
+    /// Labels to apply to new tables and to jobs, like `env:prod,team:growth`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    labels: Option<TableLabels>,
+
+    /// Partition new tables using this `DATE`, `DATETIME` or `TIMESTAMP`
+    /// column.
+    partition_field: Option<String>,
+
+    /// How finely to partition new tables, like `DAY`. If no `partition_field`
+    /// is specified, tables are partitioned by ingestion time.
+    partition_type: Option<PartitionType>,
+
+    /// How many days to keep each partition.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    partition_expiration_days: Option<u32>,
+
+    /// How long to keep new tables, like `7d`, `12h` or `30m`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    expiration: Option<TableExpiration>,
+
+    /// Create an external table over our CSV files instead of loading them.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    external: Option<bool>,
+
+    /// Where to write CSV files for an external table, when we're not copying
+    /// from `gs://`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    external_uri: Option<GsLocator>,
+
+    /// A BigLake connection to use for external tables, like
+    /// `my-project.us.my-connection`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    external_connection: Option<ConnectionId>,
+}
+
+impl BigQueryDestinationArguments {
+    /// What options should we use for the tables we create?
+    fn new_table_options(&self) -> Result<NewTableOptions> {
+        Ok(NewTableOptions {
+            time_partitioning: TimePartitioning::from_driver_args(
+                self.partition_field.as_deref(),
+                self.partition_type,
+                self.partition_expiration_days,
+            )?,
+            labels: self.labels.clone().unwrap_or_default(),
+            expiration: self.expiration,
+        })
+    }
+
+    /// Should we create an external table instead of loading our data?
+    pub(super) fn is_external(&self) -> Result<bool> {
+        let external = self.external.unwrap_or(false);
+        if !external
+            && (self.external_uri.is_some() || self.external_connection.is_some())
+        {
+            return Err(format_err!(
+                "external_uri and external_connection require external=true"
+            ));
+        }
+        Ok(external)
+    }
+
+    /// Where should we write the CSV files for an external table?
+    pub(super) fn external_uri(&self) -> Result<&GsLocator> {
+        self.external_uri.as_ref().ok_or_else(|| {
+            format_err!(
+                "external=true requires external_uri=gs://... or a gs:// source"
+            )
+        })
+    }
+
+    /// The project in which to run jobs which write to `dest`.
+    fn job_project<'a>(&'a self, dest: &'a BigQueryLocator) -> &'a str {
+        self.billing_project.as_deref().unwrap_or(dest.project())
+    }
+
+    /// What labels should we apply to our jobs? These include any table
+    /// `labels`, but `job_labels` take precedence.
+    fn all_job_labels(&self) -> Labels {
+        let mut job_labels = self
+            .labels
+            .as_ref()
+            .map(|labels| labels.labels().to_owned())
+            .unwrap_or_default();
+        job_labels.extend(
+            self.job_labels
+                .iter()
+                .map(|(key, value)| (key.to_owned(), value.to_owned())),
+        );
+        job_labels
+    }
+}
+
 /// Copy `source` to `dest` using `schema`.
 ///
 /// The function `BigQueryLocator::write_remote_data` isn't (yet) allowed to be
@@ -33,7 +148,7 @@ pub(crate) async fn write_remote_data_helper(
 
     // Verify our arguments.
     let shared_args = shared_args.verify(BigQueryLocator::features())?;
-    let _source_args = source_args.verify(Features::empty())?;
+    let _source_args = source_args.verify(Features::default())?;
     let dest_args = dest_args.verify(BigQueryLocator::features())?;
 
     // Get the arguments we care about.
@@ -41,13 +156,20 @@ pub(crate) async fn write_remote_data_helper(
     let temporary_storage = shared_args.temporary_storage();
     let if_exists = dest_args.if_exists();
 
-    // Get our billing labels.
-    let job_labels = dest_args
+    // Get our billing labels and table options.
+    let args = dest_args
         .driver_args()
-        .deserialize::<GCloudDriverArguments>()
-        .context("error parsing --to-args")?
-        .job_labels
-        .to_owned();
+        .deserialize::<BigQueryDestinationArguments>()
+        .context("error parsing --to-args")?;
+    let new_table_options = args.new_table_options()?;
+    let job_labels = args.all_job_labels();
+    let job_project = args.job_project(&dest);
+
+    // Figure out where to run our jobs.
+    let location = match &args.location {
+        Some(location) => location.to_owned(),
+        None => bigquery::dataset_location(&ctx, &dest.table_name).await?,
+    };
 
     // If our URL looks like a directory, add a glob.
     //
@@ -60,8 +182,27 @@ pub(crate) async fn write_remote_data_helper(
     }
     let ctx = ctx.child(o!("source_url" => source_url.as_str().to_owned()));
 
-    // Decide if we need to use a temp table.
-    let use_temp = !schema.bigquery_can_import_from_csv()? || if_exists.is_upsert();
+    // If we're creating an external table, we don't need to load anything.
+    if args.is_external()? {
+        create_external_table(
+            &ctx,
+            &source_url,
+            &dest,
+            &args,
+            schema,
+            &location,
+            if_exists,
+        )
+        .await?;
+        return Ok(vec![dest.boxed()]);
+    }
+
+    // Decide if we need to use a temp table. Load jobs can't set a table
+    // expiration, so we also use a temp table when we need one, and create the
+    // final table using SQL.
+    let use_temp = !schema.bigquery_can_import_from_csv()?
+        || matches!(if_exists, IfExists::Upsert(_))
+        || new_table_options.expiration.is_some();
     let initial_table_name = if use_temp {
         let initial_table_name =
             dest.table_name.temporary_table_name(temporary_storage)?;
@@ -97,16 +238,38 @@ pub(crate) async fn write_remote_data_helper(
         if_exists
     };
 
-    // Load our data.
+    // Load our data. Temporary tables are labeled, but never partitioned.
+    let initial_table_options = if use_temp {
+        NewTableOptions {
+            time_partitioning: None,
+            labels: new_table_options.labels.clone(),
+            expiration: None,
+        }
+    } else {
+        new_table_options.clone()
+    };
     bigquery::load(
         &ctx,
+        job_project,
         &source_url,
         &initial_table,
+        &location,
         if_initial_table_exists,
+        &initial_table_options,
         &job_labels,
     )
     .await?;
 
+    // Make sure our temp table gets cleaned up even if we crash before we
+    // drop it.
+    if let (true, Some(expiration)) = (use_temp, &new_table_options.expiration) {
+        let mut sql = vec![];
+        expiration.write_alter_table_sql(initial_table.name(), &mut sql)?;
+        let sql =
+            String::from_utf8(sql).expect("generated SQL should always be UTF-8");
+        bigquery::execute_sql(&ctx, job_project, &location, &sql, &job_labels).await?;
+    }
+
     // If `use_temp` is false, then we're done. Otherwise, run the update SQL to
     // build the final table (if needed).
     if use_temp {
@@ -124,15 +287,208 @@ pub(crate) async fn write_remote_data_helper(
 
         // Generate and run our import SQL.
         let mut query = Vec::new();
-        dest_table.write_import_sql(initial_table.name(), if_exists, &mut query)?;
+        dest_table.write_import_sql(
+            initial_table.name(),
+            if_exists,
+            &new_table_options,
+            &mut query,
+        )?;
         let query =
             String::from_utf8(query).expect("generated SQL should always be UTF-8");
         debug!(ctx.log(), "import sql: {}", query);
-        bigquery::execute_sql(&ctx, dest.project(), &query, &job_labels).await?;
+        bigquery::execute_sql(&ctx, job_project, &location, &query, &job_labels)
+            .await?;
 
         // Delete temp table.
-        bigquery::drop_table(&ctx, initial_table.name(), &job_labels).await?;
+        bigquery::drop_table(
+            &ctx,
+            job_project,
+            initial_table.name(),
+            &location,
+            &job_labels,
+        )
+        .await?;
     }
 
     Ok(vec![dest.boxed()])
 }
+
+/// Create an external table `dest` over the CSV files matching `source_url`.
+async fn create_external_table(
+    ctx: &Context,
+    source_url: &Url,
+    dest: &BigQueryLocator,
+    args: &BigQueryDestinationArguments,
+    schema: &Table,
+    location: &str,
+    if_exists: &IfExists,
+) -> Result<()> {
+    // When we write local data to `external_uri`, we pass it to ourselves as
+    // our source. Otherwise, it would be ignored, so complain.
+    if let Some(external_uri) = &args.external_uri {
+        if !source_url
+            .as_str()
+            .starts_with(external_uri.as_url().as_str())
+        {
+            return Err(format_err!(
+                "cannot use external_uri when copying from {}",
+                source_url,
+            ));
+        }
+    }
+
+    // External tables read CSV files directly, so we can't fix up any columns
+    // afterwards.
+    if !schema.bigquery_can_import_from_csv()? {
+        return Err(format_err!(
+            "cannot create external table {} because some columns can't be \
+             read from CSV files",
+            dest.table_name,
+        ));
+    }
+    let table = BqTable::for_table_name_and_columns(
+        dest.table_name.clone(),
+        &schema.columns,
+        Usage::FinalTable,
+    )?;
+
+    // Generate and run our SQL.
+    let mut sql = vec![];
+    table.write_create_external_table_sql(
+        source_url,
+        args.external_connection.as_ref(),
+        if_exists,
+        &args.new_table_options()?,
+        &mut sql,
+    )?;
+    let sql = String::from_utf8(sql).expect("generated SQL should always be UTF-8");
+    debug!(ctx.log(), "external table sql: {}", sql);
+    bigquery::execute_sql(
+        ctx,
+        args.job_project(dest),
+        location,
+        &sql,
+        &args.all_job_labels(),
+    )
+    .await
+}
+
+#[test]
+fn parse_partition_args() {
+    let args = DriverArguments::from_cli_args(&[
+        "partition_field=event_date",
+        "partition_type=DAY",
+        "partition_expiration_days=90",
+        "job_labels[team]=growth",
+    ])
+    .unwrap()
+    .deserialize::<BigQueryDestinationArguments>()
+    .unwrap();
+    assert_eq!(args.partition_field.as_deref(), Some("event_date"));
+    assert_eq!(args.partition_type, Some(PartitionType::Day));
+    assert_eq!(args.partition_expiration_days, Some(90));
+    let options = args.new_table_options().unwrap();
+    assert!(options.time_partitioning.is_some());
+    assert_eq!(args.job_labels.get("team").map(|s| &s[..]), Some("growth"));
+
+    let args = DriverArguments::from_cli_args(&["partition_type=day"]).unwrap();
+    assert!(args.deserialize::<BigQueryDestinationArguments>().is_err());
+}
+
+#[test]
+fn parse_labels_args() {
+    let args = DriverArguments::from_cli_args(&[
+        "labels=env:prod,team:growth",
+        "job_labels[team]=etl",
+    ])
+    .unwrap()
+    .deserialize::<BigQueryDestinationArguments>()
+    .unwrap();
+    let options = args.new_table_options().unwrap();
+    assert_eq!(options.labels.labels().len(), 2);
+    assert!(options.time_partitioning.is_none());
+    let job_labels = args.all_job_labels();
+    assert_eq!(job_labels.get("env").map(|s| &s[..]), Some("prod"));
+    assert_eq!(job_labels.get("team").map(|s| &s[..]), Some("etl"));
+
+    let args = DriverArguments::from_cli_args(&["labels=env=prod"]).unwrap();
+    assert!(args.deserialize::<BigQueryDestinationArguments>().is_err());
+}
+
+#[test]
+fn parse_expiration_args() {
+    let args = DriverArguments::from_cli_args(&["expiration=7d"])
+        .unwrap()
+        .deserialize::<BigQueryDestinationArguments>()
+        .unwrap();
+    let options = args.new_table_options().unwrap();
+    assert_eq!(options.expiration, Some("7d".parse().unwrap()));
+
+    let args = DriverArguments::from_cli_args(&["expiration=1w"]).unwrap();
+    assert!(args.deserialize::<BigQueryDestinationArguments>().is_err());
+}
+
+#[test]
+fn parse_location_args() {
+    let args = DriverArguments::from_cli_args(&["location=EU"])
+        .unwrap()
+        .deserialize::<BigQueryDestinationArguments>()
+        .unwrap();
+    assert_eq!(args.location.as_deref(), Some("EU"));
+
+    let args = DriverArguments::default()
+        .deserialize::<BigQueryDestinationArguments>()
+        .unwrap();
+    assert_eq!(args.location, None);
+}
+
+#[test]
+fn parse_billing_project_args() {
+    let dest = "bigquery:storage-project:dataset.table"
+        .parse::<BigQueryLocator>()
+        .unwrap();
+    let args = DriverArguments::from_cli_args(&["billing_project=etl-billing"])
+        .unwrap()
+        .deserialize::<BigQueryDestinationArguments>()
+        .unwrap();
+    assert_eq!(args.job_project(&dest), "etl-billing");
+
+    let args = DriverArguments::default()
+        .deserialize::<BigQueryDestinationArguments>()
+        .unwrap();
+    assert_eq!(args.job_project(&dest), "storage-project");
+}
+
+#[test]
+fn parse_external_args() {
+    let args = DriverArguments::from_cli_args(&[
+        "external=true",
+        "external_uri=gs://bucket/archive/",
+        "external_connection=project.us.lake",
+    ])
+    .unwrap()
+    .deserialize::<BigQueryDestinationArguments>()
+    .unwrap();
+    assert!(args.is_external().unwrap());
+    assert_eq!(
+        args.external_uri().unwrap().as_url().as_str(),
+        "gs://bucket/archive/",
+    );
+
+    let args = DriverArguments::from_cli_args(&["external=true"])
+        .unwrap()
+        .deserialize::<BigQueryDestinationArguments>()
+        .unwrap();
+    assert!(args.is_external().unwrap());
+    assert!(args.external_uri().is_err());
+
+    let args = DriverArguments::from_cli_args(&["external_uri=gs://bucket/archive/"])
+        .unwrap()
+        .deserialize::<BigQueryDestinationArguments>()
+        .unwrap();
+    assert!(args.is_external().is_err());
+
+    let args =
+        DriverArguments::from_cli_args(&["external_uri=gs://bucket/archive"]).unwrap();
+    assert!(args.deserialize::<BigQueryDestinationArguments>().is_err());
+}
@@ -46,6 +46,15 @@ pub(crate) struct BqColumn {
     #[serde(default)]
     mode: Mode,
 
+    /// The precision of a `NUMERIC` or `BIGNUMERIC` column, if specified. The
+    /// API represents this `int64` as a string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    precision: Option<String>,
+
+    /// The scale of a `NUMERIC` or `BIGNUMERIC` column, if specified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scale: Option<String>,
+
     /// If `ty` is `BqRecordOrNonArrayDataType::Record`, this will contain the fields
     /// we need to construct a struct.
     ///
@@ -73,13 +82,68 @@ impl BqColumn {
             }
             BqDataType::NonArray(ty) => (ty, Mode::Required),
         };
-        Ok(BqColumn {
+        Ok(BqColumn::for_non_array_data_type(
             name,
-            description: None,
-            ty: BqRecordOrNonArrayDataType::DataType(ty),
+            col.comment.clone(),
+            ty,
             mode,
-            fields: vec![],
-        })
+        ))
+    }
+
+    /// Construct a column of type `ty`. The BigQuery API doesn't allow types
+    /// like `NUMERIC(10, 2)`, so we move any precision and scale into separate
+    /// fields. Similarly, we represent `STRUCT` types as `RECORD` columns with
+    /// nested `fields`, which is what `bq mk --schema` and the API expect.
+    fn for_non_array_data_type(
+        name: ColumnName,
+        description: Option<String>,
+        ty: BqNonArrayDataType,
+        mode: Mode,
+    ) -> BqColumn {
+        let mut params = None;
+        let mut fields = vec![];
+        let ty = match ty {
+            BqNonArrayDataType::FixedNumeric { precision, scale } => {
+                params = Some((precision, scale));
+                BqRecordOrNonArrayDataType::DataType(BqNonArrayDataType::Numeric)
+            }
+            BqNonArrayDataType::FixedBigNumeric { precision, scale } => {
+                params = Some((precision, scale));
+                BqRecordOrNonArrayDataType::DataType(BqNonArrayDataType::BigNumeric)
+            }
+            // `RECORD` fields must have names, so we leave structs containing
+            // anonymous fields (which we use to represent nested arrays) as
+            // `STRUCT<...>` types.
+            BqNonArrayDataType::Struct(struct_fields)
+                if struct_fields.iter().all(|f| f.name.is_some()) =>
+            {
+                fields = struct_fields
+                    .into_iter()
+                    .map(|f| {
+                        let name = f.name.expect("checked for anonymous fields above");
+                        let (ty, mode) = match f.ty {
+                            BqDataType::Array(ty) => (ty, Mode::Repeated),
+                            BqDataType::NonArray(ty) if f.is_nullable => {
+                                (ty, Mode::Nullable)
+                            }
+                            BqDataType::NonArray(ty) => (ty, Mode::Required),
+                        };
+                        BqColumn::for_non_array_data_type(name, None, ty, mode)
+                    })
+                    .collect();
+                BqRecordOrNonArrayDataType::Record
+            }
+            ty => BqRecordOrNonArrayDataType::DataType(ty),
+        };
+        BqColumn {
+            name,
+            description,
+            ty,
+            mode,
+            precision: params.map(|(precision, _)| precision.to_string()),
+            scale: params.map(|(_, scale)| scale.to_string()),
+            fields,
+        }
     }
 
     /// Given a `BqColumn`, construct a portable `Column`.
@@ -117,7 +181,24 @@ impl BqColumn {
                     .collect::<Result<Vec<_>>>()?;
                 BqNonArrayDataType::Struct(fields)
             }
-            BqRecordOrNonArrayDataType::DataType(ty) => ty.to_owned(),
+            BqRecordOrNonArrayDataType::DataType(ty) => match self.numeric_params()? {
+                Some((precision, scale)) => match ty {
+                    BqNonArrayDataType::Numeric => {
+                        BqNonArrayDataType::FixedNumeric { precision, scale }
+                    }
+                    BqNonArrayDataType::BigNumeric => {
+                        BqNonArrayDataType::FixedBigNumeric { precision, scale }
+                    }
+                    _ => {
+                        return Err(format_err!(
+                            "column {} has a precision, but it is a {}",
+                            self.name.quoted(),
+                            ty,
+                        ))
+                    }
+                },
+                None => ty.to_owned(),
+            },
         };
         match self.mode {
             Mode::Repeated => Ok(BqDataType::Array(ty)),
@@ -125,6 +206,31 @@ impl BqColumn {
         }
     }
 
+    /// The precision and scale of this column, if specified. If we only have a
+    /// precision, the scale defaults to 0.
+    fn numeric_params(&self) -> Result<Option<(u32, u32)>> {
+        let parse = |s: &str| -> Result<u32> {
+            Ok(s.parse::<u32>().with_context(|_| {
+                format!(
+                    "cannot parse precision or scale {:?} for column {}",
+                    s,
+                    self.name.quoted(),
+                )
+            })?)
+        };
+        match (&self.precision, &self.scale) {
+            (None, None) => Ok(None),
+            (Some(precision), scale) => {
+                let scale = scale.as_ref().map(|s| parse(s)).transpose()?;
+                Ok(Some((parse(precision)?, scale.unwrap_or(0))))
+            }
+            (None, Some(_)) => Err(format_err!(
+                "column {} has a scale but no precision",
+                self.name.quoted(),
+            )),
+        }
+    }
+
     /// Should this column be declared as `NOT NULL` when generating a `CREATE TABLE`?
     pub(crate) fn is_not_null(&self) -> bool {
         match &self.mode {
@@ -133,6 +239,15 @@ impl BqColumn {
         }
     }
 
+    /// Write an `OPTIONS(...)` clause for this column, preceded by a space, if
+    /// we need one when generating a `CREATE TABLE`.
+    pub(crate) fn write_column_options_sql(&self, f: &mut dyn Write) -> Result<()> {
+        if let Some(description) = &self.description {
+            write!(f, " OPTIONS(description={})", string_literal(description))?;
+        }
+        Ok(())
+    }
+
     /// Given two columns with the same name, use `other` to "upgrade" the
     /// information that we have about `self`, and return the result.
     ///
@@ -158,30 +273,28 @@ impl BqColumn {
         let aligned_ty = self_ty.aligned_with(&other_ty)?;
 
         // Reconstruct our column using the new type. This is unnecessarily
-        // annoying because of how BigQuery represents structs and arrays. This
-        // will also end up replacing `RECORD` with a `STRUCT` type, just to make things
-        // easier.
+        // annoying because of how BigQuery represents structs and arrays.
         match (self.mode, aligned_ty) {
-            (Mode::Repeated, BqDataType::Array(nested)) => Ok(Self {
-                description: self.description.clone(),
-                name: self.name.clone(),
-                ty: BqRecordOrNonArrayDataType::DataType(nested),
-                mode: self.mode,
-                fields: vec![],
-            }),
+            (Mode::Repeated, BqDataType::Array(nested)) => {
+                Ok(Self::for_non_array_data_type(
+                    self.name.clone(),
+                    self.description.clone(),
+                    nested,
+                    self.mode,
+                ))
+            }
             (Mode::Repeated, _) => {
                 unreachable!("should never have REPEATED without ARRAY")
             }
             (_, BqDataType::Array(_)) => {
                 unreachable!("should never have ARRAY without REPEATED")
             }
-            (_, BqDataType::NonArray(nested)) => Ok(Self {
-                description: self.description.clone(),
-                name: self.name.clone(),
-                ty: BqRecordOrNonArrayDataType::DataType(nested),
-                mode: self.mode,
-                fields: vec![],
-            }),
+            (_, BqDataType::NonArray(nested)) => Ok(Self::for_non_array_data_type(
+                self.name.clone(),
+                self.description.clone(),
+                nested,
+                self.mode,
+            )),
         }
     }
 
@@ -191,6 +304,7 @@ impl BqColumn {
         Ok(BqStructField {
             name: Some(self.name.clone()),
             ty: self.bq_data_type()?,
+            is_nullable: self.mode != Mode::Required,
         })
     }
 
@@ -343,6 +457,9 @@ AS ((
             | BqNonArrayDataType::Float64
             | BqNonArrayDataType::Int64
             | BqNonArrayDataType::Numeric
+            | BqNonArrayDataType::FixedNumeric { .. }
+            | BqNonArrayDataType::BigNumeric
+            | BqNonArrayDataType::FixedBigNumeric { .. }
             | BqNonArrayDataType::String => {
                 write!(f, "{}", self.name.quoted())?;
             }
@@ -402,6 +519,9 @@ AS ((
             | BqNonArrayDataType::Float64
             | BqNonArrayDataType::Int64
             | BqNonArrayDataType::Numeric
+            | BqNonArrayDataType::FixedNumeric { .. }
+            | BqNonArrayDataType::BigNumeric
+            | BqNonArrayDataType::FixedBigNumeric { .. }
             | BqNonArrayDataType::String
             | BqNonArrayDataType::Stringified(_) => {
                 write!(f, "{}", self.name.quoted())?;
@@ -469,6 +589,25 @@ AS ((
     }
 }
 
+/// Quote `s` as a BigQuery string literal.
+pub(crate) fn string_literal(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
 #[test]
 fn column_without_mode() {
     let json = r#"{"type":"STRING","name":"state"}"#;
@@ -476,6 +615,162 @@ fn column_without_mode() {
     assert_eq!(col.mode, Mode::Nullable);
 }
 
+#[test]
+fn column_descriptions() {
+    use crate::schema::DataType;
+
+    let portable = Column {
+        name: "note".to_owned(),
+        is_nullable: true,
+        data_type: DataType::Text,
+        comment: Some("Say \"hi\"\n\\o/".to_owned()),
+    };
+    let name = ColumnName::try_from("note").unwrap();
+    let col = BqColumn::for_column(name, &portable, Usage::FinalTable).unwrap();
+    assert_eq!(col.to_column().unwrap(), portable);
+    assert_eq!(
+        serde_json::to_value(&col).unwrap()["description"],
+        serde_json::json!("Say \"hi\"\n\\o/"),
+    );
+
+    let mut out = vec![];
+    col.write_column_options_sql(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#" OPTIONS(description="Say \"hi\"\n\\o/")"#,
+    );
+}
+
+#[test]
+fn column_numeric_precision_and_scale() {
+    use crate::schema::DataType;
+
+    let portable = Column {
+        name: "price".to_owned(),
+        is_nullable: false,
+        data_type: DataType::FixedDecimal {
+            precision: 10,
+            scale: 2,
+        },
+        comment: None,
+    };
+    let name = ColumnName::try_from("price").unwrap();
+    let col = BqColumn::for_column(name, &portable, Usage::FinalTable).unwrap();
+    assert_eq!(
+        serde_json::to_value(&col).unwrap(),
+        serde_json::json!({
+            "name": "price",
+            "type": "NUMERIC",
+            "mode": "REQUIRED",
+            "precision": "10",
+            "scale": "2",
+        }),
+    );
+    assert_eq!(col.bq_data_type().unwrap().to_string(), "NUMERIC(10, 2)");
+    assert_eq!(col.to_column().unwrap(), portable);
+
+    let json = r#"{"type":"BIGNUMERIC","name":"big","precision":"50"}"#;
+    let col: BqColumn = serde_json::from_str(json).unwrap();
+    assert_eq!(col.bq_data_type().unwrap().to_string(), "BIGNUMERIC(50, 0)");
+
+    let json = r#"{"type":"STRING","name":"s","precision":"10"}"#;
+    let col: BqColumn = serde_json::from_str(json).unwrap();
+    assert!(col.bq_data_type().is_err());
+}
+
+#[test]
+fn struct_columns_use_records() {
+    use crate::schema::{DataType, StructField};
+
+    let field = |name: &str, data_type| StructField {
+        name: name.to_owned(),
+        is_nullable: true,
+        data_type,
+    };
+    let portable = Column {
+        name: "events".to_owned(),
+        is_nullable: true,
+        data_type: DataType::Array(Box::new(DataType::Struct(vec![
+            StructField {
+                name: "id".to_owned(),
+                is_nullable: false,
+                data_type: DataType::Int64,
+            },
+            field("tags", DataType::Array(Box::new(DataType::Text))),
+            field(
+                "amount",
+                DataType::FixedDecimal {
+                    precision: 10,
+                    scale: 2,
+                },
+            ),
+            field("payload", DataType::Json),
+        ]))),
+        comment: None,
+    };
+    let name = ColumnName::try_from("events").unwrap();
+    let col = BqColumn::for_column(name, &portable, Usage::FinalTable).unwrap();
+    assert_eq!(
+        serde_json::to_value(&col).unwrap(),
+        serde_json::json!({
+            "name": "events",
+            "type": "RECORD",
+            "mode": "REPEATED",
+            "fields": [
+                { "name": "id", "type": "INT64", "mode": "REQUIRED" },
+                { "name": "tags", "type": "STRING", "mode": "REPEATED" },
+                {
+                    "name": "amount",
+                    "type": "NUMERIC",
+                    "mode": "NULLABLE",
+                    "precision": "10",
+                    "scale": "2",
+                },
+                { "name": "payload", "type": "STRING", "mode": "NULLABLE" },
+            ],
+        }),
+    );
+    assert_eq!(
+        col.bq_data_type().unwrap().to_string(),
+        "ARRAY<STRUCT<`id` INT64 NOT NULL,`tags` ARRAY<STRING>,\
+         `amount` NUMERIC(10, 2),`payload` STRING>>",
+    );
+    assert_eq!(col.to_column().unwrap(), portable);
+
+    // UDF signatures can't contain `NOT NULL`.
+    let mut import = vec![];
+    col.write_import_udf(&mut import, 0).unwrap();
+    let import = String::from_utf8(import).unwrap();
+    assert!(import.contains("RETURNS ARRAY<STRUCT<`id` INT64,`tags`"));
+}
+
+#[test]
+fn json_arrays_use_udfs() {
+    use crate::schema::DataType;
+
+    let portable = Column {
+        name: "docs".to_owned(),
+        is_nullable: true,
+        data_type: DataType::Array(Box::new(DataType::Json)),
+        comment: None,
+    };
+    let name = ColumnName::try_from("docs").unwrap();
+    let col = BqColumn::for_column(name, &portable, Usage::FinalTable).unwrap();
+    assert_eq!(col.bq_data_type().unwrap().to_string(), "ARRAY<STRING>");
+    assert_eq!(col.to_column().unwrap(), portable);
+
+    let mut import = vec![];
+    col.write_import_udf(&mut import, 0).unwrap();
+    let import = String::from_utf8(import).unwrap();
+    assert!(import.contains("RETURNS ARRAY<STRING>"));
+    assert!(import.contains("return JSON.stringify(e);"));
+
+    let mut export = vec![];
+    col.write_export_udf(&mut export, 0).unwrap();
+    let export = String::from_utf8(export).unwrap();
+    assert!(export.contains("return JSON.parse(e);"));
+}
+
 /// A column mode.
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
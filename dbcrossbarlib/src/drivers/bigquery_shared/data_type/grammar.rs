@@ -27,7 +27,12 @@ peg::parser! {
         pub rule non_array_data_type() -> BqNonArrayDataType
             // BOOLEAN, FLOAT and INTEGER are undocumented but seen in `bq show --schema`
             // output. Also, longer names must go first.
-            = "BOOLEAN" { BqNonArrayDataType::Bool }
+            = "BIGNUMERIC" params:numeric_params() {
+                let (precision, scale) = params;
+                BqNonArrayDataType::FixedBigNumeric { precision, scale }
+            }
+            / "BIGNUMERIC" { BqNonArrayDataType::BigNumeric }
+            / "BOOLEAN" { BqNonArrayDataType::Bool }
             / "BOOL" { BqNonArrayDataType::Bool }
             / "BYTES" { BqNonArrayDataType::Bytes }
             / "DATETIME" { BqNonArrayDataType::Datetime }
@@ -37,12 +42,27 @@ peg::parser! {
             / "GEOGRAPHY" { BqNonArrayDataType::Geography }
             / "INT64" { BqNonArrayDataType::Int64 }
             / "INTEGER" { BqNonArrayDataType::Int64 }
+            / "NUMERIC" params:numeric_params() {
+                let (precision, scale) = params;
+                BqNonArrayDataType::FixedNumeric { precision, scale }
+            }
             / "NUMERIC" { BqNonArrayDataType::Numeric }
             / "STRING" { BqNonArrayDataType::String }
             / "TIMESTAMP" { BqNonArrayDataType::Timestamp }
             / "TIME" { BqNonArrayDataType::Time }
             / struct()
 
+        /// The `(precision, scale)` of a `NUMERIC` type. The scale defaults to 0.
+        rule numeric_params() -> (u32, u32)
+            = "(" ws()? precision:number() ws()?
+              scale:("," ws()? scale:number() ws()? { scale })? ")" {
+                (precision, scale.unwrap_or(0))
+            }
+
+        /// A non-negative integer.
+        rule number() -> u32
+            = n:$(['0'..='9']+) {? n.parse().or(Err("number")) }
+
         rule struct() -> BqNonArrayDataType
             = "STRUCT<" fields:(field() ++ ("," ws()?)) ">" { BqNonArrayDataType::Struct(fields) }
 
@@ -50,10 +70,15 @@ peg::parser! {
             // Since data_type is often a valid field_name, try matching this case first,
             // and only try matching a bare data_type if this fails. This might not be
             // optimal.
-            = name:field_name() ws()? ty:data_type() {
-                BqStructField { name: Some(name), ty }
+            = name:field_name() ws()? ty:data_type() is_nullable:is_nullable() {
+                BqStructField { name: Some(name), ty, is_nullable }
             }
-            / ty:data_type() { BqStructField { name: None, ty }}
+            / ty:data_type() { BqStructField { name: None, ty, is_nullable: true }}
+
+        // An optional `NOT NULL` annotation on a struct field.
+        rule is_nullable() -> bool
+            = ws() "NOT" ws() "NULL" { false }
+            / { true }
 
         // I can't find a syntax for field names in the docs, so let's assume
         // unquoted C identifiers for now.
@@ -65,11 +65,6 @@ impl BqDataType {
                 Ok(BqDataType::NonArray(BqNonArrayDataType::String))
             }
             (DataType::Array(nested), _) => {
-                if let DataType::Json = nested.as_ref() {
-                    return Err(format_err!(
-                        "cannot represent arrays of JSON in BigQuery yet"
-                    ));
-                }
                 let bq_nested = BqNonArrayDataType::for_data_type(nested, usage)?;
                 Ok(BqDataType::Array(bq_nested))
             }
@@ -96,6 +91,16 @@ impl BqDataType {
         }
     }
 
+    /// Remove any `NOT NULL` annotations from struct fields in this type.
+    /// BigQuery only allows these in table schemas, so we need to do this
+    /// before using a type in a UDF signature.
+    pub(crate) fn without_not_null(&self) -> BqDataType {
+        match self {
+            BqDataType::Array(ty) => BqDataType::Array(ty.without_not_null()),
+            BqDataType::NonArray(ty) => BqDataType::NonArray(ty.without_not_null()),
+        }
+    }
+
     /// Can this type be safely represented as a JSON value?
     pub(crate) fn is_json_safe(&self) -> bool {
         match self {
@@ -213,10 +218,13 @@ impl Serialize for BqRecordOrNonArrayDataType {
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[allow(dead_code)]
 pub enum BqNonArrayDataType {
+    BigNumeric,
     Bool,
     Bytes,
     Date,
     Datetime,
+    FixedBigNumeric { precision: u32, scale: u32 },
+    FixedNumeric { precision: u32, scale: u32 },
     Float64,
     Geography,
     Int64,
@@ -260,12 +268,35 @@ impl BqNonArrayDataType {
                 let field = BqStructField {
                     name: None,
                     ty: BqDataType::Array(bq_nested),
+                    is_nullable: true,
                 };
                 Ok(BqNonArrayDataType::Struct(vec![field]))
             }
             DataType::Bool => Ok(BqNonArrayDataType::Bool),
             DataType::Date => Ok(BqNonArrayDataType::Date),
             DataType::Decimal => Ok(BqNonArrayDataType::Numeric),
+            DataType::FixedDecimal { precision, scale } => {
+                let (precision, scale) = (*precision, *scale);
+                let int_digits = precision.checked_sub(scale);
+                if precision == 0 || int_digits.is_none() {
+                    Err(format_err!(
+                        "cannot use NUMERIC({}, {}), because the scale is larger \
+                         than the precision",
+                        precision,
+                        scale,
+                    ))
+                } else if scale <= 9 && int_digits <= Some(29) {
+                    Ok(BqNonArrayDataType::FixedNumeric { precision, scale })
+                } else if scale <= 38 && int_digits <= Some(38) {
+                    Ok(BqNonArrayDataType::FixedBigNumeric { precision, scale })
+                } else {
+                    Err(format_err!(
+                        "BigQuery cannot store NUMERIC({}, {}) without rounding",
+                        precision,
+                        scale,
+                    ))
+                }
+            }
             DataType::Float32 => Ok(BqNonArrayDataType::Float64),
             DataType::Float64 => Ok(BqNonArrayDataType::Float64),
             DataType::GeoJson(srid) if *srid == Srid::wgs84() => {
@@ -303,6 +334,19 @@ impl BqNonArrayDataType {
             BqNonArrayDataType::Bool => Ok(DataType::Bool),
             BqNonArrayDataType::Date => Ok(DataType::Date),
             BqNonArrayDataType::Numeric => Ok(DataType::Decimal),
+            // `BIGNUMERIC` is really `BIGNUMERIC(76.76, 38)`, but 76 digits is
+            // enough for all but the very largest values.
+            BqNonArrayDataType::BigNumeric => Ok(DataType::FixedDecimal {
+                precision: 76,
+                scale: 38,
+            }),
+            BqNonArrayDataType::FixedBigNumeric { precision, scale }
+            | BqNonArrayDataType::FixedNumeric { precision, scale } => {
+                Ok(DataType::FixedDecimal {
+                    precision: *precision,
+                    scale: *scale,
+                })
+            }
             BqNonArrayDataType::Float64 => Ok(DataType::Float64),
             BqNonArrayDataType::Geography => Ok(DataType::GeoJson(Srid::wgs84())),
             BqNonArrayDataType::Int64 => Ok(DataType::Int64),
@@ -348,6 +392,23 @@ impl BqNonArrayDataType {
         }
     }
 
+    /// Remove any `NOT NULL` annotations from struct fields in this type.
+    fn without_not_null(&self) -> BqNonArrayDataType {
+        match self {
+            BqNonArrayDataType::Struct(fields) => BqNonArrayDataType::Struct(
+                fields
+                    .iter()
+                    .map(|f| BqStructField {
+                        name: f.name.clone(),
+                        ty: f.ty.without_not_null(),
+                        is_nullable: true,
+                    })
+                    .collect(),
+            ),
+            other => other.to_owned(),
+        }
+    }
+
     /// Can this type be safely represented as a JSON value?
     pub(crate) fn is_json_safe(&self) -> bool {
         match self {
@@ -446,10 +507,17 @@ impl<'de> Deserialize<'de> for BqNonArrayDataType {
 impl fmt::Display for BqNonArrayDataType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            BqNonArrayDataType::BigNumeric => write!(f, "BIGNUMERIC"),
             BqNonArrayDataType::Bool => write!(f, "BOOL"),
             BqNonArrayDataType::Bytes => write!(f, "BYTES"),
             BqNonArrayDataType::Date => write!(f, "DATE"),
             BqNonArrayDataType::Datetime => write!(f, "DATETIME"),
+            BqNonArrayDataType::FixedBigNumeric { precision, scale } => {
+                write!(f, "BIGNUMERIC({}, {})", precision, scale)
+            }
+            BqNonArrayDataType::FixedNumeric { precision, scale } => {
+                write!(f, "NUMERIC({}, {})", precision, scale)
+            }
             BqNonArrayDataType::Float64 => write!(f, "FLOAT64"),
             BqNonArrayDataType::Geography => write!(f, "GEOGRAPHY"),
             BqNonArrayDataType::Int64 => write!(f, "INT64"),
@@ -495,15 +563,21 @@ pub struct BqStructField {
     pub(crate) name: Option<ColumnName>,
     /// The field type.
     pub(crate) ty: BqDataType,
+    /// Can this field be `NULL`? BigQuery does not allow `ARRAY` fields to be
+    /// declared `NOT NULL`, so this is always true for arrays.
+    pub(crate) is_nullable: bool,
 }
 
 impl BqStructField {
     /// Create a `BqStructField` from a portable `StructField`.
     fn for_struct_field(f: &StructField) -> Result<Self> {
         let name = ColumnName::try_from(&f.name)?;
+        let ty = BqDataType::for_data_type(&f.data_type, Usage::FinalTable)?;
+        let is_nullable = f.is_nullable || matches!(ty, BqDataType::Array(_));
         Ok(BqStructField {
             name: Some(name),
-            ty: BqDataType::for_data_type(&f.data_type, Usage::FinalTable)?,
+            ty,
+            is_nullable,
         })
     }
 
@@ -514,7 +588,7 @@ impl BqStructField {
             assert!(!name.as_str().is_empty());
             Ok(StructField {
                 name: name.to_portable_name(),
-                is_nullable: true,
+                is_nullable: self.is_nullable,
                 data_type: self.ty.to_data_type()?,
             })
         } else {
@@ -532,7 +606,11 @@ impl fmt::Display for BqStructField {
             // `Ident` to insert backticks.
             write!(f, "{} ", name.quoted())?;
         }
-        write!(f, "{}", self.ty)
+        write!(f, "{}", self.ty)?;
+        if !self.is_nullable {
+            write!(f, " NOT NULL")?;
+        }
+        Ok(())
     }
 }
 
@@ -554,6 +632,31 @@ fn nested_arrays() {
     );
 }
 
+#[test]
+fn fixed_decimals() {
+    let bq = |precision, scale| {
+        let ty = DataType::FixedDecimal { precision, scale };
+        BqDataType::for_data_type(&ty, Usage::FinalTable).map(|bq| bq.to_string())
+    };
+    assert_eq!(bq(10, 2).unwrap(), "NUMERIC(10, 2)");
+    assert_eq!(bq(38, 9).unwrap(), "NUMERIC(38, 9)");
+    assert_eq!(bq(39, 9).unwrap(), "BIGNUMERIC(39, 9)");
+    assert_eq!(bq(20, 12).unwrap(), "BIGNUMERIC(20, 12)");
+    assert!(bq(0, 0).is_err());
+    assert!(bq(2, 3).is_err());
+    assert!(bq(77, 38).is_err());
+    assert!(bq(40, 39).is_err());
+
+    let ty = BqNonArrayDataType::BigNumeric;
+    assert_eq!(
+        ty.to_data_type().unwrap(),
+        DataType::FixedDecimal {
+            precision: 76,
+            scale: 38,
+        },
+    );
+}
+
 #[test]
 fn parsing() {
     use std::convert::TryFrom;
@@ -570,6 +673,28 @@ fn parsing() {
         ("GEOGRAPHY", DT::NonArray(NADT::Geography)),
         ("INT64", DT::NonArray(NADT::Int64)),
         ("NUMERIC", DT::NonArray(NADT::Numeric)),
+        (
+            "NUMERIC(10, 2)",
+            DT::NonArray(NADT::FixedNumeric {
+                precision: 10,
+                scale: 2,
+            }),
+        ),
+        (
+            "NUMERIC(5)",
+            DT::NonArray(NADT::FixedNumeric {
+                precision: 5,
+                scale: 0,
+            }),
+        ),
+        ("BIGNUMERIC", DT::NonArray(NADT::BigNumeric)),
+        (
+            "BIGNUMERIC(50,20)",
+            DT::NonArray(NADT::FixedBigNumeric {
+                precision: 50,
+                scale: 20,
+            }),
+        ),
         ("STRING", DT::NonArray(NADT::String)),
         ("TIME", DT::NonArray(NADT::Time)),
         ("TIMESTAMP", DT::NonArray(NADT::Timestamp)),
@@ -580,10 +705,12 @@ fn parsing() {
                 BqStructField {
                     name: None,
                     ty: DT::NonArray(NADT::Float64),
+                    is_nullable: true,
                 },
                 BqStructField {
                     name: None,
                     ty: DT::NonArray(NADT::Float64),
+                    is_nullable: true,
                 },
             ])),
         ),
@@ -593,10 +720,27 @@ fn parsing() {
                 BqStructField {
                     name: Some(ColumnName::try_from("x").unwrap()),
                     ty: DT::NonArray(NADT::Float64),
+                    is_nullable: true,
+                },
+                BqStructField {
+                    name: Some(ColumnName::try_from("y").unwrap()),
+                    ty: DT::NonArray(NADT::Float64),
+                    is_nullable: true,
+                },
+            ])),
+        ),
+        (
+            "STRUCT<x FLOAT64 NOT NULL, y FLOAT64>",
+            DT::NonArray(NADT::Struct(vec![
+                BqStructField {
+                    name: Some(ColumnName::try_from("x").unwrap()),
+                    ty: DT::NonArray(NADT::Float64),
+                    is_nullable: false,
                 },
                 BqStructField {
                     name: Some(ColumnName::try_from("y").unwrap()),
                     ty: DT::NonArray(NADT::Float64),
+                    is_nullable: true,
                 },
             ])),
         ),
@@ -605,6 +749,7 @@ fn parsing() {
             DT::Array(NADT::Struct(vec![BqStructField {
                 name: None,
                 ty: DT::Array(NADT::Int64),
+                is_nullable: true,
             }])),
         ),
     ];
@@ -11,4 +11,20 @@ pub(crate) struct GCloudDriverArguments {
     /// Billing labels to apply to objects and jobs.
     #[serde(default)]
     pub(crate) job_labels: Labels,
+
+    /// The location of our dataset, like `EU`. If this isn't specified, we look
+    /// it up.
+    pub(crate) location: Option<String>,
+
+    /// The project in which to run and bill our jobs, if it's different from
+    /// the project containing our table.
+    pub(crate) billing_project: Option<String>,
+}
+
+impl GCloudDriverArguments {
+    /// The project in which to run jobs which read from a table in
+    /// `table_project`.
+    pub(crate) fn job_project<'a>(&'a self, table_project: &'a str) -> &'a str {
+        self.billing_project.as_deref().unwrap_or(table_project)
+    }
 }
@@ -72,6 +72,9 @@ fn non_array_needs_custom_json_export(
         | BqNonArrayDataType::Float64
         | BqNonArrayDataType::Int64
         | BqNonArrayDataType::Numeric
+        | BqNonArrayDataType::FixedNumeric { .. }
+        | BqNonArrayDataType::BigNumeric
+        | BqNonArrayDataType::FixedBigNumeric { .. }
         | BqNonArrayDataType::String
         | BqNonArrayDataType::Timestamp => Ok(NeedsCustomJsonExport::Never),
 
@@ -136,7 +139,7 @@ pub(crate) fn generate_export_udf(
     idx: usize,
     f: &mut dyn Write,
 ) -> Result<()> {
-    let ty = column.bq_data_type()?;
+    let ty = column.bq_data_type()?.without_not_null();
     write!(
         f,
         r#"CREATE TEMP FUNCTION ExportJson_{idx}(value {bq_type})
@@ -228,6 +231,9 @@ fn write_non_array_transform_expr(
         | BqNonArrayDataType::Float64
         | BqNonArrayDataType::Int64
         | BqNonArrayDataType::Numeric
+        | BqNonArrayDataType::FixedNumeric { .. }
+        | BqNonArrayDataType::BigNumeric
+        | BqNonArrayDataType::FixedBigNumeric { .. }
         | BqNonArrayDataType::String
         | BqNonArrayDataType::Timestamp => {
             write!(f, "{}", input_expr)?;
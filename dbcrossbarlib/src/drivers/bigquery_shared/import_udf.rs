@@ -15,7 +15,7 @@ pub(crate) fn generate_import_udf(
     idx: usize,
     f: &mut dyn Write,
 ) -> Result<()> {
-    let ty = column.bq_data_type()?;
+    let ty = column.bq_data_type()?.without_not_null();
     write!(
         f,
         r#"CREATE TEMP FUNCTION ImportJson_{idx}(json_string STRING)
@@ -80,6 +80,9 @@ fn write_non_array_transform_expr(
         | BqNonArrayDataType::Float64
         | BqNonArrayDataType::Int64
         | BqNonArrayDataType::Numeric
+        | BqNonArrayDataType::FixedNumeric { .. }
+        | BqNonArrayDataType::BigNumeric
+        | BqNonArrayDataType::FixedBigNumeric { .. }
         | BqNonArrayDataType::String => {
             write!(f, "{}", input_expr)?;
         }
@@ -19,6 +19,8 @@ mod import_udf;
 mod indent_level;
 mod table;
 mod table_name;
+mod table_options;
+mod time_partitioning;
 
 pub(crate) use self::column::*;
 pub(crate) use self::column_name::*;
@@ -26,3 +28,5 @@ pub(crate) use self::data_type::*;
 pub(crate) use self::driver_args::*;
 pub(crate) use self::table::*;
 pub(crate) use self::table_name::*;
+pub(crate) use self::table_options::*;
+pub(crate) use self::time_partitioning::*;
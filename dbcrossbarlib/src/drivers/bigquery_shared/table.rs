@@ -8,7 +8,10 @@ use std::{
     iter::FromIterator,
 };
 
-use super::{BqColumn, ColumnBigQueryExt, ColumnName, TableName, Usage};
+use super::{
+    string_literal, BqColumn, ColumnBigQueryExt, ColumnName, ConnectionId,
+    NewTableOptions, TableName, Usage,
+};
 use crate::clouds::gcloud::bigquery;
 use crate::common::*;
 use crate::schema::{Column, Table};
@@ -161,11 +164,13 @@ impl BqTable {
 
     /// Generate SQL which imports data from a temp table into a final
     /// destination table, fixing any columns that couldn't be directly imported
-    /// from CSVs.
+    /// from CSVs. If we need to create the destination table, we use
+    /// `options`.
     pub(crate) fn write_import_sql(
         &self,
         source_table_name: &TableName,
         if_exists: &IfExists,
+        options: &NewTableOptions,
         f: &mut dyn Write,
     ) -> Result<()> {
         // Write out any helper functions we'll need to transform data.
@@ -180,7 +185,7 @@ impl BqTable {
             IfExists::Error => CreateTableType::Plain,
             IfExists::Overwrite => CreateTableType::OrReplace,
         };
-        self.write_create_table_sql(create_table_type, f)?;
+        self.write_create_table_sql(create_table_type, options, f)?;
         writeln!(f)?;
 
         match if_exists {
@@ -199,6 +204,7 @@ impl BqTable {
     fn write_create_table_sql(
         &self,
         create_table_type: CreateTableType,
+        options: &NewTableOptions,
         f: &mut dyn Write,
     ) -> Result<()> {
         // Write the appropriate CREATE TABLE part.
@@ -210,6 +216,62 @@ impl BqTable {
         )?;
 
         // Write the columns.
+        self.write_column_definitions(f)?;
+
+        // Write the footer.
+        write!(f, "\n)")?;
+        options.write_create_table_clauses(self, f)?;
+        writeln!(f, ";")?;
+        Ok(())
+    }
+
+    /// Write a `CREATE EXTERNAL TABLE` statement for this table, which reads
+    /// CSV files matching `uri`. If we have a `connection`, this will create a
+    /// BigLake table.
+    pub(crate) fn write_create_external_table_sql(
+        &self,
+        uri: &Url,
+        connection: Option<&ConnectionId>,
+        if_exists: &IfExists,
+        options: &NewTableOptions,
+        f: &mut dyn Write,
+    ) -> Result<()> {
+        if options.time_partitioning.is_some() {
+            return Err(format_err!("cannot partition external table {}", self.name,));
+        }
+        let create_table = match if_exists {
+            IfExists::Error => "CREATE EXTERNAL TABLE",
+            IfExists::Overwrite => "CREATE OR REPLACE EXTERNAL TABLE",
+            IfExists::Append | IfExists::Upsert(_) => {
+                return Err(format_err!(
+                    "external table {} only supports --if-exists=error or \
+                     --if-exists=overwrite",
+                    self.name,
+                ));
+            }
+        };
+        writeln!(f, "{} {} (", create_table, self.name.dotted_and_quoted())?;
+        self.write_column_definitions(f)?;
+        write!(f, "\n)")?;
+        if let Some(connection) = connection {
+            write!(f, "\nWITH CONNECTION {}", connection.quoted())?;
+        }
+        options.write_options_clause(
+            vec![
+                "format=\"CSV\"".to_owned(),
+                format!("uris=[{}]", string_literal(uri.as_str())),
+                "skip_leading_rows=1".to_owned(),
+                "allow_quoted_newlines=true".to_owned(),
+            ],
+            f,
+        )?;
+        writeln!(f, ";")?;
+        Ok(())
+    }
+
+    /// Write the column definitions used by `CREATE TABLE`, separated by
+    /// commas and newlines.
+    fn write_column_definitions(&self, f: &mut dyn Write) -> Result<()> {
         for (i, col) in self.columns.iter().enumerate() {
             if i > 0 {
                 writeln!(f, ",")?;
@@ -218,10 +280,8 @@ impl BqTable {
             if col.is_not_null() {
                 write!(f, " NOT NULL")?;
             }
+            col.write_column_options_sql(f)?;
         }
-
-        // Write the footer.
-        writeln!(f, "\n);")?;
         Ok(())
     }
 
@@ -387,3 +447,56 @@ WHEN NOT MATCHED THEN INSERT (
         Ok(())
     }
 }
+
+#[test]
+fn create_external_table_sql() {
+    let table = BqTable::for_table_name_and_columns(
+        "project:dataset.archive".parse::<TableName>().unwrap(),
+        &[Column {
+            name: "id".to_owned(),
+            is_nullable: false,
+            data_type: crate::schema::DataType::Int64,
+            comment: None,
+        }],
+        Usage::FinalTable,
+    )
+    .unwrap();
+    let uri = "gs://bucket/archive/*.csv".parse::<Url>().unwrap();
+    let connection = "project.us.lake".parse::<ConnectionId>().unwrap();
+    let labels = "env:prod".parse().unwrap();
+    let options = NewTableOptions {
+        time_partitioning: None,
+        labels,
+        expiration: None,
+    };
+    let mut out = vec![];
+    table
+        .write_create_external_table_sql(
+            &uri,
+            Some(&connection),
+            &IfExists::Overwrite,
+            &options,
+            &mut out,
+        )
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"CREATE OR REPLACE EXTERNAL TABLE `project`.`dataset`.`archive` (
+    `id` INT64 NOT NULL
+)
+WITH CONNECTION `project.us.lake`
+OPTIONS(format="CSV", uris=["gs://bucket/archive/*.csv"], skip_leading_rows=1, allow_quoted_newlines=true, labels=[("env", "prod")]);
+"#,
+    );
+
+    let mut out = vec![];
+    assert!(table
+        .write_create_external_table_sql(
+            &uri,
+            None,
+            &IfExists::Append,
+            &NewTableOptions::default(),
+            &mut out,
+        )
+        .is_err());
+}
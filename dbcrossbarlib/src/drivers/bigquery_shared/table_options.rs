@@ -0,0 +1,389 @@
+//! Options for the BigQuery tables we create.
+
+use std::str::FromStr;
+
+use super::{BqTable, TableName, TimePartitioning};
+use crate::clouds::gcloud::bigquery::Labels;
+use crate::common::*;
+
+/// The maximum number of labels BigQuery allows on a resource.
+const MAX_LABELS: usize = 64;
+
+/// The maximum length of a label key or value, in characters.
+const MAX_LABEL_LEN: usize = 63;
+
+/// Labels to attach to tables and jobs, parsed from a string like
+/// `env:prod,team:growth`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct TableLabels {
+    /// Our labels.
+    labels: Labels,
+}
+
+impl TableLabels {
+    /// Our labels, in the format used by the BigQuery API.
+    pub(crate) fn labels(&self) -> &Labels {
+        &self.labels
+    }
+}
+
+impl FromStr for TableLabels {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut labels = Labels::new();
+        for label in s.split(',') {
+            let mut parts = label.splitn(2, ':');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| {
+                    format_err!("expected label {:?} to look like KEY:VALUE", label)
+                })?
+                .trim();
+            let starts_with_letter =
+                key.chars().next().map_or(false, |c| c.is_lowercase());
+            if !starts_with_letter || !is_valid_label_text(key) {
+                return Err(format_err!(
+                    "label key {:?} must start with a lowercase letter, and contain \
+                     at most {} lowercase letters, digits, '_' or '-'",
+                    key,
+                    MAX_LABEL_LEN,
+                ));
+            }
+            if !is_valid_label_text(value) {
+                return Err(format_err!(
+                    "label value {:?} may contain at most {} lowercase letters, \
+                     digits, '_' or '-'",
+                    value,
+                    MAX_LABEL_LEN,
+                ));
+            }
+            if labels.insert(key.to_owned(), value.to_owned()).is_some() {
+                return Err(format_err!("label {:?} specified more than once", key));
+            }
+        }
+        if labels.len() > MAX_LABELS {
+            return Err(format_err!(
+                "BigQuery allows at most {} labels, found {}",
+                MAX_LABELS,
+                labels.len(),
+            ));
+        }
+        Ok(TableLabels { labels })
+    }
+}
+
+/// Is `s` a valid label key or value? Keys must also start with a letter.
+fn is_valid_label_text(s: &str) -> bool {
+    s.chars().count() <= MAX_LABEL_LEN
+        && s.chars()
+            .all(|c| c.is_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+/// A BigLake connection used to create external tables, like
+/// `my-project.us.my-connection`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ConnectionId {
+    /// Our connection ID.
+    id: String,
+}
+
+impl ConnectionId {
+    /// Quote this connection ID for use in SQL.
+    pub(crate) fn quoted(&self) -> String {
+        format!("`{}`", self.id)
+    }
+}
+
+impl FromStr for ConnectionId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = s.split('.').collect::<Vec<_>>();
+        let valid = parts.len() == 3
+            && parts.iter().all(|part| {
+                !part.is_empty()
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            });
+        if valid {
+            Ok(ConnectionId { id: s.to_owned() })
+        } else {
+            Err(format_err!(
+                "expected connection {:?} to look like PROJECT.LOCATION.CONNECTION",
+                s,
+            ))
+        }
+    }
+}
+
+/// How long to keep a table after creating it, parsed from a string like `7d`,
+/// `12h` or `30m`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct TableExpiration {
+    /// The number of `unit`s to keep the table.
+    amount: u32,
+    /// The unit of `amount`, as a BigQuery `INTERVAL` date part.
+    unit: &'static str,
+}
+
+impl TableExpiration {
+    /// An SQL expression for the time at which a table created now expires.
+    fn timestamp_sql(&self) -> String {
+        format!(
+            "TIMESTAMP_ADD(CURRENT_TIMESTAMP(), INTERVAL {} {})",
+            self.amount, self.unit,
+        )
+    }
+
+    /// Write an `ALTER TABLE` statement which makes an existing table expire
+    /// this long from now. We use this for tables created by load jobs.
+    pub(crate) fn write_alter_table_sql(
+        &self,
+        table_name: &TableName,
+        f: &mut dyn Write,
+    ) -> Result<()> {
+        writeln!(
+            f,
+            "ALTER TABLE {} SET OPTIONS(expiration_timestamp={});",
+            table_name.dotted_and_quoted(),
+            self.timestamp_sql(),
+        )?;
+        Ok(())
+    }
+}
+
+impl FromStr for TableExpiration {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (amount, unit) = match s.char_indices().last() {
+            Some((idx, _)) => s.split_at(idx),
+            None => (s, ""),
+        };
+        let unit = match unit {
+            "m" => "MINUTE",
+            "h" => "HOUR",
+            "d" => "DAY",
+            _ => {
+                return Err(format_err!(
+                    "expected expiration {:?} to look like 7d, 12h or 30m",
+                    s,
+                ))
+            }
+        };
+        let amount = amount
+            .parse::<u32>()
+            .with_context(|_| format!("cannot parse expiration {:?}", s))?;
+        if amount == 0 {
+            return Err(format_err!("expiration must be greater than 0"));
+        }
+        Ok(TableExpiration { amount, unit })
+    }
+}
+
+/// Options to use when we create a new BigQuery table.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NewTableOptions {
+    /// How should we partition the table?
+    pub(crate) time_partitioning: Option<TimePartitioning>,
+    /// Labels to attach to the table.
+    pub(crate) labels: TableLabels,
+    /// How long to keep the table before BigQuery deletes it.
+    pub(crate) expiration: Option<TableExpiration>,
+}
+
+impl NewTableOptions {
+    /// Write the `PARTITION BY` and `OPTIONS` clauses needed to create `table`,
+    /// each preceded by a newline.
+    pub(crate) fn write_create_table_clauses(
+        &self,
+        table: &BqTable,
+        f: &mut dyn Write,
+    ) -> Result<()> {
+        if let Some(time_partitioning) = &self.time_partitioning {
+            writeln!(f)?;
+            time_partitioning.write_partition_by_sql(table, f)?;
+        }
+        self.write_options_clause(vec![], f)
+    }
+
+    /// Write an `OPTIONS` clause containing `options`, followed by our own
+    /// options, preceded by a newline. If there are no options, write nothing.
+    pub(crate) fn write_options_clause(
+        &self,
+        mut options: Vec<String>,
+        f: &mut dyn Write,
+    ) -> Result<()> {
+        if let Some(days) = self
+            .time_partitioning
+            .as_ref()
+            .and_then(|tp| tp.expiration_days())
+        {
+            options.push(format!("partition_expiration_days={}", days));
+        }
+        if let Some(expiration) = &self.expiration {
+            options.push(format!(
+                "expiration_timestamp={}",
+                expiration.timestamp_sql()
+            ));
+        }
+        if !self.labels.labels().is_empty() {
+            // Sort our labels so that our SQL is predictable. We don't need to
+            // escape anything, because we only allow a few characters.
+            let mut labels = self.labels.labels().iter().collect::<Vec<_>>();
+            labels.sort();
+            let labels = labels
+                .into_iter()
+                .map(|(key, value)| format!("(\"{}\", \"{}\")", key, value))
+                .collect::<Vec<_>>();
+            options.push(format!("labels=[{}]", labels.join(", ")));
+        }
+        if !options.is_empty() {
+            write!(f, "\nOPTIONS({})", options.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn parse_table_labels() {
+    let labels = "env:prod, team:growth,empty:"
+        .parse::<TableLabels>()
+        .unwrap();
+    let mut expected = Labels::new();
+    expected.insert("env".to_owned(), "prod".to_owned());
+    expected.insert("team".to_owned(), "growth".to_owned());
+    expected.insert("empty".to_owned(), "".to_owned());
+    assert_eq!(labels.labels(), &expected);
+
+    let too_many = (0..65).map(|i| format!("k{}:v", i)).collect::<Vec<_>>();
+    let too_many = too_many.join(",");
+    let invalid = &[
+        "",
+        "env",
+        ":prod",
+        "Env:prod",
+        "1env:prod",
+        "env:Prod",
+        "env:a.b",
+        "env:prod,env:dev",
+        &too_many,
+    ];
+    for &s in invalid {
+        assert!(
+            s.parse::<TableLabels>().is_err(),
+            "should not parse {:?}",
+            s
+        );
+    }
+}
+
+#[test]
+fn parse_connection_id() {
+    let connection = "my-project.eu.archive_conn"
+        .parse::<ConnectionId>()
+        .unwrap();
+    assert_eq!(connection.quoted(), "`my-project.eu.archive_conn`");
+    for &s in &["", "conn", "eu.conn", "p..conn", "p.eu.conn`", "p.eu.a.b"] {
+        assert!(
+            s.parse::<ConnectionId>().is_err(),
+            "should not parse {:?}",
+            s
+        );
+    }
+}
+
+#[test]
+fn create_table_clauses() {
+    use super::{PartitionType, TableName, Usage};
+    use crate::schema::{Column, DataType};
+
+    let table = BqTable::for_table_name_and_columns(
+        "project:dataset.table".parse::<TableName>().unwrap(),
+        &[Column {
+            name: "day".to_owned(),
+            is_nullable: true,
+            data_type: DataType::Date,
+            comment: None,
+        }],
+        Usage::FinalTable,
+    )
+    .unwrap();
+    let sql = |options: &NewTableOptions| {
+        let mut out = vec![];
+        options
+            .write_create_table_clauses(&table, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(sql(&NewTableOptions::default()), "");
+
+    let labels = "team:growth,env:prod".parse::<TableLabels>().unwrap();
+    let options = NewTableOptions {
+        time_partitioning: None,
+        labels: labels.clone(),
+        expiration: None,
+    };
+    assert_eq!(
+        sql(&options),
+        "\nOPTIONS(labels=[(\"env\", \"prod\"), (\"team\", \"growth\")])",
+    );
+
+    let time_partitioning = TimePartitioning::from_driver_args(
+        Some("day"),
+        Some(PartitionType::Day),
+        Some(30),
+    )
+    .unwrap();
+    let options = NewTableOptions {
+        time_partitioning,
+        labels,
+        expiration: None,
+    };
+    assert_eq!(
+        sql(&options),
+        "\nPARTITION BY `day`\nOPTIONS(partition_expiration_days=30, \
+         labels=[(\"env\", \"prod\"), (\"team\", \"growth\")])",
+    );
+
+    let options = NewTableOptions {
+        expiration: Some("7d".parse().unwrap()),
+        ..NewTableOptions::default()
+    };
+    assert_eq!(
+        sql(&options),
+        "\nOPTIONS(expiration_timestamp=TIMESTAMP_ADD(CURRENT_TIMESTAMP(), \
+         INTERVAL 7 DAY))",
+    );
+}
+
+#[test]
+fn parse_table_expiration() {
+    let table_name = "project:dataset.table".parse::<TableName>().unwrap();
+    let alter_sql = |s: &str| {
+        let expiration = s.parse::<TableExpiration>().unwrap();
+        let mut out = vec![];
+        expiration
+            .write_alter_table_sql(&table_name, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(
+        alter_sql("12h"),
+        "ALTER TABLE `project`.`dataset`.`table` SET OPTIONS(expiration_timestamp=\
+         TIMESTAMP_ADD(CURRENT_TIMESTAMP(), INTERVAL 12 HOUR));\n",
+    );
+    assert!(alter_sql("30m").contains("INTERVAL 30 MINUTE"));
+    assert!(alter_sql(" 7d ").contains("INTERVAL 7 DAY"));
+    for &s in &["", "7", "d", "0d", "-1d", "7w", "1.5d", "7 d", "7\u{e9}"] {
+        assert!(
+            s.parse::<TableExpiration>().is_err(),
+            "should not parse {:?}",
+            s
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! Time-partitioned BigQuery tables.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::{BqDataType, BqNonArrayDataType, BqTable, ColumnName};
+use crate::common::*;
+
+/// How finely should we partition a table?
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum PartitionType {
+    Hour,
+    Day,
+    Month,
+    Year,
+}
+
+impl fmt::Display for PartitionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PartitionType::Hour => write!(f, "HOUR"),
+            PartitionType::Day => write!(f, "DAY"),
+            PartitionType::Month => write!(f, "MONTH"),
+            PartitionType::Year => write!(f, "YEAR"),
+        }
+    }
+}
+
+/// Time partitioning for a BigQuery table. This is serialized in the format
+/// expected by the BigQuery API.
+///
+/// Docs: https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#TimePartitioning
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TimePartitioning {
+    /// How finely we partition our table.
+    #[serde(rename = "type")]
+    partition_type: PartitionType,
+
+    /// The column to partition on, or `None` to partition by ingestion time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<ColumnName>,
+
+    /// How long to keep each partition, in milliseconds. The API expects an
+    /// `int64`, which is sent as a string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expiration_ms: Option<String>,
+
+    /// How many days to keep each partition. We use this when generating SQL.
+    #[serde(skip)]
+    expiration_days: Option<u32>,
+}
+
+impl TimePartitioning {
+    /// Build time partitioning from our driver arguments, or return `None` if
+    /// none were specified. If we have a `partition_field` but no
+    /// `partition_type`, we default to `DAY`. If we have a `partition_type` but
+    /// no `partition_field`, we partition by ingestion time.
+    pub(crate) fn from_driver_args(
+        partition_field: Option<&str>,
+        partition_type: Option<PartitionType>,
+        partition_expiration_days: Option<u32>,
+    ) -> Result<Option<TimePartitioning>> {
+        if partition_field.is_none() && partition_type.is_none() {
+            if partition_expiration_days.is_some() {
+                return Err(format_err!(
+                    "partition_expiration_days requires partition_field or \
+                     partition_type"
+                ));
+            }
+            return Ok(None);
+        }
+        let field = partition_field.map(ColumnName::try_from).transpose()?;
+        if partition_expiration_days == Some(0) {
+            return Err(format_err!("partition_expiration_days must be at least 1"));
+        }
+        Ok(Some(TimePartitioning {
+            partition_type: partition_type.unwrap_or(PartitionType::Day),
+            field,
+            expiration_ms: partition_expiration_days
+                .map(|days| (u64::from(days) * 24 * 60 * 60 * 1000).to_string()),
+            expiration_days: partition_expiration_days,
+        }))
+    }
+
+    /// How many days should we keep each partition?
+    pub(crate) fn expiration_days(&self) -> Option<u32> {
+        self.expiration_days
+    }
+
+    /// Write the `PARTITION BY` clause used to create `table` with this
+    /// partitioning.
+    pub(crate) fn write_partition_by_sql(
+        &self,
+        table: &BqTable,
+        f: &mut dyn Write,
+    ) -> Result<()> {
+        write!(f, "PARTITION BY {}", self.partition_expr(table)?)?;
+        Ok(())
+    }
+
+    /// The SQL expression we partition `table` by.
+    fn partition_expr(&self, table: &BqTable) -> Result<String> {
+        let ty = self.partition_type;
+        let field = match &self.field {
+            Some(field) => field,
+            None => return Ok(format!("TIMESTAMP_TRUNC(_PARTITIONTIME, {})", ty)),
+        };
+        let col =
+            table
+                .columns
+                .iter()
+                .find(|c| &c.name == field)
+                .ok_or_else(|| {
+                    format_err!(
+                        "cannot partition by {}, because it is not a column of {}",
+                        field.quoted(),
+                        table.name(),
+                    )
+                })?;
+        let name = col.name.quoted();
+        match (col.bq_data_type()?, ty) {
+            (BqDataType::NonArray(BqNonArrayDataType::Date), PartitionType::Day) => {
+                Ok(name.to_string())
+            }
+            (BqDataType::NonArray(BqNonArrayDataType::Date), PartitionType::Hour) => {
+                Err(format_err!("cannot partition DATE column {} by HOUR", name,))
+            }
+            (BqDataType::NonArray(BqNonArrayDataType::Date), _) => {
+                Ok(format!("DATE_TRUNC({}, {})", name, ty))
+            }
+            (BqDataType::NonArray(BqNonArrayDataType::Datetime), _) => {
+                Ok(format!("DATETIME_TRUNC({}, {})", name, ty))
+            }
+            (BqDataType::NonArray(BqNonArrayDataType::Timestamp), _) => {
+                Ok(format!("TIMESTAMP_TRUNC({}, {})", name, ty))
+            }
+            (other, _) => Err(format_err!(
+                "cannot partition by {}, because it has type {} instead of DATE, \
+                 DATETIME or TIMESTAMP",
+                name,
+                other,
+            )),
+        }
+    }
+}
+
+#[test]
+fn partitioning_from_driver_args() {
+    assert_eq!(
+        TimePartitioning::from_driver_args(None, None, None).unwrap(),
+        None
+    );
+    let partitioning =
+        TimePartitioning::from_driver_args(Some("event_date"), None, Some(90))
+            .unwrap()
+            .unwrap();
+    assert_eq!(
+        serde_json::to_value(&partitioning).unwrap(),
+        serde_json::json!({
+            "type": "DAY",
+            "field": "event_date",
+            "expirationMs": "7776000000",
+        }),
+    );
+    let partitioning =
+        TimePartitioning::from_driver_args(None, Some(PartitionType::Hour), None)
+            .unwrap()
+            .unwrap();
+    assert_eq!(
+        serde_json::to_value(&partitioning).unwrap(),
+        serde_json::json!({ "type": "HOUR" }),
+    );
+    assert!(TimePartitioning::from_driver_args(None, None, Some(30)).is_err());
+    assert!(TimePartitioning::from_driver_args(Some("a b"), None, None).is_err());
+    assert!(TimePartitioning::from_driver_args(Some("d"), None, Some(0)).is_err());
+}
+
+#[test]
+fn partition_by_sql() {
+    use super::{TableName, Usage};
+    use crate::schema::{Column, DataType};
+
+    let column = |name: &str, data_type| Column {
+        name: name.to_owned(),
+        is_nullable: true,
+        data_type,
+        comment: None,
+    };
+    let table = BqTable::for_table_name_and_columns(
+        "project:dataset.table".parse::<TableName>().unwrap(),
+        &[
+            column("day", DataType::Date),
+            column("created_at", DataType::TimestampWithTimeZone),
+            column("local_time", DataType::TimestampWithoutTimeZone),
+            column("name", DataType::Text),
+        ],
+        Usage::FinalTable,
+    )
+    .unwrap();
+    let sql = |field, ty| -> Result<String> {
+        let partitioning =
+            TimePartitioning::from_driver_args(field, ty, None)?.unwrap();
+        let mut out = vec![];
+        partitioning.write_partition_by_sql(&table, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    };
+    assert_eq!(sql(Some("DAY"), None).unwrap(), "PARTITION BY `day`");
+    assert_eq!(
+        sql(Some("day"), Some(PartitionType::Month)).unwrap(),
+        "PARTITION BY DATE_TRUNC(`day`, MONTH)",
+    );
+    assert_eq!(
+        sql(Some("created_at"), Some(PartitionType::Hour)).unwrap(),
+        "PARTITION BY TIMESTAMP_TRUNC(`created_at`, HOUR)",
+    );
+    assert_eq!(
+        sql(Some("local_time"), None).unwrap(),
+        "PARTITION BY DATETIME_TRUNC(`local_time`, DAY)",
+    );
+    assert_eq!(
+        sql(None, Some(PartitionType::Day)).unwrap(),
+        "PARTITION BY TIMESTAMP_TRUNC(_PARTITIONTIME, DAY)",
+    );
+    assert!(sql(Some("day"), Some(PartitionType::Hour)).is_err());
+    assert!(sql(Some("name"), None).is_err());
+    assert!(sql(Some("missing"), None).is_err());
+}
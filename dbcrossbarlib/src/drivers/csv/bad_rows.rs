@@ -0,0 +1,186 @@
+//! Skipping malformed rows, and optionally saving them for later inspection.
+
+use serde::Deserialize;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    str::{self, FromStr},
+};
+
+use crate::common::*;
+use crate::path_or_stdio::{ends_with_separator, long_path};
+
+/// What should we do when we find a malformed row?
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OnError {
+    /// Fail, and let the user fix the input.
+    Error,
+    /// Skip the row, and keep going.
+    Skip,
+}
+
+impl Default for OnError {
+    fn default() -> Self {
+        OnError::Error
+    }
+}
+
+/// A local directory where we save the rows that we skip, specified as
+/// `csv:rejects/`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct BadRowsDir(PathBuf);
+
+impl BadRowsDir {
+    /// The path to the file where we should save bad rows from `stream_name`.
+    pub(crate) fn path_for_stream(&self, stream_name: &str) -> PathBuf {
+        self.0.join(format!("{}.csv", stream_name))
+    }
+}
+
+impl FromStr for BadRowsDir {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if !s.starts_with("csv:") || !ends_with_separator(Path::new(&s[4..])) {
+            return Err(format_err!(
+                "bad_rows must be a csv: directory ending in '/', not {:?}",
+                s
+            ));
+        }
+        Ok(BadRowsDir(PathBuf::from(&s[4..])))
+    }
+}
+
+/// If `row` is malformed, explain why.
+fn bad_row_reason(
+    row_idx: usize,
+    expected_len: usize,
+    row: &csv::ByteRecord,
+) -> Option<String> {
+    if row.len() != expected_len {
+        Some(format!(
+            "row {} has {} fields, but header has {}",
+            row_idx,
+            row.len(),
+            expected_len,
+        ))
+    } else if row.iter().any(|cell| str::from_utf8(cell).is_err()) {
+        Some(format!("row {} contains invalid UTF-8", row_idx))
+    } else {
+        None
+    }
+}
+
+/// Copy CSV data from `rdr` to `wtr`, skipping any rows with the wrong number
+/// of columns or invalid UTF-8. If `rejects_path` is specified, we write the
+/// rows we skip to that file, preceded by a `bad_row_reason` column.
+pub(crate) fn skip_bad_rows<R, W>(
+    ctx: &Context,
+    rejects_path: Option<&Path>,
+    rdr: R,
+    wtr: W,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+
+    let mut header = csv::ByteRecord::new();
+    if !rdr
+        .read_byte_record(&mut header)
+        .context("cannot read CSV header")?
+    {
+        return Ok(());
+    }
+    wtr.write_byte_record(&header)
+        .context("cannot write CSV header")?;
+
+    // We only create our rejects file once we have a row to put in it.
+    let mut rejects: Option<csv::Writer<File>> = None;
+    let mut skipped = 0;
+    let mut row = csv::ByteRecord::new();
+    let mut row_idx = 1;
+    while rdr.read_byte_record(&mut row).context("cannot read row")? {
+        if let Some(reason) = bad_row_reason(row_idx, header.len(), &row) {
+            debug!(ctx.log(), "skipping bad row: {}", reason);
+            skipped += 1;
+            if let Some(rejects_path) = rejects_path {
+                if rejects.is_none() {
+                    rejects = Some(create_rejects_file(rejects_path, &header)?);
+                }
+                let rejects = rejects.as_mut().expect("rejects file should exist");
+                let mut reject = csv::ByteRecord::new();
+                reject.push_field(reason.as_bytes());
+                reject.extend(row.iter());
+                rejects.write_byte_record(&reject).with_context(|_| {
+                    format!("error writing {}", rejects_path.display())
+                })?;
+            }
+        } else {
+            wtr.write_byte_record(&row).context("cannot write row")?;
+        }
+        row_idx += 1;
+    }
+    wtr.flush().context("error flushing output")?;
+
+    if let Some(mut rejects) = rejects {
+        rejects.flush().context("error flushing bad rows")?;
+    }
+    if skipped > 0 {
+        warn!(ctx.log(), "skipped {} bad rows", skipped);
+    }
+    Ok(())
+}
+
+/// Create a file to hold bad rows, and write a header to it.
+fn create_rejects_file(
+    path: &Path,
+    header: &csv::ByteRecord,
+) -> Result<csv::Writer<File>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(long_path(dir)?).with_context(|_| {
+            format!("unable to create directory {}", dir.display())
+        })?;
+    }
+    let f = File::create(long_path(path)?)
+        .with_context(|_| format!("cannot create {}", path.display()))?;
+    let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(f);
+    let mut reject_header = csv::ByteRecord::new();
+    reject_header.push_field(b"bad_row_reason");
+    reject_header.extend(header.iter());
+    wtr.write_byte_record(&reject_header)
+        .with_context(|_| format!("error writing {}", path.display()))?;
+    Ok(wtr)
+}
+
+#[test]
+fn skip_bad_rows_saves_rejects() {
+    use std::env;
+
+    let (ctx, _worker_fut) = Context::create_for_test("skip_bad_rows");
+    let dir = env::temp_dir().join("dbcrossbar_skip_bad_rows_saves_rejects");
+    let bad_rows = format!("csv:{}/", dir.display())
+        .parse::<BadRowsDir>()
+        .unwrap();
+    let rejects_path = bad_rows.path_for_stream("data");
+
+    let input = b"a,b\n1,2\n3\n4,\xff\n5,6\n";
+    let mut out = vec![];
+    skip_bad_rows(&ctx, Some(&rejects_path), &input[..], &mut out).unwrap();
+    assert_eq!(out, b"a,b\n1,2\n5,6\n");
+    assert_eq!(
+        fs::read(&rejects_path).unwrap(),
+        &b"bad_row_reason,a,b\n\
+           \"row 2 has 1 fields, but header has 2\",3\n\
+           row 3 contains invalid UTF-8,4,\xff\n"[..],
+    );
+
+    assert!("csv:rejects.csv".parse::<BadRowsDir>().is_err());
+    assert!("s3://bucket/rejects/".parse::<BadRowsDir>().is_err());
+}
@@ -0,0 +1,71 @@
+//! Handling byte-order marks written by Windows tools.
+
+use crate::common::*;
+
+/// The UTF-8 encoding of U+FEFF, the byte-order mark.
+pub(crate) const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Remove a leading UTF-8 byte-order mark from `data`, if present.
+///
+/// Many Windows tools, including PowerShell and Excel, add a BOM to the start
+/// of UTF-8 text. If we left it in place, it would become part of the name of
+/// our first column.
+pub(crate) async fn strip_utf8_bom(
+    mut data: BoxStream<BytesMut>,
+) -> Result<BoxStream<BytesMut>> {
+    // Read until we have enough bytes to check for a BOM.
+    let mut buffer = BytesMut::new();
+    while buffer.len() < UTF8_BOM.len() {
+        match data.next().await {
+            Some(bytes) => buffer.extend_from_slice(&bytes?),
+            None => break,
+        }
+    }
+    if buffer.starts_with(UTF8_BOM) {
+        let _bom = buffer.split_to(UTF8_BOM.len());
+    }
+    if buffer.is_empty() {
+        Ok(data)
+    } else {
+        Ok(box_stream_once(Ok(buffer)).chain(data).boxed())
+    }
+}
+
+/// Add a UTF-8 byte-order mark to the start of `data`. Excel needs this to
+/// recognize UTF-8 CSV files.
+pub(crate) fn add_utf8_bom(data: BoxStream<BytesMut>) -> BoxStream<BytesMut> {
+    box_stream_once(Ok(BytesMut::from(UTF8_BOM)))
+        .chain(data)
+        .boxed()
+}
+
+#[test]
+fn strip_utf8_bom_handles_split_boms() {
+    let (ctx, worker_fut) = Context::create_for_test("strip_utf8_bom");
+    let cmd_fut = async move {
+        let inputs: &[&[&[u8]]] = &[
+            &[b"\xEF\xBB\xBFa,b\r\n"],
+            &[b"\xEF", b"\xBB", b"\xBFa,b\r\n"],
+            &[b"a,b\r\n"],
+            &[b"\xEF\xBB\xBF"],
+        ];
+        for (i, chunks) in inputs.iter().enumerate() {
+            let chunks = chunks
+                .iter()
+                .map(|chunk| Ok(BytesMut::from(*chunk)))
+                .collect::<Vec<_>>();
+            let stripped = strip_utf8_bom(stream::iter(chunks).boxed()).await?;
+            let output = CsvStream {
+                name: "data".to_owned(),
+                metadata: StreamMetadata::default(),
+                data: stripped,
+            }
+            .into_bytes(ctx.clone())
+            .await?;
+            let expected: &[u8] = if i == 3 { b"" } else { b"a,b\r\n" };
+            assert_eq!(output, expected);
+        }
+        Ok(())
+    };
+    run_futures_with_runtime(cmd_fut.boxed(), worker_fut).unwrap();
+}
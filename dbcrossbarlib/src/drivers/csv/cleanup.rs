@@ -0,0 +1,164 @@
+//! Simple per-column cleanups, like removing currency symbols.
+
+use std::{collections::HashMap, str::FromStr};
+
+use crate::common::*;
+
+/// A single cleanup operation to apply to a cell.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum CleanupOp {
+    /// Remove all occurrences of the specified string.
+    Strip(String),
+    /// Remove leading and trailing whitespace.
+    Trim,
+}
+
+impl CleanupOp {
+    /// Apply this operation to `cell`.
+    fn apply(&self, cell: &str) -> String {
+        match self {
+            CleanupOp::Strip(s) => cell.replace(&s[..], ""),
+            CleanupOp::Trim => cell.trim().to_owned(),
+        }
+    }
+}
+
+/// A list of cleanup operations, parsed from a string like
+/// `strip:'$',strip:',',trim`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct CleanupSpec(Vec<CleanupOp>);
+
+impl CleanupSpec {
+    /// Apply all our operations to `cell`, in order.
+    fn apply(&self, cell: &str) -> String {
+        let mut result = cell.to_owned();
+        for op in &self.0 {
+            result = op.apply(&result);
+        }
+        result
+    }
+}
+
+impl FromStr for CleanupSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(grammar::spec(s).map_err(|err| {
+            format_err!(
+                "error parsing cleanup {:?} at {}: expected {}",
+                s,
+                err.location.offset,
+                err.expected,
+            )
+        })?)
+    }
+}
+
+peg::parser! {
+    /// A grammar for parsing cleanup specs.
+    grammar grammar() for str {
+        /// A comma-separated list of operations.
+        pub(super) rule spec() -> CleanupSpec
+            = ops:(op() ++ ",") { CleanupSpec(ops) }
+
+        /// A single operation.
+        rule op() -> CleanupOp
+            = "strip:" s:string() { CleanupOp::Strip(s) }
+            / "trim" { CleanupOp::Trim }
+
+        /// A quoted or unquoted string.
+        rule string() -> String
+            = "'" chars:(quoted_char()*) "'" { chars.into_iter().collect() }
+            / s:$((![',' | '\''] [_])+) { s.to_owned() }
+
+        /// A character inside a single-quoted string, where `''` represents
+        /// a single quote.
+        rule quoted_char() -> char
+            = "''" { '\'' }
+            / !"'" c:$([_]) { c.chars().next().expect("should have char") }
+    }
+}
+
+#[test]
+fn parse_cleanup_specs() {
+    let examples = &[
+        (
+            "strip:'$',strip:','",
+            vec![
+                CleanupOp::Strip("$".to_owned()),
+                CleanupOp::Strip(",".to_owned()),
+            ],
+        ),
+        (
+            "strip:kg,trim",
+            vec![CleanupOp::Strip("kg".to_owned()), CleanupOp::Trim],
+        ),
+        ("strip:''''", vec![CleanupOp::Strip("'".to_owned())]),
+    ];
+    for (input, expected) in examples {
+        assert_eq!(&input.parse::<CleanupSpec>().unwrap().0, expected);
+    }
+    assert!("".parse::<CleanupSpec>().is_err());
+    assert!("strip:".parse::<CleanupSpec>().is_err());
+    assert!("upcase".parse::<CleanupSpec>().is_err());
+}
+
+/// Copy CSV data from `rdr` to `wtr`, applying the cleanups in `specs` to the
+/// columns with the corresponding names.
+pub(crate) fn cleanup_csv<R, W>(
+    specs: &HashMap<String, CleanupSpec>,
+    rdr: R,
+    wtr: W,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+
+    // Figure out which cleanup spec, if any, applies to each column.
+    let headers = rdr.headers().context("cannot read CSV header")?.to_owned();
+    for name in specs.keys() {
+        if !headers.iter().any(|h| h == name) {
+            return Err(format_err!("cannot clean up unknown column {}", name));
+        }
+    }
+    let column_specs = headers
+        .iter()
+        .map(|name| specs.get(name))
+        .collect::<Vec<_>>();
+    wtr.write_record(&headers)
+        .context("cannot write CSV header")?;
+
+    let mut out = csv::StringRecord::new();
+    for row in rdr.records() {
+        let row = row.context("cannot read row")?;
+        out.clear();
+        for (cell, spec) in row.iter().zip(column_specs.iter()) {
+            match spec {
+                Some(spec) => out.push_field(&spec.apply(cell)),
+                None => out.push_field(cell),
+            }
+        }
+        wtr.write_record(&out).context("cannot write row")?;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn cleanup_csv_strips_currency() {
+    let mut specs = HashMap::new();
+    specs.insert(
+        "amount".to_owned(),
+        "strip:'$',strip:','".parse::<CleanupSpec>().unwrap(),
+    );
+    let input = "name,amount\n\"a,b\",\"$1,234.50\"\n";
+    let mut out = vec![];
+    cleanup_csv(&specs, input.as_bytes(), &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "name,amount\n\"a,b\",1234.50\n"
+    );
+}
@@ -0,0 +1,98 @@
+//! Handling rows with the wrong number of columns.
+
+use serde::Deserialize;
+
+use crate::common::*;
+
+/// What should we do when a CSV row has more or fewer fields than the header?
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ColumnMismatch {
+    /// Fail with an error that points to the offending row.
+    Error,
+    /// Add empty (`NULL`) fields to short rows, and drop the extra fields
+    /// from long rows.
+    PadNull,
+    /// Drop the extra fields from long rows, but fail on short rows.
+    Truncate,
+}
+
+impl Default for ColumnMismatch {
+    fn default() -> Self {
+        ColumnMismatch::Error
+    }
+}
+
+/// Copy CSV data from `rdr` to `wtr`, making sure that every row has the same
+/// number of fields as the header row, as specified by `policy`.
+pub(crate) fn fix_column_counts<R, W>(
+    policy: ColumnMismatch,
+    rdr: R,
+    wtr: W,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+
+    let mut expected_len = None;
+    let mut row = csv::ByteRecord::new();
+    let mut row_idx = 0;
+    while rdr.read_byte_record(&mut row).context("cannot read row")? {
+        // The header row determines how many fields we expect.
+        let expected_len = *expected_len.get_or_insert(row.len());
+        if row.len() < expected_len {
+            if policy != ColumnMismatch::PadNull {
+                return Err(format_err!(
+                    "row {} has {} fields, but header has {}",
+                    row_idx,
+                    row.len(),
+                    expected_len,
+                ));
+            }
+            while row.len() < expected_len {
+                row.push_field(b"");
+            }
+        } else if row.len() > expected_len {
+            if policy == ColumnMismatch::Error {
+                return Err(format_err!(
+                    "row {} has {} fields, but header has {}",
+                    row_idx,
+                    row.len(),
+                    expected_len,
+                ));
+            }
+            row.truncate(expected_len);
+        }
+        wtr.write_byte_record(&row).context("cannot write row")?;
+        row_idx += 1;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn fix_column_counts_honors_policy() {
+    let input = b"a,b,c\n1,2\n1,2,3,4\n";
+    let fix = |policy| -> Result<String> {
+        let mut out = vec![];
+        fix_column_counts(policy, &input[..], &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    };
+    assert!(fix(ColumnMismatch::Error).is_err());
+    assert!(fix(ColumnMismatch::Truncate).is_err());
+    assert_eq!(
+        fix(ColumnMismatch::PadNull).unwrap(),
+        "a,b,c\n1,2,\n1,2,3\n"
+    );
+
+    let mut out = vec![];
+    fix_column_counts(ColumnMismatch::Truncate, &b"a,b\n1,2,3\n"[..], &mut out)
+        .unwrap();
+    assert_eq!(out, b"a,b\n1,2\n");
+}
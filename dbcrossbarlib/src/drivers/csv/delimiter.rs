@@ -0,0 +1,183 @@
+//! Support for tab-, pipe- and other non-comma-delimited files.
+
+use std::{ffi::OsStr, path::Path, str::FromStr};
+
+use crate::common::*;
+
+/// The character used to separate fields in a delimited text file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Delimiter(u8);
+
+impl Delimiter {
+    /// The standard CSV delimiter, used by our CSV interchange format.
+    pub(crate) const COMMA: Delimiter = Delimiter(b',');
+
+    /// The delimiter used by TSV files.
+    pub(crate) const TAB: Delimiter = Delimiter(b'\t');
+
+    /// The delimiter used by pipe-delimited files.
+    const PIPE: Delimiter = Delimiter(b'|');
+
+    /// The default delimiter for the file at `path`, based on its extension.
+    pub(crate) fn for_path(path: &Path) -> Delimiter {
+        let ext = path.extension().map(OsStr::to_string_lossy);
+        match ext.as_deref() {
+            Some("tsv") | Some("TSV") => Delimiter::TAB,
+            _ => Delimiter::COMMA,
+        }
+    }
+
+    /// Guess the delimiter used by a file from its header line. We only look
+    /// for tabs and pipes if the header contains no commas, so that we never
+    /// misinterpret a valid CSV file.
+    pub(crate) fn sniff(header: &[u8]) -> Delimiter {
+        let header = match header.iter().position(|b| *b == b'\n') {
+            Some(pos) => &header[..pos],
+            None => header,
+        };
+        [Delimiter::COMMA, Delimiter::TAB, Delimiter::PIPE]
+            .iter()
+            .copied()
+            .find(|d| header.contains(&d.0))
+            .unwrap_or(Delimiter::COMMA)
+    }
+
+    /// Return this delimiter as a byte, for use with the `csv` crate.
+    pub(crate) fn as_byte(self) -> u8 {
+        self.0
+    }
+}
+
+/// Guess the delimiter used by `data` from its header line, and return it along
+/// with a stream containing all of `data`.
+pub(crate) async fn sniff_stream_delimiter(
+    mut data: BoxStream<BytesMut>,
+) -> Result<(Delimiter, BoxStream<BytesMut>)> {
+    // Read until we have a complete header line, or we've seen enough.
+    let mut buffer = BytesMut::new();
+    while buffer.len() < 4096 && !buffer.contains(&b'\n') {
+        match data.next().await {
+            Some(bytes) => buffer.extend_from_slice(&bytes?),
+            None => break,
+        }
+    }
+    let delimiter = Delimiter::sniff(&buffer);
+    if buffer.is_empty() {
+        Ok((delimiter, data))
+    } else {
+        Ok((delimiter, box_stream_once(Ok(buffer)).chain(data).boxed()))
+    }
+}
+
+impl FromStr for Delimiter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let b = match s {
+            "tab" | "\\t" | "\t" => b'\t',
+            "comma" => b',',
+            "pipe" => b'|',
+            _ if s.len() == 1 => s.as_bytes()[0],
+            _ => {
+                return Err(format_err!(
+                    "delimiter must be `tab` or a single ASCII character"
+                ))
+            }
+        };
+        if !b.is_ascii() || b == b'"' || b == b'\n' || b == b'\r' {
+            return Err(format_err!("cannot use {:?} as a delimiter", s));
+        }
+        Ok(Delimiter(b))
+    }
+}
+
+#[test]
+fn parse_and_sniff_delimiters() {
+    assert_eq!("tab".parse::<Delimiter>().unwrap(), Delimiter::TAB);
+    assert_eq!("\\t".parse::<Delimiter>().unwrap(), Delimiter::TAB);
+    assert_eq!("|".parse::<Delimiter>().unwrap(), Delimiter::PIPE);
+    assert_eq!(";".parse::<Delimiter>().unwrap(), Delimiter(b';'));
+    for bad in &["", "\"", "\n", "||", "é"] {
+        assert!(bad.parse::<Delimiter>().is_err());
+    }
+
+    assert_eq!(Delimiter::sniff(b"a,b|c\n1,2\n"), Delimiter::COMMA);
+    assert_eq!(Delimiter::sniff(b"a\tb|c\n"), Delimiter::TAB);
+    assert_eq!(Delimiter::sniff(b"a|b\n1,2\n"), Delimiter::PIPE);
+    assert_eq!(Delimiter::sniff(b"a\n"), Delimiter::COMMA);
+    assert_eq!(Delimiter::for_path(Path::new("x/y.TSV")), Delimiter::TAB);
+    assert_eq!(Delimiter::for_path(Path::new("x/y.csv")), Delimiter::COMMA);
+}
+
+#[test]
+fn sniff_stream_delimiter_keeps_data() {
+    let (ctx, worker_fut) = Context::create_for_test("sniff_stream_delimiter");
+    let cmd_fut = async move {
+        let chunks: &[&[u8]] = &[b"a|", b"b\n1|\"2,3\"\n"];
+        let chunks = chunks
+            .iter()
+            .map(|chunk| Ok(BytesMut::from(*chunk)))
+            .collect::<Vec<_>>();
+        let (delimiter, data) =
+            sniff_stream_delimiter(stream::iter(chunks).boxed()).await?;
+        assert_eq!(delimiter, Delimiter::PIPE);
+        let output = CsvStream {
+            name: "data".to_owned(),
+            metadata: StreamMetadata::default(),
+            data,
+        }
+        .into_bytes(ctx.clone())
+        .await?;
+        assert_eq!(output, &b"a|b\n1|\"2,3\"\n"[..]);
+        Ok(())
+    };
+    run_futures_with_runtime(cmd_fut.boxed(), worker_fut).unwrap();
+}
+
+/// Copy delimited data from `rdr` to `wtr`, replacing the delimiter `from` with
+/// `to`, and quoting fields as needed.
+pub(crate) fn change_delimiter<R, W>(
+    from: Delimiter,
+    to: Delimiter,
+    rdr: R,
+    wtr: W,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    // Don't check row lengths or headers here. That's somebody else's job.
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(from.0)
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(rdr);
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(to.0)
+        .flexible(true)
+        .from_writer(wtr);
+    let mut row = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut row).context("cannot read row")? {
+        wtr.write_byte_record(&row).context("cannot write row")?;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn change_delimiter_requotes_fields() {
+    let input = "name\tnote\na,b\t\"x\"\"y\"\n";
+    let mut out = vec![];
+    change_delimiter(Delimiter::TAB, Delimiter::COMMA, input.as_bytes(), &mut out)
+        .unwrap();
+    let csv = String::from_utf8(out).unwrap();
+    assert_eq!(csv, "name,note\n\"a,b\",\"x\"\"y\"\n");
+
+    let mut out = vec![];
+    change_delimiter(Delimiter::COMMA, Delimiter::PIPE, csv.as_bytes(), &mut out)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "name|note\na,b|\"x\"\"y\"\n"
+    );
+}
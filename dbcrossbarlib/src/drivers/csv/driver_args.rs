@@ -0,0 +1,446 @@
+//! Driver arguments for CSV sources and destinations.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::bad_rows::{skip_bad_rows, BadRowsDir, OnError};
+use super::bom::add_utf8_bom;
+use super::cleanup::{cleanup_csv, CleanupSpec};
+use super::column_mismatch::{fix_column_counts, ColumnMismatch};
+use super::delimiter::{change_delimiter, Delimiter};
+use super::encoding::{transcode_to_utf8, Encoding};
+use super::fixed_width::{fixed_width_to_csv, ColumnWidths};
+use super::header::{add_header, remove_header};
+use super::line_ending::{change_line_ending, LineEnding};
+use super::locale::{delocalize_csv, LocaleOptions};
+use super::null_value::{read_null_values, write_null_values, NullValues};
+use crate::common::*;
+use crate::compression::Compression;
+use crate::driver_args::deserialize_opt_from_str;
+use crate::rechunk::ChunkLimits;
+use crate::select_columns::SelectColumns;
+use crate::transform::spawn_sync_transform;
+
+/// Parsed version of `--from-arg` values.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct CsvSourceArguments {
+    /// The character encoding of our input, if it isn't UTF-8.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    encoding: Option<Encoding>,
+
+    /// The character used to separate fields, if it isn't `,`. Files ending
+    /// in `*.tsv` default to tabs.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    delimiter: Option<Delimiter>,
+
+    /// Does our input have a header row? If not, we take column names from
+    /// our schema. Defaults to `true`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    has_header: Option<bool>,
+
+    /// If specified, our input is a fixed-width text file with columns of
+    /// these widths, and no header row.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    column_widths: Option<ColumnWidths>,
+
+    /// Strings, like `\N` or `NULL`, which should be treated as `NULL`.
+    null_value: Option<NullValues>,
+
+    /// What should we do with rows that have the wrong number of columns?
+    on_column_mismatch: Option<ColumnMismatch>,
+
+    /// What should we do with rows that have the wrong number of columns
+    /// after applying `on_column_mismatch`, or that contain invalid UTF-8?
+    on_error: Option<OnError>,
+
+    /// Where should we save rows skipped by `on_error=skip`?
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    bad_rows: Option<BadRowsDir>,
+
+    /// The character used to separate the integer and fractional parts of
+    /// numbers, if it isn't `.`.
+    decimal_separator: Option<char>,
+
+    /// The format used for dates, if they aren't `YYYY-MM-DD`.
+    date_format: Option<String>,
+
+    /// Per-column cleanups, like `strip:'$',strip:','`, indexed by column
+    /// name.
+    #[serde(default)]
+    cleanup: HashMap<String, String>,
+
+    /// Split each uncompressed input file into up to this many streams at
+    /// line boundaries, so that we can read it in parallel.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    chunks: Option<usize>,
+}
+
+impl CsvSourceArguments {
+    /// Is our input a fixed-width text file?
+    pub(crate) fn is_fixed_width(&self) -> bool {
+        self.column_widths.is_some()
+    }
+
+    /// Does each input file start with a header row?
+    pub(crate) fn has_header_row(&self) -> bool {
+        !self.is_fixed_width() && self.has_header.unwrap_or(true)
+    }
+
+    /// How many chunks should we split each input file into?
+    pub(crate) fn chunk_count(&self) -> Result<usize> {
+        match self.chunks {
+            None => Ok(1),
+            Some(0) => Err(format_err!("chunks must be at least 1")),
+            Some(count) => {
+                if let Some(encoding) = self.encoding {
+                    if !encoding.is_ascii_compatible() {
+                        return Err(format_err!(
+                            "cannot use chunks with encoding={:?}",
+                            encoding
+                        ));
+                    }
+                }
+                Ok(count)
+            }
+        }
+    }
+
+    /// Apply any cleanups requested by our arguments to `data`, using `schema`
+    /// to decide how to interpret each column. If no delimiter was specified,
+    /// we use `default_delimiter`. We use `stream_name` to name any file of
+    /// bad rows.
+    pub(crate) fn transform_data(
+        &self,
+        ctx: &Context,
+        schema: &Table,
+        stream_name: &str,
+        default_delimiter: Delimiter,
+        mut data: BoxStream<BytesMut>,
+    ) -> Result<BoxStream<BytesMut>> {
+        // Convert to UTF-8 and regular CSV first, so that everything else can
+        // assume UTF-8 and commas.
+        if let Some(encoding) = self.encoding.filter(|e| !e.is_utf8()) {
+            data = spawn_sync_transform(
+                ctx.clone(),
+                "transcode_to_utf8".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| transcode_to_utf8(encoding, rdr, wtr),
+            )?;
+        }
+        if let Some(widths) = self.column_widths.clone() {
+            if self.delimiter.is_some() || self.has_header.is_some() {
+                return Err(format_err!(
+                    "column_widths cannot be used with delimiter or has_header"
+                ));
+            }
+            let schema = schema.to_owned();
+            data = spawn_sync_transform(
+                ctx.clone(),
+                "fixed_width_to_csv".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| fixed_width_to_csv(&schema, &widths, rdr, wtr),
+            )?;
+        } else {
+            let delimiter = self.delimiter.unwrap_or(default_delimiter);
+            if delimiter != Delimiter::COMMA {
+                data = spawn_sync_transform(
+                    ctx.clone(),
+                    "change_delimiter".to_owned(),
+                    data,
+                    move |_ctx, rdr, wtr| {
+                        change_delimiter(delimiter, Delimiter::COMMA, rdr, wtr)
+                    },
+                )?;
+            }
+            if !self.has_header.unwrap_or(true) {
+                let schema = schema.to_owned();
+                data = spawn_sync_transform(
+                    ctx.clone(),
+                    "add_header".to_owned(),
+                    data,
+                    move |_ctx, rdr, wtr| add_header(&schema, rdr, wtr),
+                )?;
+            }
+        }
+        if let Some(null_values) = self.null_value.clone() {
+            data = spawn_sync_transform(
+                ctx.clone(),
+                "read_null_values".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| read_null_values(&null_values, rdr, wtr),
+            )?;
+        }
+        if let Some(policy) = self.on_column_mismatch {
+            data = spawn_sync_transform(
+                ctx.clone(),
+                "fix_column_counts".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| fix_column_counts(policy, rdr, wtr),
+            )?;
+        }
+        match (self.on_error.unwrap_or_default(), &self.bad_rows) {
+            (OnError::Error, None) => {}
+            (OnError::Error, Some(_)) => {
+                return Err(format_err!("bad_rows requires on_error=skip"));
+            }
+            (OnError::Skip, bad_rows) => {
+                let rejects_path = bad_rows
+                    .as_ref()
+                    .map(|dir| dir.path_for_stream(stream_name));
+                data = spawn_sync_transform(
+                    ctx.clone(),
+                    "skip_bad_rows".to_owned(),
+                    data,
+                    move |ctx, rdr, wtr| {
+                        skip_bad_rows(&ctx, rejects_path.as_deref(), rdr, wtr)
+                    },
+                )?;
+            }
+        }
+        if !self.cleanup.is_empty() {
+            let specs = self
+                .cleanup
+                .iter()
+                .map(|(name, spec)| {
+                    Ok((name.to_owned(), spec.parse::<CleanupSpec>()?))
+                })
+                .collect::<Result<HashMap<_, _>>>()?;
+            data = spawn_sync_transform(
+                ctx.clone(),
+                "cleanup_csv".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| cleanup_csv(&specs, rdr, wtr),
+            )?;
+        }
+        if self.decimal_separator.is_some() || self.date_format.is_some() {
+            let locale = LocaleOptions::new(
+                self.decimal_separator,
+                self.date_format.as_deref(),
+            )?;
+            let schema = schema.to_owned();
+            data = spawn_sync_transform(
+                ctx.clone(),
+                "delocalize_csv".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| delocalize_csv(&locale, &schema, rdr, wtr),
+            )?;
+        }
+        Ok(data)
+    }
+}
+
+/// Parsed version of `--to-arg` values.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct CsvDestinationArguments {
+    /// The columns to write, in order, like `id,name,email`. Any other input
+    /// columns are dropped.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    columns: Option<SelectColumns>,
+
+    /// The character used to separate fields, if it isn't `,`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    delimiter: Option<Delimiter>,
+
+    /// The line ending to use at the end of each row. Defaults to `lf`.
+    line_ending: Option<LineEnding>,
+
+    /// Should we write a header row? Defaults to `true`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    write_header: Option<bool>,
+
+    /// Should we start our output with a UTF-8 byte-order mark? Defaults to
+    /// `false`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    write_bom: Option<bool>,
+
+    /// The string used to represent `NULL`, if it isn't an empty field.
+    null_value: Option<String>,
+
+    /// How should we compress our output?
+    compression: Option<Compression>,
+
+    /// Split each output stream into files of approximately this size, like
+    /// `256MB`.
+    max_file_size: Option<String>,
+
+    /// Split each output stream into files with at most this many rows.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    max_rows_per_file: Option<usize>,
+
+    /// Split our output into Hive-style directories like `event_date=...`
+    /// using the values of this column.
+    partition_by: Option<String>,
+}
+
+impl CsvDestinationArguments {
+    /// The columns we were asked to write, if any.
+    pub(crate) fn columns(&self) -> Option<&SelectColumns> {
+        self.columns.as_ref()
+    }
+
+    /// The compression we were asked to use, if any.
+    pub(crate) fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// The column we should use to partition our output, if any.
+    pub(crate) fn partition_by(&self) -> Option<&str> {
+        self.partition_by.as_deref()
+    }
+
+    /// How should we split our output into multiple files?
+    pub(crate) fn chunk_limits(&self) -> Result<ChunkLimits> {
+        ChunkLimits::from_driver_args(
+            self.max_file_size.as_deref(),
+            self.max_rows_per_file,
+        )
+    }
+
+    /// Convert `data` from our CSV interchange format to the format requested
+    /// by our arguments. If no compression was specified, we use
+    /// `default_compression`.
+    pub(crate) fn transform_data(
+        &self,
+        ctx: &Context,
+        default_compression: Option<Compression>,
+        mut data: BoxStream<BytesMut>,
+    ) -> Result<BoxStream<BytesMut>> {
+        if let Some(null_value) = self.null_value.clone() {
+            data = spawn_sync_transform(
+                ctx.clone(),
+                "write_null_values".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| write_null_values(&null_value, rdr, wtr),
+            )?;
+        }
+        if !self.write_header.unwrap_or(true) {
+            data = spawn_sync_transform(
+                ctx.clone(),
+                "remove_header".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| remove_header(rdr, wtr),
+            )?;
+        }
+        let delimiter = self.delimiter.unwrap_or(Delimiter::COMMA);
+        if delimiter != Delimiter::COMMA {
+            data = spawn_sync_transform(
+                ctx.clone(),
+                "change_delimiter".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| {
+                    change_delimiter(Delimiter::COMMA, delimiter, rdr, wtr)
+                },
+            )?;
+        }
+        if let Some(line_ending) = self.line_ending {
+            data = spawn_sync_transform(
+                ctx.clone(),
+                "change_line_ending".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| {
+                    change_line_ending(delimiter, line_ending, rdr, wtr)
+                },
+            )?;
+        }
+        if self.write_bom.unwrap_or(false) {
+            data = add_utf8_bom(data);
+        }
+        if let Some(compression) = self.compression.or(default_compression) {
+            data = compression.compress_stream(ctx, data)?;
+        }
+        Ok(data)
+    }
+}
+
+#[test]
+fn parse_encoding_args() {
+    let args = DriverArguments::from_cli_args(&["encoding=latin1"]).unwrap();
+    let src = args.deserialize::<CsvSourceArguments>().unwrap();
+    assert_eq!(src.encoding, Some("windows-1252".parse().unwrap()));
+    let args = DriverArguments::from_cli_args(&["encoding=ebcdic"]).unwrap();
+    assert!(args.deserialize::<CsvSourceArguments>().is_err());
+}
+
+#[test]
+fn parse_delimiter_args() {
+    let args = DriverArguments::from_cli_args(&["delimiter=tab"]).unwrap();
+    let src = args.deserialize::<CsvSourceArguments>().unwrap();
+    assert_eq!(src.delimiter, Some(Delimiter::TAB));
+    let args = DriverArguments::from_cli_args(&["delimiter=||"]).unwrap();
+    assert!(args.deserialize::<CsvDestinationArguments>().is_err());
+    let args = DriverArguments::from_cli_args(&["compression=zstd"]).unwrap();
+    let dest = args.deserialize::<CsvDestinationArguments>().unwrap();
+    assert_eq!(dest.compression(), Some(Compression::Zstd));
+}
+
+#[test]
+fn parse_column_widths_args() {
+    let args = DriverArguments::from_cli_args(&["column_widths=4,10,8"]).unwrap();
+    let src = args.deserialize::<CsvSourceArguments>().unwrap();
+    assert!(src.is_fixed_width());
+    let args = DriverArguments::from_cli_args(&["column_widths=4,x"]).unwrap();
+    assert!(args.deserialize::<CsvSourceArguments>().is_err());
+}
+
+#[test]
+fn parse_line_ending_args() {
+    let args = DriverArguments::from_cli_args(&["line_ending=crlf"]).unwrap();
+    let dest = args.deserialize::<CsvDestinationArguments>().unwrap();
+    assert_eq!(dest.line_ending, Some(LineEnding::Crlf));
+    let args = DriverArguments::from_cli_args(&["line_ending=cr"]).unwrap();
+    assert!(args.deserialize::<CsvDestinationArguments>().is_err());
+}
+
+#[test]
+fn parse_chunks_args() {
+    let src = CsvSourceArguments::default();
+    assert_eq!(src.chunk_count().unwrap(), 1);
+    let args = DriverArguments::from_cli_args(&["chunks=8"]).unwrap();
+    let src = args.deserialize::<CsvSourceArguments>().unwrap();
+    assert_eq!(src.chunk_count().unwrap(), 8);
+    for &bad in &[&["chunks=0"][..], &["chunks=2", "encoding=utf-16le"][..]] {
+        let args = DriverArguments::from_cli_args(bad).unwrap();
+        let src = args.deserialize::<CsvSourceArguments>().unwrap();
+        assert!(src.chunk_count().is_err());
+    }
+}
+
+#[test]
+fn parse_header_args() {
+    let args = DriverArguments::from_cli_args(&["has_header=false"]).unwrap();
+    let src = args.deserialize::<CsvSourceArguments>().unwrap();
+    assert_eq!(src.has_header, Some(false));
+    let args = DriverArguments::from_cli_args(&["write_header=no"]).unwrap();
+    assert!(args.deserialize::<CsvDestinationArguments>().is_err());
+}
+
+#[test]
+fn parse_null_value_args() {
+    let args = DriverArguments::from_cli_args(&["null_value=\\N"]).unwrap();
+    let src = args.deserialize::<CsvSourceArguments>().unwrap();
+    assert_eq!(src.null_value, Some(NullValues::One("\\N".to_owned())));
+    let args =
+        DriverArguments::from_cli_args(&["null_value[]=\\N", "null_value[]=NULL"])
+            .unwrap();
+    let src = args.deserialize::<CsvSourceArguments>().unwrap();
+    assert_eq!(
+        src.null_value,
+        Some(NullValues::Many(vec!["\\N".to_owned(), "NULL".to_owned()])),
+    );
+}
+
+#[test]
+fn parse_chunk_limit_args() {
+    let args = DriverArguments::from_cli_args(&[
+        "max_file_size=256MB",
+        "max_rows_per_file=10",
+    ])
+    .unwrap();
+    let dest = args.deserialize::<CsvDestinationArguments>().unwrap();
+    assert!(!dest.chunk_limits().unwrap().is_unlimited());
+    let args = DriverArguments::from_cli_args(&["max_file_size=big"]).unwrap();
+    let dest = args.deserialize::<CsvDestinationArguments>().unwrap();
+    assert!(dest.chunk_limits().is_err());
+}
@@ -0,0 +1,108 @@
+//! Converting CSV files in other character encodings to UTF-8.
+
+use encoding_rs::DecoderResult;
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+
+/// A character encoding, like `windows-1252` or `utf-16le`.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub(crate) struct Encoding(&'static encoding_rs::Encoding);
+
+impl Encoding {
+    /// Is this UTF-8, which needs no conversion?
+    pub(crate) fn is_utf8(self) -> bool {
+        self.0 == encoding_rs::UTF_8
+    }
+
+    /// Does this encoding represent `\n` as a single byte, with no other
+    /// characters containing that byte? If so, we can split data at newlines
+    /// without decoding it.
+    pub(crate) fn is_ascii_compatible(self) -> bool {
+        self.0.is_ascii_compatible()
+    }
+}
+
+impl fmt::Debug for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.name())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // This accepts all the labels used by web browsers, like `latin1`.
+        encoding_rs::Encoding::for_label_no_replacement(s.as_bytes())
+            .map(Encoding)
+            .ok_or_else(|| format_err!("unknown character encoding {:?}", s))
+    }
+}
+
+/// Copy data from `rdr` to `wtr`, converting it from `encoding` to UTF-8. If
+/// the data begins with a byte-order mark, we use it to choose between UTF-8,
+/// UTF-16LE and UTF-16BE, and remove it.
+pub(crate) fn transcode_to_utf8<R, W>(
+    encoding: Encoding,
+    mut rdr: R,
+    mut wtr: W,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut decoder = encoding.0.new_decoder();
+    let mut input = vec![0; BUFFER_SIZE];
+    let mut output = vec![0; 3 * BUFFER_SIZE];
+    loop {
+        let count = rdr.read(&mut input).context("error reading data")?;
+        let is_last = count == 0;
+        let mut src = &input[..count];
+        loop {
+            let (result, read, written) =
+                decoder.decode_to_utf8_without_replacement(src, &mut output, is_last);
+            wtr.write_all(&output[..written])
+                .context("error writing data")?;
+            src = &src[read..];
+            match result {
+                DecoderResult::InputEmpty => break,
+                DecoderResult::OutputFull => {}
+                DecoderResult::Malformed(_, _) => {
+                    return Err(format_err!(
+                        "found invalid {} data",
+                        encoding.0.name()
+                    ));
+                }
+            }
+        }
+        if is_last {
+            break;
+        }
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn transcode_encodings_to_utf8() {
+    let windows_1252 = "windows-1252".parse::<Encoding>().unwrap();
+    assert!(!windows_1252.is_utf8());
+    let mut out = vec![];
+    transcode_to_utf8(windows_1252, &b"caf\xe9,\x80\n"[..], &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "café,€\n");
+
+    // UTF-16 with a byte-order mark.
+    let utf16le = "UTF-16LE".parse::<Encoding>().unwrap();
+    let input = b"\xff\xfea\x00,\x00\xe9\x00\n\x00";
+    let mut out = vec![];
+    transcode_to_utf8(utf16le, &input[..], &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "a,é\n");
+
+    let utf16be = "utf-16be".parse::<Encoding>().unwrap();
+    let mut out = vec![];
+    assert!(transcode_to_utf8(utf16be, &b"\x00a\xd8\x00"[..], &mut out).is_err());
+
+    assert!("utf-8".parse::<Encoding>().unwrap().is_utf8());
+    assert!("klingon".parse::<Encoding>().is_err());
+}
@@ -0,0 +1,168 @@
+//! Splitting one large CSV file into chunks which can be read in parallel.
+
+use std::{
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::Path,
+};
+use tokio::fs;
+
+use crate::common::*;
+use crate::path_or_stdio::long_path;
+use crate::tokio_glue::copy_reader_to_stream;
+
+/// A range of bytes in a CSV file, starting and ending at line boundaries.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct FileChunk {
+    /// The offset of the first byte in this chunk.
+    start: u64,
+    /// The offset just past the last byte in this chunk.
+    end: u64,
+    /// Data to output before this chunk. This contains a copy of the header
+    /// row for every chunk except the first.
+    prefix: Vec<u8>,
+}
+
+impl FileChunk {
+    /// Read this chunk from `file`, starting with our prefix.
+    pub(crate) async fn read_from(
+        self,
+        ctx: Context,
+        mut file: fs::File,
+    ) -> Result<BoxStream<BytesMut>> {
+        file.seek(SeekFrom::Start(self.start))
+            .await
+            .context("cannot seek to start of chunk")?;
+        let data = copy_reader_to_stream(ctx, file.take(self.end - self.start))?;
+        if self.prefix.is_empty() {
+            Ok(data.boxed())
+        } else {
+            let prefix = box_stream_once(Ok(BytesMut::from(&self.prefix[..])));
+            Ok(prefix.chain(data).boxed())
+        }
+    }
+}
+
+/// Split the file at `path` into up to `count` chunks, each starting at the
+/// beginning of a line. If `has_header` is true, we repeat the first line of
+/// the file at the start of each chunk.
+///
+/// This will give the wrong answer if any quoted fields contain line breaks,
+/// so it needs to be requested explicitly.
+pub(crate) fn find_file_chunks(
+    path: &Path,
+    count: usize,
+    has_header: bool,
+) -> Result<Vec<FileChunk>> {
+    let mut file = std::fs::File::open(long_path(path)?)
+        .with_context(|_| format!("cannot open {}", path.display()))?;
+    let len = file
+        .metadata()
+        .with_context(|_| format!("cannot stat {}", path.display()))?
+        .len();
+    Ok(find_chunks(&mut file, len, count, has_header)
+        .with_context(|_| format!("cannot split {} into chunks", path.display()))?)
+}
+
+/// Split the `len` bytes of data in `rdr` into up to `count` chunks.
+fn find_chunks<R: Read + Seek>(
+    rdr: &mut R,
+    len: u64,
+    count: usize,
+    has_header: bool,
+) -> Result<Vec<FileChunk>> {
+    let mut line = vec![];
+    let header = if has_header {
+        read_line_at(rdr, 0, &mut line)?;
+        line.clone()
+    } else {
+        vec![]
+    };
+    let header_len = header.len() as u64;
+
+    // Pick evenly-spaced offsets, and move each one forward to the start of
+    // the next line. Every chunk must contain at least one row.
+    let mut starts = vec![0];
+    for i in 1..count as u64 {
+        let target = (len * i / count as u64).max(header_len);
+        let start = if target == 0 {
+            0
+        } else {
+            (target - 1) + read_line_at(rdr, target - 1, &mut line)? as u64
+        };
+        let prev_start = *starts.last().expect("starts should never be empty");
+        if start > prev_start.max(header_len) && start < len {
+            starts.push(start);
+        }
+    }
+
+    Ok(starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| FileChunk {
+            start,
+            end: starts.get(i + 1).copied().unwrap_or(len),
+            prefix: if i == 0 { vec![] } else { header.clone() },
+        })
+        .collect())
+}
+
+/// Read from `offset` up to and including the next newline, returning the
+/// number of bytes read.
+fn read_line_at<R: Read + Seek>(
+    rdr: &mut R,
+    offset: u64,
+    line: &mut Vec<u8>,
+) -> Result<usize> {
+    rdr.seek(SeekFrom::Start(offset))
+        .context("cannot seek in file")?;
+    line.clear();
+    Ok(BufReader::new(rdr)
+        .read_until(b'\n', line)
+        .context("cannot read line")?)
+}
+
+#[test]
+fn find_chunks_at_line_boundaries() {
+    use std::io::Cursor;
+
+    let data = b"id,name\n1,aaaa\n2,bbbb\n3,cccc\n4,dddd\n";
+    let chunks = |count, has_header| {
+        find_chunks(
+            &mut Cursor::new(&data[..]),
+            data.len() as u64,
+            count,
+            has_header,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|c| {
+            let mut out = c.prefix.clone();
+            let range =
+                usize::try_from(c.start).unwrap()..usize::try_from(c.end).unwrap();
+            out.extend_from_slice(&data[range]);
+            String::from_utf8(out).unwrap()
+        })
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        chunks(1, true),
+        vec![String::from_utf8(data.to_vec()).unwrap()]
+    );
+    assert_eq!(
+        chunks(2, true),
+        vec!["id,name\n1,aaaa\n2,bbbb\n", "id,name\n3,cccc\n4,dddd\n"],
+    );
+    assert_eq!(
+        chunks(4, false),
+        vec!["id,name\n1,aaaa\n", "2,bbbb\n", "3,cccc\n", "4,dddd\n"],
+    );
+    // We never return more chunks than we have rows.
+    assert_eq!(chunks(100, true).len(), 4);
+    assert_eq!(
+        find_chunks(&mut Cursor::new(&b"id\n"[..]), 3, 4, true)
+            .unwrap()
+            .len(),
+        1,
+    );
+}
@@ -0,0 +1,135 @@
+//! Reading fixed-width text files, like mainframe extracts.
+
+use std::{
+    io::{BufRead, BufReader},
+    str::FromStr,
+};
+
+use crate::common::*;
+
+/// The width of each column in a fixed-width file, measured in characters.
+/// Parsed from `column_widths=4,10,8`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ColumnWidths(Vec<usize>);
+
+impl FromStr for ColumnWidths {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let widths = s
+            .split(',')
+            .map(|w| match w.trim().parse::<usize>() {
+                Ok(w) if w > 0 => Ok(w),
+                _ => Err(format_err!("invalid column width {:?} in {:?}", w, s)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ColumnWidths(widths))
+    }
+}
+
+/// Copy fixed-width data from `rdr` to `wtr`, converting it to CSV with a
+/// header row containing the column names from `schema`. Leading and trailing
+/// spaces are removed from each field, and blank lines are skipped.
+pub(crate) fn fixed_width_to_csv<R, W>(
+    schema: &Table,
+    widths: &ColumnWidths,
+    rdr: R,
+    wtr: W,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    if widths.0.len() != schema.columns.len() {
+        return Err(format_err!(
+            "column_widths has {} widths, but schema has {} columns",
+            widths.0.len(),
+            schema.columns.len(),
+        ));
+    }
+
+    let mut rdr = BufReader::with_capacity(BUFFER_SIZE, rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+    wtr.write_record(schema.columns.iter().map(|c| &c.name))
+        .context("cannot write CSV header")?;
+
+    let mut line = String::new();
+    let mut line_idx = 0;
+    loop {
+        line.clear();
+        if rdr
+            .read_line(&mut line)
+            .context("cannot read fixed-width data")?
+            == 0
+        {
+            break;
+        }
+        line_idx += 1;
+        let record = line.trim_end_matches(&['\r', '\n'][..]);
+        if record.trim().is_empty() {
+            continue;
+        }
+
+        // Split our line into fields, counting characters, not bytes. Lines
+        // may be missing trailing fields if they're blank.
+        let mut rest = record;
+        let mut fields = Vec::with_capacity(widths.0.len());
+        for &width in &widths.0 {
+            let end = rest
+                .char_indices()
+                .nth(width)
+                .map_or(rest.len(), |(idx, _)| idx);
+            fields.push(rest[..end].trim());
+            rest = &rest[end..];
+        }
+        if !rest.trim().is_empty() {
+            return Err(format_err!(
+                "line {} is longer than the {} characters in column_widths",
+                line_idx,
+                widths.0.iter().sum::<usize>(),
+            ));
+        }
+        wtr.write_record(&fields).context("cannot write row")?;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn convert_fixed_width_to_csv() {
+    use crate::schema::{Column, DataType};
+
+    let schema = Table {
+        name: "t".to_owned(),
+        columns: ["id", "name", "amount"]
+            .iter()
+            .map(|&name| Column {
+                name: name.to_owned(),
+                is_nullable: true,
+                data_type: DataType::Text,
+                comment: None,
+            })
+            .collect(),
+    };
+    let widths = "3, 8,6".parse::<ColumnWidths>().unwrap();
+    let input = "001Zoë     12.50\r\n002Bob, Jr. \n\n003        -1\n";
+    let mut out = vec![];
+    fixed_width_to_csv(&schema, &widths, input.as_bytes(), &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "id,name,amount\n001,Zoë,12.50\n002,\"Bob, Jr.\",\n003,,-1\n",
+    );
+
+    let mut out = vec![];
+    assert!(fixed_width_to_csv(
+        &schema,
+        &widths,
+        &b"001Zoe     12.50  x\n"[..],
+        &mut out
+    )
+    .is_err());
+    let widths = "3,8".parse::<ColumnWidths>().unwrap();
+    assert!(fixed_width_to_csv(&schema, &widths, &b""[..], &mut vec![]).is_err());
+    assert!("3,0".parse::<ColumnWidths>().is_err());
+    assert!("3,,8".parse::<ColumnWidths>().is_err());
+}
@@ -0,0 +1,86 @@
+//! Support for input paths containing glob patterns, like `data/**/*.csv`.
+
+use glob::{MatchOptions, Pattern};
+use std::path::{Component, Path, PathBuf};
+
+use crate::common::*;
+use crate::path_or_stdio::to_slash_lossy;
+
+/// A path containing glob patterns, split into the directory that we need to
+/// search, and a pattern to match against paths relative to that directory.
+#[derive(Debug)]
+pub(crate) struct GlobPath {
+    /// The part of our path before the first component containing a wildcard.
+    base: PathBuf,
+    /// A pattern matching paths relative to `base`.
+    pattern: Pattern,
+}
+
+impl GlobPath {
+    /// Parse `path` as a glob, if it contains any of `*`, `?` or `[`.
+    /// Otherwise, return `None`.
+    pub(crate) fn parse(path: &Path) -> Result<Option<GlobPath>> {
+        let components = path.components().collect::<Vec<_>>();
+        let first_glob = components.iter().position(|c| match c {
+            Component::Normal(part) => {
+                part.to_string_lossy().contains(&['*', '?', '['][..])
+            }
+            _ => false,
+        });
+        let first_glob = match first_glob {
+            Some(first_glob) => first_glob,
+            None => return Ok(None),
+        };
+
+        let mut base = components[..first_glob].iter().collect::<PathBuf>();
+        if base.as_os_str().is_empty() {
+            base = PathBuf::from(".");
+        }
+        let pattern_str = components[first_glob..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let pattern = Pattern::new(&pattern_str)
+            .with_context(|_| format!("invalid glob pattern {}", path.display()))?;
+        Ok(Some(GlobPath { base, pattern }))
+    }
+
+    /// The directory we need to search for matching files.
+    pub(crate) fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Does `rel_path`, which should be relative to `self.base()`, match our
+    /// pattern?
+    pub(crate) fn matches(&self, rel_path: &Path) -> bool {
+        // Don't let `*` match `/`. Only `**` should match directories.
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        self.pattern
+            .matches_with(&to_slash_lossy(rel_path), options)
+    }
+}
+
+#[test]
+fn parse_and_match_globs() {
+    assert!(GlobPath::parse(Path::new("data/2023/")).unwrap().is_none());
+
+    let glob = GlobPath::parse(Path::new("data/**/*_2023*.csv"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(glob.base(), Path::new("data"));
+    assert!(glob.matches(Path::new("sales_2023.csv")));
+    assert!(glob.matches(Path::new("a/b/sales_2023_01.csv")));
+    assert!(!glob.matches(Path::new("a/sales_2022.csv")));
+    assert!(!glob.matches(Path::new("sales_2023.tsv")));
+
+    let glob = GlobPath::parse(Path::new("*.csv")).unwrap().unwrap();
+    assert_eq!(glob.base(), Path::new("."));
+    assert!(glob.matches(Path::new("a.csv")));
+    assert!(!glob.matches(Path::new("dir/a.csv")));
+
+    assert!(GlobPath::parse(Path::new("data/[.csv")).is_err());
+}
@@ -0,0 +1,73 @@
+//! Support for CSV files without a header row.
+
+use std::io;
+
+use crate::common::*;
+
+/// Copy headerless CSV data from `rdr` to `wtr`, adding a header row with
+/// the column names from `schema`.
+pub(crate) fn add_header<R, W>(schema: &Table, mut rdr: R, mut wtr: W) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut header_wtr = csv::Writer::from_writer(&mut wtr);
+    header_wtr
+        .write_record(schema.columns.iter().map(|c| &c.name))
+        .context("cannot write CSV header")?;
+    header_wtr.flush().context("cannot write CSV header")?;
+    drop(header_wtr);
+    io::copy(&mut rdr, &mut wtr).context("error copying CSV data")?;
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+/// Copy CSV data from `rdr` to `wtr`, leaving out the header row.
+pub(crate) fn remove_header<R, W>(rdr: R, wtr: W) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(true)
+        .from_reader(rdr);
+    let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(wtr);
+    let mut row = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut row).context("cannot read row")? {
+        wtr.write_byte_record(&row).context("cannot write row")?;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn add_and_remove_headers() {
+    use crate::schema::{Column, DataType};
+
+    let schema = Table {
+        name: "t".to_owned(),
+        columns: ["id", "note, with comma"]
+            .iter()
+            .map(|&name| Column {
+                name: name.to_owned(),
+                is_nullable: true,
+                data_type: DataType::Text,
+                comment: None,
+            })
+            .collect(),
+    };
+    let mut with_header = vec![];
+    add_header(&schema, &b"1,\"a\nb\"\n2,c\n"[..], &mut with_header).unwrap();
+    assert_eq!(
+        String::from_utf8(with_header.clone()).unwrap(),
+        "id,\"note, with comma\"\n1,\"a\nb\"\n2,c\n",
+    );
+
+    let mut without_header = vec![];
+    remove_header(&with_header[..], &mut without_header).unwrap();
+    assert_eq!(
+        String::from_utf8(without_header).unwrap(),
+        "1,\"a\nb\"\n2,c\n",
+    );
+}
@@ -0,0 +1,155 @@
+//! Guessing column types by looking at the first few rows of a CSV file.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use uuid::Uuid;
+
+use crate::common::*;
+use crate::from_csv_cell::FromCsvCell;
+use crate::schema::{Column, DataType};
+
+/// How many rows should we look at by default?
+pub(crate) const DEFAULT_INFER_ROWS: usize = 1000;
+
+/// The types we know how to infer, in order of preference. When more than one
+/// type matches every value in a column, we use the first.
+const CANDIDATES: &[DataType] = &[
+    DataType::Int64,
+    DataType::Float64,
+    DataType::Bool,
+    DataType::Date,
+    DataType::TimestampWithoutTimeZone,
+    DataType::TimestampWithTimeZone,
+    DataType::Uuid,
+];
+
+/// Can `cell` be parsed as a value of type `data_type`? We use the same parsers
+/// that we use when importing CSV data, so that every value in our sample is
+/// guaranteed to be readable.
+fn cell_matches(data_type: &DataType, cell: &str) -> bool {
+    match data_type {
+        // Leading zeros usually mean something like a ZIP code or an account
+        // number, which should stay as text.
+        DataType::Int64 => !has_leading_zero(cell) && i64::from_csv_cell(cell).is_ok(),
+        DataType::Float64 => {
+            !has_leading_zero(cell)
+                && cell.bytes().any(|b| b.is_ascii_digit())
+                && f64::from_csv_cell(cell).is_ok()
+        }
+        DataType::Bool => bool::from_csv_cell(cell).is_ok(),
+        DataType::Date => NaiveDate::from_csv_cell(cell).is_ok(),
+        DataType::TimestampWithoutTimeZone => {
+            NaiveDateTime::from_csv_cell(cell).is_ok()
+        }
+        DataType::TimestampWithTimeZone => {
+            DateTime::<FixedOffset>::from_csv_cell(cell).is_ok()
+        }
+        DataType::Uuid => Uuid::from_csv_cell(cell).is_ok(),
+        _ => false,
+    }
+}
+
+/// Does `cell` look like a number with a leading zero, like `007`?
+fn has_leading_zero(cell: &str) -> bool {
+    let digits = cell.trim_start_matches(&['-', '+'][..]);
+    digits.len() > 1
+        && digits.starts_with('0')
+        && digits.as_bytes()[1].is_ascii_digit()
+}
+
+/// Read the headers and up to `max_rows` rows from `rdr`, and use them to guess
+/// the type of each column. `rdr` should be `flexible`, so that we can tolerate
+/// rows with the wrong number of columns. All columns are nullable, because we can't know
+/// what the rest of the file contains. If `max_rows` is 0, every column will
+/// be `Text`.
+pub(crate) fn infer_columns<R: Read>(
+    rdr: &mut csv::Reader<R>,
+    max_rows: usize,
+) -> Result<Vec<Column>> {
+    let headers = rdr.headers().context("cannot read CSV header")?.to_owned();
+
+    // For each column, keep track of which types are still possible, and
+    // whether we've seen any non-empty values at all.
+    let mut candidates = vec![CANDIDATES.to_vec(); headers.len()];
+    let mut seen_values = vec![false; headers.len()];
+    for row in rdr.records().take(max_rows) {
+        // If we can't parse a row, we stop guessing and leave it to whoever
+        // reads the data to report a proper error. Some destinations, like
+        // `csv:`, may not even care.
+        let row = match row {
+            Ok(row) => row,
+            Err(_) => break,
+        };
+        for (idx, cell) in row.iter().enumerate().take(headers.len()) {
+            // Empty cells are `NULL`, which is compatible with every type.
+            if cell.is_empty() {
+                continue;
+            }
+            seen_values[idx] = true;
+            candidates[idx].retain(|data_type| cell_matches(data_type, cell));
+        }
+    }
+
+    Ok(headers
+        .iter()
+        .zip(candidates.into_iter().zip(seen_values))
+        .map(|(name, (candidates, seen_values))| Column {
+            name: name.to_owned(),
+            is_nullable: true,
+            data_type: match candidates.into_iter().next() {
+                Some(data_type) if seen_values => data_type,
+                _ => DataType::Text,
+            },
+            comment: None,
+        })
+        .collect())
+}
+
+#[test]
+fn infer_columns_detects_types() {
+    let data = "\
+int,float,bool,date,ts,tstz,uuid,zip,text,empty
+1,1.5,true,2020-01-31,2020-01-31 10:00:00,2020-01-31 10:00:00+00,cf0b5cd8-10b0-4a1b-9f3f-0c3b3fb8d1a2,02134,a,
+-20,2,F,2020-02-01,2020-02-01T10:00:00.5,2020-02-01T10:00:00-05:00,,90210,1,
+,,,,,,,,,
+";
+    let mut rdr = csv::Reader::from_reader(data.as_bytes());
+    let columns = infer_columns(&mut rdr, DEFAULT_INFER_ROWS).unwrap();
+    let types = columns
+        .iter()
+        .map(|c| (c.name.as_str(), c.data_type.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            ("int", DataType::Int64),
+            ("float", DataType::Float64),
+            ("bool", DataType::Bool),
+            ("date", DataType::Date),
+            ("ts", DataType::TimestampWithoutTimeZone),
+            ("tstz", DataType::TimestampWithTimeZone),
+            ("uuid", DataType::Uuid),
+            ("zip", DataType::Text),
+            ("text", DataType::Text),
+            ("empty", DataType::Text),
+        ],
+    );
+    assert!(columns.iter().all(|c| c.is_nullable));
+
+    // Only look at the first row.
+    let data = "a\n1\nx\n";
+    let mut rdr = csv::Reader::from_reader(data.as_bytes());
+    let columns = infer_columns(&mut rdr, 1).unwrap();
+    assert_eq!(columns[0].data_type, DataType::Int64);
+    let mut rdr = csv::Reader::from_reader(data.as_bytes());
+    let columns = infer_columns(&mut rdr, 0).unwrap();
+    assert_eq!(columns[0].data_type, DataType::Text);
+
+    // Ragged and malformed rows are tolerated.
+    let data = b"a,b\n1\n2,x,y\n\xff,z\n";
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(&data[..]);
+    let columns = infer_columns(&mut rdr, DEFAULT_INFER_ROWS).unwrap();
+    assert_eq!(columns[0].data_type, DataType::Int64);
+    assert_eq!(columns[1].data_type, DataType::Text);
+}
@@ -0,0 +1,77 @@
+//! Choosing the line endings used in CSV output.
+
+use serde::Deserialize;
+
+use super::delimiter::Delimiter;
+use crate::common::*;
+
+/// What line ending should we use at the end of each row?
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum LineEnding {
+    /// `\n`, as used on Unix systems.
+    Lf,
+    /// `\r\n`, as used on Windows and required by some Windows tools.
+    Crlf,
+}
+
+impl LineEnding {
+    /// The `csv` terminator for this line ending.
+    fn terminator(self) -> csv::Terminator {
+        match self {
+            LineEnding::Lf => csv::Terminator::Any(b'\n'),
+            LineEnding::Crlf => csv::Terminator::CRLF,
+        }
+    }
+}
+
+/// Copy data separated by `delimiter` from `rdr` to `wtr`, ending each row
+/// with `line_ending`. Line breaks inside quoted fields are left alone.
+pub(crate) fn change_line_ending<R, W>(
+    delimiter: Delimiter,
+    line_ending: LineEnding,
+    rdr: R,
+    wtr: W,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    // Don't check row lengths or headers here. That's somebody else's job.
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter.as_byte())
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(rdr);
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(delimiter.as_byte())
+        .terminator(line_ending.terminator())
+        .flexible(true)
+        .from_writer(wtr);
+    let mut row = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut row).context("cannot read row")? {
+        wtr.write_byte_record(&row).context("cannot write row")?;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn change_line_endings() {
+    let input = "a,b\n1,\"x\ny\"\r\n";
+    let mut out = vec![];
+    change_line_ending(
+        Delimiter::COMMA,
+        LineEnding::Crlf,
+        input.as_bytes(),
+        &mut out,
+    )
+    .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "a,b\r\n1,\"x\ny\"\r\n");
+
+    let mut out = vec![];
+    let input = "a|b\r\n1,2|3\r\n";
+    let pipe = "|".parse::<Delimiter>().unwrap();
+    change_line_ending(pipe, LineEnding::Lf, input.as_bytes(), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "a|b\n1,2|3\n");
+}
@@ -0,0 +1,202 @@
+//! Parsing numbers and dates written using local conventions.
+
+use chrono::NaiveDate;
+
+use crate::common::*;
+use crate::schema::DataType;
+
+/// How should we interpret locale-specific numbers and dates?
+#[derive(Clone, Debug)]
+pub(crate) struct LocaleOptions {
+    /// The character used as a decimal point.
+    decimal_separator: char,
+    /// A `chrono`-style format string for parsing dates.
+    date_format: Option<String>,
+}
+
+impl LocaleOptions {
+    /// Create new `LocaleOptions`. `date_format` uses patterns like
+    /// `DD.MM.YYYY`.
+    pub(crate) fn new(
+        decimal_separator: Option<char>,
+        date_format: Option<&str>,
+    ) -> Result<Self> {
+        let decimal_separator = decimal_separator.unwrap_or('.');
+        if decimal_separator.is_ascii_digit() || decimal_separator == '-' {
+            return Err(format_err!(
+                "cannot use {:?} as a decimal separator",
+                decimal_separator,
+            ));
+        }
+        Ok(LocaleOptions {
+            decimal_separator,
+            date_format: date_format.map(date_format_to_strftime).transpose()?,
+        })
+    }
+
+    /// Convert a number from our local format to our CSV interchange format.
+    ///
+    /// If our decimal separator is `,`, then we assume that `.` and spaces are
+    /// used to group thousands, and we remove them.
+    fn delocalize_number(&self, cell: &str) -> String {
+        if self.decimal_separator == '.' {
+            return cell.to_owned();
+        }
+        cell.chars()
+            .filter_map(|c| {
+                if c == self.decimal_separator {
+                    Some('.')
+                } else if c == '.' || c == ' ' || c == '\u{a0}' {
+                    None
+                } else {
+                    Some(c)
+                }
+            })
+            .collect()
+    }
+
+    /// Convert a date from our local format to our CSV interchange format.
+    fn delocalize_date(&self, cell: &str) -> Result<String> {
+        match &self.date_format {
+            Some(date_format) => {
+                let date = NaiveDate::parse_from_str(cell, date_format)
+                    .with_context(|_| format!("cannot parse {:?} as date", cell))?;
+                Ok(date.format("%Y-%m-%d").to_string())
+            }
+            None => Ok(cell.to_owned()),
+        }
+    }
+}
+
+/// Convert a date format like `DD.MM.YYYY` to a `chrono` format string like
+/// `%d.%m.%Y`.
+fn date_format_to_strftime(date_format: &str) -> Result<String> {
+    let mut result = String::with_capacity(date_format.len() * 2);
+    let mut rest = date_format;
+    while !rest.is_empty() {
+        let (replacement, len) = if rest.starts_with("YYYY") {
+            ("%Y", 4)
+        } else if rest.starts_with("YY") {
+            ("%y", 2)
+        } else if rest.starts_with("MM") {
+            ("%m", 2)
+        } else if rest.starts_with("DD") {
+            ("%d", 2)
+        } else if rest.starts_with('%') {
+            ("%%", 1)
+        } else {
+            let c = rest.chars().next().expect("rest should not be empty");
+            if c.is_ascii_alphanumeric() {
+                return Err(format_err!(
+                    "unsupported character {:?} in date format {:?}",
+                    c,
+                    date_format,
+                ));
+            }
+            result.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        result.push_str(replacement);
+        rest = &rest[len..];
+    }
+    Ok(result)
+}
+
+#[test]
+fn date_format_to_strftime_examples() {
+    assert_eq!(date_format_to_strftime("DD.MM.YYYY").unwrap(), "%d.%m.%Y");
+    assert_eq!(date_format_to_strftime("MM/DD/YY").unwrap(), "%m/%d/%y");
+    assert!(date_format_to_strftime("DD.MM.YYYY hh").is_err());
+}
+
+/// Copy CSV data from `rdr` to `wtr`, converting locale-specific numbers and
+/// dates to our CSV interchange format. We use `schema` to decide which columns
+/// to convert.
+pub(crate) fn delocalize_csv<R, W>(
+    locale: &LocaleOptions,
+    schema: &Table,
+    rdr: R,
+    wtr: W,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+
+    // Look up the data type of each column in our CSV file.
+    let headers = rdr.headers().context("cannot read CSV header")?.to_owned();
+    let data_types = headers
+        .iter()
+        .map(|name| {
+            schema
+                .columns
+                .iter()
+                .find(|c| c.name == name)
+                .map(|c| c.data_type.clone())
+                .ok_or_else(|| format_err!("could not find column {} in schema", name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    wtr.write_record(&headers)
+        .context("cannot write CSV header")?;
+
+    let mut out = csv::StringRecord::new();
+    for (row_idx, row) in rdr.records().enumerate() {
+        let row = row.context("cannot read row")?;
+        out.clear();
+        for (cell, data_type) in row.iter().zip(data_types.iter()) {
+            if cell.is_empty() {
+                out.push_field(cell);
+                continue;
+            }
+            match data_type {
+                DataType::Decimal
+                | DataType::FixedDecimal { .. }
+                | DataType::Float32
+                | DataType::Float64 => {
+                    out.push_field(&locale.delocalize_number(cell));
+                }
+                DataType::Date => {
+                    let date = locale
+                        .delocalize_date(cell)
+                        .with_context(|_| format!("error in row {}", row_idx + 1))?;
+                    out.push_field(&date);
+                }
+                _ => out.push_field(cell),
+            }
+        }
+        wtr.write_record(&out).context("cannot write row")?;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn delocalize_csv_converts_numbers_and_dates() {
+    use crate::schema::Column;
+
+    let column = |name: &str, data_type| Column {
+        name: name.to_owned(),
+        is_nullable: true,
+        data_type,
+        comment: None,
+    };
+    let schema = Table {
+        name: "example".to_owned(),
+        columns: vec![
+            column("name", DataType::Text),
+            column("amount", DataType::Decimal),
+            column("day", DataType::Date),
+        ],
+    };
+    let locale = LocaleOptions::new(Some(','), Some("DD.MM.YYYY")).unwrap();
+    let input = "name,amount,day\n\"a,b\",\"1.234,5\",31.12.2020\nc,,\n";
+    let mut out = vec![];
+    delocalize_csv(&locale, &schema, input.as_bytes(), &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "name,amount,day\n\"a,b\",1234.5,2020-12-31\nc,,\n",
+    );
+}
@@ -1,6 +1,12 @@
 //! Driver for working with CSV files.
 
-use std::{ffi::OsStr, fmt, path::PathBuf, str::FromStr};
+use chrono::{DateTime, Utc};
+use std::{
+    ffi::OsStr,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use tokio::{
     fs,
     io::{self, BufReader},
@@ -8,11 +14,39 @@ use tokio::{
 use walkdir::WalkDir;
 
 use crate::common::*;
+use crate::compression::{decompress_stream_for_file_name, Compression};
 use crate::concat::concatenate_csv_streams;
-use crate::csv_stream::csv_stream_name;
-use crate::schema::{Column, DataType, Table};
+use crate::csv_stream::{csv_stream_file_name, csv_stream_name};
+use crate::partition::partition_csv_streams;
+use crate::path_or_stdio::{ends_with_separator, long_path, to_slash_lossy};
+use crate::rechunk::split_csv_streams;
 use crate::tokio_glue::{copy_reader_to_stream, copy_stream_to_writer};
 
+mod bad_rows;
+mod bom;
+mod cleanup;
+mod column_mismatch;
+mod delimiter;
+mod driver_args;
+mod encoding;
+mod file_chunks;
+mod fixed_width;
+mod glob_path;
+mod header;
+mod infer;
+mod line_ending;
+mod locale;
+mod null_value;
+mod stdin;
+
+use self::bom::{strip_utf8_bom, UTF8_BOM};
+use self::delimiter::{sniff_stream_delimiter, Delimiter};
+use self::driver_args::{CsvDestinationArguments, CsvSourceArguments};
+use self::file_chunks::{find_file_chunks, FileChunk};
+use self::glob_path::GlobPath;
+use self::infer::{infer_columns, DEFAULT_INFER_ROWS};
+use self::stdin::{peek_stdin, stdin_stream};
+
 /// (Incomplete.) A CSV file containing data, or a directory containing CSV
 /// files.
 ///
@@ -21,6 +55,9 @@ use crate::tokio_glue::{copy_reader_to_stream, copy_stream_to_writer};
 #[derive(Clone, Debug)]
 pub(crate) struct CsvLocator {
     path: PathOrStdio,
+    /// How many rows should we look at when guessing column types? Specified
+    /// using `?infer_rows=N`.
+    infer_rows: Option<usize>,
 }
 
 impl CsvLocator {
@@ -28,13 +65,18 @@ impl CsvLocator {
     fn from_path<P: Into<PathBuf>>(path: P) -> Self {
         Self {
             path: PathOrStdio::Path(path.into()),
+            infer_rows: None,
         }
     }
 }
 
 impl fmt::Display for CsvLocator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.path.fmt_locator_helper(Self::scheme(), f)
+        self.path.fmt_locator_helper(Self::scheme(), f)?;
+        if let Some(infer_rows) = self.infer_rows {
+            write!(f, "?infer_rows={}", infer_rows)?;
+        }
+        Ok(())
     }
 }
 
@@ -42,8 +84,44 @@ impl FromStr for CsvLocator {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), s)?;
-        Ok(CsvLocator { path })
+        let (path, query) = match s.find('?') {
+            Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+            None => (s, None),
+        };
+        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), path)?;
+        let mut infer_rows = None;
+        for param in query.into_iter().flat_map(|q| q.split('&')) {
+            let mut parts = param.splitn(2, '=');
+            let key = parts.next().expect("split always returns one value");
+            let value = parts.next().unwrap_or("");
+            match key {
+                "infer_rows" => {
+                    infer_rows = Some(value.parse::<usize>().with_context(|_| {
+                        format!("cannot parse {:?} in {:?}", param, s)
+                    })?);
+                }
+                _ => {
+                    return Err(format_err!(
+                        "unknown option {:?} in {:?} (expected infer_rows)",
+                        param,
+                        s
+                    ))
+                }
+            }
+        }
+        Ok(CsvLocator { path, infer_rows })
+    }
+}
+
+#[test]
+fn parse_infer_rows() {
+    let loc = "csv:data.csv?infer_rows=50".parse::<CsvLocator>().unwrap();
+    assert_eq!(loc.infer_rows, Some(50));
+    assert_eq!(loc.to_string(), "csv:data.csv?infer_rows=50");
+    let loc = "csv:data.csv".parse::<CsvLocator>().unwrap();
+    assert_eq!(loc.infer_rows, None);
+    for bad in &["csv:data.csv?infer_rows=x", "csv:data.csv?rows=10"] {
+        assert!(bad.parse::<CsvLocator>().is_err());
     }
 }
 
@@ -57,38 +135,38 @@ impl Locator for CsvLocator {
         // purely synchrnous library.
         let source = self.to_owned();
         spawn_blocking(move || {
+            let infer_rows = source.infer_rows.unwrap_or(DEFAULT_INFER_ROWS);
             match &source.path {
                 PathOrStdio::Stdio => {
-                    // This is actually fairly tricky, because we may need to first
-                    // read the columns from stdin, _then_ start re-reading from the
-                    // beginning to read the data when `local_data` is called.
-                    Err(format_err!("cannot yet read CSV schema from stdin"))
+                    // Read just enough of stdin to build our schema. This
+                    // data will be replayed when `local_data` is called.
+                    let table = peek_stdin(|rdr| {
+                        infer_table(
+                            "data".to_owned(),
+                            Delimiter::COMMA,
+                            rdr,
+                            infer_rows,
+                        )
+                    })
+                    .context("error reading stdin")?;
+                    Ok(Some(table))
                 }
                 PathOrStdio::Path(path) => {
-                    // Build our columns.
-                    let mut rdr = csv::Reader::from_path(path).with_context(|_| {
-                        format!("error opening {}", path.display())
-                    })?;
-                    let mut columns = vec![];
-                    let headers = rdr.headers().with_context(|_| {
-                        format!("error reading {}", path.display())
-                    })?;
-                    for col_name in headers {
-                        columns.push(Column {
-                            name: col_name.to_owned(),
-                            is_nullable: true,
-                            data_type: DataType::Text,
-                            comment: None,
-                        })
-                    }
-
-                    // Build our table.
-                    let name = path
+                    // We don't have access to `--from-arg` here, so guess our
+                    // delimiter from the file name or the header line.
+                    let name = Compression::strip_extension(path)
                         .file_stem()
                         .unwrap_or_else(|| OsStr::new("data"))
                         .to_string_lossy()
                         .into_owned();
-                    Ok(Some(Table { name, columns }))
+                    let table = infer_table(
+                        name,
+                        guess_delimiter(path)?,
+                        &mut open_sync(path)?,
+                        infer_rows,
+                    )
+                    .with_context(|_| format!("error reading {}", path.display()))?;
+                    Ok(Some(table))
                 }
             }
         })
@@ -104,6 +182,14 @@ impl Locator for CsvLocator {
         local_data_helper(ctx, self.path.clone(), shared_args, source_args).boxed()
     }
 
+    fn size_hint(
+        &self,
+        ctx: Context,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<SizeHint> {
+        size_hint_helper(ctx, self.path.clone(), source_args).boxed()
+    }
+
     fn display_output_locators(&self) -> DisplayOutputLocators {
         match &self.path {
             // If we write our data to standard output, we don't also want to
@@ -131,60 +217,50 @@ async fn local_data_helper(
     shared_args: SharedArguments<Unverified>,
     source_args: SourceArguments<Unverified>,
 ) -> Result<Option<BoxStream<CsvStream>>> {
-    let _shared_args = shared_args.verify(CsvLocator::features())?;
-    let _source_args = source_args.verify(CsvLocator::features())?;
+    let shared_args = shared_args.verify(CsvLocator::features())?;
+    let source_args = source_args.verify(CsvLocator::features())?;
+    let schema = shared_args.schema().to_owned();
+    let csv_args = source_args
+        .driver_args()
+        .deserialize::<CsvSourceArguments>()
+        .context("could not parse --from-arg")?;
     match path {
         PathOrStdio::Stdio => {
-            let data = BufReader::with_capacity(BUFFER_SIZE, io::stdin());
-            let stream = copy_reader_to_stream(ctx, data)?;
+            let stream = strip_utf8_bom(stdin_stream(&ctx)?).await?;
+            let (delimiter, stream) = sniff_stream_delimiter(stream).await?;
             let csv_stream = CsvStream {
                 name: "data".to_owned(),
-                data: stream
-                    .map_err(move |e| format_err!("cannot read stdin: {}", e))
-                    .boxed(),
+                metadata: StreamMetadata::default(),
+                data: csv_args
+                    .transform_data(&ctx, &schema, "data", delimiter, stream)?,
             };
             Ok(Some(box_stream_once(Ok(csv_stream))))
         }
-        PathOrStdio::Path(base_path) => {
+        PathOrStdio::Path(path) => {
             // Recursively look at our paths, picking out the ones that look
             // like CSVs. We do this synchronously because it's reasonably
             // fast and we'd like to catch errors up front.
-            let mut paths = vec![];
-            debug!(ctx.log(), "walking {}", base_path.display());
-            let walker = WalkDir::new(&base_path).follow_links(true);
-            for dirent in walker.into_iter() {
-                let dirent = dirent.with_context(|_| {
-                    format!("error listing files in {}", base_path.display())
-                })?;
-                let p = dirent.path();
-                trace!(ctx.log(), "found dirent {}", p.display());
-                if dirent.file_type().is_dir() {
-                    continue;
-                } else if !dirent.file_type().is_file() {
-                    return Err(format_err!("not a file: {}", p.display()));
-                }
-
-                let ext = p.extension();
-                if ext == Some(OsStr::new("csv")) || ext == Some(OsStr::new("CSV")) {
-                    paths.push(p.to_owned());
-                } else {
-                    return Err(format_err!(
-                        "{} must end in *.csv or *.CSV",
-                        p.display()
-                    ));
-                }
-            }
+            let (base_path, paths) =
+                find_csv_paths(&ctx, &path, csv_args.is_fixed_width())?;
+            let sources = split_csv_paths(&csv_args, paths)?;
 
-            let csv_streams = stream::iter(paths).map(Ok).and_then(move |file_path| {
+            let csv_streams = stream::iter(sources).map(Ok).and_then(move |source| {
+                let (file_path, chunk) = source;
                 let ctx = ctx.clone();
                 let base_path = base_path.clone();
+                let csv_args = csv_args.clone();
+                let schema = schema.clone();
                 async move {
                     // Get the name of our stream.
                     let name = csv_stream_name(
-                        &base_path.to_string_lossy(),
-                        &file_path.to_string_lossy(),
+                        &to_slash_lossy(&base_path),
+                        &to_slash_lossy(&file_path),
                     )?
                     .to_owned();
+                    let name = match &chunk {
+                        Some((idx, _)) => format!("{}_{:04}", name, idx),
+                        None => name,
+                    };
                     let ctx = ctx.child(o!(
                         "stream" => name.clone(),
                         "path" => format!("{}", file_path.display())
@@ -194,20 +270,43 @@ async fn local_data_helper(
                     let data = fs::File::open(file_path.clone()).await.with_context(
                         |_| format!("cannot open {}", file_path.display()),
                     )?;
-                    let data = BufReader::with_capacity(BUFFER_SIZE, data);
-                    let stream = copy_reader_to_stream(ctx, data)?;
+                    let modified = data
+                        .metadata()
+                        .await
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                        .map(DateTime::<Utc>::from);
+                    let metadata = StreamMetadata {
+                        source: Some(file_path.display().to_string()),
+                        modified,
+                    };
+                    let file_name = file_path.to_string_lossy().into_owned();
+                    let delimiter = {
+                        let file_path = file_path.clone();
+                        spawn_blocking(move || guess_delimiter(&file_path)).await?
+                    };
+                    let stream = match chunk {
+                        Some((_, chunk)) => chunk.read_from(ctx.clone(), data).await?,
+                        None => {
+                            let data = BufReader::with_capacity(BUFFER_SIZE, data);
+                            copy_reader_to_stream(ctx.clone(), data)?.boxed()
+                        }
+                    };
+                    let stream = stream
+                        .map_err(move |e| {
+                            format_err!("cannot read {}: {}", file_path.display(), e)
+                        })
+                        .boxed();
+                    let stream =
+                        decompress_stream_for_file_name(&ctx, &file_name, stream)?;
+                    let stream = strip_utf8_bom(stream).await?;
 
+                    let data = csv_args
+                        .transform_data(&ctx, &schema, &name, delimiter, stream)?;
                     Ok(CsvStream {
                         name,
-                        data: stream
-                            .map_err(move |e| {
-                                format_err!(
-                                    "cannot read {}: {}",
-                                    file_path.display(),
-                                    e
-                                )
-                            })
-                            .boxed(),
+                        metadata,
+                        data,
                     })
                 }
                 .boxed()
@@ -218,52 +317,290 @@ async fn local_data_helper(
     }
 }
 
+/// A path to read, plus a chunk number and chunk if the file was split.
+type SplitPath = (PathBuf, Option<(usize, FileChunk)>);
+
+/// Split each of `paths` into chunks if `csv_args` asks us to. Returns each
+/// path, plus a chunk number and chunk if the file was split.
+fn split_csv_paths(
+    csv_args: &CsvSourceArguments,
+    paths: Vec<PathBuf>,
+) -> Result<Vec<SplitPath>> {
+    let chunk_count = csv_args.chunk_count()?;
+    if chunk_count == 1 {
+        return Ok(paths.into_iter().map(|path| (path, None)).collect());
+    }
+    let mut sources = vec![];
+    for path in paths {
+        if Compression::for_path(&path).is_some() {
+            return Err(format_err!(
+                "cannot split compressed file {} into chunks",
+                path.display()
+            ));
+        }
+        let chunks = find_file_chunks(&path, chunk_count, csv_args.has_header_row())?;
+        if chunks.len() == 1 {
+            sources.push((path, None));
+        } else {
+            for (idx, chunk) in chunks.into_iter().enumerate() {
+                sources.push((path.clone(), Some((idx + 1, chunk))));
+            }
+        }
+    }
+    Ok(sources)
+}
+
+/// Build a table named `name` from the CSV data in `rdr`, guessing column types
+/// from the first `infer_rows` rows. If `delimiter` is `Delimiter::COMMA`, we
+/// also look at the header line to see if another delimiter is used.
+fn infer_table(
+    name: String,
+    delimiter: Delimiter,
+    rdr: &mut dyn Read,
+    infer_rows: usize,
+) -> Result<Table> {
+    let mut start = vec![];
+    (&mut *rdr)
+        .take(4096)
+        .read_to_end(&mut start)
+        .context("cannot read CSV header")?;
+    // Don't let a byte-order mark become part of our first column name.
+    if start.starts_with(UTF8_BOM) {
+        start.drain(..UTF8_BOM.len());
+    }
+    let delimiter = match delimiter {
+        Delimiter::COMMA => Delimiter::sniff(&start),
+        delimiter => delimiter,
+    };
+    let mut csv_rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter.as_byte())
+        .flexible(true)
+        .from_reader(Read::chain(&start[..], rdr));
+    let columns = infer_columns(&mut csv_rdr, infer_rows)?;
+    Ok(Table { name, columns })
+}
+
+/// Guess the delimiter used by the local file at `path`, using its extension or
+/// its header line. We use this when reading both schemas and data, so that
+/// they always agree.
+fn guess_delimiter(path: &Path) -> Result<Delimiter> {
+    let delimiter = Delimiter::for_path(&Compression::strip_extension(path));
+    if delimiter != Delimiter::COMMA {
+        return Ok(delimiter);
+    }
+    let mut start = vec![];
+    open_sync(path)?
+        .take(4096)
+        .read_to_end(&mut start)
+        .with_context(|_| format!("cannot read CSV header from {}", path.display()))?;
+    Ok(Delimiter::sniff(&start))
+}
+
+/// Open the local file at `path` for synchronous reading, decompressing it if
+/// necessary.
+fn open_sync(path: &Path) -> Result<Box<dyn Read + Send>> {
+    let f = std::fs::File::open(long_path(path)?)
+        .with_context(|_| format!("error opening {}", path.display()))?;
+    match Compression::for_path(path) {
+        Some(compression) => compression.decoder(f),
+        None => Ok(Box::new(f)),
+    }
+}
+
+/// Recursively find all the CSV and TSV files at `path`, which may be a file, a
+/// directory, or a glob pattern like `data/**/*.csv`. Returns the directory or
+/// file that we searched, and the files we found. If `is_fixed_width` is true,
+/// we accept files with any extension, because fixed-width files are often
+/// named `*.txt` or `*.dat`.
+fn find_csv_paths(
+    ctx: &Context,
+    path: &Path,
+    is_fixed_width: bool,
+) -> Result<(PathBuf, Vec<PathBuf>)> {
+    // If we have a glob, we search the directory containing it, and skip any
+    // files which don't match.
+    let glob = GlobPath::parse(path)?;
+    let base_path = long_path(glob.as_ref().map(|g| g.base()).unwrap_or(path))?;
+
+    let is_match = |p: &Path| match (&glob, p.strip_prefix(&base_path)) {
+        (Some(glob), Ok(rel_path)) => glob.matches(rel_path),
+        (Some(_), Err(_)) => false,
+        (None, _) => true,
+    };
+
+    let mut paths = vec![];
+    debug!(ctx.log(), "walking {}", base_path.display());
+    let walker = WalkDir::new(&base_path).follow_links(true);
+    for dirent in walker.into_iter() {
+        let dirent = dirent.with_context(|_| {
+            format!("error listing files in {}", base_path.display())
+        })?;
+        let p = dirent.path();
+        trace!(ctx.log(), "found dirent {}", p.display());
+        if dirent.file_type().is_dir() {
+            continue;
+        } else if !is_match(p) {
+            trace!(ctx.log(), "skipping {}, which does not match", p.display());
+            continue;
+        } else if !dirent.file_type().is_file() {
+            return Err(format_err!("not a file: {}", p.display()));
+        }
+
+        // Look at the extension of the uncompressed file, so that we also
+        // accept names like `data.csv.gz`.
+        let uncompressed = Compression::strip_extension(p);
+        let ext = uncompressed.extension().and_then(OsStr::to_str);
+        if is_fixed_width
+            || matches!(ext, Some("csv") | Some("CSV") | Some("tsv") | Some("TSV"))
+        {
+            paths.push(p.to_owned());
+        } else {
+            return Err(format_err!(
+                "{} must end in *.csv, *.CSV, *.tsv or *.TSV, optionally followed \
+                 by a compression extension like *.gz",
+                p.display()
+            ));
+        }
+    }
+    Ok((base_path, paths))
+}
+
+/// Count and measure the CSV files we would read.
+async fn size_hint_helper(
+    ctx: Context,
+    path: PathOrStdio,
+    source_args: SourceArguments<Unverified>,
+) -> Result<SizeHint> {
+    let source_args = source_args.verify(CsvLocator::features())?;
+    let csv_args = source_args
+        .driver_args()
+        .deserialize::<CsvSourceArguments>()
+        .context("could not parse --from-arg")?;
+    match path {
+        // We can't know how much data is waiting on standard input.
+        PathOrStdio::Stdio => Ok(SizeHint::default()),
+        PathOrStdio::Path(path) => {
+            let (_base_path, paths) =
+                find_csv_paths(&ctx, &path, csv_args.is_fixed_width())?;
+            let chunk_count = csv_args.chunk_count()? as u64;
+            let mut sizes = vec![];
+            for path in paths {
+                let metadata = fs::metadata(&path)
+                    .await
+                    .with_context(|_| format!("cannot stat {}", path.display()))?;
+                // Estimate the size of each chunk if we'll split this file.
+                let len = metadata.len();
+                if chunk_count > 1 && Compression::for_path(&path).is_none() {
+                    for i in 0..chunk_count {
+                        sizes.push(
+                            (len * (i + 1) / chunk_count) - (len * i / chunk_count),
+                        );
+                    }
+                } else {
+                    sizes.push(len);
+                }
+            }
+            Ok(SizeHint::from_sizes(sizes))
+        }
+    }
+}
+
 async fn write_local_data_helper(
     ctx: Context,
     path: PathOrStdio,
-    data: BoxStream<CsvStream>,
+    mut data: BoxStream<CsvStream>,
     shared_args: SharedArguments<Unverified>,
     dest_args: DestinationArguments<Unverified>,
 ) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
     let _shared_args = shared_args.verify(CsvLocator::features())?;
     let dest_args = dest_args.verify(CsvLocator::features())?;
     let if_exists = dest_args.if_exists().to_owned();
+    let csv_args = dest_args
+        .driver_args()
+        .deserialize::<CsvDestinationArguments>()
+        .context("could not parse --to-arg")?;
+    if let Some(columns) = csv_args.columns().cloned() {
+        let ctx = ctx.clone();
+        data = data
+            .map(move |stream| columns.select_from_csv_stream(&ctx, stream?))
+            .boxed();
+    }
+    let chunk_limits = csv_args.chunk_limits()?;
+    let is_dir = match &path {
+        PathOrStdio::Stdio => false,
+        PathOrStdio::Path(path) => ends_with_separator(path),
+    };
+    if !is_dir && (csv_args.partition_by().is_some() || !chunk_limits.is_unlimited()) {
+        return Err(format_err!(
+            "partition_by, max_file_size and max_rows_per_file require a csv: \
+             directory ending in '/'"
+        ));
+    }
     match path {
         PathOrStdio::Stdio => {
             if_exists.warn_if_not_default_for_stdout(&ctx);
             let stream = concatenate_csv_streams(ctx.clone(), data)?;
+            let stream_data = csv_args.transform_data(&ctx, None, stream.data)?;
             let fut = async move {
-                copy_stream_to_writer(ctx.clone(), stream.data, io::stdout())
+                copy_stream_to_writer(ctx.clone(), stream_data, io::stdout())
                     .await
                     .context("error writing to stdout")?;
                 Ok(CsvLocator {
                     path: PathOrStdio::Stdio,
+                    infer_rows: None,
                 }
                 .boxed())
             };
             Ok(box_stream_once(Ok(fut.boxed())))
         }
         PathOrStdio::Path(path) => {
-            if path.to_string_lossy().ends_with('/') {
-                // Write streams to our directory as multiple files.
+            if ends_with_separator(&path) {
+                // Write streams to our directory as multiple files,
+                // partitioning them or splitting them into smaller files if
+                // we were asked to.
+                let data = match csv_args.partition_by() {
+                    Some(column) => {
+                        partition_csv_streams(
+                            ctx.clone(),
+                            column.to_owned(),
+                            chunk_limits,
+                            data,
+                        )
+                        .await?
+                    }
+                    None => split_csv_streams(ctx.clone(), chunk_limits, data),
+                };
                 let result_stream = data.map_ok(move |stream| {
                     let path = path.clone();
                     let ctx = ctx.clone();
                     let if_exists = if_exists.clone();
+                    let csv_args = csv_args.clone();
 
                     async move {
                         // TODO: This join does not handle `..` or nested `/` in
                         // a particularly safe fashion.
-                        let csv_path = path.join(&format!("{}.csv", stream.name));
+                        let mut file_name =
+                            csv_stream_file_name(&stream.name, &if_exists);
+                        if let Some(compression) = csv_args.compression() {
+                            file_name = compression.add_extension(&file_name);
+                        }
+                        let csv_path = path.join(&file_name);
                         let ctx = ctx.child(o!(
                             "stream" => stream.name.clone(),
                             "path" => format!("{}", csv_path.display()),
                         ));
+                        // When appending, we've chosen a new, unique file name,
+                        // so it's an error if it already exists.
+                        let file_if_exists = match if_exists {
+                            IfExists::Append => IfExists::Error,
+                            other => other,
+                        };
+                        let data = csv_args.transform_data(&ctx, None, stream.data)?;
                         write_stream_to_file(
                             ctx,
-                            stream.data,
+                            data,
                             csv_path.clone(),
-                            if_exists,
+                            file_if_exists,
                         )
                         .await?;
                         Ok(CsvLocator::from_path(csv_path).boxed())
@@ -271,6 +608,11 @@ async fn write_local_data_helper(
                     .boxed()
                 });
                 Ok(result_stream.boxed())
+            } else if if_exists == IfExists::Append {
+                Err(format_err!(
+                    "--if-exists=append is only supported for csv: directories \
+                     ending in '/'"
+                ))
             } else {
                 // Write all our streams as a single file.
                 let stream = concatenate_csv_streams(ctx.clone(), data)?;
@@ -279,8 +621,13 @@ async fn write_local_data_helper(
                         "stream" => stream.name.clone(),
                         "path" => format!("{}", path.display()),
                     ));
-                    write_stream_to_file(ctx, stream.data, path.clone(), if_exists)
-                        .await?;
+                    // Compress our output if our file name asks us to.
+                    let data = csv_args.transform_data(
+                        &ctx,
+                        Compression::for_path(&path),
+                        stream.data,
+                    )?;
+                    write_stream_to_file(ctx, data, path.clone(), if_exists).await?;
                     Ok(CsvLocator::from_path(path).boxed())
                 };
                 Ok(box_stream_once(Ok(fut.boxed())))
@@ -290,7 +637,7 @@ async fn write_local_data_helper(
 }
 
 /// Write `data` to `dest`, honoring `if_exists`.
-async fn write_stream_to_file(
+pub(crate) async fn write_stream_to_file(
     ctx: Context,
     data: BoxStream<BytesMut>,
     dest: PathBuf,
@@ -300,7 +647,7 @@ async fn write_stream_to_file(
     let dir = dest
         .parent()
         .ok_or_else(|| format_err!("cannot find parent dir for {}", dest.display()))?;
-    fs::create_dir_all(dir)
+    fs::create_dir_all(long_path(dir)?)
         .await
         .with_context(|_| format!("unable to create directory {}", dir.display()))?;
 
@@ -308,7 +655,7 @@ async fn write_stream_to_file(
     debug!(ctx.log(), "writing stream to file {}", dest.display());
     let wtr = if_exists
         .to_async_open_options_no_append()?
-        .open(dest.clone())
+        .open(long_path(&dest)?)
         .await
         .with_context(|_| format!("cannot open {}", dest.display()))?;
     copy_stream_to_writer(ctx.clone(), data, wtr)
@@ -328,9 +675,9 @@ impl LocatorStatic for CsvLocator {
                 | LocatorFeatures::LocalData
                 | LocatorFeatures::WriteLocalData,
             write_schema_if_exists: EnumSet::empty(),
-            source_args: EnumSet::empty(),
-            dest_args: EnumSet::empty(),
-            dest_if_exists: IfExistsFeatures::no_append(),
+            source_args: SourceArgumentsFeatures::DriverArgs.into(),
+            dest_args: DestinationArgumentsFeatures::DriverArgs.into(),
+            dest_if_exists: IfExistsFeatures::no_append() | IfExistsFeatures::Append,
             _placeholder: (),
         }
     }
@@ -0,0 +1,109 @@
+//! Custom `NULL` representations, like `\N` or `NULL`.
+
+use serde::Deserialize;
+
+use crate::common::*;
+
+/// One or more strings which represent `NULL` in an input file. Parsed from
+/// either `null_value=\N` or `null_value[]=\N`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum NullValues {
+    /// A single `NULL` value.
+    One(String),
+    /// Several `NULL` values.
+    Many(Vec<String>),
+}
+
+impl NullValues {
+    /// Is `cell` one of our `NULL` values?
+    fn contains(&self, cell: &[u8]) -> bool {
+        match self {
+            NullValues::One(value) => value.as_bytes() == cell,
+            NullValues::Many(values) => values.iter().any(|v| v.as_bytes() == cell),
+        }
+    }
+}
+
+/// Copy CSV data from `rdr` to `wtr`, replacing any cells matching
+/// `null_values` with empty cells, which represent `NULL` in our CSV
+/// interchange format. The header row is copied unchanged.
+pub(crate) fn read_null_values<R, W>(
+    null_values: &NullValues,
+    rdr: R,
+    wtr: W,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    replace_cells(rdr, wtr, |cell| null_values.contains(cell), b"")
+}
+
+/// Copy CSV data from `rdr` to `wtr`, replacing any empty cells with
+/// `null_value`. The header row is copied unchanged.
+pub(crate) fn write_null_values<R, W>(null_value: &str, rdr: R, wtr: W) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    replace_cells(rdr, wtr, |cell| cell.is_empty(), null_value.as_bytes())
+}
+
+/// Copy CSV data from `rdr` to `wtr`, replacing every cell for which
+/// `should_replace` returns true with `replacement`. The header row is copied
+/// unchanged.
+fn replace_cells<R, W, F>(
+    rdr: R,
+    wtr: W,
+    should_replace: F,
+    replacement: &[u8],
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+    F: Fn(&[u8]) -> bool,
+{
+    // Don't check row lengths here. That's somebody else's job.
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(rdr);
+    let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(wtr);
+    let mut row = csv::ByteRecord::new();
+    let mut out = csv::ByteRecord::new();
+    let mut is_header = true;
+    while rdr.read_byte_record(&mut row).context("cannot read row")? {
+        if is_header {
+            wtr.write_byte_record(&row)
+                .context("cannot write CSV header")?;
+            is_header = false;
+            continue;
+        }
+        out.clear();
+        for cell in row.iter() {
+            if should_replace(cell) {
+                out.push_field(replacement);
+            } else {
+                out.push_field(cell);
+            }
+        }
+        wtr.write_byte_record(&out).context("cannot write row")?;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn read_and_write_null_values() {
+    let null_values = NullValues::Many(vec!["\\N".to_owned(), "NULL".to_owned()]);
+    let input = "a,NULL\n1,\\N\nNULL,x\n";
+    let mut out = vec![];
+    read_null_values(&null_values, input.as_bytes(), &mut out).unwrap();
+    let csv = String::from_utf8(out).unwrap();
+    assert_eq!(csv, "a,NULL\n1,\n,x\n");
+
+    let mut out = vec![];
+    write_null_values("\\N", csv.as_bytes(), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "a,NULL\n1,\\N\n\\N,x\n");
+}
@@ -0,0 +1,81 @@
+//! Reading standard input twice: once to get our schema, and again to get our
+//! data.
+
+use lazy_static::lazy_static;
+use std::{io as sync_io, mem, sync::Mutex};
+use tokio::io::{self, BufReader};
+
+use crate::common::*;
+use crate::tokio_glue::copy_reader_to_stream;
+
+lazy_static! {
+    /// Any data which we've already read from standard input while looking at
+    /// our schema, and which we still need to return from `stdin_stream`.
+    static ref STDIN_PREFIX: Mutex<Vec<u8>> = Mutex::new(vec![]);
+}
+
+/// A reader which keeps a copy of all the data it reads.
+struct RecordingReader<R> {
+    inner: R,
+    recorded: Vec<u8>,
+}
+
+impl<R: Read> Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> sync_io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.recorded.extend_from_slice(&buf[..count]);
+        Ok(count)
+    }
+}
+
+/// Call `f` with a synchronous reader for standard input. All data read by
+/// `f` is saved in memory, and will be returned again by `stdin_stream`.
+///
+/// This blocks, so it should be called from `spawn_blocking`.
+pub(crate) fn peek_stdin<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce(&mut dyn Read) -> Result<T>,
+{
+    let mut prefix = STDIN_PREFIX.lock().expect("lock poisoned");
+    let stdin = sync_io::stdin();
+    let mut recorder = RecordingReader {
+        inner: stdin.lock(),
+        recorded: vec![],
+    };
+    // If somebody already peeked at stdin, replay what they read first. We
+    // call `Read::chain` explicitly, because `tokio` also implements
+    // `AsyncRead` for `&[u8]`.
+    let result = f(&mut Read::chain(&prefix[..], &mut recorder));
+    prefix.extend_from_slice(&recorder.recorded);
+    result
+}
+
+/// Return a stream containing all the data on standard input, including any
+/// data already read by `peek_stdin`.
+pub(crate) fn stdin_stream(ctx: &Context) -> Result<BoxStream<BytesMut>> {
+    let prefix = mem::take(&mut *STDIN_PREFIX.lock().expect("lock poisoned"));
+    // `tokio::io::stdin` reads from the same buffered `std::io::stdin` as
+    // `peek_stdin`, so it will pick up exactly where we left off.
+    let data = BufReader::with_capacity(BUFFER_SIZE, io::stdin());
+    let stream = copy_reader_to_stream(ctx.clone(), data)?
+        .map_err(move |e| format_err!("cannot read stdin: {}", e));
+    if prefix.is_empty() {
+        Ok(stream.boxed())
+    } else {
+        Ok(box_stream_once(Ok(BytesMut::from(&prefix[..])))
+            .chain(stream)
+            .boxed())
+    }
+}
+
+#[test]
+fn recording_reader_records_data() {
+    let mut rdr = RecordingReader {
+        inner: &b"a,b\n1,2\n"[..],
+        recorded: vec![],
+    };
+    let mut line = [0; 4];
+    rdr.read_exact(&mut line).unwrap();
+    assert_eq!(&line, b"a,b\n");
+    assert_eq!(rdr.recorded, b"a,b\n");
+}
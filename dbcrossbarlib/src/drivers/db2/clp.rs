@@ -0,0 +1,303 @@
+//! Running Db2 commands using the `db2` command line processor (CLP).
+//!
+//! The CLP keeps its database connection in a background process associated
+//! with the shell that started it, so we run each operation as a single `sh`
+//! script which connects, does its work and disconnects. We pass SQL and
+//! credentials using environment variables, so we never need to quote them for
+//! the shell.
+
+use futures::future;
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+use tokio::{io::BufReader, process::Command};
+
+use super::{data_type::parse_db2_type, quote_string, Db2Locator};
+use crate::common::*;
+use crate::process::wait_for_process;
+use crate::schema::Column;
+use crate::tokio_glue::{copy_reader_to_stream, copy_stream_to_writer};
+
+/// Shell code which we run before each script.
+///
+/// `run MAX_RC ARGS...` runs `db2 ARGS...`, and exits if it returns `MAX_RC`
+/// or higher. The CLP returns 1 for "no rows", 2 for warnings and 4 or more for
+/// errors. It prints errors on standard output, so we copy them to standard
+/// error when a command fails.
+const PRELUDE: &str = r#"
+out=$(mktemp)
+fifo="$DBCROSSBAR_DB2_FIFO"
+copier=
+cleanup() {
+    if [ -n "$copier" ]; then kill "$copier" 2>/dev/null; fi
+    db2 terminate > /dev/null 2>&1
+    rm -f "$out"
+    if [ -n "$fifo" ]; then rm -f "$fifo"; fi
+}
+trap cleanup EXIT
+run() {
+    max_rc=$1
+    shift
+    db2 "$@" > "$out"
+    rc=$?
+    if [ "$rc" -ge "$max_rc" ]; then
+        cat "$out" >&2
+        exit "$rc"
+    fi
+    cat "$out"
+}
+"#;
+
+/// Run a query, printing rows without headers.
+const QUERY_SCRIPT: &str = r#"run 4 -x "$DBCROSSBAR_DB2_SQL"
+"#;
+
+/// Run an `EXPORT` command which writes to `$fifo`, and copy `$fifo` to
+/// standard output. We start reading before we export, because opening a FIFO
+/// blocks until both ends are open.
+const EXPORT_SCRIPT: &str = r#"mkfifo "$fifo" || exit 1
+cat "$fifo" &
+copier=$!
+run 4 "$DBCROSSBAR_DB2_SQL" > /dev/null
+wait "$copier"
+copier=
+"#;
+
+/// Copy standard input to `$fifo`, and run a `LOAD` or `INGEST` command which
+/// reads from it. We treat warnings as errors, because `LOAD` reports rejected
+/// rows as a warning. Background jobs get `/dev/null` as standard input, so we
+/// need to save ours first.
+const IMPORT_SCRIPT: &str = r#"mkfifo "$fifo" || exit 1
+exec 3<&0
+cat <&3 > "$fifo" &
+copier=$!
+run 2 "$DBCROSSBAR_DB2_SQL" > /dev/null
+wait "$copier"
+copier=
+"#;
+
+/// Choose a path for a FIFO that we can use to pass data to or from the CLP.
+pub(crate) fn fifo_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "dbcrossbar-db2-{}.del",
+        TemporaryStorage::random_tag()
+    ))
+}
+
+/// A client which runs Db2 commands using the `db2` CLP.
+pub(crate) struct Clp {
+    /// The alias of our database in the local Db2 catalog.
+    database: String,
+    /// The user to connect as.
+    user: Option<String>,
+    /// The password to connect with.
+    password: Option<String>,
+}
+
+impl Clp {
+    /// Create a new client for the catalogued database `database`.
+    pub(crate) fn new(database: &str, user: &str, password: Option<&str>) -> Self {
+        Clp {
+            database: database.to_owned(),
+            user: if user.is_empty() {
+                None
+            } else {
+                Some(user.to_owned())
+            },
+            password: password.map(|p| p.to_owned()),
+        }
+    }
+
+    /// Build a complete shell script which connects to our database and then
+    /// runs `body`.
+    fn script(&self, body: &str) -> String {
+        let mut connect = r#"run 4 connect to "$DBCROSSBAR_DB2_DATABASE""#.to_owned();
+        if self.user.is_some() {
+            connect.push_str(r#" user "$DBCROSSBAR_DB2_USER""#);
+        }
+        if self.password.is_some() {
+            connect.push_str(r#" using "$DBCROSSBAR_DB2_PASSWORD""#);
+        }
+        format!("{}{} > /dev/null\n{}", PRELUDE, connect, body)
+    }
+
+    /// Build a command which runs `body` with `sql` and `fifo` available to
+    /// it.
+    fn command(&self, body: &str, sql: &str, fifo: Option<&Path>) -> Command {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(self.script(body))
+            .env("DBCROSSBAR_DB2_DATABASE", &self.database)
+            .env("DBCROSSBAR_DB2_SQL", sql)
+            .env("DBCROSSBAR_DB2_FIFO", fifo.unwrap_or_else(|| Path::new("")));
+        if let Some(user) = &self.user {
+            command.env("DBCROSSBAR_DB2_USER", user);
+        }
+        if let Some(password) = &self.password {
+            command.env("DBCROSSBAR_DB2_PASSWORD", password);
+        }
+        command
+    }
+
+    /// Run `sql` and return the rows of output, without trailing padding.
+    pub(crate) async fn query(&self, ctx: &Context, sql: &str) -> Result<Vec<String>> {
+        debug!(ctx.log(), "running Db2 SQL: {}", sql);
+        let output = self
+            .command(QUERY_SCRIPT, sql, None)
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .context("error running `db2` script")?;
+        if !output.status.success() {
+            return Err(format_err!(
+                "`db2` failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            ));
+        }
+        let stdout = String::from_utf8(output.stdout)
+            .context("`db2` output was not valid UTF-8")?;
+        Ok(stdout
+            .lines()
+            .map(|line| line.trim_end())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_owned())
+            .collect())
+    }
+
+    /// Run `sql`, ignoring any output.
+    pub(crate) async fn execute(&self, ctx: &Context, sql: &str) -> Result<()> {
+        self.query(ctx, sql).await?;
+        Ok(())
+    }
+
+    /// Look up the columns of the table at `locator`. Returns an empty list if
+    /// the table does not exist.
+    pub(crate) async fn table_columns(
+        &self,
+        ctx: &Context,
+        locator: &Db2Locator,
+    ) -> Result<Vec<Column>> {
+        let sql = format!(
+            "SELECT COLNAME || CHR(9) || TYPENAME || CHR(9) || NULLS \
+             FROM SYSCAT.COLUMNS \
+             WHERE TABSCHEMA = {} AND TABNAME = {} \
+             ORDER BY COLNO",
+            quote_string(&locator.table_schema),
+            quote_string(&locator.table),
+        );
+        let rows = self.query(ctx, &sql).await?;
+        Ok(parse_columns(&rows)
+            .with_context(|_| format!("could not get columns of {}", locator))?)
+    }
+
+    /// Run `export_sql`, which should be an `EXPORT` command writing to
+    /// `fifo`, and return the exported data as a stream.
+    pub(crate) fn export(
+        &self,
+        ctx: &Context,
+        export_sql: &str,
+        fifo: &Path,
+    ) -> Result<BoxStream<BytesMut>> {
+        debug!(ctx.log(), "exporting from Db2: {}", export_sql);
+        let child = self
+            .command(EXPORT_SCRIPT, export_sql, Some(fifo))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("error running `db2` export script")?;
+        let child_stdout =
+            ctx.spawn_process_with_stdout("db2 export".to_owned(), child);
+        let child_stdout = BufReader::with_capacity(BUFFER_SIZE, child_stdout);
+        let data = copy_reader_to_stream(ctx.clone(), child_stdout)?;
+        Ok(data.boxed())
+    }
+
+    /// Run `import_sql`, which should be a `LOAD` or `INGEST` command reading
+    /// from `fifo`, and feed it `data`.
+    pub(crate) async fn import(
+        &self,
+        ctx: &Context,
+        import_sql: &str,
+        fifo: &Path,
+        data: BoxStream<BytesMut>,
+    ) -> Result<()> {
+        debug!(ctx.log(), "importing into Db2: {}", import_sql);
+        let mut child = self
+            .command(IMPORT_SCRIPT, import_sql, Some(fifo))
+            .stdin(Stdio::piped())
+            // Throw away stdout so it doesn't corrupt our output.
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("error running `db2` import script")?;
+        let child_stdin = child.stdin.take().expect("child should have stdin");
+        let (copy_result, wait_result) = future::join(
+            copy_stream_to_writer(ctx.clone(), data, child_stdin),
+            wait_for_process(ctx, "db2 import", child, None),
+        )
+        .await;
+        // If `db2` failed, that's probably why our copy failed, so report it
+        // first.
+        wait_result?;
+        copy_result.context("error copying data to `db2`")?;
+        Ok(())
+    }
+}
+
+#[test]
+fn script_connects_with_credentials() {
+    let clp = Clp::new("sample", "user", Some("pass"));
+    let script = clp.script(QUERY_SCRIPT);
+    assert!(script.contains(
+        r#"run 4 connect to "$DBCROSSBAR_DB2_DATABASE" user "$DBCROSSBAR_DB2_USER" using "$DBCROSSBAR_DB2_PASSWORD" > /dev/null"#
+    ));
+    assert!(script.ends_with(QUERY_SCRIPT));
+
+    let clp = Clp::new("sample", "", None);
+    assert!(clp
+        .script(QUERY_SCRIPT)
+        .contains("run 4 connect to \"$DBCROSSBAR_DB2_DATABASE\" > /dev/null"));
+}
+
+/// Parse our query against `SYSCAT.COLUMNS`, which returns tab-separated
+/// column names, type names and nullability.
+fn parse_columns(rows: &[String]) -> Result<Vec<Column>> {
+    let mut columns = vec![];
+    for row in rows {
+        let fields = row.split('\t').collect::<Vec<_>>();
+        match &fields[..] {
+            [name, type_name, nulls] => columns.push(Column {
+                name: (*name).to_owned(),
+                is_nullable: *nulls != "N",
+                data_type: parse_db2_type(type_name).with_context(|_| {
+                    format!("could not parse type of column {:?}", name)
+                })?,
+                comment: None,
+            }),
+            _ => return Err(format_err!("could not parse column info {:?}", row)),
+        }
+    }
+    Ok(columns)
+}
+
+#[test]
+fn parse_columns_from_syscat() {
+    use crate::schema::DataType;
+
+    let rows = vec![
+        "EMPNO\tCHARACTER\tN".to_owned(),
+        "SALARY\tDECIMAL\tY".to_owned(),
+    ];
+    let columns = parse_columns(&rows).unwrap();
+    assert_eq!(columns.len(), 2);
+    assert_eq!(columns[0].name, "EMPNO");
+    assert!(!columns[0].is_nullable);
+    assert_eq!(columns[0].data_type, DataType::Text);
+    assert!(columns[1].is_nullable);
+    assert_eq!(columns[1].data_type, DataType::Decimal);
+    assert!(parse_columns(&["bad".to_owned()]).is_err());
+}
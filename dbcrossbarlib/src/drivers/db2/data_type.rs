@@ -0,0 +1,86 @@
+//! Converting between Db2 and portable data types.
+
+use crate::common::*;
+use crate::schema::DataType;
+
+/// Parse a Db2 type name, as found in `SYSCAT.COLUMNS.TYPENAME`.
+pub(crate) fn parse_db2_type(type_name: &str) -> Result<DataType> {
+    match type_name.trim() {
+        "BOOLEAN" => Ok(DataType::Bool),
+        "DATE" => Ok(DataType::Date),
+        "DECFLOAT" | "DECIMAL" | "NUMERIC" => Ok(DataType::Decimal),
+        "REAL" => Ok(DataType::Float32),
+        "DOUBLE" | "FLOAT" => Ok(DataType::Float64),
+        "SMALLINT" => Ok(DataType::Int16),
+        "INTEGER" => Ok(DataType::Int32),
+        "BIGINT" => Ok(DataType::Int64),
+        "TIMESTAMP" => Ok(DataType::TimestampWithoutTimeZone),
+        // We don't have a portable `TIME` type, so we treat it as text.
+        "CHARACTER" | "CLOB" | "DBCLOB" | "GRAPHIC" | "LONG VARCHAR"
+        | "LONG VARGRAPHIC" | "TIME" | "VARCHAR" | "VARGRAPHIC" | "XML" => {
+            Ok(DataType::Text)
+        }
+        other => Err(format_err!("cannot convert Db2 type {:?}", other)),
+    }
+}
+
+/// Choose the Db2 type to use when creating a column of type `data_type`.
+///
+/// Arrays, structs and other complex types are stored as JSON strings, which
+/// is how they're represented in our CSV interchange format.
+pub(crate) fn db2_column_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Bool => "BOOLEAN".to_owned(),
+        DataType::Date => "DATE".to_owned(),
+        // Db2 decimals have at most 31 digits, so fall back to `DECFLOAT` for
+        // anything larger.
+        DataType::FixedDecimal { precision, scale } if *precision <= 31 => {
+            format!("DECIMAL({}, {})", precision, scale)
+        }
+        DataType::Decimal | DataType::FixedDecimal { .. } => "DECFLOAT(34)".to_owned(),
+        DataType::Float32 => "REAL".to_owned(),
+        DataType::Float64 => "DOUBLE".to_owned(),
+        DataType::Int16 => "SMALLINT".to_owned(),
+        DataType::Int32 => "INTEGER".to_owned(),
+        DataType::Int64 => "BIGINT".to_owned(),
+        // Db2 has no time zone support, so we store timestamps in UTC.
+        DataType::TimestampWithoutTimeZone | DataType::TimestampWithTimeZone => {
+            "TIMESTAMP(6)".to_owned()
+        }
+        DataType::Text => "VARCHAR(32672)".to_owned(),
+        DataType::Uuid => "CHAR(36)".to_owned(),
+        DataType::Array(_)
+        | DataType::GeoJson(_)
+        | DataType::Json
+        | DataType::Struct(_) => "CLOB(1G)".to_owned(),
+    }
+}
+
+/// Is `data_type` a number? Db2 may export these with a leading `+`.
+pub(crate) fn is_numeric(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Decimal
+        | DataType::FixedDecimal { .. }
+        | DataType::Float32
+        | DataType::Float64
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64 => true,
+        _ => false,
+    }
+}
+
+#[test]
+fn parse_db2_types() {
+    let examples = &[
+        ("BIGINT", DataType::Int64),
+        ("DECIMAL", DataType::Decimal),
+        ("VARCHAR", DataType::Text),
+        ("TIMESTAMP", DataType::TimestampWithoutTimeZone),
+        ("BOOLEAN", DataType::Bool),
+    ];
+    for (type_name, expected) in examples {
+        assert_eq!(&parse_db2_type(type_name).unwrap(), expected);
+    }
+    assert!(parse_db2_type("BLOB").is_err());
+}
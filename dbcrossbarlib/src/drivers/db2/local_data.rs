@@ -0,0 +1,145 @@
+//! Reading data from Db2 tables.
+
+use std::path::Path;
+
+use super::{clp::fifo_path, data_type::is_numeric, quote_identifier, Db2Locator};
+use crate::common::*;
+use crate::transform::spawn_sync_transform;
+
+/// Implementation of `local_data`, but as a real `async` function.
+pub(crate) async fn local_data_helper(
+    ctx: Context,
+    source: Db2Locator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Option<BoxStream<CsvStream>>> {
+    let shared_args = shared_args.verify(Db2Locator::features())?;
+    let source_args = source_args.verify(Db2Locator::features())?;
+    let schema = shared_args.schema().to_owned();
+
+    // Export our table in Db2's DEL format, and convert it to CSV.
+    let fifo = fifo_path();
+    let sql = export_sql(&source, &schema, source_args.where_clause(), &fifo);
+    let del = source.clp().export(&ctx, &sql, &fifo)?;
+    let data = spawn_sync_transform(
+        ctx,
+        "db2-del-to-csv".to_owned(),
+        del,
+        move |_ctx, rdr, wtr| copy_del_to_csv(&schema, rdr, wtr),
+    )?;
+    Ok(Some(box_stream_once(Ok(CsvStream {
+        name: source.table.clone(),
+        metadata: StreamMetadata::default(),
+        data,
+    }))))
+}
+
+/// Generate an `EXPORT` command which writes the columns in `schema` to
+/// `fifo`, using ISO dates and timestamps.
+fn export_sql(
+    source: &Db2Locator,
+    schema: &Table,
+    where_clause: Option<&str>,
+    fifo: &Path,
+) -> String {
+    let columns = schema
+        .columns
+        .iter()
+        .map(|c| quote_identifier(&c.name))
+        .collect::<Vec<_>>();
+    let mut sql = format!(
+        "EXPORT TO {} OF DEL \
+         MODIFIED BY codepage=1208 datesiso striplzeros \
+         timestampformat=\"YYYY-MM-DD HH:MM:SS.UUUUUU\" \
+         SELECT {} FROM {}",
+        fifo.display(),
+        columns.join(", "),
+        source.quoted_table_name(),
+    );
+    if let Some(where_clause) = where_clause {
+        sql.push_str(&format!(" WHERE {}", where_clause));
+    }
+    sql
+}
+
+#[test]
+fn export_sql_selects_columns() {
+    use crate::schema::{Column, DataType};
+
+    let source = "db2://sample/DB2INST1.T".parse::<Db2Locator>().unwrap();
+    let schema = Table {
+        name: "T".to_owned(),
+        columns: vec![Column {
+            name: "ID".to_owned(),
+            is_nullable: false,
+            data_type: DataType::Int32,
+            comment: None,
+        }],
+    };
+    let sql = export_sql(&source, &schema, Some("ID > 1"), Path::new("/tmp/x.del"));
+    assert_eq!(
+        sql,
+        "EXPORT TO /tmp/x.del OF DEL MODIFIED BY codepage=1208 datesiso \
+         striplzeros timestampformat=\"YYYY-MM-DD HH:MM:SS.UUUUUU\" \
+         SELECT \"ID\" FROM \"DB2INST1\".\"T\" WHERE ID > 1",
+    );
+}
+
+/// Convert Db2's DEL format to CSV. DEL files are basically CSV without
+/// headers, except that numbers may have a leading `+`.
+fn copy_del_to_csv<R: Read, W: Write>(schema: &Table, rdr: R, wtr: W) -> Result<()> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+    wtr.write_record(schema.columns.iter().map(|c| &c.name))?;
+    for row in rdr.records() {
+        let row = row.context("error reading Db2 DEL data")?;
+        if row.len() != schema.columns.len() {
+            return Err(format_err!(
+                "expected {} columns from Db2, found {}",
+                schema.columns.len(),
+                row.len(),
+            ));
+        }
+        wtr.write_record(row.iter().zip(&schema.columns).map(|(cell, col)| {
+            if is_numeric(&col.data_type) {
+                cell.trim_start_matches('+')
+            } else {
+                cell
+            }
+        }))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[test]
+fn copy_del_to_csv_adds_headers() {
+    use crate::schema::{Column, DataType};
+
+    let schema = Table {
+        name: "T".to_owned(),
+        columns: vec![
+            Column {
+                name: "PRICE".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Decimal,
+                comment: None,
+            },
+            Column {
+                name: "NAME".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Text,
+                comment: None,
+            },
+        ],
+    };
+    let input = "+12.50,\"a \"\"b\"\"\"\n,\"+1\"\n";
+    let mut output = vec![];
+    copy_del_to_csv(&schema, input.as_bytes(), &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "PRICE,NAME\n12.50,\"a \"\"b\"\"\"\n,+1\n",
+    );
+}
@@ -0,0 +1,292 @@
+//! Writing data to Db2 tables.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Deserialize;
+use std::{borrow::Cow, path::Path};
+
+use super::{
+    clp::fifo_path, data_type::db2_column_type, quote_identifier, Db2Locator,
+};
+use crate::common::*;
+use crate::concat::concatenate_csv_streams;
+use crate::from_csv_cell::FromCsvCell;
+use crate::schema::DataType;
+use crate::transform::spawn_sync_transform;
+
+/// The timestamp format we use in DEL files.
+const DEL_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.6f";
+
+/// How should we import data into Db2?
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ImportMethod {
+    /// Use `LOAD`, which is fast, but which locks the table and bypasses
+    /// triggers.
+    Load,
+    /// Use `INGEST`, which uses ordinary SQL inserts and leaves the table
+    /// available to other users.
+    Ingest,
+}
+
+impl Default for ImportMethod {
+    fn default() -> Self {
+        ImportMethod::Load
+    }
+}
+
+/// Db2-specific `--to-arg` values.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Db2DestinationArguments {
+    /// How to import our data.
+    #[serde(default)]
+    method: ImportMethod,
+}
+
+/// Implementation of `write_local_data`, but as a real `async` function.
+pub(crate) async fn write_local_data_helper(
+    ctx: Context,
+    dest: Db2Locator,
+    data: BoxStream<CsvStream>,
+    shared_args: SharedArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
+    let shared_args = shared_args.verify(Db2Locator::features())?;
+    let dest_args = dest_args.verify(Db2Locator::features())?;
+    let if_exists = dest_args.if_exists().to_owned();
+    let db2_args = dest_args
+        .driver_args()
+        .deserialize::<Db2DestinationArguments>()
+        .context("could not parse --to-arg")?;
+    let schema = shared_args.schema().to_owned();
+    let clp = dest.clp();
+
+    // Find or create our table. We don't pass a column list to `LOAD` or
+    // `INGEST`, so the columns of an existing table must match our schema.
+    let existing_columns = clp.table_columns(&ctx, &dest).await?;
+    if existing_columns.is_empty() {
+        clp.execute(&ctx, &create_table_sql(&dest, &schema)?)
+            .await?;
+    } else if if_exists == IfExists::Error {
+        return Err(format_err!("table {} already exists", dest));
+    } else {
+        let existing_names = existing_columns.iter().map(|c| &c.name);
+        let names = schema.columns.iter().map(|c| &c.name);
+        if !existing_names.eq(names) {
+            return Err(format_err!(
+                "the columns of {} do not match the schema",
+                dest
+            ));
+        }
+    }
+
+    // Convert our data to DEL format.
+    let stream = concatenate_csv_streams(ctx.clone(), data)?;
+    let del = spawn_sync_transform(
+        ctx.clone(),
+        "csv-to-db2-del".to_owned(),
+        stream.data,
+        move |_ctx, rdr, wtr| copy_csv_to_del(&schema, rdr, wtr),
+    )?;
+
+    let fut = async move {
+        let fifo = fifo_path();
+        let sql = import_sql(&dest, db2_args.method, &if_exists, &fifo);
+        clp.import(&ctx, &sql, &fifo, del)
+            .await
+            .with_context(|_| format!("error importing data into {}", dest))?;
+        Ok(dest.boxed())
+    };
+    Ok(box_stream_once(Ok(fut.boxed())))
+}
+
+/// Generate SQL to create our table.
+fn create_table_sql(dest: &Db2Locator, schema: &Table) -> Result<String> {
+    if schema.columns.is_empty() {
+        return Err(format_err!("cannot create {} with no columns", dest));
+    }
+    let columns = schema
+        .columns
+        .iter()
+        .map(|column| {
+            let mut column_sql = format!(
+                "{} {}",
+                quote_identifier(&column.name),
+                db2_column_type(&column.data_type),
+            );
+            if !column.is_nullable {
+                column_sql.push_str(" NOT NULL");
+            }
+            column_sql
+        })
+        .collect::<Vec<_>>();
+    Ok(format!(
+        "CREATE TABLE {} ({})",
+        dest.quoted_table_name(),
+        columns.join(", "),
+    ))
+}
+
+/// Generate a `LOAD` or `INGEST` command which reads DEL data from `fifo`.
+fn import_sql(
+    dest: &Db2Locator,
+    method: ImportMethod,
+    if_exists: &IfExists,
+    fifo: &Path,
+) -> String {
+    let action = match if_exists {
+        IfExists::Overwrite => "REPLACE",
+        _ => "INSERT",
+    };
+    match method {
+        // `CLIENT` is ignored when the database is local, and `delprioritychar`
+        // allows newlines in quoted strings.
+        ImportMethod::Load => format!(
+            "LOAD CLIENT FROM {} OF DEL \
+             MODIFIED BY codepage=1208 delprioritychar dateformat=\"YYYY-MM-DD\" \
+             timestampformat=\"YYYY-MM-DD HH:MM:SS.UUUUUU\" \
+             {} INTO {} NONRECOVERABLE",
+            fifo.display(),
+            action,
+            dest.quoted_table_name(),
+        ),
+        // `RESTART OFF` means we don't need a restart table.
+        ImportMethod::Ingest => format!(
+            "INGEST FROM PIPE {} FORMAT DELIMITED INPUT CODEPAGE 1208 \
+             RESTART OFF {} INTO {}",
+            fifo.display(),
+            action,
+            dest.quoted_table_name(),
+        ),
+    }
+}
+
+#[test]
+fn create_and_import_sql() {
+    use crate::schema::Column;
+
+    let dest = "db2://sample/DB2INST1.T".parse::<Db2Locator>().unwrap();
+    let schema = Table {
+        name: "T".to_owned(),
+        columns: vec![
+            Column {
+                name: "ID".to_owned(),
+                is_nullable: false,
+                data_type: DataType::Int64,
+                comment: None,
+            },
+            Column {
+                name: "TAGS".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Array(Box::new(DataType::Text)),
+                comment: None,
+            },
+        ],
+    };
+    assert_eq!(
+        create_table_sql(&dest, &schema).unwrap(),
+        "CREATE TABLE \"DB2INST1\".\"T\" (\"ID\" BIGINT NOT NULL, \"TAGS\" CLOB(1G))",
+    );
+
+    let fifo = Path::new("/tmp/x.del");
+    let load = import_sql(&dest, ImportMethod::Load, &IfExists::Overwrite, fifo);
+    assert!(load.starts_with("LOAD CLIENT FROM /tmp/x.del OF DEL "));
+    assert!(load.ends_with(" REPLACE INTO \"DB2INST1\".\"T\" NONRECOVERABLE"));
+    assert_eq!(
+        import_sql(&dest, ImportMethod::Ingest, &IfExists::Append, fifo),
+        "INGEST FROM PIPE /tmp/x.del FORMAT DELIMITED INPUT CODEPAGE 1208 \
+         RESTART OFF INSERT INTO \"DB2INST1\".\"T\"",
+    );
+}
+
+/// Convert CSV data to Db2's DEL format, which is CSV without headers.
+fn copy_csv_to_del<R: Read, W: Write>(schema: &Table, rdr: R, wtr: W) -> Result<()> {
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+
+    // Make sure our CSV columns match our schema.
+    let headers = rdr.headers()?;
+    let names = schema.columns.iter().map(|c| &c.name[..]);
+    if !headers.iter().eq(names) {
+        return Err(format_err!(
+            "CSV columns {:?} do not match schema for {}",
+            headers,
+            schema.name,
+        ));
+    }
+
+    for row in rdr.records() {
+        let row = row?;
+        let mut cells = Vec::with_capacity(schema.columns.len());
+        for (cell, col) in row.iter().zip(&schema.columns) {
+            cells.push(csv_cell_to_del(cell, &col.data_type).with_context(|_| {
+                format!("error converting column {:?}", col.name)
+            })?);
+        }
+        wtr.write_record(cells.iter().map(|c| c.as_bytes()))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Convert a cell in our CSV interchange format to a value that Db2 can load.
+fn csv_cell_to_del<'a>(cell: &'a str, data_type: &DataType) -> Result<Cow<'a, str>> {
+    if cell.is_empty() {
+        return Ok(Cow::Borrowed(cell));
+    }
+    match data_type {
+        DataType::Bool => {
+            let value = if bool::from_csv_cell(cell)? { "1" } else { "0" };
+            Ok(Cow::Borrowed(value))
+        }
+        DataType::TimestampWithoutTimeZone => {
+            let timestamp = NaiveDateTime::from_csv_cell(cell)?;
+            Ok(Cow::Owned(
+                timestamp.format(DEL_TIMESTAMP_FORMAT).to_string(),
+            ))
+        }
+        DataType::TimestampWithTimeZone => {
+            let timestamp = DateTime::<Utc>::from_csv_cell(cell)?;
+            Ok(Cow::Owned(
+                timestamp.format(DEL_TIMESTAMP_FORMAT).to_string(),
+            ))
+        }
+        _ => Ok(Cow::Borrowed(cell)),
+    }
+}
+
+#[test]
+fn copy_csv_to_del_converts_values() {
+    use crate::schema::Column;
+
+    let schema = Table {
+        name: "T".to_owned(),
+        columns: vec![
+            Column {
+                name: "ACTIVE".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Bool,
+                comment: None,
+            },
+            Column {
+                name: "SEEN_AT".to_owned(),
+                is_nullable: true,
+                data_type: DataType::TimestampWithTimeZone,
+                comment: None,
+            },
+            Column {
+                name: "NAME".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Text,
+                comment: None,
+            },
+        ],
+    };
+    let input = "ACTIVE,SEEN_AT,NAME\nt,2020-09-01T12:00:00+02:00,\"a,b\"\n,,\n";
+    let mut output = vec![];
+    copy_csv_to_del(&schema, input.as_bytes(), &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "1,2020-09-01 10:00:00.000000,\"a,b\"\n,,\n",
+    );
+}
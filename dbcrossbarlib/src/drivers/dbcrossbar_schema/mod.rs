@@ -1,8 +1,18 @@
 //! Support for `dbcrossbar-schema` locators.
 
+use serde_derive::Serialize;
+use serde_json::Value;
 use std::{fmt, str::FromStr};
 
 use crate::common::*;
+use crate::schema::DataType;
+
+/// The newest version of the schema format that we can read. This should be
+/// incremented whenever we make a change that older versions of `dbcrossbar`
+/// can't read.
+///
+/// - Version 2 added the `fixed_decimal` type.
+const SCHEMA_VERSION: u64 = 2;
 
 /// A JSON file containing a `dbcrossbar` native schema.
 #[derive(Clone, Debug)]
@@ -73,7 +83,7 @@ async fn schema_helper(
         .with_context(|_| format!("error reading {}", source.path))?;
 
     // Parse our input as table JSON.
-    let table: Table = serde_json::from_slice(&data)
+    let table = table_from_json(&data)
         .with_context(|_| format!("error parsing {}", source.path))?;
     Ok(Some(table))
 }
@@ -87,11 +97,142 @@ async fn write_schema_helper(
 ) -> Result<()> {
     // Generate our JSON.
     let mut f = dest.path.create_async(ctx, if_exists).await?;
+    let versioned = VersionedTable {
+        version: schema_version(&table),
+        table: &table,
+    };
     buffer_sync_write_and_copy_to_async(&mut f, |buff| {
-        serde_json::to_writer_pretty(buff, &table)
+        serde_json::to_writer_pretty(buff, &versioned)
     })
     .await
     .with_context(|_| format!("error writing to {}", dest.path))?;
     f.flush().await?;
     Ok(())
 }
+
+/// A `Table`, plus the version of the schema format.
+#[derive(Serialize)]
+struct VersionedTable<'a> {
+    version: u64,
+    #[serde(flatten)]
+    table: &'a Table,
+}
+
+/// The oldest schema version which can represent `table`. We only use newer
+/// versions when we need to, so that older versions of `dbcrossbar` can still
+/// read most schemas.
+fn schema_version(table: &Table) -> u64 {
+    fn data_type_version(data_type: &DataType) -> u64 {
+        match data_type {
+            DataType::Array(elem) => data_type_version(elem),
+            DataType::FixedDecimal { .. } => 2,
+            DataType::Struct(fields) => fields
+                .iter()
+                .map(|f| data_type_version(&f.data_type))
+                .max()
+                .unwrap_or(1),
+            _ => 1,
+        }
+    }
+    table
+        .columns
+        .iter()
+        .map(|c| data_type_version(&c.data_type))
+        .max()
+        .unwrap_or(1)
+}
+
+/// Parse a schema, checking that we support its version. Schemas without a
+/// version were written before we added versions, and are treated as version 1.
+fn table_from_json(data: &[u8]) -> Result<Table> {
+    let mut value: Value = serde_json::from_slice(data)?;
+    let version = value.as_object_mut().and_then(|obj| obj.remove("version"));
+    if let Some(version) = version {
+        match version.as_u64() {
+            Some(version) if (1..=SCHEMA_VERSION).contains(&version) => {}
+            Some(version) => {
+                return Err(format_err!(
+                    "schema version {} is not supported (this version of dbcrossbar supports versions up to {})",
+                    version,
+                    SCHEMA_VERSION,
+                ))
+            }
+            None => {
+                return Err(format_err!(
+                    "expected schema version to be a number, found {}",
+                    version
+                ))
+            }
+        }
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+#[test]
+fn versioned_schema_roundtrip() {
+    use crate::schema::{Column, DataType};
+
+    let table = Table {
+        name: "example".to_owned(),
+        columns: vec![Column {
+            name: "tags".to_owned(),
+            is_nullable: true,
+            data_type: DataType::Array(Box::new(DataType::Text)),
+            comment: Some("Free-form tags".to_owned()),
+        }],
+    };
+    let versioned = VersionedTable {
+        version: schema_version(&table),
+        table: &table,
+    };
+    let json = serde_json::to_vec(&versioned).unwrap();
+    assert_eq!(
+        serde_json::from_slice::<Value>(&json).unwrap()["version"],
+        1
+    );
+    assert_eq!(table_from_json(&json).unwrap(), table);
+
+    // Schemas without a version are still supported.
+    let unversioned = serde_json::to_vec(&table).unwrap();
+    assert_eq!(table_from_json(&unversioned).unwrap(), table);
+
+    // Schemas from the future are not.
+    let future = br#"{ "version": 3, "name": "example", "columns": [] }"#;
+    assert!(table_from_json(future).is_err());
+}
+
+#[test]
+fn fixed_decimal_requires_version_2() {
+    use crate::schema::{Column, StructField};
+
+    let fixed = DataType::FixedDecimal {
+        precision: 10,
+        scale: 2,
+    };
+    let table = |data_type| Table {
+        name: "example".to_owned(),
+        columns: vec![Column {
+            name: "amount".to_owned(),
+            is_nullable: true,
+            data_type,
+            comment: None,
+        }],
+    };
+    assert_eq!(schema_version(&table(DataType::Decimal)), 1);
+    assert_eq!(schema_version(&table(fixed.clone())), 2);
+    let nested = DataType::Array(Box::new(DataType::Struct(vec![StructField {
+        name: "amount".to_owned(),
+        is_nullable: true,
+        data_type: fixed.clone(),
+    }])));
+    assert_eq!(schema_version(&table(nested)), 2);
+
+    // We can read what we write.
+    let table = table(fixed);
+    let versioned = VersionedTable {
+        version: schema_version(&table),
+        table: &table,
+    };
+    let json = serde_json::to_vec(&versioned).unwrap();
+    assert_eq!(table_from_json(&json).unwrap(), table);
+}
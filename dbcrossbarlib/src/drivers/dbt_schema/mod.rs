@@ -0,0 +1,256 @@
+//! Schema-only driver for writing dbt `sources:` YAML files.
+
+use percent_encoding::percent_decode_str;
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+use crate::schema::DataType;
+
+/// A dbt YAML file declaring our table as a source, such as
+/// `dbt-schema:models/sources.yml?source=raw`.
+#[derive(Clone, Debug)]
+pub struct DbtSchemaLocator {
+    path: PathOrStdio,
+    /// The name of the dbt source. Defaults to the table's schema.
+    source: Option<String>,
+}
+
+impl fmt::Display for DbtSchemaLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encode = |s: &str| s.replace('%', "%25").replace('&', "%26");
+        self.path.fmt_locator_helper(Self::scheme(), f)?;
+        if let Some(source) = &self.source {
+            write!(f, "?source={}", encode(source))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DbtSchemaLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (path, query) = match s.find('?') {
+            Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+            None => (s, None),
+        };
+        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), path)?;
+        let mut source = None;
+        for param in query.into_iter().flat_map(|q| q.split('&')) {
+            let mut parts = param.splitn(2, '=');
+            let key = parts.next().expect("split always returns one value");
+            let value = percent_decode_str(parts.next().unwrap_or(""))
+                .decode_utf8()
+                .with_context(|_| format!("cannot decode {:?}", param))?;
+            match key {
+                "source" if !value.is_empty() => source = Some(value.into_owned()),
+                _ => {
+                    return Err(format_err!(
+                        "unknown option {:?} in {:?} (expected source)",
+                        param,
+                        s
+                    ))
+                }
+            }
+        }
+        Ok(DbtSchemaLocator { path, source })
+    }
+}
+
+#[test]
+fn parse_and_display() {
+    let loc = "dbt-schema:models/sources.yml?source=raw%26more"
+        .parse::<DbtSchemaLocator>()
+        .unwrap();
+    assert_eq!(loc.source.as_deref(), Some("raw&more"));
+    assert_eq!(
+        loc.to_string(),
+        "dbt-schema:models/sources.yml?source=raw%26more"
+    );
+
+    let loc = "dbt-schema:-".parse::<DbtSchemaLocator>().unwrap();
+    assert_eq!(loc.source, None);
+
+    for bad in &["dbt-schema:s.yml?source=", "dbt-schema:s.yml?model=x"] {
+        assert!(bad.parse::<DbtSchemaLocator>().is_err());
+    }
+}
+
+impl Locator for DbtSchemaLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn write_schema(
+        &self,
+        ctx: Context,
+        table: Table,
+        if_exists: IfExists,
+    ) -> BoxFuture<()> {
+        write_schema_helper(ctx, self.to_owned(), table, if_exists).boxed()
+    }
+}
+
+impl LocatorStatic for DbtSchemaLocator {
+    fn scheme() -> &'static str {
+        "dbt-schema:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::WriteSchema.into(),
+            write_schema_if_exists: IfExistsFeatures::no_append(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
+
+/// Implementation of `write_schema`, but as a real `async` function.
+async fn write_schema_helper(
+    ctx: Context,
+    dest: DbtSchemaLocator,
+    table: Table,
+    if_exists: IfExists,
+) -> Result<()> {
+    // Generate our YAML before we create our output file.
+    let yaml = dbt_sources_yaml(&table, dest.source.as_deref());
+
+    // Output our schema to our destination.
+    let mut f = dest.path.create_async(ctx, if_exists).await?;
+    buffer_sync_write_and_copy_to_async(&mut f, |buff| write!(buff, "{}", yaml))
+        .await
+        .with_context(|_| format!("error writing to {}", dest.path))?;
+    f.flush().await?;
+    Ok(())
+}
+
+/// Generate a dbt `sources:` file declaring `table`.
+///
+/// If `table.name` has a schema, we use it as the source's `schema` and,
+/// unless `source` is specified, as the source's name.
+fn dbt_sources_yaml(table: &Table, source: Option<&str>) -> String {
+    let (schema, table_name) = match table.name.rfind('.') {
+        Some(idx) => (Some(&table.name[..idx]), &table.name[idx + 1..]),
+        None => (None, &table.name[..]),
+    };
+    let source_name = source.or(schema).unwrap_or("default");
+
+    let mut yaml = String::new();
+    yaml.push_str("version: 2\n\n");
+    yaml.push_str("sources:\n");
+    yaml.push_str(&format!("  - name: {}\n", yaml_string(source_name)));
+    if let Some(schema) = schema {
+        yaml.push_str(&format!("    schema: {}\n", yaml_string(schema)));
+    }
+    yaml.push_str("    tables:\n");
+    yaml.push_str(&format!("      - name: {}\n", yaml_string(table_name)));
+    yaml.push_str("        columns:\n");
+    for column in &table.columns {
+        yaml.push_str(&format!(
+            "          - name: {}\n",
+            yaml_string(&column.name)
+        ));
+        if let Some(comment) = &column.comment {
+            yaml.push_str(&format!(
+                "            description: {}\n",
+                yaml_string(comment)
+            ));
+        }
+        yaml.push_str(&format!(
+            "            data_type: {}\n",
+            yaml_string(&sql_type(&column.data_type))
+        ));
+        if !column.is_nullable {
+            yaml.push_str("            tests:\n");
+            yaml.push_str("              - not_null\n");
+        }
+    }
+    yaml
+}
+
+/// Choose a PostgreSQL-style type name to use as a column's `data_type`.
+fn sql_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Array(elem_type) => format!("{}[]", sql_type(elem_type)),
+        DataType::Bool => "boolean".to_owned(),
+        DataType::Date => "date".to_owned(),
+        DataType::Decimal => "numeric".to_owned(),
+        DataType::FixedDecimal { precision, scale } => {
+            format!("numeric({}, {})", precision, scale)
+        }
+        DataType::Float32 => "real".to_owned(),
+        DataType::Float64 => "double precision".to_owned(),
+        DataType::GeoJson(srid) => format!("geometry(Geometry, {})", srid),
+        DataType::Int16 => "smallint".to_owned(),
+        DataType::Int32 => "integer".to_owned(),
+        DataType::Int64 => "bigint".to_owned(),
+        DataType::Json | DataType::Struct(_) => "jsonb".to_owned(),
+        DataType::Text => "text".to_owned(),
+        DataType::TimestampWithoutTimeZone => "timestamp without time zone".to_owned(),
+        DataType::TimestampWithTimeZone => "timestamp with time zone".to_owned(),
+        DataType::Uuid => "uuid".to_owned(),
+    }
+}
+
+/// Format `s` as a YAML scalar, quoting it unless it's made of simple words
+/// that YAML won't interpret as something other than a string.
+fn yaml_string(s: &str) -> String {
+    let is_plain = matches!(s.chars().next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && !s.ends_with(' ')
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ' ')
+        && !["true", "false", "yes", "no", "on", "off", "null", "y", "n"]
+            .contains(&s.to_ascii_lowercase().as_str());
+    if is_plain {
+        s.to_owned()
+    } else {
+        // JSON strings are valid YAML double-quoted scalars.
+        serde_json::to_string(s).expect("strings can always be serialized")
+    }
+}
+
+#[test]
+fn dbt_sources_yaml_declares_columns() {
+    use crate::schema::Column;
+
+    let table = Table {
+        name: "public.orders".to_owned(),
+        columns: vec![
+            Column {
+                name: "id".to_owned(),
+                is_nullable: false,
+                data_type: DataType::Int64,
+                comment: Some("Unique ID: \"primary\"".to_owned()),
+            },
+            Column {
+                name: "on".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Array(Box::new(DataType::Text)),
+                comment: None,
+            },
+        ],
+    };
+    assert_eq!(
+        dbt_sources_yaml(&table, None),
+        r#"version: 2
+
+sources:
+  - name: public
+    schema: public
+    tables:
+      - name: orders
+        columns:
+          - name: id
+            description: "Unique ID: \"primary\""
+            data_type: bigint
+            tests:
+              - not_null
+          - name: "on"
+            data_type: "text[]"
+"#,
+    );
+    assert!(dbt_sources_yaml(&table, Some("raw")).contains("  - name: raw\n"));
+}
@@ -0,0 +1,176 @@
+//! Driver for piping CSV data to and from external commands.
+//!
+//! This provides an escape hatch for data formats that we don't support
+//! directly: users can write a small script which converts between their
+//! format and our CSV interchange format.
+
+use std::{fmt, process::Stdio, str::FromStr};
+use tokio::process::Command;
+
+use crate::common::*;
+use crate::concat::concatenate_csv_streams;
+use crate::tokio_glue::{copy_reader_to_stream, copy_stream_to_writer};
+
+/// An external command which reads or writes CSV data, such as
+/// `exec:./my-filter.sh --verbose`. The command will be run using `sh -c`.
+#[derive(Clone, Debug)]
+pub(crate) struct ExecLocator {
+    command: String,
+}
+
+impl ExecLocator {
+    /// Build a `Command` which runs our command using the shell.
+    fn shell_command(&self) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&self.command);
+        command
+    }
+}
+
+impl fmt::Display for ExecLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", Self::scheme(), self.command)
+    }
+}
+
+impl FromStr for ExecLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if !s.starts_with(Self::scheme()) {
+            return Err(format_err!("expected {} to begin with exec:", s));
+        }
+        let command = &s[Self::scheme().len()..];
+        if command.trim().is_empty() {
+            return Err(format_err!("expected a command after exec: in {:?}", s));
+        }
+        Ok(ExecLocator {
+            command: command.to_owned(),
+        })
+    }
+}
+
+#[test]
+fn from_str_requires_command() {
+    let locator = "exec:./filter.sh --flag".parse::<ExecLocator>().unwrap();
+    assert_eq!(locator.command, "./filter.sh --flag");
+    assert!("exec:".parse::<ExecLocator>().is_err());
+    assert!("exec:  ".parse::<ExecLocator>().is_err());
+}
+
+impl Locator for ExecLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn local_data(
+        &self,
+        ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Option<BoxStream<CsvStream>>> {
+        local_data_helper(ctx, self.clone(), shared_args, source_args).boxed()
+    }
+
+    fn display_output_locators(&self) -> DisplayOutputLocators {
+        // Our command may write its own output to standard output.
+        DisplayOutputLocators::Never
+    }
+
+    fn write_local_data(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+        shared_args: SharedArguments<Unverified>,
+        dest_args: DestinationArguments<Unverified>,
+    ) -> BoxFuture<BoxStream<BoxFuture<BoxLocator>>> {
+        write_local_data_helper(ctx, self.clone(), data, shared_args, dest_args)
+            .boxed()
+    }
+}
+
+/// Run our command and read CSV data from its standard output.
+async fn local_data_helper(
+    ctx: Context,
+    locator: ExecLocator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Option<BoxStream<CsvStream>>> {
+    let _shared_args = shared_args.verify(ExecLocator::features())?;
+    let _source_args = source_args.verify(ExecLocator::features())?;
+
+    debug!(ctx.log(), "reading CSV data from `{}`", locator.command);
+    let mut child = locator
+        .shell_command()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|_| format!("error running `{}`", locator.command))?;
+    let child_stdout = child.stdout.take().expect("child should have stdout");
+    ctx.spawn_process(format!("`{}`", locator.command), child);
+
+    let data = copy_reader_to_stream(ctx.clone(), child_stdout)?;
+    Ok(Some(box_stream_once(Ok(CsvStream {
+        name: "data".to_owned(),
+        metadata: StreamMetadata::default(),
+        data: data.boxed(),
+    }))))
+}
+
+/// Run our command and write all our CSV data to its standard input.
+async fn write_local_data_helper(
+    ctx: Context,
+    locator: ExecLocator,
+    data: BoxStream<CsvStream>,
+    shared_args: SharedArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
+    let _shared_args = shared_args.verify(ExecLocator::features())?;
+    let dest_args = dest_args.verify(ExecLocator::features())?;
+    let if_exists = dest_args.if_exists().to_owned();
+
+    let stream = concatenate_csv_streams(ctx.clone(), data)?;
+    let fut = async move {
+        debug!(ctx.log(), "writing CSV data to `{}`", locator.command);
+
+        // Let the command decide what `--if-exists` means for it.
+        let mut child = locator
+            .shell_command()
+            .env("DBCROSSBAR_IF_EXISTS", if_exists.to_string())
+            .stdin(Stdio::piped())
+            .spawn()
+            .with_context(|_| format!("error running `{}`", locator.command))?;
+        let child_stdin = child.stdin.take().expect("child should have stdin");
+
+        // Copy our data, and close stdin so that the command sees EOF.
+        copy_stream_to_writer(ctx.clone(), stream.data, child_stdin)
+            .await
+            .with_context(|_| format!("error writing to `{}`", locator.command))?;
+
+        let status = child
+            .await
+            .with_context(|_| format!("error waiting for `{}`", locator.command))?;
+        if !status.success() {
+            return Err(format_err!("`{}` failed with {}", locator.command, status));
+        }
+        Ok(locator.boxed())
+    };
+    Ok(box_stream_once(Ok(fut.boxed())))
+}
+
+impl LocatorStatic for ExecLocator {
+    fn scheme() -> &'static str {
+        "exec:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::LocalData | LocatorFeatures::WriteLocalData,
+            write_schema_if_exists: EnumSet::empty(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::all(),
+            _placeholder: (),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Generating random values which match a portable schema.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rand::{seq::SliceRandom, Rng};
+use serde_json::{json, Value};
+
+use crate::common::*;
+use crate::schema::{Column, DataType};
+
+/// First names used to generate realistic-looking names.
+const FIRST_NAMES: &[&str] = &[
+    "Ada",
+    "Alan",
+    "Barbara",
+    "Carlos",
+    "Dorothy",
+    "Edsger",
+    "Frances",
+    "Grace",
+    "Hedy",
+    "John",
+    "Katherine",
+    "Leslie",
+    "Margaret",
+    "Niklaus",
+    "Radia",
+    "Yukihiro",
+];
+
+/// Last names used to generate realistic-looking names.
+const LAST_NAMES: &[&str] = &[
+    "Allen",
+    "Backus",
+    "Dijkstra",
+    "Hamilton",
+    "Hopper",
+    "Johnson",
+    "Kay",
+    "Lamarr",
+    "Liskov",
+    "Lovelace",
+    "Matsumoto",
+    "Perlman",
+    "Ritchie",
+    "Turing",
+    "Wirth",
+];
+
+/// Words used to generate random text.
+const WORDS: &[&str] = &[
+    "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india",
+    "juliett", "kilo", "lima", "mike", "november", "oscar", "papa",
+];
+
+/// Options controlling how we generate values.
+#[derive(Clone, Debug)]
+pub(crate) struct GenerateOptions {
+    /// How often should nullable values be `NULL`?
+    pub(crate) null_probability: f64,
+}
+
+/// Write a CSV row of random values for `columns` to `wtr`.
+pub(crate) fn write_fake_row<R, W>(
+    rng: &mut R,
+    options: &GenerateOptions,
+    columns: &[Column],
+    wtr: &mut csv::Writer<W>,
+) -> Result<()>
+where
+    R: Rng,
+    W: Write,
+{
+    for col in columns {
+        if col.is_nullable && rng.gen_bool(options.null_probability) {
+            wtr.write_field("")?;
+            continue;
+        }
+        let value = fake_value(rng, options, &col.name, &col.data_type);
+        let cell = if col.data_type.serializes_as_json_for_csv() {
+            value.to_string()
+        } else {
+            match value {
+                Value::Bool(true) => "t".to_owned(),
+                Value::Bool(false) => "f".to_owned(),
+                Value::String(s) => s,
+                other => other.to_string(),
+            }
+        };
+        wtr.write_field(&cell)?;
+    }
+    wtr.write_record(None::<&[u8]>)?;
+    Ok(())
+}
+
+/// Generate a random value of type `data_type`, represented as JSON, the way it
+/// would appear inside a JSON array in our CSV interchange format. We use
+/// `name` to pick realistic text values for columns like `email`.
+fn fake_value<R: Rng>(
+    rng: &mut R,
+    options: &GenerateOptions,
+    name: &str,
+    data_type: &DataType,
+) -> Value {
+    match data_type {
+        DataType::Array(elem_type) => {
+            let len = rng.gen_range(0, 4);
+            Value::Array(
+                (0..len)
+                    .map(|_| fake_value(rng, options, name, elem_type))
+                    .collect(),
+            )
+        }
+        DataType::Bool => Value::Bool(rng.gen()),
+        DataType::Date => {
+            Value::String(fake_timestamp(rng).date().format("%Y-%m-%d").to_string())
+        }
+        DataType::Decimal => Value::String(format!(
+            "{}.{:02}",
+            rng.gen_range(-100_000, 100_000),
+            rng.gen_range(0, 100),
+        )),
+        DataType::FixedDecimal { precision, scale } => {
+            // Stay well inside the range allowed by `precision` and `scale`.
+            let int_digits = precision.saturating_sub(*scale).min(5);
+            let frac_digits = (*scale).min(4);
+            let sign = if rng.gen() { "-" } else { "" };
+            let int_part = rng.gen_range(0, 10i64.pow(int_digits));
+            if frac_digits == 0 {
+                Value::String(format!("{}{}", sign, int_part))
+            } else {
+                Value::String(format!(
+                    "{}{}.{:0width$}",
+                    sign,
+                    int_part,
+                    rng.gen_range(0, 10i64.pow(frac_digits)),
+                    width = frac_digits as usize,
+                ))
+            }
+        }
+        DataType::Float32 => json!(f64::from(rng.gen_range(-1000.0f32, 1000.0))),
+        DataType::Float64 => json!(rng.gen_range(-1_000_000.0, 1_000_000.0)),
+        DataType::GeoJson(_) => json!({
+            "type": "Point",
+            "coordinates": [rng.gen_range(-180.0, 180.0), rng.gen_range(-90.0, 90.0)],
+        }),
+        DataType::Int16 => json!(rng.gen::<i16>()),
+        DataType::Int32 => json!(rng.gen::<i32>()),
+        // We represent 64-bit integers as strings in JSON, because many JSON
+        // parsers can't handle them.
+        DataType::Int64 => Value::String(rng.gen::<i64>().to_string()),
+        DataType::Json => json!({ "id": rng.gen_range(0, 1_000_000) }),
+        DataType::Text => Value::String(fake_text(rng, name)),
+        DataType::Struct(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|field| {
+                    let value = if field.is_nullable
+                        && rng.gen_bool(options.null_probability)
+                    {
+                        Value::Null
+                    } else {
+                        fake_value(rng, options, &field.name, &field.data_type)
+                    };
+                    (field.name.clone(), value)
+                })
+                .collect(),
+        ),
+        DataType::TimestampWithoutTimeZone => {
+            Value::String(fake_timestamp(rng).format("%Y-%m-%dT%H:%M:%S").to_string())
+        }
+        DataType::TimestampWithTimeZone => {
+            Value::String(fake_timestamp(rng).format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        }
+        DataType::Uuid => Value::String(
+            uuid::Builder::from_bytes(rng.gen())
+                .set_variant(uuid::Variant::RFC4122)
+                .set_version(uuid::Version::Random)
+                .build()
+                .to_string(),
+        ),
+    }
+}
+
+/// Generate a random timestamp between 1970 and 2030.
+fn fake_timestamp<R: Rng>(rng: &mut R) -> NaiveDateTime {
+    let start = NaiveDate::from_ymd_opt(1970, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("start of fake timestamp range should be valid");
+    let end = NaiveDate::from_ymd_opt(2030, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("end of fake timestamp range should be valid");
+    let seconds = rng.gen_range(0, (end - start).num_seconds());
+    start + chrono::Duration::seconds(seconds)
+}
+
+/// Generate random text, using `name` to guess what kind of text we want.
+fn fake_text<R: Rng>(rng: &mut R, name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    let first = *FIRST_NAMES.choose(rng).expect("should have first names");
+    let last = *LAST_NAMES.choose(rng).expect("should have last names");
+    if name.contains("email") {
+        format!(
+            "{}.{}@example.com",
+            first.to_ascii_lowercase(),
+            last.to_ascii_lowercase(),
+        )
+    } else if name.contains("first") {
+        first.to_owned()
+    } else if name.contains("last") {
+        last.to_owned()
+    } else if name.contains("name") {
+        format!("{} {}", first, last)
+    } else {
+        let len = rng.gen_range(1, 5);
+        (0..len)
+            .map(|_| *WORDS.choose(rng).expect("should have words"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[test]
+fn write_fake_row_matches_schema() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let columns = vec![
+        Column {
+            name: "id".to_owned(),
+            is_nullable: false,
+            data_type: DataType::Int64,
+            comment: None,
+        },
+        Column {
+            name: "email".to_owned(),
+            is_nullable: false,
+            data_type: DataType::Text,
+            comment: None,
+        },
+        Column {
+            name: "tags".to_owned(),
+            is_nullable: false,
+            data_type: DataType::Array(Box::new(DataType::Int64)),
+            comment: None,
+        },
+        Column {
+            name: "maybe".to_owned(),
+            is_nullable: true,
+            data_type: DataType::Bool,
+            comment: None,
+        },
+    ];
+    let options = GenerateOptions {
+        null_probability: 1.0,
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    write_fake_row(&mut rng, &options, &columns, &mut wtr).unwrap();
+    let out = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(out.as_bytes());
+    let row = rdr.records().next().unwrap().unwrap();
+    assert!(row[0].parse::<i64>().is_ok());
+    assert!(row[1].ends_with("@example.com"));
+    assert!(serde_json::from_str::<Vec<String>>(&row[2]).is_ok());
+    assert_eq!(&row[3], "");
+}
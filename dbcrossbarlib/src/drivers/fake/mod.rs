@@ -0,0 +1,150 @@
+//! Driver which generates synthetic data matching a schema.
+
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+use crate::driver_args::deserialize_opt_from_str;
+use crate::tokio_glue::{spawn_blocking_stage, SyncStreamWriter};
+
+mod generate;
+
+use self::generate::{write_fake_row, GenerateOptions};
+
+/// How often nullable columns are `NULL` by default.
+const DEFAULT_NULL_PROBABILITY: f64 = 0.1;
+
+/// A source of random data, such as `fake:1000`, which generates the specified
+/// number of rows matching `--schema`.
+#[derive(Clone, Debug)]
+pub(crate) struct FakeLocator {
+    /// The number of rows to generate.
+    rows: u64,
+}
+
+impl fmt::Display for FakeLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", Self::scheme(), self.rows)
+    }
+}
+
+impl FromStr for FakeLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if !s.starts_with(Self::scheme()) {
+            return Err(format_err!("expected {} to begin with fake:", s));
+        }
+        let rows = s[Self::scheme().len()..].parse::<u64>().with_context(|_| {
+            format!("expected a row count in {:?}, like fake:1000", s)
+        })?;
+        Ok(FakeLocator { rows })
+    }
+}
+
+/// Source arguments for `fake:`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FakeSourceArguments {
+    /// A random seed, for generating the same data every time.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    seed: Option<u64>,
+
+    /// How often should nullable columns be `NULL`?
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    null_probability: Option<f64>,
+}
+
+impl Locator for FakeLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn count(
+        &self,
+        _ctx: Context,
+        _shared_args: SharedArguments<Unverified>,
+        _source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<usize> {
+        let rows = self.rows;
+        async move { Ok(usize::try_from(rows)?) }.boxed()
+    }
+
+    fn local_data(
+        &self,
+        ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Option<BoxStream<CsvStream>>> {
+        local_data_helper(ctx, self.rows, shared_args, source_args).boxed()
+    }
+}
+
+/// Generate our data in a background thread.
+async fn local_data_helper(
+    ctx: Context,
+    rows: u64,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Option<BoxStream<CsvStream>>> {
+    let shared_args = shared_args.verify(FakeLocator::features())?;
+    let source_args = source_args.verify(FakeLocator::features())?;
+    let schema = shared_args.schema().to_owned();
+    let fake_args = source_args
+        .driver_args()
+        .deserialize::<FakeSourceArguments>()
+        .context("could not parse --from-arg")?;
+
+    let null_probability = fake_args
+        .null_probability
+        .unwrap_or(DEFAULT_NULL_PROBABILITY);
+    if !(0.0..=1.0).contains(&null_probability) {
+        return Err(format_err!(
+            "null_probability must be between 0 and 1, found {}",
+            null_probability,
+        ));
+    }
+    let options = GenerateOptions { null_probability };
+    let seed = fake_args.seed.unwrap_or_else(|| thread_rng().gen());
+    debug!(
+        ctx.log(),
+        "generating {} fake rows with seed {}", rows, seed
+    );
+
+    let (wtr, data) = SyncStreamWriter::pipe(ctx.clone());
+    let worker = spawn_blocking_stage(move || -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut wtr = csv::Writer::from_writer(wtr);
+        wtr.write_record(schema.columns.iter().map(|c| &c.name))?;
+        for _ in 0..rows {
+            write_fake_row(&mut rng, &options, &schema.columns, &mut wtr)?;
+        }
+        wtr.flush().context("error writing fake data")?;
+        Ok(())
+    });
+    ctx.spawn_worker(worker.boxed());
+
+    Ok(Some(box_stream_once(Ok(CsvStream {
+        name: "data".to_owned(),
+        metadata: StreamMetadata::default(),
+        data: data.boxed(),
+    }))))
+}
+
+impl LocatorStatic for FakeLocator {
+    fn scheme() -> &'static str {
+        "fake:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::LocalData | LocatorFeatures::Count,
+            write_schema_if_exists: EnumSet::empty(),
+            source_args: SourceArgumentsFeatures::DriverArgs.into(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
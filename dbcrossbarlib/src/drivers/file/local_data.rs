@@ -0,0 +1,186 @@
+//! Reading files as opaque byte streams.
+
+use chrono::{DateTime, Utc};
+use futures::future;
+use percent_encoding::percent_decode_str;
+use tokio::{
+    fs,
+    io::{self, BufReader},
+};
+use walkdir::WalkDir;
+
+use super::{
+    names::{check_relative_name, file_stream_name},
+    FileLocator, FileStorage,
+};
+use crate::clouds::{aws::s3, gcloud::storage};
+use crate::common::*;
+use crate::path_or_stdio::{long_path, to_slash_lossy};
+use crate::tokio_glue::copy_reader_to_stream;
+
+/// Implementation of `local_data`, but as a real `async` function.
+pub(crate) async fn local_data_helper(
+    ctx: Context,
+    locator: FileLocator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Option<BoxStream<CsvStream>>> {
+    let _shared_args = shared_args.verify(FileLocator::features())?;
+    let _source_args = source_args.verify(FileLocator::features())?;
+    debug!(ctx.log(), "getting files from {}", locator);
+
+    let is_directory = locator.is_directory();
+    match locator.storage {
+        FileStorage::Local(PathOrStdio::Stdio) => {
+            let data = BufReader::with_capacity(BUFFER_SIZE, io::stdin());
+            let data = copy_reader_to_stream(ctx, data)?
+                .map_err(move |e| format_err!("cannot read stdin: {}", e))
+                .boxed();
+            let stream = CsvStream {
+                name: "data".to_owned(),
+                metadata: StreamMetadata::default(),
+                data,
+            };
+            Ok(Some(box_stream_once(Ok(stream))))
+        }
+        FileStorage::Local(PathOrStdio::Path(base_path)) => {
+            // Find all our files up front, so that we report errors early.
+            let mut paths = vec![];
+            let base_path = long_path(&base_path)?;
+            for dirent in WalkDir::new(&base_path).follow_links(true) {
+                let dirent = dirent.with_context(|_| {
+                    format!("error listing files in {}", base_path.display())
+                })?;
+                if dirent.file_type().is_file() {
+                    paths.push(dirent.path().to_owned());
+                } else if !dirent.file_type().is_dir() {
+                    return Err(format_err!(
+                        "not a file: {}",
+                        dirent.path().display()
+                    ));
+                }
+            }
+
+            let streams = stream::iter(paths).map(Ok).and_then(move |file_path| {
+                let ctx = ctx.clone();
+                let base_path = base_path.clone();
+                async move {
+                    let name = file_stream_name(
+                        &to_slash_lossy(&base_path),
+                        &to_slash_lossy(&file_path),
+                    )?;
+                    let ctx = ctx.child(o!(
+                        "stream" => name.clone(),
+                        "path" => format!("{}", file_path.display()),
+                    ));
+                    let file = fs::File::open(file_path.clone()).await.with_context(
+                        |_| format!("cannot open {}", file_path.display()),
+                    )?;
+                    let modified = file
+                        .metadata()
+                        .await
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                        .map(DateTime::<Utc>::from);
+                    let metadata = StreamMetadata {
+                        source: Some(file_path.display().to_string()),
+                        modified,
+                    };
+                    let file = BufReader::with_capacity(BUFFER_SIZE, file);
+                    let data = copy_reader_to_stream(ctx, file)?
+                        .map_err(move |e| {
+                            format_err!("cannot read {}: {}", file_path.display(), e)
+                        })
+                        .boxed();
+                    Ok(CsvStream {
+                        name,
+                        metadata,
+                        data,
+                    })
+                }
+                .boxed()
+            });
+            Ok(Some(streams.boxed()))
+        }
+        FileStorage::S3(url) => {
+            let files = s3::ls(&ctx, &url, None, &s3::S3Connection::default()).await?;
+            let filter_url = url.clone();
+            let streams = files
+                // S3 listings match prefixes, so `s3://b/a.json` would also
+                // match `s3://b/a.json.bak`.
+                .try_filter(move |item| {
+                    future::ready(is_directory || item.url == filter_url)
+                })
+                .and_then(move |item| {
+                    let ctx = ctx.clone();
+                    let url = url.clone();
+                    async move {
+                        let name = url_stream_name(&url, &item.url)?;
+                        let ctx = ctx.child(o!(
+                            "stream" => name.clone(),
+                            "url" => item.url.as_str().to_owned(),
+                        ));
+                        let data = s3::download_file(
+                            &ctx,
+                            &item.url,
+                            None,
+                            &s3::S3Connection::default(),
+                        )
+                        .await?;
+                        Ok(CsvStream {
+                            name,
+                            metadata: StreamMetadata {
+                                source: Some(item.url.as_str().to_owned()),
+                                modified: item.modified,
+                            },
+                            data,
+                        })
+                    }
+                    .boxed()
+                });
+            Ok(Some(streams.boxed()))
+        }
+        FileStorage::Gs(url) => {
+            let files = storage::ls(&ctx, &url, None).await?;
+            let filter_url = url.clone();
+            let streams = files
+                .try_filter(move |item| {
+                    future::ready(
+                        is_directory || item.to_url_string() == filter_url.as_str(),
+                    )
+                })
+                .and_then(move |item| {
+                    let ctx = ctx.clone();
+                    let url = url.clone();
+                    async move {
+                        let file_url = item.to_url_string().parse::<Url>()?;
+                        let name = url_stream_name(&url, &file_url)?;
+                        let ctx = ctx.child(o!(
+                            "stream" => name.clone(),
+                            "url" => file_url.as_str().to_owned(),
+                        ));
+                        let metadata = StreamMetadata {
+                            source: Some(file_url.as_str().to_owned()),
+                            modified: item.updated()?,
+                        };
+                        let data = storage::download_file(&ctx, &item, None).await?;
+                        Ok(CsvStream {
+                            name,
+                            metadata,
+                            data,
+                        })
+                    }
+                    .boxed()
+                });
+            Ok(Some(streams.boxed()))
+        }
+    }
+}
+
+/// Choose a stream name for `file_url`, which was found under `base_url`.
+fn url_stream_name(base_url: &Url, file_url: &Url) -> Result<String> {
+    let name = file_stream_name(base_url.as_str(), file_url.as_str())?;
+    let name = percent_decode_str(&name).decode_utf8_lossy().into_owned();
+    check_relative_name(&name)?;
+    Ok(name)
+}
@@ -0,0 +1,178 @@
+//! Driver for copying arbitrary files without looking at their contents.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+use crate::path_or_stdio::ends_with_separator;
+use crate::schema::{Column, DataType};
+
+mod local_data;
+mod names;
+mod write_local_data;
+
+use self::local_data::local_data_helper;
+use self::write_local_data::write_local_data_helper;
+
+/// Where our files are stored.
+#[derive(Clone, Debug)]
+pub(crate) enum FileStorage {
+    /// A local file or directory, or standard I/O.
+    Local(PathOrStdio),
+    /// An `s3://` URL.
+    S3(Url),
+    /// A `gs://` URL.
+    Gs(Url),
+}
+
+/// A file or directory of files, which we copy as opaque bytes. This may be
+/// local, or stored in any cloud bucket we support. For example,
+/// `file:s3://example/sidecars/` or `file:data/model.bin`.
+#[derive(Clone, Debug)]
+pub(crate) struct FileLocator {
+    storage: FileStorage,
+}
+
+impl FileLocator {
+    /// Does this locator point to a directory?
+    fn is_directory(&self) -> bool {
+        match &self.storage {
+            FileStorage::Local(PathOrStdio::Path(path)) => ends_with_separator(path),
+            FileStorage::Local(PathOrStdio::Stdio) => false,
+            FileStorage::S3(url) | FileStorage::Gs(url) => url.path().ends_with('/'),
+        }
+    }
+}
+
+impl fmt::Display for FileLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.storage {
+            FileStorage::Local(path) => path.fmt_locator_helper(Self::scheme(), f),
+            FileStorage::S3(url) | FileStorage::Gs(url) => {
+                write!(f, "{}{}", Self::scheme(), url)
+            }
+        }
+    }
+}
+
+impl FromStr for FileLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if !s.starts_with(Self::scheme()) {
+            return Err(format_err!("expected {} to begin with file:", s));
+        }
+        let rest = &s[Self::scheme().len()..];
+        let storage = if rest.starts_with("s3://") || rest.starts_with("gs://") {
+            let url = rest
+                .parse::<Url>()
+                .with_context(|_| format!("cannot parse {}", s))?;
+            if url.host().is_none() || !url.path().starts_with('/') {
+                return Err(format_err!("cannot find bucket in {}", s));
+            }
+            if url.scheme() == "s3" {
+                FileStorage::S3(url)
+            } else {
+                FileStorage::Gs(url)
+            }
+        } else if rest.contains("://") {
+            return Err(format_err!(
+                "file: only supports local paths, s3:// and gs:// URLs, not {}",
+                rest,
+            ));
+        } else {
+            FileStorage::Local(rest.parse::<PathOrStdio>()?)
+        };
+        Ok(FileLocator { storage })
+    }
+}
+
+impl Locator for FileLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self, _ctx: Context) -> BoxFuture<Option<Table>> {
+        // We never look inside our files, but `cp` always needs a schema, so
+        // describe each file as a single opaque value.
+        let table = Table {
+            name: "file".to_owned(),
+            columns: vec![Column {
+                name: "data".to_owned(),
+                is_nullable: false,
+                data_type: DataType::Text,
+                comment: Some("The contents of a file, copied as bytes.".to_owned()),
+            }],
+        };
+        async move { Ok(Some(table)) }.boxed()
+    }
+
+    fn local_data(
+        &self,
+        ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Option<BoxStream<CsvStream>>> {
+        local_data_helper(ctx, self.clone(), shared_args, source_args).boxed()
+    }
+
+    fn display_output_locators(&self) -> DisplayOutputLocators {
+        match &self.storage {
+            // Don't mix our output locator with file contents on standard
+            // output.
+            FileStorage::Local(PathOrStdio::Stdio) => DisplayOutputLocators::Never,
+            _ => DisplayOutputLocators::IfRequested,
+        }
+    }
+
+    fn write_local_data(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+        shared_args: SharedArguments<Unverified>,
+        dest_args: DestinationArguments<Unverified>,
+    ) -> BoxFuture<BoxStream<BoxFuture<BoxLocator>>> {
+        write_local_data_helper(ctx, self.clone(), data, shared_args, dest_args)
+            .boxed()
+    }
+}
+
+impl LocatorStatic for FileLocator {
+    fn scheme() -> &'static str {
+        "file:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::Schema
+                | LocatorFeatures::LocalData
+                | LocatorFeatures::WriteLocalData,
+            write_schema_if_exists: EnumSet::empty(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: IfExistsFeatures::no_append() | IfExistsFeatures::Append,
+            _placeholder: (),
+        }
+    }
+}
+
+#[test]
+fn parses_storage_types() {
+    let loc = FileLocator::from_str("file:s3://example/sidecars/").unwrap();
+    match &loc.storage {
+        FileStorage::S3(_) => {}
+        other => panic!("expected S3 storage, found {:?}", other),
+    }
+    assert!(loc.is_directory());
+    let loc = FileLocator::from_str("file:gs://example/model.bin").unwrap();
+    match &loc.storage {
+        FileStorage::Gs(_) => {}
+        other => panic!("expected GCS storage, found {:?}", other),
+    }
+    assert!(!loc.is_directory());
+    let loc = FileLocator::from_str("file:-").unwrap();
+    match &loc.storage {
+        FileStorage::Local(PathOrStdio::Stdio) => {}
+        other => panic!("expected standard I/O, found {:?}", other),
+    }
+    assert!(FileLocator::from_str("file:sftp://example.com/x").is_err());
+}
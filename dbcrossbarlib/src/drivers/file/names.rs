@@ -0,0 +1,90 @@
+//! Naming streams after the files they contain.
+//!
+//! Unlike `csv_stream_name`, we keep file extensions, because we don't know
+//! what kind of files we're copying.
+
+use crate::common::*;
+
+/// Given a `base_path` pointing to a file or directory, and a `file_path`
+/// pointing to a file, return the relative path we should use as the stream
+/// name.
+pub(crate) fn file_stream_name(base_path: &str, file_path: &str) -> Result<String> {
+    let name = if file_path == base_path {
+        file_path.rsplitn(2, '/').next().unwrap_or(file_path)
+    } else if base_path.ends_with('/') && file_path.starts_with(base_path) {
+        &file_path[base_path.len()..]
+    } else if file_path.starts_with(base_path)
+        && file_path[base_path.len()..].starts_with('/')
+    {
+        &file_path[base_path.len() + 1..]
+    } else {
+        return Err(format_err!(
+            "expected {} to start with {}",
+            file_path,
+            base_path,
+        ));
+    };
+    check_relative_name(name)?;
+    Ok(name.to_owned())
+}
+
+/// Make sure that `name` can be safely joined to a destination directory.
+pub(crate) fn check_relative_name(name: &str) -> Result<()> {
+    if name
+        .split('/')
+        .any(|segment| segment.is_empty() || segment == "." || segment == "..")
+    {
+        Err(format_err!("cannot copy file with unsafe name {:?}", name))
+    } else {
+        Ok(())
+    }
+}
+
+/// Choose the name to use when writing the stream `name` to a directory. When
+/// appending, we insert a unique suffix before the extension so that we never
+/// replace existing files.
+pub(crate) fn file_name_for_stream(name: &str, if_exists: &IfExists) -> String {
+    match if_exists {
+        IfExists::Append => {
+            let tag = TemporaryStorage::random_tag();
+            let (dir, base) = match name.rfind('/') {
+                Some(idx) => name.split_at(idx + 1),
+                None => ("", name),
+            };
+            match base.find('.') {
+                Some(idx) if idx > 0 => {
+                    format!("{}{}_{}{}", dir, &base[..idx], tag, &base[idx..])
+                }
+                _ => format!("{}_{}", name, tag),
+            }
+        }
+        _ => name.to_owned(),
+    }
+}
+
+#[test]
+fn file_stream_name_keeps_extensions_and_subdirectories() {
+    let expected = &[
+        ("s3://b/model.tar.gz", "s3://b/model.tar.gz", "model.tar.gz"),
+        ("s3://b/dir/", "s3://b/dir/sub/a.json", "sub/a.json"),
+        ("dir", "dir/README", "README"),
+    ];
+    for &(base_path, file_path, name) in expected {
+        assert_eq!(file_stream_name(base_path, file_path).unwrap(), name);
+    }
+    assert!(file_stream_name("dir/", "other/a.json").is_err());
+    assert!(file_stream_name("dir/", "dir/../a.json").is_err());
+}
+
+#[test]
+fn file_name_for_stream_is_unique_when_appending() {
+    assert_eq!(
+        file_name_for_stream("sub/a.json", &IfExists::Overwrite),
+        "sub/a.json",
+    );
+    let appended = file_name_for_stream("sub/a.tar.gz", &IfExists::Append);
+    assert!(appended.starts_with("sub/a_"));
+    assert!(appended.ends_with(".tar.gz"));
+    let appended = file_name_for_stream(".env", &IfExists::Append);
+    assert!(appended.starts_with(".env_"));
+}
@@ -0,0 +1,186 @@
+//! Writing opaque byte streams to files.
+
+use tokio::io;
+
+use super::{
+    names::{check_relative_name, file_name_for_stream},
+    FileLocator, FileStorage,
+};
+use crate::clouds::{aws::s3, gcloud::storage};
+use crate::common::*;
+use crate::drivers::{csv::write_stream_to_file, gs, s3 as s3_driver};
+use crate::tokio_glue::copy_stream_to_writer;
+
+impl FileLocator {
+    /// Return a locator for the file `name` in this directory.
+    fn join(&self, name: &str) -> Result<FileLocator> {
+        let storage = match &self.storage {
+            FileStorage::Local(PathOrStdio::Path(path)) => {
+                FileStorage::Local(PathOrStdio::Path(path.join(name)))
+            }
+            FileStorage::Local(PathOrStdio::Stdio) => {
+                return Err(format_err!("cannot write multiple files to stdout"));
+            }
+            FileStorage::S3(url) => FileStorage::S3(join_url(url, name)?),
+            FileStorage::Gs(url) => FileStorage::Gs(join_url(url, name)?),
+        };
+        Ok(FileLocator { storage })
+    }
+
+    /// Write `data` to the file we point to.
+    async fn write_file(
+        &self,
+        ctx: Context,
+        data: BoxStream<BytesMut>,
+        if_exists: IfExists,
+    ) -> Result<()> {
+        match &self.storage {
+            FileStorage::Local(PathOrStdio::Path(path)) => {
+                write_stream_to_file(ctx, data, path.to_owned(), if_exists).await
+            }
+            FileStorage::Local(PathOrStdio::Stdio) => {
+                if_exists.warn_if_not_default_for_stdout(&ctx);
+                copy_stream_to_writer(ctx.clone(), data, io::stdout())
+                    .await
+                    .context("error writing to stdout")?;
+                Ok(())
+            }
+            FileStorage::S3(url) => {
+                let opt = s3::UploadOptions::default();
+                let conn = s3::S3Connection::default();
+                s3::upload_file(&ctx, data, url, &opt, &conn).await
+            }
+            FileStorage::Gs(url) => {
+                let opt = storage::UploadOptions::default();
+                storage::upload_file(&ctx, data, url, &opt).await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Append the relative path `name` to the directory `url`, escaping each path
+/// component.
+fn join_url(url: &Url, name: &str) -> Result<Url> {
+    let mut joined = url.to_owned();
+    joined
+        .path_segments_mut()
+        .map_err(|_| format_err!("cannot append path to {}", url))?
+        .pop_if_empty()
+        .extend(name.split('/'));
+    Ok(joined)
+}
+
+#[test]
+fn join_url_escapes_names() {
+    let url = Url::parse("s3://example/sidecars/").unwrap();
+    assert_eq!(
+        join_url(&url, "sub/a b#1.json").unwrap().as_str(),
+        "s3://example/sidecars/sub/a%20b%231.json",
+    );
+}
+
+/// Implementation of `write_local_data`, but as a real `async` function.
+pub(crate) async fn write_local_data_helper(
+    ctx: Context,
+    locator: FileLocator,
+    data: BoxStream<CsvStream>,
+    shared_args: SharedArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
+    let _shared_args = shared_args.verify(FileLocator::features())?;
+    let dest_args = dest_args.verify(FileLocator::features())?;
+    let if_exists = dest_args.if_exists().to_owned();
+
+    if locator.is_directory() {
+        // Delete existing cloud files if we're overwriting. As with `csv:`, we
+        // leave other local files alone.
+        match &locator.storage {
+            FileStorage::S3(url) => {
+                s3_driver::prepare_as_destination_helper(
+                    ctx.clone(),
+                    url.to_owned(),
+                    if_exists.clone(),
+                    s3::S3Connection::default(),
+                )
+                .await?
+            }
+            FileStorage::Gs(url) => {
+                gs::prepare_as_destination_helper(
+                    ctx.clone(),
+                    url.to_owned(),
+                    if_exists.clone(),
+                )
+                .await?
+            }
+            FileStorage::Local(_) => {}
+        }
+
+        // Write each stream to a separate file.
+        let written = data.map_ok(move |stream| {
+            let ctx = ctx.clone();
+            let locator = locator.clone();
+            let if_exists = if_exists.clone();
+            async move {
+                check_relative_name(&stream.name)?;
+                let dest =
+                    locator.join(&file_name_for_stream(&stream.name, &if_exists))?;
+                let ctx = ctx.child(o!(
+                    "stream" => stream.name.clone(),
+                    "dest" => dest.to_string(),
+                ));
+                // When appending, we've chosen a new, unique file name, so it's
+                // an error if it already exists.
+                let file_if_exists = match if_exists {
+                    IfExists::Append => IfExists::Error,
+                    other => other,
+                };
+                dest.write_file(ctx, stream.data, file_if_exists).await?;
+                Ok(dest.boxed())
+            }
+            .boxed()
+        });
+        Ok(written.boxed())
+    } else {
+        match (&locator.storage, &if_exists) {
+            (_, IfExists::Append) => {
+                return Err(format_err!(
+                    "--if-exists=append is only supported for file: directories \
+                     ending in '/'"
+                ));
+            }
+            (FileStorage::S3(_), IfExists::Error)
+            | (FileStorage::Gs(_), IfExists::Error) => {
+                return Err(format_err!(
+                    "must specify --if-exists=overwrite for {}",
+                    locator,
+                ));
+            }
+            _ => {}
+        }
+
+        // Write a single stream to our file. We refuse to concatenate
+        // multiple files, because we don't know their format.
+        let fut = async move {
+            let mut data = data;
+            let stream = data
+                .try_next()
+                .await?
+                .ok_or_else(|| format_err!("no input files to copy to {}", locator))?;
+            if data.try_next().await?.is_some() {
+                return Err(format_err!(
+                    "cannot copy multiple files to {} (end it with '/' to write \
+                     a directory)",
+                    locator,
+                ));
+            }
+            let ctx = ctx.child(o!(
+                "stream" => stream.name.clone(),
+                "dest" => locator.to_string(),
+            ));
+            locator.write_file(ctx, stream.data, if_exists).await?;
+            Ok(locator.boxed())
+        };
+        Ok(box_stream_once(Ok(fut.boxed())))
+    }
+}
@@ -0,0 +1,250 @@
+//! Generating GraphQL type definitions from our portable schemas.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Write as _,
+};
+
+use crate::common::*;
+use crate::schema::{DataType, StructField};
+use crate::unique_name::unique_name;
+
+/// Generate a GraphQL schema containing a type which matches `table`.
+pub(crate) fn graphql_for_table(table: &Table) -> Result<String> {
+    let mut writer = GraphqlWriter::default();
+    let fields = table
+        .columns
+        .iter()
+        .map(|col| {
+            (
+                StructField {
+                    name: col.name.clone(),
+                    is_nullable: col.is_nullable,
+                    data_type: col.data_type.clone(),
+                },
+                col.comment.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let name = unique_name(&mut writer.type_names, type_name(&table.name), "");
+    writer.object_type(&name, &fields)?;
+
+    let mut out = String::new();
+    writeln!(&mut out, "# Generated by dbcrossbar from {:?}.", table.name)?;
+    if !writer.scalars.is_empty() {
+        writeln!(&mut out)?;
+        for scalar in &writer.scalars {
+            writeln!(&mut out, "scalar {}", scalar)?;
+        }
+    }
+    for object_type in &writer.types {
+        writeln!(&mut out)?;
+        out.push_str(object_type);
+    }
+    Ok(out)
+}
+
+/// State we need while generating a GraphQL schema.
+#[derive(Default)]
+struct GraphqlWriter {
+    /// The custom scalar types that we need to declare.
+    scalars: BTreeSet<&'static str>,
+    /// The types we've generated so far, in the order they should appear.
+    types: Vec<String>,
+    /// The type names we've already used.
+    type_names: HashSet<String>,
+}
+
+impl GraphqlWriter {
+    /// Generate an object type named `name` containing `fields`. Types for any
+    /// nested structs will appear after this one.
+    fn object_type(
+        &mut self,
+        name: &str,
+        fields: &[(StructField, Option<&str>)],
+    ) -> Result<()> {
+        if fields.is_empty() {
+            return Err(format_err!(
+                "cannot create GraphQL type {} with no fields",
+                name
+            ));
+        }
+
+        // Reserve a slot for our type, so that it appears before any types
+        // that it refers to.
+        let idx = self.types.len();
+        self.types.push(String::new());
+
+        let mut out = String::new();
+        writeln!(&mut out, "type {} {{", name)?;
+        let mut field_names = HashSet::new();
+        for (field, comment) in fields {
+            if let Some(comment) = comment {
+                writeln!(&mut out, "  {}", block_string(comment))?;
+            }
+            let field_name =
+                unique_name(&mut field_names, field_name(&field.name), "_");
+            let mut ty = self.graphql_type(name, &field.name, &field.data_type)?;
+            if !field.is_nullable {
+                ty.push('!');
+            }
+            writeln!(&mut out, "  {}: {}", field_name, ty)?;
+        }
+        writeln!(&mut out, "}}")?;
+
+        self.types[idx] = out;
+        Ok(())
+    }
+
+    /// Choose a GraphQL type for `data_type`, which appears in the field
+    /// `field_name` of the type `parent`.
+    fn graphql_type(
+        &mut self,
+        parent: &str,
+        field_name: &str,
+        data_type: &DataType,
+    ) -> Result<String> {
+        Ok(match data_type {
+            // We don't know whether array elements may be null, so we assume
+            // that they can be.
+            DataType::Array(elem_type) => {
+                format!("[{}]", self.graphql_type(parent, field_name, elem_type)?)
+            }
+            DataType::Bool => "Boolean".to_owned(),
+            DataType::Date => self.scalar("Date"),
+            DataType::Decimal | DataType::FixedDecimal { .. } => {
+                self.scalar("BigFloat")
+            }
+            DataType::Float32 | DataType::Float64 => "Float".to_owned(),
+            DataType::GeoJson(_) | DataType::Json => self.scalar("JSON"),
+            // GraphQL's `Int` is a signed 32-bit integer.
+            DataType::Int16 | DataType::Int32 => "Int".to_owned(),
+            DataType::Int64 => self.scalar("BigInt"),
+            DataType::Struct(fields) => {
+                let name = unique_name(
+                    &mut self.type_names,
+                    format!("{}{}", parent, type_name(field_name)),
+                    "",
+                );
+                let fields = fields
+                    .iter()
+                    .map(|f| (f.to_owned(), None))
+                    .collect::<Vec<_>>();
+                self.object_type(&name, &fields)?;
+                name
+            }
+            DataType::Text => "String".to_owned(),
+            DataType::TimestampWithoutTimeZone | DataType::TimestampWithTimeZone => {
+                self.scalar("Datetime")
+            }
+            DataType::Uuid => self.scalar("UUID"),
+        })
+    }
+
+    /// Record that we need the custom scalar type `scalar`, and return it.
+    fn scalar(&mut self, scalar: &'static str) -> String {
+        self.scalars.insert(scalar);
+        scalar.to_owned()
+    }
+}
+
+/// Format `comment` as a GraphQL block string, for use as a description.
+fn block_string(comment: &str) -> String {
+    format!("\"\"\"{}\"\"\"", comment.replace("\"\"\"", "\\\"\"\""))
+}
+
+/// Convert a column name into a valid GraphQL field name.
+fn field_name(name: &str) -> String {
+    let mut result = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    // Names may not start with a digit, and names starting with `__` are
+    // reserved for introspection.
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        || result.starts_with("__")
+    {
+        result.insert(0, 'f');
+    }
+    result
+}
+
+/// Convert a table or column name into a `PascalCase` type name.
+fn type_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut capitalize = true;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if capitalize {
+                result.push(c.to_ascii_uppercase());
+            } else {
+                result.push(c);
+            }
+            capitalize = false;
+        } else {
+            capitalize = true;
+        }
+    }
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        result.insert(0, 'T');
+    }
+    result
+}
+
+#[test]
+fn graphql_for_table_generates_types() {
+    use crate::schema::Column;
+
+    let column = |name: &str, is_nullable: bool, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let table = Table {
+        name: "public.orders".to_owned(),
+        columns: vec![
+            Column {
+                comment: Some("Unique ID".to_owned()),
+                ..column("id", false, DataType::Uuid)
+            },
+            column("total", false, DataType::Decimal),
+            column("quantity", true, DataType::Int32),
+            column("placed_at", true, DataType::TimestampWithTimeZone),
+            column("tags", false, DataType::Array(Box::new(DataType::Text))),
+            column("Customer Name", true, DataType::Text),
+            column(
+                "line_items",
+                false,
+                DataType::Array(Box::new(DataType::Struct(vec![StructField {
+                    name: "sku".to_owned(),
+                    is_nullable: false,
+                    data_type: DataType::Int64,
+                }]))),
+            ),
+        ],
+    };
+    let expected = r#"# Generated by dbcrossbar from "public.orders".
+
+scalar BigFloat
+scalar BigInt
+scalar Datetime
+scalar UUID
+
+type PublicOrders {
+  """Unique ID"""
+  id: UUID!
+  total: BigFloat!
+  quantity: Int
+  placed_at: Datetime
+  tags: [String]!
+  Customer_Name: String
+  line_items: [PublicOrdersLineItems]!
+}
+
+type PublicOrdersLineItems {
+  sku: BigInt!
+}
+"#;
+    assert_eq!(graphql_for_table(&table).unwrap(), expected);
+}
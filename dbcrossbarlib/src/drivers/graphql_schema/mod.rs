@@ -0,0 +1,82 @@
+//! Support for `graphql-schema` locators.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+
+mod graphql;
+
+use self::graphql::graphql_for_table;
+
+/// A GraphQL schema file containing a type which matches our table. This is
+/// only supported as an output.
+#[derive(Clone, Debug)]
+pub struct GraphqlSchemaLocator {
+    path: PathOrStdio,
+}
+
+impl fmt::Display for GraphqlSchemaLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.path.fmt_locator_helper(Self::scheme(), f)
+    }
+}
+
+impl FromStr for GraphqlSchemaLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), s)?;
+        Ok(GraphqlSchemaLocator { path })
+    }
+}
+
+impl Locator for GraphqlSchemaLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn write_schema(
+        &self,
+        ctx: Context,
+        table: Table,
+        if_exists: IfExists,
+    ) -> BoxFuture<()> {
+        write_schema_helper(ctx, self.to_owned(), table, if_exists).boxed()
+    }
+}
+
+impl LocatorStatic for GraphqlSchemaLocator {
+    fn scheme() -> &'static str {
+        "graphql-schema:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::WriteSchema.into(),
+            write_schema_if_exists: IfExistsFeatures::no_append(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
+
+/// Implementation of `write_schema`, but as a real `async` function.
+async fn write_schema_helper(
+    ctx: Context,
+    dest: GraphqlSchemaLocator,
+    table: Table,
+    if_exists: IfExists,
+) -> Result<()> {
+    // Generate our GraphQL before we create our output file.
+    let graphql = graphql_for_table(&table)?;
+
+    // Output our schema to our destination.
+    let mut f = dest.path.create_async(ctx, if_exists).await?;
+    buffer_sync_write_and_copy_to_async(&mut f, |buff| write!(buff, "{}", graphql))
+        .await
+        .with_context(|_| format!("error writing to {}", dest.path))?;
+    f.flush().await?;
+    Ok(())
+}
@@ -1,9 +1,86 @@
 //! Reading data from Google Cloud Storage.
 
+use futures::future;
+use serde::Deserialize;
+
 use super::GsLocator;
-use crate::clouds::gcloud::storage;
+use crate::clouds::gcloud::storage::{self, StorageObject};
 use crate::common::*;
+use crate::compression::{decompress_stream_for_file_name, Compression};
 use crate::csv_stream::csv_stream_name;
+use crate::driver_args::deserialize_opt_from_str;
+use crate::key_pattern::{KeyFilter, KeyPattern};
+use crate::manifest::is_manifest_url;
+
+/// Parsed version of `--from-arg` values.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GsSourceArguments {
+    /// The Google Cloud project to bill for reading requester-pays buckets.
+    billing_project: Option<String>,
+
+    /// Only read files matching this glob pattern, like `shard-*.csv`. If this
+    /// isn't specified, we read all CSV files, including compressed ones.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    include: Option<KeyPattern>,
+
+    /// Skip files matching this glob pattern, like `_SUCCESS`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    exclude: Option<KeyPattern>,
+}
+
+impl GsSourceArguments {
+    /// Parse our `--from-arg` values.
+    fn from_source_args(source_args: &SourceArguments<Verified>) -> Result<Self> {
+        Ok(source_args
+            .driver_args()
+            .deserialize::<GsSourceArguments>()
+            .context("could not parse --from-arg")?)
+    }
+}
+
+/// Should we read `name` when we weren't given an `include` pattern? We
+/// accept `.csv` files and compressed `.csv` files, like `.csv.gz`.
+fn is_csv_file_name(name: &str) -> bool {
+    let name = match Compression::for_file_name(name) {
+        Some(compression) => &name[..name.len() - compression.extension().len()],
+        None => name,
+    };
+    name.to_ascii_lowercase().ends_with(".csv")
+}
+
+#[test]
+fn csv_file_names() {
+    assert!(is_csv_file_name("dir/a.csv"));
+    assert!(is_csv_file_name("dir/A.CSV"));
+    assert!(is_csv_file_name("dir/a.csv.gz"));
+    assert!(!is_csv_file_name("dir/_SUCCESS"));
+    assert!(!is_csv_file_name("dir/a.json"));
+    assert!(!is_csv_file_name("dir/a.gz"));
+}
+
+/// List the files we should read at `url`, skipping any manifests we wrote.
+/// If `include` was specified, we read files matching it. Otherwise, we read
+/// all CSV files. Either way, we skip anything matching `exclude`.
+async fn list_files(
+    ctx: &Context,
+    url: &Url,
+    args: &GsSourceArguments,
+) -> Result<BoxStream<StorageObject>> {
+    let (_, prefix) = storage::parse_gs_url(url)?;
+    let only_csv = args.include.is_none();
+    let key_filter = KeyFilter::new(args.include.clone(), args.exclude.clone());
+    let files = storage::ls(ctx, url, args.billing_project.as_deref())
+        .await?
+        .try_filter(move |item| {
+            future::ready(
+                !is_manifest_url(&item.to_url_string())
+                    && (!only_csv || is_csv_file_name(&item.name))
+                    && key_filter.should_read(&prefix, &item.name),
+            )
+        });
+    Ok(files.boxed())
+}
 
 /// Implementation of `local_data`, but as a real `async` function.
 pub(crate) async fn local_data_helper(
@@ -13,25 +90,36 @@ pub(crate) async fn local_data_helper(
     source_args: SourceArguments<Unverified>,
 ) -> Result<Option<BoxStream<CsvStream>>> {
     let _shared_args = shared_args.verify(GsLocator::features())?;
-    let _source_args = source_args.verify(GsLocator::features())?;
+    let source_args = source_args.verify(GsLocator::features())?;
+    let gs_args = GsSourceArguments::from_source_args(&source_args)?;
     debug!(ctx.log(), "getting CSV files from {}", url);
 
-    let file_urls = storage::ls(&ctx, &url).await?;
+    // List the files at our URL.
+    let file_urls = list_files(&ctx, &url, &gs_args).await?;
 
+    let billing_project = gs_args.billing_project;
     let csv_streams = file_urls.and_then(move |item| {
         let ctx = ctx.clone();
         let url = url.clone();
+        let billing_project = billing_project.clone();
         async move {
             // Stream the file from the cloud.
             let file_url = item.to_url_string();
             let name = csv_stream_name(url.as_str(), &file_url)?;
             let ctx =
                 ctx.child(o!("stream" => name.to_owned(), "url" => file_url.clone()));
-            let data = storage::download_file(&ctx, &item).await?;
+            let metadata = StreamMetadata {
+                modified: item.updated()?,
+                source: Some(file_url.clone()),
+            };
+            let data = storage::download_file(&ctx, &item, billing_project.as_deref())
+                .await?;
+            let data = decompress_stream_for_file_name(&ctx, &file_url, data)?;
 
             // Assemble everything into a CSV stream.
             Ok(CsvStream {
                 name: name.to_owned(),
+                metadata,
                 data,
             })
         }
@@ -40,3 +128,37 @@ pub(crate) async fn local_data_helper(
 
     Ok(Some(csv_streams.boxed()))
 }
+
+/// Implementation of `size_hint`, using a directory listing.
+pub(crate) async fn size_hint_helper(
+    ctx: Context,
+    url: Url,
+    source_args: SourceArguments<Unverified>,
+) -> Result<SizeHint> {
+    let source_args = source_args.verify(GsLocator::features())?;
+    let gs_args = GsSourceArguments::from_source_args(&source_args)?;
+    let sizes = list_files(&ctx, &url, &gs_args)
+        .await?
+        .map_ok(|item| item.size)
+        .try_collect::<Vec<_>>()
+        .await?;
+    Ok(SizeHint::from_sizes(sizes))
+}
+
+#[test]
+fn parse_include_and_exclude_args() {
+    let args =
+        DriverArguments::from_cli_args(&["include=shard-*.csv", "exclude=_SUCCESS"])
+            .unwrap()
+            .deserialize::<GsSourceArguments>()
+            .unwrap();
+    assert!(args.include.unwrap().matches("shard-0001.csv"));
+    assert!(args.exclude.unwrap().matches("2024/_SUCCESS"));
+    let args = DriverArguments::from_cli_args(&["billing_project=my-project"])
+        .unwrap()
+        .deserialize::<GsSourceArguments>()
+        .unwrap();
+    assert_eq!(args.billing_project.as_deref(), Some("my-project"));
+    let args = DriverArguments::from_cli_args(&["include=["]).unwrap();
+    assert!(args.deserialize::<GsSourceArguments>().is_err());
+}
@@ -2,29 +2,33 @@
 
 use std::{fmt, str::FromStr};
 
+use crate::clouds::gcloud::storage::KmsKeyName;
 use crate::common::*;
+#[cfg(feature = "bigquery")]
 use crate::drivers::bigquery::BigQueryLocator;
 
 mod local_data;
 mod prepare_as_destination;
+#[cfg(feature = "bigquery")]
+mod temporary;
 mod write_local_data;
+#[cfg(feature = "bigquery")]
 mod write_remote_data;
 
-use local_data::local_data_helper;
+use local_data::{local_data_helper, size_hint_helper};
 pub(crate) use prepare_as_destination::prepare_as_destination_helper;
+#[cfg(feature = "bigquery")]
+pub(crate) use temporary::find_gs_temp_dir;
 use write_local_data::write_local_data_helper;
+#[cfg(feature = "bigquery")]
 use write_remote_data::write_remote_data_helper;
 
 #[derive(Clone, Debug)]
 pub(crate) struct GsLocator {
     url: Url,
-}
-
-impl GsLocator {
-    /// Access the `gs://` URL in this locator.
-    pub(crate) fn as_url(&self) -> &Url {
-        &self.url
-    }
+    /// The KMS key to use when writing to this locator, if it was specified
+    /// as part of a temporary storage URL.
+    kms_key: Option<KmsKeyName>,
 }
 
 impl fmt::Display for GsLocator {
@@ -46,7 +50,7 @@ impl FromStr for GsLocator {
             } else if !url.path().ends_with('/') {
                 Err(format_err!("{} must end with a '/'", url))
             } else {
-                Ok(GsLocator { url })
+                Ok(GsLocator { url, kms_key: None })
             }
         } else {
             Err(format_err!("expected {} to begin with gs://", s))
@@ -68,6 +72,14 @@ impl Locator for GsLocator {
         local_data_helper(ctx, self.url.clone(), shared_args, source_args).boxed()
     }
 
+    fn size_hint(
+        &self,
+        ctx: Context,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<SizeHint> {
+        size_hint_helper(ctx, self.url.clone(), source_args).boxed()
+    }
+
     fn write_local_data(
         &self,
         ctx: Context,
@@ -75,16 +87,18 @@ impl Locator for GsLocator {
         shared_args: SharedArguments<Unverified>,
         dest_args: DestinationArguments<Unverified>,
     ) -> BoxFuture<BoxStream<BoxFuture<BoxLocator>>> {
-        write_local_data_helper(ctx, self.url.clone(), data, shared_args, dest_args)
+        write_local_data_helper(ctx, self.clone(), data, shared_args, dest_args)
             .boxed()
     }
 
+    #[cfg(feature = "bigquery")]
     fn supports_write_remote_data(&self, source: &dyn Locator) -> bool {
         // We can only do `write_remote_data` if `source` is a `BigQueryLocator`.
         // Otherwise, we need to do `write_local_data` like normal.
         source.as_any().is::<BigQueryLocator>()
     }
 
+    #[cfg(feature = "bigquery")]
     fn write_remote_data(
         &self,
         ctx: Context,
@@ -114,27 +128,10 @@ impl LocatorStatic for GsLocator {
         Features {
             locator: LocatorFeatures::LocalData | LocatorFeatures::WriteLocalData,
             write_schema_if_exists: EnumSet::empty(),
-            source_args: EnumSet::empty(),
-            dest_args: EnumSet::empty(),
-            dest_if_exists: IfExistsFeatures::Overwrite.into(),
+            source_args: SourceArgumentsFeatures::DriverArgs.into(),
+            dest_args: DestinationArgumentsFeatures::DriverArgs.into(),
+            dest_if_exists: IfExistsFeatures::Overwrite | IfExistsFeatures::Append,
             _placeholder: (),
         }
     }
 }
-
-/// Given a `TemporaryStorage`, extract a unique `gs://` temporary directory,
-/// including a random component.
-pub(crate) fn find_gs_temp_dir(
-    temporary_storage: &TemporaryStorage,
-) -> Result<GsLocator> {
-    let mut temp = temporary_storage
-        .find_scheme(GsLocator::scheme())
-        .ok_or_else(|| format_err!("need `--temporary=gs://...` argument"))?
-        .to_owned();
-    if !temp.ends_with('/') {
-        temp.push_str("/");
-    }
-    temp.push_str(&TemporaryStorage::random_tag());
-    temp.push_str("/");
-    GsLocator::from_str(&temp)
-}
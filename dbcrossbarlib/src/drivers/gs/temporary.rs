@@ -0,0 +1,56 @@
+//! Temporary `gs://` directories, which BigQuery uses to stage data.
+
+use std::str::FromStr;
+
+use super::GsLocator;
+use crate::clouds::gcloud::storage::{self, KmsKeyName};
+use crate::common::*;
+
+impl GsLocator {
+    /// Delete this temporary directory once our copy has finished, whether or
+    /// not it succeeds.
+    pub(crate) fn delete_when_done(&self, temporary_storage: &TemporaryStorage) {
+        let url = self.url.clone();
+        temporary_storage.delete_when_done(url.to_string(), move |ctx| {
+            async move { storage::rmdir(&ctx, &url).await }.boxed()
+        });
+    }
+}
+
+/// Given a `TemporaryStorage`, extract a unique `gs://` temporary directory,
+/// including a random component. The temporary storage URL may specify a KMS
+/// key, as in `gs://bucket/tmp/?kms_key=projects/...`.
+pub(crate) fn find_gs_temp_dir(
+    temporary_storage: &TemporaryStorage,
+) -> Result<GsLocator> {
+    let temp = temporary_storage
+        .find_scheme(GsLocator::scheme())
+        .ok_or_else(|| format_err!("need `--temporary=gs://...` argument"))?;
+    let mut url = temp
+        .parse::<Url>()
+        .with_context(|_| format!("cannot parse {}", temp))?;
+    let kms_key = KmsKeyName::from_url_query(&url)?;
+    url.set_query(None);
+    let mut temp = url.to_string();
+    if !temp.ends_with('/') {
+        temp.push_str("/");
+    }
+    temp.push_str(&TemporaryStorage::temp_dir_name());
+    temp.push_str("/");
+    let mut locator = GsLocator::from_str(&temp)?;
+    locator.kms_key = kms_key;
+    Ok(locator)
+}
+
+#[test]
+fn find_gs_temp_dir_with_kms_key() {
+    let key = "projects/p/locations/us/keyRings/r/cryptoKeys/etl";
+    let storage =
+        TemporaryStorage::new(vec![format!("gs://example/tmp?kms_key={}", key)]);
+    let temp = find_gs_temp_dir(&storage).unwrap();
+    assert!(temp
+        .to_string()
+        .starts_with("gs://example/tmp/dbcrossbar-tmp-"));
+    assert!(temp.to_string().ends_with('/'));
+    assert_eq!(temp.kms_key.unwrap().as_str(), key);
+}
@@ -1,13 +1,74 @@
 //! Writing data to Google Cloud Storage.
 
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
 use super::{prepare_as_destination_helper, GsLocator};
-use crate::clouds::gcloud::storage;
+use crate::clouds::gcloud::storage::{self, KmsKeyName, UploadOptions};
 use crate::common::*;
+use crate::compression::Compression;
+use crate::csv_stream::csv_stream_file_name;
+use crate::driver_args::deserialize_opt_from_str;
+use crate::manifest::{
+    manifest_file_name, write_manifest_when_done, FileTracker, Manifest,
+};
+use crate::object_metadata::{CustomMetadata, ObjectMetadata};
+use crate::partition::partition_csv_streams;
+use crate::rechunk::{split_csv_streams, ChunkLimits};
+
+/// Parsed version of `--to-arg` values.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GsDestinationArguments {
+    /// How should we compress the files we write?
+    compression: Option<Compression>,
+
+    /// Split each output stream into files of approximately this size, like
+    /// `256MB`.
+    max_file_size: Option<String>,
+
+    /// Split each output stream into files with at most this many rows.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    max_rows_per_file: Option<usize>,
+
+    /// Split our output into Hive-style directories like `event_date=...`
+    /// using the values of this column.
+    partition_by: Option<String>,
+
+    /// Should we write a `manifest.json` file listing the files we wrote?
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    manifest: Option<bool>,
+
+    /// A Cloud KMS key to encrypt the objects we write, like
+    /// `projects/p/locations/us/keyRings/r/cryptoKeys/k`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    kms_key: Option<KmsKeyName>,
+
+    /// How much data to send in each request when uploading a file, like
+    /// `16MiB`. This must be a multiple of 256 KiB.
+    upload_chunk_size: Option<String>,
+
+    /// The maximum number of files to upload at once.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    max_concurrent_uploads: Option<usize>,
+
+    /// The `Content-Type` of the objects we write, like `text/csv`.
+    content_type: Option<String>,
+
+    /// The `Content-Encoding` of the objects we write, like `gzip`.
+    content_encoding: Option<String>,
+
+    /// Custom metadata to attach to every object we write, like
+    /// `source=etl,owner=data`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    metadata: Option<CustomMetadata>,
+}
 
 /// Implementation of `write_local_data`, but as a real `async` function.
 pub(crate) async fn write_local_data_helper(
     ctx: Context,
-    url: Url,
+    dest: GsLocator,
     data: BoxStream<CsvStream>,
     shared_args: SharedArguments<Unverified>,
     dest_args: DestinationArguments<Unverified>,
@@ -15,24 +76,141 @@ pub(crate) async fn write_local_data_helper(
     let _shared_args = shared_args.verify(GsLocator::features())?;
     let dest_args = dest_args.verify(GsLocator::features())?;
 
-    // Delete the existing output, if it exists.
+    // Look up our arguments.
+    let args = dest_args
+        .driver_args()
+        .deserialize::<GsDestinationArguments>()
+        .context("could not parse --to-arg")?;
+    let url = dest.url;
+    let compression = args.compression;
+    // A `--to-arg` takes precedence over a key from our temporary storage URL.
+    let kms_key = args.kms_key.or(dest.kms_key);
+    let metadata =
+        ObjectMetadata::new(args.content_type, args.content_encoding, args.metadata)?;
+    // Our manifest is JSON, so it doesn't get the metadata for our CSV files.
+    let manifest_opt = UploadOptions::new(kms_key, args.upload_chunk_size.as_deref())?;
+    let upload_opt = Arc::new(manifest_opt.clone().with_metadata(metadata));
+    let upload_slots = match args.max_concurrent_uploads {
+        Some(0) => {
+            return Err(format_err!("max_concurrent_uploads must be at least 1"))
+        }
+        Some(max) => Some(Arc::new(Semaphore::new(max))),
+        None => None,
+    };
+    let write_manifest = args.manifest.unwrap_or(false);
+    let chunk_limits = ChunkLimits::from_driver_args(
+        args.max_file_size.as_deref(),
+        args.max_rows_per_file,
+    )?;
+
+    // Delete the existing output, if it exists and we're not appending.
     let if_exists = dest_args.if_exists().to_owned();
-    prepare_as_destination_helper(ctx.clone(), url.clone(), if_exists).await?;
+    prepare_as_destination_helper(ctx.clone(), url.clone(), if_exists.clone()).await?;
+
+    // Partition our streams or split them into smaller files if we were
+    // asked to.
+    let data = match args.partition_by {
+        Some(column) => {
+            partition_csv_streams(ctx.clone(), column, chunk_limits, data).await?
+        }
+        None => split_csv_streams(ctx.clone(), chunk_limits, data),
+    };
+
+    // Prepare to write a manifest once all our files are written, if we were
+    // asked to.
+    let manifest_writer = if write_manifest {
+        let ctx = ctx.clone();
+        let url = url.join(&manifest_file_name(&if_exists))?;
+        Some(move |manifest: Manifest| -> BoxFuture<BoxLocator> {
+            async move {
+                let data = box_stream_once(Ok(manifest.to_json_bytes()?));
+                storage::upload_file(&ctx, data, &url, &manifest_opt).await?;
+                let kms_key = manifest_opt.kms_key().cloned();
+                Ok(GsLocator { url, kms_key }.boxed())
+            }
+            .boxed()
+        })
+    } else {
+        None
+    };
 
     // Spawn our uploader processes.
     let written = data.map_ok(move |stream| {
         let url = url.clone();
         let ctx = ctx.clone();
+        let if_exists = if_exists.clone();
+        let upload_opt = upload_opt.clone();
+        let upload_slots = upload_slots.clone();
         async move {
-            let url = url.join(&format!("{}.csv", stream.name))?;
+            let mut file_name = csv_stream_file_name(&stream.name, &if_exists);
+            let tracker = FileTracker::new(write_manifest);
+            let mut data = tracker.count_rows(stream.data);
+            if let Some(compression) = compression {
+                file_name = compression.add_extension(&file_name);
+                data = compression.compress_stream(&ctx, data)?;
+            }
+            let data = tracker.checksum(data);
+            let url = url.join(&file_name)?;
             let ctx = ctx
                 .child(o!("stream" => stream.name.clone(), "url" => url.to_string()));
 
-            storage::upload_file(&ctx, stream.data, &url).await?;
-            Ok(GsLocator { url }.boxed())
+            // Wait for an upload slot, if we were asked to limit them.
+            let _permit = match &upload_slots {
+                Some(slots) => Some(slots.acquire().await),
+                None => None,
+            };
+            storage::upload_file(&ctx, data, &url, &upload_opt).await?;
+            let entry = tracker.finish(&url);
+            let kms_key = upload_opt.kms_key().cloned();
+            Ok((GsLocator { url, kms_key }.boxed(), entry))
         }
         .boxed()
     });
 
-    Ok(written.boxed())
+    Ok(write_manifest_when_done(written.boxed(), manifest_writer))
+}
+
+#[test]
+fn parse_kms_key_and_upload_args() {
+    let key = "projects/p/locations/us/keyRings/r/cryptoKeys/etl";
+    let args = DriverArguments::from_cli_args(&[format!("kms_key={}", key)])
+        .unwrap()
+        .deserialize::<GsDestinationArguments>()
+        .unwrap();
+    assert_eq!(args.kms_key.unwrap().as_str(), key);
+    let args = DriverArguments::from_cli_args(&[
+        "upload_chunk_size=16MiB",
+        "max_concurrent_uploads=16",
+    ])
+    .unwrap()
+    .deserialize::<GsDestinationArguments>()
+    .unwrap();
+    assert_eq!(args.upload_chunk_size.as_deref(), Some("16MiB"));
+    assert_eq!(args.max_concurrent_uploads, Some(16));
+    assert!(DriverArguments::from_cli_args(&["kms_key=etl"])
+        .unwrap()
+        .deserialize::<GsDestinationArguments>()
+        .is_err());
+}
+
+#[test]
+fn parse_metadata_args() {
+    let args = DriverArguments::from_cli_args(&[
+        "content_type=text/csv",
+        "content_encoding=gzip",
+        "metadata=source=etl,owner=data",
+    ])
+    .unwrap()
+    .deserialize::<GsDestinationArguments>()
+    .unwrap();
+    assert_eq!(args.content_type.as_deref(), Some("text/csv"));
+    assert_eq!(args.content_encoding.as_deref(), Some("gzip"));
+    assert_eq!(
+        args.metadata,
+        Some("source=etl,owner=data".parse::<CustomMetadata>().unwrap()),
+    );
+    assert!(DriverArguments::from_cli_args(&["metadata=source"])
+        .unwrap()
+        .deserialize::<GsDestinationArguments>()
+        .is_err());
 }
@@ -8,6 +8,13 @@ use crate::drivers::{
     bigquery_shared::{BqTable, GCloudDriverArguments, Usage},
 };
 
+impl GsLocator {
+    /// Access the `gs://` URL in this locator.
+    pub(crate) fn as_url(&self) -> &Url {
+        &self.url
+    }
+}
+
 /// Copy `source` to `dest` using `schema`.
 ///
 /// The function `BigQueryLocator::write_remote_data` isn't (yet) allowed to be
@@ -39,14 +46,34 @@ pub(crate) async fn write_remote_data_helper(
     let schema = shared_args.schema();
     let temporary_storage = shared_args.temporary_storage();
     let if_exists = dest_args.if_exists().to_owned();
+    if if_exists == IfExists::Append {
+        // `bq extract` chooses its own file names, which might replace
+        // existing files.
+        return Err(format_err!(
+            "cannot use --if-exists=append when extracting from {}",
+            source,
+        ));
+    }
+    if !dest_args.driver_args().is_empty() {
+        // We don't compress data which never passes through `dbcrossbar`.
+        return Err(format_err!(
+            "cannot use --to-arg when extracting from {}",
+            source,
+        ));
+    }
 
     // Get our billing labels.
-    let job_labels = source_args
+    let args = source_args
         .driver_args()
         .deserialize::<GCloudDriverArguments>()
-        .context("error parsing --from-args")?
-        .job_labels
-        .to_owned();
+        .context("error parsing --from-args")?;
+    let job_labels = args.job_labels.to_owned();
+
+    // Figure out where to run our jobs.
+    let location = match &args.location {
+        Some(location) => location.to_owned(),
+        None => bigquery::dataset_location(&ctx, &source_table_name).await?,
+    };
 
     // Construct a `BqTable` describing our source table.
     let source_table = BqTable::for_table_name_and_columns(
@@ -75,8 +102,10 @@ pub(crate) async fn write_remote_data_helper(
     // Run our query.
     bigquery::query_to_table(
         &ctx,
-        source.project(),
+        args.job_project(source.project()),
+        &location,
         &export_sql,
+        source_args.query_params(),
         &temp_table_name,
         &IfExists::Overwrite,
         &job_labels,
@@ -88,9 +117,24 @@ pub(crate) async fn write_remote_data_helper(
         .await?;
 
     // Build and run a `bq extract` command.
-    bigquery::extract(&ctx, &temp_table_name, dest.as_url(), &job_labels).await?;
+    bigquery::extract(
+        &ctx,
+        args.job_project(source.project()),
+        &temp_table_name,
+        &location,
+        dest.as_url(),
+        &job_labels,
+    )
+    .await?;
 
     // Delete temp table.
-    bigquery::drop_table(&ctx, &temp_table_name, &job_labels).await?;
+    bigquery::drop_table(
+        &ctx,
+        args.job_project(source.project()),
+        &temp_table_name,
+        &location,
+        &job_labels,
+    )
+    .await?;
     Ok(vec![dest.boxed()])
 }
@@ -0,0 +1,197 @@
+//! Running HiveQL using the `beeline` CLI tool.
+
+use std::process::Stdio;
+use tokio::process::Command;
+
+use super::{data_type::parse_hive_type, quote_identifier, quote_string, HiveLocator};
+use crate::common::*;
+use crate::schema::Column;
+
+/// A client which runs HiveQL statements using `beeline`.
+pub(crate) struct Beeline {
+    /// Our JDBC URL.
+    jdbc_url: String,
+    /// The user to connect as.
+    user: Option<String>,
+    /// The password to connect with.
+    password: Option<String>,
+}
+
+impl Beeline {
+    /// Create a new client for the HiveServer2 at `host:port`.
+    pub(crate) fn new(
+        host: &str,
+        port: u16,
+        database: &str,
+        user: &str,
+        password: Option<&str>,
+    ) -> Self {
+        Beeline {
+            jdbc_url: format!("jdbc:hive2://{}:{}/{}", host, port, database),
+            user: if user.is_empty() {
+                None
+            } else {
+                Some(user.to_owned())
+            },
+            password: password.map(|p| p.to_owned()),
+        }
+    }
+
+    /// Run `sql` and return the rows of output as tab-separated lines.
+    pub(crate) async fn query(&self, ctx: &Context, sql: &str) -> Result<Vec<String>> {
+        debug!(ctx.log(), "running HiveQL: {}", sql);
+        let mut command = Command::new("beeline");
+        command.args(&["-u", &self.jdbc_url]);
+        if let Some(user) = &self.user {
+            command.args(&["-n", user]);
+        }
+        if let Some(password) = &self.password {
+            command.args(&["-p", password]);
+        }
+        let output = command
+            .args(&["--silent=true", "--showHeader=false", "--outputformat=tsv2"])
+            .args(&["-e", sql])
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .context("error running `beeline` (is it installed?)")?;
+        if !output.status.success() {
+            return Err(format_err!(
+                "`beeline` failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            ));
+        }
+        let stdout = String::from_utf8(output.stdout)
+            .context("`beeline` output was not valid UTF-8")?;
+        Ok(stdout
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.to_owned())
+            .collect())
+    }
+
+    /// Run `sql`, ignoring any output.
+    pub(crate) async fn execute(&self, ctx: &Context, sql: &str) -> Result<()> {
+        self.query(ctx, sql).await?;
+        Ok(())
+    }
+
+    /// Does the table at `locator` exist?
+    pub(crate) async fn table_exists(
+        &self,
+        ctx: &Context,
+        locator: &HiveLocator,
+    ) -> Result<bool> {
+        let sql = format!(
+            "SHOW TABLES IN {} LIKE {}",
+            quote_identifier(&locator.database),
+            quote_string(&locator.table),
+        );
+        let rows = self.query(ctx, &sql).await?;
+        Ok(rows
+            .iter()
+            .any(|row| row.trim().eq_ignore_ascii_case(&locator.table)))
+    }
+
+    /// Look up the storage location of the table at `locator`.
+    pub(crate) async fn table_location(
+        &self,
+        ctx: &Context,
+        locator: &HiveLocator,
+    ) -> Result<String> {
+        let sql = format!("DESCRIBE FORMATTED {}", locator.quoted_table_name());
+        let rows = self.query(ctx, &sql).await?;
+        parse_location(&rows)
+            .ok_or_else(|| format_err!("could not find location of {}", locator))
+    }
+
+    /// Look up the columns of the table at `locator`.
+    pub(crate) async fn describe_columns(
+        &self,
+        ctx: &Context,
+        locator: &HiveLocator,
+    ) -> Result<Vec<Column>> {
+        let sql = format!("DESCRIBE {}", locator.quoted_table_name());
+        let rows = self.query(ctx, &sql).await?;
+        Ok(parse_columns(&rows)
+            .with_context(|_| format!("could not get columns of {}", locator))?)
+    }
+}
+
+/// Find the `Location:` row in the output of `DESCRIBE FORMATTED`.
+fn parse_location(rows: &[String]) -> Option<String> {
+    rows.iter().find_map(|row| {
+        let mut fields = row.split('\t');
+        match (fields.next(), fields.next()) {
+            (Some(name), Some(value)) if name.trim() == "Location:" => {
+                Some(value.trim().to_owned())
+            }
+            _ => None,
+        }
+    })
+}
+
+#[test]
+fn parse_location_finds_location() {
+    let rows = vec![
+        "# col_name\tdata_type\tcomment".to_owned(),
+        "a\tint\t".to_owned(),
+        "Database:\tdb\tNULL".to_owned(),
+        "Location:\thdfs://nn:8020/warehouse/db.db/t\tNULL".to_owned(),
+    ];
+    assert_eq!(
+        parse_location(&rows).unwrap(),
+        "hdfs://nn:8020/warehouse/db.db/t"
+    );
+}
+
+/// Parse the output of `DESCRIBE`. Partition columns are listed along with
+/// the other columns, and then listed again in a separate section, which we
+/// ignore.
+fn parse_columns(rows: &[String]) -> Result<Vec<Column>> {
+    let mut columns = vec![];
+    for row in rows {
+        let mut fields = row.split('\t');
+        let name = fields.next().unwrap_or("").trim();
+        if name.is_empty() || name.starts_with('#') {
+            break;
+        }
+        let data_type = fields
+            .next()
+            .ok_or_else(|| format_err!("no type for column {:?}", name))?;
+        let comment = fields
+            .next()
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty() && *c != "NULL");
+        columns.push(Column {
+            name: name.to_owned(),
+            is_nullable: true,
+            data_type: parse_hive_type(data_type.trim())?,
+            comment: comment.map(|c| c.to_owned()),
+        });
+    }
+    Ok(columns)
+}
+
+#[test]
+fn parse_columns_stops_at_partition_info() {
+    use crate::schema::DataType;
+
+    let rows = vec![
+        "id\tbigint\tThe ID".to_owned(),
+        "tags\tarray<string>\t".to_owned(),
+        "dt\tstring\t".to_owned(),
+        "\tNULL\tNULL".to_owned(),
+        "# Partition Information\tNULL\tNULL".to_owned(),
+        "dt\tstring\t".to_owned(),
+    ];
+    let columns = parse_columns(&rows).unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_eq!(columns[0].data_type, DataType::Int64);
+    assert_eq!(columns[0].comment.as_deref(), Some("The ID"));
+    assert_eq!(
+        columns[1].data_type,
+        DataType::Array(Box::new(DataType::Text))
+    );
+}
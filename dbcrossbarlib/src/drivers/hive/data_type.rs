@@ -0,0 +1,290 @@
+//! Converting between Hive and portable data types.
+
+use super::quote_identifier;
+use crate::common::*;
+use crate::schema::{DataType, StructField};
+
+/// Parse a Hive data type, as printed by `DESCRIBE`.
+pub(crate) fn parse_hive_type(s: &str) -> Result<DataType> {
+    let mut parser = TypeParser { input: s, pos: 0 };
+    let data_type = parser
+        .data_type()
+        .with_context(|_| format!("could not parse Hive type {:?}", s))?;
+    parser.skip_whitespace();
+    if parser.pos == s.len() {
+        Ok(data_type)
+    } else {
+        Err(format_err!(
+            "unexpected {:?} in Hive type {:?}",
+            &s[parser.pos..],
+            s
+        ))
+    }
+}
+
+/// Choose the Hive type to use when storing `data_type` in a CSV file.
+///
+/// Arrays, structs and other complex types are stored as JSON strings, which
+/// is how they're represented in our CSV interchange format.
+pub(crate) fn hive_csv_column_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Bool => "BOOLEAN".to_owned(),
+        DataType::Date => "DATE".to_owned(),
+        DataType::Decimal => "DECIMAL(38,9)".to_owned(),
+        // Hive decimals have at most 38 digits, so store anything larger as a
+        // string instead of rounding it.
+        DataType::FixedDecimal { precision, scale } if *precision <= 38 => {
+            format!("DECIMAL({},{})", precision, scale)
+        }
+        DataType::Float32 => "FLOAT".to_owned(),
+        DataType::Float64 => "DOUBLE".to_owned(),
+        DataType::Int16 => "SMALLINT".to_owned(),
+        DataType::Int32 => "INT".to_owned(),
+        DataType::Int64 => "BIGINT".to_owned(),
+        DataType::TimestampWithoutTimeZone | DataType::TimestampWithTimeZone => {
+            "TIMESTAMP".to_owned()
+        }
+        DataType::Array(_)
+        | DataType::FixedDecimal { .. }
+        | DataType::GeoJson(_)
+        | DataType::Json
+        | DataType::Struct(_)
+        | DataType::Text
+        | DataType::Uuid => "STRING".to_owned(),
+    }
+}
+
+/// Choose the Hive type to use when storing `data_type` in a typed file format
+/// like Parquet or ORC, which supports nested types.
+pub(crate) fn hive_column_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Array(elem_type) => {
+            format!("ARRAY<{}>", hive_column_type(elem_type))
+        }
+        DataType::Struct(fields) => {
+            let fields = fields
+                .iter()
+                .map(|f| {
+                    format!(
+                        "{}:{}",
+                        quote_identifier(&f.name),
+                        hive_column_type(&f.data_type)
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("STRUCT<{}>", fields.join(","))
+        }
+        DataType::GeoJson(_) | DataType::Json => "STRING".to_owned(),
+        other => hive_csv_column_type(other),
+    }
+}
+
+/// A simple recursive-descent parser for Hive types.
+struct TypeParser<'a> {
+    /// The type we're parsing.
+    input: &'a str,
+    /// Our current position in `input`.
+    pos: usize,
+}
+
+impl<'a> TypeParser<'a> {
+    /// Skip any whitespace.
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume `c` if it's the next non-whitespace character.
+    fn consume(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.input[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Require `c` to be the next non-whitespace character.
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.consume(c) {
+            Ok(())
+        } else {
+            Err(format_err!("expected {:?} at position {}", c, self.pos))
+        }
+    }
+
+    /// Parse an identifier, which may be quoted using backticks.
+    fn identifier(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        if rest.starts_with('`') {
+            let end = rest[1..]
+                .find('`')
+                .ok_or_else(|| format_err!("unterminated quoted identifier"))?;
+            self.pos += end + 2;
+            Ok(rest[1..=end].to_owned())
+        } else {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or_else(|| rest.len());
+            if len == 0 {
+                return Err(format_err!("expected name at position {}", self.pos));
+            }
+            self.pos += len;
+            Ok(rest[..len].to_owned())
+        }
+    }
+
+    /// Consume a sequence of keywords, if present, and return true if we did.
+    fn keywords(&mut self, keywords: &[&str]) -> bool {
+        let start = self.pos;
+        for &keyword in keywords {
+            match self.identifier() {
+                Ok(ref word) if word.eq_ignore_ascii_case(keyword) => {}
+                _ => {
+                    self.pos = start;
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Skip any parameters like `(10,2)`.
+    fn skip_parameters(&mut self) -> Result<()> {
+        if self.consume('(') {
+            let rest = &self.input[self.pos..];
+            let end = rest
+                .find(')')
+                .ok_or_else(|| format_err!("expected ')' after parameters"))?;
+            self.pos += end + 1;
+        }
+        Ok(())
+    }
+
+    /// Parse a data type.
+    fn data_type(&mut self) -> Result<DataType> {
+        let name = self.identifier()?.to_ascii_lowercase();
+        match &name[..] {
+            "array" => {
+                self.expect('<')?;
+                let element_type = self.data_type()?;
+                self.expect('>')?;
+                Ok(DataType::Array(Box::new(element_type)))
+            }
+            "bigint" => Ok(DataType::Int64),
+            "boolean" => Ok(DataType::Bool),
+            "char" | "varchar" => {
+                self.skip_parameters()?;
+                Ok(DataType::Text)
+            }
+            "date" => Ok(DataType::Date),
+            "decimal" | "numeric" => {
+                self.skip_parameters()?;
+                Ok(DataType::Decimal)
+            }
+            "double" => {
+                self.keywords(&["precision"]);
+                Ok(DataType::Float64)
+            }
+            "float" => Ok(DataType::Float32),
+            "int" | "integer" => Ok(DataType::Int32),
+            "map" => {
+                // We have no map type, so represent maps as JSON objects.
+                self.expect('<')?;
+                self.data_type()?;
+                self.expect(',')?;
+                self.data_type()?;
+                self.expect('>')?;
+                Ok(DataType::Json)
+            }
+            "smallint" | "tinyint" => Ok(DataType::Int16),
+            "string" => Ok(DataType::Text),
+            "struct" => {
+                self.expect('<')?;
+                let mut fields = vec![];
+                loop {
+                    let name = self.identifier()?;
+                    self.expect(':')?;
+                    let data_type = self.data_type()?;
+                    fields.push(StructField {
+                        name,
+                        is_nullable: true,
+                        data_type,
+                    });
+                    if !self.consume(',') {
+                        break;
+                    }
+                }
+                self.expect('>')?;
+                Ok(DataType::Struct(fields))
+            }
+            "timestamp" => {
+                if self.keywords(&["with", "local", "time", "zone"]) {
+                    Ok(DataType::TimestampWithTimeZone)
+                } else {
+                    Ok(DataType::TimestampWithoutTimeZone)
+                }
+            }
+            _ => Err(format_err!("unsupported Hive type {:?}", name)),
+        }
+    }
+}
+
+#[test]
+fn parse_hive_types() {
+    let examples = vec![
+        ("bigint", DataType::Int64),
+        ("BOOLEAN", DataType::Bool),
+        ("varchar(20)", DataType::Text),
+        ("decimal(10, 2)", DataType::Decimal),
+        ("double precision", DataType::Float64),
+        ("timestamp", DataType::TimestampWithoutTimeZone),
+        (
+            "timestamp with local time zone",
+            DataType::TimestampWithTimeZone,
+        ),
+        ("map<string,int>", DataType::Json),
+        (
+            "array<struct<a:int,`b c`:array<string>>>",
+            DataType::Array(Box::new(DataType::Struct(vec![
+                StructField {
+                    name: "a".to_owned(),
+                    is_nullable: true,
+                    data_type: DataType::Int32,
+                },
+                StructField {
+                    name: "b c".to_owned(),
+                    is_nullable: true,
+                    data_type: DataType::Array(Box::new(DataType::Text)),
+                },
+            ]))),
+        ),
+    ];
+    for (hive_type, expected) in examples {
+        assert_eq!(parse_hive_type(hive_type).unwrap(), expected);
+    }
+    for bad in &["binary", "array<int", "int extra", "struct<>"] {
+        assert!(parse_hive_type(bad).is_err());
+    }
+}
+
+#[test]
+fn hive_column_types_can_be_parsed() {
+    let data_type = DataType::Array(Box::new(DataType::Struct(vec![
+        StructField {
+            name: "a".to_owned(),
+            is_nullable: true,
+            data_type: DataType::Int32,
+        },
+        StructField {
+            name: "b c".to_owned(),
+            is_nullable: true,
+            data_type: DataType::Array(Box::new(DataType::Text)),
+        },
+    ])));
+    let hive_type = hive_column_type(&data_type);
+    assert_eq!(hive_type, "ARRAY<STRUCT<`a`:INT,`b c`:ARRAY<STRING>>>");
+    assert_eq!(parse_hive_type(&hive_type).unwrap(), data_type);
+}
@@ -0,0 +1,122 @@
+//! Reading and writing HDFS files using the `hdfs dfs` CLI tool.
+
+use std::process::Stdio;
+use tokio::{io::BufReader, process::Command};
+
+use crate::common::*;
+use crate::process::wait_for_process;
+use crate::tokio_glue::{copy_reader_to_stream, copy_stream_to_writer};
+
+/// Build an `hdfs dfs` command with the specified arguments.
+fn hdfs_dfs(args: &[&str]) -> Command {
+    let mut command = Command::new("hdfs");
+    command.arg("dfs").args(args);
+    command
+}
+
+/// Run `hdfs dfs` with `args`, and fail if it fails.
+async fn run_hdfs_dfs(ctx: &Context, args: &[&str]) -> Result<String> {
+    debug!(ctx.log(), "running `hdfs dfs {}`", args.join(" "));
+    let output = hdfs_dfs(args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("error running `hdfs dfs` (is it installed?)")?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(format_err!(
+            "`hdfs dfs {}` failed with {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim(),
+        ))
+    }
+}
+
+/// Recursively list all the files under `dir`.
+pub(crate) async fn ls(ctx: &Context, dir: &str) -> Result<Vec<String>> {
+    let output = run_hdfs_dfs(ctx, &["-ls", "-R", dir]).await?;
+    Ok(parse_ls_output(&output))
+}
+
+/// Extract file paths from the output of `hdfs dfs -ls -R`. Each line looks
+/// like `ls -l` output, with the path in the last column.
+fn parse_ls_output(output: &str) -> Vec<String> {
+    let mut files = output
+        .lines()
+        .filter(|line| line.starts_with('-'))
+        .filter_map(|line| line.split_whitespace().nth(7))
+        .map(|path| path.to_owned())
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+#[test]
+fn parse_ls_output_finds_files() {
+    let output = "\
+drwxr-xr-x   - hive hive          0 2020-09-01 12:30 /t/dt=2020-09-01
+-rw-r--r--   3 hive hive         12 2020-09-01 12:30 /t/dt=2020-09-01/b.csv
+-rw-r--r--   3 hive hive         12 2020-09-01 12:30 /t/a.csv
+";
+    assert_eq!(
+        parse_ls_output(output),
+        vec!["/t/a.csv".to_owned(), "/t/dt=2020-09-01/b.csv".to_owned()],
+    );
+}
+
+/// Download the file at `path` as a stream.
+pub(crate) async fn cat(ctx: &Context, path: &str) -> Result<BoxStream<BytesMut>> {
+    debug!(ctx.log(), "streaming from {} using `hdfs dfs -cat`", path);
+    let child = hdfs_dfs(&["-cat", path])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("error running `hdfs dfs -cat`")?;
+    let child_stdout =
+        ctx.spawn_process_with_stdout(format!("hdfs dfs -cat {}", path), child);
+    let child_stdout = BufReader::with_capacity(BUFFER_SIZE, child_stdout);
+    let data = copy_reader_to_stream(ctx.clone(), child_stdout)?;
+    Ok(data.boxed())
+}
+
+/// Upload `data` to `path`, replacing any existing file.
+pub(crate) async fn put(
+    ctx: &Context,
+    data: BoxStream<BytesMut>,
+    path: &str,
+) -> Result<()> {
+    debug!(
+        ctx.log(),
+        "uploading stream to {} using `hdfs dfs -put`", path
+    );
+    let mut child = hdfs_dfs(&["-put", "-f", "-", path])
+        .stdin(Stdio::piped())
+        // Throw away stdout so it doesn't corrupt our output.
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("error running `hdfs dfs -put`")?;
+    let child_stdin = child.stdin.take().expect("child should have stdin");
+    copy_stream_to_writer(ctx.clone(), data, child_stdin)
+        .await
+        .context("error copying data to `hdfs dfs -put`")?;
+    wait_for_process(ctx, &format!("hdfs dfs -put {}", path), child, None)
+        .await
+        .with_context(|_| format!("error finishing upload to {}", path))?;
+    Ok(())
+}
+
+/// Create the directory `dir`, if it doesn't already exist.
+pub(crate) async fn mkdir(ctx: &Context, dir: &str) -> Result<()> {
+    run_hdfs_dfs(ctx, &["-mkdir", "-p", dir]).await?;
+    Ok(())
+}
+
+/// Recursively delete `dir`, if it exists.
+pub(crate) async fn rmdir(ctx: &Context, dir: &str) -> Result<()> {
+    run_hdfs_dfs(ctx, &["-rm", "-r", "-f", dir]).await?;
+    Ok(())
+}
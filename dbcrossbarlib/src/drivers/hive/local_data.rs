@@ -0,0 +1,24 @@
+//! Reading data from Hive tables.
+
+use std::str::FromStr;
+
+use super::{storage::TableStorage, HiveLocator};
+use crate::common::*;
+
+/// Implementation of `local_data`, but as a real `async` function.
+pub(crate) async fn local_data_helper(
+    ctx: Context,
+    source: HiveLocator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Option<BoxStream<CsvStream>>> {
+    let _shared_args_v = shared_args.clone().verify(HiveLocator::features())?;
+    let _source_args = source_args.verify(HiveLocator::features())?;
+
+    // Find where our table is stored, and read the files directly.
+    let location = source.beeline().table_location(&ctx, &source).await?;
+    let storage = TableStorage::from_str(&location)?;
+    debug!(ctx.log(), "reading {} from {}", source, storage);
+    let storage_ctx = ctx.child(o!("storage" => storage.to_string()));
+    storage.local_data(storage_ctx, shared_args).await
+}
@@ -0,0 +1,205 @@
+//! Reading and writing the files which hold a Hive table's data.
+
+use std::{fmt, str::FromStr};
+
+use super::{hdfs, HiveLocator};
+use crate::common::*;
+use crate::csv_stream::{csv_stream_file_name, csv_stream_name};
+use crate::drivers::s3::S3Locator;
+use crate::tokio_glue::ConsumeWithParallelism;
+
+/// Where a Hive table (or partition) keeps its files.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum TableStorage {
+    /// An HDFS directory, such as `hdfs://namenode:8020/warehouse/t/`.
+    Hdfs(String),
+    /// An S3 directory. Hive often uses `s3a://` URLs, but we always store
+    /// an `s3://` URL so that we can use our regular S3 driver.
+    S3(Url),
+}
+
+impl TableStorage {
+    /// The storage for the partition with the specified values, which we
+    /// store in a subdirectory named like `key1=value1/key2=value2/`.
+    pub(crate) fn partition(&self, partition: &[(String, String)]) -> Result<Self> {
+        let mut subdir = String::new();
+        for (key, value) in partition {
+            if key.contains('/') || value.contains('/') {
+                return Err(format_err!(
+                    "partition key {:?} and value {:?} may not contain '/'",
+                    key,
+                    value,
+                ));
+            }
+            subdir.push_str(&format!("{}={}/", key, value));
+        }
+        match self {
+            TableStorage::Hdfs(dir) => {
+                Ok(TableStorage::Hdfs(format!("{}{}", dir, subdir)))
+            }
+            TableStorage::S3(url) => {
+                Ok(TableStorage::S3(url.join(&subdir).with_context(|_| {
+                    format!("could not add {} to {}", subdir, url)
+                })?))
+            }
+        }
+    }
+
+    /// Read all the CSV files in this directory, recursively.
+    pub(crate) async fn local_data(
+        &self,
+        ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+    ) -> Result<Option<BoxStream<CsvStream>>> {
+        match self {
+            TableStorage::Hdfs(dir) => {
+                let files = hdfs::ls(&ctx, dir).await?;
+                let dir = dir.to_owned();
+                let csv_streams =
+                    stream::iter(files.into_iter().map(Ok)).and_then(move |path| {
+                        let ctx = ctx.clone();
+                        let dir = dir.clone();
+                        async move {
+                            let name = csv_stream_name(&dir, &path)?.to_owned();
+                            let ctx = ctx.child(o!("stream" => name.clone()));
+                            let data = hdfs::cat(&ctx, &path).await?;
+                            Ok(CsvStream {
+                                name,
+                                metadata: StreamMetadata {
+                                    source: Some(path),
+                                    modified: None,
+                                },
+                                data,
+                            })
+                        }
+                        .boxed()
+                    });
+                Ok(Some(csv_streams.boxed()))
+            }
+            TableStorage::S3(url) => {
+                let locator = S3Locator::from_str(url.as_str())?;
+                locator
+                    .local_data(ctx, shared_args, SourceArguments::for_temporary())
+                    .await
+            }
+        }
+    }
+
+    /// Write `data` to this directory, waiting until all data is written.
+    pub(crate) async fn write_local_data(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+        shared_args: SharedArguments<Unverified>,
+        if_exists: IfExists,
+    ) -> Result<()> {
+        let parallelism = shared_args
+            .clone()
+            .verify(HiveLocator::features())?
+            .max_streams();
+        let written = match self {
+            TableStorage::Hdfs(dir) => {
+                if if_exists == IfExists::Overwrite {
+                    hdfs::rmdir(&ctx, dir).await?;
+                }
+                hdfs::mkdir(&ctx, dir).await?;
+                let dir = dir.to_owned();
+                data.map_ok(move |stream| {
+                    let ctx = ctx.clone();
+                    let path = format!(
+                        "{}{}",
+                        dir,
+                        csv_stream_file_name(&stream.name, &if_exists)
+                    );
+                    async move {
+                        let ctx = ctx.child(o!("stream" => stream.name.clone()));
+                        hdfs::put(&ctx, stream.data, &path).await?;
+                        Ok(())
+                    }
+                    .boxed()
+                })
+                .boxed()
+            }
+            TableStorage::S3(url) => {
+                let locator = S3Locator::from_str(url.as_str())?;
+                let dest_args =
+                    DestinationArguments::new(DriverArguments::default(), if_exists);
+                locator
+                    .write_local_data(ctx, data, shared_args, dest_args)
+                    .await?
+                    .map_ok(|fut| async move { fut.await.map(|_| ()) }.boxed())
+                    .boxed()
+            }
+        };
+        written.consume_with_parallelism(parallelism).await?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for TableStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableStorage::Hdfs(dir) => dir.fmt(f),
+            TableStorage::S3(url) => url.fmt(f),
+        }
+    }
+}
+
+impl FromStr for TableStorage {
+    type Err = Error;
+
+    /// Parse a Hive table location. We always add a trailing `/`.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut dir = s.to_owned();
+        if !dir.ends_with('/') {
+            dir.push('/');
+        }
+        if dir.starts_with("hdfs://") || dir.starts_with('/') {
+            Ok(TableStorage::Hdfs(dir))
+        } else if dir.starts_with("s3://")
+            || dir.starts_with("s3a://")
+            || dir.starts_with("s3n://")
+        {
+            let rest = &dir[dir.find(':').expect("should contain ':'")..];
+            let url = format!("s3{}", rest)
+                .parse::<Url>()
+                .with_context(|_| format!("could not parse {:?}", s))?;
+            Ok(TableStorage::S3(url))
+        } else {
+            Err(format_err!(
+                "Hive tables must be stored on hdfs:// or s3://, not {:?}",
+                s,
+            ))
+        }
+    }
+}
+
+#[test]
+fn parse_table_storage() {
+    assert_eq!(
+        "hdfs://nn:8020/warehouse/t"
+            .parse::<TableStorage>()
+            .unwrap(),
+        TableStorage::Hdfs("hdfs://nn:8020/warehouse/t/".to_owned()),
+    );
+    assert_eq!(
+        "s3a://bucket/t/".parse::<TableStorage>().unwrap(),
+        TableStorage::S3("s3://bucket/t/".parse().unwrap()),
+    );
+    assert!("file:///tmp/t".parse::<TableStorage>().is_err());
+}
+
+#[test]
+fn partition_storage() {
+    let partition = vec![
+        ("dt".to_owned(), "2020-09-01".to_owned()),
+        ("region".to_owned(), "us".to_owned()),
+    ];
+    let storage = "s3a://bucket/t".parse::<TableStorage>().unwrap();
+    assert_eq!(
+        storage.partition(&partition).unwrap().to_string(),
+        "s3://bucket/t/dt=2020-09-01/region=us/",
+    );
+    let bad = vec![("dt".to_owned(), "a/b".to_owned())];
+    assert!(storage.partition(&bad).is_err());
+}
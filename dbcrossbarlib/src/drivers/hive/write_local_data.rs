@@ -0,0 +1,191 @@
+//! Writing data to Hive tables.
+
+use serde::Deserialize;
+use std::{collections::BTreeMap, str::FromStr};
+
+use super::{
+    data_type::hive_csv_column_type, quote_identifier, quote_string,
+    storage::TableStorage, HiveLocator,
+};
+use crate::common::*;
+
+/// Hive-specific `--to-arg` values.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HiveDestinationArguments {
+    /// Where to store the table's files, if we need to create it.
+    location: Option<String>,
+    /// The partition to write, as `partition.key=value`.
+    #[serde(default)]
+    partition: BTreeMap<String, String>,
+}
+
+/// Implementation of `write_local_data`, but as a real `async` function.
+pub(crate) async fn write_local_data_helper(
+    ctx: Context,
+    dest: HiveLocator,
+    data: BoxStream<CsvStream>,
+    shared_args: SharedArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
+    let shared_args_v = shared_args.clone().verify(HiveLocator::features())?;
+    let dest_args = dest_args.verify(HiveLocator::features())?;
+    let if_exists = dest_args.if_exists().to_owned();
+    let hive_args = dest_args
+        .driver_args()
+        .deserialize::<HiveDestinationArguments>()
+        .context("could not parse --to-arg")?;
+    let partition = hive_args.partition.into_iter().collect::<Vec<_>>();
+    let beeline = dest.beeline();
+
+    // Find or create our table.
+    let location = if beeline.table_exists(&ctx, &dest).await? {
+        if if_exists == IfExists::Error {
+            return Err(format_err!("table {} already exists", dest));
+        }
+        beeline.table_location(&ctx, &dest).await?
+    } else {
+        let location = hive_args.location.ok_or_else(|| {
+            format_err!(
+                "{} does not exist, so please pass --to-arg=location=... to create it",
+                dest,
+            )
+        })?;
+        let sql =
+            create_table_sql(&dest, shared_args_v.schema(), &partition, &location)?;
+        beeline.execute(&ctx, &sql).await?;
+        location
+    };
+
+    // Write our files. When writing a partition, we only overwrite that
+    // partition.
+    let storage = TableStorage::from_str(&location)?.partition(&partition)?;
+    let storage_if_exists = match if_exists {
+        IfExists::Append => IfExists::Append,
+        _ => IfExists::Overwrite,
+    };
+    debug!(ctx.log(), "writing {} to {}", dest, storage);
+    let storage_ctx = ctx.child(o!("storage" => storage.to_string()));
+    storage
+        .write_local_data(storage_ctx, data, shared_args, storage_if_exists)
+        .await?;
+
+    // Register our partition with the metastore.
+    if !partition.is_empty() {
+        let spec = partition
+            .iter()
+            .map(|(key, value)| {
+                format!("{}={}", quote_identifier(key), quote_string(value))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "ALTER TABLE {} ADD IF NOT EXISTS PARTITION ({}) LOCATION {}",
+            dest.quoted_table_name(),
+            spec,
+            quote_string(&partition_location(&location, &partition)),
+        );
+        beeline.execute(&ctx, &sql).await?;
+    }
+
+    // We've already done all the work, so return a single future.
+    let fut = async { Ok(dest.boxed()) }.boxed();
+    Ok(box_stream_once(Ok(fut)))
+}
+
+/// The location of a partition, using the same URL scheme as `location`.
+fn partition_location(location: &str, partition: &[(String, String)]) -> String {
+    let mut partition_location = location.trim_end_matches('/').to_owned();
+    for (key, value) in partition {
+        partition_location.push_str(&format!("/{}={}", key, value));
+    }
+    partition_location
+}
+
+/// Generate SQL to create an external table stored as CSV files with headers.
+fn create_table_sql(
+    dest: &HiveLocator,
+    schema: &Table,
+    partition: &[(String, String)],
+    location: &str,
+) -> Result<String> {
+    let mut columns = vec![];
+    for column in &schema.columns {
+        if partition.iter().any(|(key, _)| key == &column.name) {
+            return Err(format_err!(
+                "partition column {:?} should not be included in the schema",
+                column.name,
+            ));
+        }
+        let mut column_sql = format!(
+            "{} {}",
+            quote_identifier(&column.name),
+            hive_csv_column_type(&column.data_type),
+        );
+        if let Some(comment) = &column.comment {
+            column_sql.push_str(&format!(" COMMENT {}", quote_string(comment)));
+        }
+        columns.push(column_sql);
+    }
+    if columns.is_empty() {
+        return Err(format_err!("cannot create {} with no columns", dest));
+    }
+
+    let mut sql = format!(
+        "CREATE EXTERNAL TABLE {} ({})",
+        dest.quoted_table_name(),
+        columns.join(", "),
+    );
+    if !partition.is_empty() {
+        let keys = partition
+            .iter()
+            .map(|(key, _)| format!("{} STRING", quote_identifier(key)))
+            .collect::<Vec<_>>();
+        sql.push_str(&format!(" PARTITIONED BY ({})", keys.join(", ")));
+    }
+    sql.push_str(&format!(
+        " ROW FORMAT SERDE 'org.apache.hadoop.hive.serde2.OpenCSVSerde' \
+         STORED AS TEXTFILE LOCATION {} \
+         TBLPROPERTIES ('skip.header.line.count'='1')",
+        quote_string(location),
+    ));
+    Ok(sql)
+}
+
+#[test]
+fn create_table_sql_uses_csv_serde() {
+    use crate::schema::{Column, DataType};
+
+    let dest = "hive://hiveserver/db.t".parse::<HiveLocator>().unwrap();
+    let schema = Table {
+        name: "t".to_owned(),
+        columns: vec![
+            Column {
+                name: "id".to_owned(),
+                is_nullable: false,
+                data_type: DataType::Int64,
+                comment: Some("It's the ID".to_owned()),
+            },
+            Column {
+                name: "tags".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Array(Box::new(DataType::Text)),
+                comment: None,
+            },
+        ],
+    };
+    let partition = vec![("dt".to_owned(), "2020-09-01".to_owned())];
+    let sql = create_table_sql(&dest, &schema, &partition, "s3a://b/t").unwrap();
+    assert_eq!(
+        sql,
+        "CREATE EXTERNAL TABLE `db`.`t` (`id` BIGINT COMMENT 'It\\'s the ID', \
+         `tags` STRING) PARTITIONED BY (`dt` STRING) \
+         ROW FORMAT SERDE 'org.apache.hadoop.hive.serde2.OpenCSVSerde' \
+         STORED AS TEXTFILE LOCATION 's3a://b/t' \
+         TBLPROPERTIES ('skip.header.line.count'='1')",
+    );
+    assert_eq!(
+        partition_location("s3a://b/t/", &partition),
+        "s3a://b/t/dt=2020-09-01"
+    );
+}
@@ -0,0 +1,302 @@
+//! Schema-only driver for writing Hive `CREATE EXTERNAL TABLE` statements.
+
+use percent_encoding::percent_decode_str;
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+use crate::drivers::hive::{
+    data_type::{hive_column_type, hive_csv_column_type},
+    quote_identifier, quote_string,
+};
+
+/// The file formats we can declare in our `CREATE EXTERNAL TABLE` statements.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum HiveFormat {
+    /// CSV files with a header row, as written by `dbcrossbar`.
+    Csv,
+    /// Newline-delimited JSON.
+    Json,
+    /// Parquet files.
+    Parquet,
+    /// ORC files.
+    Orc,
+    /// Avro files.
+    Avro,
+}
+
+impl Default for HiveFormat {
+    fn default() -> Self {
+        HiveFormat::Csv
+    }
+}
+
+impl fmt::Display for HiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HiveFormat::Csv => "csv".fmt(f),
+            HiveFormat::Json => "json".fmt(f),
+            HiveFormat::Parquet => "parquet".fmt(f),
+            HiveFormat::Orc => "orc".fmt(f),
+            HiveFormat::Avro => "avro".fmt(f),
+        }
+    }
+}
+
+impl FromStr for HiveFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(HiveFormat::Csv),
+            "json" => Ok(HiveFormat::Json),
+            "parquet" => Ok(HiveFormat::Parquet),
+            "orc" => Ok(HiveFormat::Orc),
+            "avro" => Ok(HiveFormat::Avro),
+            _ => Err(format_err!(
+                "unknown Hive format {:?} (expected csv, json, parquet, orc or avro)",
+                s
+            )),
+        }
+    }
+}
+
+/// An SQL file containing a Hive `CREATE EXTERNAL TABLE` statement, such as
+/// `hive-sql:table.sql?format=parquet&location=s3a://bucket/table/`.
+#[derive(Clone, Debug)]
+pub struct HiveSqlLocator {
+    path: PathOrStdio,
+    /// The format of the table's files.
+    format: HiveFormat,
+    /// Where the table's files are stored.
+    location: Option<String>,
+}
+
+impl fmt::Display for HiveSqlLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encode = |s: &str| s.replace('%', "%25").replace('&', "%26");
+        self.path.fmt_locator_helper(Self::scheme(), f)?;
+        let mut sep = '?';
+        if self.format != HiveFormat::default() {
+            write!(f, "{}format={}", sep, self.format)?;
+            sep = '&';
+        }
+        if let Some(location) = &self.location {
+            write!(f, "{}location={}", sep, encode(location))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for HiveSqlLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (path, query) = match s.find('?') {
+            Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+            None => (s, None),
+        };
+        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), path)?;
+        let mut format = HiveFormat::default();
+        let mut location = None;
+        for param in query.into_iter().flat_map(|q| q.split('&')) {
+            let mut parts = param.splitn(2, '=');
+            let key = parts.next().expect("split always returns one value");
+            let value = percent_decode_str(parts.next().unwrap_or(""))
+                .decode_utf8()
+                .with_context(|_| format!("cannot decode {:?}", param))?;
+            match key {
+                "format" => format = value.parse()?,
+                "location" => location = Some(value.into_owned()),
+                _ => {
+                    return Err(format_err!(
+                        "unknown option {:?} in {:?} (expected format or location)",
+                        key,
+                        s
+                    ))
+                }
+            }
+        }
+        Ok(HiveSqlLocator {
+            path,
+            format,
+            location,
+        })
+    }
+}
+
+#[test]
+fn parse_and_display() {
+    let loc = "hive-sql:t.sql?format=parquet&location=s3a://b/a%26b/"
+        .parse::<HiveSqlLocator>()
+        .unwrap();
+    assert_eq!(loc.format, HiveFormat::Parquet);
+    assert_eq!(loc.location.as_deref(), Some("s3a://b/a&b/"));
+
+    let loc = "hive-sql:-".parse::<HiveSqlLocator>().unwrap();
+    assert_eq!(loc.format, HiveFormat::Csv);
+    assert_eq!(loc.location, None);
+
+    for bad in &["hive-sql:t.sql?format=xml", "hive-sql:t.sql?bucket=b"] {
+        assert!(bad.parse::<HiveSqlLocator>().is_err());
+    }
+}
+
+impl Locator for HiveSqlLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn write_schema(
+        &self,
+        ctx: Context,
+        table: Table,
+        if_exists: IfExists,
+    ) -> BoxFuture<()> {
+        write_schema_helper(ctx, self.to_owned(), table, if_exists).boxed()
+    }
+}
+
+impl LocatorStatic for HiveSqlLocator {
+    fn scheme() -> &'static str {
+        "hive-sql:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::WriteSchema.into(),
+            write_schema_if_exists: IfExistsFeatures::no_append(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
+
+/// Implementation of `write_schema`, but as a real `async` function.
+async fn write_schema_helper(
+    ctx: Context,
+    dest: HiveSqlLocator,
+    table: Table,
+    if_exists: IfExists,
+) -> Result<()> {
+    // Generate our SQL before we create our output file.
+    let sql = create_table_sql(&table, dest.format, dest.location.as_deref())?;
+
+    // Output our schema to our destination.
+    let mut f = dest.path.create_async(ctx, if_exists).await?;
+    buffer_sync_write_and_copy_to_async(&mut f, |buff| write!(buff, "{}", sql))
+        .await
+        .with_context(|_| format!("error writing to {}", dest.path))?;
+    f.flush().await?;
+    Ok(())
+}
+
+/// Generate a `CREATE EXTERNAL TABLE` statement for `table`.
+fn create_table_sql(
+    table: &Table,
+    format: HiveFormat,
+    location: Option<&str>,
+) -> Result<String> {
+    if table.columns.is_empty() {
+        return Err(format_err!("cannot create {} with no columns", table.name));
+    }
+    let name = table
+        .name
+        .split('.')
+        .map(quote_identifier)
+        .collect::<Vec<_>>()
+        .join(".");
+
+    let mut sql = format!("CREATE EXTERNAL TABLE {} (\n", name);
+    for (idx, column) in table.columns.iter().enumerate() {
+        // CSV files store complex types as JSON strings.
+        let column_type = match format {
+            HiveFormat::Csv => hive_csv_column_type(&column.data_type),
+            _ => hive_column_type(&column.data_type),
+        };
+        sql.push_str(&format!(
+            "    {} {}",
+            quote_identifier(&column.name),
+            column_type,
+        ));
+        if let Some(comment) = &column.comment {
+            sql.push_str(&format!(" COMMENT {}", quote_string(comment)));
+        }
+        if idx + 1 < table.columns.len() {
+            sql.push(',');
+        }
+        sql.push('\n');
+    }
+    sql.push(')');
+
+    match format {
+        HiveFormat::Csv => sql.push_str(
+            "\nROW FORMAT SERDE 'org.apache.hadoop.hive.serde2.OpenCSVSerde'\
+             \nSTORED AS TEXTFILE",
+        ),
+        HiveFormat::Json => sql.push_str(
+            "\nROW FORMAT SERDE 'org.apache.hive.hcatalog.data.JsonSerDe'\
+             \nSTORED AS TEXTFILE",
+        ),
+        HiveFormat::Parquet => sql.push_str("\nSTORED AS PARQUET"),
+        HiveFormat::Orc => sql.push_str("\nSTORED AS ORC"),
+        HiveFormat::Avro => sql.push_str("\nSTORED AS AVRO"),
+    }
+    if let Some(location) = location {
+        sql.push_str(&format!("\nLOCATION {}", quote_string(location)));
+    }
+    if format == HiveFormat::Csv {
+        sql.push_str("\nTBLPROPERTIES ('skip.header.line.count'='1')");
+    }
+    sql.push_str(";\n");
+    Ok(sql)
+}
+
+#[test]
+fn create_table_sql_supports_formats() {
+    use crate::schema::{Column, DataType};
+
+    let table = Table {
+        name: "db.t".to_owned(),
+        columns: vec![
+            Column {
+                name: "id".to_owned(),
+                is_nullable: false,
+                data_type: DataType::Int64,
+                comment: Some("It's the ID".to_owned()),
+            },
+            Column {
+                name: "tags".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Array(Box::new(DataType::Text)),
+                comment: None,
+            },
+        ],
+    };
+
+    let sql = create_table_sql(&table, HiveFormat::Csv, Some("s3a://b/t/")).unwrap();
+    assert_eq!(
+        sql,
+        "CREATE EXTERNAL TABLE `db`.`t` (
+    `id` BIGINT COMMENT 'It\\'s the ID',
+    `tags` STRING
+)
+ROW FORMAT SERDE 'org.apache.hadoop.hive.serde2.OpenCSVSerde'
+STORED AS TEXTFILE
+LOCATION 's3a://b/t/'
+TBLPROPERTIES ('skip.header.line.count'='1');
+",
+    );
+
+    let sql = create_table_sql(&table, HiveFormat::Parquet, None).unwrap();
+    assert_eq!(
+        sql,
+        "CREATE EXTERNAL TABLE `db`.`t` (
+    `id` BIGINT COMMENT 'It\\'s the ID',
+    `tags` ARRAY<STRING>
+)
+STORED AS PARQUET;
+",
+    );
+}
@@ -0,0 +1,172 @@
+//! Driver for reading CSV files from `https:` URLs, including presigned S3
+//! URLs and signed GCS URLs.
+
+use chrono::{DateTime, Utc};
+use percent_encoding::percent_decode_str;
+use reqwest::header::LAST_MODIFIED;
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+use crate::compression::decompress_stream_for_file_name;
+use crate::csv_stream::csv_stream_name;
+use crate::http_response::http_response_stream;
+
+/// A single CSV file at an `https:` URL, such as
+/// `https://example.s3.amazonaws.com/data.csv?X-Amz-Signature=...`.
+///
+/// Presigned URLs contain temporary credentials in their query strings, so
+/// we never display or log the query string.
+#[derive(Clone)]
+pub(crate) struct HttpsLocator {
+    url: Url,
+}
+
+impl HttpsLocator {
+    /// Our URL, with any query string hidden.
+    fn url_with_hidden_query(&self) -> String {
+        if self.url.query().is_some() {
+            let mut url = self.url.clone();
+            url.set_query(Some("XXXXXX"));
+            url.to_string()
+        } else {
+            self.url.to_string()
+        }
+    }
+}
+
+impl fmt::Debug for HttpsLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpsLocator")
+            .field("url", &self.url_with_hidden_query())
+            .finish()
+    }
+}
+
+impl fmt::Display for HttpsLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.url_with_hidden_query())
+    }
+}
+
+impl FromStr for HttpsLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // Don't include `s` in our errors, because it may contain credentials.
+        let url = s
+            .parse::<Url>()
+            .map_err(|err| format_err!("could not parse https: locator: {}", err))?;
+        if url.scheme() != "https" || url.host().is_none() {
+            return Err(format_err!("expected locator to start with https://"));
+        }
+        if url.path().ends_with('/') {
+            return Err(format_err!(
+                "https: locators must point to a single file, not a directory"
+            ));
+        }
+        Ok(HttpsLocator { url })
+    }
+}
+
+#[test]
+fn display_hides_query() {
+    let locator = "https://example.s3.amazonaws.com/dir/data.csv?X-Amz-Signature=abc"
+        .parse::<HttpsLocator>()
+        .unwrap();
+    assert_eq!(
+        locator.to_string(),
+        "https://example.s3.amazonaws.com/dir/data.csv?XXXXXX",
+    );
+    assert!(!format!("{:?}", locator).contains("abc"));
+    let locator = "https://example.com/data.csv"
+        .parse::<HttpsLocator>()
+        .unwrap();
+    assert_eq!(locator.to_string(), "https://example.com/data.csv");
+    assert!("http://example.com/data.csv"
+        .parse::<HttpsLocator>()
+        .is_err());
+    assert!("https://example.com/dir/".parse::<HttpsLocator>().is_err());
+}
+
+impl Locator for HttpsLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn local_data(
+        &self,
+        ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Option<BoxStream<CsvStream>>> {
+        local_data_helper(ctx, self.clone(), shared_args, source_args).boxed()
+    }
+}
+
+/// Download our CSV file.
+async fn local_data_helper(
+    ctx: Context,
+    locator: HttpsLocator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Option<BoxStream<CsvStream>>> {
+    let _shared_args = shared_args.verify(HttpsLocator::features())?;
+    let _source_args = source_args.verify(HttpsLocator::features())?;
+    debug!(ctx.log(), "getting CSV file from {}", locator);
+
+    let resp = reqwest::Client::new()
+        .get(locator.url.as_str())
+        .send()
+        .await
+        // Don't include the `reqwest` error, because it may contain our URL.
+        .map_err(|_| format_err!("could not GET {}", locator))?;
+    let status = resp.status();
+    if !status.is_success() {
+        // S3 and GCS both explain what went wrong using an XML body, which
+        // we include in our debug log.
+        let body = resp.text().await.unwrap_or_default();
+        debug!(ctx.log(), "error body: {}", body);
+        return Err(format_err!(
+            "could not GET {}: {} (presigned URLs may have expired)",
+            locator,
+            status,
+        ));
+    }
+    let modified = resp
+        .headers()
+        .get(LAST_MODIFIED)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .map(|modified| modified.with_timezone(&Utc));
+
+    let path = locator.url.path();
+    let name = csv_stream_name(path, path)?;
+    let name = percent_decode_str(name).decode_utf8_lossy().into_owned();
+    let data =
+        decompress_stream_for_file_name(&ctx, path, http_response_stream(resp))?;
+    Ok(Some(box_stream_once(Ok(CsvStream {
+        name,
+        metadata: StreamMetadata {
+            source: Some(locator.to_string()),
+            modified,
+        },
+        data,
+    }))))
+}
+
+impl LocatorStatic for HttpsLocator {
+    fn scheme() -> &'static str {
+        "https:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::LocalData.into(),
+            write_schema_if_exists: EnumSet::empty(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
@@ -0,0 +1,430 @@
+// A helper process for dbcrossbar's `jdbc:` driver.
+//
+// dbcrossbar writes this file to a temporary directory and runs it using
+// Java's single-file source launcher (Java 11 or later):
+//
+//     java DbcrossbarJdbc.java schema JDBC_URL TABLE
+//     java DbcrossbarJdbc.java export JDBC_URL TABLE COLUMN...
+//     java DbcrossbarJdbc.java import JDBC_URL TABLE append|overwrite
+//
+// `schema` prints a JSON description of TABLE's columns. `export` prints the
+// specified columns as CSV with a header row, filtered by the SQL expression in
+// `DBCROSSBAR_JDBC_WHERE` if it's set. `import` reads CSV with a header row
+// from standard input and inserts it into TABLE.
+//
+// If `DBCROSSBAR_JDBC_USER` and `DBCROSSBAR_JDBC_PASSWORD` are set, we pass
+// them to the JDBC driver. The JDBC driver itself must be on the `CLASSPATH`.
+//
+// This file must only use the Java standard library.
+
+import java.io.BufferedReader;
+import java.io.BufferedWriter;
+import java.io.IOException;
+import java.io.InputStreamReader;
+import java.io.OutputStreamWriter;
+import java.io.Reader;
+import java.io.Writer;
+import java.math.BigDecimal;
+import java.nio.charset.StandardCharsets;
+import java.sql.Connection;
+import java.sql.DriverManager;
+import java.sql.JDBCType;
+import java.sql.PreparedStatement;
+import java.sql.ResultSet;
+import java.sql.ResultSetMetaData;
+import java.sql.SQLException;
+import java.sql.Statement;
+import java.sql.Timestamp;
+import java.sql.Types;
+import java.time.LocalDate;
+import java.time.LocalDateTime;
+import java.time.OffsetDateTime;
+import java.time.format.DateTimeFormatter;
+import java.time.format.DateTimeFormatterBuilder;
+import java.time.temporal.ChronoField;
+import java.util.ArrayList;
+import java.util.List;
+import java.util.Properties;
+
+public class DbcrossbarJdbc {
+    /** How many rows to insert in each batch. */
+    private static final int BATCH_SIZE = 1000;
+
+    /** The format we use when writing timestamps without time zones. */
+    private static final DateTimeFormatter TIMESTAMP_FORMAT =
+        DateTimeFormatter.ofPattern("yyyy-MM-dd HH:mm:ss.SSSSSS");
+
+    /** The format we use when writing timestamps with time zones. */
+    private static final DateTimeFormatter TIMESTAMP_TZ_FORMAT =
+        DateTimeFormatter.ofPattern("yyyy-MM-dd HH:mm:ss.SSSSSSxxx");
+
+    /** The format we accept when reading timestamps with time zones. */
+    private static final DateTimeFormatter TIMESTAMP_TZ_PARSER =
+        new DateTimeFormatterBuilder()
+            .appendPattern("yyyy-MM-dd")
+            .optionalStart().appendLiteral('T').optionalEnd()
+            .optionalStart().appendLiteral(' ').optionalEnd()
+            .appendPattern("HH:mm:ss")
+            .optionalStart()
+            .appendFraction(ChronoField.NANO_OF_SECOND, 0, 9, true)
+            .optionalEnd()
+            .optionalStart().appendPattern("XXX").optionalEnd()
+            .optionalStart().appendPattern("X").optionalEnd()
+            .toFormatter();
+
+    public static void main(String[] args) throws Exception {
+        if (args.length < 3) {
+            usage();
+        }
+        String command = args[0];
+        String url = args[1];
+        String table = args[2];
+        try (Connection conn = connect(url)) {
+            if (command.equals("schema") && args.length == 3) {
+                schema(conn, table);
+            } else if (command.equals("export") && args.length > 3) {
+                List<String> columns = new ArrayList<>();
+                for (int i = 3; i < args.length; i++) {
+                    columns.add(args[i]);
+                }
+                export(conn, table, columns, System.getenv("DBCROSSBAR_JDBC_WHERE"));
+            } else if (command.equals("import") && args.length == 4) {
+                importCsv(conn, table, args[3].equals("overwrite"));
+            } else {
+                usage();
+            }
+        } catch (SQLException e) {
+            System.err.println("JDBC error: " + e.getMessage());
+            System.exit(1);
+        }
+    }
+
+    private static void usage() {
+        System.err.println("usage: DbcrossbarJdbc (schema|export|import) URL TABLE ...");
+        System.exit(2);
+    }
+
+    private static Connection connect(String url) throws SQLException {
+        Properties props = new Properties();
+        String user = System.getenv("DBCROSSBAR_JDBC_USER");
+        String password = System.getenv("DBCROSSBAR_JDBC_PASSWORD");
+        if (user != null) {
+            props.setProperty("user", user);
+        }
+        if (password != null) {
+            props.setProperty("password", password);
+        }
+        return DriverManager.getConnection(url, props);
+    }
+
+    /** Print a JSON description of the columns in `table`. */
+    private static void schema(Connection conn, String table) throws Exception {
+        try (Statement stmt = conn.createStatement();
+             ResultSet rs = stmt.executeQuery("SELECT * FROM " + table + " WHERE 1 = 0")) {
+            ResultSetMetaData meta = rs.getMetaData();
+            StringBuilder out = new StringBuilder("{\"columns\":[");
+            for (int i = 1; i <= meta.getColumnCount(); i++) {
+                if (i > 1) {
+                    out.append(',');
+                }
+                out.append("{\"name\":").append(jsonString(meta.getColumnName(i)));
+                out.append(",\"jdbc_type\":").append(jsonString(jdbcTypeName(meta.getColumnType(i))));
+                out.append(",\"type_name\":").append(jsonString(meta.getColumnTypeName(i)));
+                out.append(",\"precision\":").append(meta.getPrecision(i));
+                out.append(",\"scale\":").append(meta.getScale(i));
+                out.append(",\"is_nullable\":")
+                    .append(meta.isNullable(i) != ResultSetMetaData.columnNoNulls);
+                out.append('}');
+            }
+            out.append("]}");
+            System.out.println(out);
+        }
+    }
+
+    private static String jdbcTypeName(int type) {
+        try {
+            return JDBCType.valueOf(type).getName();
+        } catch (IllegalArgumentException e) {
+            return "OTHER";
+        }
+    }
+
+    private static String jsonString(String s) {
+        if (s == null) {
+            return "null";
+        }
+        StringBuilder out = new StringBuilder("\"");
+        for (char c : s.toCharArray()) {
+            if (c == '"' || c == '\\') {
+                out.append('\\').append(c);
+            } else if (c < 0x20) {
+                out.append(String.format("\\u%04x", (int) c));
+            } else {
+                out.append(c);
+            }
+        }
+        return out.append('"').toString();
+    }
+
+    /** Write `columns` from `table` to standard output as CSV. */
+    private static void export(
+        Connection conn,
+        String table,
+        List<String> columns,
+        String whereClause
+    ) throws Exception {
+        String quote = conn.getMetaData().getIdentifierQuoteString().trim();
+        List<String> quoted = new ArrayList<>();
+        for (String column : columns) {
+            quoted.add(quoteIdentifier(quote, column));
+        }
+        String sql = "SELECT " + String.join(", ", quoted) + " FROM " + table;
+        if (whereClause != null && !whereClause.isEmpty()) {
+            sql += " WHERE " + whereClause;
+        }
+
+        Writer out = new BufferedWriter(
+            new OutputStreamWriter(System.out, StandardCharsets.UTF_8), 64 * 1024);
+        writeCsvRow(out, columns);
+        try (Statement stmt = conn.createStatement()) {
+            stmt.setFetchSize(BATCH_SIZE);
+            try (ResultSet rs = stmt.executeQuery(sql)) {
+                ResultSetMetaData meta = rs.getMetaData();
+                List<String> row = new ArrayList<>();
+                while (rs.next()) {
+                    row.clear();
+                    for (int i = 1; i <= columns.size(); i++) {
+                        row.add(formatValue(rs, i, meta.getColumnType(i)));
+                    }
+                    writeCsvRow(out, row);
+                }
+            }
+        }
+        out.flush();
+    }
+
+    private static String quoteIdentifier(String quote, String name) {
+        if (quote.isEmpty()) {
+            return name;
+        }
+        return quote + name.replace(quote, quote + quote) + quote;
+    }
+
+    /** Format a value using our CSV interchange format, or return `null`. */
+    private static String formatValue(ResultSet rs, int i, int type) throws SQLException {
+        String value;
+        switch (type) {
+            case Types.BIT:
+            case Types.BOOLEAN:
+                value = rs.getBoolean(i) ? "true" : "false";
+                break;
+            case Types.DATE:
+                LocalDate date = rs.getObject(i, LocalDate.class);
+                value = date == null ? null : date.toString();
+                break;
+            case Types.TIMESTAMP:
+                LocalDateTime timestamp = rs.getObject(i, LocalDateTime.class);
+                value = timestamp == null ? null : timestamp.format(TIMESTAMP_FORMAT);
+                break;
+            case Types.TIMESTAMP_WITH_TIMEZONE:
+                OffsetDateTime timestampTz = rs.getObject(i, OffsetDateTime.class);
+                value = timestampTz == null ? null : timestampTz.format(TIMESTAMP_TZ_FORMAT);
+                break;
+            default:
+                value = rs.getString(i);
+        }
+        return rs.wasNull() ? null : value;
+    }
+
+    private static void writeCsvRow(Writer out, List<String> row) throws IOException {
+        for (int i = 0; i < row.size(); i++) {
+            if (i > 0) {
+                out.write(',');
+            }
+            String cell = row.get(i);
+            if (cell == null) {
+                continue;
+            }
+            if (cell.isEmpty() || cell.indexOf(',') >= 0 || cell.indexOf('"') >= 0
+                || cell.indexOf('\n') >= 0 || cell.indexOf('\r') >= 0) {
+                out.write('"');
+                out.write(cell.replace("\"", "\"\""));
+                out.write('"');
+            } else {
+                out.write(cell);
+            }
+        }
+        out.write('\n');
+    }
+
+    /** Read CSV from standard input and insert it into `table`. */
+    private static void importCsv(Connection conn, String table, boolean overwrite)
+        throws Exception {
+        Reader in = new BufferedReader(
+            new InputStreamReader(System.in, StandardCharsets.UTF_8), 64 * 1024);
+        List<String> columns = readCsvRow(in);
+        if (columns == null) {
+            throw new IOException("expected CSV header row");
+        }
+
+        String quote = conn.getMetaData().getIdentifierQuoteString().trim();
+        List<String> quoted = new ArrayList<>();
+        List<String> placeholders = new ArrayList<>();
+        for (String column : columns) {
+            quoted.add(quoteIdentifier(quote, column));
+            placeholders.add("?");
+        }
+        String columnList = String.join(", ", quoted);
+
+        // Look up the types of our columns.
+        int[] types = new int[columns.size()];
+        try (Statement stmt = conn.createStatement();
+             ResultSet rs = stmt.executeQuery(
+                 "SELECT " + columnList + " FROM " + table + " WHERE 1 = 0")) {
+            ResultSetMetaData meta = rs.getMetaData();
+            for (int i = 0; i < types.length; i++) {
+                types[i] = meta.getColumnType(i + 1);
+            }
+        }
+
+        conn.setAutoCommit(false);
+        try {
+            if (overwrite) {
+                try (Statement stmt = conn.createStatement()) {
+                    stmt.executeUpdate("DELETE FROM " + table);
+                }
+            }
+            String sql = "INSERT INTO " + table + " (" + columnList + ") VALUES ("
+                + String.join(", ", placeholders) + ")";
+            try (PreparedStatement stmt = conn.prepareStatement(sql)) {
+                int pending = 0;
+                List<String> row;
+                while ((row = readCsvRow(in)) != null) {
+                    if (row.size() != types.length) {
+                        throw new IOException(
+                            "expected " + types.length + " columns, found " + row.size());
+                    }
+                    for (int i = 0; i < types.length; i++) {
+                        setValue(stmt, i + 1, types[i], row.get(i));
+                    }
+                    stmt.addBatch();
+                    if (++pending == BATCH_SIZE) {
+                        stmt.executeBatch();
+                        pending = 0;
+                    }
+                }
+                if (pending > 0) {
+                    stmt.executeBatch();
+                }
+            }
+            conn.commit();
+        } catch (Exception e) {
+            conn.rollback();
+            throw e;
+        }
+    }
+
+    /** Set a parameter from a cell in our CSV interchange format. */
+    private static void setValue(PreparedStatement stmt, int i, int type, String cell)
+        throws SQLException {
+        if (cell.isEmpty()) {
+            stmt.setNull(i, type);
+            return;
+        }
+        switch (type) {
+            case Types.BIT:
+            case Types.BOOLEAN:
+                stmt.setBoolean(i, parseBoolean(cell));
+                break;
+            case Types.TINYINT:
+            case Types.SMALLINT:
+            case Types.INTEGER:
+            case Types.BIGINT:
+                stmt.setLong(i, Long.parseLong(cell));
+                break;
+            case Types.REAL:
+            case Types.FLOAT:
+            case Types.DOUBLE:
+                stmt.setDouble(i, Double.parseDouble(cell));
+                break;
+            case Types.NUMERIC:
+            case Types.DECIMAL:
+                stmt.setBigDecimal(i, new BigDecimal(cell));
+                break;
+            case Types.DATE:
+                stmt.setObject(i, LocalDate.parse(cell));
+                break;
+            case Types.TIMESTAMP:
+                stmt.setTimestamp(i, Timestamp.valueOf(cell.replace('T', ' ')));
+                break;
+            case Types.TIMESTAMP_WITH_TIMEZONE:
+                stmt.setObject(i, OffsetDateTime.parse(cell, TIMESTAMP_TZ_PARSER));
+                break;
+            default:
+                stmt.setString(i, cell);
+        }
+    }
+
+    private static boolean parseBoolean(String cell) {
+        switch (cell.toLowerCase()) {
+            case "t":
+            case "true":
+            case "y":
+            case "yes":
+            case "on":
+            case "1":
+                return true;
+            case "f":
+            case "false":
+            case "n":
+            case "no":
+            case "off":
+            case "0":
+                return false;
+            default:
+                throw new IllegalArgumentException("cannot parse boolean: " + cell);
+        }
+    }
+
+    /** Read a CSV row, or return `null` at the end of the input. */
+    private static List<String> readCsvRow(Reader in) throws IOException {
+        List<String> row = new ArrayList<>();
+        StringBuilder cell = new StringBuilder();
+        boolean quoted = false;
+        boolean sawAnything = false;
+        int c;
+        while ((c = in.read()) != -1) {
+            sawAnything = true;
+            if (quoted) {
+                if (c == '"') {
+                    in.mark(1);
+                    int next = in.read();
+                    if (next == '"') {
+                        cell.append('"');
+                    } else {
+                        quoted = false;
+                        if (next != -1) {
+                            in.reset();
+                        }
+                    }
+                } else {
+                    cell.append((char) c);
+                }
+            } else if (c == '"') {
+                quoted = true;
+            } else if (c == ',') {
+                row.add(cell.toString());
+                cell.setLength(0);
+            } else if (c == '\n') {
+                break;
+            } else if (c != '\r') {
+                cell.append((char) c);
+            }
+        }
+        if (!sawAnything) {
+            return null;
+        }
+        row.add(cell.toString());
+        return row;
+    }
+}
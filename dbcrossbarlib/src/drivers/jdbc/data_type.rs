@@ -0,0 +1,156 @@
+//! Converting JDBC column metadata to portable columns.
+
+use serde::Deserialize;
+
+use crate::common::*;
+use crate::schema::{Column, DataType};
+
+/// The schema printed by our Java helper.
+#[derive(Debug, Deserialize)]
+pub(crate) struct JdbcSchema {
+    /// The columns of our table.
+    pub(crate) columns: Vec<JdbcColumn>,
+}
+
+/// A column, as described by JDBC's `ResultSetMetaData`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct JdbcColumn {
+    /// The name of the column.
+    name: String,
+    /// The name of the `java.sql.JDBCType`, such as `VARCHAR`.
+    jdbc_type: String,
+    /// The database-specific type name, such as `jsonb`.
+    type_name: Option<String>,
+    /// The precision of a `DECIMAL` column, or 0 if unknown.
+    #[serde(default)]
+    precision: i64,
+    /// The scale of a `DECIMAL` column. Some drivers report negative scales.
+    #[serde(default)]
+    scale: i64,
+    /// Can this column be null?
+    is_nullable: bool,
+}
+
+impl JdbcColumn {
+    /// Convert this to a portable column.
+    pub(crate) fn to_column(&self) -> Result<Column> {
+        Ok(Column {
+            name: self.name.clone(),
+            is_nullable: self.is_nullable,
+            data_type: self.data_type().with_context(|_| {
+                format!("could not convert type of column {:?}", self.name)
+            })?,
+            comment: None,
+        })
+    }
+
+    /// Convert our JDBC type to a portable type.
+    fn data_type(&self) -> Result<DataType> {
+        // Some databases report common types using vendor-specific names.
+        let type_name = self
+            .type_name
+            .as_ref()
+            .map(|t| t.to_ascii_lowercase())
+            .unwrap_or_default();
+        match (&self.jdbc_type[..], &type_name[..]) {
+            (_, "uuid") | (_, "uniqueidentifier") => Ok(DataType::Uuid),
+            (_, "json") | (_, "jsonb") => Ok(DataType::Json),
+            ("BIT", _) | ("BOOLEAN", _) => Ok(DataType::Bool),
+            ("DATE", _) => Ok(DataType::Date),
+            ("DECIMAL", _) | ("NUMERIC", _) => Ok(self.decimal_type()),
+            ("REAL", _) => Ok(DataType::Float32),
+            // JDBC's `FLOAT` is double precision.
+            ("DOUBLE", _) | ("FLOAT", _) => Ok(DataType::Float64),
+            ("SMALLINT", _) | ("TINYINT", _) => Ok(DataType::Int16),
+            ("INTEGER", _) => Ok(DataType::Int32),
+            ("BIGINT", _) => Ok(DataType::Int64),
+            ("TIMESTAMP", _) => Ok(DataType::TimestampWithoutTimeZone),
+            ("TIMESTAMP_WITH_TIMEZONE", _) => Ok(DataType::TimestampWithTimeZone),
+            // We don't have a portable `TIME` type, so we treat it as text.
+            ("CHAR", _)
+            | ("CLOB", _)
+            | ("LONGNVARCHAR", _)
+            | ("LONGVARCHAR", _)
+            | ("NCHAR", _)
+            | ("NCLOB", _)
+            | ("NVARCHAR", _)
+            | ("SQLXML", _)
+            | ("TIME", _)
+            | ("TIME_WITH_TIMEZONE", _)
+            | ("VARCHAR", _) => Ok(DataType::Text),
+            (jdbc_type, _) => Err(format_err!(
+                "cannot convert JDBC type {} ({})",
+                jdbc_type,
+                self.type_name.as_deref().unwrap_or("unknown"),
+            )),
+        }
+    }
+
+    /// Convert a `DECIMAL` column to a portable type, keeping the precision
+    /// and scale if the driver reported sensible values. Drivers report
+    /// unconstrained columns in different ways, including a precision of 0 or
+    /// a huge precision, and we treat those as plain decimals.
+    fn decimal_type(&self) -> DataType {
+        // PostgreSQL allows the most digits of any database we know about.
+        const MAX_PRECISION: u32 = 1000;
+        match (u32::try_from(self.precision), u32::try_from(self.scale)) {
+            (Ok(precision), Ok(scale))
+                if 0 < precision
+                    && precision <= MAX_PRECISION
+                    && scale <= precision =>
+            {
+                DataType::FixedDecimal { precision, scale }
+            }
+            _ => DataType::Decimal,
+        }
+    }
+}
+
+#[test]
+fn parse_helper_schema() {
+    let json = r#"{"columns":[
+        {"name":"id","jdbc_type":"BIGINT","type_name":"int8","is_nullable":false},
+        {"name":"tags","jdbc_type":"OTHER","type_name":"jsonb","is_nullable":true},
+        {"name":"seen","jdbc_type":"TIMESTAMP","type_name":null,"is_nullable":true}
+    ]}"#;
+    let schema = serde_json::from_str::<JdbcSchema>(json).unwrap();
+    let columns = schema
+        .columns
+        .iter()
+        .map(|c| c.to_column())
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(columns[0].data_type, DataType::Int64);
+    assert!(!columns[0].is_nullable);
+    assert_eq!(columns[1].data_type, DataType::Json);
+    assert_eq!(columns[2].data_type, DataType::TimestampWithoutTimeZone);
+
+    let decimal = |json: &str| {
+        serde_json::from_str::<JdbcColumn>(json)
+            .unwrap()
+            .to_column()
+            .unwrap()
+            .data_type
+    };
+    assert_eq!(
+        decimal(
+            r#"{"name":"d","jdbc_type":"DECIMAL","type_name":"decimal","precision":10,"scale":2,"is_nullable":true}"#
+        ),
+        DataType::FixedDecimal {
+            precision: 10,
+            scale: 2,
+        },
+    );
+    for &(precision, scale) in &[(0, 0), (131_089, 0), (38, -127), (5, 6)] {
+        let json = format!(
+            r#"{{"name":"d","jdbc_type":"NUMERIC","type_name":"numeric","precision":{},"scale":{},"is_nullable":true}}"#,
+            precision, scale,
+        );
+        assert_eq!(decimal(&json), DataType::Decimal);
+    }
+
+    let blob =
+        r#"{"name":"b","jdbc_type":"BLOB","type_name":"blob","is_nullable":true}"#;
+    let blob = serde_json::from_str::<JdbcColumn>(blob).unwrap();
+    assert!(blob.to_column().is_err());
+}
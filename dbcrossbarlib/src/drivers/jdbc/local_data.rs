@@ -0,0 +1,34 @@
+//! Reading data using JDBC.
+
+use super::{
+    shim::{run_shim_with_output, shim_command},
+    JdbcLocator,
+};
+use crate::common::*;
+
+/// Implementation of `local_data`, but as a real `async` function.
+pub(crate) async fn local_data_helper(
+    ctx: Context,
+    source: JdbcLocator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Option<BoxStream<CsvStream>>> {
+    let shared_args = shared_args.verify(JdbcLocator::features())?;
+    let source_args = source_args.verify(JdbcLocator::features())?;
+    let schema = shared_args.schema();
+
+    // Our helper writes CSV in our interchange format, so we don't need to
+    // transform it.
+    debug!(ctx.log(), "exporting {} using JDBC", source);
+    let mut command = shim_command(&["export", &source.url, &source.table])?;
+    command.args(schema.columns.iter().map(|c| &c.name));
+    if let Some(where_clause) = source_args.where_clause() {
+        command.env("DBCROSSBAR_JDBC_WHERE", where_clause);
+    }
+    let data = run_shim_with_output(&ctx, command)?;
+    Ok(Some(box_stream_once(Ok(CsvStream {
+        name: source.table.clone(),
+        metadata: StreamMetadata::default(),
+        data,
+    }))))
+}
@@ -0,0 +1,144 @@
+//! Support for databases with JDBC drivers, using a bundled Java helper.
+//!
+//! Some databases only ship JDBC drivers. Rather than linking against a JVM, we
+//! run a small Java program (see `DbcrossbarJdbc.java`) which exchanges CSV
+//! data with us over pipes, and describes schemas using JSON.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+
+mod data_type;
+mod local_data;
+mod shim;
+mod write_local_data;
+
+use self::data_type::JdbcSchema;
+use self::shim::{run_shim, shim_command};
+use local_data::local_data_helper;
+use write_local_data::write_local_data_helper;
+
+/// A table accessed using JDBC, such as
+/// `jdbc:sqlserver://db.example.com;databaseName=sales#dbo.orders`.
+///
+/// Everything before the `#` is passed to JDBC, and everything after it is
+/// used as a table name in SQL, without quoting.
+#[derive(Clone, Debug)]
+pub(crate) struct JdbcLocator {
+    /// Our JDBC URL, including the `jdbc:` prefix.
+    url: String,
+    /// The name of our table.
+    table: String,
+}
+
+impl fmt::Display for JdbcLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.url, self.table)
+    }
+}
+
+impl FromStr for JdbcLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if !s.starts_with(Self::scheme()) {
+            return Err(format_err!("expected {:?} to start with \"jdbc:\"", s));
+        }
+        match s.rfind('#') {
+            Some(pos) if pos > Self::scheme().len() && pos + 1 < s.len() => {
+                Ok(JdbcLocator {
+                    url: s[..pos].to_owned(),
+                    table: s[pos + 1..].to_owned(),
+                })
+            }
+            _ => Err(format_err!("expected {:?} to end with #table", s)),
+        }
+    }
+}
+
+#[test]
+fn parse_and_display() {
+    let s = "jdbc:sqlserver://db.example.com;databaseName=sales#dbo.orders";
+    let loc = s.parse::<JdbcLocator>().unwrap();
+    assert_eq!(
+        loc.url,
+        "jdbc:sqlserver://db.example.com;databaseName=sales"
+    );
+    assert_eq!(loc.table, "dbo.orders");
+    assert_eq!(loc.to_string(), s);
+
+    for bad in &["jdbc:h2:mem:test", "jdbc:h2:mem:test#", "jdbc:#t", "h2:x#t"] {
+        assert!(bad.parse::<JdbcLocator>().is_err());
+    }
+}
+
+impl Locator for JdbcLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self, ctx: Context) -> BoxFuture<Option<Table>> {
+        let source = self.clone();
+        async move {
+            debug!(ctx.log(), "reading schema of {} using JDBC", source);
+            let command = shim_command(&["schema", &source.url, &source.table])?;
+            let output = run_shim(command).await?;
+            let schema = serde_json::from_str::<JdbcSchema>(&output)
+                .context("could not parse schema from JDBC helper")?;
+            let columns = schema
+                .columns
+                .iter()
+                .map(|c| c.to_column())
+                .collect::<Result<Vec<_>>>()
+                .with_context(|_| format!("could not get columns of {}", source))?;
+            Ok(Some(Table {
+                name: source.table.clone(),
+                columns,
+            }))
+        }
+        .boxed()
+    }
+
+    fn local_data(
+        &self,
+        ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Option<BoxStream<CsvStream>>> {
+        local_data_helper(ctx, self.clone(), shared_args, source_args).boxed()
+    }
+
+    fn write_local_data(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+        shared_args: SharedArguments<Unverified>,
+        dest_args: DestinationArguments<Unverified>,
+    ) -> BoxFuture<BoxStream<BoxFuture<BoxLocator>>> {
+        write_local_data_helper(ctx, self.clone(), data, shared_args, dest_args)
+            .boxed()
+    }
+}
+
+impl LocatorStatic for JdbcLocator {
+    fn scheme() -> &'static str {
+        "jdbc:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::Schema
+                | LocatorFeatures::LocalData
+                | LocatorFeatures::WriteLocalData,
+            write_schema_if_exists: EnumSet::empty(),
+            source_args: SourceArgumentsFeatures::WhereClause.into(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: IfExistsFeatures::Overwrite | IfExistsFeatures::Append,
+            _placeholder: (),
+        }
+    }
+
+    fn is_unstable() -> bool {
+        true
+    }
+}
@@ -0,0 +1,108 @@
+//! Running our bundled Java helper, which talks to JDBC drivers for us.
+
+use crc32c::crc32c;
+use futures::future;
+use std::{env, fs, path::PathBuf, process::Stdio};
+use tokio::{io::BufReader, process::Command};
+
+use crate::common::*;
+use crate::process::wait_for_process;
+use crate::tokio_glue::{copy_reader_to_stream, copy_stream_to_writer};
+
+/// The source code of our Java helper.
+const SHIM_SOURCE: &str = include_str!("DbcrossbarJdbc.java");
+
+/// Write our Java helper to a temporary directory, unless it's already there,
+/// and return its path.
+///
+/// We include a checksum in the file name, so that different versions of
+/// `dbcrossbar` don't run each other's helpers.
+fn shim_path() -> Result<PathBuf> {
+    let path = env::temp_dir().join(format!(
+        "dbcrossbar-jdbc-{:08x}.java",
+        crc32c(SHIM_SOURCE.as_bytes())
+    ));
+    if !path.exists() {
+        // Write to a temporary file and rename it, so that other processes
+        // never see a partial file.
+        let tmp_path =
+            path.with_extension(format!("{}.tmp", TemporaryStorage::random_tag()));
+        fs::write(&tmp_path, SHIM_SOURCE)
+            .with_context(|_| format!("could not write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|_| format!("could not write {}", path.display()))?;
+    }
+    Ok(path)
+}
+
+/// Build a command which runs our Java helper with `args`.
+///
+/// We use Java's single-file source launcher, so we don't need to ship a
+/// compiled `.jar`. JDBC drivers are found using the usual `CLASSPATH`.
+pub(crate) fn shim_command(args: &[&str]) -> Result<Command> {
+    let mut command = Command::new("java");
+    command.arg(shim_path()?).args(args);
+    Ok(command)
+}
+
+/// Run `command` and return its standard output.
+pub(crate) async fn run_shim(mut command: Command) -> Result<String> {
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("error running `java` (is it installed?)")?;
+    if !output.status.success() {
+        return Err(format_err!(
+            "JDBC helper failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim(),
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)
+        .context("JDBC helper output was not valid UTF-8")?)
+}
+
+/// Run `command` in the background, and return its standard output as a
+/// stream.
+pub(crate) fn run_shim_with_output(
+    ctx: &Context,
+    mut command: Command,
+) -> Result<BoxStream<BytesMut>> {
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("error running `java` (is it installed?)")?;
+    let child_stdout = ctx.spawn_process_with_stdout("JDBC helper".to_owned(), child);
+    let child_stdout = BufReader::with_capacity(BUFFER_SIZE, child_stdout);
+    let data = copy_reader_to_stream(ctx.clone(), child_stdout)?;
+    Ok(data.boxed())
+}
+
+/// Run `command`, feeding it `data` on standard input.
+pub(crate) async fn run_shim_with_input(
+    ctx: &Context,
+    mut command: Command,
+    data: BoxStream<BytesMut>,
+) -> Result<()> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        // Throw away stdout so it doesn't corrupt our output.
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("error running `java` (is it installed?)")?;
+    let child_stdin = child.stdin.take().expect("child should have stdin");
+    let (copy_result, wait_result) = future::join(
+        copy_stream_to_writer(ctx.clone(), data, child_stdin),
+        wait_for_process(ctx, "JDBC helper", child, None),
+    )
+    .await;
+    // If the helper failed, that's probably why our copy failed, so report it
+    // first.
+    wait_result?;
+    copy_result.context("error copying data to JDBC helper")?;
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! Writing data using JDBC.
+
+use super::{
+    shim::{run_shim_with_input, shim_command},
+    JdbcLocator,
+};
+use crate::common::*;
+use crate::concat::concatenate_csv_streams;
+
+/// Implementation of `write_local_data`, but as a real `async` function.
+pub(crate) async fn write_local_data_helper(
+    ctx: Context,
+    dest: JdbcLocator,
+    data: BoxStream<CsvStream>,
+    shared_args: SharedArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
+    let _shared_args = shared_args.verify(JdbcLocator::features())?;
+    let dest_args = dest_args.verify(JdbcLocator::features())?;
+
+    // We can't create tables portably, so our helper only deletes or inserts
+    // rows.
+    let mode = match dest_args.if_exists() {
+        IfExists::Append => "append",
+        IfExists::Overwrite => "overwrite",
+        other => {
+            return Err(format_err!("jdbc: does not support --if-exists={}", other))
+        }
+    };
+
+    // Our helper reads CSV in our interchange format, and uses the header row
+    // to find the columns.
+    let stream = concatenate_csv_streams(ctx.clone(), data)?;
+    let fut = async move {
+        debug!(ctx.log(), "importing {} using JDBC", dest);
+        let command = shim_command(&["import", &dest.url, &dest.table, mode])?;
+        run_shim_with_input(&ctx, command, stream.data)
+            .await
+            .with_context(|_| format!("error writing to {}", dest))?;
+        Ok(dest.boxed())
+    };
+    Ok(box_stream_once(Ok(fut.boxed())))
+}
@@ -0,0 +1,493 @@
+//! Converting between JSON Schemas and our portable schemas.
+//!
+//! See https://json-schema.org/draft/2020-12/json-schema-core.html for the
+//! format.
+
+use serde::{
+    de::{Deserializer, MapAccess, Visitor},
+    ser::{SerializeMap, Serializer},
+};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+use crate::common::*;
+use crate::schema::{Column, DataType, StructField};
+
+/// The draft of JSON Schema that we generate.
+const SCHEMA_URL: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// A custom `format` which we use for decimal values stored as strings. JSON
+/// Schema validators ignore formats they don't recognize.
+const DECIMAL_FORMAT: &str = "decimal";
+
+/// A custom `format` which we use for timestamps without a time zone, because
+/// the standard `date-time` format requires a time zone offset.
+const LOCAL_DATE_TIME_FORMAT: &str = "local-date-time";
+
+/// The subset of JSON Schema that we support.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct JsonSchema {
+    /// The draft of JSON Schema used. Only present at the top level.
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    schema: Option<String>,
+
+    /// A short name for this schema.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+
+    /// A description of this value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+
+    /// The allowed JSON type or types.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    ty: Option<SchemaType>,
+
+    /// The format of a string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    format: Option<String>,
+
+    /// The minimum value of a number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    minimum: Option<i64>,
+
+    /// The maximum value of a number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    maximum: Option<i64>,
+
+    /// The properties of an object.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    properties: Option<Properties>,
+
+    /// The properties of an object which may not be omitted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    required: Vec<String>,
+
+    /// The schema of the items in an array.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    items: Option<Box<JsonSchema>>,
+
+    /// A reference to another schema. We don't support these, but we want to
+    /// report an error instead of ignoring them.
+    #[serde(rename = "$ref", default, skip_serializing)]
+    reference: Option<Value>,
+
+    /// Schema combinators, which we don't support.
+    #[serde(rename = "allOf", default, skip_serializing)]
+    all_of: Option<Value>,
+    #[serde(rename = "anyOf", default, skip_serializing)]
+    any_of: Option<Value>,
+    #[serde(rename = "oneOf", default, skip_serializing)]
+    one_of: Option<Value>,
+}
+
+/// Either a single type name, or a list of type names.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum SchemaType {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The properties of an object, in the order they were declared.
+///
+/// We can't use `serde_json::Map` for this, because it sorts keys
+/// alphabetically, and column order matters.
+#[derive(Debug, Default)]
+struct Properties(Vec<(String, JsonSchema)>);
+
+impl<'de> serde::Deserialize<'de> for Properties {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct PropertiesVisitor;
+
+        impl<'de> Visitor<'de> for PropertiesVisitor {
+            type Value = Properties;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of property schemas")
+            }
+
+            fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+            where
+                M: MapAccess<'de>,
+            {
+                let mut properties = vec![];
+                while let Some((name, schema)) = map.next_entry()? {
+                    properties.push((name, schema));
+                }
+                Ok(Properties(properties))
+            }
+        }
+
+        deserializer.deserialize_map(PropertiesVisitor)
+    }
+}
+
+impl serde::Serialize for Properties {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, schema) in &self.0 {
+            map.serialize_entry(name, schema)?;
+        }
+        map.end()
+    }
+}
+
+/// Parse a JSON Schema describing an object, and convert it into a table.
+pub(crate) fn table_from_json_schema(data: &[u8]) -> Result<Table> {
+    let schema: JsonSchema = serde_json::from_slice(data)?;
+    let name = schema.title.clone().unwrap_or_else(|| "unnamed".to_owned());
+    let columns = object_fields(&schema)?
+        .into_iter()
+        .map(|(field, comment)| Column {
+            name: field.name,
+            is_nullable: field.is_nullable,
+            data_type: field.data_type,
+            comment,
+        })
+        .collect();
+    Ok(Table { name, columns })
+}
+
+/// Convert the properties of an object schema, returning each field's
+/// description.
+fn object_fields(schema: &JsonSchema) -> Result<Vec<(StructField, Option<String>)>> {
+    let (ty, _) = schema_type(schema)?;
+    if ty != Some("object") {
+        return Err(format_err!(
+            "expected a JSON Schema with \"type\": \"object\""
+        ));
+    }
+    let properties = schema
+        .properties
+        .as_ref()
+        .ok_or_else(|| format_err!("expected \"properties\" in JSON Schema object"))?;
+    let mut result = vec![];
+    for (name, prop) in &properties.0 {
+        let (data_type, accepts_null) = data_type_from_json_schema(prop)
+            .with_context(|_| format!("error in JSON Schema property {}", name))?;
+        result.push((
+            StructField {
+                name: name.to_owned(),
+                is_nullable: accepts_null || !schema.required.contains(name),
+                data_type,
+            },
+            prop.description.clone(),
+        ));
+    }
+    Ok(result)
+}
+
+/// Get the non-`null` type allowed by `schema`, if any, and whether `schema`
+/// allows `null`.
+fn schema_type(schema: &JsonSchema) -> Result<(Option<&str>, bool)> {
+    if schema.reference.is_some()
+        || schema.all_of.is_some()
+        || schema.any_of.is_some()
+        || schema.one_of.is_some()
+    {
+        return Err(format_err!(
+            "$ref, allOf, anyOf and oneOf are not supported in JSON Schemas"
+        ));
+    }
+    match &schema.ty {
+        None => Ok((None, true)),
+        Some(SchemaType::One(ty)) if ty == "null" => {
+            Err(format_err!("cannot convert JSON Schema type \"null\""))
+        }
+        Some(SchemaType::One(ty)) => Ok((Some(ty), false)),
+        Some(SchemaType::Many(types)) => {
+            let accepts_null = types.iter().any(|ty| ty == "null");
+            let others = types.iter().filter(|ty| *ty != "null").collect::<Vec<_>>();
+            match &others[..] {
+                [ty] => Ok((Some(&ty[..]), accepts_null)),
+                _ => Err(format_err!(
+                    "cannot convert JSON Schema with types {:?}",
+                    types
+                )),
+            }
+        }
+    }
+}
+
+/// Convert a JSON Schema into a portable data type, and return whether the
+/// schema also accepts `null`.
+fn data_type_from_json_schema(schema: &JsonSchema) -> Result<(DataType, bool)> {
+    let (ty, accepts_null) = schema_type(schema)?;
+    let data_type = match ty {
+        // A schema without a type can hold any JSON value.
+        None => DataType::Json,
+        Some("array") => {
+            let items = schema.items.as_ref().ok_or_else(|| {
+                format_err!("expected \"items\" in JSON Schema array")
+            })?;
+            DataType::Array(Box::new(data_type_from_json_schema(items)?.0))
+        }
+        Some("boolean") => DataType::Bool,
+        Some("integer") => {
+            let fits = |min: i64, max: i64| {
+                matches!(schema.minimum, Some(n) if n >= min)
+                    && matches!(schema.maximum, Some(n) if n <= max)
+            };
+            if fits(i64::from(i16::MIN), i64::from(i16::MAX)) {
+                DataType::Int16
+            } else if fits(i64::from(i32::MIN), i64::from(i32::MAX)) {
+                DataType::Int32
+            } else {
+                DataType::Int64
+            }
+        }
+        Some("number") => DataType::Float64,
+        // Objects without declared properties are arbitrary JSON.
+        Some("object") if schema.properties.is_none() => DataType::Json,
+        Some("object") => DataType::Struct(
+            object_fields(schema)?
+                .into_iter()
+                .map(|(field, _description)| field)
+                .collect(),
+        ),
+        Some("string") => match schema.format.as_deref() {
+            Some("date") => DataType::Date,
+            Some("date-time") => DataType::TimestampWithTimeZone,
+            Some("uuid") => DataType::Uuid,
+            Some(DECIMAL_FORMAT) => DataType::Decimal,
+            Some(LOCAL_DATE_TIME_FORMAT) => DataType::TimestampWithoutTimeZone,
+            _ => DataType::Text,
+        },
+        Some(other) => {
+            return Err(format_err!("unsupported JSON Schema type {:?}", other))
+        }
+    };
+    Ok((data_type, accepts_null))
+}
+
+/// Generate a JSON Schema describing the rows of `table`.
+pub(crate) fn json_schema_for_table(table: &Table) -> Result<String> {
+    let fields = table
+        .columns
+        .iter()
+        .map(|col| {
+            (
+                StructField {
+                    name: col.name.clone(),
+                    is_nullable: col.is_nullable,
+                    data_type: col.data_type.clone(),
+                },
+                col.comment.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let schema = JsonSchema {
+        schema: Some(SCHEMA_URL.to_owned()),
+        title: Some(table.name.clone()),
+        ..object_schema(&fields)
+    };
+    let mut json = serde_json::to_string_pretty(&schema)?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// Build an object schema containing `fields`.
+fn object_schema(fields: &[(StructField, Option<String>)]) -> JsonSchema {
+    let properties = fields
+        .iter()
+        .map(|(field, description)| {
+            let mut schema = json_schema_for_data_type(&field.data_type);
+            if field.is_nullable {
+                schema.ty = match schema.ty {
+                    Some(SchemaType::One(ty)) => {
+                        Some(SchemaType::Many(vec![ty, "null".to_owned()]))
+                    }
+                    // A schema without a type already allows `null`.
+                    ty => ty,
+                };
+            }
+            schema.description = description.clone();
+            (field.name.clone(), schema)
+        })
+        .collect();
+    JsonSchema {
+        ty: Some(SchemaType::One("object".to_owned())),
+        properties: Some(Properties(properties)),
+        required: fields
+            .iter()
+            .filter(|(field, _)| !field.is_nullable)
+            .map(|(field, _)| field.name.clone())
+            .collect(),
+        ..JsonSchema::default()
+    }
+}
+
+/// Build a JSON Schema for values of type `data_type`.
+fn json_schema_for_data_type(data_type: &DataType) -> JsonSchema {
+    let simple = |ty: &str| JsonSchema {
+        ty: Some(SchemaType::One(ty.to_owned())),
+        ..JsonSchema::default()
+    };
+    let string_with_format = |format: &str| JsonSchema {
+        format: Some(format.to_owned()),
+        ..simple("string")
+    };
+    let integer_between = |min: i64, max: i64| JsonSchema {
+        minimum: Some(min),
+        maximum: Some(max),
+        ..simple("integer")
+    };
+    match data_type {
+        DataType::Array(elem_type) => JsonSchema {
+            items: Some(Box::new(json_schema_for_data_type(elem_type))),
+            ..simple("array")
+        },
+        DataType::Bool => simple("boolean"),
+        DataType::Date => string_with_format("date"),
+        // We represent decimals as strings, so that they're never rounded.
+        DataType::Decimal | DataType::FixedDecimal { .. } => {
+            string_with_format(DECIMAL_FORMAT)
+        }
+        DataType::Float32 | DataType::Float64 => simple("number"),
+        // We don't try to describe the structure of GeoJSON.
+        DataType::GeoJson(_) | DataType::Json => JsonSchema::default(),
+        DataType::Int16 => integer_between(i16::MIN.into(), i16::MAX.into()),
+        DataType::Int32 => integer_between(i32::MIN.into(), i32::MAX.into()),
+        DataType::Int64 => simple("integer"),
+        DataType::Struct(fields) => object_schema(
+            &fields
+                .iter()
+                .map(|f| (f.to_owned(), None))
+                .collect::<Vec<_>>(),
+        ),
+        DataType::Text => simple("string"),
+        DataType::TimestampWithoutTimeZone => {
+            string_with_format(LOCAL_DATE_TIME_FORMAT)
+        }
+        DataType::TimestampWithTimeZone => string_with_format("date-time"),
+        DataType::Uuid => string_with_format("uuid"),
+    }
+}
+
+#[test]
+fn table_from_json_schema_handles_nullability_and_formats() {
+    // We use a string here, because `json!` would sort our properties.
+    let schema = r#"{
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "events",
+        "type": "object",
+        "properties": {
+            "id": { "type": "string", "format": "uuid" },
+            "at": { "type": "string", "format": "date-time" },
+            "count": { "type": ["integer", "null"], "description": "How many" },
+            "score": { "type": "number" },
+            "tags": { "type": "array", "items": { "type": "string" } },
+            "extra": {},
+            "point": {
+                "type": "object",
+                "properties": { "x": { "type": "number" } },
+                "required": ["x"]
+            }
+        },
+        "required": ["id", "at", "count", "tags"]
+    }"#;
+    let table = table_from_json_schema(schema.as_bytes()).unwrap();
+    assert_eq!(table.name, "events");
+    let types = table
+        .columns
+        .iter()
+        .map(|c| (&c.name[..], c.is_nullable, c.data_type.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            ("id", false, DataType::Uuid),
+            ("at", false, DataType::TimestampWithTimeZone),
+            ("count", true, DataType::Int64),
+            ("score", true, DataType::Float64),
+            ("tags", false, DataType::Array(Box::new(DataType::Text))),
+            ("extra", true, DataType::Json),
+            (
+                "point",
+                true,
+                DataType::Struct(vec![StructField {
+                    name: "x".to_owned(),
+                    is_nullable: false,
+                    data_type: DataType::Float64,
+                }])
+            ),
+        ]
+    );
+    assert_eq!(table.columns[2].comment.as_deref(), Some("How many"));
+
+    for bad in &[
+        r#"{ "type": "array", "items": {} }"#,
+        r##"{ "type": "object", "properties": { "a": { "$ref": "#/$defs/a" } } }"##,
+        r#"{ "type": "object", "properties": { "a": { "type": ["string", "integer"] } } }"#,
+    ] {
+        assert!(table_from_json_schema(bad.as_bytes()).is_err());
+    }
+}
+
+#[test]
+fn json_schema_roundtrip() {
+    use crate::schema::Srid;
+
+    let column = |name: &str, is_nullable: bool, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let table = Table {
+        name: "orders".to_owned(),
+        columns: vec![
+            Column {
+                comment: Some("Unique ID".to_owned()),
+                ..column("id", false, DataType::Uuid)
+            },
+            column("flag", true, DataType::Bool),
+            column("day", true, DataType::Date),
+            column("total", false, DataType::Decimal),
+            column("score", true, DataType::Float64),
+            column("small", true, DataType::Int16),
+            column("medium", true, DataType::Int32),
+            column("large", false, DataType::Int64),
+            column("extra", true, DataType::Json),
+            column("name", true, DataType::Text),
+            column("local", true, DataType::TimestampWithoutTimeZone),
+            column("instant", true, DataType::TimestampWithTimeZone),
+            column(
+                "points",
+                true,
+                DataType::Array(Box::new(DataType::Struct(vec![StructField {
+                    name: "x".to_owned(),
+                    is_nullable: false,
+                    data_type: DataType::Float64,
+                }]))),
+            ),
+        ],
+    };
+    let json = json_schema_for_table(&table).unwrap();
+    let value: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["$schema"], SCHEMA_URL);
+    assert_eq!(
+        value["properties"]["flag"]["type"],
+        serde_json::json!(["boolean", "null"])
+    );
+    assert_eq!(table_from_json_schema(json.as_bytes()).unwrap(), table);
+
+    // GeoJSON is written as arbitrary JSON.
+    let geo = Table {
+        name: "geo".to_owned(),
+        columns: vec![column("shape", true, DataType::GeoJson(Srid::wgs84()))],
+    };
+    let json = json_schema_for_table(&geo).unwrap();
+    assert_eq!(
+        table_from_json_schema(json.as_bytes()).unwrap().columns[0].data_type,
+        DataType::Json,
+    );
+}
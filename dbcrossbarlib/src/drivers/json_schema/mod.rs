@@ -0,0 +1,102 @@
+//! Support for `json-schema` locators.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+
+mod convert;
+
+use self::convert::{json_schema_for_table, table_from_json_schema};
+
+/// A JSON Schema describing the objects in a newline-delimited JSON file.
+#[derive(Clone, Debug)]
+pub struct JsonSchemaLocator {
+    path: PathOrStdio,
+}
+
+impl fmt::Display for JsonSchemaLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.path.fmt_locator_helper(Self::scheme(), f)
+    }
+}
+
+impl FromStr for JsonSchemaLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), s)?;
+        Ok(JsonSchemaLocator { path })
+    }
+}
+
+impl Locator for JsonSchemaLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self, ctx: Context) -> BoxFuture<Option<Table>> {
+        schema_helper(ctx, self.to_owned()).boxed()
+    }
+
+    fn write_schema(
+        &self,
+        ctx: Context,
+        table: Table,
+        if_exists: IfExists,
+    ) -> BoxFuture<()> {
+        write_schema_helper(ctx, self.to_owned(), table, if_exists).boxed()
+    }
+}
+
+impl LocatorStatic for JsonSchemaLocator {
+    fn scheme() -> &'static str {
+        "json-schema:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::Schema | LocatorFeatures::WriteSchema,
+            write_schema_if_exists: IfExistsFeatures::no_append(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
+
+/// Implementation of `schema`, but as a real `async` function.
+async fn schema_helper(
+    _ctx: Context,
+    source: JsonSchemaLocator,
+) -> Result<Option<Table>> {
+    // Read our input.
+    let input = source.path.open_async().await?;
+    let data = async_read_to_end(input)
+        .await
+        .with_context(|_| format!("error reading {}", source.path))?;
+
+    // Parse our input and convert it.
+    let table = table_from_json_schema(&data)
+        .with_context(|_| format!("error parsing {}", source.path))?;
+    Ok(Some(table))
+}
+
+/// Implementation of `write_schema`, but as a real `async` function.
+async fn write_schema_helper(
+    ctx: Context,
+    dest: JsonSchemaLocator,
+    table: Table,
+    if_exists: IfExists,
+) -> Result<()> {
+    // Generate our JSON Schema before we create our output file.
+    let schema = json_schema_for_table(&table)?;
+
+    // Output our schema to our destination.
+    let mut f = dest.path.create_async(ctx, if_exists).await?;
+    buffer_sync_write_and_copy_to_async(&mut f, |buff| write!(buff, "{}", schema))
+        .await
+        .with_context(|_| format!("error writing to {}", dest.path))?;
+    f.flush().await?;
+    Ok(())
+}
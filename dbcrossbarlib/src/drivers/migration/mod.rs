@@ -0,0 +1,375 @@
+//! Schema-only driver for writing database migrations.
+
+use chrono::{DateTime, Utc};
+use percent_encoding::percent_decode_str;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tokio::fs;
+
+use crate::common::*;
+use crate::drivers::postgres_shared::{PgCreateTable, TableName};
+use crate::drivers::postgres_sql::sanitize_table_name;
+use crate::path_or_stdio::long_path;
+
+/// The directory layouts used by different migration tools.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MigrationLayout {
+    /// `$TIMESTAMP_$NAME/up.sql` and `$TIMESTAMP_$NAME/down.sql`.
+    Diesel,
+    /// `$TIMESTAMP_$NAME.up.sql` and `$TIMESTAMP_$NAME.down.sql`.
+    Sqlx,
+    /// `V$TIMESTAMP__$NAME.sql`, with no "down" migration.
+    Flyway,
+    /// A Liquibase YAML changelog named `$TIMESTAMP_$NAME.yaml`, containing a
+    /// changeset with a rollback.
+    Liquibase,
+}
+
+impl Default for MigrationLayout {
+    fn default() -> Self {
+        MigrationLayout::Diesel
+    }
+}
+
+impl fmt::Display for MigrationLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationLayout::Diesel => "diesel".fmt(f),
+            MigrationLayout::Sqlx => "sqlx".fmt(f),
+            MigrationLayout::Flyway => "flyway".fmt(f),
+            MigrationLayout::Liquibase => "liquibase".fmt(f),
+        }
+    }
+}
+
+impl FromStr for MigrationLayout {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "diesel" => Ok(MigrationLayout::Diesel),
+            "sqlx" => Ok(MigrationLayout::Sqlx),
+            "flyway" => Ok(MigrationLayout::Flyway),
+            "liquibase" => Ok(MigrationLayout::Liquibase),
+            _ => Err(format_err!(
+                "unknown migration layout {:?} (expected diesel, sqlx, flyway or liquibase)",
+                s
+            )),
+        }
+    }
+}
+
+/// A directory of migrations, such as `migration:migrations/?layout=sqlx`.
+#[derive(Clone, Debug)]
+pub struct MigrationLocator {
+    /// The directory containing our migrations.
+    dir: PathBuf,
+    /// Which tool's layout we should use.
+    layout: MigrationLayout,
+}
+
+impl fmt::Display for MigrationLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encode = |s: &str| s.replace('%', "%25").replace('?', "%3F");
+        write!(
+            f,
+            "{}{}",
+            Self::scheme(),
+            encode(&self.dir.to_string_lossy())
+        )?;
+        if self.layout != MigrationLayout::default() {
+            write!(f, "?layout={}", self.layout)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MigrationLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if !s.starts_with(Self::scheme()) {
+            return Err(format_err!(
+                "expected {:?} to start with {}",
+                s,
+                Self::scheme()
+            ));
+        }
+        let mut parts = s[Self::scheme().len()..].splitn(2, '?');
+        let dir = percent_decode_str(parts.next().expect("always one part"))
+            .decode_utf8()
+            .with_context(|_| format!("cannot decode {:?}", s))?;
+        if dir.is_empty() {
+            return Err(format_err!("expected a directory in {:?}", s));
+        }
+        let mut layout = MigrationLayout::default();
+        for param in parts.next().into_iter().flat_map(|q| q.split('&')) {
+            match param.splitn(2, '=').collect::<Vec<_>>()[..] {
+                ["layout", value] => layout = value.parse()?,
+                _ => {
+                    return Err(format_err!(
+                        "unknown option {:?} in {:?} (expected layout)",
+                        param,
+                        s
+                    ))
+                }
+            }
+        }
+        Ok(MigrationLocator {
+            dir: PathBuf::from(dir.into_owned()),
+            layout,
+        })
+    }
+}
+
+#[test]
+fn parse_and_display() {
+    let loc = "migration:db/migrations/?layout=sqlx"
+        .parse::<MigrationLocator>()
+        .unwrap();
+    assert_eq!(loc.dir, Path::new("db/migrations/"));
+    assert_eq!(loc.layout, MigrationLayout::Sqlx);
+
+    let loc = "migration:migrations/".parse::<MigrationLocator>().unwrap();
+    assert_eq!(loc.layout, MigrationLayout::Diesel);
+
+    for bad in &[
+        "migration:",
+        "migration:migrations/?layout=rails",
+        "migration:migrations/?dir=x",
+    ] {
+        assert!(bad.parse::<MigrationLocator>().is_err());
+    }
+}
+
+impl Locator for MigrationLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn write_schema(
+        &self,
+        ctx: Context,
+        table: Table,
+        if_exists: IfExists,
+    ) -> BoxFuture<()> {
+        write_schema_helper(ctx, self.to_owned(), table, if_exists).boxed()
+    }
+}
+
+impl LocatorStatic for MigrationLocator {
+    fn scheme() -> &'static str {
+        "migration:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::WriteSchema.into(),
+            write_schema_if_exists: IfExistsFeatures::no_append(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
+
+/// Implementation of `write_schema`, but as a real `async` function.
+async fn write_schema_helper(
+    ctx: Context,
+    dest: MigrationLocator,
+    table: Table,
+    if_exists: IfExists,
+) -> Result<()> {
+    // Generate our migration before we create any files.
+    let files = migration_files(&dest.dir, dest.layout, &table, Utc::now())?;
+
+    for (path, sql) in files {
+        let dir = path.parent().ok_or_else(|| {
+            format_err!("cannot find parent dir for {}", path.display())
+        })?;
+        fs::create_dir_all(long_path(dir)?)
+            .await
+            .with_context(|_| {
+                format!("unable to create directory {}", dir.display())
+            })?;
+        debug!(ctx.log(), "writing migration {}", path.display());
+        let mut f = if_exists
+            .to_async_open_options_no_append()?
+            .open(long_path(&path)?)
+            .await
+            .with_context(|_| format!("cannot open {}", path.display()))?;
+        f.write_all(sql.as_bytes())
+            .await
+            .with_context(|_| format!("error writing to {}", path.display()))?;
+        f.flush().await?;
+    }
+    Ok(())
+}
+
+/// Generate the paths and contents of the migration files needed to create
+/// `table`.
+fn migration_files(
+    dir: &Path,
+    layout: MigrationLayout,
+    table: &Table,
+    now: DateTime<Utc>,
+) -> Result<Vec<(PathBuf, String)>> {
+    let table_name = sanitize_table_name(&table.name)?.parse::<TableName>()?;
+    let create_table =
+        PgCreateTable::from_name_and_columns(table_name.clone(), &table.columns)?;
+    let up = create_table.to_string();
+    let down = format!("DROP TABLE {};\n", table_name.quoted());
+
+    // Build a migration name like `create_my_table`.
+    let name = format!(
+        "create_{}",
+        table_name
+            .table()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            })
+            .collect::<String>()
+    );
+
+    Ok(match layout {
+        MigrationLayout::Diesel => {
+            let migration_dir =
+                dir.join(format!("{}_{}", now.format("%Y-%m-%d-%H%M%S"), name));
+            vec![
+                (migration_dir.join("up.sql"), up),
+                (migration_dir.join("down.sql"), down),
+            ]
+        }
+        MigrationLayout::Sqlx => {
+            let prefix = format!("{}_{}", now.format("%Y%m%d%H%M%S"), name);
+            vec![
+                (dir.join(format!("{}.up.sql", prefix)), up),
+                (dir.join(format!("{}.down.sql", prefix)), down),
+            ]
+        }
+        // Flyway's "undo" migrations are only available in paid versions, so
+        // we don't generate one.
+        MigrationLayout::Flyway => vec![(
+            dir.join(format!("V{}__{}.sql", now.format("%Y%m%d%H%M%S"), name)),
+            up,
+        )],
+        MigrationLayout::Liquibase => {
+            let id = format!("{}-{}", now.format("%Y%m%d%H%M%S"), name);
+            let mut changelog = String::new();
+            changelog.push_str("databaseChangeLog:\n");
+            changelog.push_str("  - changeSet:\n");
+            changelog.push_str(&format!("      id: {}\n", id));
+            changelog.push_str("      author: dbcrossbar\n");
+            changelog.push_str("      dbms: postgresql\n");
+            changelog.push_str("      changes:\n");
+            changelog.push_str("        - sql:\n");
+            changelog.push_str(&yaml_block("sql", &up, 12));
+            changelog.push_str("      rollback:\n");
+            changelog.push_str("        - sql:\n");
+            changelog.push_str(&yaml_block("sql", &down, 12));
+            vec![(
+                dir.join(format!("{}_{}.yaml", now.format("%Y%m%d%H%M%S"), name)),
+                changelog,
+            )]
+        }
+    })
+}
+
+/// Format `value` as a YAML literal block scalar named `key`, indented by
+/// `indent` spaces. This preserves `value` exactly, except that it always ends
+/// with a single newline.
+fn yaml_block(key: &str, value: &str, indent: usize) -> String {
+    let prefix = " ".repeat(indent);
+    let mut out = format!("{}{}: |\n", prefix, key);
+    for line in value.trim_end().lines() {
+        if line.is_empty() {
+            out.push('\n');
+        } else {
+            out.push_str(&format!("{}  {}\n", prefix, line));
+        }
+    }
+    out
+}
+
+#[test]
+fn migration_files_uses_layout() {
+    use crate::schema::{Column, DataType};
+
+    let table = Table {
+        name: "public.Orders".to_owned(),
+        columns: vec![Column {
+            name: "id".to_owned(),
+            is_nullable: false,
+            data_type: DataType::Int64,
+            comment: None,
+        }],
+    };
+    let now = "2020-09-01T12:34:56Z".parse::<DateTime<Utc>>().unwrap();
+    let paths = |layout| {
+        migration_files(Path::new("m"), layout, &table, now)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        paths(MigrationLayout::Diesel),
+        vec![
+            Path::new("m/2020-09-01-123456_create_orders/up.sql"),
+            Path::new("m/2020-09-01-123456_create_orders/down.sql"),
+        ],
+    );
+    assert_eq!(
+        paths(MigrationLayout::Sqlx),
+        vec![
+            Path::new("m/20200901123456_create_orders.up.sql"),
+            Path::new("m/20200901123456_create_orders.down.sql"),
+        ],
+    );
+    assert_eq!(
+        paths(MigrationLayout::Flyway),
+        vec![Path::new("m/V20200901123456__create_orders.sql")],
+    );
+    assert_eq!(
+        paths(MigrationLayout::Liquibase),
+        vec![Path::new("m/20200901123456_create_orders.yaml")],
+    );
+
+    let files =
+        migration_files(Path::new("m"), MigrationLayout::Sqlx, &table, now).unwrap();
+    assert!(files[0]
+        .1
+        .starts_with("CREATE TABLE \"public\".\"Orders\" ("));
+    assert_eq!(files[1].1, "DROP TABLE \"public\".\"Orders\";\n");
+
+    let files =
+        migration_files(Path::new("m"), MigrationLayout::Liquibase, &table, now)
+            .unwrap();
+    assert_eq!(
+        files[0].1,
+        r#"databaseChangeLog:
+  - changeSet:
+      id: 20200901123456-create_orders
+      author: dbcrossbar
+      dbms: postgresql
+      changes:
+        - sql:
+            sql: |
+              CREATE TABLE "public"."Orders" (
+                  "id" bigint NOT NULL
+              );
+      rollback:
+        - sql:
+            sql: |
+              DROP TABLE "public"."Orders";
+"#,
+    );
+}
@@ -8,20 +8,65 @@ use std::collections::HashMap;
 use crate::common::*;
 use crate::locator::{LocatorDriver, LocatorDriverWrapper};
 
+#[cfg(feature = "abfss")]
+pub mod abfss;
+pub mod avro_schema;
+#[cfg(feature = "bigml")]
 pub mod bigml;
+#[cfg(feature = "bigquery")]
 pub mod bigquery;
+#[cfg(feature = "bigquery")]
 pub mod bigquery_schema;
+#[cfg(feature = "bigquery")]
 pub mod bigquery_shared;
 pub mod csv;
+#[cfg(feature = "db2")]
+pub mod db2;
 pub mod dbcrossbar_schema;
 pub mod dbcrossbar_ts;
+pub mod dbt_schema;
+pub mod exec;
+pub mod fake;
+#[cfg(feature = "file")]
+pub mod file;
+pub mod graphql_schema;
+#[cfg(feature = "gs")]
 pub mod gs;
+#[cfg(feature = "hive")]
+pub mod hive;
+#[cfg(feature = "hive")]
+pub mod hive_sql;
+#[cfg(feature = "https")]
+pub mod https;
+#[cfg(feature = "jdbc")]
+pub mod jdbc;
+pub mod json_schema;
+#[cfg(feature = "postgres")]
+pub mod migration;
+pub mod mysql_sql;
+pub mod null;
+pub mod parquet_schema;
+#[cfg(feature = "postgres")]
 pub mod postgres;
+#[cfg(feature = "postgres")]
 pub mod postgres_shared;
+#[cfg(feature = "postgres")]
 pub mod postgres_sql;
+pub mod proto_schema;
+#[cfg(feature = "redshift")]
 pub mod redshift;
+pub mod rust_schema;
+#[cfg(feature = "s3")]
 pub mod s3;
+#[cfg(feature = "shopify")]
 pub mod shopify;
+#[cfg(feature = "singer")]
+pub mod singer;
+pub mod spark_schema;
+pub mod table_schema;
+pub mod ts_schema;
+#[cfg(feature = "webdav")]
+pub mod webdav;
 
 /// A helper which builds a `Box<dyn LocatorDriver>` for a type implementating
 /// `LocatorStatic`.
@@ -32,18 +77,63 @@ fn driver<L: LocatorStatic>() -> Box<dyn LocatorDriver> {
 lazy_static! {
     /// A list of known drivers, computed the first time we use it and cached.
     static ref KNOWN_DRIVERS: Vec<Box<dyn LocatorDriver>> = vec![
+        #[cfg(feature = "abfss")]
+        driver::<abfss::AbfssLocator>(),
+        driver::<avro_schema::AvroSchemaLocator>(),
+        #[cfg(feature = "bigml")]
         driver::<bigml::BigMlLocator>(),
+        #[cfg(feature = "bigquery")]
         driver::<bigquery::BigQueryLocator>(),
+        #[cfg(feature = "bigquery")]
         driver::<bigquery_schema::BigQuerySchemaLocator>(),
         driver::<csv::CsvLocator>(),
+        #[cfg(feature = "db2")]
+        driver::<db2::Db2Locator>(),
         driver::<dbcrossbar_schema::DbcrossbarSchemaLocator>(),
         driver::<dbcrossbar_ts::DbcrossbarTsLocator>(),
+        driver::<dbt_schema::DbtSchemaLocator>(),
+        driver::<exec::ExecLocator>(),
+        driver::<fake::FakeLocator>(),
+        #[cfg(feature = "file")]
+        driver::<file::FileLocator>(),
+        driver::<graphql_schema::GraphqlSchemaLocator>(),
+        #[cfg(feature = "gs")]
         driver::<gs::GsLocator>(),
+        #[cfg(feature = "hive")]
+        driver::<hive::HiveLocator>(),
+        #[cfg(feature = "hive")]
+        driver::<hive_sql::HiveSqlLocator>(),
+        #[cfg(feature = "https")]
+        driver::<https::HttpsLocator>(),
+        #[cfg(feature = "jdbc")]
+        driver::<jdbc::JdbcLocator>(),
+        driver::<json_schema::JsonSchemaLocator>(),
+        #[cfg(feature = "postgres")]
+        driver::<migration::MigrationLocator>(),
+        driver::<mysql_sql::MysqlSqlLocator>(),
+        driver::<null::NullLocator>(),
+        driver::<parquet_schema::ParquetSchemaLocator>(),
+        #[cfg(feature = "postgres")]
         driver::<postgres::PostgresLocator>(),
+        #[cfg(feature = "postgres")]
         driver::<postgres_sql::PostgresSqlLocator>(),
+        driver::<proto_schema::ProtoSchemaLocator>(),
+        #[cfg(feature = "redshift")]
         driver::<redshift::RedshiftLocator>(),
+        driver::<rust_schema::RustSchemaLocator>(),
+        #[cfg(feature = "s3")]
         driver::<s3::S3Locator>(),
+        #[cfg(feature = "shopify")]
         driver::<shopify::ShopifyLocator>(),
+        #[cfg(feature = "singer")]
+        driver::<singer::SingerTapLocator>(),
+        #[cfg(feature = "singer")]
+        driver::<singer::SingerTargetLocator>(),
+        driver::<spark_schema::SparkSchemaLocator>(),
+        driver::<table_schema::TableSchemaLocator>(),
+        driver::<ts_schema::TsSchemaLocator>(),
+        #[cfg(feature = "webdav")]
+        driver::<webdav::WebDavLocator>(),
     ];
 
     /// A hash table of all known drivers, indexed by scheme and computed the
@@ -0,0 +1,425 @@
+//! A [`rust-peg`][peg] grammar for MySQL `CREATE TABLE` statements.
+//!
+//! We only care about column names, types, nullability and comments, so we
+//! skip over indices, constraints, default values and table options without
+//! trying to understand them.
+//!
+//! [peg]: https://github.com/kevinmehall/rust-peg
+
+use std::sync::Arc;
+
+use crate::common::*;
+use crate::parse_error::{Annotation, FileInfo, ParseError};
+use crate::schema::{Column, DataType, Srid};
+
+/// Parse a MySQL `CREATE TABLE` statement.
+pub(crate) fn parse(
+    file_name: String,
+    file_contents: String,
+) -> Result<Table, ParseError> {
+    let file_info = Arc::new(FileInfo::new(file_name, file_contents));
+    create_table_grammar::create_table(&file_info.contents).map_err(|err| {
+        ParseError::new(
+            file_info,
+            vec![Annotation::primary(
+                err.location.offset,
+                format!("expected {}", err.expected),
+            )],
+            "error parsing MySQL CREATE TABLE",
+        )
+    })
+}
+
+/// Extra information that may appear after a column's type.
+enum ColumnAttribute {
+    NotNull,
+    Null,
+    Comment(String),
+    Srid(u32),
+    /// An attribute that we don't care about.
+    Other,
+}
+
+/// Build a column from its parts.
+fn build_column(
+    name: String,
+    data_type: DataType,
+    attributes: Vec<ColumnAttribute>,
+) -> Column {
+    let mut column = Column {
+        name,
+        is_nullable: true,
+        data_type,
+        comment: None,
+    };
+    for attribute in attributes {
+        match attribute {
+            ColumnAttribute::NotNull => column.is_nullable = false,
+            ColumnAttribute::Null => column.is_nullable = true,
+            ColumnAttribute::Comment(comment) => column.comment = Some(comment),
+            ColumnAttribute::Srid(srid) => {
+                if let DataType::GeoJson(_) = column.data_type {
+                    column.data_type = DataType::GeoJson(Srid::new(srid));
+                }
+            }
+            ColumnAttribute::Other => {}
+        }
+    }
+    column
+}
+
+/// Remove backslash escapes and doubled quotes from the body of a string
+/// literal.
+fn unescape_string(quote: char, s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('0') => result.push('\0'),
+                Some('b') => result.push('\x08'),
+                Some('n') => result.push('\n'),
+                Some('r') => result.push('\r'),
+                Some('t') => result.push('\t'),
+                Some('Z') => result.push('\x1a'),
+                Some(other) => result.push(other),
+                None => {}
+            },
+            c if c == quote => {
+                // Our grammar only allows quotes to appear doubled.
+                chars.next();
+                result.push(quote);
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+peg::parser! {
+    grammar create_table_grammar() for str {
+        /// A `CREATE TABLE` expression.
+        pub rule create_table() -> Table
+            = ws()? kw("CREATE") ws() (kw("TEMPORARY") ws())? kw("TABLE") ws()
+                (kw("IF") ws() kw("NOT") ws() kw("EXISTS") ws())?
+                name:table_name() ws()? "("
+                ws()? elements:(table_element() ** (ws()? "," ws()?)) ws()?
+            ")" table_options() ws()? (";" ws()?)?
+            {
+                Table {
+                    name,
+                    columns: elements.into_iter().flatten().collect(),
+                }
+            }
+
+        /// A column definition, or an index or constraint that we ignore.
+        rule table_element() -> Option<Column>
+            = constraint() { None }
+            / column:column() { Some(column) }
+
+        /// An index or constraint. These all start with reserved words, so
+        /// they can't be confused with unquoted column names.
+        rule constraint()
+            = (
+                kw("CONSTRAINT") / kw("PRIMARY") / kw("UNIQUE") / kw("KEY")
+                / kw("INDEX") / kw("FULLTEXT") / kw("SPATIAL") / kw("FOREIGN")
+                / kw("CHECK")
+            )
+            (string_literal() {} / parens() / !['(' | ')' | ',' | '\'' | '"'] [_])*
+
+        /// A column definition of the form "name type attributes...".
+        rule column() -> Column
+            = name:identifier() ws() data_type:data_type()
+                attributes:(ws() attribute:column_attribute() { attribute })*
+            {
+                build_column(name, data_type, attributes)
+            }
+
+        /// Something which may appear after a column's type.
+        rule column_attribute() -> ColumnAttribute
+            = kw("NOT") ws() kw("NULL") { ColumnAttribute::NotNull }
+            / kw("NULL") { ColumnAttribute::Null }
+            / kw("COMMENT") ws() comment:string_literal() {
+                ColumnAttribute::Comment(comment)
+            }
+            / kw("SRID") ws() srid:srid() { ColumnAttribute::Srid(srid) }
+            / kw("DEFAULT") ws()? value() { ColumnAttribute::Other }
+            / kw("ON") ws() kw("UPDATE") ws() value() { ColumnAttribute::Other }
+            / kw("AUTO_INCREMENT") { ColumnAttribute::Other }
+            / kw("PRIMARY") ws() kw("KEY") { ColumnAttribute::Other }
+            / kw("UNIQUE") (ws() kw("KEY"))? { ColumnAttribute::Other }
+            / kw("KEY") { ColumnAttribute::Other }
+            / (kw("CHARACTER") ws() kw("SET") / kw("CHARSET")) ws()? ("=" ws()?)? word() {
+                ColumnAttribute::Other
+            }
+            / kw("COLLATE") ws()? ("=" ws()?)? word() { ColumnAttribute::Other }
+            / (kw("GENERATED") ws() kw("ALWAYS") ws())? kw("AS") ws()? parens()
+                (ws() (kw("VIRTUAL") / kw("STORED")))?
+            {
+                ColumnAttribute::Other
+            }
+            / (kw("VISIBLE") / kw("INVISIBLE")) { ColumnAttribute::Other }
+            / (kw("COLUMN_FORMAT") / kw("STORAGE")) ws() word() { ColumnAttribute::Other }
+            / kw("CHECK") ws()? parens() { ColumnAttribute::Other }
+
+        /// A MySQL data type.
+        rule data_type() -> DataType
+            = quiet! {
+                kw("TINYINT") ws()? "(" ws()? "1" ws()? ")" unsigned() { DataType::Bool }
+                / (kw("BOOLEAN") / kw("BOOL")) { DataType::Bool }
+                / kw("TINYINT") size()? unsigned() { DataType::Int16 }
+                / kw("SMALLINT") size()? is_unsigned:unsigned() {
+                    if is_unsigned { DataType::Int32 } else { DataType::Int16 }
+                }
+                / kw("MEDIUMINT") size()? unsigned() { DataType::Int32 }
+                / (kw("INTEGER") / kw("INT")) size()? is_unsigned:unsigned() {
+                    if is_unsigned { DataType::Int64 } else { DataType::Int32 }
+                }
+                // Unsigned 64-bit integers won't fit in an `INT64`.
+                / kw("BIGINT") size()? is_unsigned:unsigned() {
+                    if is_unsigned { DataType::Decimal } else { DataType::Int64 }
+                }
+                / decimal_kw() ws()? "(" ws()? precision:digits() ws()?
+                  scale:("," ws()? scale:digits() ws()? { scale })? ")" unsigned() {?
+                    // MySQL allows at most 65 digits, 30 of which may follow
+                    // the decimal point.
+                    let scale = scale.unwrap_or(0);
+                    match (u32::try_from(precision), u32::try_from(scale)) {
+                        (Ok(precision), Ok(scale))
+                            if 0 < precision
+                                && precision <= 65
+                                && scale <= 30
+                                && scale <= precision =>
+                        {
+                            Ok(DataType::FixedDecimal { precision, scale })
+                        }
+                        _ => Err("valid DECIMAL precision and scale"),
+                    }
+                }
+                / decimal_kw() unsigned() { DataType::Decimal }
+                / kw("FLOAT") ws()? "(" ws()? precision:digits() ws()? ")" unsigned() {
+                    if precision > 24 { DataType::Float64 } else { DataType::Float32 }
+                }
+                / kw("FLOAT") size()? unsigned() { DataType::Float32 }
+                / (kw("DOUBLE") (ws() kw("PRECISION"))? / kw("REAL")) size()? unsigned() {
+                    DataType::Float64
+                }
+                / kw("DATETIME") size()? { DataType::TimestampWithoutTimeZone }
+                / kw("DATE") { DataType::Date }
+                // MySQL stores `TIMESTAMP` values as UTC.
+                / kw("TIMESTAMP") size()? { DataType::TimestampWithTimeZone }
+                / kw("YEAR") size()? { DataType::Int16 }
+                / (
+                    kw("CHAR") / kw("VARCHAR") / kw("NCHAR") / kw("NVARCHAR")
+                    / kw("CHARACTER") (ws() kw("VARYING"))?
+                    / kw("NATIONAL") ws() (kw("CHAR") / kw("VARCHAR"))
+                ) size()? { DataType::Text }
+                / (kw("TINYTEXT") / kw("TEXT") / kw("MEDIUMTEXT") / kw("LONGTEXT")) size()? {
+                    DataType::Text
+                }
+                / (kw("ENUM") / kw("SET")) ws()? parens() { DataType::Text }
+                / kw("JSON") { DataType::Json }
+                / (
+                    kw("GEOMETRY") / kw("POINT") / kw("LINESTRING") / kw("POLYGON")
+                    / kw("MULTIPOINT") / kw("MULTILINESTRING") / kw("MULTIPOLYGON")
+                    / kw("GEOMETRYCOLLECTION")
+                ) { DataType::GeoJson(Srid::wgs84()) }
+            }
+            / expected!("data type")
+
+        /// The keywords used for `DECIMAL` types.
+        rule decimal_kw() = kw("DECIMAL") / kw("NUMERIC") / kw("DEC") / kw("FIXED")
+
+        /// A size or precision, such as `(10)` or `(10,2)`.
+        rule size()
+            = ws()? "(" ws()? digits() (ws()? "," ws()? digits())? ws()? ")"
+
+        /// Optional `UNSIGNED`, `SIGNED` and `ZEROFILL` modifiers. Returns true
+        /// if the type is unsigned.
+        rule unsigned() -> bool
+            = ws() kw("UNSIGNED") (ws() kw("ZEROFILL"))? { true }
+            / ws() kw("ZEROFILL") { true }
+            / ws() kw("SIGNED") { false }
+            / { false }
+
+        /// A SRID number, used to identify a coordinate system.
+        rule srid() -> u32
+            = srid:$(['0'..='9']+) {? srid.parse().or(Err("SRID")) }
+
+        /// A sequence of digits.
+        rule digits() -> u64
+            = digits:$(['0'..='9']+) {? digits.parse().or(Err("number")) }
+
+        /// A default value or other simple expression.
+        rule value()
+            = string_literal() {}
+            / parens()
+            / ['b' | 'B' | 'x' | 'X'] string_literal() {}
+            / "-"? ['0'..='9' | '.']+
+            / word() (ws()? "(" ws()? ['0'..='9']* ws()? ")")?
+
+        /// Table options like `ENGINE=InnoDB`, which we ignore.
+        rule table_options()
+            = (ws()? (
+                string_literal() {} / parens() / identifier() {} / ['=' | ',']
+            ))*
+
+        /// Anything in balanced parentheses.
+        rule parens()
+            = "(" (string_literal() {} / parens() / !['(' | ')' | '\'' | '"'] [_])* ")"
+
+        /// A string literal.
+        rule string_literal() -> String
+            = quiet! {
+                "'" s:$(("''" / "\\" [_] / !['\'' | '\\'] [_])*) "'" {
+                    unescape_string('\'', s)
+                }
+                / "\"" s:$(("\"\"" / "\\" [_] / !['"' | '\\'] [_])*) "\"" {
+                    unescape_string('"', s)
+                }
+            }
+            / expected!("string")
+
+        /// The name of a table.
+        rule table_name() -> String
+            = database:identifier() ws()? "." ws()? table:identifier() {
+                format!("{}.{}", database, table)
+            }
+            / identifier()
+
+        /// An SQL identifier.
+        rule identifier() -> String
+            = quiet! {
+                // Unquoted identifier.
+                id:word() { id.to_owned() }
+
+                // Backquoted identifier.
+                / "`" quoted:$((!['`'][_] / "``")*) "`" {
+                    quoted.replace("``", "`")
+                }
+            }
+            / expected!("identifier")
+
+        /// An unquoted word.
+        rule word() -> &'input str
+            = $(['A'..='Z' | 'a'..='z' | '0'..='9' | '_' | '$']+)
+
+        /// One or more characters of whitespace, including comments.
+        rule ws() = quiet! {
+            (
+                [' ' | '\t' | '\r' | '\n']
+                / ("--" / "#") (!['\n'][_])* ("\n" / ![_])
+                / "/*" (!"*/" [_])* "*/"
+            )+
+        }
+
+        /// Match a keyword, ignoring case. The keyword may not be followed by
+        /// any character that could appear in an unquoted identifier.
+        rule kw(literal: &'static str)
+            = i(literal) !['A'..='Z' | 'a'..='z' | '0'..='9' | '_' | '$']
+
+        /// Match a string literal, ignoring case.
+        rule i(literal: &'static str)
+            // From https://github.com/kevinmehall/rust-peg/issues/216.
+            = input:$([_]*<{literal.len()}>) {?
+                if input.eq_ignore_ascii_case(literal) {
+                    Ok(())
+                } else {
+                    Err(literal)
+                }
+            }
+    }
+}
+
+#[test]
+fn parse_mysqldump_table() {
+    let sql = r#"
+-- Dumped by mysqldump.
+CREATE TABLE IF NOT EXISTS `shop`.`orders` (
+  `id` bigint unsigned NOT NULL AUTO_INCREMENT,
+  `customer_id` int(11) NOT NULL COMMENT 'Who placed the order',
+  `is_gift` tinyint(1) NOT NULL DEFAULT '0',
+  `quantity` smallint DEFAULT NULL,
+  `total` decimal(10,2) NOT NULL DEFAULT 0.00,
+  `ratio` float,
+  `score` double precision,
+  `note` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci DEFAULT 'it''s',
+  `status` enum('new','paid') NOT NULL DEFAULT 'new',
+  `extra` json,
+  `placed_on` date,
+  `placed_at` datetime(6) DEFAULT CURRENT_TIMESTAMP(6) ON UPDATE CURRENT_TIMESTAMP(6),
+  `synced_at` timestamp NULL,
+  `location` point NOT NULL SRID 3857,
+  `key` text,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `idx_note` (`note`(10)),
+  KEY `date` (`placed_on`),
+  CONSTRAINT `fk_customer` FOREIGN KEY (`customer_id`) REFERENCES `customers` (`id`)
+) ENGINE=InnoDB AUTO_INCREMENT=5 DEFAULT CHARSET=utf8mb4 COMMENT='Orders; all of them';
+"#;
+    let table = parse("orders.sql".to_owned(), sql.to_owned()).unwrap();
+    assert_eq!(table.name, "shop.orders");
+    let columns = table
+        .columns
+        .iter()
+        .map(|c| (&c.name[..], c.is_nullable, c.data_type.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        columns,
+        vec![
+            ("id", false, DataType::Decimal),
+            ("customer_id", false, DataType::Int32),
+            ("is_gift", false, DataType::Bool),
+            ("quantity", true, DataType::Int16),
+            (
+                "total",
+                false,
+                DataType::FixedDecimal {
+                    precision: 10,
+                    scale: 2
+                }
+            ),
+            ("ratio", true, DataType::Float32),
+            ("score", true, DataType::Float64),
+            ("note", true, DataType::Text),
+            ("status", false, DataType::Text),
+            ("extra", true, DataType::Json),
+            ("placed_on", true, DataType::Date),
+            ("placed_at", true, DataType::TimestampWithoutTimeZone),
+            ("synced_at", true, DataType::TimestampWithTimeZone),
+            ("location", false, DataType::GeoJson(Srid::new(3857))),
+            ("key", true, DataType::Text),
+        ]
+    );
+    assert_eq!(
+        table.columns[1].comment.as_deref(),
+        Some("Who placed the order")
+    );
+}
+
+#[test]
+fn parse_decimal_precision_and_scale() {
+    let data_type = |ty: &str| {
+        let sql = format!("CREATE TABLE t (a {})", ty);
+        parse("t.sql".to_owned(), sql).map(|t| t.columns[0].data_type.clone())
+    };
+    let fixed = |precision, scale| DataType::FixedDecimal { precision, scale };
+    assert_eq!(data_type("DECIMAL(10, 2)").unwrap(), fixed(10, 2));
+    assert_eq!(data_type("numeric(65,30) unsigned").unwrap(), fixed(65, 30));
+    assert_eq!(data_type("dec(5)").unwrap(), fixed(5, 0));
+    assert_eq!(data_type("DECIMAL").unwrap(), DataType::Decimal);
+    for &ty in &[
+        "DECIMAL(66,0)",
+        "DECIMAL(40,31)",
+        "DECIMAL(2,3)",
+        "DECIMAL(0)",
+    ] {
+        assert!(data_type(ty).is_err(), "should not parse {:?}", ty);
+    }
+}
+
+#[test]
+fn parse_reports_unknown_types() {
+    let sql = "CREATE TABLE t (a blob)".to_owned();
+    assert!(parse("t.sql".to_owned(), sql).is_err());
+}
@@ -0,0 +1,226 @@
+//! Schema-only driver for reading and writing MySQL `CREATE TABLE` schemas.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+use crate::schema::DataType;
+
+mod create_table_sql;
+
+/// An SQL file containing a `CREATE TABLE` statement using MySQL syntax.
+#[derive(Clone, Debug)]
+pub struct MysqlSqlLocator {
+    path: PathOrStdio,
+}
+
+impl fmt::Display for MysqlSqlLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.path.fmt_locator_helper(Self::scheme(), f)
+    }
+}
+
+impl FromStr for MysqlSqlLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), s)?;
+        Ok(MysqlSqlLocator { path })
+    }
+}
+
+impl Locator for MysqlSqlLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self, ctx: Context) -> BoxFuture<Option<Table>> {
+        schema_helper(ctx, self.to_owned()).boxed()
+    }
+
+    fn write_schema(
+        &self,
+        ctx: Context,
+        table: Table,
+        if_exists: IfExists,
+    ) -> BoxFuture<()> {
+        write_schema_helper(ctx, self.to_owned(), table, if_exists).boxed()
+    }
+}
+
+impl LocatorStatic for MysqlSqlLocator {
+    fn scheme() -> &'static str {
+        "mysql-sql:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::Schema | LocatorFeatures::WriteSchema,
+            write_schema_if_exists: IfExistsFeatures::no_append(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
+
+/// Implementation of `schema`, but as a real `async` function.
+async fn schema_helper(
+    _ctx: Context,
+    source: MysqlSqlLocator,
+) -> Result<Option<Table>> {
+    let input = source
+        .path
+        .open_async()
+        .await
+        .with_context(|_| format!("error opening {}", source.path))?;
+    let sql = async_read_to_string(input)
+        .await
+        .with_context(|_| format!("error reading {}", source.path))?;
+    let table = create_table_sql::parse(source.path.to_string(), sql)?;
+    Ok(Some(table))
+}
+
+/// Implementation of `write_schema`, but as a real `async` function.
+async fn write_schema_helper(
+    ctx: Context,
+    dest: MysqlSqlLocator,
+    table: Table,
+    if_exists: IfExists,
+) -> Result<()> {
+    // Generate our SQL before we create our output file.
+    let sql = create_table_sql(&table)?;
+
+    let mut out = dest.path.create_async(ctx, if_exists).await?;
+    buffer_sync_write_and_copy_to_async(&mut out, |buff| write!(buff, "{}", sql))
+        .await
+        .with_context(|_| format!("error writing {}", dest.path))?;
+    out.flush().await?;
+    Ok(())
+}
+
+/// Generate a MySQL `CREATE TABLE` statement for `table`.
+fn create_table_sql(table: &Table) -> Result<String> {
+    if table.columns.is_empty() {
+        return Err(format_err!("cannot create {} with no columns", table.name));
+    }
+    let name = table
+        .name
+        .split('.')
+        .map(quote_identifier)
+        .collect::<Vec<_>>()
+        .join(".");
+
+    let mut sql = format!("CREATE TABLE {} (\n", name);
+    for (idx, column) in table.columns.iter().enumerate() {
+        sql.push_str(&format!(
+            "    {} {}",
+            quote_identifier(&column.name),
+            mysql_column_type(&column.data_type),
+        ));
+        if !column.is_nullable {
+            sql.push_str(" NOT NULL");
+        }
+        if let Some(comment) = &column.comment {
+            sql.push_str(&format!(" COMMENT {}", quote_string(comment)));
+        }
+        if idx + 1 < table.columns.len() {
+            sql.push(',');
+        }
+        sql.push('\n');
+    }
+    sql.push_str(");\n");
+    Ok(sql)
+}
+
+/// Choose a MySQL column type for `data_type`.
+fn mysql_column_type(data_type: &DataType) -> String {
+    match data_type {
+        // MySQL has no array or struct types, so store these as JSON.
+        DataType::Array(_) | DataType::Json | DataType::Struct(_) => "JSON".to_owned(),
+        DataType::Bool => "BOOLEAN".to_owned(),
+        DataType::Date => "DATE".to_owned(),
+        // We don't track precision, so use the same values as BigQuery's
+        // `NUMERIC`.
+        DataType::Decimal => "DECIMAL(38,9)".to_owned(),
+        // MySQL allows at most 65 digits, 30 of which may follow the decimal
+        // point. Store anything larger as text instead of rounding it.
+        DataType::FixedDecimal { precision, scale }
+            if *precision <= 65 && *scale <= 30 =>
+        {
+            format!("DECIMAL({},{})", precision, scale)
+        }
+        DataType::FixedDecimal { .. } => "LONGTEXT".to_owned(),
+        DataType::Float32 => "FLOAT".to_owned(),
+        DataType::Float64 => "DOUBLE".to_owned(),
+        DataType::GeoJson(srid) => format!("GEOMETRY SRID {}", srid),
+        DataType::Int16 => "SMALLINT".to_owned(),
+        DataType::Int32 => "INT".to_owned(),
+        DataType::Int64 => "BIGINT".to_owned(),
+        // `TEXT` is limited to 64KB, which is too small for some data.
+        DataType::Text => "LONGTEXT".to_owned(),
+        DataType::TimestampWithoutTimeZone => "DATETIME(6)".to_owned(),
+        DataType::TimestampWithTimeZone => "TIMESTAMP(6)".to_owned(),
+        DataType::Uuid => "CHAR(36)".to_owned(),
+    }
+}
+
+/// Quote an identifier for MySQL.
+fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Quote a string for MySQL.
+fn quote_string(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+#[test]
+fn create_table_sql_roundtrip() {
+    use crate::schema::{Column, Srid};
+
+    let column = |name: &str, is_nullable: bool, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let table = Table {
+        name: "shop.orders".to_owned(),
+        columns: vec![
+            Column {
+                comment: Some("It's the \\ ID".to_owned()),
+                ..column("id", false, DataType::Int64)
+            },
+            column("is_gift", true, DataType::Bool),
+            column("placed_on", true, DataType::Date),
+            column(
+                "total",
+                false,
+                DataType::FixedDecimal {
+                    precision: 10,
+                    scale: 2,
+                },
+            ),
+            column("ratio", true, DataType::Float32),
+            column("score", true, DataType::Float64),
+            column("location", true, DataType::GeoJson(Srid::new(3857))),
+            column("quantity", true, DataType::Int16),
+            column("customer_id", true, DataType::Int32),
+            column("extra", true, DataType::Json),
+            column("odd`name", true, DataType::Text),
+            column("placed_at", true, DataType::TimestampWithoutTimeZone),
+            column("synced_at", true, DataType::TimestampWithTimeZone),
+        ],
+    };
+    let sql = create_table_sql(&table).unwrap();
+    assert!(sql.starts_with(
+        "CREATE TABLE `shop`.`orders` (\n    `id` BIGINT NOT NULL COMMENT 'It''s the \\\\ ID',\n"
+    ));
+    let parsed = create_table_sql::parse("orders.sql".to_owned(), sql).unwrap();
+    assert_eq!(parsed, table);
+
+    // MySQL has no unconstrained `DECIMAL` type, so `decimal` columns are
+    // written with a fixed precision and scale, and read back that way.
+    assert_eq!(mysql_column_type(&DataType::Decimal), "DECIMAL(38,9)");
+}
@@ -0,0 +1,135 @@
+//! A destination which discards all data, for benchmarking and validation.
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use crate::common::*;
+use crate::tokio_glue::{spawn_blocking_stage, SyncStreamReader};
+
+/// A destination which reads and discards all data, counting rows and bytes.
+///
+/// This is useful for measuring how quickly we can read from a source, or for
+/// checking that a source can be read without errors.
+#[derive(Clone, Debug)]
+pub(crate) struct NullLocator;
+
+impl fmt::Display for NullLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Self::scheme().fmt(f)
+    }
+}
+
+impl FromStr for NullLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == Self::scheme() {
+            Ok(NullLocator)
+        } else {
+            Err(format_err!("expected {:?}, found {:?}", Self::scheme(), s))
+        }
+    }
+}
+
+impl Locator for NullLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn write_local_data(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+        shared_args: SharedArguments<Unverified>,
+        dest_args: DestinationArguments<Unverified>,
+    ) -> BoxFuture<BoxStream<BoxFuture<BoxLocator>>> {
+        write_local_data_helper(ctx, data, shared_args, dest_args).boxed()
+    }
+}
+
+/// Running totals for all the streams we've discarded.
+#[derive(Debug, Default)]
+struct Totals {
+    rows: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// Read and discard each CSV stream in `data`.
+async fn write_local_data_helper(
+    ctx: Context,
+    data: BoxStream<CsvStream>,
+    shared_args: SharedArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
+    let _shared_args = shared_args.verify(NullLocator::features())?;
+    let _dest_args = dest_args.verify(NullLocator::features())?;
+
+    let totals = Arc::new(Totals::default());
+    let written = data.map_ok(move |stream| {
+        let ctx = ctx.child(o!("stream" => stream.name.clone()));
+        let totals = totals.clone();
+        async move {
+            // Count bytes as they go by.
+            let bytes = Arc::new(AtomicU64::new(0));
+            let counted_bytes = bytes.clone();
+            let data = stream
+                .data
+                .inspect_ok(move |chunk| {
+                    counted_bytes.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                })
+                .boxed();
+
+            // Parse the CSV data, so that we count rows correctly and notice
+            // any invalid CSV.
+            let rdr = SyncStreamReader::new(ctx.clone(), data);
+            let rows = spawn_blocking_stage(move || -> Result<u64> {
+                let mut rdr = csv::Reader::from_reader(rdr);
+                let mut row = csv::ByteRecord::new();
+                let mut rows = 0;
+                while rdr.read_byte_record(&mut row).context("cannot read row")? {
+                    rows += 1;
+                }
+                Ok(rows)
+            })
+            .await?;
+
+            let bytes = bytes.load(Ordering::Relaxed);
+            let total_rows = totals.rows.fetch_add(rows, Ordering::Relaxed) + rows;
+            let total_bytes = totals.bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+            info!(
+                ctx.log(),
+                "discarded {} rows ({} bytes), {} rows ({} bytes) total",
+                rows,
+                bytes,
+                total_rows,
+                total_bytes,
+            );
+            Ok(NullLocator.boxed())
+        }
+        .boxed()
+    });
+    Ok(written.boxed())
+}
+
+impl LocatorStatic for NullLocator {
+    fn scheme() -> &'static str {
+        "null:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::WriteLocalData.into(),
+            write_schema_if_exists: EnumSet::empty(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::all(),
+            _placeholder: (),
+        }
+    }
+}
@@ -0,0 +1,555 @@
+//! Parquet schema types, and conversions to and from our portable schemas.
+//!
+//! See https://github.com/apache/parquet-format/blob/master/LogicalTypes.md
+//! for the meanings of the various logical types.
+
+use crate::common::*;
+use crate::schema::{Column, DataType, StructField};
+
+/// A Parquet schema, which Parquet calls a "message".
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ParquetMessage {
+    /// The name of our message.
+    pub(crate) name: String,
+    /// The top-level fields of our message.
+    pub(crate) fields: Vec<ParquetField>,
+}
+
+/// A field in a Parquet schema.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ParquetField {
+    /// The name of this field.
+    pub(crate) name: String,
+    /// How many times does this field appear?
+    pub(crate) repetition: Repetition,
+    /// The logical type of this field, if any.
+    pub(crate) annotation: Option<Annotation>,
+    /// Is this a primitive value or a group of fields?
+    pub(crate) kind: FieldKind,
+}
+
+/// How many times a Parquet field may appear.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Repetition {
+    /// Exactly once.
+    Required,
+    /// Zero or one times.
+    Optional,
+    /// Zero or more times.
+    Repeated,
+}
+
+/// The contents of a Parquet field.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum FieldKind {
+    /// A primitive value stored using a physical type.
+    Primitive(PhysicalType),
+    /// A group of nested fields.
+    Group(Vec<ParquetField>),
+}
+
+/// The physical types used to store Parquet data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum PhysicalType {
+    Boolean,
+    Int32,
+    Int64,
+    Int96,
+    Float,
+    Double,
+    ByteArray,
+    FixedLenByteArray(i32),
+}
+
+/// Logical type annotations, which explain how to interpret a physical type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Annotation {
+    String,
+    Enum,
+    Json,
+    Bson,
+    Uuid,
+    Date,
+    List,
+    Map,
+    MapKeyValue,
+    Interval,
+    Unknown,
+    Decimal {
+        precision: i32,
+        scale: i32,
+    },
+    Time {
+        unit: TimeUnit,
+        is_adjusted_to_utc: bool,
+    },
+    Timestamp {
+        unit: TimeUnit,
+        is_adjusted_to_utc: bool,
+    },
+    Integer {
+        bit_width: i8,
+        is_signed: bool,
+    },
+}
+
+/// Units used by Parquet `TIME` and `TIMESTAMP` types.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum TimeUnit {
+    Millis,
+    Micros,
+    Nanos,
+}
+
+/// Convert a Parquet message into a table.
+pub(crate) fn table_from_parquet_message(message: &ParquetMessage) -> Result<Table> {
+    let mut columns = vec![];
+    for field in &message.fields {
+        let (is_nullable, data_type) = field_type(field)
+            .with_context(|_| format!("error in Parquet field {}", field.name))?;
+        columns.push(Column {
+            name: field.name.clone(),
+            is_nullable,
+            data_type,
+            comment: None,
+        });
+    }
+    Ok(Table {
+        name: message.name.clone(),
+        columns,
+    })
+}
+
+/// Figure out whether `field` is nullable, and what type it has.
+fn field_type(field: &ParquetField) -> Result<(bool, DataType)> {
+    match field.repetition {
+        Repetition::Required => Ok((false, value_type(field)?)),
+        Repetition::Optional => Ok((true, value_type(field)?)),
+        // A bare repeated field is an array of non-null values.
+        Repetition::Repeated => {
+            Ok((false, DataType::Array(Box::new(value_type(field)?))))
+        }
+    }
+}
+
+/// Figure out the type of a single value of `field`, ignoring repetition.
+fn value_type(field: &ParquetField) -> Result<DataType> {
+    match &field.kind {
+        FieldKind::Group(children) => match field.annotation {
+            Some(Annotation::List) => list_element_type(field, children),
+            Some(Annotation::Map) | Some(Annotation::MapKeyValue) => {
+                Ok(DataType::Json)
+            }
+            None => {
+                let mut fields = vec![];
+                for child in children {
+                    let (is_nullable, data_type) = field_type(child)?;
+                    fields.push(StructField {
+                        name: child.name.clone(),
+                        is_nullable,
+                        data_type,
+                    });
+                }
+                Ok(DataType::Struct(fields))
+            }
+            Some(other) => {
+                Err(format_err!("unexpected {:?} annotation on group", other))
+            }
+        },
+        FieldKind::Primitive(physical_type) => {
+            primitive_type(*physical_type, field.annotation)
+        }
+    }
+}
+
+/// Find the element type of a `LIST` group.
+///
+/// Older Parquet writers used several different list layouts, so we follow
+/// the backwards-compatibility rules in `LogicalTypes.md`.
+fn list_element_type(
+    list: &ParquetField,
+    children: &[ParquetField],
+) -> Result<DataType> {
+    let repeated = match children {
+        [repeated] if repeated.repetition == Repetition::Repeated => repeated,
+        _ => {
+            return Err(format_err!(
+                "expected LIST to contain a single repeated field"
+            ))
+        }
+    };
+    let element = match &repeated.kind {
+        // The standard three-level layout.
+        FieldKind::Group(grandchildren)
+            if grandchildren.len() == 1
+                && repeated.name != "array"
+                && repeated.name != format!("{}_tuple", list.name) =>
+        {
+            &grandchildren[0]
+        }
+        // A two-level layout, where the repeated field is the element.
+        _ => {
+            return Ok(DataType::Array(Box::new(value_type(repeated)?)));
+        }
+    };
+    match element.repetition {
+        Repetition::Repeated => Err(format_err!("LIST element cannot be repeated")),
+        _ => Ok(DataType::Array(Box::new(value_type(element)?))),
+    }
+}
+
+/// Convert a primitive Parquet type into a portable type.
+fn primitive_type(
+    physical_type: PhysicalType,
+    annotation: Option<Annotation>,
+) -> Result<DataType> {
+    use Annotation as A;
+    use PhysicalType as P;
+    match (physical_type, annotation) {
+        (P::Boolean, None) => Ok(DataType::Bool),
+
+        (_, Some(A::Decimal { .. })) => Ok(DataType::Decimal),
+        (P::Int32, Some(A::Date)) => Ok(DataType::Date),
+        (
+            P::Int32,
+            Some(A::Integer {
+                bit_width,
+                is_signed,
+            }),
+        ) => match (bit_width, is_signed) {
+            (8, _) | (16, true) => Ok(DataType::Int16),
+            (16, false) | (32, true) => Ok(DataType::Int32),
+            (32, false) => Ok(DataType::Int64),
+            _ => Err(format_err!("unexpected INT32 bit width {}", bit_width)),
+        },
+        (
+            P::Int64,
+            Some(A::Integer {
+                bit_width: 64,
+                is_signed: true,
+            }),
+        ) => Ok(DataType::Int64),
+        // Unsigned 64-bit integers may not fit in an `Int64`.
+        (
+            P::Int64,
+            Some(A::Integer {
+                bit_width: 64,
+                is_signed: false,
+            }),
+        ) => Ok(DataType::Decimal),
+        (P::Int32, None) => Ok(DataType::Int32),
+        (P::Int64, None) => Ok(DataType::Int64),
+        (
+            P::Int64,
+            Some(A::Timestamp {
+                is_adjusted_to_utc, ..
+            }),
+        ) => {
+            if is_adjusted_to_utc {
+                Ok(DataType::TimestampWithTimeZone)
+            } else {
+                Ok(DataType::TimestampWithoutTimeZone)
+            }
+        }
+        // We have no portable time-of-day type, so we use text.
+        (P::Int32, Some(A::Time { .. })) | (P::Int64, Some(A::Time { .. })) => {
+            Ok(DataType::Text)
+        }
+        // `INT96` is a deprecated timestamp format used by Hive, Impala and
+        // older versions of Spark, without any time zone information.
+        (P::Int96, None) => Ok(DataType::TimestampWithoutTimeZone),
+
+        (P::Float, None) => Ok(DataType::Float32),
+        (P::Double, None) => Ok(DataType::Float64),
+
+        (P::ByteArray, Some(A::String))
+        | (P::ByteArray, Some(A::Enum))
+        | (P::FixedLenByteArray(_), Some(A::Interval)) => Ok(DataType::Text),
+        (P::ByteArray, Some(A::Json)) => Ok(DataType::Json),
+        (P::FixedLenByteArray(16), Some(A::Uuid)) => Ok(DataType::Uuid),
+        (P::ByteArray, None)
+        | (P::ByteArray, Some(A::Bson))
+        | (P::FixedLenByteArray(_), None) => Err(format_err!(
+            "binary Parquet data is not supported by dbcrossbar"
+        )),
+
+        (physical_type, Some(annotation)) => Err(format_err!(
+            "unexpected {:?} annotation on {:?}",
+            annotation,
+            physical_type,
+        )),
+    }
+}
+
+/// Build a Parquet message describing `table`.
+pub(crate) fn parquet_message_for_table(table: &Table) -> Result<ParquetMessage> {
+    let mut fields = vec![];
+    for col in &table.columns {
+        fields.push(
+            parquet_field(&col.name, col.is_nullable, &col.data_type)
+                .with_context(|_| format!("error in column {}", col.name))?,
+        );
+    }
+    Ok(ParquetMessage {
+        name: message_name(&table.name),
+        fields,
+    })
+}
+
+/// Build a Parquet field with the specified name and type.
+fn parquet_field(
+    name: &str,
+    is_nullable: bool,
+    data_type: &DataType,
+) -> Result<ParquetField> {
+    if !is_valid_name(name) {
+        return Err(format_err!(
+            "cannot use {:?} as a Parquet field name in a schema descriptor",
+            name
+        ));
+    }
+    let repetition = if is_nullable {
+        Repetition::Optional
+    } else {
+        Repetition::Required
+    };
+    let primitive = |physical_type, annotation| {
+        Ok(ParquetField {
+            name: name.to_owned(),
+            repetition,
+            annotation,
+            kind: FieldKind::Primitive(physical_type),
+        })
+    };
+    match data_type {
+        // We always use the standard three-level list layout.
+        DataType::Array(element_type) => {
+            let element = parquet_field("element", true, element_type)?;
+            Ok(ParquetField {
+                name: name.to_owned(),
+                repetition,
+                annotation: Some(Annotation::List),
+                kind: FieldKind::Group(vec![ParquetField {
+                    name: "list".to_owned(),
+                    repetition: Repetition::Repeated,
+                    annotation: None,
+                    kind: FieldKind::Group(vec![element]),
+                }]),
+            })
+        }
+        DataType::Bool => primitive(PhysicalType::Boolean, None),
+        DataType::Date => primitive(PhysicalType::Int32, Some(Annotation::Date)),
+        // This matches the precision and scale we use for BigQuery `NUMERIC`,
+        // and 16 bytes is enough to hold 38 decimal digits.
+        DataType::Decimal => primitive(
+            PhysicalType::FixedLenByteArray(16),
+            Some(Annotation::Decimal {
+                precision: 38,
+                scale: 9,
+            }),
+        ),
+        // Variable-length decimals can hold any precision.
+        DataType::FixedDecimal { precision, scale } => primitive(
+            PhysicalType::ByteArray,
+            Some(Annotation::Decimal {
+                precision: i32::try_from(*precision)?,
+                scale: i32::try_from(*scale)?,
+            }),
+        ),
+        DataType::Float32 => primitive(PhysicalType::Float, None),
+        DataType::Float64 => primitive(PhysicalType::Double, None),
+        // Parquet has no standard geometry type yet, so store GeoJSON as JSON.
+        DataType::GeoJson(_) | DataType::Json => {
+            primitive(PhysicalType::ByteArray, Some(Annotation::Json))
+        }
+        DataType::Int16 => primitive(
+            PhysicalType::Int32,
+            Some(Annotation::Integer {
+                bit_width: 16,
+                is_signed: true,
+            }),
+        ),
+        DataType::Int32 => primitive(PhysicalType::Int32, None),
+        DataType::Int64 => primitive(PhysicalType::Int64, None),
+        DataType::Struct(fields) => {
+            let mut children = vec![];
+            for field in fields {
+                children.push(parquet_field(
+                    &field.name,
+                    field.is_nullable,
+                    &field.data_type,
+                )?);
+            }
+            Ok(ParquetField {
+                name: name.to_owned(),
+                repetition,
+                annotation: None,
+                kind: FieldKind::Group(children),
+            })
+        }
+        DataType::Text => primitive(PhysicalType::ByteArray, Some(Annotation::String)),
+        DataType::TimestampWithoutTimeZone => primitive(
+            PhysicalType::Int64,
+            Some(Annotation::Timestamp {
+                unit: TimeUnit::Micros,
+                is_adjusted_to_utc: false,
+            }),
+        ),
+        DataType::TimestampWithTimeZone => primitive(
+            PhysicalType::Int64,
+            Some(Annotation::Timestamp {
+                unit: TimeUnit::Micros,
+                is_adjusted_to_utc: true,
+            }),
+        ),
+        DataType::Uuid => {
+            primitive(PhysicalType::FixedLenByteArray(16), Some(Annotation::Uuid))
+        }
+    }
+}
+
+/// Is `name` a name we can write in a schema descriptor?
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_name_char)
+}
+
+/// Can `c` appear in a name in a schema descriptor?
+pub(crate) fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == '$'
+}
+
+/// Convert a table name into a message name, replacing any characters that
+/// can't appear in a schema descriptor.
+fn message_name(table_name: &str) -> String {
+    let name = table_name
+        .chars()
+        .map(|c| if is_name_char(c) { c } else { '_' })
+        .collect::<String>();
+    if name.is_empty() {
+        "schema".to_owned()
+    } else {
+        name
+    }
+}
+
+#[test]
+fn parquet_message_roundtrip() {
+    use crate::schema::Srid;
+
+    let column = |name: &str, is_nullable: bool, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let table = Table {
+        name: "public.orders".to_owned(),
+        columns: vec![
+            column("id", false, DataType::Uuid),
+            column("active", true, DataType::Bool),
+            column("placed_on", true, DataType::Date),
+            column("total", false, DataType::Decimal),
+            column("weight", true, DataType::Float32),
+            column("score", true, DataType::Float64),
+            column("quantity", true, DataType::Int16),
+            column("line", true, DataType::Int32),
+            column("customer_id", false, DataType::Int64),
+            column("extra", true, DataType::Json),
+            column("note", true, DataType::Text),
+            column("placed_at", true, DataType::TimestampWithTimeZone),
+            column("shipped_at", true, DataType::TimestampWithoutTimeZone),
+            column(
+                "tags",
+                true,
+                DataType::Array(Box::new(DataType::Array(Box::new(DataType::Text)))),
+            ),
+            column(
+                "address",
+                true,
+                DataType::Struct(vec![StructField {
+                    name: "zip".to_owned(),
+                    is_nullable: false,
+                    data_type: DataType::Text,
+                }]),
+            ),
+        ],
+    };
+    let message = parquet_message_for_table(&table).unwrap();
+    assert_eq!(table_from_parquet_message(&message).unwrap(), table);
+
+    // GeoJSON comes back as plain JSON.
+    let geo = Table {
+        name: "places".to_owned(),
+        columns: vec![column("shape", true, DataType::GeoJson(Srid::wgs84()))],
+    };
+    let message = parquet_message_for_table(&geo).unwrap();
+    assert_eq!(
+        table_from_parquet_message(&message).unwrap().columns[0].data_type,
+        DataType::Json,
+    );
+
+    let bad_name = Table {
+        name: "t".to_owned(),
+        columns: vec![column("first name", true, DataType::Text)],
+    };
+    assert!(parquet_message_for_table(&bad_name).is_err());
+}
+
+#[test]
+fn legacy_list_layouts() {
+    let leaf = |name: &str, repetition, annotation, physical_type| ParquetField {
+        name: name.to_owned(),
+        repetition,
+        annotation,
+        kind: FieldKind::Primitive(physical_type),
+    };
+    let list = |children| ParquetField {
+        name: "tags".to_owned(),
+        repetition: Repetition::Optional,
+        annotation: Some(Annotation::List),
+        kind: FieldKind::Group(children),
+    };
+    let text_array = DataType::Array(Box::new(DataType::Text));
+
+    // A two-level list with a repeated primitive.
+    let two_level = list(vec![leaf(
+        "element",
+        Repetition::Repeated,
+        Some(Annotation::String),
+        PhysicalType::ByteArray,
+    )]);
+    assert_eq!(field_type(&two_level).unwrap(), (true, text_array.clone()));
+
+    // A bare repeated field, without a `LIST` annotation.
+    let repeated = leaf(
+        "tags",
+        Repetition::Repeated,
+        Some(Annotation::String),
+        PhysicalType::ByteArray,
+    );
+    assert_eq!(field_type(&repeated).unwrap(), (false, text_array));
+
+    // An old-style list of single-field structs.
+    let array_of_structs = list(vec![ParquetField {
+        name: "array".to_owned(),
+        repetition: Repetition::Repeated,
+        annotation: None,
+        kind: FieldKind::Group(vec![leaf(
+            "name",
+            Repetition::Required,
+            Some(Annotation::String),
+            PhysicalType::ByteArray,
+        )]),
+    }]);
+    assert_eq!(
+        field_type(&array_of_structs).unwrap(),
+        (
+            true,
+            DataType::Array(Box::new(DataType::Struct(vec![StructField {
+                name: "name".to_owned(),
+                is_nullable: false,
+                data_type: DataType::Text,
+            }])))
+        )
+    );
+}
@@ -0,0 +1,681 @@
+//! Reading the schema from the footer of a Parquet file.
+//!
+//! A Parquet file ends with a Thrift-encoded `FileMetaData` structure, followed
+//! by its length as a 4-byte little-endian integer and the magic bytes `PAR1`.
+//! We only need the schema, so rather than pulling in a full Parquet or Thrift
+//! implementation, we decode just enough of Thrift's "compact protocol" to find
+//! it. See https://github.com/apache/parquet-format/blob/master/src/main/thrift/parquet.thrift
+//! and https://github.com/apache/thrift/blob/master/doc/specs/thrift-compact-protocol.md.
+
+use std::{
+    convert::TryFrom,
+    fs,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use super::convert::{
+    Annotation, FieldKind, ParquetField, ParquetMessage, PhysicalType, Repetition,
+    TimeUnit,
+};
+use crate::common::*;
+use crate::path_or_stdio::long_path;
+
+/// The magic bytes at the start and end of a Parquet file.
+const MAGIC: &[u8] = b"PAR1";
+
+/// The magic bytes at the end of a Parquet file with an encrypted footer.
+const ENCRYPTED_MAGIC: &[u8] = b"PARE";
+
+/// The length of the trailer after the metadata: a length and `MAGIC`.
+const TRAILER_LEN: usize = 8;
+
+/// How deeply can Thrift structures be nested before we give up?
+const MAX_DEPTH: usize = 64;
+
+/// Does `data` look like it ends with a Parquet footer?
+pub(crate) fn is_parquet_data(data: &[u8]) -> bool {
+    data.ends_with(MAGIC) || data.ends_with(ENCRYPTED_MAGIC)
+}
+
+/// Read the trailing footer of the file at `path`, without reading the rest of
+/// the file. If it isn't a Parquet file, read the whole thing instead, because
+/// it's probably a schema descriptor.
+pub(crate) fn read_footer_or_file(path: &Path) -> Result<Vec<u8>> {
+    let mut f = fs::File::open(long_path(path)?)
+        .with_context(|_| format!("error opening {}", path.display()))?;
+    let len = f.metadata()?.len();
+    if len >= (MAGIC.len() + TRAILER_LEN) as u64 {
+        let mut trailer = [0; TRAILER_LEN];
+        f.seek(SeekFrom::End(-i64::try_from(TRAILER_LEN)?))?;
+        f.read_exact(&mut trailer)?;
+        if is_parquet_data(&trailer) {
+            let metadata_len = u64::from(u32::from_le_bytes([
+                trailer[0], trailer[1], trailer[2], trailer[3],
+            ]));
+            let footer_len = metadata_len + TRAILER_LEN as u64;
+            if footer_len > len {
+                return Err(format_err!(
+                    "Parquet metadata length {} is too long",
+                    metadata_len
+                ));
+            }
+            let mut footer = vec![0; usize::try_from(footer_len)?];
+            f.seek(SeekFrom::End(-i64::try_from(footer_len)?))?;
+            f.read_exact(&mut footer)?;
+            return Ok(footer);
+        }
+    }
+    let mut data = vec![];
+    f.seek(SeekFrom::Start(0))?;
+    f.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Extract the schema from `data`, which must end with a Parquet footer. We
+/// don't need the rest of the file.
+pub(crate) fn parse_footer(data: &[u8]) -> Result<ParquetMessage> {
+    if data.ends_with(ENCRYPTED_MAGIC) {
+        return Err(format_err!("cannot read encrypted Parquet footers"));
+    }
+    if data.len() < TRAILER_LEN || !data.ends_with(MAGIC) {
+        return Err(format_err!("could not find Parquet footer"));
+    }
+    let trailer = &data[data.len() - TRAILER_LEN..];
+    let metadata_len =
+        u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let metadata_len = usize::try_from(metadata_len)?;
+    if metadata_len > data.len() - TRAILER_LEN {
+        return Err(format_err!(
+            "Parquet metadata length {} is too long",
+            metadata_len
+        ));
+    }
+    let metadata = &data[data.len() - TRAILER_LEN - metadata_len..][..metadata_len];
+    let elements = read_schema_elements(&mut CompactReader::new(metadata))
+        .context("error reading Parquet metadata")?;
+    message_from_schema_elements(elements)
+}
+
+/// Thrift compact protocol type codes.
+mod ty {
+    pub(super) const STOP: u8 = 0;
+    pub(super) const BOOLEAN_TRUE: u8 = 1;
+    pub(super) const BOOLEAN_FALSE: u8 = 2;
+    pub(super) const BYTE: u8 = 3;
+    pub(super) const I16: u8 = 4;
+    pub(super) const I32: u8 = 5;
+    pub(super) const I64: u8 = 6;
+    pub(super) const DOUBLE: u8 = 7;
+    pub(super) const BINARY: u8 = 8;
+    pub(super) const LIST: u8 = 9;
+    pub(super) const SET: u8 = 10;
+    pub(super) const MAP: u8 = 11;
+    pub(super) const STRUCT: u8 = 12;
+}
+
+/// A minimal reader for Thrift's compact protocol.
+struct CompactReader<'a> {
+    data: &'a [u8],
+    /// The ID of the last field we read in each struct we're inside.
+    last_field_ids: Vec<i16>,
+}
+
+impl<'a> CompactReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        CompactReader {
+            data,
+            last_field_ids: vec![],
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        let (&byte, rest) = self
+            .data
+            .split_first()
+            .ok_or_else(|| format_err!("unexpected end of Thrift data"))?;
+        self.data = rest;
+        Ok(byte)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.data.len() {
+            return Err(format_err!("unexpected end of Thrift data"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn read_varint(&mut self) -> Result<u64> {
+        let mut result = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?;
+            result |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(format_err!("Thrift varint is too long"))
+    }
+
+    fn read_i64(&mut self) -> Result<i64> {
+        // Undo the zigzag encoding.
+        let n = self.read_varint()?;
+        #[allow(clippy::cast_possible_wrap)]
+        let result = (n >> 1) as i64 ^ -((n & 1) as i64);
+        Ok(result)
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        let n = self.read_i64()?;
+        Ok(i32::try_from(n)?)
+    }
+
+    fn read_binary(&mut self) -> Result<&'a [u8]> {
+        let len = usize::try_from(self.read_varint()?)?;
+        self.read_bytes(len)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let bytes = self.read_binary()?;
+        Ok(String::from_utf8(bytes.to_owned())?)
+    }
+
+    fn read_struct_begin(&mut self) -> Result<()> {
+        if self.last_field_ids.len() >= MAX_DEPTH {
+            return Err(format_err!("Thrift data is nested too deeply"));
+        }
+        self.last_field_ids.push(0);
+        Ok(())
+    }
+
+    fn read_struct_end(&mut self) {
+        self.last_field_ids.pop();
+    }
+
+    /// Read a field header, returning the field ID and type, or `None` at the
+    /// end of the struct. For boolean fields, the type is also the value.
+    fn read_field_header(&mut self) -> Result<Option<(i16, u8)>> {
+        let byte = self.read_byte()?;
+        let field_type = byte & 0x0f;
+        if field_type == ty::STOP {
+            return Ok(None);
+        }
+        let delta = i16::from(byte >> 4);
+        let field_id = if delta == 0 {
+            i16::try_from(self.read_i64()?)?
+        } else {
+            self.last_field_ids.last().expect("should be inside struct") + delta
+        };
+        *self
+            .last_field_ids
+            .last_mut()
+            .expect("should be inside struct") = field_id;
+        Ok(Some((field_id, field_type)))
+    }
+
+    /// Read a list header, returning the element count and type.
+    fn read_list_header(&mut self) -> Result<(usize, u8)> {
+        let byte = self.read_byte()?;
+        let size = match byte >> 4 {
+            15 => usize::try_from(self.read_varint()?)?,
+            size => usize::from(size),
+        };
+        Ok((size, byte & 0x0f))
+    }
+
+    /// Skip a struct field of type `field_type`.
+    fn skip_field(&mut self, field_type: u8) -> Result<()> {
+        match field_type {
+            // Boolean fields store their value in the field header.
+            ty::BOOLEAN_TRUE | ty::BOOLEAN_FALSE => Ok(()),
+            _ => self.skip_value(field_type),
+        }
+    }
+
+    /// Skip a value of type `value_type`, as found inside lists and maps.
+    fn skip_value(&mut self, value_type: u8) -> Result<()> {
+        match value_type {
+            ty::BOOLEAN_TRUE | ty::BOOLEAN_FALSE | ty::BYTE => {
+                self.read_byte()?;
+            }
+            ty::I16 | ty::I32 | ty::I64 => {
+                self.read_varint()?;
+            }
+            ty::DOUBLE => {
+                self.read_bytes(8)?;
+            }
+            ty::BINARY => {
+                self.read_binary()?;
+            }
+            ty::LIST | ty::SET => {
+                let (size, element_type) = self.read_list_header()?;
+                self.with_nesting(|reader| {
+                    for _ in 0..size {
+                        reader.skip_value(element_type)?;
+                    }
+                    Ok(())
+                })?;
+            }
+            ty::MAP => {
+                let size = usize::try_from(self.read_varint()?)?;
+                if size > 0 {
+                    let types = self.read_byte()?;
+                    self.with_nesting(|reader| {
+                        for _ in 0..size {
+                            reader.skip_value(types >> 4)?;
+                            reader.skip_value(types & 0x0f)?;
+                        }
+                        Ok(())
+                    })?;
+                }
+            }
+            ty::STRUCT => {
+                self.read_struct_begin()?;
+                while let Some((_, field_type)) = self.read_field_header()? {
+                    self.skip_field(field_type)?;
+                }
+                self.read_struct_end();
+            }
+            other => return Err(format_err!("unknown Thrift type {}", other)),
+        }
+        Ok(())
+    }
+
+    /// Run `f`, counting it as a level of nesting.
+    fn with_nesting<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.read_struct_begin()?;
+        let result = f(self);
+        self.read_struct_end();
+        result
+    }
+}
+
+/// A `SchemaElement` from the Parquet metadata. The schema is stored as a
+/// depth-first list of these.
+#[derive(Debug, Default)]
+struct SchemaElement {
+    physical_type: Option<i32>,
+    type_length: Option<i32>,
+    repetition_type: Option<i32>,
+    name: String,
+    num_children: Option<i32>,
+    converted_type: Option<i32>,
+    scale: Option<i32>,
+    precision: Option<i32>,
+    logical_type: Option<Annotation>,
+}
+
+/// Read the `schema` field of a `FileMetaData` struct.
+fn read_schema_elements(reader: &mut CompactReader<'_>) -> Result<Vec<SchemaElement>> {
+    let mut elements = None;
+    reader.read_struct_begin()?;
+    while let Some((field_id, field_type)) = reader.read_field_header()? {
+        match (field_id, field_type) {
+            (2, ty::LIST) => {
+                let (size, element_type) = reader.read_list_header()?;
+                if element_type != ty::STRUCT {
+                    return Err(format_err!("expected list of SchemaElement"));
+                }
+                let mut list = vec![];
+                for _ in 0..size {
+                    list.push(read_schema_element(reader)?);
+                }
+                elements = Some(list);
+            }
+            _ => reader.skip_field(field_type)?,
+        }
+    }
+    reader.read_struct_end();
+    elements.ok_or_else(|| format_err!("Parquet metadata contains no schema"))
+}
+
+/// Read a `SchemaElement` struct.
+fn read_schema_element(reader: &mut CompactReader<'_>) -> Result<SchemaElement> {
+    let mut element = SchemaElement::default();
+    reader.read_struct_begin()?;
+    while let Some((field_id, field_type)) = reader.read_field_header()? {
+        match (field_id, field_type) {
+            (1, ty::I32) => element.physical_type = Some(reader.read_i32()?),
+            (2, ty::I32) => element.type_length = Some(reader.read_i32()?),
+            (3, ty::I32) => element.repetition_type = Some(reader.read_i32()?),
+            (4, ty::BINARY) => element.name = reader.read_string()?,
+            (5, ty::I32) => element.num_children = Some(reader.read_i32()?),
+            (6, ty::I32) => element.converted_type = Some(reader.read_i32()?),
+            (7, ty::I32) => element.scale = Some(reader.read_i32()?),
+            (8, ty::I32) => element.precision = Some(reader.read_i32()?),
+            (10, ty::STRUCT) => element.logical_type = read_logical_type(reader)?,
+            _ => reader.skip_field(field_type)?,
+        }
+    }
+    reader.read_struct_end();
+    Ok(element)
+}
+
+/// Read a `LogicalType` union. Returns `None` for types we don't recognize.
+fn read_logical_type(reader: &mut CompactReader<'_>) -> Result<Option<Annotation>> {
+    let mut annotation = None;
+    reader.read_struct_begin()?;
+    while let Some((field_id, field_type)) = reader.read_field_header()? {
+        if field_type != ty::STRUCT {
+            reader.skip_field(field_type)?;
+            continue;
+        }
+        annotation = match field_id {
+            5 => {
+                let (mut scale, mut precision) = (0, 0);
+                read_struct_fields(reader, |reader, field_id, field_type| {
+                    match (field_id, field_type) {
+                        (1, ty::I32) => scale = reader.read_i32()?,
+                        (2, ty::I32) => precision = reader.read_i32()?,
+                        _ => reader.skip_field(field_type)?,
+                    }
+                    Ok(())
+                })?;
+                Some(Annotation::Decimal { precision, scale })
+            }
+            7 => {
+                let (unit, is_adjusted_to_utc) = read_time_fields(reader)?;
+                Some(Annotation::Time {
+                    unit,
+                    is_adjusted_to_utc,
+                })
+            }
+            8 => {
+                let (unit, is_adjusted_to_utc) = read_time_fields(reader)?;
+                Some(Annotation::Timestamp {
+                    unit,
+                    is_adjusted_to_utc,
+                })
+            }
+            10 => {
+                let (mut bit_width, mut is_signed) = (0, true);
+                read_struct_fields(reader, |reader, field_id, field_type| {
+                    match (field_id, field_type) {
+                        (1, ty::BYTE) => {
+                            bit_width = i8::from_le_bytes([reader.read_byte()?])
+                        }
+                        (2, _) => is_signed = bool_from_field_type(field_type)?,
+                        _ => reader.skip_field(field_type)?,
+                    }
+                    Ok(())
+                })?;
+                Some(Annotation::Integer {
+                    bit_width,
+                    is_signed,
+                })
+            }
+            // The remaining types are represented by empty structs.
+            _ => {
+                reader.skip_value(ty::STRUCT)?;
+                match field_id {
+                    1 => Some(Annotation::String),
+                    2 => Some(Annotation::Map),
+                    3 => Some(Annotation::List),
+                    4 => Some(Annotation::Enum),
+                    6 => Some(Annotation::Date),
+                    11 => Some(Annotation::Unknown),
+                    12 => Some(Annotation::Json),
+                    13 => Some(Annotation::Bson),
+                    14 => Some(Annotation::Uuid),
+                    _ => None,
+                }
+            }
+        };
+    }
+    reader.read_struct_end();
+    Ok(annotation)
+}
+
+/// Read a boolean from a struct field with type `field_type`.
+fn bool_from_field_type(field_type: u8) -> Result<bool> {
+    match field_type {
+        ty::BOOLEAN_TRUE => Ok(true),
+        ty::BOOLEAN_FALSE => Ok(false),
+        _ => Err(format_err!(
+            "expected Thrift boolean, found type {}",
+            field_type
+        )),
+    }
+}
+
+/// Read a struct, calling `f` for each field.
+fn read_struct_fields<'a, F>(reader: &mut CompactReader<'a>, mut f: F) -> Result<()>
+where
+    F: FnMut(&mut CompactReader<'a>, i16, u8) -> Result<()>,
+{
+    reader.read_struct_begin()?;
+    while let Some((field_id, field_type)) = reader.read_field_header()? {
+        f(reader, field_id, field_type)?;
+    }
+    reader.read_struct_end();
+    Ok(())
+}
+
+/// Read the fields of a `TimeType` or `TimestampType` struct.
+fn read_time_fields(reader: &mut CompactReader<'_>) -> Result<(TimeUnit, bool)> {
+    let mut unit = TimeUnit::Millis;
+    let mut is_adjusted_to_utc = false;
+    read_struct_fields(reader, |reader, field_id, field_type| {
+        match (field_id, field_type) {
+            (1, _) => is_adjusted_to_utc = bool_from_field_type(field_type)?,
+            // `TimeUnit` is a union of empty structs.
+            (2, ty::STRUCT) => {
+                read_struct_fields(reader, |reader, unit_id, unit_type| {
+                    match unit_id {
+                        1 => unit = TimeUnit::Millis,
+                        2 => unit = TimeUnit::Micros,
+                        3 => unit = TimeUnit::Nanos,
+                        _ => {}
+                    }
+                    reader.skip_field(unit_type)
+                })?;
+            }
+            _ => reader.skip_field(field_type)?,
+        }
+        Ok(())
+    })?;
+    Ok((unit, is_adjusted_to_utc))
+}
+
+/// Convert a depth-first list of schema elements into a message.
+fn message_from_schema_elements(
+    elements: Vec<SchemaElement>,
+) -> Result<ParquetMessage> {
+    let mut elements = elements.into_iter();
+    let root = elements
+        .next()
+        .ok_or_else(|| format_err!("Parquet schema is empty"))?;
+    let fields = fields_from_schema_elements(&mut elements, root.num_children)?;
+    if elements.next().is_some() {
+        return Err(format_err!("Parquet schema has extra elements"));
+    }
+    Ok(ParquetMessage {
+        name: root.name,
+        fields,
+    })
+}
+
+/// Read `num_children` fields from `elements`, recursively.
+fn fields_from_schema_elements(
+    elements: &mut impl Iterator<Item = SchemaElement>,
+    num_children: Option<i32>,
+) -> Result<Vec<ParquetField>> {
+    let num_children = usize::try_from(num_children.unwrap_or(0))?;
+    let mut fields = Vec::with_capacity(num_children.min(1024));
+    for _ in 0..num_children {
+        let element = elements
+            .next()
+            .ok_or_else(|| format_err!("Parquet schema ended unexpectedly"))?;
+        fields.push(field_from_schema_element(elements, element)?);
+    }
+    Ok(fields)
+}
+
+/// Convert `element` (and any children) into a field.
+fn field_from_schema_element(
+    elements: &mut impl Iterator<Item = SchemaElement>,
+    element: SchemaElement,
+) -> Result<ParquetField> {
+    let repetition = match element.repetition_type {
+        Some(0) => Repetition::Required,
+        Some(1) | None => Repetition::Optional,
+        Some(2) => Repetition::Repeated,
+        Some(other) => {
+            return Err(format_err!("unknown Parquet repetition type {}", other))
+        }
+    };
+    let annotation = match element.logical_type {
+        Some(annotation) => Some(annotation),
+        None => element
+            .converted_type
+            .map(|converted_type| {
+                annotation_from_converted_type(
+                    converted_type,
+                    element.precision,
+                    element.scale,
+                )
+            })
+            .transpose()?,
+    };
+    let kind = match (element.physical_type, element.num_children) {
+        (None, _) | (_, Some(_)) => FieldKind::Group(fields_from_schema_elements(
+            elements,
+            element.num_children,
+        )?),
+        (Some(physical_type), None) => FieldKind::Primitive(match physical_type {
+            0 => PhysicalType::Boolean,
+            1 => PhysicalType::Int32,
+            2 => PhysicalType::Int64,
+            3 => PhysicalType::Int96,
+            4 => PhysicalType::Float,
+            5 => PhysicalType::Double,
+            6 => PhysicalType::ByteArray,
+            7 => PhysicalType::FixedLenByteArray(element.type_length.ok_or_else(
+                || format_err!("FIXED_LEN_BYTE_ARRAY {} has no length", element.name),
+            )?),
+            other => {
+                return Err(format_err!("unknown Parquet physical type {}", other))
+            }
+        }),
+    };
+    Ok(ParquetField {
+        name: element.name,
+        repetition,
+        annotation,
+        kind,
+    })
+}
+
+/// Convert an older `ConvertedType` into an annotation.
+fn annotation_from_converted_type(
+    converted_type: i32,
+    precision: Option<i32>,
+    scale: Option<i32>,
+) -> Result<Annotation> {
+    let int = |bit_width, is_signed| Annotation::Integer {
+        bit_width,
+        is_signed,
+    };
+    // Older converted types are always adjusted to UTC.
+    let time = |unit| Annotation::Time {
+        unit,
+        is_adjusted_to_utc: true,
+    };
+    let timestamp = |unit| Annotation::Timestamp {
+        unit,
+        is_adjusted_to_utc: true,
+    };
+    match converted_type {
+        0 => Ok(Annotation::String),
+        1 => Ok(Annotation::Map),
+        2 => Ok(Annotation::MapKeyValue),
+        3 => Ok(Annotation::List),
+        4 => Ok(Annotation::Enum),
+        5 => Ok(Annotation::Decimal {
+            precision: precision.unwrap_or(0),
+            scale: scale.unwrap_or(0),
+        }),
+        6 => Ok(Annotation::Date),
+        7 => Ok(time(TimeUnit::Millis)),
+        8 => Ok(time(TimeUnit::Micros)),
+        9 => Ok(timestamp(TimeUnit::Millis)),
+        10 => Ok(timestamp(TimeUnit::Micros)),
+        11 => Ok(int(8, false)),
+        12 => Ok(int(16, false)),
+        13 => Ok(int(32, false)),
+        14 => Ok(int(64, false)),
+        15 => Ok(int(8, true)),
+        16 => Ok(int(16, true)),
+        17 => Ok(int(32, true)),
+        18 => Ok(int(64, true)),
+        19 => Ok(Annotation::Json),
+        20 => Ok(Annotation::Bson),
+        21 => Ok(Annotation::Interval),
+        other => Err(format_err!("unknown Parquet converted type {}", other)),
+    }
+}
+
+#[test]
+fn parse_footer_reads_schema() {
+    // The footer of a file with no row groups, written by hand using the
+    // compact protocol, with the schema:
+    //
+    //     message m {
+    //       required int64 id;
+    //       optional binary name (UTF8);
+    //       optional int64 at (TIMESTAMP(MICROS,false));
+    //     }
+    #[rustfmt::skip]
+    let metadata: &[u8] = &[
+        0x15, 0x02, // 1: version = 1
+        0x19, 0x4c, // 2: schema = list<struct>[4]
+        // Root element.
+        0x48, 0x01, b'm', // 4: name = "m"
+        0x15, 0x06, // 5: num_children = 3
+        0x00,
+        // id
+        0x15, 0x04, // 1: type = INT64
+        0x25, 0x00, // 3: repetition_type = REQUIRED
+        0x18, 0x02, b'i', b'd', // 4: name = "id"
+        0x00,
+        // name
+        0x15, 0x0c, // 1: type = BYTE_ARRAY
+        0x25, 0x02, // 3: repetition_type = OPTIONAL
+        0x18, 0x04, b'n', b'a', b'm', b'e', // 4: name = "name"
+        0x25, 0x00, // 6: converted_type = UTF8
+        0x00,
+        // at
+        0x15, 0x04, // 1: type = INT64
+        0x25, 0x02, // 3: repetition_type = OPTIONAL
+        0x18, 0x02, b'a', b't', // 4: name = "at"
+        0x6c, // 10: logicalType
+        0x8c, // 8: TIMESTAMP
+        0x12, // 1: isAdjustedToUTC = false
+        0x1c, // 2: unit
+        0x2c, 0x00, // 2: MICROS {}
+        0x00, 0x00, 0x00,
+        0x00,
+        0x16, 0x00, // 3: num_rows = 0
+        0x19, 0x0c, // 4: row_groups = list<struct>[0]
+        0x00,
+    ];
+    let mut data = b"PAR1".to_vec();
+    data.extend_from_slice(metadata);
+    data.extend_from_slice(&u32::try_from(metadata.len()).unwrap().to_le_bytes());
+    data.extend_from_slice(b"PAR1");
+    assert!(is_parquet_data(&data));
+
+    let message = parse_footer(&data).unwrap();
+    assert_eq!(message.to_string(), "message m {\n  required int64 id;\n  optional binary name (STRING);\n  optional int64 at (TIMESTAMP(MICROS,false));\n}\n");
+
+    // Truncated metadata should fail cleanly.
+    let mut truncated = metadata[..20].to_vec();
+    truncated.extend_from_slice(&20u32.to_le_bytes());
+    truncated.extend_from_slice(b"PAR1");
+    assert!(parse_footer(&truncated).is_err());
+}
@@ -0,0 +1,484 @@
+//! Parquet schema descriptors, in the text format used by `parquet-mr` and
+//! `parquet-tools schema`.
+//!
+//! ```text
+//! message orders {
+//!   required int64 id;
+//!   optional binary note (STRING);
+//! }
+//! ```
+
+use std::{fmt, iter::Peekable, str::CharIndices};
+
+use super::convert::{
+    is_name_char, Annotation, FieldKind, ParquetField, ParquetMessage, PhysicalType,
+    Repetition, TimeUnit,
+};
+use crate::common::*;
+
+impl fmt::Display for ParquetMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "message {} {{", self.name)?;
+        for field in &self.fields {
+            write_field(f, field, 1)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// Write `field` to `f`, indented by `depth` levels.
+fn write_field(
+    f: &mut fmt::Formatter<'_>,
+    field: &ParquetField,
+    depth: usize,
+) -> fmt::Result {
+    let indent = "  ".repeat(depth);
+    write!(f, "{}{} ", indent, field.repetition)?;
+    match &field.kind {
+        FieldKind::Primitive(physical_type) => {
+            write!(f, "{} {}", physical_type, field.name)?;
+            if let Some(annotation) = &field.annotation {
+                write!(f, " ({})", annotation)?;
+            }
+            writeln!(f, ";")
+        }
+        FieldKind::Group(children) => {
+            write!(f, "group {}", field.name)?;
+            if let Some(annotation) = &field.annotation {
+                write!(f, " ({})", annotation)?;
+            }
+            writeln!(f, " {{")?;
+            for child in children {
+                write_field(f, child, depth + 1)?;
+            }
+            writeln!(f, "{}}}", indent)
+        }
+    }
+}
+
+impl fmt::Display for Repetition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Repetition::Required => "required".fmt(f),
+            Repetition::Optional => "optional".fmt(f),
+            Repetition::Repeated => "repeated".fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for PhysicalType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PhysicalType::Boolean => "boolean".fmt(f),
+            PhysicalType::Int32 => "int32".fmt(f),
+            PhysicalType::Int64 => "int64".fmt(f),
+            PhysicalType::Int96 => "int96".fmt(f),
+            PhysicalType::Float => "float".fmt(f),
+            PhysicalType::Double => "double".fmt(f),
+            PhysicalType::ByteArray => "binary".fmt(f),
+            PhysicalType::FixedLenByteArray(len) => {
+                write!(f, "fixed_len_byte_array({})", len)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Annotation::String => "STRING".fmt(f),
+            Annotation::Enum => "ENUM".fmt(f),
+            Annotation::Json => "JSON".fmt(f),
+            Annotation::Bson => "BSON".fmt(f),
+            Annotation::Uuid => "UUID".fmt(f),
+            Annotation::Date => "DATE".fmt(f),
+            Annotation::List => "LIST".fmt(f),
+            Annotation::Map => "MAP".fmt(f),
+            Annotation::MapKeyValue => "MAP_KEY_VALUE".fmt(f),
+            Annotation::Interval => "INTERVAL".fmt(f),
+            Annotation::Unknown => "UNKNOWN".fmt(f),
+            Annotation::Decimal { precision, scale } => {
+                write!(f, "DECIMAL({},{})", precision, scale)
+            }
+            Annotation::Time {
+                unit,
+                is_adjusted_to_utc,
+            } => write!(f, "TIME({},{})", unit, is_adjusted_to_utc),
+            Annotation::Timestamp {
+                unit,
+                is_adjusted_to_utc,
+            } => write!(f, "TIMESTAMP({},{})", unit, is_adjusted_to_utc),
+            Annotation::Integer {
+                bit_width,
+                is_signed,
+            } => write!(f, "INTEGER({},{})", bit_width, is_signed),
+        }
+    }
+}
+
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeUnit::Millis => "MILLIS".fmt(f),
+            TimeUnit::Micros => "MICROS".fmt(f),
+            TimeUnit::Nanos => "NANOS".fmt(f),
+        }
+    }
+}
+
+/// Parse a schema descriptor.
+pub(crate) fn parse_message(input: &str) -> Result<ParquetMessage> {
+    let mut parser = Parser {
+        input,
+        chars: input.char_indices().peekable(),
+    };
+    parser.expect("message")?;
+    let name = parser.name()?;
+    let fields = parser.fields()?;
+    if let Some(token) = parser.next_token()? {
+        return Err(format_err!(
+            "unexpected {:?} after end of Parquet schema",
+            token
+        ));
+    }
+    Ok(ParquetMessage { name, fields })
+}
+
+/// A very simple recursive-descent parser for schema descriptors.
+struct Parser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    /// Return the next token, or `None` at the end of the input. A token is
+    /// either a name or a single punctuation character.
+    fn next_token(&mut self) -> Result<Option<&'a str>> {
+        while let Some((_, c)) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        let (start, c) = match self.chars.next() {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+        if "{}();,=".contains(c) {
+            return Ok(Some(&self.input[start..start + c.len_utf8()]));
+        } else if !is_name_char(c) {
+            return Err(format_err!("unexpected {:?} in Parquet schema", c));
+        }
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, c)) = self.chars.peek() {
+            if !is_name_char(c) {
+                break;
+            }
+            self.chars.next();
+            end = i + c.len_utf8();
+        }
+        Ok(Some(&self.input[start..end]))
+    }
+
+    /// Return the next token, failing at the end of the input.
+    fn token(&mut self) -> Result<&'a str> {
+        self.next_token()?
+            .ok_or_else(|| format_err!("unexpected end of Parquet schema"))
+    }
+
+    /// Look at the next token without consuming it.
+    fn peek_token(&mut self) -> Result<Option<&'a str>> {
+        let saved = self.chars.clone();
+        let token = self.next_token();
+        self.chars = saved;
+        token
+    }
+
+    /// Require the next token to be `expected`.
+    fn expect(&mut self, expected: &str) -> Result<()> {
+        let token = self.token()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(format_err!(
+                "expected {:?} in Parquet schema, found {:?}",
+                expected,
+                token
+            ))
+        }
+    }
+
+    /// If the next token is `expected`, consume it and return true.
+    fn accept(&mut self, expected: &str) -> Result<bool> {
+        if self.peek_token()? == Some(expected) {
+            self.token()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Parse a name.
+    fn name(&mut self) -> Result<String> {
+        let token = self.token()?;
+        if token.chars().all(is_name_char) {
+            Ok(token.to_owned())
+        } else {
+            Err(format_err!(
+                "expected name in Parquet schema, found {:?}",
+                token
+            ))
+        }
+    }
+
+    /// Parse an integer.
+    fn integer<T: std::str::FromStr>(&mut self) -> Result<T> {
+        let token = self.token()?;
+        token.parse::<T>().map_err(|_| {
+            format_err!("expected number in Parquet schema, found {:?}", token)
+        })
+    }
+
+    /// Parse `true` or `false`.
+    fn boolean(&mut self) -> Result<bool> {
+        match self.token()? {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            token => Err(format_err!(
+                "expected true or false in Parquet schema, found {:?}",
+                token
+            )),
+        }
+    }
+
+    /// Parse a list of fields surrounded by braces.
+    fn fields(&mut self) -> Result<Vec<ParquetField>> {
+        self.expect("{")?;
+        let mut fields = vec![];
+        while !self.accept("}")? {
+            fields.push(self.field()?);
+        }
+        Ok(fields)
+    }
+
+    /// Parse a single field.
+    fn field(&mut self) -> Result<ParquetField> {
+        let repetition = match self.token()? {
+            "required" => Repetition::Required,
+            "optional" => Repetition::Optional,
+            "repeated" => Repetition::Repeated,
+            token => {
+                return Err(format_err!(
+                    "expected required, optional or repeated in Parquet schema, found {:?}",
+                    token
+                ))
+            }
+        };
+        let type_name = self.token()?;
+        if type_name == "group" {
+            let name = self.name()?;
+            let annotation = self.annotation()?;
+            self.field_id()?;
+            let children = self.fields()?;
+            // Some writers put a semicolon after groups.
+            self.accept(";")?;
+            Ok(ParquetField {
+                name,
+                repetition,
+                annotation,
+                kind: FieldKind::Group(children),
+            })
+        } else {
+            let physical_type = match type_name {
+                "boolean" => PhysicalType::Boolean,
+                "int32" => PhysicalType::Int32,
+                "int64" => PhysicalType::Int64,
+                "int96" => PhysicalType::Int96,
+                "float" => PhysicalType::Float,
+                "double" => PhysicalType::Double,
+                "binary" => PhysicalType::ByteArray,
+                "fixed_len_byte_array" => {
+                    self.expect("(")?;
+                    let len = self.integer()?;
+                    self.expect(")")?;
+                    PhysicalType::FixedLenByteArray(len)
+                }
+                other => {
+                    return Err(format_err!(
+                        "unknown Parquet primitive type {:?}",
+                        other
+                    ))
+                }
+            };
+            let name = self.name()?;
+            let annotation = self.annotation()?;
+            self.field_id()?;
+            self.expect(";")?;
+            Ok(ParquetField {
+                name,
+                repetition,
+                annotation,
+                kind: FieldKind::Primitive(physical_type),
+            })
+        }
+    }
+
+    /// Parse an optional field ID, which we ignore.
+    fn field_id(&mut self) -> Result<()> {
+        if self.accept("=")? {
+            self.integer::<i32>()?;
+        }
+        Ok(())
+    }
+
+    /// Parse an optional logical type annotation in parentheses. We also
+    /// accept the older "converted type" names.
+    fn annotation(&mut self) -> Result<Option<Annotation>> {
+        if !self.accept("(")? {
+            return Ok(None);
+        }
+        let legacy_timestamp = |unit| Annotation::Timestamp {
+            unit,
+            is_adjusted_to_utc: true,
+        };
+        let legacy_time = |unit| Annotation::Time {
+            unit,
+            is_adjusted_to_utc: true,
+        };
+        let legacy_int = |bit_width, is_signed| Annotation::Integer {
+            bit_width,
+            is_signed,
+        };
+        let annotation = match self.token()? {
+            "STRING" | "UTF8" => Annotation::String,
+            "ENUM" => Annotation::Enum,
+            "JSON" => Annotation::Json,
+            "BSON" => Annotation::Bson,
+            "UUID" => Annotation::Uuid,
+            "DATE" => Annotation::Date,
+            "LIST" => Annotation::List,
+            "MAP" => Annotation::Map,
+            "MAP_KEY_VALUE" => Annotation::MapKeyValue,
+            "INTERVAL" => Annotation::Interval,
+            "UNKNOWN" => Annotation::Unknown,
+            "DECIMAL" => {
+                self.expect("(")?;
+                let precision = self.integer()?;
+                let scale = if self.accept(",")? {
+                    self.integer()?
+                } else {
+                    0
+                };
+                self.expect(")")?;
+                Annotation::Decimal { precision, scale }
+            }
+            "TIME" => {
+                let (unit, is_adjusted_to_utc) = self.unit_and_utc()?;
+                Annotation::Time {
+                    unit,
+                    is_adjusted_to_utc,
+                }
+            }
+            "TIMESTAMP" => {
+                let (unit, is_adjusted_to_utc) = self.unit_and_utc()?;
+                Annotation::Timestamp {
+                    unit,
+                    is_adjusted_to_utc,
+                }
+            }
+            "INTEGER" => {
+                self.expect("(")?;
+                let bit_width = self.integer()?;
+                self.expect(",")?;
+                let is_signed = self.boolean()?;
+                self.expect(")")?;
+                Annotation::Integer {
+                    bit_width,
+                    is_signed,
+                }
+            }
+            "TIME_MILLIS" => legacy_time(TimeUnit::Millis),
+            "TIME_MICROS" => legacy_time(TimeUnit::Micros),
+            "TIMESTAMP_MILLIS" => legacy_timestamp(TimeUnit::Millis),
+            "TIMESTAMP_MICROS" => legacy_timestamp(TimeUnit::Micros),
+            "INT_8" => legacy_int(8, true),
+            "INT_16" => legacy_int(16, true),
+            "INT_32" => legacy_int(32, true),
+            "INT_64" => legacy_int(64, true),
+            "UINT_8" => legacy_int(8, false),
+            "UINT_16" => legacy_int(16, false),
+            "UINT_32" => legacy_int(32, false),
+            "UINT_64" => legacy_int(64, false),
+            other => {
+                return Err(format_err!("unknown Parquet logical type {:?}", other))
+            }
+        };
+        self.expect(")")?;
+        Ok(Some(annotation))
+    }
+
+    /// Parse the `(UNIT,is_adjusted_to_utc)` arguments to `TIME` and
+    /// `TIMESTAMP`.
+    fn unit_and_utc(&mut self) -> Result<(TimeUnit, bool)> {
+        self.expect("(")?;
+        let unit = match self.token()? {
+            "MILLIS" => TimeUnit::Millis,
+            "MICROS" => TimeUnit::Micros,
+            "NANOS" => TimeUnit::Nanos,
+            other => return Err(format_err!("unknown Parquet time unit {:?}", other)),
+        };
+        self.expect(",")?;
+        let is_adjusted_to_utc = self.boolean()?;
+        self.expect(")")?;
+        Ok((unit, is_adjusted_to_utc))
+    }
+}
+
+#[test]
+fn parse_and_print_message() {
+    let input = r#"
+message spark_schema {
+  required int64 id = 1;
+  optional binary name (UTF8);
+  optional fixed_len_byte_array(16) amount (DECIMAL(38,9));
+  optional int64 created_at (TIMESTAMP(MICROS,true));
+  optional int32 rank (INT_16);
+  optional group tags (LIST) {
+    repeated group list {
+      optional binary element (STRING);
+    }
+  };
+}
+"#;
+    let message = parse_message(input).unwrap();
+    assert_eq!(message.name, "spark_schema");
+    assert_eq!(message.fields.len(), 6);
+    assert_eq!(message.fields[1].annotation, Some(Annotation::String));
+    assert_eq!(
+        message.fields[4].annotation,
+        Some(Annotation::Integer {
+            bit_width: 16,
+            is_signed: true
+        })
+    );
+    let expected = r#"message spark_schema {
+  required int64 id;
+  optional binary name (STRING);
+  optional fixed_len_byte_array(16) amount (DECIMAL(38,9));
+  optional int64 created_at (TIMESTAMP(MICROS,true));
+  optional int32 rank (INTEGER(16,true));
+  optional group tags (LIST) {
+    repeated group list {
+      optional binary element (STRING);
+    }
+  }
+}
+"#;
+    assert_eq!(message.to_string(), expected);
+    assert_eq!(parse_message(expected).unwrap(), message);
+
+    assert!(parse_message("message m { optional binary x }").is_err());
+    assert!(parse_message("message m { optional string x; }").is_err());
+    assert!(parse_message("message m { } extra").is_err());
+}
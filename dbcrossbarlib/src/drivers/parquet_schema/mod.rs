@@ -0,0 +1,120 @@
+//! Support for `parquet-schema` locators.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+
+mod convert;
+mod footer;
+mod message;
+
+use self::convert::{parquet_message_for_table, table_from_parquet_message};
+use self::footer::{is_parquet_data, parse_footer, read_footer_or_file};
+use self::message::parse_message;
+
+/// A Parquet file, or a Parquet schema descriptor like those printed by
+/// `parquet-tools schema`.
+#[derive(Clone, Debug)]
+pub struct ParquetSchemaLocator {
+    path: PathOrStdio,
+}
+
+impl fmt::Display for ParquetSchemaLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.path.fmt_locator_helper(Self::scheme(), f)
+    }
+}
+
+impl FromStr for ParquetSchemaLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), s)?;
+        Ok(ParquetSchemaLocator { path })
+    }
+}
+
+impl Locator for ParquetSchemaLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self, ctx: Context) -> BoxFuture<Option<Table>> {
+        schema_helper(ctx, self.to_owned()).boxed()
+    }
+
+    fn write_schema(
+        &self,
+        ctx: Context,
+        table: Table,
+        if_exists: IfExists,
+    ) -> BoxFuture<()> {
+        write_schema_helper(ctx, self.to_owned(), table, if_exists).boxed()
+    }
+}
+
+impl LocatorStatic for ParquetSchemaLocator {
+    fn scheme() -> &'static str {
+        "parquet-schema:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::Schema | LocatorFeatures::WriteSchema,
+            write_schema_if_exists: IfExistsFeatures::no_append(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
+
+/// Implementation of `schema`, but as a real `async` function.
+async fn schema_helper(
+    _ctx: Context,
+    source: ParquetSchemaLocator,
+) -> Result<Option<Table>> {
+    // Read our input. Parquet files may be large, so when we have a real file,
+    // we only read the footer.
+    let data = match &source.path {
+        PathOrStdio::Path(path) => {
+            let path = path.to_owned();
+            spawn_blocking(move || read_footer_or_file(&path)).await
+        }
+        PathOrStdio::Stdio => async_read_to_end(source.path.open_async().await?).await,
+    }
+    .with_context(|_| format!("error reading {}", source.path))?;
+
+    // Parse our input as either a Parquet file or a schema descriptor.
+    let message = if is_parquet_data(&data) {
+        parse_footer(&data)
+    } else {
+        String::from_utf8(data)
+            .map_err(Error::from)
+            .and_then(|text| parse_message(&text))
+    }
+    .with_context(|_| format!("error parsing {}", source.path))?;
+    let table = table_from_parquet_message(&message)
+        .with_context(|_| format!("error converting {}", source.path))?;
+    Ok(Some(table))
+}
+
+/// Implementation of `write_schema`, but as a real `async` function.
+async fn write_schema_helper(
+    ctx: Context,
+    dest: ParquetSchemaLocator,
+    table: Table,
+    if_exists: IfExists,
+) -> Result<()> {
+    // Convert our schema before we create our output file.
+    let message = parquet_message_for_table(&table)?;
+
+    // Output our schema to our destination.
+    let mut f = dest.path.create_async(ctx, if_exists).await?;
+    buffer_sync_write_and_copy_to_async(&mut f, |buff| write!(buff, "{}", message))
+        .await
+        .with_context(|_| format!("error writing to {}", dest.path))?;
+    f.flush().await?;
+    Ok(())
+}
@@ -2,7 +2,9 @@
 
 use super::PostgresLocator;
 use crate::common::*;
-use crate::drivers::postgres_shared::{connect, CheckCatalog, PgCreateTable};
+use crate::drivers::postgres_shared::{
+    connect, set_query_params, CheckCatalog, PgCreateTable,
+};
 
 /// Implementation of `count`, but as a real `async` function.
 pub(crate) async fn count_helper(
@@ -40,6 +42,7 @@ pub(crate) async fn count_helper(
 
     // Run our query.
     let conn = connect(&ctx, &url).await?;
+    set_query_params(&ctx, &conn, source_args.query_params()).await?;
     let stmt = conn.prepare(&sql).await?;
     let rows = conn
         .query(&stmt, &[])
@@ -198,12 +198,12 @@ fn json_to_binary<W: Write>(
     match data_type {
         PgScalarDataType::Boolean => write_json_as_binary::<bool, W>(wtr, json),
         PgScalarDataType::Date => write_json_as_binary::<NaiveDate, W>(wtr, json),
-        PgScalarDataType::Numeric => Err(format_err!(
-            "cannot use `numeric` arrays with PostgreSQL yet",
-        )),
+        PgScalarDataType::Numeric | PgScalarDataType::FixedNumeric { .. } => Err(
+            format_err!("cannot use `numeric` arrays with PostgreSQL yet"),
+        ),
         PgScalarDataType::Real => write_json_as_binary::<f32, W>(wtr, json),
         PgScalarDataType::DoublePrecision => write_json_as_binary::<f64, W>(wtr, json),
-        PgScalarDataType::Geometry(srid) => {
+        PgScalarDataType::Geography(srid) | PgScalarDataType::Geometry(srid) => {
             let geometry = Geometry::<f64>::from_json_value(json)?;
             let value = GeometryWithSrid {
                 geometry: &geometry,
@@ -256,7 +256,7 @@ fn scalar_to_binary(
     match data_type {
         PgScalarDataType::Boolean => write_cell_as_binary::<bool>(wtr, cell),
         PgScalarDataType::Date => write_cell_as_binary::<NaiveDate>(wtr, cell),
-        PgScalarDataType::Numeric => {
+        PgScalarDataType::Numeric | PgScalarDataType::FixedNumeric { .. } => {
             // The only sensible way to make this work is to port PostgresSQL's
             // own `decimal` parser from C, because it's an unusual internal
             // format built using very complicated parsing rules (and `numeric`
@@ -267,7 +267,8 @@ fn scalar_to_binary(
         }
         PgScalarDataType::Real => write_cell_as_binary::<f32>(wtr, cell),
         PgScalarDataType::DoublePrecision => write_cell_as_binary::<f64>(wtr, cell),
-        PgScalarDataType::Geometry(srid) => {
+        PgScalarDataType::Geography(srid) | PgScalarDataType::Geometry(srid) => {
+            // No WKT geometry type starts with a hex digit, so this is safe.
             if !cell.is_empty() && cell.as_bytes()[0].is_ascii_hexdigit() {
                 // We don't have valid GeoJSON, but it looks like it's hex, so
                 // try to treat it as hexadecimal-serialized EWKB data, for
@@ -275,7 +276,7 @@ fn scalar_to_binary(
                 let bytes = hex::decode(cell).context("not valid GeoJSON or EWKB")?;
                 (&bytes[..]).write_binary(wtr)
             } else {
-                // We should have correct GeoJSON data, so handle it normally.
+                // We should have GeoJSON or WKT data, so handle it normally.
                 let geometry = Geometry::<f64>::from_csv_cell(cell)?;
                 let value = GeometryWithSrid {
                     geometry: &geometry,
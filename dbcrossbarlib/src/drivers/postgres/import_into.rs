@@ -0,0 +1,86 @@
+//! Bulk loading using CockroachDB's `IMPORT INTO`.
+
+use itertools::Itertools;
+
+use super::Client;
+use crate::common::*;
+use crate::drivers::postgres_shared::{pg_quote, Ident, PgCreateTable, PgDialect};
+use crate::drivers::s3::S3Locator;
+use crate::tokio_glue::ConsumeWithParallelism;
+
+/// Can we load data into a database with `dialect` using `IMPORT INTO`? This
+/// is only supported by CockroachDB, and it can't perform upserts.
+pub(crate) fn can_import_into(dialect: PgDialect, if_exists: &IfExists) -> bool {
+    dialect == PgDialect::Cockroach && !matches!(if_exists, IfExists::Upsert(_))
+}
+
+/// Copy `data` to `s3_temp`, and then load it into `dest_table` using
+/// `IMPORT INTO`. The database cluster must be able to read from `s3_temp`
+/// using its own credentials.
+pub(crate) async fn import_into_from_s3(
+    ctx: &Context,
+    client: &mut Client,
+    dest_table: &PgCreateTable,
+    s3_temp: S3Locator,
+    data: BoxStream<CsvStream>,
+    shared_args: SharedArguments<Unverified>,
+) -> Result<()> {
+    // Copy to a temporary s3:// location.
+    let max_streams = shared_args.max_streams();
+    let to_temp_ctx = ctx.child(o!("to_temp" => s3_temp.to_string()));
+    let s3_dest_args = DestinationArguments::for_temporary();
+    let s3_urls = s3_temp
+        .write_local_data(to_temp_ctx, data, shared_args, s3_dest_args)
+        .await?
+        .consume_with_parallelism(max_streams)
+        .await?
+        .into_iter()
+        .map(|locator| locator.to_string())
+        .collect::<Vec<_>>();
+    if s3_urls.is_empty() {
+        debug!(ctx.log(), "no data to import");
+        return Ok(());
+    }
+
+    // Load from s3:// into CockroachDB.
+    let sql = import_into_sql(dest_table, &s3_urls);
+    debug!(ctx.log(), "IMPORT INTO SQL: {}", sql);
+    client.execute(&sql[..], &[]).await.with_context(|_| {
+        format!("error importing data into {}", dest_table.name.quoted())
+    })?;
+    Ok(())
+}
+
+/// Generate `IMPORT INTO` SQL for loading `s3_urls` into `dest_table`.
+fn import_into_sql(dest_table: &PgCreateTable, s3_urls: &[String]) -> String {
+    format!(
+        "IMPORT INTO {dest} ({columns})\nCSV DATA ({urls})\nWITH skip = '1', nullif = ''",
+        dest = dest_table.name.quoted(),
+        columns = dest_table.columns.iter().map(|c| Ident(&c.name)).join(", "),
+        urls = s3_urls
+            .iter()
+            .map(|url| pg_quote(&format!("{}?AUTH=implicit", url)))
+            .join(", "),
+    )
+}
+
+#[test]
+fn import_into_sql_lists_columns_and_urls() {
+    use crate::schema::{Column, DataType};
+
+    let table = PgCreateTable::from_name_and_columns(
+        "example".parse().unwrap(),
+        &[Column {
+            name: "id".to_owned(),
+            is_nullable: false,
+            data_type: DataType::Int64,
+            comment: None,
+        }],
+    )
+    .unwrap();
+    let urls = vec!["s3://bucket/tmp/a.csv".to_owned()];
+    assert_eq!(
+        import_into_sql(&table, &urls),
+        "IMPORT INTO \"example\" (\"id\")\nCSV DATA ('s3://bucket/tmp/a.csv?AUTH=implicit')\nWITH skip = '1', nullif = ''",
+    );
+}
@@ -6,7 +6,7 @@ use failure::Fail;
 use super::PostgresLocator;
 use crate::common::*;
 use crate::drivers::postgres_shared::{
-    connect, CheckCatalog, PgCreateTable, TableName,
+    connect, set_query_params, CheckCatalog, PgCreateTable, TableName,
 };
 
 /// Copy the specified table from the database, returning a `CsvStream`.
@@ -52,6 +52,7 @@ pub(crate) async fn local_data_helper(
 
     // Copy the data out of PostgreSQL as a CSV stream.
     let conn = connect(&ctx, &url).await?;
+    set_query_params(&ctx, &conn, source_args.query_params()).await?;
     let stmt = conn.prepare(&sql).await?;
     let rdr = conn
         .copy_out(&stmt)
@@ -70,6 +71,7 @@ pub(crate) async fn local_data_helper(
 
     let csv_stream = CsvStream {
         name: table_name.unquoted(),
+        metadata: StreamMetadata::default(),
         data: rdr.boxed(),
     };
     let box_stream = stream::once(async { Ok(csv_stream) }).boxed();
@@ -13,13 +13,18 @@ use crate::drivers::postgres_shared::{Client, PgCreateTable, TableName};
 
 mod count;
 mod csv_to_binary;
+#[cfg(feature = "s3")]
+mod import_into;
 mod local_data;
+mod size_hint;
 mod write_local_data;
 
 use self::count::count_helper;
 use self::local_data::local_data_helper;
+use self::size_hint::size_hint_helper;
 use self::write_local_data::write_local_data_helper;
 
+#[cfg(feature = "redshift")]
 pub(crate) use write_local_data::{
     columns_to_update_for_upsert, create_temp_table_for, prepare_table,
 };
@@ -146,6 +151,14 @@ impl Locator for PostgresLocator {
         .boxed()
     }
 
+    fn size_hint(
+        &self,
+        ctx: Context,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<SizeHint> {
+        size_hint_helper(ctx, self.clone(), source_args).boxed()
+    }
+
     fn write_local_data(
         &self,
         ctx: Context,
@@ -170,8 +183,9 @@ impl LocatorStatic for PostgresLocator {
                 | LocatorFeatures::WriteLocalData
                 | LocatorFeatures::Count,
             write_schema_if_exists: EnumSet::empty(),
-            source_args: SourceArgumentsFeatures::WhereClause.into(),
-            dest_args: EnumSet::empty(),
+            source_args: SourceArgumentsFeatures::WhereClause
+                | SourceArgumentsFeatures::QueryParams,
+            dest_args: DestinationArgumentsFeatures::DriverArgs.into(),
             dest_if_exists: IfExistsFeatures::Overwrite
                 | IfExistsFeatures::Append
                 | IfExistsFeatures::Error
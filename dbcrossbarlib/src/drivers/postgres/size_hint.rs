@@ -0,0 +1,48 @@
+//! Implementation of `size_hint`, but as a real `async` function.
+
+use super::PostgresLocator;
+use crate::common::*;
+use crate::drivers::postgres_shared::connect;
+
+/// Implementation of `size_hint`, but as a real `async` function.
+///
+/// We always return a single stream. If we're copying the whole table, we
+/// also use the on-disk size of the table as a rough estimate of how much CSV
+/// data we'll produce.
+pub(crate) async fn size_hint_helper(
+    ctx: Context,
+    locator: PostgresLocator,
+    source_args: SourceArguments<Unverified>,
+) -> Result<SizeHint> {
+    let source_args = source_args.verify(PostgresLocator::features())?;
+    let mut hint = SizeHint {
+        stream_count: Some(1),
+        total_bytes: None,
+    };
+    if source_args.where_clause().is_some() {
+        return Ok(hint);
+    }
+
+    let table_name = &locator.table_name;
+    let schema = table_name.schema().unwrap_or("public");
+    let table = table_name.table();
+    let size_sql = r#"
+SELECT pg_table_size(c.oid) AS size
+FROM pg_class c
+JOIN pg_namespace n ON n.oid = c.relnamespace
+WHERE
+    n.nspname = $1 AND
+    c.relname = $2
+"#;
+    let conn = connect(&ctx, &locator.url).await?;
+    let rows = conn
+        .query(size_sql, &[&schema, &table])
+        .await
+        .context("error looking up table size")?;
+    if let Some(row) = rows.first() {
+        let size: i64 = row.get("size");
+        hint.total_bytes = Some(u64::try_from(size).context("size out of range")?);
+    }
+    debug!(ctx.log(), "size hint for {}: {}", locator, hint);
+    Ok(hint)
+}
@@ -1,15 +1,79 @@
 //! Support for writing local data to Postgres.
 
+use chrono::Utc;
 use futures::pin_mut;
 use itertools::Itertools;
+use serde::Deserialize;
 use std::{collections::HashSet, io::prelude::*, iter::FromIterator, str};
 
+#[cfg(feature = "s3")]
+use super::import_into::{can_import_into, import_into_from_s3};
 use super::{csv_to_binary::copy_csv_to_pg_binary, Client, PostgresLocator};
+use crate::add_columns::AddColumns;
 use crate::common::*;
-use crate::drivers::postgres_shared::{connect, CheckCatalog, Ident, PgCreateTable};
+use crate::drivers::postgres_shared::{
+    connect, pg_quote, CheckCatalog, Ident, PgCreateTable, PgDialect,
+};
+#[cfg(feature = "s3")]
+use crate::drivers::s3::find_s3_temp_dir;
+use crate::select_columns::SelectColumns;
 use crate::tokio_glue::try_forward;
 use crate::transform::spawn_sync_transform;
 
+/// Parsed version of `--to-arg` values.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PostgresDestinationArguments {
+    /// Which PostgreSQL-compatible database are we writing to?
+    #[serde(default)]
+    dialect: PgDialect,
+
+    /// Columns to use for a Greenplum `DISTRIBUTED BY` clause.
+    #[serde(default)]
+    distributed_by: Vec<String>,
+
+    /// The columns to write, in order, like `id,name,email`. Any other input
+    /// columns are dropped.
+    columns: Option<String>,
+
+    /// Generated columns to add to every row, like
+    /// `loaded_at=now(),source_file=_stream_name`.
+    add_columns: Option<String>,
+
+    /// If specified, convert the destination table into a TimescaleDB
+    /// hypertable partitioned on this column.
+    timescale_partition_column: Option<String>,
+}
+
+impl PostgresDestinationArguments {
+    /// Check our arguments for consistency with each other and with
+    /// `if_exists`.
+    fn verify(&self, if_exists: &IfExists) -> Result<()> {
+        if !self.distributed_by.is_empty() && !self.dialect.supports_distributed_by() {
+            return Err(format_err!(
+                "{} does not support --to-arg=distributed_by",
+                self.dialect,
+            ));
+        }
+        if self.timescale_partition_column.is_some()
+            && self.dialect != PgDialect::Postgres
+        {
+            return Err(format_err!(
+                "{} does not support --to-arg=timescale_partition_column",
+                self.dialect,
+            ));
+        }
+        if matches!(if_exists, IfExists::Upsert(_)) && !self.dialect.supports_upsert()
+        {
+            return Err(format_err!(
+                "{} does not support --if-exists=upsert-on:...",
+                self.dialect,
+            ));
+        }
+        Ok(())
+    }
+}
+
 /// If `table_name` exists, `DROP` it.
 async fn drop_table_if_exists(
     ctx: &Context,
@@ -100,6 +164,33 @@ pub(crate) async fn prepare_table(
     create_table(ctx, client, &table).await
 }
 
+/// Convert `table` into a TimescaleDB hypertable partitioned on
+/// `partition_column`, unless it's already a hypertable.
+async fn create_hypertable(
+    ctx: &Context,
+    client: &mut Client,
+    table: &PgCreateTable,
+    partition_column: &str,
+) -> Result<()> {
+    if !table.columns.iter().any(|c| c.name == partition_column) {
+        return Err(format_err!(
+            "cannot partition {} on unknown column {}",
+            table.name.quoted(),
+            Ident(partition_column),
+        ));
+    }
+    let sql = format!(
+        "SELECT create_hypertable({}, {}, if_not_exists => TRUE)",
+        pg_quote(&table.name.quoted().to_string()),
+        pg_quote(partition_column),
+    );
+    debug!(ctx.log(), "create hypertable SQL: {}", sql);
+    client.execute(&sql[..], &[]).await.with_context(|_| {
+        format!("error creating hypertable {}", table.name.quoted())
+    })?;
+    Ok(())
+}
+
 /// Generate the `COPY ... FROM ...` SQL we'll pass to `copy_in`. `data_format`
 /// should be something like `"CSV HRADER"` or `"BINARY"`.
 ///
@@ -122,17 +213,17 @@ fn copy_from_sql(table: &PgCreateTable, data_format: &str) -> Result<String> {
     Ok(copy_sql)
 }
 
-/// Given `stream` containing CSV data, plus a the URL and table_name for a
-/// destination, as well as `"COPY FROM"` SQL, copy the data into the specified
-/// destination.
+/// Given `stream` containing data in `data_format`, plus a the URL and
+/// table_name for a destination, copy the data into the specified destination.
 async fn copy_from_stream<'a>(
     ctx: &'a Context,
     client: &'a mut Client,
     dest: &'a PgCreateTable,
+    data_format: &'a str,
     stream: BoxStream<BytesMut>,
 ) -> Result<()> {
     debug!(ctx.log(), "copying data into {:?}", dest.name);
-    let copy_from_sql = copy_from_sql(&dest, "BINARY")?;
+    let copy_from_sql = copy_from_sql(&dest, data_format)?;
     let stmt = client.prepare(&copy_from_sql).await?;
     let sink = client
         .copy_in::<_, BytesMut>(&stmt)
@@ -234,6 +325,27 @@ pub(crate) async fn upsert_from(
     Ok(())
 }
 
+/// Return a copy of `table` containing only the columns in `columns`, in the
+/// order they were listed.
+fn select_schema_columns(columns: &SelectColumns, table: &Table) -> Result<Table> {
+    let selected = columns
+        .names()
+        .iter()
+        .map(|name| {
+            table
+                .columns
+                .iter()
+                .find(|c| &c.name == name)
+                .cloned()
+                .ok_or_else(|| format_err!("cannot select unknown column {:?}", name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Table {
+        name: table.name.clone(),
+        columns: selected,
+    })
+}
+
 /// The actual implementation of `write_local_data`, in a separate function so we
 /// can use `async`.
 pub(crate) async fn write_local_data_helper(
@@ -243,12 +355,49 @@ pub(crate) async fn write_local_data_helper(
     shared_args: SharedArguments<Unverified>,
     dest_args: DestinationArguments<Unverified>,
 ) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
-    let shared_args = shared_args.verify(PostgresLocator::features())?;
+    let shared_args_v = shared_args.clone().verify(PostgresLocator::features())?;
     let dest_args = dest_args.verify(PostgresLocator::features())?;
 
     // Look up our arguments.
-    let schema = shared_args.schema();
+    let mut schema = shared_args_v.schema().to_owned();
     let if_exists = dest_args.if_exists().to_owned();
+    let pg_dest_args = dest_args
+        .driver_args()
+        .deserialize::<PostgresDestinationArguments>()
+        .context("could not parse --to-arg")?;
+    pg_dest_args.verify(&if_exists)?;
+    let dialect = pg_dest_args.dialect;
+
+    // If we're only writing some columns, remove the others from our schema
+    // and from each incoming stream.
+    let columns = pg_dest_args
+        .columns
+        .as_ref()
+        .map(|s| s.parse::<SelectColumns>())
+        .transpose()?;
+    if let Some(columns) = columns {
+        schema = select_schema_columns(&columns, &schema)?;
+        let ctx = ctx.clone();
+        data = data
+            .map(move |stream| columns.select_from_csv_stream(&ctx, stream?))
+            .boxed();
+    }
+
+    // If we're adding generated columns, add them to our schema and to each
+    // incoming stream.
+    let add_columns = pg_dest_args
+        .add_columns
+        .as_ref()
+        .map(|s| s.parse::<AddColumns>())
+        .transpose()?;
+    if let Some(add_columns) = add_columns {
+        schema = add_columns.add_to_schema(&schema)?;
+        let ctx = ctx.clone();
+        let now = Utc::now();
+        data = data
+            .map(move |stream| add_columns.add_to_csv_stream(&ctx, stream?, now))
+            .boxed();
+    }
 
     let url = dest.url.clone();
     let table_name = dest.table_name.clone();
@@ -261,18 +410,61 @@ pub(crate) async fn write_local_data_helper(
     );
 
     // Try to look up our destination table schema in the database.
-    let dest_table = PgCreateTable::from_pg_catalog_or_default(
+    let mut dest_table = PgCreateTable::from_pg_catalog_or_default(
         &ctx,
         CheckCatalog::from(&if_exists),
         dest.url(),
         dest.table_name(),
-        schema,
+        &schema,
     )
     .await?;
+    if !pg_dest_args.distributed_by.is_empty() {
+        dest_table.distributed_by = Some(pg_dest_args.distributed_by.clone());
+    }
+
+    // If we can't use `BINARY`, we'll need to pass our CSV data straight
+    // through, so make sure that it will mean the same thing to the database.
+    let data_format = if dialect.supports_binary_copy() {
+        "BINARY"
+    } else {
+        dialect.check_csv_copy_compatible(&dest_table)?;
+        "CSV HEADER"
+    };
 
     // Connect to PostgreSQL and prepare our destination table.
     let mut client = connect(&ctx, &url).await?;
+    if let Some(setup_sql) = dialect.session_setup_sql() {
+        client
+            .execute(setup_sql, &[])
+            .await
+            .with_context(|_| format!("error running {:?}", setup_sql))?;
+    }
     prepare_table(&ctx, &mut client, dest_table.clone(), &if_exists).await?;
+    if let Some(partition_column) = &pg_dest_args.timescale_partition_column {
+        create_hypertable(&ctx, &mut client, &dest_table, partition_column).await?;
+    }
+
+    // If we can load our data using `IMPORT INTO`, and we have somewhere to
+    // stage it, do that instead of using `COPY`. This is much faster.
+    #[cfg(feature = "s3")]
+    if can_import_into(dialect, &if_exists) {
+        if let Ok(s3_temp) = find_s3_temp_dir(shared_args_v.temporary_storage()) {
+            s3_temp.delete_when_done(shared_args_v.temporary_storage());
+            let fut = async move {
+                import_into_from_s3(
+                    &ctx,
+                    &mut client,
+                    &dest_table,
+                    s3_temp,
+                    data,
+                    shared_args,
+                )
+                .await?;
+                Ok(dest.boxed())
+            };
+            return Ok(box_stream_once(Ok(fut.boxed())));
+        }
+    }
 
     // Insert data streams one at a time, because parallel insertion _probably_
     // won't gain much with Postgres (but we haven't measured).
@@ -286,16 +478,21 @@ pub(crate) async fn write_local_data_helper(
                 Ok(csv_stream) => {
                     let ctx = ctx.child(o!("stream" => csv_stream.name.clone()));
 
-                    // Convert our CSV stream into a PostgreSQL `BINARY` stream.
-                    let transform_table = dest_table.clone();
-                    let binary_stream = spawn_sync_transform(
-                        ctx.clone(),
-                        "copy_csv_to_pg_binary".to_owned(),
-                        csv_stream.data,
-                        move |_ctx, rdr, wtr| {
-                            copy_csv_to_pg_binary(&transform_table, rdr, wtr)
-                        },
-                    )?;
+                    // Convert our CSV stream into a PostgreSQL `BINARY` stream,
+                    // if our database supports it.
+                    let copy_stream = if dialect.supports_binary_copy() {
+                        let transform_table = dest_table.clone();
+                        spawn_sync_transform(
+                            ctx.clone(),
+                            "copy_csv_to_pg_binary".to_owned(),
+                            csv_stream.data,
+                            move |_ctx, rdr, wtr| {
+                                copy_csv_to_pg_binary(&transform_table, rdr, wtr)
+                            },
+                        )?
+                    } else {
+                        csv_stream.data
+                    };
 
                     // Decide whether to do an upsert or regular insert.
                     if let IfExists::Upsert(cols) = &if_exists {
@@ -309,7 +506,8 @@ pub(crate) async fn write_local_data_helper(
                             &ctx,
                             &mut client,
                             &temp_table,
-                            binary_stream,
+                            data_format,
+                            copy_stream,
                         )
                         .await?;
 
@@ -332,7 +530,8 @@ pub(crate) async fn write_local_data_helper(
                             &ctx,
                             &mut client,
                             &dest_table,
-                            binary_stream,
+                            data_format,
+                            copy_stream,
                         )
                         .await?;
                     }
@@ -52,12 +52,25 @@ struct PgColumnSchema {
     data_type: String,
     udt_schema: String,
     udt_name: String,
+    numeric_precision: Option<i32>,
+    numeric_scale: Option<i32>,
+    comment: Option<String>,
 }
 
 impl PgColumnSchema {
     /// Get the data type for a column.
     fn data_type(&self) -> Result<PgDataType> {
-        pg_data_type(&self.data_type, &self.udt_schema, &self.udt_name)
+        let ty = pg_data_type(&self.data_type, &self.udt_schema, &self.udt_name)?;
+        match (ty, self.numeric_precision) {
+            // `numeric` columns declared with a precision also have a scale.
+            (PgDataType::Scalar(PgScalarDataType::Numeric), Some(precision)) => {
+                Ok(PgDataType::Scalar(PgScalarDataType::FixedNumeric {
+                    precision: u32::try_from(precision)?,
+                    scale: u32::try_from(self.numeric_scale.unwrap_or(0))?,
+                }))
+            }
+            (ty, _) => Ok(ty),
+        }
     }
 }
 
@@ -89,9 +102,17 @@ WHERE
         return Ok(None);
     }
 
-    // Look up column information.
+    // Look up column information. `ordinal_position` is the same as the
+    // `attnum` used by `col_description`.
     let columns_sql = r#"
-SELECT column_name, is_nullable, data_type, udt_schema, udt_name 
+SELECT
+    column_name, is_nullable, data_type, udt_schema, udt_name,
+    numeric_precision::integer AS numeric_precision,
+    numeric_scale::integer AS numeric_scale,
+    col_description(
+        (quote_ident(table_schema) || '.' || quote_ident(table_name))::regclass,
+        ordinal_position::integer
+    ) AS comment
 FROM information_schema.columns
 WHERE
     table_schema = $1 AND
@@ -107,16 +128,24 @@ ORDER BY ordinal_position
             data_type: row.get("data_type"),
             udt_schema: row.get("udt_schema"),
             udt_name: row.get("udt_name"),
+            numeric_precision: row.get("numeric_precision"),
+            numeric_scale: row.get("numeric_scale"),
+            comment: row.get("comment"),
         })
         .collect::<Vec<PgColumnSchema>>();
 
-    // Do we have any PostGIS geometry columns?
-    let need_srids = pg_columns
-        .iter()
-        .any(|c| c.data_type == "USER-DEFINED" && c.udt_name == "geometry");
+    // Do we have any PostGIS geometry or geography columns?
+    let has_udt = |name: &str| {
+        pg_columns
+            .iter()
+            .any(|c| c.data_type == "USER-DEFINED" && c.udt_name == name)
+    };
 
-    // Look up SRIDs for our geometry columns.
-    let srid_map = if need_srids {
+    // Look up SRIDs for our geometry and geography columns. We only run these
+    // queries if we have such columns, because it's possible that PostGIS
+    // isn't installed and we have no `Find_SRID` function.
+    let mut spatial_types = HashMap::new();
+    if has_udt("geometry") {
         // This SQL will fail if `Find_SRID` isn't defined. But `Find_SRID` is
         // part of the PostGIS extension, and we've confirmed that we have
         // geometry columns, so we should be fine.
@@ -135,26 +164,40 @@ WHERE
     data_type = 'USER-DEFINED' AND
     udt_name = 'geometry'
 "#;
-        let rows = client.query(srid_sql, &[&schema, &table]).await?;
-        rows.into_iter()
-            .map(|row| {
-                let name = row.get("column_name");
-                let srid: i32 = row.get("srid");
-                Ok((name, Srid::new(u32::try_from(srid)?)))
-            })
-            .collect::<Result<HashMap<String, Srid>>>()?
-    } else {
-        // If we don't have any geometry columns, then we don't want to run the
-        // query, because it's possible that PostGIS isn't installed and we have
-        // no `find_srid` function.
-        HashMap::new()
-    };
+        for row in client.query(srid_sql, &[&schema, &table]).await? {
+            let name: String = row.get("column_name");
+            let srid: i32 = row.get("srid");
+            let ty = PgScalarDataType::Geometry(Srid::new(u32::try_from(srid)?));
+            spatial_types.insert(name, ty);
+        }
+    }
+    if has_udt("geography") {
+        // As above, `geography_columns` is only defined if PostGIS is
+        // installed. PostGIS reports an SRID of 0 for `geography` columns
+        // declared without one, which means WGS 84.
+        let srid_sql = r#"
+SELECT f_geography_column AS column_name, srid
+FROM geography_columns
+WHERE
+    f_table_schema = $1 AND
+    f_table_name = $2
+"#;
+        for row in client.query(srid_sql, &[&schema, &table]).await? {
+            let name: String = row.get("column_name");
+            let srid: i32 = row.get("srid");
+            let srid = match srid {
+                0 => Srid::wgs84(),
+                srid => Srid::new(u32::try_from(srid)?),
+            };
+            spatial_types.insert(name, PgScalarDataType::Geography(srid));
+        }
+    }
 
     let mut columns = Vec::with_capacity(pg_columns.len());
     for pg_col in pg_columns {
         // Get the data type for our column.
-        let data_type = if let Some(srid) = srid_map.get(&pg_col.column_name) {
-            PgDataType::Scalar(PgScalarDataType::Geometry(*srid))
+        let data_type = if let Some(ty) = spatial_types.get(&pg_col.column_name) {
+            PgDataType::Scalar(ty.clone())
         } else {
             pg_col.data_type()?
         };
@@ -173,6 +216,7 @@ WHERE
                     ));
                 }
             },
+            comment: pg_col.comment,
         })
     }
 
@@ -181,6 +225,7 @@ WHERE
         columns,
         temporary: false,
         if_not_exists: false,
+        distributed_by: None,
     }))
 }
 
@@ -217,8 +262,9 @@ fn pg_data_type(
     } else if data_type == "USER-DEFINED" {
         match udt_name {
             "citext" => Ok(PgDataType::Scalar(PgScalarDataType::Text)),
-            "geometry" => Err(format_err!(
-                "cannot extract SRID for geometry columns without database connection"
+            "geography" | "geometry" => Err(format_err!(
+                "cannot extract SRID for {} columns without database connection",
+                udt_name,
             )),
             other => Err(format_err!("unknown user-defined data type {:?}", other)),
         }
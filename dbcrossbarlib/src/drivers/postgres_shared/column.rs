@@ -15,6 +15,8 @@ pub(crate) struct PgColumn {
     pub(crate) data_type: PgDataType,
     /// Can this column be `NULL`?
     pub(crate) is_nullable: bool,
+    /// The comment on this column, if any.
+    pub(crate) comment: Option<String>,
 }
 
 impl PgColumn {
@@ -25,6 +27,7 @@ impl PgColumn {
             name: col.name.clone(),
             data_type,
             is_nullable: col.is_nullable,
+            comment: col.comment.clone(),
         })
     }
 
@@ -34,7 +37,7 @@ impl PgColumn {
             name: self.name.clone(),
             data_type: self.data_type.to_data_type()?,
             is_nullable: self.is_nullable,
-            comment: None,
+            comment: self.comment.clone(),
         })
     }
 
@@ -94,7 +97,8 @@ impl PgColumn {
             PgDataType::Array { .. } => {
                 write!(f, "array_to_json({name}) AS {name}", name = name)?;
             }
-            PgDataType::Scalar(PgScalarDataType::Geometry(_srid)) => {
+            PgDataType::Scalar(PgScalarDataType::Geography(_srid))
+            | PgDataType::Scalar(PgScalarDataType::Geometry(_srid)) => {
                 // TODO: This will preserve the current SRID of the column, so
                 // let's hope `_srid` matches the database's if we make it this far.
                 write!(f, "ST_AsGeoJSON({name}) AS {name}", name = name)?;
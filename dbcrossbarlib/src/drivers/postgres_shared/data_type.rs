@@ -116,8 +116,10 @@ pub(crate) enum PgScalarDataType {
     Boolean,
     Date,
     Numeric,
+    FixedNumeric { precision: u32, scale: u32 },
     Real,
     DoublePrecision,
+    Geography(Srid),
     Geometry(Srid),
     Smallint,
     Int,
@@ -141,6 +143,12 @@ impl PgScalarDataType {
             DataType::Bool => Ok(PgScalarDataType::Boolean),
             DataType::Date => Ok(PgScalarDataType::Date),
             DataType::Decimal => Ok(PgScalarDataType::Numeric),
+            DataType::FixedDecimal { precision, scale } => {
+                Ok(PgScalarDataType::FixedNumeric {
+                    precision: *precision,
+                    scale: *scale,
+                })
+            }
             DataType::Float32 => Ok(PgScalarDataType::Real),
             DataType::Float64 => Ok(PgScalarDataType::DoublePrecision),
             DataType::GeoJson(srid) => Ok(PgScalarDataType::Geometry(*srid)),
@@ -166,9 +174,17 @@ impl PgScalarDataType {
             PgScalarDataType::Boolean => Ok(DataType::Bool),
             PgScalarDataType::Date => Ok(DataType::Date),
             PgScalarDataType::Numeric => Ok(DataType::Decimal),
+            PgScalarDataType::FixedNumeric { precision, scale } => {
+                Ok(DataType::FixedDecimal {
+                    precision: *precision,
+                    scale: *scale,
+                })
+            }
             PgScalarDataType::Real => Ok(DataType::Float32),
             PgScalarDataType::DoublePrecision => Ok(DataType::Float64),
-            PgScalarDataType::Geometry(srid) => Ok(DataType::GeoJson(*srid)),
+            PgScalarDataType::Geography(srid) | PgScalarDataType::Geometry(srid) => {
+                Ok(DataType::GeoJson(*srid))
+            }
             PgScalarDataType::Smallint => Ok(DataType::Int16),
             PgScalarDataType::Int => Ok(DataType::Int32),
             PgScalarDataType::Bigint => Ok(DataType::Int64),
@@ -191,9 +207,14 @@ impl PgScalarDataType {
         match self {
             PgScalarDataType::Boolean => Ok(16),
             PgScalarDataType::Date => Ok(1082),
-            PgScalarDataType::Numeric => Ok(1700),
+            PgScalarDataType::Numeric | PgScalarDataType::FixedNumeric { .. } => {
+                Ok(1700)
+            }
             PgScalarDataType::Real => Ok(700),
             PgScalarDataType::DoublePrecision => Ok(701),
+            PgScalarDataType::Geography(_) => Err(format_err!(
+                "don't know the PostgreSQL OID for type `geography`"
+            )),
             PgScalarDataType::Geometry(_) => Err(format_err!(
                 "don't know the PostgreSQL OID for type `geometry`"
             )),
@@ -216,8 +237,14 @@ impl fmt::Display for PgScalarDataType {
             PgScalarDataType::Boolean => write!(f, "boolean")?,
             PgScalarDataType::Date => write!(f, "date")?,
             PgScalarDataType::Numeric => write!(f, "numeric")?,
+            PgScalarDataType::FixedNumeric { precision, scale } => {
+                write!(f, "numeric({}, {})", precision, scale)?
+            }
             PgScalarDataType::Real => write!(f, "real")?,
             PgScalarDataType::DoublePrecision => write!(f, "double precision")?,
+            PgScalarDataType::Geography(srid) => {
+                write!(f, "public.geography(Geometry, {})", srid)?
+            }
             PgScalarDataType::Geometry(srid) => {
                 write!(f, "public.geometry(Geometry, {})", srid)?
             }
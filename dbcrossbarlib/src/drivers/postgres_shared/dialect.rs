@@ -0,0 +1,100 @@
+//! Variations between PostgreSQL-compatible databases.
+
+use serde::Deserialize;
+use std::fmt;
+
+use super::{PgCreateTable, PgDataType, PgScalarDataType};
+use crate::common::*;
+
+/// Which PostgreSQL-compatible database are we talking to?
+///
+/// Many databases speak the PostgreSQL wire protocol, but they differ in which
+/// SQL they accept and how they prefer to load data.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PgDialect {
+    /// Regular PostgreSQL.
+    Postgres,
+    /// Greenplum, a massively-parallel fork of PostgreSQL.
+    Greenplum,
+    /// CockroachDB, a distributed database which speaks the PostgreSQL
+    /// protocol.
+    Cockroach,
+}
+
+impl PgDialect {
+    /// Can we load data using `COPY ... FROM STDIN WITH BINARY`? If not, we
+    /// need to fall back to `CSV`.
+    pub(crate) fn supports_binary_copy(self) -> bool {
+        match self {
+            PgDialect::Postgres => true,
+            PgDialect::Greenplum | PgDialect::Cockroach => false,
+        }
+    }
+
+    /// Does this database support `INSERT ... ON CONFLICT`?
+    pub(crate) fn supports_upsert(self) -> bool {
+        match self {
+            PgDialect::Postgres | PgDialect::Cockroach => true,
+            PgDialect::Greenplum => false,
+        }
+    }
+
+    /// Does this database support `DISTRIBUTED BY` clauses?
+    pub(crate) fn supports_distributed_by(self) -> bool {
+        match self {
+            PgDialect::Postgres | PgDialect::Cockroach => false,
+            PgDialect::Greenplum => true,
+        }
+    }
+
+    /// SQL which needs to be run after connecting, before we do anything else.
+    pub(crate) fn session_setup_sql(self) -> Option<&'static str> {
+        match self {
+            PgDialect::Postgres | PgDialect::Greenplum => None,
+            // We need this to create the temporary tables used for upserts.
+            PgDialect::Cockroach => Some("SET experimental_enable_temp_tables = 'on'"),
+        }
+    }
+
+    /// Make sure that we can load `table` by passing our CSV interchange
+    /// format directly to `COPY ... WITH CSV`. This is only true if none of our
+    /// columns need to be converted to PostgreSQL's own text representation.
+    pub(crate) fn check_csv_copy_compatible(
+        self,
+        table: &PgCreateTable,
+    ) -> Result<()> {
+        for col in &table.columns {
+            match &col.data_type {
+                PgDataType::Array { .. }
+                | PgDataType::Scalar(PgScalarDataType::Geography(_))
+                | PgDataType::Scalar(PgScalarDataType::Geometry(_)) => {
+                    return Err(format_err!(
+                        "cannot load column {} of type {} into {}",
+                        col.name,
+                        col.data_type,
+                        self,
+                    ));
+                }
+                PgDataType::Scalar(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for PgDialect {
+    fn default() -> Self {
+        PgDialect::Postgres
+    }
+}
+
+impl fmt::Display for PgDialect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgDialect::Postgres => "PostgreSQL".fmt(f),
+            PgDialect::Greenplum => "Greenplum".fmt(f),
+            PgDialect::Cockroach => "CockroachDB".fmt(f),
+        }
+    }
+}
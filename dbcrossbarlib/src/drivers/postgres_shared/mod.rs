@@ -12,10 +12,14 @@ use crate::common::*;
 mod catalog;
 mod column;
 mod data_type;
+mod dialect;
+mod query_params;
 mod table;
 
 pub(crate) use self::column::PgColumn;
 pub(crate) use self::data_type::{PgDataType, PgScalarDataType};
+pub(crate) use self::dialect::PgDialect;
+pub(crate) use self::query_params::{bind_query_params, set_query_params};
 pub(crate) use self::table::{CheckCatalog, PgCreateTable};
 
 /// Connect to the database, using SSL if possible.
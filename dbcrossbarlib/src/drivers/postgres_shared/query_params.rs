@@ -0,0 +1,243 @@
+//! Binding `--query-param` values to PostgreSQL `WHERE` clauses.
+//!
+//! PostgreSQL can't bind parameters to a `COPY (SELECT ...)` statement, so we
+//! store each parameter in a session setting using `set_config`, and replace
+//! `@name` in the `WHERE` clause with `current_setting('dbcrossbar.name')`.
+//! The value itself never appears in our SQL.
+
+use super::Client;
+
+use crate::common::*;
+
+/// The name of the session setting we use to store the parameter `name`.
+fn setting_name(name: &str) -> String {
+    format!("dbcrossbar.{}", name.to_ascii_lowercase())
+}
+
+/// Store `params` in session settings on `client`, so that they can be used by
+/// SQL generated with `bind_query_params`.
+pub(crate) async fn set_query_params(
+    ctx: &Context,
+    client: &Client,
+    params: &[QueryParam],
+) -> Result<()> {
+    for param in params {
+        let setting = setting_name(param.name());
+        debug!(ctx.log(), "setting query parameter {}", setting);
+        client
+            .execute(
+                "SELECT set_config($1, $2, false)",
+                &[&setting, &param.value()],
+            )
+            .await
+            .with_context(|_| {
+                format!("could not set query parameter {:?}", param.name())
+            })?;
+    }
+    Ok(())
+}
+
+/// Replace every `@name` in `sql` which refers to one of `params` with an
+/// expression returning the value of that parameter as `text`. We don't look
+/// inside string literals, quoted identifiers or comments.
+pub(crate) fn bind_query_params(sql: &str, params: &[QueryParam]) -> String {
+    if params.is_empty() {
+        return sql.to_owned();
+    }
+
+    let bytes = sql.as_bytes();
+    let mut out = String::with_capacity(sql.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' => {
+                // `E'...'` strings allow backslash escapes.
+                let escapes = i > 0
+                    && (bytes[i - 1] == b'E' || bytes[i - 1] == b'e')
+                    && (i < 2 || !is_ident_byte(bytes[i - 2]));
+                i = skip_quoted(bytes, i, b'\'', escapes);
+            }
+            b'"' => i = skip_quoted(bytes, i, b'"', false),
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = find(bytes, i, b"\n").map_or(bytes.len(), |end| end + 1);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = skip_block_comment(bytes, i)
+            }
+            b'$' => i = skip_dollar_quoted(bytes, i),
+            b'@' => {
+                let start = i + 1;
+                let end = simple_ident_end(bytes, start);
+                let param = params
+                    .iter()
+                    .find(|p| p.name().eq_ignore_ascii_case(&sql[start..end]));
+                match param {
+                    Some(param) if end > start => {
+                        out.push_str(&sql[copied..i]);
+                        out.push_str(&format!(
+                            "current_setting('{}')",
+                            setting_name(param.name()),
+                        ));
+                        copied = end;
+                        i = end;
+                    }
+                    _ => i += 1,
+                }
+            }
+            b if is_ident_byte(b) => {
+                while i < bytes.len() && is_ident_byte(bytes[i]) {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    out.push_str(&sql[copied..]);
+    out
+}
+
+/// Can `b` appear in an unquoted identifier or number? We treat all non-ASCII
+/// bytes as identifier characters.
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+/// Find the end of a parameter name starting at `start`, or return `start` if
+/// there isn't one.
+fn simple_ident_end(bytes: &[u8], start: usize) -> usize {
+    match bytes.get(start) {
+        Some(b) if b.is_ascii_alphabetic() || *b == b'_' => {}
+        _ => return start,
+    }
+    let mut end = start;
+    while end < bytes.len()
+        && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_')
+    {
+        end += 1;
+    }
+    if end < bytes.len() && is_ident_byte(bytes[end]) {
+        // This is part of a longer identifier, which can't be a parameter.
+        start
+    } else {
+        end
+    }
+}
+
+/// Find the first occurrence of `needle` in `bytes` at or after `start`.
+fn find(bytes: &[u8], start: usize, needle: &[u8]) -> Option<usize> {
+    bytes[start..]
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|pos| start + pos)
+}
+
+/// Skip over a string or identifier beginning with `quote` at `start`, and
+/// return the position just after it.
+fn skip_quoted(bytes: &[u8], start: usize, quote: u8, escapes: bool) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if escapes && bytes[i] == b'\\' {
+            i += 2;
+        } else if bytes[i] == quote {
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+            } else {
+                return i + 1;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Skip over a `/* ... */` comment at `start`, which may contain nested
+/// comments, and return the position just after it.
+fn skip_block_comment(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else if bytes[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// If there's a `$tag$ ... $tag$` string at `start`, skip over it. Otherwise,
+/// skip the `$`.
+fn skip_dollar_quoted(bytes: &[u8], start: usize) -> usize {
+    let tag_start = start + 1;
+    let mut tag_end = tag_start;
+    if bytes.get(tag_start).map_or(false, |b| {
+        b.is_ascii_alphabetic() || *b == b'_' || *b >= 0x80
+    }) {
+        while tag_end < bytes.len()
+            && is_ident_byte(bytes[tag_end])
+            && bytes[tag_end] != b'$'
+        {
+            tag_end += 1;
+        }
+    }
+    if bytes.get(tag_end) != Some(&b'$') {
+        return start + 1;
+    }
+    let delimiter = &bytes[start..=tag_end];
+    find(bytes, tag_end + 1, delimiter)
+        .map_or(bytes.len(), |end| end + delimiter.len())
+}
+
+#[test]
+fn binds_query_params() {
+    let params = ["day=2024-06-01", "Name=x"]
+        .iter()
+        .map(|s| s.parse::<QueryParam>().unwrap())
+        .collect::<Vec<_>>();
+    let day = "current_setting('dbcrossbar.day')";
+    let name = "current_setting('dbcrossbar.name')";
+    let examples = &[
+        (
+            "created_on = @day::date",
+            format!("created_on = {}::date", day),
+        ),
+        ("a=@DAY AND b=@name", format!("a={} AND b={}", day, name)),
+        (
+            "@days > 0 AND @other > 0",
+            "@days > 0 AND @other > 0".to_owned(),
+        ),
+        ("@day$x", "@day$x".to_owned()),
+        ("x @ -1", "x @ -1".to_owned()),
+        ("'@day' || @day", format!("'@day' || {}", day)),
+        ("'it''s @day' = @day", format!("'it''s @day' = {}", day)),
+        (r"E'\'@day' = @day", format!(r"E'\'@day' = {}", day)),
+        (r"e'\\' = @day", format!(r"e'\\' = {}", day)),
+        (r"'\' = @day", format!(r"'\' = {}", day)),
+        ("\"@day\" = @day", format!("\"@day\" = {}", day)),
+        ("@day -- @day\n= 1", format!("{} -- @day\n= 1", day)),
+        (
+            "/* /* @day */ @day */ @day",
+            format!("/* /* @day */ @day */ {}", day),
+        ),
+        ("$$@day$$ = @day", format!("$$@day$$ = {}", day)),
+        (
+            "$q$ $$ @day $q$ = @day",
+            format!("$q$ $$ @day $q$ = {}", day),
+        ),
+        ("$1 = @day", format!("$1 = {}", day)),
+        ("'unterminated @day", "'unterminated @day".to_owned()),
+    ];
+    for (sql, expected) in examples {
+        assert_eq!(&bind_query_params(sql, &params), expected, "for {:?}", sql);
+    }
+    assert_eq!(bind_query_params("@day", &[]), "@day");
+}
@@ -30,6 +30,7 @@ peg::parser! {
                     // We don't worry about trying to parse this, which we only use
                     // internally at the moment.
                     temporary: false,
+                    distributed_by: None,
                 }
             }
 
@@ -40,6 +41,7 @@ peg::parser! {
                     name,
                     is_nullable,
                     data_type,
+                    comment: None,
                 }
             }
 
@@ -74,6 +76,10 @@ peg::parser! {
             / i("date") { PgScalarDataType::Date }
             / i("double") ws() i("precision") { PgScalarDataType::DoublePrecision }
             / i("float") { PgScalarDataType::DoublePrecision }
+            / i("public.")? i("geography") srid:geography_srid() {
+                // PostGIS defaults to WGS 84 for `geography` columns.
+                PgScalarDataType::Geography(srid.map_or_else(Srid::wgs84, Srid::new))
+            }
             / i("public.")? i("geometry") ws()? "(" ws()? identifier() ws()? "," ws()? srid:srid() ws()? ")" {
                 PgScalarDataType::Geometry(Srid::new(srid))
             }
@@ -81,7 +87,12 @@ peg::parser! {
             / i("int") { PgScalarDataType::Int }
             / i("jsonb") { PgScalarDataType::Jsonb }
             / i("json") { PgScalarDataType::Json }
+            / (i("numeric") / i("decimal")) ws()? "(" ws()? precision:number() ws()?
+              scale:("," ws()? scale:number() ws()? { scale })? ")" {
+                PgScalarDataType::FixedNumeric { precision, scale: scale.unwrap_or(0) }
+            }
             / i("numeric") { PgScalarDataType::Numeric }
+            / i("decimal") { PgScalarDataType::Numeric }
             / i("real") { PgScalarDataType::Real }
             / i("smallint") { PgScalarDataType::Smallint }
             / i("text") { PgScalarDataType::Text }
@@ -96,6 +107,17 @@ peg::parser! {
             }
             / i("uuid") { PgScalarDataType::Uuid }
 
+        /// The optional `(type, srid)` following `geography`, which may omit the
+        /// SRID.
+        rule geography_srid() -> Option<u32>
+            = ws()? "(" ws()? identifier() ws()?
+              srid:("," ws()? srid:srid() ws()? { srid })? ")" { srid }
+            / { None }
+
+        /// A non-negative integer, like the precision of a `numeric` type.
+        rule number() -> u32
+            = n:$(['0'..='9']+) {? n.parse().or(Err("number")) }
+
         /// A GeoJSON SRID number, used to identify a coordinate system.
         rule srid() -> u32
             = srid:$(['0'..='9']+) { srid.parse().expect("should always parse") }
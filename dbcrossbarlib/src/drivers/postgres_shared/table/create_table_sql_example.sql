@@ -11,5 +11,7 @@ CREATE TABLE example (
     -- Just to be annoying:
     i public.geometry(Geometry,3857),
     j smallint,
-    k timestamp without time zone
+    k timestamp without time zone,
+    l geography(Point,4326),
+    m geography
 )
@@ -3,7 +3,7 @@
 use itertools::Itertools;
 use std::{collections::HashMap, fmt, iter::FromIterator, sync::Arc};
 
-use super::{catalog, PgColumn, TableName};
+use super::{bind_query_params, catalog, Ident, PgColumn, TableName};
 use crate::common::*;
 use crate::parse_error::{Annotation, FileInfo, ParseError};
 use crate::schema::Column;
@@ -49,6 +49,8 @@ pub struct PgCreateTable {
     pub(crate) if_not_exists: bool,
     /// Create a temporary table local to a specific client session.
     pub(crate) temporary: bool,
+    /// Columns to use for a Greenplum `DISTRIBUTED BY` clause, if any.
+    pub(crate) distributed_by: Option<Vec<String>>,
 }
 
 impl PgCreateTable {
@@ -93,6 +95,7 @@ impl PgCreateTable {
             columns: pg_columns,
             if_not_exists: false,
             temporary: false,
+            distributed_by: None,
         })
     }
 
@@ -192,6 +195,7 @@ impl PgCreateTable {
                 .collect::<Result<Vec<_>>>()?,
             if_not_exists: self.if_not_exists,
             temporary: self.temporary,
+            distributed_by: self.distributed_by.clone(),
         })
     }
 
@@ -224,6 +228,8 @@ impl PgCreateTable {
         }
         write!(f, " FROM {}", &self.name.quoted())?;
         if let Some(where_clause) = source_args.where_clause() {
+            let where_clause =
+                bind_query_params(where_clause, source_args.query_params());
             write!(f, " WHERE ({})", where_clause)?;
         }
         Ok(())
@@ -238,6 +244,8 @@ impl PgCreateTable {
         writeln!(f, "SELECT COUNT(*)")?;
         writeln!(f, " FROM {}", &self.name.quoted())?;
         if let Some(where_clause) = source_args.where_clause() {
+            let where_clause =
+                bind_query_params(where_clause, source_args.query_params());
             writeln!(f, " WHERE ({})", where_clause)?;
         }
         Ok(())
@@ -263,7 +271,16 @@ impl fmt::Display for PgCreateTable {
                 writeln!(f, ",")?;
             }
         }
-        writeln!(f, ");")?;
+        write!(f, ")")?;
+        if let Some(distributed_by) = &self.distributed_by {
+            write!(f, " DISTRIBUTED BY (")?;
+            let mut sep = Separator::new(", ");
+            for col_name in distributed_by {
+                write!(f, "{}{}", sep.display(), Ident(col_name))?;
+            }
+            write!(f, ")")?;
+        }
+        writeln!(f, ";")?;
         Ok(())
     }
 }
@@ -348,6 +365,18 @@ mod test {
                     data_type: DataType::TimestampWithoutTimeZone,
                     comment: None,
                 },
+                Column {
+                    name: "l".to_string(),
+                    is_nullable: true,
+                    data_type: DataType::GeoJson(Srid::wgs84()),
+                    comment: None,
+                },
+                Column {
+                    name: "m".to_string(),
+                    is_nullable: true,
+                    data_type: DataType::GeoJson(Srid::wgs84()),
+                    comment: None,
+                },
             ],
         };
         assert_eq!(table, expected);
@@ -363,4 +392,23 @@ mod test {
         let parsed_again = pg_parsed_again.to_table().unwrap();
         assert_eq!(parsed_again, expected);
     }
+
+    #[test]
+    fn distributed_by_clause() {
+        let mut pg_table = PgCreateTable::from_name_and_columns(
+            "example".parse::<TableName>().unwrap(),
+            &[Column {
+                name: "id".to_string(),
+                is_nullable: false,
+                data_type: DataType::Int64,
+                comment: None,
+            }],
+        )
+        .unwrap();
+        pg_table.distributed_by = Some(vec!["id".to_owned()]);
+        assert_eq!(
+            pg_table.to_string(),
+            "CREATE TABLE \"example\" (\n    \"id\" bigint NOT NULL\n) DISTRIBUTED BY (\"id\");\n",
+        );
+    }
 }
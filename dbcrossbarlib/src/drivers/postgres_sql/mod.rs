@@ -112,7 +112,7 @@ async fn write_schema_helper(
 ///
 /// This will use an valid-looking table name if it can find one somewhere in
 /// the string, or it will return a default value.
-fn sanitize_table_name(table_name: &str) -> Result<String> {
+pub(crate) fn sanitize_table_name(table_name: &str) -> Result<String> {
     lazy_static! {
         static ref RE: Regex = Regex::new(
             r"(?x)
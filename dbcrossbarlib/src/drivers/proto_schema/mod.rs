@@ -0,0 +1,82 @@
+//! Support for `proto-schema` locators.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+
+mod proto;
+
+use self::proto::proto_for_table;
+
+/// A Protocol Buffers `.proto` file containing a message which matches our
+/// table. This is only supported as an output.
+#[derive(Clone, Debug)]
+pub struct ProtoSchemaLocator {
+    path: PathOrStdio,
+}
+
+impl fmt::Display for ProtoSchemaLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.path.fmt_locator_helper(Self::scheme(), f)
+    }
+}
+
+impl FromStr for ProtoSchemaLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), s)?;
+        Ok(ProtoSchemaLocator { path })
+    }
+}
+
+impl Locator for ProtoSchemaLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn write_schema(
+        &self,
+        ctx: Context,
+        table: Table,
+        if_exists: IfExists,
+    ) -> BoxFuture<()> {
+        write_schema_helper(ctx, self.to_owned(), table, if_exists).boxed()
+    }
+}
+
+impl LocatorStatic for ProtoSchemaLocator {
+    fn scheme() -> &'static str {
+        "proto-schema:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::WriteSchema.into(),
+            write_schema_if_exists: IfExistsFeatures::no_append(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
+
+/// Implementation of `write_schema`, but as a real `async` function.
+async fn write_schema_helper(
+    ctx: Context,
+    dest: ProtoSchemaLocator,
+    table: Table,
+    if_exists: IfExists,
+) -> Result<()> {
+    // Generate our `.proto` file before we create our output file.
+    let proto = proto_for_table(&table)?;
+
+    // Output our schema to our destination.
+    let mut f = dest.path.create_async(ctx, if_exists).await?;
+    buffer_sync_write_and_copy_to_async(&mut f, |buff| write!(buff, "{}", proto))
+        .await
+        .with_context(|_| format!("error writing to {}", dest.path))?;
+    f.flush().await?;
+    Ok(())
+}
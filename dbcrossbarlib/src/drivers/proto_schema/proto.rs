@@ -0,0 +1,303 @@
+//! Generating Protocol Buffers message definitions from our portable schemas.
+//!
+//! See https://developers.google.com/protocol-buffers/docs/proto3 for the
+//! format.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Write as _,
+};
+
+use crate::common::*;
+use crate::schema::{DataType, StructField};
+use crate::unique_name::unique_name;
+
+/// The well-known type we use for JSON values.
+const VALUE_TYPE: &str = "google.protobuf.Value";
+
+/// The well-known type we use for timestamps with a time zone.
+const TIMESTAMP_TYPE: &str = "google.protobuf.Timestamp";
+
+/// Generate a `.proto` file containing a message which matches `table`.
+pub(crate) fn proto_for_table(table: &Table) -> Result<String> {
+    let mut writer = ProtoWriter::default();
+    let fields = table
+        .columns
+        .iter()
+        .map(|col| {
+            (
+                StructField {
+                    name: col.name.clone(),
+                    is_nullable: col.is_nullable,
+                    data_type: col.data_type.clone(),
+                },
+                col.comment.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let message = writer.message(&message_name(&table.name), &fields, 0)?;
+
+    let mut out = String::new();
+    writeln!(
+        &mut out,
+        "// Generated by dbcrossbar from {:?}.",
+        table.name
+    )?;
+    writeln!(&mut out, "syntax = \"proto3\";")?;
+    if !writer.imports.is_empty() {
+        writeln!(&mut out)?;
+        for import in &writer.imports {
+            writeln!(&mut out, "import \"{}\";", import)?;
+        }
+    }
+    writeln!(&mut out)?;
+    out.push_str(&message);
+    Ok(out)
+}
+
+/// State we need while generating a `.proto` file.
+#[derive(Default)]
+struct ProtoWriter {
+    /// The files we need to import.
+    imports: BTreeSet<&'static str>,
+}
+
+impl ProtoWriter {
+    /// Generate a message named `name` containing `fields`, indented by
+    /// `depth` levels. Any nested messages are defined inside this message.
+    fn message(
+        &mut self,
+        name: &str,
+        fields: &[(StructField, Option<&str>)],
+        depth: usize,
+    ) -> Result<String> {
+        let indent = "  ".repeat(depth);
+        let mut body = String::new();
+        let mut nested = String::new();
+        let mut field_names = HashSet::new();
+        let mut nested_names = HashSet::new();
+        for (i, (field, comment)) in fields.iter().enumerate() {
+            let field_name =
+                unique_name(&mut field_names, field_name(&field.name), "_");
+            let (is_repeated, element_type) = match &field.data_type {
+                DataType::Array(element_type) => (true, element_type.as_ref()),
+                other => (false, other),
+            };
+            let (is_message, proto_type) = match element_type {
+                DataType::Array(_) => {
+                    return Err(format_err!(
+                        "cannot represent nested arrays in column {}",
+                        field.name
+                    ))
+                }
+                DataType::Struct(struct_fields) => {
+                    let nested_name =
+                        unique_name(&mut nested_names, message_name(&field.name), "_");
+                    let struct_fields = struct_fields
+                        .iter()
+                        .map(|f| (f.to_owned(), None))
+                        .collect::<Vec<_>>();
+                    nested.push('\n');
+                    nested.push_str(&self.message(
+                        &nested_name,
+                        &struct_fields,
+                        depth + 1,
+                    )?);
+                    (true, nested_name)
+                }
+                other => {
+                    let proto_type = self.scalar_type(other);
+                    (proto_type.starts_with("google."), proto_type.to_owned())
+                }
+            };
+
+            // Choose a label. Message types already track whether they're
+            // present, so only scalars need `optional`.
+            let label = if is_repeated {
+                "repeated "
+            } else if field.is_nullable && !is_message {
+                "optional "
+            } else {
+                ""
+            };
+
+            if let Some(comment) = comment {
+                for line in comment.lines() {
+                    writeln!(&mut body, "{}  // {}", indent, line)?;
+                }
+            }
+            write!(
+                &mut body,
+                "{}  {}{} {} = {}",
+                indent,
+                label,
+                proto_type,
+                field_name,
+                i + 1,
+            )?;
+            // Make sure JSON data uses the original column name.
+            if default_json_name(&field_name) != field.name {
+                write!(&mut body, " [json_name = {:?}]", field.name)?;
+            }
+            writeln!(&mut body, ";")?;
+        }
+
+        let mut out = String::new();
+        writeln!(&mut out, "{}message {} {{", indent, name)?;
+        out.push_str(&body);
+        out.push_str(&nested);
+        writeln!(&mut out, "{}}}", indent)?;
+        Ok(out)
+    }
+
+    /// Choose a protobuf type for a data type which isn't an array or struct.
+    fn scalar_type(&mut self, data_type: &DataType) -> &'static str {
+        match data_type {
+            DataType::Array(_) | DataType::Struct(_) => {
+                unreachable!("arrays and structs are handled by caller")
+            }
+            DataType::Bool => "bool",
+            // Dates, decimals and UUIDs are represented using the same strings
+            // we use in CSV files, so that they're not rounded or reformatted.
+            DataType::Date
+            | DataType::Decimal
+            | DataType::FixedDecimal { .. }
+            | DataType::Text
+            | DataType::TimestampWithoutTimeZone
+            | DataType::Uuid => "string",
+            DataType::Float32 => "float",
+            DataType::Float64 => "double",
+            DataType::GeoJson(_) | DataType::Json => {
+                self.imports.insert("google/protobuf/struct.proto");
+                VALUE_TYPE
+            }
+            DataType::Int16 | DataType::Int32 => "int32",
+            DataType::Int64 => "int64",
+            DataType::TimestampWithTimeZone => {
+                self.imports.insert("google/protobuf/timestamp.proto");
+                TIMESTAMP_TYPE
+            }
+        }
+    }
+}
+
+/// Convert a column name into a valid protobuf field name.
+fn field_name(name: &str) -> String {
+    let mut result = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        result.insert_str(0, "f_");
+    }
+    result
+}
+
+/// Convert a table or column name into a `PascalCase` message name.
+fn message_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut capitalize = true;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if capitalize {
+                result.push(c.to_ascii_uppercase());
+            } else {
+                result.push(c);
+            }
+            capitalize = false;
+        } else {
+            capitalize = true;
+        }
+    }
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        result.insert(0, 'M');
+    }
+    result
+}
+
+/// The JSON name that `protoc` would choose for a field: the field name in
+/// `lowerCamelCase`.
+fn default_json_name(field_name: &str) -> String {
+    let mut result = String::new();
+    let mut capitalize = false;
+    for c in field_name.chars() {
+        if c == '_' {
+            capitalize = true;
+        } else if capitalize {
+            result.push(c.to_ascii_uppercase());
+            capitalize = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[test]
+fn proto_for_table_generates_messages() {
+    use crate::schema::Column;
+
+    let column = |name: &str, is_nullable: bool, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let table = Table {
+        name: "public.orders".to_owned(),
+        columns: vec![
+            Column {
+                comment: Some("Unique ID".to_owned()),
+                ..column("id", false, DataType::Uuid)
+            },
+            column("total", false, DataType::Decimal),
+            column("quantity", true, DataType::Int16),
+            column("placed_at", true, DataType::TimestampWithTimeZone),
+            column("extra", true, DataType::Json),
+            column("tags", true, DataType::Array(Box::new(DataType::Text))),
+            column("Customer Name", true, DataType::Text),
+            column(
+                "shipping_address",
+                true,
+                DataType::Struct(vec![StructField {
+                    name: "zip".to_owned(),
+                    is_nullable: false,
+                    data_type: DataType::Text,
+                }]),
+            ),
+        ],
+    };
+    let expected = r#"// Generated by dbcrossbar from "public.orders".
+syntax = "proto3";
+
+import "google/protobuf/struct.proto";
+import "google/protobuf/timestamp.proto";
+
+message PublicOrders {
+  // Unique ID
+  string id = 1;
+  string total = 2;
+  optional int32 quantity = 3;
+  google.protobuf.Timestamp placed_at = 4 [json_name = "placed_at"];
+  google.protobuf.Value extra = 5;
+  repeated string tags = 6;
+  optional string Customer_Name = 7 [json_name = "Customer Name"];
+  ShippingAddress shipping_address = 8 [json_name = "shipping_address"];
+
+  message ShippingAddress {
+    string zip = 1;
+  }
+}
+"#;
+    assert_eq!(proto_for_table(&table).unwrap(), expected);
+
+    let nested = Table {
+        name: "t".to_owned(),
+        columns: vec![column(
+            "matrix",
+            false,
+            DataType::Array(Box::new(DataType::Array(Box::new(DataType::Int64)))),
+        )],
+    };
+    assert!(proto_for_table(&nested).is_err());
+}
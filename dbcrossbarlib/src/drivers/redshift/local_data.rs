@@ -1,6 +1,6 @@
 //! Helper for reading data from BigQuery.
 
-use super::RedshiftLocator;
+use super::{aws_role, RedshiftLocator};
 use crate::common::*;
 use crate::drivers::s3::find_s3_temp_dir;
 
@@ -13,7 +13,10 @@ pub(crate) async fn local_data_helper(
 ) -> Result<Option<BoxStream<CsvStream>>> {
     // Build a temporary location.
     let shared_args_v = shared_args.clone().verify(RedshiftLocator::features())?;
-    let s3_temp = find_s3_temp_dir(shared_args_v.temporary_storage())?;
+    let source_args_v = source_args.clone().verify(RedshiftLocator::features())?;
+    let mut s3_temp = find_s3_temp_dir(shared_args_v.temporary_storage())?;
+    s3_temp.set_role(aws_role(source_args_v.driver_args())?);
+    s3_temp.delete_when_done(shared_args_v.temporary_storage());
     let s3_dest_args = DestinationArguments::for_temporary();
     let s3_source_args = SourceArguments::for_temporary();
 
@@ -8,6 +8,7 @@ use std::{
     str::{self, FromStr},
 };
 
+use crate::clouds::aws::{AwsCredentials, AwsRole};
 use crate::common::*;
 use crate::drivers::postgres::PostgresLocator;
 use crate::drivers::{
@@ -150,8 +151,36 @@ impl LocatorStatic for RedshiftLocator {
     }
 }
 
-/// Given a `DriverArgs` structure, convert it into Redshift credentials SQL.
-pub(crate) fn credentials_sql(args: &DriverArguments) -> Result<String> {
+/// Given a `DriverArgs` structure, look up the IAM role specified by the
+/// `role_arn` and `external_id` arguments, if any.
+pub(crate) fn aws_role(args: &DriverArguments) -> Result<Option<AwsRole>> {
+    let map = args.deserialize::<HashMap<String, String>>()?;
+    AwsRole::from_driver_args(
+        map.get("role_arn").map(|s| &s[..]),
+        map.get("external_id").map(|s| &s[..]),
+    )
+}
+
+/// Given a `DriverArgs` structure, convert it into Redshift credentials SQL. If
+/// `role_arn` is specified, we assume that role and pass its temporary
+/// credentials to Redshift.
+pub(crate) async fn credentials_sql(
+    ctx: &Context,
+    args: &DriverArguments,
+) -> Result<String> {
+    let role_creds = match aws_role(args)? {
+        Some(role) => Some(role.assume(ctx).await?),
+        None => None,
+    };
+    credentials_sql_with(args, role_creds.as_ref())
+}
+
+/// Convert `args` into Redshift credentials SQL, using `role_creds` in place of
+/// `role_arn` and `external_id`.
+fn credentials_sql_with(
+    args: &DriverArguments,
+    role_creds: Option<&AwsCredentials>,
+) -> Result<String> {
     let mut out = vec![];
     let map = args.deserialize::<HashMap<String, String>>()?;
     for (k, v) in &map {
@@ -159,10 +188,51 @@ pub(crate) fn credentials_sql(args: &DriverArguments) -> Result<String> {
             static ref KEY_RE: Regex =
                 Regex::new("^[_A-Za-z0-9]+$").expect("invalid regex in source code");
         }
+        if k == "role_arn" || k == "external_id" {
+            continue;
+        }
         if !KEY_RE.is_match(k) {
             return Err(format_err!("cannot pass {:?} as Redshift credential", k));
         }
         writeln!(&mut out, "{} {}", k, pg_quote(v))?;
     }
+    if let Some(creds) = role_creds {
+        writeln!(&mut out, "ACCESS_KEY_ID {}", pg_quote(&creds.access_key_id))?;
+        writeln!(
+            &mut out,
+            "SECRET_ACCESS_KEY {}",
+            pg_quote(&creds.secret_access_key),
+        )?;
+        if let Some(session_token) = &creds.session_token {
+            writeln!(&mut out, "SESSION_TOKEN {}", pg_quote(session_token))?;
+        }
+    }
     Ok(String::from_utf8(out).expect("found non-UTF-8 SQL"))
 }
+
+#[test]
+fn credentials_sql_uses_role_credentials() {
+    let args = DriverArguments::from_cli_args(&[
+        "role_arn=arn:aws:iam::123456789012:role/etl",
+        "external_id=x",
+        "region=us-east-1",
+    ])
+    .unwrap();
+    assert!(aws_role(&args).unwrap().is_some());
+    let creds = AwsCredentials {
+        access_key_id: "AKID".to_owned(),
+        secret_access_key: "secret".to_owned(),
+        session_token: Some("token".to_owned()),
+    };
+    assert_eq!(
+        credentials_sql_with(&args, Some(&creds)).unwrap(),
+        concat!(
+            "region 'us-east-1'\n",
+            "ACCESS_KEY_ID 'AKID'\n",
+            "SECRET_ACCESS_KEY 'secret'\n",
+            "SESSION_TOKEN 'token'\n",
+        ),
+    );
+    let args = DriverArguments::from_cli_args(&["external_id=x"]).unwrap();
+    assert!(aws_role(&args).is_err());
+}
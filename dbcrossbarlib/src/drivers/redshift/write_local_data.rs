@@ -1,6 +1,6 @@
 //! Implementation of `write_local_data` for Redshift.
 
-use super::RedshiftLocator;
+use super::{aws_role, RedshiftLocator};
 use crate::common::*;
 use crate::drivers::s3::find_s3_temp_dir;
 use crate::tokio_glue::ConsumeWithParallelism;
@@ -15,7 +15,10 @@ pub(crate) async fn write_local_data_helper(
 ) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
     // Build a temporary location.
     let shared_args_v = shared_args.clone().verify(RedshiftLocator::features())?;
-    let s3_temp = find_s3_temp_dir(shared_args_v.temporary_storage())?;
+    let dest_args_v = dest_args.clone().verify(RedshiftLocator::features())?;
+    let mut s3_temp = find_s3_temp_dir(shared_args_v.temporary_storage())?;
+    s3_temp.set_role(aws_role(dest_args_v.driver_args())?);
+    s3_temp.delete_when_done(shared_args_v.temporary_storage());
     let s3_dest_args = DestinationArguments::for_temporary();
     let s3_source_args = SourceArguments::for_temporary();
 
@@ -38,7 +38,7 @@ pub(crate) async fn write_remote_data_helper(
     let ctx = ctx.child(o!("source_url" => source_url.as_str().to_owned()));
 
     let shared_args = shared_args.verify(RedshiftLocator::features())?;
-    let _source_args = source_args.verify(Features::empty())?;
+    let _source_args = source_args.verify(Features::default())?;
     let dest_args = dest_args.verify(RedshiftLocator::features())?;
 
     // Look up our arguments.
@@ -103,7 +103,7 @@ async fn copy_in(
         "COPY {dest} FROM {source}\n{credentials}FORMAT CSV\nIGNOREHEADER 1\nDATEFORMAT 'auto'\nTIMEFORMAT 'auto'",
         dest = dest_table.quoted(),
         source = pg_quote(source_s3_url.as_str()), // `$1` doesn't work here.
-        credentials = credentials_sql(to_args)?,
+        credentials = credentials_sql(ctx, to_args).await?,
     );
     let copy_stmt = client.prepare(&copy_sql).await?;
     client.execute(&copy_stmt, &[]).await.with_context(|_| {
@@ -250,8 +250,12 @@ impl VerifyRedshiftCanImportFromCsv for DataType {
             | DataType::Text
             | DataType::TimestampWithoutTimeZone
             | DataType::TimestampWithTimeZone => Ok(()),
+            // Redshift's `numeric` defaults to `numeric(18, 0)`, so we can only
+            // load decimals whose precision we know.
+            DataType::FixedDecimal { precision, .. } if *precision <= 38 => Ok(()),
             DataType::Array(_)
             | DataType::Decimal
+            | DataType::FixedDecimal { .. }
             | DataType::GeoJson(_)
             | DataType::Json
             | DataType::Struct(_)
@@ -0,0 +1,260 @@
+//! Generating Rust structs from our portable schemas.
+
+use std::{collections::HashSet, fmt::Write as _};
+
+use crate::common::*;
+use crate::schema::{DataType, StructField};
+use crate::unique_name::unique_name;
+
+/// Rust keywords, which can't be used as field names. We include reserved
+/// keywords, too.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for",
+    "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut",
+    "override", "priv", "pub", "ref", "return", "self", "static", "struct", "super",
+    "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Generate a Rust source file containing a struct which matches `table`.
+pub(crate) fn rust_for_table(table: &Table) -> Result<String> {
+    let mut writer = RustWriter::default();
+    let fields = table
+        .columns
+        .iter()
+        .map(|col| {
+            (
+                StructField {
+                    name: col.name.clone(),
+                    is_nullable: col.is_nullable,
+                    data_type: col.data_type.clone(),
+                },
+                col.comment.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let name = unique_name(&mut writer.struct_names, struct_name(&table.name), "");
+    writer.rust_struct(&name, &fields)?;
+
+    let mut out = String::new();
+    writeln!(
+        &mut out,
+        "// Generated by dbcrossbar from {:?}.",
+        table.name
+    )?;
+    writeln!(&mut out)?;
+    writeln!(&mut out, "use serde::{{Deserialize, Serialize}};")?;
+    for rust_struct in &writer.structs {
+        writeln!(&mut out)?;
+        out.push_str(rust_struct);
+    }
+    Ok(out)
+}
+
+/// State we need while generating Rust code.
+#[derive(Default)]
+struct RustWriter {
+    /// The structs we've generated so far, in the order they should appear.
+    structs: Vec<String>,
+    /// The struct names we've already used.
+    struct_names: HashSet<String>,
+}
+
+impl RustWriter {
+    /// Generate a struct named `name` containing `fields`. Structs for any
+    /// nested structs will appear after this one.
+    fn rust_struct(
+        &mut self,
+        name: &str,
+        fields: &[(StructField, Option<&str>)],
+    ) -> Result<()> {
+        // Reserve a slot for our struct, so that it appears before any structs
+        // that it refers to.
+        let idx = self.structs.len();
+        self.structs.push(String::new());
+
+        let mut out = String::new();
+        writeln!(
+            &mut out,
+            "#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]"
+        )?;
+        writeln!(&mut out, "pub struct {} {{", name)?;
+        let mut field_names = HashSet::new();
+        for (field, comment) in fields {
+            if let Some(comment) = comment {
+                for line in comment.lines() {
+                    writeln!(&mut out, "    /// {}", line)?;
+                }
+            }
+            let field_name =
+                unique_name(&mut field_names, field_name(&field.name), "_");
+            if field_name != field.name {
+                writeln!(&mut out, "    #[serde(rename = {:?})]", field.name)?;
+            }
+            let mut ty = self.rust_type(name, &field.name, &field.data_type)?;
+            if field.is_nullable {
+                ty = format!("Option<{}>", ty);
+            }
+            writeln!(&mut out, "    pub {}: {},", field_name, ty)?;
+        }
+        writeln!(&mut out, "}}")?;
+
+        self.structs[idx] = out;
+        Ok(())
+    }
+
+    /// Choose a Rust type for `data_type`, which appears in the field
+    /// `field_name` of the struct `parent`.
+    fn rust_type(
+        &mut self,
+        parent: &str,
+        field_name: &str,
+        data_type: &DataType,
+    ) -> Result<String> {
+        Ok(match data_type {
+            DataType::Array(elem_type) => {
+                format!("Vec<{}>", self.rust_type(parent, field_name, elem_type)?)
+            }
+            DataType::Bool => "bool".to_owned(),
+            DataType::Date => "chrono::NaiveDate".to_owned(),
+            // Use a string so that we never round decimal values.
+            DataType::Decimal | DataType::FixedDecimal { .. } | DataType::Text => {
+                "String".to_owned()
+            }
+            DataType::Float32 => "f32".to_owned(),
+            DataType::Float64 => "f64".to_owned(),
+            DataType::GeoJson(_) | DataType::Json => "serde_json::Value".to_owned(),
+            DataType::Int16 => "i16".to_owned(),
+            DataType::Int32 => "i32".to_owned(),
+            DataType::Int64 => "i64".to_owned(),
+            DataType::Struct(fields) => {
+                let name = unique_name(
+                    &mut self.struct_names,
+                    format!("{}{}", parent, struct_name(field_name)),
+                    "",
+                );
+                let fields = fields
+                    .iter()
+                    .map(|f| (f.to_owned(), None))
+                    .collect::<Vec<_>>();
+                self.rust_struct(&name, &fields)?;
+                name
+            }
+            DataType::TimestampWithoutTimeZone => "chrono::NaiveDateTime".to_owned(),
+            DataType::TimestampWithTimeZone => {
+                "chrono::DateTime<chrono::Utc>".to_owned()
+            }
+            DataType::Uuid => "uuid::Uuid".to_owned(),
+        })
+    }
+}
+
+/// Convert a column name into a `snake_case` Rust field name.
+fn field_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut prev_was_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if prev_was_lower {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+            prev_was_lower = false;
+        } else if c.is_ascii_alphanumeric() {
+            result.push(c);
+            prev_was_lower = true;
+        } else {
+            result.push('_');
+            prev_was_lower = false;
+        }
+    }
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        || result == "_"
+    {
+        result.insert_str(0, "f_");
+    }
+    if KEYWORDS.contains(&result.as_str()) {
+        result.push('_');
+    }
+    result
+}
+
+/// Convert a table or column name into a `PascalCase` struct name.
+fn struct_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut capitalize = true;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if capitalize {
+                result.push(c.to_ascii_uppercase());
+            } else {
+                result.push(c);
+            }
+            capitalize = false;
+        } else {
+            capitalize = true;
+        }
+    }
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        result.insert(0, 'T');
+    }
+    result
+}
+
+#[test]
+fn rust_for_table_generates_structs() {
+    use crate::schema::Column;
+
+    let column = |name: &str, is_nullable: bool, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let table = Table {
+        name: "public.orders".to_owned(),
+        columns: vec![
+            Column {
+                comment: Some("Unique ID".to_owned()),
+                ..column("id", false, DataType::Uuid)
+            },
+            column("total", false, DataType::Decimal),
+            column("placed_at", true, DataType::TimestampWithTimeZone),
+            column("type", true, DataType::Text),
+            column("CustomerName", true, DataType::Text),
+            column(
+                "line_items",
+                false,
+                DataType::Array(Box::new(DataType::Struct(vec![StructField {
+                    name: "quantity".to_owned(),
+                    is_nullable: false,
+                    data_type: DataType::Int16,
+                }]))),
+            ),
+        ],
+    };
+    let expected = r#"// Generated by dbcrossbar from "public.orders".
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PublicOrders {
+    /// Unique ID
+    pub id: uuid::Uuid,
+    pub total: String,
+    pub placed_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    #[serde(rename = "CustomerName")]
+    pub customer_name: Option<String>,
+    pub line_items: Vec<PublicOrdersLineItems>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PublicOrdersLineItems {
+    pub quantity: i16,
+}
+"#;
+    assert_eq!(rust_for_table(&table).unwrap(), expected);
+}
@@ -1,47 +1,276 @@
 //! Reading data from AWS S3.
 
+use futures::future;
+use serde::Deserialize;
+use std::path::PathBuf;
+
 use super::S3Locator;
-use crate::clouds::aws::s3;
+use crate::clouds::aws::{
+    s3::{self, RequestPayer, S3Connection, S3Object},
+    AwsRole,
+};
 use crate::common::*;
+use crate::compression::decompress_stream_for_file_name;
 use crate::csv_stream::csv_stream_name;
+use crate::decrypt::DecryptionKey;
+use crate::driver_args::deserialize_opt_from_str;
+use crate::key_pattern::{KeyFilter, KeyPattern};
+use crate::manifest::is_manifest_url;
+
+/// Parsed version of `--from-arg` values.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct S3SourceArguments {
+    /// A private key to use to decrypt PGP- or age-encrypted files.
+    decrypt_key: Option<PathBuf>,
+
+    /// Set to `requester` to read from requester-pays buckets.
+    request_payer: Option<RequestPayer>,
+
+    /// An IAM role to assume using STS, like
+    /// `arn:aws:iam::123456789012:role/etl`.
+    role_arn: Option<String>,
+
+    /// The external ID required to assume `role_arn`, if any.
+    external_id: Option<String>,
+
+    /// The AWS region containing our bucket, instead of looking it up.
+    region: Option<String>,
+
+    /// Should we use S3 Transfer Acceleration?
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    accelerate: Option<bool>,
+
+    /// Only read files matching this glob pattern, like `*.csv.gz`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    include: Option<KeyPattern>,
+
+    /// Skip files matching this glob pattern, like `_SUCCESS`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    exclude: Option<KeyPattern>,
+}
+
+impl S3SourceArguments {
+    /// Which IAM role should we assume? If no `role_arn` argument was
+    /// specified, we use `default_role`.
+    fn aws_role(&self, default_role: Option<&AwsRole>) -> Result<Option<AwsRole>> {
+        let role = AwsRole::from_driver_args(
+            self.role_arn.as_deref(),
+            self.external_id.as_deref(),
+        )?;
+        Ok(role.or_else(|| default_role.cloned()))
+    }
+
+    /// How should we connect to S3? If no `role_arn` argument was specified,
+    /// we assume `default_role`.
+    fn connection(&self, default_role: Option<&AwsRole>) -> Result<S3Connection> {
+        S3Connection::new(
+            self.aws_role(default_role)?,
+            self.region.clone(),
+            self.accelerate.unwrap_or(false),
+        )
+    }
+
+    /// Decide which files to read, using our `include` and `exclude`
+    /// arguments.
+    fn key_filter(&self) -> KeyFilter {
+        KeyFilter::new(self.include.clone(), self.exclude.clone())
+    }
+}
+
+/// List the files we should read at `url`, skipping any manifests we wrote
+/// and anything excluded by `key_filter`.
+async fn list_files(
+    ctx: &Context,
+    url: &Url,
+    request_payer: Option<RequestPayer>,
+    conn: &S3Connection,
+    key_filter: KeyFilter,
+) -> Result<BoxStream<S3Object>> {
+    let (_, prefix) = s3::parse_s3_url(url)?;
+    let files = s3::ls(ctx, url, request_payer, conn)
+        .await?
+        .try_filter(move |item| {
+            future::ready(
+                !is_manifest_url(item.url.as_str())
+                    && key_filter.should_read(&prefix, &item.key),
+            )
+        });
+    Ok(files.boxed())
+}
 
 /// Implementation of `local_data`, but as a real `async` function.
 pub(crate) async fn local_data_helper(
     ctx: Context,
     url: Url,
+    default_role: Option<AwsRole>,
     shared_args: SharedArguments<Unverified>,
     source_args: SourceArguments<Unverified>,
 ) -> Result<Option<BoxStream<CsvStream>>> {
     let _shared_args = shared_args.verify(S3Locator::features())?;
-    let _source_args = source_args.verify(S3Locator::features())?;
+    let source_args = source_args.verify(S3Locator::features())?;
+    let s3_args = source_args
+        .driver_args()
+        .deserialize::<S3SourceArguments>()
+        .context("could not parse --from-arg")?;
+    let decrypt_key = s3_args
+        .decrypt_key
+        .as_ref()
+        .map(|path| DecryptionKey::from_path(path))
+        .transpose()?;
+
+    let request_payer = s3_args.request_payer;
+    let conn = s3_args.connection(default_role.as_ref())?;
 
     debug!(ctx.log(), "getting CSV files from {}", url);
 
     // List the files at our URL.
-    let file_urls = s3::ls(&ctx, &url).await?;
+    let files =
+        list_files(&ctx, &url, request_payer, &conn, s3_args.key_filter()).await?;
 
     // Convert into `CsvStream` values lazily in case there are a lot of CSV
     // files we need to read.
-    //
-    // XXX - This will fail (either silently or noisily, I'm not sure) if there
-    // are 1000+ files in the S3 directory, and we can't fix this without
-    // switching from `aws s3` to native S3 API calls from Rust.
-    let csv_streams = file_urls.and_then(move |file_url| {
+    let csv_streams = files.and_then(move |item| {
         let ctx = ctx.clone();
         let url = url.clone();
+        let decrypt_key = decrypt_key.clone();
+        let conn = conn.clone();
         async move {
             // Stream the file from the cloud.
+            let file_url = item.url;
             let name = csv_stream_name(url.as_str(), file_url.as_str())?.to_owned();
             let ctx = ctx.child(
                 o!("stream" => name.clone(), "url" => file_url.as_str().to_owned()),
             );
-            let data = s3::download_file(&ctx, &file_url).await?;
+            let metadata = StreamMetadata {
+                source: Some(file_url.as_str().to_owned()),
+                modified: item.modified,
+            };
+            let mut data =
+                s3::download_file(&ctx, &file_url, request_payer, &conn).await?;
+            let mut file_name = file_url.path().to_owned();
+            if let Some(decrypt_key) = &decrypt_key {
+                data = decrypt_key.decrypt_stream(&ctx, data).await?;
+                // Remove `.gpg` or `.age`, so we can see any compression
+                // extension underneath.
+                if let Some(idx) = file_name.rfind('.') {
+                    file_name.truncate(idx);
+                }
+            }
+            let data = decompress_stream_for_file_name(&ctx, &file_name, data)?;
 
             // Assemble everything into a CSV stream.
-            Ok(CsvStream { name, data })
+            Ok(CsvStream {
+                name,
+                metadata,
+                data,
+            })
         }
         .boxed()
     });
 
     Ok(Some(csv_streams.boxed()))
 }
+
+/// The most files we'll list when computing a size hint. Listing a huge
+/// prefix may take minutes, and `cp` waits for our hint before it starts
+/// copying, so we give up on a hint rather than listing everything twice.
+const MAX_SIZE_HINT_FILES: usize = 10_000;
+
+/// Implementation of `size_hint`, using a directory listing.
+pub(crate) async fn size_hint_helper(
+    ctx: Context,
+    url: Url,
+    default_role: Option<AwsRole>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<SizeHint> {
+    let source_args = source_args.verify(S3Locator::features())?;
+    let s3_args = source_args
+        .driver_args()
+        .deserialize::<S3SourceArguments>()
+        .context("could not parse --from-arg")?;
+    let conn = s3_args.connection(default_role.as_ref())?;
+    let sizes = list_files(
+        &ctx,
+        &url,
+        s3_args.request_payer,
+        &conn,
+        s3_args.key_filter(),
+    )
+    .await?
+    .map_ok(|item| item.size)
+    .take(MAX_SIZE_HINT_FILES + 1)
+    .try_collect::<Vec<_>>()
+    .await?;
+    if sizes.len() > MAX_SIZE_HINT_FILES {
+        debug!(
+            ctx.log(),
+            "{} contains more than {} files, so not computing size hint",
+            url,
+            MAX_SIZE_HINT_FILES,
+        );
+        return Ok(SizeHint::default());
+    }
+    Ok(SizeHint::from_sizes(sizes))
+}
+
+#[test]
+fn parse_request_payer_args() {
+    let args = DriverArguments::from_cli_args(&["request_payer=requester"])
+        .unwrap()
+        .deserialize::<S3SourceArguments>()
+        .unwrap();
+    assert_eq!(args.request_payer, Some(RequestPayer::Requester));
+    let args = DriverArguments::from_cli_args(&["request_payer=owner"]).unwrap();
+    assert!(args.deserialize::<S3SourceArguments>().is_err());
+}
+
+#[test]
+fn parse_role_args() {
+    let default_role = AwsRole::from_driver_args(Some("arn:default"), None).unwrap();
+    let args = DriverArguments::from_cli_args(&["role_arn=arn:etl", "external_id=x"])
+        .unwrap()
+        .deserialize::<S3SourceArguments>()
+        .unwrap();
+    assert_eq!(
+        args.aws_role(default_role.as_ref()).unwrap(),
+        AwsRole::from_driver_args(Some("arn:etl"), Some("x")).unwrap(),
+    );
+    let args = S3SourceArguments::default();
+    assert_eq!(args.aws_role(default_role.as_ref()).unwrap(), default_role);
+    let args = DriverArguments::from_cli_args(&["external_id=x"])
+        .unwrap()
+        .deserialize::<S3SourceArguments>()
+        .unwrap();
+    assert!(args.aws_role(None).is_err());
+}
+
+#[test]
+fn parse_include_and_exclude_args() {
+    let args =
+        DriverArguments::from_cli_args(&["include=*.csv.gz", "exclude=_SUCCESS"])
+            .unwrap()
+            .deserialize::<S3SourceArguments>()
+            .unwrap();
+    let filter = args.key_filter();
+    assert!(filter.should_read("exports/", "exports/a.csv.gz"));
+    assert!(!filter.should_read("exports/", "exports/_SUCCESS"));
+    let args = DriverArguments::from_cli_args(&["include=["]).unwrap();
+    assert!(args.deserialize::<S3SourceArguments>().is_err());
+}
+
+#[test]
+fn parse_region_and_accelerate_args() {
+    let args =
+        DriverArguments::from_cli_args(&["region=eu-west-1", "accelerate=true"])
+            .unwrap()
+            .deserialize::<S3SourceArguments>()
+            .unwrap();
+    let conn = args.connection(None).unwrap();
+    assert_eq!(conn.region(), Some("eu-west-1"));
+    assert!(conn.accelerate());
+    let conn = S3SourceArguments::default().connection(None).unwrap();
+    assert_eq!(conn, S3Connection::default());
+    let args = DriverArguments::from_cli_args(&["accelerate=yes"]).unwrap();
+    assert!(args.deserialize::<S3SourceArguments>().is_err());
+}
@@ -2,29 +2,36 @@
 
 use std::{fmt, str::FromStr};
 
+use crate::clouds::aws::{s3::ServerSideEncryption, AwsRole};
 use crate::common::*;
+#[cfg(feature = "redshift")]
 use crate::drivers::redshift::RedshiftLocator;
 
 mod local_data;
 mod prepare_as_destination;
+#[cfg(any(feature = "bigml", feature = "postgres"))]
+mod temporary;
 mod write_local_data;
+#[cfg(feature = "redshift")]
 mod write_remote_data;
 
-use local_data::local_data_helper;
+use local_data::{local_data_helper, size_hint_helper};
 pub(crate) use prepare_as_destination::prepare_as_destination_helper;
+#[cfg(any(feature = "bigml", feature = "postgres"))]
+pub(crate) use temporary::find_s3_temp_dir;
 use write_local_data::write_local_data_helper;
+#[cfg(feature = "redshift")]
 use write_remote_data::write_remote_data_helper;
 
 #[derive(Clone, Debug)]
 pub(crate) struct S3Locator {
     url: Url,
-}
-
-impl S3Locator {
-    /// Access the `s3://` URL in this locator.
-    pub(crate) fn as_url(&self) -> &Url {
-        &self.url
-    }
+    /// How should we encrypt the objects we write, unless overridden by
+    /// `--to-arg`? This is only set for temporary storage.
+    sse: ServerSideEncryption,
+    /// An IAM role to assume when accessing this location, unless overridden
+    /// by `--from-arg` or `--to-arg`. This is only set for temporary storage.
+    role: Option<AwsRole>,
 }
 
 impl fmt::Display for S3Locator {
@@ -46,7 +53,11 @@ impl FromStr for S3Locator {
             } else if !url.path().ends_with('/') {
                 Err(format_err!("{} must end with a '/'", url))
             } else {
-                Ok(S3Locator { url })
+                Ok(S3Locator {
+                    url,
+                    sse: ServerSideEncryption::default(),
+                    role: None,
+                })
             }
         } else {
             Err(format_err!("expected {} to begin with s3://", s))
@@ -65,7 +76,22 @@ impl Locator for S3Locator {
         shared_args: SharedArguments<Unverified>,
         source_args: SourceArguments<Unverified>,
     ) -> BoxFuture<Option<BoxStream<CsvStream>>> {
-        local_data_helper(ctx, self.url.clone(), shared_args, source_args).boxed()
+        local_data_helper(
+            ctx,
+            self.url.clone(),
+            self.role.clone(),
+            shared_args,
+            source_args,
+        )
+        .boxed()
+    }
+
+    fn size_hint(
+        &self,
+        ctx: Context,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<SizeHint> {
+        size_hint_helper(ctx, self.url.clone(), self.role.clone(), source_args).boxed()
     }
 
     fn write_local_data(
@@ -75,10 +101,11 @@ impl Locator for S3Locator {
         shared_args: SharedArguments<Unverified>,
         dest_args: DestinationArguments<Unverified>,
     ) -> BoxFuture<BoxStream<BoxFuture<BoxLocator>>> {
-        write_local_data_helper(ctx, self.url.clone(), data, shared_args, dest_args)
+        write_local_data_helper(ctx, self.to_owned(), data, shared_args, dest_args)
             .boxed()
     }
 
+    #[cfg(feature = "redshift")]
     fn supports_write_remote_data(&self, source: &dyn Locator) -> bool {
         // We can only do `write_remote_data` if `source` is a
         // `RedshiftLocator`. Otherwise, we need to do `write_local_data` like
@@ -86,6 +113,7 @@ impl Locator for S3Locator {
         source.as_any().is::<RedshiftLocator>()
     }
 
+    #[cfg(feature = "redshift")]
     fn write_remote_data(
         &self,
         ctx: Context,
@@ -115,27 +143,12 @@ impl LocatorStatic for S3Locator {
         Features {
             locator: LocatorFeatures::LocalData | LocatorFeatures::WriteLocalData,
             write_schema_if_exists: EnumSet::empty(),
-            source_args: EnumSet::empty(),
-            dest_args: EnumSet::empty(),
-            dest_if_exists: IfExistsFeatures::Overwrite.into(),
+            source_args: SourceArgumentsFeatures::DriverArgs.into(),
+            dest_args: DestinationArgumentsFeatures::DriverArgs.into(),
+            dest_if_exists: IfExistsFeatures::Error
+                | IfExistsFeatures::Overwrite
+                | IfExistsFeatures::Append,
             _placeholder: (),
         }
     }
 }
-
-/// Given a `TemporaryStorage`, extract a unique `s3://` temporary directory,
-/// including a random component.
-pub(crate) fn find_s3_temp_dir(
-    temporary_storage: &TemporaryStorage,
-) -> Result<S3Locator> {
-    let mut temp = temporary_storage
-        .find_scheme(S3Locator::scheme())
-        .ok_or_else(|| format_err!("need `--temporary=s3://...` argument"))?
-        .to_owned();
-    if !temp.ends_with('/') {
-        temp.push_str("/");
-    }
-    temp.push_str(&TemporaryStorage::random_tag());
-    temp.push_str("/");
-    S3Locator::from_str(&temp)
-}
@@ -1,22 +1,37 @@
 //! Preparing bucket directories as output destinations.
 
-use crate::clouds::aws::s3;
+use crate::clouds::aws::s3::{self, S3Connection};
 use crate::common::*;
 
-/// Prepare the target of this locator for use as a destination.
+/// Prepare the target of this locator for use as a destination, using `conn`
+/// if we need to list or delete anything.
 pub(crate) async fn prepare_as_destination_helper(
     ctx: Context,
     s3_url: Url,
     if_exists: IfExists,
+    conn: S3Connection,
 ) -> Result<()> {
-    // Delete the existing output, if it exists.
-    if if_exists == IfExists::Overwrite {
+    match if_exists {
         // Delete all the files under `self.url`.
-        s3::rmdir(&ctx, &s3_url).await
-    } else {
-        Err(format_err!(
-            "must specify `overwrite` for {} destination",
+        IfExists::Overwrite => s3::rmdir(&ctx, &s3_url, &conn).await,
+        // Leave existing files alone. Our caller is responsible for choosing
+        // new file names.
+        IfExists::Append => Ok(()),
+        // Fail if there are any files under `self.url`.
+        IfExists::Error => {
+            if s3::is_empty(&ctx, &s3_url, &conn).await? {
+                Ok(())
+            } else {
+                Err(format_err!(
+                    "{} already contains files (use `--if-exists=overwrite` or \
+                     `--if-exists=append`)",
+                    s3_url,
+                ))
+            }
+        }
+        _ => Err(format_err!(
+            "must specify `error`, `overwrite` or `append` for {} destination",
             s3_url,
-        ))
+        )),
     }
 }
@@ -0,0 +1,64 @@
+//! Temporary `s3://` directories, which other drivers use to stage data.
+
+use std::str::FromStr;
+
+use super::S3Locator;
+use crate::clouds::aws::s3::{self, S3Connection, ServerSideEncryption};
+use crate::common::*;
+
+impl S3Locator {
+    /// Delete this temporary directory once our copy has finished, whether or
+    /// not it succeeds.
+    pub(crate) fn delete_when_done(&self, temporary_storage: &TemporaryStorage) {
+        let url = self.url.clone();
+        let role = self.role.clone();
+        temporary_storage.delete_when_done(url.to_string(), move |ctx| {
+            async move {
+                let conn = S3Connection::new(role, None, false)?;
+                s3::rmdir(&ctx, &url, &conn).await
+            }
+            .boxed()
+        });
+    }
+}
+
+/// Given a `TemporaryStorage`, extract a unique `s3://` temporary directory,
+/// including a random component. The temporary directory may specify
+/// server-side encryption, as in `s3://bucket/tmp/?sse=aws:kms`.
+pub(crate) fn find_s3_temp_dir(
+    temporary_storage: &TemporaryStorage,
+) -> Result<S3Locator> {
+    let temp = temporary_storage
+        .find_scheme(S3Locator::scheme())
+        .ok_or_else(|| format_err!("need `--temporary=s3://...` argument"))?;
+    let mut url = temp
+        .parse::<Url>()
+        .with_context(|_| format!("cannot parse {}", temp))?;
+    let sse = ServerSideEncryption::from_url_query(&url)?;
+    url.set_query(None);
+    let mut temp = url.to_string();
+    if !temp.ends_with('/') {
+        temp.push_str("/");
+    }
+    temp.push_str(&TemporaryStorage::temp_dir_name());
+    temp.push_str("/");
+    let mut locator = S3Locator::from_str(&temp)?;
+    locator.sse = sse;
+    Ok(locator)
+}
+
+#[test]
+fn find_s3_temp_dir_with_encryption() {
+    use crate::clouds::aws::s3::SseAlgorithm;
+
+    let storage = TemporaryStorage::new(vec![
+        "s3://example/tmp?sse=aws:kms&sse_kms_key_id=k".to_owned(),
+    ]);
+    let temp = find_s3_temp_dir(&storage).unwrap();
+    assert!(temp
+        .to_string()
+        .starts_with("s3://example/tmp/dbcrossbar-tmp-"));
+    assert!(temp.to_string().ends_with('/'));
+    assert_eq!(temp.sse.algorithm(), Some(SseAlgorithm::AwsKms));
+    assert_eq!(temp.sse.kms_key_id(), Some("k"));
+}
@@ -1,13 +1,155 @@
 //! Writing data to AWS S3.
 
+use serde::Deserialize;
+use std::sync::Arc;
+
 use super::{prepare_as_destination_helper, S3Locator};
-use crate::clouds::aws::s3;
+use crate::clouds::aws::{
+    s3::{
+        self, ObjectTags, S3Connection, ServerSideEncryption, SseAlgorithm,
+        StorageClass, UploadOptions,
+    },
+    AwsRole,
+};
 use crate::common::*;
+use crate::compression::Compression;
+use crate::csv_stream::csv_stream_file_name;
+use crate::driver_args::deserialize_opt_from_str;
+use crate::manifest::{
+    manifest_file_name, write_manifest_when_done, FileTracker, Manifest,
+};
+use crate::object_metadata::{CustomMetadata, ObjectMetadata};
+use crate::partition::partition_csv_streams;
+use crate::rechunk::{split_csv_streams, ChunkLimits};
+
+/// Parsed version of `--to-arg` values.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct S3DestinationArguments {
+    /// How should we compress the files we write?
+    compression: Option<Compression>,
+
+    /// Split each output stream into files of approximately this size, like
+    /// `256MB`.
+    max_file_size: Option<String>,
+
+    /// Split each output stream into files with at most this many rows.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    max_rows_per_file: Option<usize>,
+
+    /// Split our output into Hive-style directories like `event_date=...`
+    /// using the values of this column.
+    partition_by: Option<String>,
+
+    /// Server-side encryption to use, like `aws:kms`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    sse: Option<SseAlgorithm>,
+
+    /// The KMS key to use with `sse=aws:kms`.
+    sse_kms_key_id: Option<String>,
+
+    /// The storage class to use for the objects we write, like
+    /// `INTELLIGENT_TIERING`.
+    storage_class: Option<StorageClass>,
+
+    /// Tags to attach to every object we write, like
+    /// `team=data,cost-center=1234`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    tags: Option<ObjectTags>,
+
+    /// The `Content-Type` of the objects we write, like `text/csv`.
+    content_type: Option<String>,
+
+    /// The `Content-Encoding` of the objects we write, like `gzip`.
+    content_encoding: Option<String>,
+
+    /// Custom metadata to attach to every object we write, like
+    /// `source=etl,owner=data`.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    metadata: Option<CustomMetadata>,
+
+    /// An IAM role to assume using STS, like
+    /// `arn:aws:iam::123456789012:role/etl`.
+    role_arn: Option<String>,
+
+    /// The external ID required to assume `role_arn`, if any.
+    external_id: Option<String>,
+
+    /// The AWS region containing our bucket, instead of looking it up.
+    region: Option<String>,
+
+    /// Should we use S3 Transfer Acceleration?
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    accelerate: Option<bool>,
+
+    /// Should we write a `manifest.json` file listing the files we wrote?
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    manifest: Option<bool>,
+}
+
+impl S3DestinationArguments {
+    /// Do we only contain arguments which affect how objects are encrypted, or
+    /// which role we assume?
+    #[cfg(feature = "redshift")]
+    pub(super) fn only_encryption_and_role(&self) -> bool {
+        self.compression.is_none()
+            && self.max_file_size.is_none()
+            && self.max_rows_per_file.is_none()
+            && self.partition_by.is_none()
+            && self.storage_class.is_none()
+            && self.tags.is_none()
+            && self.content_type.is_none()
+            && self.content_encoding.is_none()
+            && self.metadata.is_none()
+            && self.region.is_none()
+            && self.accelerate.is_none()
+            && self.manifest.is_none()
+    }
+
+    /// How should we encrypt the objects we write? If no `sse` argument was
+    /// specified, we use `default_sse`.
+    pub(super) fn server_side_encryption(
+        &self,
+        default_sse: &ServerSideEncryption,
+    ) -> Result<ServerSideEncryption> {
+        if self.sse.is_none() && self.sse_kms_key_id.is_none() {
+            Ok(default_sse.to_owned())
+        } else {
+            ServerSideEncryption::new(self.sse, self.sse_kms_key_id.clone())
+        }
+    }
+
+    /// Which IAM role should we assume? If no `role_arn` argument was
+    /// specified, we use `default_role`.
+    pub(super) fn aws_role(
+        &self,
+        default_role: Option<&AwsRole>,
+    ) -> Result<Option<AwsRole>> {
+        let role = AwsRole::from_driver_args(
+            self.role_arn.as_deref(),
+            self.external_id.as_deref(),
+        )?;
+        Ok(role.or_else(|| default_role.cloned()))
+    }
+
+    /// How should we connect to S3? If no `role_arn` argument was specified,
+    /// we assume `default_role`.
+    pub(super) fn connection(
+        &self,
+        default_role: Option<&AwsRole>,
+    ) -> Result<S3Connection> {
+        S3Connection::new(
+            self.aws_role(default_role)?,
+            self.region.clone(),
+            self.accelerate.unwrap_or(false),
+        )
+    }
+}
 
 /// Implementation of `write_local_data`, but as a real `async` function.
 pub(crate) async fn write_local_data_helper(
     ctx: Context,
-    url: Url,
+    dest: S3Locator,
     data: BoxStream<CsvStream>,
     shared_args: SharedArguments<Unverified>,
     dest_args: DestinationArguments<Unverified>,
@@ -17,23 +159,241 @@ pub(crate) async fn write_local_data_helper(
 
     // Look up our arguments.
     let if_exists = dest_args.if_exists().to_owned();
+    let args = dest_args
+        .driver_args()
+        .deserialize::<S3DestinationArguments>()
+        .context("could not parse --to-arg")?;
+    let compression = args.compression;
+    let sse = args.server_side_encryption(&dest.sse)?;
+    let role = args.aws_role(dest.role.as_ref())?;
+    let conn = args.connection(dest.role.as_ref())?;
+    let metadata =
+        ObjectMetadata::new(args.content_type, args.content_encoding, args.metadata)?;
+    // Our manifest is JSON, so it doesn't get the metadata for our CSV files.
+    let manifest_opt = UploadOptions {
+        sse: sse.clone(),
+        storage_class: args.storage_class,
+        tags: args.tags,
+        metadata: ObjectMetadata::default(),
+    };
+    let upload_opt = Arc::new(UploadOptions {
+        metadata,
+        ..manifest_opt.clone()
+    });
+    let write_manifest = args.manifest.unwrap_or(false);
+    let chunk_limits = ChunkLimits::from_driver_args(
+        args.max_file_size.as_deref(),
+        args.max_rows_per_file,
+    )?;
+
+    // Delete the existing output, if it exists and we're not appending.
+    let url = dest.url;
+    prepare_as_destination_helper(
+        ctx.clone(),
+        url.clone(),
+        if_exists.clone(),
+        conn.clone(),
+    )
+    .await?;
+
+    // Partition our streams or split them into smaller files if we were
+    // asked to.
+    let data = match args.partition_by {
+        Some(column) => {
+            partition_csv_streams(ctx.clone(), column, chunk_limits, data).await?
+        }
+        None => split_csv_streams(ctx.clone(), chunk_limits, data),
+    };
 
-    // Delete the existing output, if it exists.
-    prepare_as_destination_helper(ctx.clone(), url.clone(), if_exists).await?;
+    // Prepare to write a manifest once all our files are written, if we were
+    // asked to.
+    let manifest_writer = if write_manifest {
+        let ctx = ctx.clone();
+        let url = url.join(&manifest_file_name(&if_exists))?;
+        let sse = sse.clone();
+        let role = role.clone();
+        let conn = conn.clone();
+        Some(move |manifest: Manifest| -> BoxFuture<BoxLocator> {
+            async move {
+                let data = box_stream_once(Ok(manifest.to_json_bytes()?));
+                s3::upload_file(&ctx, data, &url, &manifest_opt, &conn).await?;
+                Ok(S3Locator { url, sse, role }.boxed())
+            }
+            .boxed()
+        })
+    } else {
+        None
+    };
 
     // Spawn our uploader threads.
     let written = data.map_ok(move |stream| {
         let url = url.clone();
         let ctx = ctx.clone();
+        let if_exists = if_exists.clone();
+        let sse = sse.clone();
+        let upload_opt = upload_opt.clone();
+        let role = role.clone();
+        let conn = conn.clone();
         async move {
-            let url = url.join(&format!("{}.csv", stream.name))?;
+            let mut file_name = csv_stream_file_name(&stream.name, &if_exists);
+            let tracker = FileTracker::new(write_manifest);
+            let mut data = tracker.count_rows(stream.data);
+            if let Some(compression) = compression {
+                file_name = compression.add_extension(&file_name);
+                data = compression.compress_stream(&ctx, data)?;
+            }
+            let data = tracker.checksum(data);
+            let url = url.join(&file_name)?;
             let ctx = ctx
                 .child(o!("stream" => stream.name.clone(), "url" => url.to_string()));
-            s3::upload_file(&ctx, stream.data, &url).await?;
-            Ok(S3Locator { url }.boxed())
+            s3::upload_file(&ctx, data, &url, &upload_opt, &conn).await?;
+            let entry = tracker.finish(&url);
+            Ok((S3Locator { url, sse, role }.boxed(), entry))
         }
         .boxed()
     });
 
-    Ok(written.boxed())
+    Ok(write_manifest_when_done(written.boxed(), manifest_writer))
+}
+
+#[test]
+fn parse_sse_args() {
+    let args = DriverArguments::from_cli_args(&["sse=aws:kms", "sse_kms_key_id=k"])
+        .unwrap()
+        .deserialize::<S3DestinationArguments>()
+        .unwrap();
+    let sse = args
+        .server_side_encryption(&ServerSideEncryption::default())
+        .unwrap();
+    assert_eq!(sse.algorithm(), Some(SseAlgorithm::AwsKms));
+    assert_eq!(sse.kms_key_id(), Some("k"));
+
+    let default_sse =
+        ServerSideEncryption::new(Some(SseAlgorithm::Aes256), None).unwrap();
+    let args = S3DestinationArguments::default();
+    assert_eq!(
+        args.server_side_encryption(&default_sse).unwrap(),
+        default_sse
+    );
+
+    let args = DriverArguments::from_cli_args(&["sse_kms_key_id=k"])
+        .unwrap()
+        .deserialize::<S3DestinationArguments>()
+        .unwrap();
+    assert!(args.server_side_encryption(&default_sse).is_err());
+}
+
+#[test]
+fn parse_storage_class_args() {
+    let args = DriverArguments::from_cli_args(&["storage_class=INTELLIGENT_TIERING"])
+        .unwrap()
+        .deserialize::<S3DestinationArguments>()
+        .unwrap();
+    assert_eq!(args.storage_class, Some(StorageClass::IntelligentTiering));
+    let args = DriverArguments::from_cli_args(&["storage_class=standard_ia"]).unwrap();
+    assert!(args.deserialize::<S3DestinationArguments>().is_err());
+}
+
+#[test]
+fn parse_role_args() {
+    let args = DriverArguments::from_cli_args(&["role_arn=arn:etl", "sse=AES256"])
+        .unwrap()
+        .deserialize::<S3DestinationArguments>()
+        .unwrap();
+    let default_role = AwsRole::from_driver_args(Some("arn:default"), None).unwrap();
+    assert_eq!(
+        args.aws_role(default_role.as_ref()).unwrap(),
+        AwsRole::from_driver_args(Some("arn:etl"), None).unwrap(),
+    );
+    let args = S3DestinationArguments::default();
+    assert_eq!(args.aws_role(default_role.as_ref()).unwrap(), default_role);
+}
+
+#[test]
+fn parse_manifest_args() {
+    let args = DriverArguments::from_cli_args(&["manifest=true"])
+        .unwrap()
+        .deserialize::<S3DestinationArguments>()
+        .unwrap();
+    assert_eq!(args.manifest, Some(true));
+}
+
+#[test]
+#[cfg(feature = "redshift")]
+fn only_encryption_and_role_args() {
+    for cli_args in &[
+        &["sse=aws:kms", "sse_kms_key_id=k"][..],
+        &["role_arn=arn:etl", "sse=AES256"][..],
+    ] {
+        let args = DriverArguments::from_cli_args(*cli_args)
+            .unwrap()
+            .deserialize::<S3DestinationArguments>()
+            .unwrap();
+        assert!(args.only_encryption_and_role());
+    }
+    for cli_args in &[
+        &["storage_class=INTELLIGENT_TIERING"][..],
+        &["manifest=true"][..],
+        &["tags=team=data"][..],
+        &["region=eu-west-1", "accelerate=true"][..],
+        &["content_type=text/csv"][..],
+        &["metadata=source=etl"][..],
+    ] {
+        let args = DriverArguments::from_cli_args(*cli_args)
+            .unwrap()
+            .deserialize::<S3DestinationArguments>()
+            .unwrap();
+        assert!(!args.only_encryption_and_role());
+    }
+}
+
+#[test]
+fn parse_tags_args() {
+    let args = DriverArguments::from_cli_args(&["tags=team=data,cost-center=1234"])
+        .unwrap()
+        .deserialize::<S3DestinationArguments>()
+        .unwrap();
+    assert_eq!(
+        args.tags,
+        Some("team=data,cost-center=1234".parse::<ObjectTags>().unwrap()),
+    );
+    let args = DriverArguments::from_cli_args(&["tags=team"]).unwrap();
+    assert!(args.deserialize::<S3DestinationArguments>().is_err());
+}
+
+#[test]
+fn parse_region_and_accelerate_args() {
+    let args =
+        DriverArguments::from_cli_args(&["region=eu-west-1", "accelerate=true"])
+            .unwrap()
+            .deserialize::<S3DestinationArguments>()
+            .unwrap();
+    let conn = args.connection(None).unwrap();
+    assert_eq!(conn.region(), Some("eu-west-1"));
+    assert!(conn.accelerate());
+    let args = DriverArguments::from_cli_args(&["region=EU WEST"])
+        .unwrap()
+        .deserialize::<S3DestinationArguments>()
+        .unwrap();
+    assert!(args.connection(None).is_err());
+}
+
+#[test]
+fn parse_metadata_args() {
+    let args = DriverArguments::from_cli_args(&[
+        "content_type=text/csv",
+        "content_encoding=gzip",
+        "metadata=source=etl,owner=data",
+    ])
+    .unwrap()
+    .deserialize::<S3DestinationArguments>()
+    .unwrap();
+    assert_eq!(args.content_type.as_deref(), Some("text/csv"));
+    assert_eq!(args.content_encoding.as_deref(), Some("gzip"));
+    assert_eq!(
+        args.metadata,
+        Some("source=etl,owner=data".parse::<CustomMetadata>().unwrap()),
+    );
+    let args = DriverArguments::from_cli_args(&["metadata=source"]).unwrap();
+    assert!(args.deserialize::<S3DestinationArguments>().is_err());
 }
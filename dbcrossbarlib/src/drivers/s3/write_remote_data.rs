@@ -1,12 +1,31 @@
 //! Implementation of `GsLocator::write_remote_data`.
 
-use super::{prepare_as_destination_helper, S3Locator};
+use super::{
+    prepare_as_destination_helper, write_local_data::S3DestinationArguments, S3Locator,
+};
+use crate::clouds::aws::{
+    s3::{ServerSideEncryption, SseAlgorithm},
+    AwsRole,
+};
 use crate::common::*;
 use crate::drivers::{
     postgres_shared::{connect, pg_quote, CheckCatalog, PgCreateTable},
     redshift::{credentials_sql, RedshiftLocator},
 };
 
+impl S3Locator {
+    /// Access the `s3://` URL in this locator.
+    pub(crate) fn as_url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Assume `role` when accessing this location. This is used by drivers
+    /// which have been asked to assume a role for their temporary storage.
+    pub(crate) fn set_role(&mut self, role: Option<AwsRole>) {
+        self.role = role;
+    }
+}
+
 /// Copy `source` to `dest` using `schema`.
 ///
 /// The function `BigQueryLocator::write_remote_data` isn't (yet) allowed to be
@@ -34,10 +53,38 @@ pub(crate) async fn write_remote_data_helper(
     let schema = shared_args.schema();
     let from_args = source_args.driver_args();
     let if_exists = dest_args.if_exists().to_owned();
+    if if_exists == IfExists::Append {
+        // `UNLOAD` chooses its own file names, which might replace existing
+        // files.
+        return Err(format_err!(
+            "cannot use --if-exists=append when unloading from {}",
+            source,
+        ));
+    }
+    let s3_args = dest_args
+        .driver_args()
+        .deserialize::<S3DestinationArguments>()
+        .context("could not parse --to-arg")?;
+    if !s3_args.only_encryption_and_role() {
+        // We don't compress or split data which never passes through
+        // `dbcrossbar`.
+        return Err(format_err!(
+            "cannot use --to-arg other than sse, sse_kms_key_id, role_arn and \
+             external_id when unloading from {}",
+            source,
+        ));
+    }
+    let sse = s3_args.server_side_encryption(&dest.sse)?;
+    let conn = s3_args.connection(dest.role.as_ref())?;
 
     // Delete the existing output, if it exists.
-    prepare_as_destination_helper(ctx.clone(), dest.as_url().to_owned(), if_exists)
-        .await?;
+    prepare_as_destination_helper(
+        ctx.clone(),
+        dest.as_url().to_owned(),
+        if_exists,
+        conn,
+    )
+    .await?;
 
     // Convert our schema to a native PostgreSQL schema.
     let table_name = source.table_name();
@@ -61,10 +108,11 @@ pub(crate) async fn write_remote_data_helper(
     // Export as CSV.
     let client = connect(&ctx, source.url()).await?;
     let unload_sql = format!(
-        "UNLOAD ({source}) TO {dest}\n{credentials}HEADER FORMAT CSV",
+        "UNLOAD ({source}) TO {dest}\n{credentials}{encryption}HEADER FORMAT CSV",
         source = pg_quote(&select_sql),
         dest = pg_quote(dest.as_url().as_str()),
-        credentials = credentials_sql(from_args)?,
+        credentials = credentials_sql(&ctx, from_args).await?,
+        encryption = unload_encryption_sql(&sse),
     );
     let unload_stmt = client.prepare(&unload_sql).await?;
     client.execute(&unload_stmt, &[]).await.with_context(|_| {
@@ -72,3 +120,35 @@ pub(crate) async fn write_remote_data_helper(
     })?;
     Ok(vec![dest.boxed()])
 }
+
+/// Generate the encryption options for `UNLOAD`. Redshift always uses S3-managed
+/// keys by default, so we only need to say something if we want KMS.
+fn unload_encryption_sql(sse: &ServerSideEncryption) -> String {
+    match (sse.algorithm(), sse.kms_key_id()) {
+        (Some(SseAlgorithm::AwsKms), Some(kms_key_id)) => {
+            format!("KMS_KEY_ID {} ENCRYPTED\n", pg_quote(kms_key_id))
+        }
+        (Some(SseAlgorithm::AwsKms), None) => "ENCRYPTED AUTO\n".to_owned(),
+        (Some(SseAlgorithm::Aes256), _) | (None, _) => "".to_owned(),
+    }
+}
+
+#[test]
+fn unload_encryption() {
+    let sse = |algorithm, kms_key_id: Option<&str>| {
+        ServerSideEncryption::new(algorithm, kms_key_id.map(|k| k.to_owned())).unwrap()
+    };
+    assert_eq!(unload_encryption_sql(&sse(None, None)), "");
+    assert_eq!(
+        unload_encryption_sql(&sse(Some(SseAlgorithm::Aes256), None)),
+        "",
+    );
+    assert_eq!(
+        unload_encryption_sql(&sse(Some(SseAlgorithm::AwsKms), None)),
+        "ENCRYPTED AUTO\n",
+    );
+    assert_eq!(
+        unload_encryption_sql(&sse(Some(SseAlgorithm::AwsKms), Some("k"))),
+        "KMS_KEY_ID 'k' ENCRYPTED\n",
+    );
+}
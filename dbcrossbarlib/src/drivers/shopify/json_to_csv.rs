@@ -28,7 +28,7 @@ pub(crate) fn write_rows<W: Write>(
 }
 
 /// Write a JSON row to a CSV document.
-fn write_row<W: Write>(
+pub(crate) fn write_row<W: Write>(
     wtr: &mut csv::Writer<W>,
     schema: &Table,
     row: Value,
@@ -116,6 +116,7 @@ pub(crate) async fn local_data_helper(
 
     Ok(Some(box_stream_once(Ok(CsvStream {
         name: "data".to_owned(),
+        metadata: StreamMetadata::default(),
         data: receiver.boxed(),
     }))))
 }
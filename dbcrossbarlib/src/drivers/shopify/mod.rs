@@ -6,7 +6,7 @@ use std::{fmt, str::FromStr};
 
 use crate::common::*;
 
-mod json_to_csv;
+pub(crate) mod json_to_csv;
 mod local_data;
 
 use local_data::local_data_helper;
@@ -0,0 +1,222 @@
+//! Converting between Singer's JSON Schemas and our portable schemas.
+
+use serde_json::{json, Map, Value};
+
+use crate::common::*;
+use crate::schema::{Column, DataType, StructField};
+
+/// Convert the JSON Schema for a Singer stream into a table.
+pub(crate) fn table_from_json_schema(name: &str, schema: &Value) -> Result<Table> {
+    let properties = schema
+        .get("properties")
+        .and_then(|props| props.as_object())
+        .ok_or_else(|| format_err!("expected properties in schema for {}", name))?;
+    let required = schema
+        .get("required")
+        .and_then(|req| req.as_array())
+        .map(|req| req.iter().filter_map(|r| r.as_str()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let mut columns = vec![];
+    for (col_name, col_schema) in properties {
+        let (is_nullable, data_type) = data_type_from_json_schema(col_schema)
+            .with_context(|_| format!("error in schema for {}.{}", name, col_name))?;
+        columns.push(Column {
+            name: col_name.to_owned(),
+            is_nullable: is_nullable || !required.contains(&&col_name[..]),
+            data_type,
+            comment: col_schema
+                .get("description")
+                .and_then(|d| d.as_str())
+                .map(|d| d.to_owned()),
+        });
+    }
+    Ok(Table {
+        name: name.to_owned(),
+        columns,
+    })
+}
+
+/// Convert a JSON Schema into a data type, returning whether `null` is allowed.
+fn data_type_from_json_schema(schema: &Value) -> Result<(bool, DataType)> {
+    // Handle `anyOf`, which is often used to make complex types nullable.
+    if let Some(variants) = schema.get("anyOf").and_then(|v| v.as_array()) {
+        let mut is_nullable = false;
+        let mut non_null = vec![];
+        for variant in variants {
+            if variant.get("type") == Some(&json!("null")) {
+                is_nullable = true;
+            } else {
+                non_null.push(variant);
+            }
+        }
+        return if non_null.len() == 1 {
+            let (variant_is_nullable, data_type) =
+                data_type_from_json_schema(non_null[0])?;
+            Ok((is_nullable || variant_is_nullable, data_type))
+        } else {
+            Ok((true, DataType::Json))
+        };
+    }
+
+    // Get our list of types, which may be a single string.
+    let types = match schema.get("type") {
+        None => return Ok((true, DataType::Json)),
+        Some(Value::String(ty)) => vec![&ty[..]],
+        Some(Value::Array(tys)) => tys.iter().filter_map(|t| t.as_str()).collect(),
+        Some(other) => {
+            return Err(format_err!("unexpected JSON Schema type {}", other))
+        }
+    };
+    let is_nullable = types.contains(&"null");
+    let non_null = types
+        .into_iter()
+        .filter(|&ty| ty != "null")
+        .collect::<Vec<_>>();
+    let data_type = match &non_null[..] {
+        ["boolean"] => DataType::Bool,
+        ["integer"] => DataType::Int64,
+        ["number"] => DataType::Float64,
+        ["string"] => match schema.get("format").and_then(|f| f.as_str()) {
+            Some("date") => DataType::Date,
+            Some("date-time") => DataType::TimestampWithTimeZone,
+            Some("singer.decimal") => DataType::Decimal,
+            Some("uuid") => DataType::Uuid,
+            _ => DataType::Text,
+        },
+        ["array"] => match schema.get("items") {
+            Some(items) => {
+                DataType::Array(Box::new(data_type_from_json_schema(items)?.1))
+            }
+            None => DataType::Array(Box::new(DataType::Json)),
+        },
+        // Objects and mixed types are passed through as JSON.
+        _ => DataType::Json,
+    };
+    Ok((is_nullable, data_type))
+}
+
+/// Build a JSON Schema describing rows of `table`.
+pub(crate) fn json_schema_for_table(table: &Table) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![];
+    for col in &table.columns {
+        let mut schema = json_schema_for_data_type(&col.data_type, col.is_nullable);
+        if let (Some(comment), Value::Object(obj)) = (&col.comment, &mut schema) {
+            obj.insert("description".to_owned(), json!(comment));
+        }
+        properties.insert(col.name.clone(), schema);
+        if !col.is_nullable {
+            required.push(col.name.clone());
+        }
+    }
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// Build a JSON Schema describing `data_type`.
+fn json_schema_for_data_type(data_type: &DataType, is_nullable: bool) -> Value {
+    let ty = |ty: &str| {
+        if is_nullable {
+            json!([ty, "null"])
+        } else {
+            json!(ty)
+        }
+    };
+    match data_type {
+        DataType::Array(elem) => json!({
+            "type": ty("array"),
+            "items": json_schema_for_data_type(elem, true),
+        }),
+        DataType::Bool => json!({ "type": ty("boolean") }),
+        DataType::Date => json!({ "type": ty("string"), "format": "date" }),
+        DataType::Decimal | DataType::FixedDecimal { .. } => {
+            json!({ "type": ty("string"), "format": "singer.decimal" })
+        }
+        DataType::Float32 | DataType::Float64 => json!({ "type": ty("number") }),
+        DataType::GeoJson(_) => json!({ "type": ty("object") }),
+        DataType::Int16 | DataType::Int32 | DataType::Int64 => {
+            json!({ "type": ty("integer") })
+        }
+        // An empty schema allows any value, including `null`.
+        DataType::Json => json!({}),
+        DataType::Struct(fields) => {
+            let mut properties = Map::new();
+            for StructField {
+                name,
+                is_nullable,
+                data_type,
+            } in fields
+            {
+                properties.insert(
+                    name.clone(),
+                    json_schema_for_data_type(data_type, *is_nullable),
+                );
+            }
+            json!({ "type": ty("object"), "properties": properties })
+        }
+        DataType::Text => json!({ "type": ty("string") }),
+        DataType::TimestampWithoutTimeZone | DataType::TimestampWithTimeZone => {
+            json!({ "type": ty("string"), "format": "date-time" })
+        }
+        DataType::Uuid => json!({ "type": ty("string"), "format": "uuid" }),
+    }
+}
+
+#[test]
+fn table_from_json_schema_handles_singer_types() {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "id": { "type": "integer" },
+            "name": { "type": ["null", "string"] },
+            "created_at": { "type": ["null", "string"], "format": "date-time" },
+            "tags": { "type": ["null", "array"], "items": { "type": "string" } },
+            "address": { "anyOf": [{ "type": "object" }, { "type": "null" }] },
+            "price": { "type": "string", "format": "singer.decimal" },
+        },
+        "required": ["id", "price"],
+    });
+    let table = table_from_json_schema("users", &schema).unwrap();
+    let types = table
+        .columns
+        .iter()
+        .map(|c| (&c.name[..], c.is_nullable, c.data_type.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            ("address", true, DataType::Json),
+            ("created_at", true, DataType::TimestampWithTimeZone),
+            ("id", false, DataType::Int64),
+            ("name", true, DataType::Text),
+            ("price", false, DataType::Decimal),
+            ("tags", true, DataType::Array(Box::new(DataType::Text))),
+        ]
+    );
+}
+
+#[test]
+fn json_schema_roundtrip() {
+    let table = Table {
+        name: "example".to_owned(),
+        columns: vec![
+            Column {
+                name: "id".to_owned(),
+                is_nullable: false,
+                data_type: DataType::Int64,
+                comment: None,
+            },
+            Column {
+                name: "seen_on".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Date,
+                comment: Some("Date first seen".to_owned()),
+            },
+        ],
+    };
+    let schema = json_schema_for_table(&table);
+    assert_eq!(table_from_json_schema("example", &schema).unwrap(), table);
+}
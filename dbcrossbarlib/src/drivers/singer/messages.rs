@@ -0,0 +1,110 @@
+//! Singer protocol messages.
+//!
+//! See the [Singer specification][spec]. Each message is a JSON object on a
+//! single line.
+//!
+//! [spec]: https://github.com/singer-io/getting-started/blob/master/docs/SPEC.md
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::common::*;
+
+/// A `SCHEMA` message, describing the records in a stream.
+#[derive(Debug, Deserialize)]
+pub(crate) struct SchemaMessage {
+    /// The stream described by this schema.
+    pub(crate) stream: String,
+    /// A JSON Schema describing each record.
+    pub(crate) schema: Value,
+}
+
+/// A `RECORD` message, containing a single row of data.
+#[derive(Debug, Deserialize)]
+pub(crate) struct RecordMessage {
+    /// The stream containing this record.
+    pub(crate) stream: String,
+    /// The record itself, normally a JSON object.
+    pub(crate) record: Value,
+}
+
+/// A message sent by a tap.
+#[derive(Debug)]
+pub(crate) enum Message {
+    Schema(SchemaMessage),
+    Record(RecordMessage),
+    /// A bookmark which can be used to resume extraction later.
+    State(Value),
+    /// A message type we don't use, such as `ACTIVATE_VERSION`.
+    Other(String),
+}
+
+impl Message {
+    /// Parse a single line of tap output.
+    pub(crate) fn parse(line: &str) -> Result<Message> {
+        let value = serde_json::from_str::<Value>(line)
+            .with_context(|_| format!("cannot parse Singer message {:?}", line))?;
+        let ty = value
+            .get("type")
+            .and_then(|ty| ty.as_str())
+            .ok_or_else(|| format_err!("no type in Singer message {:?}", line))?
+            .to_owned();
+        let parsed = match &ty[..] {
+            "SCHEMA" => Message::Schema(serde_json::from_value(value)?),
+            "RECORD" => Message::Record(serde_json::from_value(value)?),
+            "STATE" => {
+                Message::State(value.get("value").cloned().unwrap_or(Value::Null))
+            }
+            _ => Message::Other(ty),
+        };
+        Ok(parsed)
+    }
+}
+
+/// Build a `SCHEMA` message.
+pub(crate) fn schema_message(
+    stream: &str,
+    schema: Value,
+    key_properties: &[String],
+) -> Value {
+    json!({
+        "type": "SCHEMA",
+        "stream": stream,
+        "schema": schema,
+        "key_properties": key_properties,
+    })
+}
+
+/// Build a `RECORD` message.
+pub(crate) fn record_message(stream: &str, record: Value) -> Value {
+    json!({
+        "type": "RECORD",
+        "stream": stream,
+        "record": record,
+    })
+}
+
+#[test]
+fn parse_messages() {
+    let schema = Message::parse(
+        r#"{"type": "SCHEMA", "stream": "users", "schema": {}, "key_properties": ["id"]}"#,
+    )
+    .unwrap();
+    match schema {
+        Message::Schema(msg) => assert_eq!(msg.stream, "users"),
+        other => panic!("expected SCHEMA, found {:?}", other),
+    }
+    let record = Message::parse(
+        r#"{"type": "RECORD", "stream": "users", "record": {"id": 1}}"#,
+    )
+    .unwrap();
+    match record {
+        Message::Record(msg) => assert_eq!(msg.record, json!({"id": 1})),
+        other => panic!("expected RECORD, found {:?}", other),
+    }
+    match Message::parse(r#"{"type": "ACTIVATE_VERSION", "version": 2}"#).unwrap() {
+        Message::Other(ty) => assert_eq!(ty, "ACTIVATE_VERSION"),
+        other => panic!("expected other message, found {:?}", other),
+    }
+    assert!(Message::parse(r#"{"stream": "users"}"#).is_err());
+}
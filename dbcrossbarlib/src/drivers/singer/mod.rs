@@ -0,0 +1,93 @@
+//! Drivers for Singer taps and targets.
+//!
+//! [Singer](https://www.singer.io/) is a protocol used by many open source data
+//! connectors. A "tap" writes JSON messages describing a data source to
+//! standard output, and a "target" reads the same messages from standard input.
+//! We run taps and targets using `sh -c`, just like the `exec:` driver.
+
+use percent_encoding::percent_decode_str;
+use std::fmt;
+use tokio::process::Command;
+
+use crate::common::*;
+
+mod json_schema;
+mod messages;
+mod tap;
+mod target;
+
+pub(crate) use self::tap::SingerTapLocator;
+pub(crate) use self::target::SingerTargetLocator;
+
+/// A shell command which runs a Singer tap or target, and an optional Singer
+/// stream name. We write these as `COMMAND#STREAM`, escaping `%` and `#` in
+/// both parts.
+#[derive(Clone, Debug)]
+pub(crate) struct SingerCommand {
+    /// The command to run.
+    command: String,
+    /// The Singer stream to read or write.
+    stream: Option<String>,
+}
+
+impl SingerCommand {
+    /// Parse the part of a locator following `scheme`.
+    fn parse(scheme: &str, s: &str) -> Result<Self> {
+        if !s.starts_with(scheme) {
+            return Err(format_err!("expected {:?} to start with {}", s, scheme));
+        }
+        let decode = |part: &str| -> Result<String> {
+            Ok(percent_decode_str(part)
+                .decode_utf8()
+                .with_context(|_| format!("error decoding {:?}", s))?
+                .into_owned())
+        };
+        let mut parts = s[scheme.len()..].splitn(2, '#');
+        let command = decode(parts.next().unwrap_or(""))?;
+        let stream = parts.next().map(decode).transpose()?;
+        if command.trim().is_empty() {
+            return Err(format_err!(
+                "expected a command after {} in {:?}",
+                scheme,
+                s
+            ));
+        }
+        if stream.as_ref().map(|s| s.is_empty()).unwrap_or(false) {
+            return Err(format_err!("expected a stream name after '#' in {:?}", s));
+        }
+        Ok(SingerCommand { command, stream })
+    }
+
+    /// Format this command as part of a locator.
+    fn fmt_locator(&self, scheme: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encode = |s: &str| s.replace('%', "%25").replace('#', "%23");
+        write!(f, "{}{}", scheme, encode(&self.command))?;
+        if let Some(stream) = &self.stream {
+            write!(f, "#{}", encode(stream))?;
+        }
+        Ok(())
+    }
+
+    /// Build a `Command` which runs our command using the shell.
+    fn shell_command(&self) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&self.command);
+        command
+    }
+}
+
+#[test]
+fn parse_command_and_stream() {
+    let cmd =
+        SingerCommand::parse("singer-tap:", "singer-tap:tap-x --config c.json#users")
+            .unwrap();
+    assert_eq!(cmd.command, "tap-x --config c.json");
+    assert_eq!(cmd.stream.as_ref().unwrap(), "users");
+
+    let cmd = SingerCommand::parse("singer-tap:", "singer-tap:echo %23%25").unwrap();
+    assert_eq!(cmd.command, "echo #%");
+    assert!(cmd.stream.is_none());
+
+    assert!(SingerCommand::parse("singer-tap:", "singer-tap:").is_err());
+    assert!(SingerCommand::parse("singer-tap:", "singer-tap:tap-x#").is_err());
+}
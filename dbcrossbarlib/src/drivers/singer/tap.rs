@@ -0,0 +1,248 @@
+//! Reading data from Singer taps.
+
+use std::{
+    fmt,
+    io::{BufRead, BufReader},
+    process::Stdio,
+    str::FromStr,
+};
+use tokio::io::BufReader as AsyncBufReader;
+
+use super::{
+    json_schema::table_from_json_schema,
+    messages::{Message, SchemaMessage},
+    SingerCommand,
+};
+use crate::common::*;
+use crate::drivers::shopify::json_to_csv::write_row;
+use crate::tokio_glue::copy_reader_to_stream;
+use crate::transform::spawn_sync_transform;
+
+/// A Singer tap, such as `singer-tap:tap-github --config config.json#commits`.
+/// If no stream is specified, we use the first stream the tap describes.
+#[derive(Clone, Debug)]
+pub(crate) struct SingerTapLocator {
+    tap: SingerCommand,
+}
+
+impl fmt::Display for SingerTapLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.tap.fmt_locator(Self::scheme(), f)
+    }
+}
+
+impl FromStr for SingerTapLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tap = SingerCommand::parse(Self::scheme(), s)?;
+        Ok(SingerTapLocator { tap })
+    }
+}
+
+impl Locator for SingerTapLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self, ctx: Context) -> BoxFuture<Option<Table>> {
+        schema_helper(ctx, self.clone()).boxed()
+    }
+
+    fn local_data(
+        &self,
+        ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Option<BoxStream<CsvStream>>> {
+        local_data_helper(ctx, self.clone(), shared_args, source_args).boxed()
+    }
+}
+
+/// Run our tap until it describes the stream we want, and convert that
+/// description to a table.
+async fn schema_helper(
+    ctx: Context,
+    locator: SingerTapLocator,
+) -> Result<Option<Table>> {
+    debug!(
+        ctx.log(),
+        "reading Singer schema from `{}`", locator.tap.command
+    );
+
+    // We stop reading as soon as we see our schema, so kill the tap when we
+    // drop `child`.
+    let mut child = locator
+        .tap
+        .shell_command()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|_| format!("error running `{}`", locator.tap.command))?;
+    let child_stdout = child.stdout.take().expect("child should have stdout");
+    let mut lines = AsyncBufReader::with_capacity(BUFFER_SIZE, child_stdout).lines();
+    while let Some(line) = lines.next().await {
+        let line =
+            line.with_context(|_| format!("error reading `{}`", locator.tap.command))?;
+        if let Message::Schema(SchemaMessage { stream, schema }) =
+            Message::parse(&line)?
+        {
+            if locator.tap.stream.is_none()
+                || locator.tap.stream.as_ref() == Some(&stream)
+            {
+                return Ok(Some(table_from_json_schema(&stream, &schema)?));
+            }
+        }
+    }
+    Err(format_err!(
+        "`{}` did not output a SCHEMA message for {}",
+        locator.tap.command,
+        locator.tap.stream.as_deref().unwrap_or("any stream"),
+    ))
+}
+
+/// Run our tap and convert its records to CSV.
+async fn local_data_helper(
+    ctx: Context,
+    locator: SingerTapLocator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Option<BoxStream<CsvStream>>> {
+    let shared_args = shared_args.verify(SingerTapLocator::features())?;
+    let _source_args = source_args.verify(SingerTapLocator::features())?;
+    let schema = shared_args.schema().to_owned();
+
+    debug!(
+        ctx.log(),
+        "reading Singer messages from `{}`", locator.tap.command
+    );
+    let mut child = locator
+        .tap
+        .shell_command()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|_| format!("error running `{}`", locator.tap.command))?;
+    let child_stdout = child.stdout.take().expect("child should have stdout");
+    ctx.spawn_process(format!("`{}`", locator.tap.command), child);
+
+    let messages = copy_reader_to_stream(ctx.clone(), child_stdout)?.boxed();
+    let stream = locator.tap.stream.clone();
+    let data = spawn_sync_transform(
+        ctx,
+        "singer-to-csv".to_owned(),
+        messages,
+        move |ctx, rdr, wtr| copy_singer_to_csv(&ctx, &schema, stream, rdr, wtr),
+    )?;
+    Ok(Some(box_stream_once(Ok(CsvStream {
+        name: locator.tap.stream.unwrap_or_else(|| "data".to_owned()),
+        metadata: StreamMetadata::default(),
+        data,
+    }))))
+}
+
+/// Read Singer messages from `rdr`, and write the records in `stream` to `wtr`
+/// as CSV. If `stream` is `None`, use the first stream we see.
+fn copy_singer_to_csv<R: Read, W: Write>(
+    ctx: &Context,
+    schema: &Table,
+    mut stream: Option<String>,
+    rdr: R,
+    wtr: W,
+) -> Result<()> {
+    let rdr = BufReader::with_capacity(BUFFER_SIZE, rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+    wtr.write_record(schema.columns.iter().map(|c| &c.name))?;
+    let mut buffer = Vec::with_capacity(2 * 1024);
+    for line in rdr.lines() {
+        let line = line.context("error reading Singer messages")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Message::parse(&line)? {
+            Message::Schema(msg) => {
+                if stream.is_none() {
+                    debug!(ctx.log(), "reading Singer stream {}", msg.stream);
+                    stream = Some(msg.stream);
+                }
+            }
+            Message::Record(msg) => match &stream {
+                Some(stream) if stream == &msg.stream => {
+                    write_row(&mut wtr, schema, msg.record, &mut buffer)
+                        .with_context(|_| {
+                            format!("error converting record from {}", stream)
+                        })?;
+                }
+                Some(_) => trace!(ctx.log(), "skipping record from {}", msg.stream),
+                None => {
+                    return Err(format_err!(
+                        "Singer stream {} sent RECORD before SCHEMA",
+                        msg.stream,
+                    ))
+                }
+            },
+            Message::State(value) => debug!(ctx.log(), "Singer state: {}", value),
+            Message::Other(ty) => trace!(ctx.log(), "ignoring Singer {} message", ty),
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[test]
+fn copy_singer_to_csv_selects_first_stream() {
+    use crate::schema::{Column, DataType};
+
+    let (ctx, _worker_fut) = Context::create_for_test("copy_singer_to_csv");
+    let schema = Table {
+        name: "users".to_owned(),
+        columns: vec![
+            Column {
+                name: "id".to_owned(),
+                is_nullable: false,
+                data_type: DataType::Int64,
+                comment: None,
+            },
+            Column {
+                name: "tags".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Array(Box::new(DataType::Text)),
+                comment: None,
+            },
+        ],
+    };
+    let input = r#"{"type": "SCHEMA", "stream": "users", "schema": {}, "key_properties": ["id"]}
+{"type": "RECORD", "stream": "users", "record": {"id": 1, "tags": ["a"]}}
+{"type": "RECORD", "stream": "orders", "record": {"id": 7}}
+{"type": "STATE", "value": {"users": 1}}
+{"type": "RECORD", "stream": "users", "record": {"id": 2}}
+"#;
+    let mut output = vec![];
+    copy_singer_to_csv(&ctx, &schema, None, input.as_bytes(), &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "id,tags\n1,\"[\"\"a\"\"]\"\n2,\n",
+    );
+}
+
+impl LocatorStatic for SingerTapLocator {
+    fn scheme() -> &'static str {
+        "singer-tap:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::Schema | LocatorFeatures::LocalData,
+            write_schema_if_exists: EnumSet::empty(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+
+    fn is_unstable() -> bool {
+        true
+    }
+}
@@ -0,0 +1,292 @@
+//! Writing data to Singer targets.
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{fmt, process::Stdio, str::FromStr};
+
+use super::{
+    json_schema::json_schema_for_table,
+    messages::{record_message, schema_message},
+    SingerCommand,
+};
+use crate::common::*;
+use crate::concat::concatenate_csv_streams;
+use crate::from_csv_cell::FromCsvCell;
+use crate::rate_limit::RateLimitArguments;
+use crate::schema::DataType;
+use crate::tokio_glue::copy_stream_to_writer;
+
+/// A Singer target, such as `singer-target:target-csv --config config.json`.
+/// If no stream is specified, we use the name of our table schema.
+#[derive(Clone, Debug)]
+pub(crate) struct SingerTargetLocator {
+    target: SingerCommand,
+}
+
+impl fmt::Display for SingerTargetLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.target.fmt_locator(Self::scheme(), f)
+    }
+}
+
+impl FromStr for SingerTargetLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let target = SingerCommand::parse(Self::scheme(), s)?;
+        Ok(SingerTargetLocator { target })
+    }
+}
+
+impl Locator for SingerTargetLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn display_output_locators(&self) -> DisplayOutputLocators {
+        // Targets write their state messages to standard output.
+        DisplayOutputLocators::Never
+    }
+
+    fn write_local_data(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+        shared_args: SharedArguments<Unverified>,
+        dest_args: DestinationArguments<Unverified>,
+    ) -> BoxFuture<BoxStream<BoxFuture<BoxLocator>>> {
+        write_local_data_helper(ctx, self.clone(), data, shared_args, dest_args)
+            .boxed()
+    }
+}
+
+/// Driver arguments for `singer-target:`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SingerTargetArguments {
+    /// The columns which uniquely identify each record.
+    #[serde(default)]
+    key_properties: Vec<String>,
+
+    /// How quickly we should send records to the target.
+    #[serde(default)]
+    rate_limit: RateLimitArguments,
+}
+
+/// Convert our CSV data to Singer messages, and pipe them to our target.
+async fn write_local_data_helper(
+    ctx: Context,
+    locator: SingerTargetLocator,
+    data: BoxStream<CsvStream>,
+    shared_args: SharedArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
+    let shared_args = shared_args.verify(SingerTargetLocator::features())?;
+    let dest_args = dest_args.verify(SingerTargetLocator::features())?;
+    let if_exists = dest_args.if_exists().to_owned();
+    let target_args = dest_args
+        .driver_args()
+        .deserialize::<SingerTargetArguments>()
+        .context("could not parse --to-arg")?;
+    let schema = shared_args.schema().to_owned();
+    for key in &target_args.key_properties {
+        if !schema.columns.iter().any(|c| &c.name == key) {
+            return Err(format_err!("key property {:?} is not in schema", key));
+        }
+    }
+    let stream_name = locator
+        .target
+        .stream
+        .clone()
+        .unwrap_or_else(|| schema.name.clone());
+
+    // Send our `SCHEMA` message, followed by our records, released no faster
+    // than our rate limit allows.
+    let schema_line =
+        singer_schema_line(&schema, &stream_name, &target_args.key_properties)?;
+    let stream = concatenate_csv_streams(ctx.clone(), data)?;
+    let batches =
+        target_args
+            .rate_limit
+            .csv_row_batches(&ctx, &schema, stream.data)?;
+    let records =
+        batches.map(move |rows| singer_record_lines(&schema, &stream_name, &rows?));
+    let messages = box_stream_once(Ok(schema_line)).chain(records).boxed();
+
+    let fut = async move {
+        let command = &locator.target.command;
+        debug!(ctx.log(), "writing Singer messages to `{}`", command);
+
+        // Let the target decide what `--if-exists` means for it, just like
+        // `exec:`.
+        let mut child = locator
+            .target
+            .shell_command()
+            .env("DBCROSSBAR_IF_EXISTS", if_exists.to_string())
+            .stdin(Stdio::piped())
+            .spawn()
+            .with_context(|_| format!("error running `{}`", command))?;
+        let child_stdin = child.stdin.take().expect("child should have stdin");
+
+        // Copy our messages, and close stdin so that the target sees EOF.
+        copy_stream_to_writer(ctx.clone(), messages, child_stdin)
+            .await
+            .with_context(|_| format!("error writing to `{}`", command))?;
+
+        let status = child
+            .await
+            .with_context(|_| format!("error waiting for `{}`", command))?;
+        if !status.success() {
+            return Err(format_err!("`{}` failed with {}", command, status));
+        }
+        Ok(locator.boxed())
+    };
+    Ok(box_stream_once(Ok(fut.boxed())))
+}
+
+/// Serialize a Singer `SCHEMA` message describing `schema` as a line of JSON.
+fn singer_schema_line(
+    schema: &Table,
+    stream: &str,
+    key_properties: &[String],
+) -> Result<BytesMut> {
+    let json_schema = json_schema_for_table(schema);
+    let mut line =
+        serde_json::to_vec(&schema_message(stream, json_schema, key_properties))?;
+    line.push(b'\n');
+    Ok(BytesMut::from(&line[..]))
+}
+
+/// Serialize `rows` as Singer `RECORD` messages, one per line.
+fn singer_record_lines(
+    schema: &Table,
+    stream: &str,
+    rows: &[csv::StringRecord],
+) -> Result<BytesMut> {
+    let mut lines = vec![];
+    for row in rows {
+        let mut record = Map::new();
+        for (cell, col) in row.iter().zip(&schema.columns) {
+            let value = csv_cell_to_json(cell, &col.data_type)
+                .with_context(|_| format!("error converting column {:?}", col.name))?;
+            record.insert(col.name.clone(), value);
+        }
+        serde_json::to_writer(
+            &mut lines,
+            &record_message(stream, Value::Object(record)),
+        )?;
+        lines.push(b'\n');
+    }
+    Ok(BytesMut::from(&lines[..]))
+}
+
+/// Convert a cell in our CSV interchange format to a JSON value.
+fn csv_cell_to_json(cell: &str, data_type: &DataType) -> Result<Value> {
+    if cell.is_empty() {
+        return Ok(Value::Null);
+    }
+    match data_type {
+        DataType::Bool => Ok(Value::Bool(bool::from_csv_cell(cell)?)),
+        DataType::Int16 | DataType::Int32 | DataType::Int64 => {
+            Ok(Value::from(i64::from_csv_cell(cell)?))
+        }
+        DataType::Float32 | DataType::Float64 => {
+            let f = f64::from_csv_cell(cell)?;
+            serde_json::Number::from_f64(f)
+                .map(Value::Number)
+                .ok_or_else(|| format_err!("cannot represent {} as JSON", cell))
+        }
+        DataType::Array(_)
+        | DataType::GeoJson(_)
+        | DataType::Json
+        | DataType::Struct(_) => Value::from_csv_cell(cell),
+        // Singer represents everything else as a string, including dates and
+        // decimals.
+        DataType::Date
+        | DataType::Decimal
+        | DataType::FixedDecimal { .. }
+        | DataType::Text
+        | DataType::TimestampWithoutTimeZone
+        | DataType::TimestampWithTimeZone
+        | DataType::Uuid => Ok(Value::String(cell.to_owned())),
+    }
+}
+
+#[test]
+fn singer_lines_contain_messages() {
+    use crate::schema::Column;
+    use serde_json::json;
+
+    let schema = Table {
+        name: "users".to_owned(),
+        columns: vec![
+            Column {
+                name: "id".to_owned(),
+                is_nullable: false,
+                data_type: DataType::Int64,
+                comment: None,
+            },
+            Column {
+                name: "active".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Bool,
+                comment: None,
+            },
+            Column {
+                name: "tags".to_owned(),
+                is_nullable: true,
+                data_type: DataType::Array(Box::new(DataType::Text)),
+                comment: None,
+            },
+        ],
+    };
+    let rows = vec![
+        csv::StringRecord::from(vec!["1", "t", "[\"a\"]"]),
+        csv::StringRecord::from(vec!["2", "", ""]),
+    ];
+    let mut output =
+        singer_schema_line(&schema, "people", &["id".to_owned()]).unwrap();
+    output.extend_from_slice(&singer_record_lines(&schema, "people", &rows).unwrap());
+    let lines = String::from_utf8(output.to_vec())
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str::<Value>(l).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["type"], json!("SCHEMA"));
+    assert_eq!(lines[0]["stream"], json!("people"));
+    assert_eq!(lines[0]["key_properties"], json!(["id"]));
+    assert_eq!(
+        lines[1],
+        json!({
+            "type": "RECORD",
+            "stream": "people",
+            "record": { "id": 1, "active": true, "tags": ["a"] },
+        }),
+    );
+    assert_eq!(
+        lines[2]["record"],
+        json!({ "id": 2, "active": null, "tags": null }),
+    );
+}
+
+impl LocatorStatic for SingerTargetLocator {
+    fn scheme() -> &'static str {
+        "singer-target:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::WriteLocalData.into(),
+            write_schema_if_exists: EnumSet::empty(),
+            source_args: EnumSet::empty(),
+            dest_args: DestinationArgumentsFeatures::DriverArgs.into(),
+            dest_if_exists: EnumSet::all(),
+            _placeholder: (),
+        }
+    }
+
+    fn is_unstable() -> bool {
+        true
+    }
+}
@@ -0,0 +1,317 @@
+//! Converting between Spark `StructType` JSON and our portable schemas.
+//!
+//! This is the format produced by `StructType.json()` in PySpark and by
+//! `StructType.json` in Scala.
+
+use serde_json::{json, Map, Value};
+
+use crate::common::*;
+use crate::schema::{Column, DataType, StructField};
+
+/// The type we use when writing `decimal` types. We don't track precision in
+/// our portable schema, so we use the same values as BigQuery's `NUMERIC`.
+const DECIMAL_TYPE: &str = "decimal(38,9)";
+
+/// Convert a Spark `StructType` into a table.
+pub(crate) fn table_from_spark_schema(schema: &Value) -> Result<Table> {
+    let columns = struct_fields(schema)?
+        .into_iter()
+        .map(|(field, comment)| Column {
+            name: field.name,
+            is_nullable: field.is_nullable,
+            data_type: field.data_type,
+            comment,
+        })
+        .collect();
+    Ok(Table {
+        name: "unnamed".to_owned(),
+        columns,
+    })
+}
+
+/// Convert the fields of a Spark `struct` type, returning each field's comment.
+fn struct_fields(schema: &Value) -> Result<Vec<(StructField, Option<String>)>> {
+    if schema.get("type").and_then(|t| t.as_str()) != Some("struct") {
+        return Err(format_err!("expected Spark struct type, found {}", schema));
+    }
+    let fields = schema
+        .get("fields")
+        .and_then(|fields| fields.as_array())
+        .ok_or_else(|| format_err!("expected fields in Spark struct type"))?;
+    let mut result = vec![];
+    for field in fields {
+        let name = field.get("name").and_then(|n| n.as_str()).ok_or_else(|| {
+            format_err!("expected name in Spark struct field {}", field)
+        })?;
+        let field_type = field.get("type").ok_or_else(|| {
+            format_err!("expected type for Spark struct field {}", name)
+        })?;
+        let data_type = data_type_from_spark_type(field_type)
+            .with_context(|_| format!("error in Spark struct field {}", name))?;
+        let comment = field
+            .get("metadata")
+            .and_then(|m| m.get("comment"))
+            .and_then(|c| c.as_str())
+            .map(|c| c.to_owned());
+        result.push((
+            StructField {
+                name: name.to_owned(),
+                // Spark defaults to nullable if this is missing.
+                is_nullable: field
+                    .get("nullable")
+                    .and_then(|n| n.as_bool())
+                    .unwrap_or(true),
+                data_type,
+            },
+            comment,
+        ));
+    }
+    Ok(result)
+}
+
+/// Convert a Spark data type into a portable data type.
+fn data_type_from_spark_type(spark_type: &Value) -> Result<DataType> {
+    match spark_type {
+        Value::String(name) => match &name[..] {
+            "boolean" => Ok(DataType::Bool),
+            "byte" | "short" => Ok(DataType::Int16),
+            "integer" => Ok(DataType::Int32),
+            "long" => Ok(DataType::Int64),
+            "float" => Ok(DataType::Float32),
+            "double" => Ok(DataType::Float64),
+            "date" => Ok(DataType::Date),
+            // Spark's `timestamp` represents an instant in time, which is
+            // displayed using the session time zone.
+            "timestamp" => Ok(DataType::TimestampWithTimeZone),
+            "timestamp_ntz" => Ok(DataType::TimestampWithoutTimeZone),
+            "string" => Ok(DataType::Text),
+            name if name == "decimal" || name.starts_with("decimal(") => {
+                Ok(DataType::Decimal)
+            }
+            name if name.starts_with("char(") || name.starts_with("varchar(") => {
+                Ok(DataType::Text)
+            }
+            other => Err(format_err!("unsupported Spark type {:?}", other)),
+        },
+        Value::Object(_) => {
+            match spark_type.get("type").and_then(|t| t.as_str()) {
+                Some("array") => {
+                    let elem_type =
+                        spark_type.get("elementType").ok_or_else(|| {
+                            format_err!("expected elementType in {}", spark_type)
+                        })?;
+                    Ok(DataType::Array(Box::new(data_type_from_spark_type(
+                        elem_type,
+                    )?)))
+                }
+                // We have no map type, so represent maps as JSON objects.
+                Some("map") => Ok(DataType::Json),
+                Some("struct") => Ok(DataType::Struct(
+                    struct_fields(spark_type)?
+                        .into_iter()
+                        .map(|(field, _comment)| field)
+                        .collect(),
+                )),
+                _ => Err(format_err!("unsupported Spark type {}", spark_type)),
+            }
+        }
+        _ => Err(format_err!("unexpected Spark type {}", spark_type)),
+    }
+}
+
+/// Build a Spark `StructType` describing `table`.
+pub(crate) fn spark_schema_for_table(table: &Table) -> Value {
+    let fields = table
+        .columns
+        .iter()
+        .map(|col| {
+            spark_field(
+                &col.name,
+                col.is_nullable,
+                &col.data_type,
+                col.comment.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>();
+    json!({ "type": "struct", "fields": fields })
+}
+
+/// Build a Spark `StructField`.
+fn spark_field(
+    name: &str,
+    is_nullable: bool,
+    data_type: &DataType,
+    comment: Option<&str>,
+) -> Value {
+    let mut metadata = Map::new();
+    if let Some(comment) = comment {
+        metadata.insert("comment".to_owned(), json!(comment));
+    }
+    json!({
+        "name": name,
+        "type": spark_type_for_data_type(data_type),
+        "nullable": is_nullable,
+        "metadata": metadata,
+    })
+}
+
+/// Choose a Spark data type for `data_type`.
+fn spark_type_for_data_type(data_type: &DataType) -> Value {
+    match data_type {
+        DataType::Array(elem_type) => json!({
+            "type": "array",
+            "elementType": spark_type_for_data_type(elem_type),
+            "containsNull": true,
+        }),
+        DataType::Bool => json!("boolean"),
+        DataType::Date => json!("date"),
+        DataType::Decimal => json!(DECIMAL_TYPE),
+        // Spark decimals have at most 38 digits, so store anything larger as a
+        // string instead of rounding it.
+        DataType::FixedDecimal { precision, scale } if *precision <= 38 => {
+            json!(format!("decimal({},{})", precision, scale))
+        }
+        DataType::FixedDecimal { .. } => json!("string"),
+        DataType::Float32 => json!("float"),
+        DataType::Float64 => json!("double"),
+        // Spark has no JSON or geography types, so we store these as strings.
+        DataType::GeoJson(_) | DataType::Json | DataType::Text | DataType::Uuid => {
+            json!("string")
+        }
+        DataType::Int16 => json!("short"),
+        DataType::Int32 => json!("integer"),
+        DataType::Int64 => json!("long"),
+        DataType::Struct(fields) => {
+            let fields = fields
+                .iter()
+                .map(|f| spark_field(&f.name, f.is_nullable, &f.data_type, None))
+                .collect::<Vec<_>>();
+            json!({ "type": "struct", "fields": fields })
+        }
+        DataType::TimestampWithoutTimeZone => json!("timestamp_ntz"),
+        DataType::TimestampWithTimeZone => json!("timestamp"),
+    }
+}
+
+#[test]
+fn table_from_spark_schema_handles_nested_types() {
+    let schema = json!({
+        "type": "struct",
+        "fields": [
+            { "name": "id", "type": "long", "nullable": false, "metadata": {} },
+            {
+                "name": "total",
+                "type": "decimal(10,2)",
+                "nullable": true,
+                "metadata": { "comment": "Order total" },
+            },
+            {
+                "name": "tags",
+                "type": {
+                    "type": "array",
+                    "elementType": "string",
+                    "containsNull": true,
+                },
+                "nullable": true,
+                "metadata": {},
+            },
+            {
+                "name": "attrs",
+                "type": {
+                    "type": "map",
+                    "keyType": "string",
+                    "valueType": "integer",
+                    "valueContainsNull": true,
+                },
+                "nullable": true,
+                "metadata": {},
+            },
+            {
+                "name": "ship_to",
+                "type": {
+                    "type": "struct",
+                    "fields": [
+                        { "name": "zip", "type": "varchar(10)", "nullable": false },
+                    ],
+                },
+                "nullable": true,
+                "metadata": {},
+            },
+        ],
+    });
+    let table = table_from_spark_schema(&schema).unwrap();
+    let types = table
+        .columns
+        .iter()
+        .map(|c| (&c.name[..], c.is_nullable, c.data_type.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            ("id", false, DataType::Int64),
+            ("total", true, DataType::Decimal),
+            ("tags", true, DataType::Array(Box::new(DataType::Text))),
+            ("attrs", true, DataType::Json),
+            (
+                "ship_to",
+                true,
+                DataType::Struct(vec![StructField {
+                    name: "zip".to_owned(),
+                    is_nullable: false,
+                    data_type: DataType::Text,
+                }])
+            ),
+        ]
+    );
+    assert_eq!(table.columns[1].comment.as_deref(), Some("Order total"));
+
+    let unknown = json!({
+        "type": "struct",
+        "fields": [{ "name": "x", "type": "binary", "nullable": true }],
+    });
+    assert!(table_from_spark_schema(&unknown).is_err());
+}
+
+#[test]
+fn spark_schema_roundtrip() {
+    let column = |name: &str, is_nullable: bool, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let table = Table {
+        name: "unnamed".to_owned(),
+        columns: vec![
+            Column {
+                comment: Some("Unique ID".to_owned()),
+                ..column("id", false, DataType::Int64)
+            },
+            column("flag", true, DataType::Bool),
+            column("day", true, DataType::Date),
+            column("price", true, DataType::Decimal),
+            column("ratio", true, DataType::Float32),
+            column("score", true, DataType::Float64),
+            column("small", true, DataType::Int16),
+            column("medium", true, DataType::Int32),
+            column("name", true, DataType::Text),
+            column("local", true, DataType::TimestampWithoutTimeZone),
+            column("instant", true, DataType::TimestampWithTimeZone),
+            column(
+                "points",
+                true,
+                DataType::Array(Box::new(DataType::Struct(vec![StructField {
+                    name: "x".to_owned(),
+                    is_nullable: false,
+                    data_type: DataType::Float64,
+                }]))),
+            ),
+        ],
+    };
+    let schema = spark_schema_for_table(&table);
+    assert_eq!(
+        schema["fields"][0]["metadata"]["comment"],
+        json!("Unique ID")
+    );
+    assert_eq!(table_from_spark_schema(&schema).unwrap(), table);
+}
@@ -0,0 +1,105 @@
+//! Support for `spark-schema` locators.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+
+mod convert;
+
+use self::convert::{spark_schema_for_table, table_from_spark_schema};
+
+/// A Spark `StructType`, in the JSON format produced by `StructType.json()`.
+#[derive(Clone, Debug)]
+pub struct SparkSchemaLocator {
+    path: PathOrStdio,
+}
+
+impl fmt::Display for SparkSchemaLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.path.fmt_locator_helper(Self::scheme(), f)
+    }
+}
+
+impl FromStr for SparkSchemaLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = PathOrStdio::from_str_locator_helper(Self::scheme(), s)?;
+        Ok(SparkSchemaLocator { path })
+    }
+}
+
+impl Locator for SparkSchemaLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self, ctx: Context) -> BoxFuture<Option<Table>> {
+        schema_helper(ctx, self.to_owned()).boxed()
+    }
+
+    fn write_schema(
+        &self,
+        ctx: Context,
+        table: Table,
+        if_exists: IfExists,
+    ) -> BoxFuture<()> {
+        write_schema_helper(ctx, self.to_owned(), table, if_exists).boxed()
+    }
+}
+
+impl LocatorStatic for SparkSchemaLocator {
+    fn scheme() -> &'static str {
+        "spark-schema:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::Schema | LocatorFeatures::WriteSchema,
+            write_schema_if_exists: IfExistsFeatures::no_append(),
+            source_args: EnumSet::empty(),
+            dest_args: EnumSet::empty(),
+            dest_if_exists: EnumSet::empty(),
+            _placeholder: (),
+        }
+    }
+}
+
+/// Implementation of `schema`, but as a real `async` function.
+async fn schema_helper(
+    _ctx: Context,
+    source: SparkSchemaLocator,
+) -> Result<Option<Table>> {
+    // Read our input.
+    let input = source.path.open_async().await?;
+    let data = async_read_to_end(input)
+        .await
+        .with_context(|_| format!("error reading {}", source.path))?;
+
+    // Parse our input as JSON, and convert it.
+    let schema: serde_json::Value = serde_json::from_slice(&data)
+        .with_context(|_| format!("error parsing {}", source.path))?;
+    let table = table_from_spark_schema(&schema)
+        .with_context(|_| format!("error converting {}", source.path))?;
+    Ok(Some(table))
+}
+
+/// Implementation of `write_schema`, but as a real `async` function.
+async fn write_schema_helper(
+    ctx: Context,
+    dest: SparkSchemaLocator,
+    table: Table,
+    if_exists: IfExists,
+) -> Result<()> {
+    let schema = spark_schema_for_table(&table);
+
+    // Output our schema to our destination.
+    let mut f = dest.path.create_async(ctx, if_exists).await?;
+    buffer_sync_write_and_copy_to_async(&mut f, |buff| {
+        serde_json::to_writer_pretty(buff, &schema)
+    })
+    .await
+    .with_context(|_| format!("error writing to {}", dest.path))?;
+    f.flush().await?;
+    Ok(())
+}
@@ -0,0 +1,230 @@
+//! Converting between Frictionless Data Table Schemas and our portable schemas.
+//!
+//! See https://specs.frictionlessdata.io/table-schema/ for the format.
+
+use serde_json::{json, Map, Value};
+
+use crate::common::*;
+use crate::schema::{Column, DataType, Srid};
+
+/// Convert a Table Schema, or a data package containing exactly one resource
+/// with a schema, into a table.
+pub(crate) fn table_from_table_schema(schema: &Value) -> Result<Table> {
+    // If we have a data package, find the schema of its resource.
+    if let Some(resources) = schema.get("resources").and_then(|r| r.as_array()) {
+        let resources = resources
+            .iter()
+            .filter(|r| r.get("schema").is_some())
+            .collect::<Vec<_>>();
+        return match &resources[..] {
+            [resource] => {
+                let name = resource
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or("unnamed");
+                table_from_fields(name, &resource["schema"])
+            }
+            _ => Err(format_err!(
+                "expected data package to contain exactly one resource with a schema, found {}",
+                resources.len(),
+            )),
+        };
+    }
+    table_from_fields("unnamed", schema)
+}
+
+/// Convert the `fields` of a Table Schema into a table.
+fn table_from_fields(name: &str, schema: &Value) -> Result<Table> {
+    let fields = schema
+        .get("fields")
+        .and_then(|fields| fields.as_array())
+        .ok_or_else(|| format_err!("expected fields in Table Schema"))?;
+    let mut columns = vec![];
+    for field in fields {
+        let field_name =
+            field.get("name").and_then(|n| n.as_str()).ok_or_else(|| {
+                format_err!("expected name in Table Schema field {}", field)
+            })?;
+        let is_required = field
+            .get("constraints")
+            .and_then(|c| c.get("required"))
+            .and_then(|r| r.as_bool())
+            .unwrap_or(false);
+        columns.push(Column {
+            name: field_name.to_owned(),
+            is_nullable: !is_required,
+            data_type: data_type_from_field(field).with_context(|_| {
+                format!("error in Table Schema field {}", field_name)
+            })?,
+            comment: field
+                .get("description")
+                .and_then(|d| d.as_str())
+                .map(|d| d.to_owned()),
+        });
+    }
+    Ok(Table {
+        name: name.to_owned(),
+        columns,
+    })
+}
+
+/// Convert the type of a Table Schema field into a data type.
+fn data_type_from_field(field: &Value) -> Result<DataType> {
+    // The default type is `string`.
+    let ty = match field.get("type") {
+        None => "string",
+        Some(Value::String(ty)) => &ty[..],
+        Some(other) => return Err(format_err!("unexpected field type {}", other)),
+    };
+    let format = field.get("format").and_then(|f| f.as_str());
+    match (ty, format) {
+        ("string", Some("uuid")) => Ok(DataType::Uuid),
+        ("string", _) => Ok(DataType::Text),
+        ("number", _) => Ok(DataType::Float64),
+        ("integer", _) => Ok(DataType::Int64),
+        ("boolean", _) => Ok(DataType::Bool),
+        ("object", _) | ("array", _) => Ok(DataType::Json),
+        ("date", _) => Ok(DataType::Date),
+        ("datetime", _) => Ok(DataType::TimestampWithTimeZone),
+        ("geojson", _) => Ok(DataType::GeoJson(Srid::wgs84())),
+        // These have no portable equivalent, so we leave them as text.
+        ("time", _)
+        | ("year", _)
+        | ("yearmonth", _)
+        | ("duration", _)
+        | ("geopoint", _)
+        | ("any", _) => Ok(DataType::Text),
+        (other, _) => Err(format_err!("unknown Table Schema type {:?}", other)),
+    }
+}
+
+/// Build a Table Schema describing `table`.
+pub(crate) fn table_schema_for_table(table: &Table) -> Value {
+    let fields = table
+        .columns
+        .iter()
+        .map(|col| {
+            let mut field = Map::new();
+            field.insert("name".to_owned(), json!(col.name));
+            let (ty, format) = field_type_for_data_type(&col.data_type);
+            field.insert("type".to_owned(), json!(ty));
+            if let Some(format) = format {
+                field.insert("format".to_owned(), json!(format));
+            }
+            if let Some(comment) = &col.comment {
+                field.insert("description".to_owned(), json!(comment));
+            }
+            if !col.is_nullable {
+                field.insert("constraints".to_owned(), json!({ "required": true }));
+            }
+            Value::Object(field)
+        })
+        .collect::<Vec<_>>();
+    json!({ "fields": fields })
+}
+
+/// Choose a Table Schema type and format for `data_type`.
+fn field_type_for_data_type(
+    data_type: &DataType,
+) -> (&'static str, Option<&'static str>) {
+    match data_type {
+        // Table Schema arrays and objects are untyped JSON.
+        DataType::Array(_) => ("array", None),
+        DataType::Bool => ("boolean", None),
+        DataType::Date => ("date", None),
+        DataType::Decimal
+        | DataType::FixedDecimal { .. }
+        | DataType::Float32
+        | DataType::Float64 => ("number", None),
+        DataType::GeoJson(_) => ("geojson", None),
+        DataType::Int16 | DataType::Int32 | DataType::Int64 => ("integer", None),
+        DataType::Json | DataType::Struct(_) => ("object", None),
+        DataType::Text => ("string", None),
+        DataType::TimestampWithoutTimeZone | DataType::TimestampWithTimeZone => {
+            ("datetime", None)
+        }
+        DataType::Uuid => ("string", Some("uuid")),
+    }
+}
+
+#[test]
+fn table_from_table_schema_handles_data_packages() {
+    let package = json!({
+        "name": "orders-package",
+        "resources": [{
+            "name": "orders",
+            "path": "orders.csv",
+            "schema": {
+                "fields": [
+                    {
+                        "name": "id",
+                        "type": "string",
+                        "format": "uuid",
+                        "constraints": { "required": true },
+                    },
+                    { "name": "note", "description": "Free-form note" },
+                    { "name": "total", "type": "number" },
+                    { "name": "placed_at", "type": "datetime" },
+                    { "name": "year", "type": "year" },
+                    { "name": "lines", "type": "array" },
+                ],
+            },
+        }],
+    });
+    let table = table_from_table_schema(&package).unwrap();
+    assert_eq!(table.name, "orders");
+    let types = table
+        .columns
+        .iter()
+        .map(|c| (&c.name[..], c.is_nullable, c.data_type.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            ("id", false, DataType::Uuid),
+            ("note", true, DataType::Text),
+            ("total", true, DataType::Float64),
+            ("placed_at", true, DataType::TimestampWithTimeZone),
+            ("year", true, DataType::Text),
+            ("lines", true, DataType::Json),
+        ]
+    );
+    assert_eq!(table.columns[1].comment.as_deref(), Some("Free-form note"));
+
+    let ambiguous = json!({ "resources": [
+        { "name": "a", "schema": { "fields": [] } },
+        { "name": "b", "schema": { "fields": [] } },
+    ] });
+    assert!(table_from_table_schema(&ambiguous).is_err());
+    let unknown = json!({ "fields": [{ "name": "x", "type": "mystery" }] });
+    assert!(table_from_table_schema(&unknown).is_err());
+}
+
+#[test]
+fn table_schema_roundtrip() {
+    let column = |name: &str, is_nullable: bool, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let table = Table {
+        name: "unnamed".to_owned(),
+        columns: vec![
+            Column {
+                comment: Some("Primary key".to_owned()),
+                ..column("id", false, DataType::Uuid)
+            },
+            column("active", true, DataType::Bool),
+            column("born_on", true, DataType::Date),
+            column("score", false, DataType::Float64),
+            column("count", false, DataType::Int64),
+            column("name", true, DataType::Text),
+            column("created_at", false, DataType::TimestampWithTimeZone),
+            column("shape", true, DataType::GeoJson(Srid::wgs84())),
+            column("extra", true, DataType::Json),
+        ],
+    };
+    let schema = table_schema_for_table(&table);
+    assert_eq!(table_from_table_schema(&schema).unwrap(), table);
+}
@@ -0,0 +1,259 @@
+//! Generating TypeScript interfaces from our portable schemas.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Write as _,
+};
+
+use crate::common::*;
+use crate::schema::{DataType, StructField};
+use crate::unique_name::unique_name;
+
+/// Generate a TypeScript file containing an interface which matches `table`.
+pub(crate) fn typescript_for_table(table: &Table) -> Result<String> {
+    let mut writer = TsWriter::default();
+    let fields = table
+        .columns
+        .iter()
+        .map(|col| {
+            (
+                StructField {
+                    name: col.name.clone(),
+                    is_nullable: col.is_nullable,
+                    data_type: col.data_type.clone(),
+                },
+                col.comment.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let name =
+        unique_name(&mut writer.interface_names, interface_name(&table.name), "");
+    writer.interface(&name, &fields)?;
+
+    let mut out = String::new();
+    writeln!(
+        &mut out,
+        "// Generated by dbcrossbar from {:?}.",
+        table.name
+    )?;
+    if !writer.aliases.is_empty() {
+        writeln!(&mut out)?;
+        writeln!(
+            &mut out,
+            "// These may be either numbers or strings, depending on how the data \
+             was exported."
+        )?;
+        for alias in &writer.aliases {
+            writeln!(&mut out, "export type {} = number | string;", alias)?;
+        }
+    }
+    for interface in &writer.interfaces {
+        writeln!(&mut out)?;
+        out.push_str(interface);
+    }
+    Ok(out)
+}
+
+/// State we need while generating a TypeScript file.
+#[derive(Default)]
+struct TsWriter {
+    /// The "magic" type aliases that we need to declare. These are the same
+    /// aliases supported by `dbcrossbar-ts:`.
+    aliases: BTreeSet<&'static str>,
+    /// The interfaces we've generated so far, in the order they should appear.
+    interfaces: Vec<String>,
+    /// The interface names we've already used.
+    interface_names: HashSet<String>,
+}
+
+impl TsWriter {
+    /// Generate an interface named `name` containing `fields`. Interfaces for
+    /// any nested structs will appear after this one.
+    fn interface(
+        &mut self,
+        name: &str,
+        fields: &[(StructField, Option<&str>)],
+    ) -> Result<()> {
+        // Reserve a slot for our interface, so that it appears before any
+        // interfaces that it refers to.
+        let idx = self.interfaces.len();
+        self.interfaces.push(String::new());
+
+        let mut out = String::new();
+        writeln!(&mut out, "export interface {} {{", name)?;
+        for (field, comment) in fields {
+            if let Some(comment) = comment {
+                write_doc_comment(&mut out, comment)?;
+            }
+            let mut ty = self.ts_type(name, &field.name, &field.data_type)?;
+            // `any` already includes `null`.
+            if field.is_nullable && ty != "any" {
+                ty.push_str(" | null");
+            }
+            writeln!(&mut out, "  {}: {};", property_name(&field.name)?, ty)?;
+        }
+        writeln!(&mut out, "}}")?;
+
+        self.interfaces[idx] = out;
+        Ok(())
+    }
+
+    /// Choose a TypeScript type for `data_type`, which appears in the field
+    /// `field_name` of the interface `parent`.
+    fn ts_type(
+        &mut self,
+        parent: &str,
+        field_name: &str,
+        data_type: &DataType,
+    ) -> Result<String> {
+        Ok(match data_type {
+            DataType::Array(elem_type) => {
+                format!("{}[]", self.ts_type(parent, field_name, elem_type)?)
+            }
+            DataType::Bool => "boolean".to_owned(),
+            // We don't use `Date` for dates and timestamps, because JSON and
+            // CSV data will contain strings.
+            DataType::Date
+            | DataType::Text
+            | DataType::TimestampWithoutTimeZone
+            | DataType::TimestampWithTimeZone
+            | DataType::Uuid => "string".to_owned(),
+            DataType::Decimal | DataType::FixedDecimal { .. } => self.alias("decimal"),
+            DataType::Float32 | DataType::Float64 => "number".to_owned(),
+            DataType::GeoJson(_) | DataType::Json => "any".to_owned(),
+            DataType::Int16 => self.alias("int16"),
+            DataType::Int32 => self.alias("int32"),
+            DataType::Int64 => self.alias("int64"),
+            DataType::Struct(fields) => {
+                let name = unique_name(
+                    &mut self.interface_names,
+                    format!("{}{}", parent, interface_name(field_name)),
+                    "",
+                );
+                let fields = fields
+                    .iter()
+                    .map(|f| (f.to_owned(), None))
+                    .collect::<Vec<_>>();
+                self.interface(&name, &fields)?;
+                name
+            }
+        })
+    }
+
+    /// Record that we need the "magic" type alias `alias`, and return it.
+    fn alias(&mut self, alias: &'static str) -> String {
+        self.aliases.insert(alias);
+        alias.to_owned()
+    }
+}
+
+/// Write `comment` as an indented JSDoc comment.
+fn write_doc_comment(out: &mut String, comment: &str) -> Result<()> {
+    let comment = comment.replace("*/", "*\\/");
+    let lines = comment.lines().collect::<Vec<_>>();
+    if lines.len() == 1 {
+        writeln!(out, "  /** {} */", lines[0])?;
+    } else {
+        writeln!(out, "  /**")?;
+        for line in lines {
+            writeln!(out, "   * {}", line)?;
+        }
+        writeln!(out, "   */")?;
+    }
+    Ok(())
+}
+
+/// Format a column name as a property name, quoting it if necessary.
+fn property_name(name: &str) -> Result<String> {
+    let is_identifier = matches!(
+        name.chars().next(),
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$'
+    ) && name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        Ok(name.to_owned())
+    } else {
+        Ok(serde_json::to_string(name)?)
+    }
+}
+
+/// Convert a table or column name into a `PascalCase` interface name.
+fn interface_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut capitalize = true;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if capitalize {
+                result.push(c.to_ascii_uppercase());
+            } else {
+                result.push(c);
+            }
+            capitalize = false;
+        } else {
+            capitalize = true;
+        }
+    }
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        result.insert(0, 'T');
+    }
+    result
+}
+
+#[test]
+fn typescript_for_table_generates_interfaces() {
+    use crate::schema::Column;
+
+    let column = |name: &str, is_nullable: bool, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let table = Table {
+        name: "public.orders".to_owned(),
+        columns: vec![
+            Column {
+                comment: Some("Unique ID".to_owned()),
+                ..column("id", false, DataType::Int64)
+            },
+            column("total", false, DataType::Decimal),
+            column("placed_at", true, DataType::TimestampWithTimeZone),
+            column("extra", true, DataType::Json),
+            column("tags", false, DataType::Array(Box::new(DataType::Text))),
+            column("Customer Name", true, DataType::Text),
+            column(
+                "line_items",
+                false,
+                DataType::Array(Box::new(DataType::Struct(vec![StructField {
+                    name: "quantity".to_owned(),
+                    is_nullable: false,
+                    data_type: DataType::Int16,
+                }]))),
+            ),
+        ],
+    };
+    let expected = r#"// Generated by dbcrossbar from "public.orders".
+
+// These may be either numbers or strings, depending on how the data was exported.
+export type decimal = number | string;
+export type int16 = number | string;
+export type int64 = number | string;
+
+export interface PublicOrders {
+  /** Unique ID */
+  id: int64;
+  total: decimal;
+  placed_at: string | null;
+  extra: any;
+  tags: string[];
+  "Customer Name": string | null;
+  line_items: PublicOrdersLineItems[];
+}
+
+export interface PublicOrdersLineItems {
+  quantity: int16;
+}
+"#;
+    assert_eq!(typescript_for_table(&table).unwrap(), expected);
+}
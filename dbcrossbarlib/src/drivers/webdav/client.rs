@@ -0,0 +1,310 @@
+//! A minimal WebDAV client.
+
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::WWW_AUTHENTICATE, Method, RequestBuilder, Response, StatusCode,
+};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::digest::DigestChallenge;
+use super::multistatus::{parse_multistatus, PROPFIND_BODY};
+use crate::common::*;
+use crate::tokio_glue::IdiomaticBytesStream;
+
+/// Driver arguments for `webdav:`, used for both sources and destinations.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct WebDavArguments {
+    /// The user name to authenticate as.
+    user: Option<String>,
+    /// The password for `user`.
+    password: Option<String>,
+    /// The authentication scheme to use.
+    #[serde(default)]
+    auth: AuthScheme,
+}
+
+/// Supported HTTP authentication schemes.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum AuthScheme {
+    Basic,
+    Digest,
+}
+
+impl Default for AuthScheme {
+    fn default() -> Self {
+        AuthScheme::Basic
+    }
+}
+
+/// How we authenticate each request.
+enum Auth {
+    None,
+    Basic {
+        user: String,
+        password: String,
+    },
+    Digest {
+        user: String,
+        password: String,
+        challenge: DigestChallenge,
+        /// How many requests we've made with `challenge`.
+        nonce_count: AtomicU32,
+    },
+}
+
+/// A file found when listing a WebDAV directory.
+#[derive(Clone, Debug)]
+pub(crate) struct WebDavFile {
+    /// The `https:` URL of this file.
+    pub(crate) url: Url,
+    /// When this file was last modified.
+    pub(crate) modified: Option<DateTime<Utc>>,
+}
+
+/// A WebDAV client, with credentials.
+pub(crate) struct WebDavClient {
+    client: reqwest::Client,
+    auth: Auth,
+}
+
+impl WebDavClient {
+    /// Create a new client for talking to the server at `url`.
+    ///
+    /// If we're using digest authentication, this makes a request to get an
+    /// authentication challenge, so that we can authenticate streaming uploads
+    /// without needing to retry them.
+    pub(crate) async fn new(
+        ctx: &Context,
+        url: &Url,
+        args: WebDavArguments,
+    ) -> Result<WebDavClient> {
+        let client = reqwest::Client::new();
+        let auth = match (args.user, args.password, args.auth) {
+            (None, None, _) => Auth::None,
+            (Some(user), Some(password), AuthScheme::Basic) => {
+                Auth::Basic { user, password }
+            }
+            (Some(user), Some(password), AuthScheme::Digest) => {
+                trace!(ctx.log(), "requesting digest challenge from {}", url);
+                let resp = client
+                    .request(propfind(), url.as_str())
+                    .header("Depth", "0")
+                    .send()
+                    .await
+                    .with_context(|_| format!("could not connect to {}", url))?;
+                if resp.status() != StatusCode::UNAUTHORIZED {
+                    return Err(format_err!(
+                        "expected {} to ask for digest authentication, got {}",
+                        url,
+                        resp.status(),
+                    ));
+                }
+                let challenge = resp
+                    .headers()
+                    .get_all(WWW_AUTHENTICATE)
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .find(|value| {
+                        value
+                            .trim_start()
+                            .to_ascii_lowercase()
+                            .starts_with("digest ")
+                    })
+                    .ok_or_else(|| {
+                        format_err!("{} did not offer digest authentication", url)
+                    })?
+                    .parse::<DigestChallenge>()?;
+                Auth::Digest {
+                    user,
+                    password,
+                    challenge,
+                    nonce_count: AtomicU32::new(0),
+                }
+            }
+            _ => {
+                return Err(format_err!(
+                    "must specify both user and password driver arguments"
+                ))
+            }
+        };
+        Ok(WebDavClient { client, auth })
+    }
+
+    /// Build an authenticated request.
+    fn request(&self, method: Method, url: &Url) -> Result<RequestBuilder> {
+        let builder = self.client.request(method.clone(), url.as_str());
+        match &self.auth {
+            Auth::None => Ok(builder),
+            Auth::Basic { user, password } => {
+                Ok(builder.basic_auth(user, Some(password)))
+            }
+            Auth::Digest {
+                user,
+                password,
+                challenge,
+                nonce_count,
+            } => {
+                let nc = nonce_count.fetch_add(1, Ordering::SeqCst) + 1;
+                let mut uri = url.path().to_owned();
+                if let Some(query) = url.query() {
+                    uri.push('?');
+                    uri.push_str(query);
+                }
+                let header = challenge.authorization(
+                    user,
+                    password,
+                    method.as_str(),
+                    &uri,
+                    nc,
+                    &TemporaryStorage::random_tag(),
+                )?;
+                Ok(builder.header("Authorization", header))
+            }
+        }
+    }
+
+    /// List all the files under the directory `url`, recursively.
+    pub(crate) async fn ls(
+        &self,
+        ctx: &Context,
+        url: &Url,
+    ) -> Result<Vec<WebDavFile>> {
+        let mut files = vec![];
+        let mut dirs = vec![url.to_owned()];
+        while let Some(dir) = dirs.pop() {
+            trace!(ctx.log(), "PROPFIND {}", dir);
+            let resp = self
+                .request(propfind(), &dir)?
+                .header("Depth", "1")
+                .header("Content-Type", "application/xml; charset=utf-8")
+                .body(PROPFIND_BODY)
+                .send()
+                .await
+                .with_context(|_| format!("could not list {}", dir))?;
+            let resp = check_response("PROPFIND", &dir, resp).await?;
+            let body = resp
+                .text()
+                .await
+                .with_context(|_| format!("could not read listing of {}", dir))?;
+            for resource in parse_multistatus(&body)
+                .with_context(|_| format!("could not parse listing of {}", dir))?
+            {
+                let resource_url = dir.join(&resource.href).with_context(|_| {
+                    format!(
+                        "could not parse {:?} in listing of {}",
+                        resource.href, dir
+                    )
+                })?;
+                if resource.is_collection {
+                    // Servers include the directory itself in the listing, so
+                    // be careful not to visit it again.
+                    if resource_url.path().trim_end_matches('/')
+                        != dir.path().trim_end_matches('/')
+                    {
+                        let mut subdir = resource_url;
+                        if !subdir.path().ends_with('/') {
+                            subdir.set_path(&format!("{}/", subdir.path()));
+                        }
+                        dirs.push(subdir);
+                    }
+                } else {
+                    files.push(WebDavFile {
+                        url: resource_url,
+                        modified: resource.modified,
+                    });
+                }
+            }
+        }
+        files.sort_by(|a, b| a.url.as_str().cmp(b.url.as_str()));
+        Ok(files)
+    }
+
+    /// Download the file at `url`.
+    pub(crate) async fn get(&self, ctx: &Context, url: &Url) -> Result<Response> {
+        trace!(ctx.log(), "GET {}", url);
+        let resp = self
+            .request(Method::GET, url)?
+            .send()
+            .await
+            .with_context(|_| format!("could not GET {}", url))?;
+        check_response("GET", url, resp).await
+    }
+
+    /// Upload `data` to `url`, replacing any existing file.
+    pub(crate) async fn put(
+        &self,
+        ctx: &Context,
+        url: &Url,
+        data: IdiomaticBytesStream,
+    ) -> Result<()> {
+        trace!(ctx.log(), "PUT {}", url);
+        let resp = self
+            .request(Method::PUT, url)?
+            .body(reqwest::Body::wrap_stream(data))
+            .send()
+            .await
+            .with_context(|_| format!("could not PUT {}", url))?;
+        check_response("PUT", url, resp).await?;
+        Ok(())
+    }
+
+    /// Delete `url` (and everything under it, if it's a directory). Does
+    /// nothing if `url` does not exist.
+    pub(crate) async fn delete(&self, ctx: &Context, url: &Url) -> Result<()> {
+        trace!(ctx.log(), "DELETE {}", url);
+        let resp = self
+            .request(Method::DELETE, url)?
+            .send()
+            .await
+            .with_context(|_| format!("could not DELETE {}", url))?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check_response("DELETE", url, resp).await?;
+        Ok(())
+    }
+
+    /// Create the directory `url`. Does nothing if it already exists.
+    pub(crate) async fn mkcol(&self, ctx: &Context, url: &Url) -> Result<()> {
+        trace!(ctx.log(), "MKCOL {}", url);
+        let mkcol = Method::from_bytes(b"MKCOL").expect("invalid method in source");
+        let resp = self
+            .request(mkcol, url)?
+            .send()
+            .await
+            .with_context(|_| format!("could not MKCOL {}", url))?;
+        // RFC 4918 says that `MKCOL` returns "405 Method Not Allowed" if the
+        // directory already exists.
+        if resp.status() == StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(());
+        }
+        check_response("MKCOL", url, resp).await?;
+        Ok(())
+    }
+}
+
+/// The WebDAV `PROPFIND` method.
+fn propfind() -> Method {
+    Method::from_bytes(b"PROPFIND").expect("invalid method in source")
+}
+
+/// Return `resp` if it succeeded, or an error containing the response body if
+/// it failed.
+async fn check_response(method: &str, url: &Url, resp: Response) -> Result<Response> {
+    let status = resp.status();
+    if status.is_success() {
+        Ok(resp)
+    } else {
+        let body = resp.text().await.unwrap_or_else(|_| String::new());
+        Err(format_err!(
+            "{} {} failed with {}: {}",
+            method,
+            url,
+            status,
+            body.trim()
+        ))
+    }
+}
@@ -0,0 +1,265 @@
+//! Driver for reading and writing CSV files on WebDAV servers.
+
+use percent_encoding::percent_decode_str;
+use std::{fmt, str::FromStr, sync::Arc};
+
+use crate::common::*;
+use crate::concat::concatenate_csv_streams;
+use crate::csv_stream::{csv_stream_file_name, csv_stream_name};
+use crate::http_response::http_response_stream;
+use crate::tokio_glue::idiomatic_bytes_stream;
+
+mod client;
+mod digest;
+mod multistatus;
+
+use self::client::{WebDavArguments, WebDavClient};
+
+/// A CSV file or directory on a WebDAV server, such as
+/// `webdav://dav.example.com/exports/`. We always connect using HTTPS.
+#[derive(Clone, Debug)]
+pub(crate) struct WebDavLocator {
+    url: Url,
+}
+
+impl WebDavLocator {
+    /// Convert an `https:` URL back into a locator.
+    fn from_https_url(url: &Url) -> Result<Self> {
+        assert!(url.as_str().starts_with("https:"));
+        format!("{}{}", Self::scheme(), &url.as_str()["https:".len()..]).parse()
+    }
+
+    /// Convert this locator to an `https:` URL.
+    fn to_https_url(&self) -> Result<Url> {
+        assert!(self.url.as_str().starts_with(Self::scheme()));
+        let https_str =
+            format!("https:{}", &self.url.as_str()[Self::scheme().len()..]);
+        let https_url = https_str
+            .parse::<Url>()
+            .with_context(|_| format_err!("could not set URL scheme for {}", self))?;
+        Ok(https_url)
+    }
+
+    /// Does this locator point to a directory?
+    fn is_directory(&self) -> bool {
+        self.url.path().ends_with('/')
+    }
+}
+
+#[test]
+fn https_url_roundtrip() {
+    let loc =
+        WebDavLocator::from_str("webdav://dav.example.com/exports/a.csv").unwrap();
+    let https_url = loc.to_https_url().unwrap();
+    assert_eq!(https_url.as_str(), "https://dav.example.com/exports/a.csv");
+    assert_eq!(
+        WebDavLocator::from_https_url(&https_url)
+            .unwrap()
+            .to_string(),
+        "webdav://dav.example.com/exports/a.csv",
+    );
+}
+
+impl fmt::Display for WebDavLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.url.fmt(f)
+    }
+}
+
+impl FromStr for WebDavLocator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let url = s
+            .parse::<Url>()
+            .with_context(|_| format!("could not parse WebDAV locator {:?}", s))?;
+        if url.scheme() != "webdav" {
+            Err(format_err!("expected {:?} to start with \"webdav:\"", s))
+        } else if url.host().is_none() || !url.path().starts_with('/') {
+            Err(format_err!("{} must start with webdav://", url))
+        } else {
+            Ok(WebDavLocator { url })
+        }
+    }
+}
+
+impl Locator for WebDavLocator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn local_data(
+        &self,
+        ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Option<BoxStream<CsvStream>>> {
+        local_data_helper(ctx, self.clone(), shared_args, source_args).boxed()
+    }
+
+    fn write_local_data(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+        shared_args: SharedArguments<Unverified>,
+        dest_args: DestinationArguments<Unverified>,
+    ) -> BoxFuture<BoxStream<BoxFuture<BoxLocator>>> {
+        write_local_data_helper(ctx, self.clone(), data, shared_args, dest_args)
+            .boxed()
+    }
+}
+
+/// List and download our CSV files.
+async fn local_data_helper(
+    ctx: Context,
+    locator: WebDavLocator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Option<BoxStream<CsvStream>>> {
+    let _shared_args = shared_args.verify(WebDavLocator::features())?;
+    let source_args = source_args.verify(WebDavLocator::features())?;
+    let webdav_args = source_args
+        .driver_args()
+        .deserialize::<WebDavArguments>()
+        .context("could not parse --from-arg")?;
+
+    let url = locator.to_https_url()?;
+    debug!(ctx.log(), "getting CSV files from {}", url);
+    let client = Arc::new(WebDavClient::new(&ctx, &url, webdav_args).await?);
+
+    // Find the files we want to read.
+    let files = if locator.is_directory() {
+        client
+            .ls(&ctx, &url)
+            .await?
+            .into_iter()
+            .filter(|file| file.url.path().to_ascii_lowercase().ends_with(".csv"))
+            .collect::<Vec<_>>()
+    } else {
+        vec![client::WebDavFile {
+            url: url.clone(),
+            modified: None,
+        }]
+    };
+
+    // Download each file lazily.
+    let csv_streams = stream::iter(files.into_iter().map(Ok)).and_then(move |file| {
+        let ctx = ctx.clone();
+        let client = client.clone();
+        let url = url.clone();
+        async move {
+            let name = csv_stream_name(url.path(), file.url.path())?;
+            let name = percent_decode_str(name).decode_utf8_lossy().into_owned();
+            let ctx = ctx.child(
+                o!("stream" => name.clone(), "url" => file.url.as_str().to_owned()),
+            );
+            let resp = client.get(&ctx, &file.url).await?;
+            Ok(CsvStream {
+                name,
+                metadata: StreamMetadata {
+                    source: Some(
+                        WebDavLocator::from_https_url(&file.url)?.to_string(),
+                    ),
+                    modified: file.modified,
+                },
+                data: http_response_stream(resp),
+            })
+        }
+        .boxed()
+    });
+    Ok(Some(csv_streams.boxed()))
+}
+
+/// Upload our CSV streams.
+async fn write_local_data_helper(
+    ctx: Context,
+    locator: WebDavLocator,
+    data: BoxStream<CsvStream>,
+    shared_args: SharedArguments<Unverified>,
+    dest_args: DestinationArguments<Unverified>,
+) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
+    let _shared_args = shared_args.verify(WebDavLocator::features())?;
+    let dest_args = dest_args.verify(WebDavLocator::features())?;
+    let if_exists = dest_args.if_exists().to_owned();
+    let webdav_args = dest_args
+        .driver_args()
+        .deserialize::<WebDavArguments>()
+        .context("could not parse --to-arg")?;
+
+    let url = locator.to_https_url()?;
+    let client = Arc::new(WebDavClient::new(&ctx, &url, webdav_args).await?);
+
+    if locator.is_directory() {
+        // Prepare our directory.
+        match if_exists {
+            IfExists::Overwrite => {
+                client.delete(&ctx, &url).await?;
+                client.mkcol(&ctx, &url).await?;
+            }
+            // Our file names will be unique, so just make sure our directory
+            // exists.
+            IfExists::Append => client.mkcol(&ctx, &url).await?,
+            _ => {
+                return Err(format_err!(
+                    "must specify `overwrite` or `append` for {} destination",
+                    locator,
+                ))
+            }
+        }
+
+        // Upload each stream as a separate file.
+        let written = data.map_ok(move |stream| {
+            let ctx = ctx.clone();
+            let client = client.clone();
+            let url = url.clone();
+            let if_exists = if_exists.clone();
+            async move {
+                let file_url =
+                    url.join(&csv_stream_file_name(&stream.name, &if_exists))?;
+                let ctx = ctx.child(
+                    o!("stream" => stream.name.clone(), "url" => file_url.to_string()),
+                );
+                let data = idiomatic_bytes_stream(&ctx, stream.data);
+                client.put(&ctx, &file_url, data).await?;
+                Ok(WebDavLocator::from_https_url(&file_url)?.boxed())
+            }
+            .boxed()
+        });
+        Ok(written.boxed())
+    } else {
+        // Upload all our data as a single file.
+        match if_exists {
+            IfExists::Overwrite => {}
+            _ => {
+                return Err(format_err!(
+                    "must specify `overwrite` for single-file {} destination",
+                    locator,
+                ))
+            }
+        }
+        let stream = concatenate_csv_streams(ctx.clone(), data)?;
+        let fut = async move {
+            let data = idiomatic_bytes_stream(&ctx, stream.data);
+            client.put(&ctx, &url, data).await?;
+            Ok(locator.boxed())
+        };
+        Ok(box_stream_once(Ok(fut.boxed())))
+    }
+}
+
+impl LocatorStatic for WebDavLocator {
+    fn scheme() -> &'static str {
+        "webdav:"
+    }
+
+    fn features() -> Features {
+        Features {
+            locator: LocatorFeatures::LocalData | LocatorFeatures::WriteLocalData,
+            write_schema_if_exists: EnumSet::empty(),
+            source_args: SourceArgumentsFeatures::DriverArgs.into(),
+            dest_args: DestinationArgumentsFeatures::DriverArgs.into(),
+            dest_if_exists: IfExistsFeatures::Overwrite | IfExistsFeatures::Append,
+            _placeholder: (),
+        }
+    }
+}
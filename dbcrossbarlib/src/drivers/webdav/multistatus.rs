@@ -0,0 +1,136 @@
+//! Parsing WebDAV `PROPFIND` responses.
+//!
+//! We only need a few properties from a `multistatus` response, so we use
+//! regular expressions instead of a full XML parser. Element names may use any
+//! namespace prefix, because different servers use `D:`, `d:`, `lp1:` or no
+//! prefix at all.
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::common::*;
+
+/// The body we send with `PROPFIND` requests.
+pub(crate) const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:propfind xmlns:D="DAV:">
+  <D:prop>
+    <D:resourcetype/>
+    <D:getlastmodified/>
+  </D:prop>
+</D:propfind>
+"#;
+
+/// A single resource described by a `multistatus` response.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DavResource {
+    /// The `href` of this resource, with XML entities decoded. This is normally
+    /// a percent-encoded path, but it may also be a full URL.
+    pub(crate) href: String,
+    /// Is this resource a collection (ie, a directory)?
+    pub(crate) is_collection: bool,
+    /// When was this resource last modified?
+    pub(crate) modified: Option<DateTime<Utc>>,
+}
+
+/// Build a regex matching the contents of the XML element `name`, with any
+/// namespace prefix.
+fn element_regex(name: &str) -> Regex {
+    Regex::new(&format!(
+        r"(?s)<(?:[A-Za-z][-\w.]*:)?{name}\b[^>]*>(.*?)</(?:[A-Za-z][-\w.]*:)?{name}\s*>",
+        name = name,
+    ))
+    .expect("invalid regex in source")
+}
+
+/// Parse a `multistatus` response body.
+pub(crate) fn parse_multistatus(xml: &str) -> Result<Vec<DavResource>> {
+    lazy_static! {
+        static ref RESPONSE_RE: Regex = element_regex("response");
+        static ref HREF_RE: Regex = element_regex("href");
+        static ref LAST_MODIFIED_RE: Regex = element_regex("getlastmodified");
+        static ref COLLECTION_RE: Regex =
+            Regex::new(r"<(?:[A-Za-z][-\w.]*:)?collection\b")
+                .expect("invalid regex in source");
+    }
+
+    let mut resources = vec![];
+    for response in RESPONSE_RE.captures_iter(xml) {
+        let response = &response[1];
+        let href = HREF_RE
+            .captures(response)
+            .map(|cap| decode_entities(cap[1].trim()))
+            .ok_or_else(|| format_err!("no href in WebDAV response {:?}", response))?;
+        let is_collection = COLLECTION_RE.is_match(response);
+        let modified = match LAST_MODIFIED_RE.captures(response) {
+            Some(cap) => {
+                let text = decode_entities(cap[1].trim());
+                let modified =
+                    DateTime::parse_from_rfc2822(&text).with_context(|_| {
+                        format!("cannot parse WebDAV modification time {:?}", text)
+                    })?;
+                Some(modified.with_timezone(&Utc))
+            }
+            None => None,
+        };
+        resources.push(DavResource {
+            href,
+            is_collection,
+            modified,
+        });
+    }
+    Ok(resources)
+}
+
+#[test]
+fn parse_multistatus_finds_files_and_collections() {
+    use chrono::TimeZone;
+
+    let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:multistatus xmlns:D="DAV:">
+  <D:response>
+    <D:href>/dav/exports/</D:href>
+    <D:propstat>
+      <D:prop><D:resourcetype><D:collection/></D:resourcetype></D:prop>
+      <D:status>HTTP/1.1 200 OK</D:status>
+    </D:propstat>
+  </D:response>
+  <D:response>
+    <D:href>/dav/exports/a%20b&amp;c.csv</D:href>
+    <D:propstat>
+      <D:prop>
+        <D:resourcetype/>
+        <D:getlastmodified>Tue, 01 Sep 2020 12:30:00 GMT</D:getlastmodified>
+      </D:prop>
+      <D:status>HTTP/1.1 200 OK</D:status>
+    </D:propstat>
+  </D:response>
+  <D:responsedescription>ignored</D:responsedescription>
+</D:multistatus>
+"#;
+    let resources = parse_multistatus(xml).unwrap();
+    assert_eq!(
+        resources,
+        vec![
+            DavResource {
+                href: "/dav/exports/".to_owned(),
+                is_collection: true,
+                modified: None,
+            },
+            DavResource {
+                href: "/dav/exports/a%20b&c.csv".to_owned(),
+                is_collection: false,
+                modified: Some(Utc.ymd(2020, 9, 1).and_hms(12, 30, 0)),
+            },
+        ]
+    );
+}
+
+/// Decode the predefined XML entities in `s`.
+fn decode_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
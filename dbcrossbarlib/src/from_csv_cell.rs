@@ -9,6 +9,7 @@ use std::convert::TryInto;
 use uuid::Uuid;
 
 use crate::common::*;
+use crate::wkt::parse_wkt;
 
 /// Parse a value found in a CSV cell. This is analogous to Rust's built-in
 /// [`FromStr`] trait, but it follws the rules of our CSV interchange format.
@@ -105,6 +106,11 @@ impl FromCsvCell for f64 {
 
 impl FromCsvCell for Geometry<f64> {
     fn from_csv_cell(cell: &str) -> Result<Self> {
+        // GeoJSON is our interchange format, but WKT is common in CSV files,
+        // and it's easy to tell the two apart.
+        if !cell.trim_start().starts_with('{') {
+            return parse_wkt(cell);
+        }
         let geojson = cell
             .parse::<GeoJson>()
             .with_context(|_| format!("cannot parse {:?} as GeoJSON", cell))?;
@@ -133,6 +139,10 @@ fn parse_geometry() {
     let geometry = Geometry::<f64>::from_csv_cell(&geojson).unwrap();
     let expected = Geometry::Point(Point::new(-71.0, 42.0));
     assert_eq!(geometry, expected);
+    assert_eq!(
+        Geometry::<f64>::from_csv_cell("POINT(-71 42)").unwrap(),
+        expected
+    );
 }
 
 impl FromCsvCell for i16 {
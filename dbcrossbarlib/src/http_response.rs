@@ -0,0 +1,18 @@
+//! Reading HTTP responses.
+
+use crate::common::*;
+
+/// Convert an HTTP response into a `BoxStream<BytesMut>`.
+///
+/// This is limited to a single concrete input stream type.
+pub(crate) fn http_response_stream(
+    response: reqwest::Response,
+) -> BoxStream<BytesMut> {
+    response
+        .bytes_stream()
+        // Convert `Bytes` to `BytesMut` by copying, which is slightly
+        // expensive.
+        .map_ok(|chunk| BytesMut::from(chunk.as_ref()))
+        .map_err(|err| err.into())
+        .boxed()
+}
@@ -61,14 +61,6 @@ pub enum IfExists {
 }
 
 impl IfExists {
-    /// Are we supposed to perform an upsert?
-    pub(crate) fn is_upsert(&self) -> bool {
-        match self {
-            IfExists::Upsert(_) => true,
-            _ => false,
-        }
-    }
-
     /// Convert to an `tokio::OpenOptions` value, returning an error for
     /// `IfExists::Append`.
     pub(crate) fn to_async_open_options_no_append(
@@ -0,0 +1,110 @@
+//! Glob patterns for choosing which objects to read from cloud storage.
+
+use glob::{MatchOptions, Pattern};
+use std::str::FromStr;
+
+use crate::common::*;
+
+/// A glob pattern like `*.csv.gz` or `2024/*/_SUCCESS`, used by the `include`
+/// and `exclude` driver arguments.
+///
+/// Patterns without a `/` are matched against the file name, so that
+/// `_SUCCESS` matches marker files in any subdirectory. Patterns containing a
+/// `/` are matched against the key relative to the directory we're reading.
+#[derive(Clone, Debug)]
+pub(crate) struct KeyPattern {
+    /// Our compiled pattern.
+    pattern: Pattern,
+    /// Should we match against the whole relative key?
+    match_path: bool,
+}
+
+impl KeyPattern {
+    /// Does `rel_key` match this pattern?
+    pub(crate) fn matches(&self, rel_key: &str) -> bool {
+        // Don't let `*` match `/`. Only `**` should match directories.
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        let candidate = if self.match_path {
+            rel_key
+        } else {
+            rel_key.rsplit('/').next().unwrap_or(rel_key)
+        };
+        self.pattern.matches_with(candidate, options)
+    }
+}
+
+impl FromStr for KeyPattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let pattern =
+            Pattern::new(s).with_context(|_| format!("invalid glob pattern {}", s))?;
+        Ok(KeyPattern {
+            pattern,
+            match_path: s.contains('/'),
+        })
+    }
+}
+
+/// Decide which objects to read, using our `include` and `exclude` patterns.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct KeyFilter {
+    /// If present, only read objects matching this pattern.
+    include: Option<KeyPattern>,
+    /// If present, skip objects matching this pattern.
+    exclude: Option<KeyPattern>,
+}
+
+impl KeyFilter {
+    /// Create a new filter.
+    pub(crate) fn new(
+        include: Option<KeyPattern>,
+        exclude: Option<KeyPattern>,
+    ) -> KeyFilter {
+        KeyFilter { include, exclude }
+    }
+
+    /// Should we read the object at `key`, given that we were asked to read
+    /// `prefix`? We match patterns against the part of `key` after the last
+    /// `/` in `prefix`.
+    pub(crate) fn should_read(&self, prefix: &str, key: &str) -> bool {
+        let base_len = prefix.rfind('/').map(|idx| idx + 1).unwrap_or(0);
+        let rel_key = key.get(base_len..).unwrap_or(key);
+        self.include
+            .as_ref()
+            .map_or(true, |pattern| pattern.matches(rel_key))
+            && !self
+                .exclude
+                .as_ref()
+                .map_or(false, |pattern| pattern.matches(rel_key))
+    }
+}
+
+#[test]
+fn filter_keys() {
+    let filter = KeyFilter::new(
+        Some("*.csv.gz".parse().unwrap()),
+        Some("_SUCCESS".parse().unwrap()),
+    );
+    assert!(filter.should_read("exports/", "exports/a.csv.gz"));
+    assert!(filter.should_read("exports/", "exports/2024/a.csv.gz"));
+    assert!(!filter.should_read("exports/", "exports/a.csv"));
+    assert!(!filter.should_read("exports/", "exports/_SUCCESS"));
+
+    let filter = KeyFilter::new(None, Some("_SUCCESS".parse().unwrap()));
+    assert!(filter.should_read("exports/", "exports/a.csv"));
+    assert!(!filter.should_read("exports/", "exports/2024/_SUCCESS"));
+
+    // Patterns containing `/` match the key relative to our directory.
+    let filter = KeyFilter::new(Some("2024/*.csv".parse().unwrap()), None);
+    assert!(filter.should_read("exports/", "exports/2024/a.csv"));
+    assert!(!filter.should_read("exports/", "exports/2023/a.csv"));
+    assert!(!filter.should_read("exports/", "exports/2024/x/a.csv"));
+    assert!(filter.should_read("exports/2024", "exports/2024/a.csv"));
+
+    assert!(KeyFilter::default().should_read("", "a.csv"));
+    assert!("[".parse::<KeyPattern>().is_err());
+}
@@ -21,28 +21,68 @@
 
 use std::result;
 
+#[cfg(feature = "postgres")]
+pub(crate) mod add_columns;
 pub(crate) mod args;
+pub mod clean_temp;
 pub(crate) mod clouds;
+pub(crate) mod compression;
 pub(crate) mod concat;
 pub mod config;
 pub(crate) mod context;
+pub mod coordination;
+pub mod copy;
+#[cfg(any(feature = "abfss", feature = "gs", feature = "s3", feature = "shopify"))]
 pub(crate) mod credentials;
 pub(crate) mod csv_stream;
+#[cfg(feature = "s3")]
+pub(crate) mod decrypt;
+pub mod doctor;
 mod driver_args;
 pub mod drivers;
 pub(crate) mod from_csv_cell;
+#[cfg(feature = "postgres")]
 pub(crate) mod from_json_value;
+#[cfg(any(
+    feature = "abfss",
+    feature = "bigml",
+    feature = "https",
+    feature = "s3",
+    feature = "webdav"
+))]
+pub(crate) mod http_response;
 pub(crate) mod if_exists;
+#[cfg(any(feature = "gs", feature = "s3"))]
+pub(crate) mod key_pattern;
 pub(crate) mod locator;
+#[cfg(any(feature = "gs", feature = "s3"))]
+pub(crate) mod manifest;
+#[cfg(any(feature = "gs", feature = "s3"))]
+pub(crate) mod object_metadata;
 pub(crate) mod parse_error;
+pub(crate) mod partition;
 pub(crate) mod path_or_stdio;
+pub(crate) mod process;
+mod query_param;
+#[cfg(feature = "singer")]
+pub(crate) mod rate_limit;
 pub mod rechunk;
+pub mod recording;
+#[cfg(any(feature = "gs", feature = "s3"))]
+pub(crate) mod retry;
 pub mod schema;
+pub(crate) mod select_columns;
 pub(crate) mod separator;
+pub(crate) mod size_hint;
 mod temporary_storage;
+pub(crate) mod token_bucket;
 pub mod tokio_glue;
 pub(crate) mod transform;
+pub(crate) mod unique_name;
+#[cfg(any(feature = "db2", feature = "hive", feature = "postgres"))]
 mod url_with_hidden_password;
+pub(crate) mod validate;
+pub(crate) mod wkt;
 
 /// Standard error type for this library.
 pub use failure::Error;
@@ -58,12 +98,17 @@ pub use args::{
     Verified,
 };
 pub use context::Context;
-pub use csv_stream::CsvStream;
+pub use csv_stream::{CsvStream, StreamMetadata};
 pub use driver_args::DriverArguments;
 pub use if_exists::IfExists;
 pub use locator::{BoxLocator, DisplayOutputLocators, Locator, UnparsedLocator};
+pub use query_param::QueryParam;
+pub use size_hint::SizeHint;
 pub use temporary_storage::TemporaryStorage;
-pub use tokio_glue::{run_futures_with_runtime, ConsumeWithParallelism};
+pub use tokio_glue::{
+    run_futures_with_runtime, run_futures_with_runtime_options,
+    ConsumeWithParallelism, RuntimeOptions,
+};
 
 /// Definitions included by all the files in this crate.
 ///
@@ -87,6 +132,8 @@ pub(crate) mod common {
     pub(crate) use tokio::{prelude::*, sync::mpsc};
     pub(crate) use url::Url;
 
+    #[cfg(any(feature = "db2", feature = "hive", feature = "postgres"))]
+    pub(crate) use crate::url_with_hidden_password::UrlWithHiddenPassword;
     pub(crate) use crate::{
         args::{
             ArgumentState, DestinationArguments, DestinationArgumentsFeatures,
@@ -94,7 +141,7 @@ pub(crate) mod common {
             Verified,
         },
         context::Context,
-        csv_stream::CsvStream,
+        csv_stream::{CsvStream, StreamMetadata},
         driver_args::DriverArguments,
         if_exists::{IfExists, IfExistsFeatures},
         locator::{
@@ -102,14 +149,15 @@ pub(crate) mod common {
             LocatorStatic,
         },
         path_or_stdio::PathOrStdio,
+        query_param::QueryParam,
         schema::Table,
+        size_hint::SizeHint,
         temporary_storage::TemporaryStorage,
         tokio_glue::{
             async_read_to_end, async_read_to_string, box_stream_once,
             buffer_sync_write_and_copy_to_async, run_futures_with_runtime,
             spawn_blocking, BoxFuture, BoxStream, SendResultExt,
         },
-        url_with_hidden_password::UrlWithHiddenPassword,
         Error, Result, BUFFER_SIZE,
     };
 }
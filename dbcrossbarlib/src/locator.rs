@@ -102,6 +102,18 @@ pub trait Locator: fmt::Debug + fmt::Display + Send + Sync + 'static {
         async { Ok(None) }.boxed()
     }
 
+    /// Estimate how much data `local_data` will return, without reading it.
+    /// This is used to choose parallelism and to decide between different
+    /// ways of copying the data. Sources which can't cheaply estimate their
+    /// size should return `SizeHint::default()`.
+    fn size_hint(
+        &self,
+        _ctx: Context,
+        _source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<SizeHint> {
+        async { Ok(SizeHint::default()) }.boxed()
+    }
+
     /// Should we display the individual output locations?
     fn display_output_locators(&self) -> DisplayOutputLocators {
         DisplayOutputLocators::IfRequested
@@ -175,6 +187,17 @@ fn parse_locator(s: &str, enable_unstable: bool) -> Result<BoxLocator> {
         .ok_or_else(|| format_err!("cannot parse locator: {:?}", s))?;
     let scheme = &cap[0];
 
+    // A single-letter "scheme" followed by a path separator is almost
+    // certainly a Windows drive letter, like `C:\data.csv`.
+    let rest = &s[scheme.len()..];
+    if scheme.len() == 2 && (rest.starts_with('\\') || rest.starts_with('/')) {
+        return Err(format_err!(
+            "{:?} looks like a Windows path, try csv:{} instead",
+            s,
+            s,
+        ));
+    }
+
     // Select an appropriate locator type.
     let driver = find_driver(scheme, enable_unstable)?;
     driver.parse(s)
@@ -183,22 +206,71 @@ fn parse_locator(s: &str, enable_unstable: bool) -> Result<BoxLocator> {
 #[test]
 fn locator_from_str_to_string_roundtrip() {
     let locators = vec![
+        #[cfg(feature = "abfss")]
+        "abfss://data@example.dfs.core.windows.net/my-dir/",
+        "avro-schema:dir/my_table.avsc",
+        #[cfg(feature = "bigquery")]
         "bigquery:my_project:my_dataset.my_table",
+        #[cfg(feature = "bigquery")]
         "bigquery-schema:dir/my_table.json",
+        #[cfg(feature = "bigml")]
         "bigml:dataset",
+        #[cfg(feature = "bigml")]
         "bigml:datasets",
+        #[cfg(feature = "bigml")]
         "bigml:dataset/abc123",
+        #[cfg(feature = "bigml")]
         "bigml:source",
+        #[cfg(feature = "bigml")]
         "bigml:sources",
         "csv:file.csv",
         "csv:dir/",
+        "csv:file.csv?infer_rows=100",
+        #[cfg(feature = "db2")]
+        "db2://sample/DB2INST1.EMPLOYEE",
         "dbcrossbar-schema:file.json",
         "dbcrossbar-ts:file %231 20%25.ts#Type",
+        "dbt-schema:models/sources.yml?source=raw",
+        "exec:./filter.sh --flag 'quoted arg'",
+        "fake:1000",
+        #[cfg(feature = "file")]
+        "file:s3://example/sidecars/",
+        #[cfg(feature = "file")]
+        "file:data/model.bin",
+        "graphql-schema:dir/my_table.graphql",
+        #[cfg(feature = "gs")]
         "gs://example-bucket/tmp/",
+        #[cfg(feature = "hive")]
+        "hive://hiveserver:10000/warehouse.events",
+        #[cfg(feature = "hive")]
+        "hive-sql:dir/my_table.sql?format=parquet&location=s3a://b/t/",
+        #[cfg(feature = "jdbc")]
+        "jdbc:sqlserver://db.example.com;databaseName=sales#dbo.orders",
+        "json-schema:dir/my_table.json",
+        #[cfg(feature = "postgres")]
+        "migration:db/migrations/?layout=sqlx",
+        "mysql-sql:dir/my_table.sql",
+        "null:",
+        "parquet-schema:dir/my_table.parquet",
+        #[cfg(feature = "postgres")]
         "postgres://localhost:5432/db#my_table",
+        #[cfg(feature = "postgres")]
         "postgres-sql:dir/my_table.sql",
+        "proto-schema:dir/my_table.proto",
+        "rust-schema:dir/my_table.rs",
+        #[cfg(feature = "s3")]
         "s3://example/my-dir/",
+        #[cfg(feature = "shopify")]
         "shopify://example.myshopify.com/admin/api/2020-04/orders.json",
+        #[cfg(feature = "singer")]
+        "singer-tap:tap-github --config config.json#commits",
+        #[cfg(feature = "singer")]
+        "singer-target:target-csv --config %2523config.json",
+        "spark-schema:dir/my_table.json",
+        "table-schema:datapackage.json",
+        "ts-schema:dir/my_table.ts",
+        #[cfg(feature = "webdav")]
+        "webdav://dav.example.com/exports/",
     ];
     for locator in locators.into_iter() {
         let parsed: BoxLocator = parse_locator(locator, true).unwrap();
@@ -206,6 +278,14 @@ fn locator_from_str_to_string_roundtrip() {
     }
 }
 
+#[test]
+fn locator_from_str_rejects_windows_drive_paths() {
+    let err = parse_locator(r"C:\data\file.csv", true).unwrap_err();
+    assert!(err.to_string().contains(r"try csv:C:\data\file.csv"));
+    let loc = parse_locator(r"csv:C:\data\file.csv", true).unwrap();
+    assert_eq!(loc.to_string(), r"csv:C:\data\file.csv");
+}
+
 /// A locator which has not yet been parsed.
 ///
 /// This is separate from `BoxLocator` because `BoxLocator` can only be parsed
@@ -240,8 +320,8 @@ pub enum LocatorFeatures {
 
 /// A collection of all the features supported by a given driver. This is
 /// used to automatically verify whether the arguments passed to a driver
-/// are actually supported.
-#[derive(Debug, Copy, Clone)]
+/// are actually supported. The default value is the empty set of features.
+#[derive(Debug, Copy, Clone, Default)]
 pub struct Features {
     pub locator: EnumSet<LocatorFeatures>,
     pub write_schema_if_exists: EnumSet<IfExistsFeatures>,
@@ -251,20 +331,6 @@ pub struct Features {
     pub(crate) _placeholder: (),
 }
 
-impl Features {
-    /// Return the empty set of features.
-    pub(crate) fn empty() -> Self {
-        Features {
-            locator: EnumSet::empty(),
-            write_schema_if_exists: EnumSet::empty(),
-            source_args: EnumSet::empty(),
-            dest_args: EnumSet::empty(),
-            dest_if_exists: EnumSet::empty(),
-            _placeholder: (),
-        }
-    }
-}
-
 impl fmt::Display for Features {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.locator.contains(LocatorFeatures::Schema) {
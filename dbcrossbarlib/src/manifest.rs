@@ -0,0 +1,331 @@
+//! Manifests listing the files we wrote to a cloud bucket.
+//!
+//! We use the same format as Redshift's `COPY ... MANIFEST`, which can load
+//! our output directly. Other tools can use the row counts and checksums to
+//! check that they've seen every file.
+
+use serde::Serialize;
+use std::{
+    mem,
+    sync::{Arc, Mutex},
+};
+
+use crate::common::*;
+use crate::temporary_storage::TemporaryStorage;
+
+/// Choose a file name for our manifest. When appending, we add a unique suffix
+/// so that we never replace an existing manifest.
+pub(crate) fn manifest_file_name(if_exists: &IfExists) -> String {
+    match if_exists {
+        IfExists::Append => {
+            format!("manifest_{}.json", TemporaryStorage::random_tag())
+        }
+        _ => "manifest.json".to_owned(),
+    }
+}
+
+/// Is `url` a manifest written by `manifest_file_name`? We skip these when
+/// reading a directory.
+pub(crate) fn is_manifest_url(url: &str) -> bool {
+    let file_name = url.rsplit('/').next().unwrap_or(url);
+    file_name == "manifest.json"
+        || (file_name.starts_with("manifest_") && file_name.ends_with(".json"))
+}
+
+#[test]
+fn manifest_file_names_are_recognized() {
+    assert_eq!(manifest_file_name(&IfExists::Overwrite), "manifest.json");
+    let appended = manifest_file_name(&IfExists::Append);
+    assert_ne!(appended, manifest_file_name(&IfExists::Append));
+    assert!(is_manifest_url(&format!("s3://bucket/dir/{}", appended)));
+    assert!(is_manifest_url("gs://bucket/dir/manifest.json"));
+    assert!(!is_manifest_url("gs://bucket/dir/manifest.csv"));
+    assert!(!is_manifest_url("gs://bucket/manifest.json/data.csv"));
+}
+
+/// A manifest listing the files we wrote.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Manifest {
+    /// The files we wrote, sorted by URL.
+    entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Serialize this manifest as JSON.
+    pub(crate) fn to_json_bytes(&self) -> Result<BytesMut> {
+        let mut json = serde_json::to_vec_pretty(self)?;
+        json.push(b'\n');
+        Ok(BytesMut::from(&json[..]))
+    }
+}
+
+/// A single file in a manifest.
+#[derive(Debug, Serialize)]
+pub(crate) struct ManifestEntry {
+    /// The URL of the file.
+    url: String,
+    /// Should Redshift fail if this file is missing? Always true.
+    mandatory: bool,
+    /// Information about the file.
+    meta: ManifestMeta,
+}
+
+/// Information about a file in a manifest.
+#[derive(Debug, Serialize)]
+struct ManifestMeta {
+    /// The size of the file, in bytes, after any compression.
+    content_length: u64,
+    /// The number of CSV rows in the file, not counting the header.
+    record_count: u64,
+    /// The MD5 checksum of the file, in hexadecimal.
+    md5: String,
+}
+
+#[test]
+fn manifest_serializes_like_redshift() {
+    let manifest = Manifest {
+        entries: vec![ManifestEntry {
+            url: "s3://bucket/dir/data.csv".to_owned(),
+            mandatory: true,
+            meta: ManifestMeta {
+                content_length: 12,
+                record_count: 1,
+                md5: "d41d8cd98f00b204e9800998ecf8427e".to_owned(),
+            },
+        }],
+    };
+    let json = serde_json::from_slice::<serde_json::Value>(
+        &manifest.to_json_bytes().unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "entries": [{
+                "url": "s3://bucket/dir/data.csv",
+                "mandatory": true,
+                "meta": {
+                    "content_length": 12,
+                    "record_count": 1,
+                    "md5": "d41d8cd98f00b204e9800998ecf8427e",
+                },
+            }],
+        }),
+    );
+}
+
+/// Counts the rows in CSV data, one chunk at a time.
+#[derive(Debug)]
+struct RowCounter {
+    /// The number of line breaks we've seen outside of quoted fields.
+    lines: u64,
+    /// Are we inside a quoted field?
+    in_quotes: bool,
+    /// Are we at the start of a line?
+    at_line_start: bool,
+}
+
+impl Default for RowCounter {
+    fn default() -> Self {
+        RowCounter {
+            lines: 0,
+            in_quotes: false,
+            at_line_start: true,
+        }
+    }
+}
+
+impl RowCounter {
+    /// Count the line breaks in `data`.
+    fn consume(&mut self, data: &[u8]) {
+        for &b in data {
+            // An escaped `""` toggles `in_quotes` twice, which is what we want.
+            match b {
+                b'"' => self.in_quotes = !self.in_quotes,
+                b'\n' if !self.in_quotes => self.lines += 1,
+                _ => {}
+            }
+        }
+        if let Some(&last) = data.last() {
+            self.at_line_start = last == b'\n' && !self.in_quotes;
+        }
+    }
+
+    /// The number of rows we've seen, not counting the header.
+    fn rows(&self) -> u64 {
+        let lines = if self.at_line_start {
+            self.lines
+        } else {
+            // Count a final line with no line break.
+            self.lines + 1
+        };
+        lines.saturating_sub(1)
+    }
+}
+
+#[test]
+fn row_counter_handles_quoted_newlines() {
+    let examples: &[(&[&str], u64)] = &[
+        (&[], 0),
+        (&["a,b\n"], 0),
+        (&["a,b\n1,2\n3,4\n"], 2),
+        (&["a,b\n1,2\n3,4"], 2),
+        (&["a,b\r\n1,\"x\ny\"\r\n"], 1),
+        (&["a,b\n1,\"x", "\"\"\n\"\n2,", "3\n"], 2),
+    ];
+    for &(chunks, expected) in examples {
+        let mut counter = RowCounter::default();
+        for chunk in chunks {
+            counter.consume(chunk.as_bytes());
+        }
+        assert_eq!(counter.rows(), expected, "{:?}", chunks);
+    }
+}
+
+/// What we've learned about a file so far.
+struct TrackerState {
+    /// Counts the rows in the uncompressed data.
+    rows: RowCounter,
+    /// Checksums the data we actually wrote.
+    md5: md5::Context,
+    /// The number of bytes we actually wrote.
+    content_length: u64,
+}
+
+impl Default for TrackerState {
+    fn default() -> Self {
+        TrackerState {
+            rows: RowCounter::default(),
+            md5: md5::Context::new(),
+            content_length: 0,
+        }
+    }
+}
+
+/// Collects the information we need for a `ManifestEntry` as a file's data
+/// passes through, if we're writing a manifest.
+pub(crate) struct FileTracker {
+    state: Option<Arc<Mutex<TrackerState>>>,
+}
+
+impl FileTracker {
+    /// Create a new tracker. If `enabled` is false, we pass through all data
+    /// untouched.
+    pub(crate) fn new(enabled: bool) -> FileTracker {
+        FileTracker {
+            state: if enabled {
+                Some(Arc::new(Mutex::new(TrackerState::default())))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Count the rows in `data`, which must be uncompressed CSV data.
+    pub(crate) fn count_rows(&self, data: BoxStream<BytesMut>) -> BoxStream<BytesMut> {
+        match &self.state {
+            Some(state) => {
+                let state = state.clone();
+                data.inspect_ok(move |bytes| {
+                    let mut state = state.lock().expect("lock poisoned");
+                    state.rows.consume(&bytes[..]);
+                })
+                .boxed()
+            }
+            None => data,
+        }
+    }
+
+    /// Checksum `data`, which must be exactly what we write to the file.
+    pub(crate) fn checksum(&self, data: BoxStream<BytesMut>) -> BoxStream<BytesMut> {
+        match &self.state {
+            Some(state) => {
+                let state = state.clone();
+                data.inspect_ok(move |bytes| {
+                    let mut state = state.lock().expect("lock poisoned");
+                    state.md5.consume(&bytes[..]);
+                    state.content_length += bytes.len() as u64;
+                })
+                .boxed()
+            }
+            None => data,
+        }
+    }
+
+    /// Build a manifest entry for the file at `url`, once all its data has
+    /// been written.
+    pub(crate) fn finish(self, url: &Url) -> Option<ManifestEntry> {
+        let state = self.state?;
+        let mut state = state.lock().expect("lock poisoned");
+        let md5 = mem::replace(&mut state.md5, md5::Context::new());
+        Some(ManifestEntry {
+            url: url.as_str().to_owned(),
+            mandatory: true,
+            meta: ManifestMeta {
+                content_length: state.content_length,
+                record_count: state.rows.rows(),
+                md5: format!("{:x}", md5.compute()),
+            },
+        })
+    }
+}
+
+/// Wait until every file in `written` has been written, and then pass a
+/// manifest listing them to `write_manifest`, if it was specified. The locator
+/// returned by `write_manifest` is added to the end of our output.
+///
+/// We still write our files in parallel, because our final future isn't
+/// created until the caller has started writing every file, and it waits for
+/// those files to finish.
+pub(crate) fn write_manifest_when_done<F>(
+    written: BoxStream<BoxFuture<(BoxLocator, Option<ManifestEntry>)>>,
+    write_manifest: Option<F>,
+) -> BoxStream<BoxFuture<BoxLocator>>
+where
+    F: FnOnce(Manifest) -> BoxFuture<BoxLocator> + Send + 'static,
+{
+    let write_manifest = match write_manifest {
+        Some(write_manifest) => write_manifest,
+        None => {
+            return written
+                .map_ok(|fut| fut.map_ok(|(locator, _)| locator).boxed())
+                .boxed()
+        }
+    };
+
+    // Each file sends its entry to `receiver` when it's done. The receiver
+    // will see the end of the stream once every sender has been dropped.
+    let (sender, mut receiver) = mpsc::unbounded_channel::<ManifestEntry>();
+    let sender = Arc::new(Mutex::new(Some(sender)));
+    let file_sender = sender.clone();
+    let files = written.map_ok(move |fut| {
+        let sender = file_sender.lock().expect("lock poisoned").clone();
+        async move {
+            let (locator, entry) = fut.await?;
+            if let (Some(sender), Some(entry)) = (sender, entry) {
+                sender
+                    .send(entry)
+                    .map_err(|_| format_err!("could not record manifest entry"))?;
+            }
+            Ok(locator)
+        }
+        .boxed()
+    });
+
+    let manifest = stream::once(async move {
+        Ok(async move {
+            // Every file has been started, so drop our original sender and
+            // wait for the files to finish.
+            sender.lock().expect("lock poisoned").take();
+            let mut entries = vec![];
+            while let Some(entry) = receiver.recv().await {
+                entries.push(entry);
+            }
+            entries.sort_by(|a, b| a.url.cmp(&b.url));
+            write_manifest(Manifest { entries }).await
+        }
+        .boxed())
+    });
+
+    files.chain(manifest).boxed()
+}
@@ -0,0 +1,153 @@
+//! HTTP headers and custom metadata for the objects we write to cloud storage.
+
+use std::{collections::HashSet, str::FromStr};
+
+use crate::common::*;
+
+/// Custom metadata to attach to every object we write, parsed from a string
+/// like `source=etl,owner=data`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct CustomMetadata {
+    /// Our metadata, in the order it was specified.
+    entries: Vec<(String, String)>,
+}
+
+impl FromStr for CustomMetadata {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut entries = vec![];
+        let mut seen = HashSet::new();
+        for entry in s.split(',') {
+            let mut parts = entry.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| {
+                    format_err!("expected metadata {:?} to look like KEY=VALUE", entry)
+                })?
+                .trim();
+            // Keys are sent as part of S3 header names, so keep them simple.
+            let valid_key = !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid_key {
+                return Err(format_err!(
+                    "metadata key {:?} may only contain letters, digits, '-' and '_'",
+                    key,
+                ));
+            }
+            check_header_value(value)?;
+            if !seen.insert(key.to_ascii_lowercase()) {
+                return Err(format_err!(
+                    "metadata {:?} specified more than once",
+                    key
+                ));
+            }
+            entries.push((key.to_owned(), value.to_owned()));
+        }
+        Ok(CustomMetadata { entries })
+    }
+}
+
+/// Metadata to set on every object we write.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ObjectMetadata {
+    /// The `Content-Type` of our objects, like `text/csv`.
+    content_type: Option<String>,
+    /// The `Content-Encoding` of our objects, like `gzip`.
+    content_encoding: Option<String>,
+    /// Custom metadata.
+    custom: CustomMetadata,
+}
+
+impl ObjectMetadata {
+    /// Create new object metadata, checking that `content_type` and
+    /// `content_encoding` can be sent as HTTP headers.
+    pub(crate) fn new(
+        content_type: Option<String>,
+        content_encoding: Option<String>,
+        custom: Option<CustomMetadata>,
+    ) -> Result<ObjectMetadata> {
+        for value in content_type.iter().chain(content_encoding.iter()) {
+            if value.is_empty() {
+                return Err(format_err!(
+                    "content_type and content_encoding may not be empty"
+                ));
+            }
+            check_header_value(value)?;
+        }
+        Ok(ObjectMetadata {
+            content_type,
+            content_encoding,
+            custom: custom.unwrap_or_default(),
+        })
+    }
+
+    /// The `Content-Type` of our objects, if any.
+    pub(crate) fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// The `Content-Encoding` of our objects, if any.
+    pub(crate) fn content_encoding(&self) -> Option<&str> {
+        self.content_encoding.as_deref()
+    }
+
+    /// Custom metadata keys and values.
+    pub(crate) fn custom(&self) -> &[(String, String)] {
+        &self.custom.entries
+    }
+}
+
+/// Make sure that `value` only contains printable ASCII characters, so that
+/// we can send it in an HTTP header.
+fn check_header_value(value: &str) -> Result<()> {
+    if value.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+        Ok(())
+    } else {
+        Err(format_err!(
+            "metadata value {:?} may only contain printable ASCII characters",
+            value,
+        ))
+    }
+}
+
+#[test]
+fn parse_object_metadata() {
+    let custom = "source=etl, owner=data,note=a b"
+        .parse::<CustomMetadata>()
+        .unwrap();
+    let metadata = ObjectMetadata::new(
+        Some("text/csv".to_owned()),
+        Some("gzip".to_owned()),
+        Some(custom),
+    )
+    .unwrap();
+    assert_eq!(metadata.content_type(), Some("text/csv"));
+    assert_eq!(metadata.content_encoding(), Some("gzip"));
+    assert_eq!(
+        metadata.custom(),
+        &[
+            ("source".to_owned(), "etl".to_owned()),
+            ("owner".to_owned(), "data".to_owned()),
+            ("note".to_owned(), "a b".to_owned()),
+        ],
+    );
+    assert_eq!(
+        ObjectMetadata::new(None, None, None).unwrap(),
+        ObjectMetadata::default(),
+    );
+    assert!(ObjectMetadata::new(Some("".to_owned()), None, None).is_err());
+    assert!(ObjectMetadata::new(None, Some("gzip\r\nx: y".to_owned()), None).is_err());
+
+    let invalid = &["", "source", "=etl", "a.b=c", "a=1,A=2", "a=caf\u{e9}"];
+    for &s in invalid {
+        assert!(
+            s.parse::<CustomMetadata>().is_err(),
+            "should not parse {:?}",
+            s
+        );
+    }
+}
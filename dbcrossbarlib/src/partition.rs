@@ -0,0 +1,331 @@
+//! Splitting CSV data into Hive-style partitions, like
+//! `event_date=2024-01-01/part-0001.csv`.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{File, OpenOptions},
+};
+use tempfile::{NamedTempFile, TempPath};
+
+use crate::common::*;
+use crate::concat::concatenate_csv_streams;
+use crate::rechunk::ChunkLimits;
+use crate::tokio_glue::{
+    copy_reader_to_stream, spawn_blocking_stage, SyncStreamReader,
+};
+
+/// The directory name Hive uses for `NULL` partition values.
+const NULL_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// The maximum number of partition files we keep open for writing at once. If
+/// we need more, we close the least recently opened file, and reopen it later
+/// if necessary.
+const MAX_OPEN_WRITERS: usize = 64;
+
+/// Split `streams` into one or more streams per distinct value of `column`.
+/// The output streams are named like `event_date=2024-01-01/part-0001`, and
+/// `column` itself is left out of the data, as Hive expects. A new part is
+/// started each time a part reaches `limits`.
+///
+/// Because rows for different partitions may be mixed together, we need to
+/// read all our input before we can return any streams. We spool each part to
+/// a temporary file, which is deleted once we finish reading it. Finished
+/// parts are closed until we need them, so that we don't run out of file
+/// descriptors.
+pub(crate) async fn partition_csv_streams(
+    ctx: Context,
+    column: String,
+    limits: ChunkLimits,
+    streams: BoxStream<CsvStream>,
+) -> Result<BoxStream<CsvStream>> {
+    let ctx = ctx.child(o!("streams_transform" => "partition_csv_streams"));
+    let input_csv_stream = concatenate_csv_streams(ctx.clone(), streams)?;
+    let csv_rdr = SyncStreamReader::new(ctx.clone(), input_csv_stream.data);
+    let worker_ctx = ctx.clone();
+    let parts = spawn_blocking_stage(move || {
+        write_partitions(&worker_ctx, &column, limits, csv_rdr)
+    })
+    .await?;
+
+    let csv_streams = stream::iter(parts).then(move |(name, path)| {
+        let ctx = ctx.child(o!("stream" => name.clone()));
+        async move {
+            let file = tokio::fs::File::open(&path)
+                .await
+                .with_context(|_| format!("cannot open partition {}", name))?;
+            let data = copy_reader_to_stream(ctx, file)?
+                // Keep our temporary file until we've read it.
+                .map(move |bytes| {
+                    let _path = &path;
+                    bytes
+                })
+                .boxed();
+            Ok(CsvStream {
+                name,
+                metadata: StreamMetadata::default(),
+                data,
+            })
+        }
+    });
+    Ok(csv_streams.boxed())
+}
+
+/// A partition part which we're currently writing.
+struct Part {
+    /// The name of the output stream for this part.
+    name: String,
+    /// The temporary file containing our CSV data.
+    path: TempPath,
+    /// Where we write our CSV data, if our file is currently open.
+    wtr: Option<csv::Writer<File>>,
+    /// Approximately how many bytes have we written?
+    bytes_written: usize,
+    /// How many rows have we written, not counting the header?
+    rows_written: usize,
+}
+
+/// A partition, and the part we're currently writing to it.
+struct Partition {
+    /// The directory name for this partition, like `event_date=2024-01-01`.
+    dir_name: String,
+    /// How many parts have we started?
+    part_count: usize,
+    /// The part we're currently writing, if any.
+    part: Option<Part>,
+}
+
+impl Partition {
+    /// Start a new part, writing `header` to it.
+    fn start_part(&mut self, header: &csv::ByteRecord) -> Result<Part> {
+        self.part_count += 1;
+        let name = format!("{}/part-{:04}", self.dir_name, self.part_count);
+        let (file, path) = NamedTempFile::new()
+            .context("cannot create temporary file")?
+            .into_parts();
+        let mut wtr = csv::Writer::from_writer(file);
+        wtr.write_byte_record(header)
+            .context("cannot write partition header")?;
+        Ok(Part {
+            name,
+            path,
+            wtr: Some(wtr),
+            bytes_written: 0,
+            rows_written: 0,
+        })
+    }
+}
+
+impl Part {
+    /// Get our writer, reopening our file if necessary.
+    fn writer(&mut self) -> Result<&mut csv::Writer<File>> {
+        if self.wtr.is_none() {
+            let file = OpenOptions::new()
+                .append(true)
+                .open(&self.path)
+                .with_context(|_| format!("cannot reopen partition {}", self.name))?;
+            self.wtr = Some(
+                csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(file),
+            );
+        }
+        Ok(self.wtr.as_mut().expect("writer should be open"))
+    }
+
+    /// Flush and close our file, if it's open. Returns true if we closed it.
+    fn close(&mut self) -> Result<bool> {
+        match self.wtr.take() {
+            Some(wtr) => {
+                wtr.into_inner()
+                    .map_err(|err| format_err!("cannot flush partition: {}", err))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Close our file and return our name and the path to our data.
+    fn finish(mut self) -> Result<(String, TempPath)> {
+        self.close()?;
+        Ok((self.name, self.path))
+    }
+}
+
+/// Read CSV data from `rdr`, and write it to temporary files partitioned by
+/// `column`. Returns the stream name and temporary file for each part.
+fn write_partitions<R: Read>(
+    ctx: &Context,
+    column: &str,
+    limits: ChunkLimits,
+    rdr: R,
+) -> Result<Vec<(String, TempPath)>> {
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let header = rdr
+        .byte_headers()
+        .context("cannot read CSV header")?
+        .to_owned();
+    let column_idx = header
+        .iter()
+        .position(|name| name == column.as_bytes())
+        .ok_or_else(|| {
+            format_err!("cannot partition by unknown column {:?}", column)
+        })?;
+    let part_header = without_field(&header, column_idx);
+
+    let mut partitions = HashMap::<Vec<u8>, Partition>::new();
+    let mut finished = vec![];
+    // The partitions whose parts we opened, oldest first, which may include
+    // partitions whose parts have since been closed.
+    let mut opened = VecDeque::<Vec<u8>>::new();
+    let mut open_count = 0;
+    let mut row = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut row).context("cannot read row")? {
+        let value = &row[column_idx];
+        if !partitions.contains_key(value) {
+            let dir_name = partition_dir_name(column, value);
+            trace!(ctx.log(), "starting partition {}", dir_name);
+            partitions.insert(
+                value.to_owned(),
+                Partition {
+                    dir_name,
+                    part_count: 0,
+                    part: None,
+                },
+            );
+        }
+
+        // Make sure we have an open part, closing other parts if we have too
+        // many files open.
+        let needs_open = partitions[value]
+            .part
+            .as_ref()
+            .map_or(true, |part| part.wtr.is_none());
+        if needs_open {
+            while open_count >= MAX_OPEN_WRITERS {
+                let oldest = opened.pop_front().expect("open parts should be queued");
+                let part = partitions
+                    .get_mut(&oldest)
+                    .and_then(|partition| partition.part.as_mut());
+                if let Some(part) = part {
+                    if part.close()? {
+                        open_count -= 1;
+                    }
+                }
+            }
+            opened.push_back(value.to_owned());
+            open_count += 1;
+        }
+        let partition = partitions.get_mut(value).expect("partition should exist");
+        let mut part = match partition.part.take() {
+            Some(part) => part,
+            None => partition.start_part(&part_header)?,
+        };
+
+        let out = without_field(&row, column_idx);
+        part.writer()?
+            .write_byte_record(&out)
+            .context("cannot write row")?;
+        part.bytes_written += out.as_slice().len() + out.len();
+        part.rows_written += 1;
+
+        if limits.is_full(part.bytes_written, part.rows_written) {
+            finished.push(part.finish()?);
+            open_count -= 1;
+        } else {
+            partition.part = Some(part);
+        }
+    }
+
+    for (_, partition) in partitions {
+        if let Some(part) = partition.part {
+            finished.push(part.finish()?);
+        }
+    }
+    finished.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+    debug!(ctx.log(), "wrote {} partition parts", finished.len());
+    Ok(finished)
+}
+
+/// Return a copy of `record` without the field at `idx`.
+fn without_field(record: &csv::ByteRecord, idx: usize) -> csv::ByteRecord {
+    record
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != idx)
+        .map(|(_, field)| field)
+        .collect()
+}
+
+/// The directory name to use for rows with `value` in `column`. We
+/// percent-encode anything that might be unsafe in a path or URL.
+fn partition_dir_name(column: &str, value: &[u8]) -> String {
+    if value.is_empty() {
+        return format!("{}={}", column, NULL_PARTITION);
+    }
+    let mut escaped = String::with_capacity(value.len());
+    for (i, &b) in value.iter().enumerate() {
+        // Don't allow `.` at the start, so we never produce `.` or `..`.
+        if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || (b == b'.' && i > 0)
+        {
+            escaped.push(char::from(b));
+        } else {
+            escaped.push_str(&format!("%{:02X}", b));
+        }
+    }
+    format!("{}={}", column, escaped)
+}
+
+#[test]
+fn partition_dir_names_are_escaped() {
+    assert_eq!(
+        partition_dir_name("event_date", b"2024-01-01"),
+        "event_date=2024-01-01",
+    );
+    assert_eq!(partition_dir_name("v", b""), "v=__HIVE_DEFAULT_PARTITION__");
+    assert_eq!(partition_dir_name("v", b"a/b c"), "v=a%2Fb%20c");
+    assert_eq!(partition_dir_name("v", b".."), "v=%2E.");
+    assert_eq!(partition_dir_name("v", b"1.5"), "v=1.5");
+}
+
+#[test]
+fn write_partitions_by_column() {
+    let (ctx, _worker_fut) = Context::create_for_test("write_partitions");
+    let input = b"id,day\n1,mon\n2,tue\n3,mon\n4,\n5,mon\n";
+    let limits = ChunkLimits::from_driver_args(None, Some(2)).unwrap();
+    let parts = write_partitions(&ctx, "day", limits, &input[..]).unwrap();
+    let parts = parts
+        .into_iter()
+        .map(|(name, path)| (name, std::fs::read_to_string(&path).unwrap()))
+        .collect::<Vec<_>>();
+    let expected = vec![
+        ("day=__HIVE_DEFAULT_PARTITION__/part-0001", "id\n4\n"),
+        ("day=mon/part-0001", "id\n1\n3\n"),
+        ("day=mon/part-0002", "id\n5\n"),
+        ("day=tue/part-0001", "id\n2\n"),
+    ];
+    assert_eq!(parts.len(), expected.len());
+    for ((name, data), (expected_name, expected_data)) in parts.iter().zip(expected) {
+        assert_eq!(name, expected_name);
+        assert_eq!(data, expected_data);
+    }
+
+    assert!(write_partitions(&ctx, "nope", limits, &input[..]).is_err());
+}
+
+#[test]
+fn write_partitions_with_many_values() {
+    let (ctx, _worker_fut) = Context::create_for_test("write_partitions");
+    let count = 3 * MAX_OPEN_WRITERS;
+    let mut input = "id,v\n".to_owned();
+    for round in 0..2 {
+        for v in 0..count {
+            input.push_str(&format!("{},{}\n", round, v));
+        }
+    }
+    let limits = ChunkLimits::from_driver_args(None, None).unwrap();
+    let parts = write_partitions(&ctx, "v", limits, input.as_bytes()).unwrap();
+    assert_eq!(parts.len(), count);
+    for (_, path) in &parts {
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "id\n0\n1\n");
+    }
+}
@@ -1,8 +1,9 @@
 //! Support for working with either files or standard I/O.
 
 use std::{
+    borrow::Cow,
     fmt,
-    path::{Path, PathBuf},
+    path::{self, Path, PathBuf},
     str::FromStr,
 };
 use tokio::{fs as tokio_fs, io as tokio_io};
@@ -53,7 +54,7 @@ impl PathOrStdio {
         match self {
             PathOrStdio::Path(p) => {
                 let p = p.to_owned();
-                let f = tokio_fs::File::open(p.clone())
+                let f = tokio_fs::File::open(long_path(&p)?)
                     .await
                     .with_context(|_| format!("error opening {}", p.display()))?;
                 Ok(Box::new(f) as Box<dyn AsyncRead + Send + Unpin + 'static>)
@@ -74,7 +75,7 @@ impl PathOrStdio {
                 let p = p.to_owned();
                 let f = if_exists
                     .to_async_open_options_no_append()?
-                    .open(p.clone())
+                    .open(long_path(&p)?)
                     .await
                     .with_context(|_| format!("error opening {}", p.display()))?;
                 Ok(Box::new(f) as Box<dyn AsyncWrite + Send + Unpin + 'static>)
@@ -108,3 +109,121 @@ impl FromStr for PathOrStdio {
         }
     }
 }
+
+/// Does `path` end with a path separator, indicating that it's a directory?
+///
+/// On Windows, we accept both `/` and `\`.
+pub(crate) fn ends_with_separator(path: &Path) -> bool {
+    path.to_string_lossy()
+        .chars()
+        .last()
+        .map(path::is_separator)
+        .unwrap_or(false)
+}
+
+#[test]
+fn ends_with_separator_detects_directories() {
+    assert!(ends_with_separator(Path::new("dir/")));
+    assert!(!ends_with_separator(Path::new("dir/file.csv")));
+    assert!(!ends_with_separator(Path::new("")));
+    #[cfg(windows)]
+    assert!(ends_with_separator(Path::new(r"C:\dir\")));
+}
+
+/// Convert `path` to a string using `/` as a separator on all platforms. This
+/// is useful when we want to use relative paths as stream names.
+pub(crate) fn to_slash_lossy(path: &Path) -> Cow<'_, str> {
+    let s = path.to_string_lossy();
+    if cfg!(windows) && s.contains('\\') {
+        Cow::Owned(s.replace('\\', "/"))
+    } else {
+        s
+    }
+}
+
+/// The longest path which Windows can open without a `\\?\` prefix.
+#[cfg(windows)]
+const MAX_PATH: usize = 260;
+
+/// Convert `path` to a form that can be opened even if it's longer than the
+/// Windows `MAX_PATH` limit.
+///
+/// On Windows, we make long paths absolute, and add a `\\?\` prefix. Because
+/// this prefix turns off all further path processing, we resolve `.` and `..`
+/// ourselves, and convert any `/` separators to `\`. On other platforms, or
+/// for short paths, we return `path` unchanged.
+#[cfg(windows)]
+pub(crate) fn long_path(path: &Path) -> Result<PathBuf> {
+    use std::{
+        env,
+        ffi::{OsStr, OsString},
+        path::{Component, Prefix},
+    };
+
+    let abs_path = if path.is_absolute() {
+        path.to_owned()
+    } else {
+        env::current_dir()
+            .context("cannot get current directory")?
+            .join(path)
+    };
+    if abs_path.as_os_str().len() < MAX_PATH {
+        return Ok(path.to_owned());
+    }
+
+    let mut prefix = None;
+    let mut has_root = false;
+    let mut parts: Vec<&OsStr> = vec![];
+    for component in abs_path.components() {
+        match component {
+            Component::Prefix(p) => prefix = Some(p.kind()),
+            Component::RootDir => has_root = true,
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::Normal(part) => parts.push(part),
+        }
+    }
+    let mut long = match prefix {
+        Some(Prefix::Disk(drive)) if has_root => {
+            OsString::from(format!(r"\\?\{}:", drive as char))
+        }
+        Some(Prefix::UNC(server, share)) => {
+            let mut long = OsString::from(r"\\?\UNC\");
+            long.push(server);
+            long.push(r"\");
+            long.push(share);
+            long
+        }
+        // Already verbatim, or something we don't know how to handle.
+        _ => return Ok(path.to_owned()),
+    };
+    for part in parts {
+        long.push(r"\");
+        long.push(part);
+    }
+    Ok(PathBuf::from(long))
+}
+
+/// Convert `path` to a form that can be opened even if it's longer than the
+/// Windows `MAX_PATH` limit. On this platform, this does nothing.
+#[cfg(not(windows))]
+pub(crate) fn long_path(path: &Path) -> Result<PathBuf> {
+    Ok(path.to_owned())
+}
+
+#[test]
+#[cfg(windows)]
+fn long_path_adds_verbatim_prefix() {
+    let dir = "d".repeat(200);
+    let path = format!(r"C:\{}\.\x\..\{}/file.csv", dir, dir);
+    assert_eq!(
+        long_path(Path::new(&path)).unwrap(),
+        PathBuf::from(format!(r"\\?\C:\{}\{}\file.csv", dir, dir)),
+    );
+    assert_eq!(
+        long_path(Path::new(r"C:\short.csv")).unwrap(),
+        PathBuf::from(r"C:\short.csv"),
+    );
+}
@@ -0,0 +1,221 @@
+//! Monitoring child processes.
+//!
+//! We run a number of external tools, such as `hdfs` and `gpg`. If one of
+//! these hangs, we'd rather find out promptly than wait forever, and if it
+//! fails, we'd like to know why.
+
+use std::{
+    env,
+    process::ExitStatus,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::BufReader,
+    process::{Child, ChildStderr},
+    task::JoinHandle,
+    time::timeout,
+};
+
+use crate::common::*;
+
+#[cfg(any(feature = "db2", feature = "hive", feature = "jdbc"))]
+mod stdout;
+#[cfg(any(feature = "db2", feature = "hive", feature = "jdbc", feature = "s3"))]
+pub(crate) mod watched_reader;
+
+/// How long we'll wait for a child process to produce output before we decide
+/// that it has hung, unless overridden by `DBCROSSBAR_NO_OUTPUT_TIMEOUT`.
+const DEFAULT_NO_OUTPUT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How often we check whether a child process has hung.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long we'll wait for a child process to produce output, or `None` if we
+/// should wait forever.
+///
+/// This can be set using `DBCROSSBAR_NO_OUTPUT_TIMEOUT`, in seconds. A value of
+/// `0` disables the timeout.
+fn no_output_timeout() -> Result<Option<Duration>> {
+    match env::var("DBCROSSBAR_NO_OUTPUT_TIMEOUT") {
+        Ok(secs) => {
+            let secs = secs.parse::<u64>().with_context(|_| {
+                format!("could not parse DBCROSSBAR_NO_OUTPUT_TIMEOUT={:?}", secs)
+            })?;
+            if secs == 0 {
+                Ok(None)
+            } else {
+                Ok(Some(Duration::from_secs(secs)))
+            }
+        }
+        Err(env::VarError::NotPresent) => Ok(Some(DEFAULT_NO_OUTPUT_TIMEOUT)),
+        Err(err) => Err(format_err!("DBCROSSBAR_NO_OUTPUT_TIMEOUT: {}", err)),
+    }
+}
+
+/// Keeps track of when a `WatchedReader` started waiting for data that hasn't
+/// arrived yet.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OutputWatch {
+    waiting_since: Arc<Mutex<Option<Instant>>>,
+}
+
+impl OutputWatch {
+    /// How long have we been waiting for output? Returns `None` if we're not
+    /// currently waiting.
+    ///
+    /// This only counts time spent waiting on the child process, and not time
+    /// spent waiting for our own consumers, so a slow destination will never
+    /// cause a timeout.
+    fn waiting_for(&self) -> Option<Duration> {
+        let waiting_since = self.waiting_since.lock().expect("lock poisoned");
+        waiting_since.map(|since| since.elapsed())
+    }
+
+    /// Note that the child process is still doing something, even if it isn't
+    /// producing output yet.
+    fn touch(&self) {
+        let mut waiting_since = self.waiting_since.lock().expect("lock poisoned");
+        if waiting_since.is_some() {
+            *waiting_since = Some(Instant::now());
+        }
+    }
+}
+
+/// A child process which will be killed if we drop it before it exits. This
+/// ensures that we clean up child processes when a copy fails or is cancelled.
+struct KillOnDrop {
+    child: Child,
+    exited: bool,
+}
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        if !self.exited {
+            // This may fail if the process has already exited on its own.
+            let _ = self.child.kill();
+        }
+    }
+}
+
+/// Wait for `child` to exit, and return an error if it fails.
+///
+/// If the child's standard error was piped, we copy it to our log, and we
+/// include the last line in any error message. If `watch` is specified, we
+/// kill the child if we spend too long waiting for it to produce output.
+pub(crate) async fn wait_for_process(
+    ctx: &Context,
+    name: &str,
+    mut child: Child,
+    watch: Option<OutputWatch>,
+) -> Result<()> {
+    let no_output_timeout = no_output_timeout()?;
+    let stderr_task = child
+        .stderr
+        .take()
+        .map(|stderr| log_stderr(ctx, name, stderr, watch.clone()));
+    let mut guard = KillOnDrop {
+        child,
+        exited: false,
+    };
+
+    // Wait for our child, checking periodically to see whether it's stuck.
+    let status = loop {
+        match timeout(CHECK_INTERVAL, &mut guard.child).await {
+            Ok(status) => {
+                guard.exited = true;
+                break status
+                    .with_context(|_| format!("error waiting for {}", name))?;
+            }
+            Err(_) => {
+                let waiting_for = watch.as_ref().and_then(|w| w.waiting_for());
+                match (waiting_for, no_output_timeout) {
+                    (Some(waiting_for), Some(limit)) if waiting_for > limit => {
+                        error!(
+                            ctx.log(),
+                            "{} produced no output for {}s, killing it",
+                            name,
+                            waiting_for.as_secs(),
+                        );
+                        // Dropping `guard` will kill the child.
+                        return Err(format_err!(
+                            "{} produced no output for {} seconds, so we killed it \
+                             (set DBCROSSBAR_NO_OUTPUT_TIMEOUT to change this)",
+                            name,
+                            waiting_for.as_secs(),
+                        ));
+                    }
+                    _ => trace!(ctx.log(), "{} is still running", name),
+                }
+            }
+        }
+    };
+
+    // Collect the last line of standard error, if we captured it.
+    let last_stderr_line = match stderr_task {
+        Some(task) => task.await.context("error joining stderr task")?,
+        None => None,
+    };
+    check_status(name, status, last_stderr_line)
+}
+
+/// Turn an exit status into an error, if necessary.
+fn check_status(
+    name: &str,
+    status: ExitStatus,
+    last_stderr_line: Option<String>,
+) -> Result<()> {
+    match (status.success(), last_stderr_line) {
+        (true, _) => Ok(()),
+        (false, Some(line)) => {
+            Err(format_err!("{} failed with {}: {}", name, status, line))
+        }
+        (false, None) => Err(format_err!("{} failed with {}", name, status)),
+    }
+}
+
+/// Copy lines from a child's standard error to our log, returning the last
+/// non-blank line.
+fn log_stderr(
+    ctx: &Context,
+    name: &str,
+    stderr: ChildStderr,
+    watch: Option<OutputWatch>,
+) -> JoinHandle<Option<String>> {
+    let log = ctx.log().new(o!("process" => name.to_owned()));
+    tokio::spawn(async move {
+        let mut last_line = None;
+        let mut lines = BufReader::new(stderr).lines();
+        while let Some(line) = lines.next().await {
+            match line {
+                Ok(line) => {
+                    // Messages on standard error show that the child is still
+                    // alive.
+                    if let Some(watch) = &watch {
+                        watch.touch();
+                    }
+                    if !line.trim().is_empty() {
+                        warn!(log, "{}", line);
+                        last_line = Some(line);
+                    }
+                }
+                Err(err) => {
+                    debug!(log, "error reading standard error: {}", err);
+                    break;
+                }
+            }
+        }
+        last_line
+    })
+}
+
+#[test]
+fn check_status_includes_stderr() {
+    use std::process::Command;
+
+    let status = Command::new("sh").args(&["-c", "exit 3"]).status().unwrap();
+    let err = check_status("`sh`", status, Some("oops".to_owned())).unwrap_err();
+    assert!(err.to_string().ends_with(": oops"));
+    let ok = Command::new("true").status().unwrap();
+    assert!(check_status("`true`", ok, None).is_ok());
+}
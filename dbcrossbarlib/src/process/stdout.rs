@@ -0,0 +1,25 @@
+//! Reading a child process's standard output.
+
+use tokio::process::{Child, ChildStdout};
+
+use super::{wait_for_process, watched_reader::WatchedReader};
+use crate::common::*;
+
+impl Context {
+    /// Like `spawn_process`, but take ownership of the child's standard
+    /// output, and kill the child if we wait too long for it to produce any
+    /// output. This prevents hung tools from silently stalling a copy.
+    pub(crate) fn spawn_process_with_stdout(
+        &self,
+        name: String,
+        mut child: Child,
+    ) -> WatchedReader<ChildStdout> {
+        let stdout = child.stdout.take().expect("child should have stdout");
+        let (stdout, watch) = WatchedReader::new(stdout);
+        let ctx = self.clone();
+        let worker =
+            async move { wait_for_process(&ctx, &name, child, Some(watch)).await };
+        self.spawn_worker(worker.boxed());
+        stdout
+    }
+}
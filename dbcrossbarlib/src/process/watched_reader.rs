@@ -0,0 +1,68 @@
+//! Watching a child process's output for hangs.
+
+use std::{
+    pin::Pin,
+    task::{self, Poll},
+    time::Instant,
+};
+
+use super::OutputWatch;
+use crate::common::*;
+
+impl OutputWatch {
+    /// Record whether the last read was waiting for data.
+    fn set_waiting(&self, waiting: bool) {
+        let mut waiting_since = self.waiting_since.lock().expect("lock poisoned");
+        match (waiting, *waiting_since) {
+            (true, None) => *waiting_since = Some(Instant::now()),
+            (true, Some(_)) => {}
+            (false, _) => *waiting_since = None,
+        }
+    }
+}
+
+/// An `AsyncRead` wrapper which records how long we've been waiting for data.
+pub(crate) struct WatchedReader<R> {
+    inner: R,
+    watch: OutputWatch,
+}
+
+impl<R: AsyncRead + Unpin> WatchedReader<R> {
+    /// Wrap `inner`, returning the wrapper and an `OutputWatch` which can be
+    /// used to see whether it's stuck.
+    pub(crate) fn new(inner: R) -> (Self, OutputWatch) {
+        let watch = OutputWatch::default();
+        let reader = WatchedReader {
+            inner,
+            watch: watch.clone(),
+        };
+        (reader, watch)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for WatchedReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        self.watch.set_waiting(result.is_pending());
+        result
+    }
+}
+
+#[test]
+fn output_watch_tracks_waiting() {
+    let watch = OutputWatch::default();
+    assert!(watch.waiting_for().is_none());
+    watch.set_waiting(true);
+    assert!(watch.waiting_for().is_some());
+    watch.touch();
+    assert!(watch.waiting_for().is_some());
+    watch.set_waiting(false);
+    assert!(watch.waiting_for().is_none());
+    // Touching a reader which isn't waiting doesn't start the clock.
+    watch.touch();
+    assert!(watch.waiting_for().is_none());
+}
@@ -0,0 +1,93 @@
+//! Named query parameters, specified as `--query-param=NAME=VALUE`.
+
+use std::{collections::HashSet, str::FromStr};
+
+use crate::common::*;
+
+/// A named parameter which will be bound to a `--where` clause by the driver,
+/// instead of being interpolated into the SQL.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueryParam {
+    /// The name of this parameter, which is referred to as `@name` in SQL.
+    name: String,
+    /// The value of this parameter. We always pass values as strings, and
+    /// queries should cast them to the type they need.
+    value: String,
+}
+
+impl QueryParam {
+    /// The name of this parameter.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The value of this parameter.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Make sure that no parameter name appears more than once in `params`.
+    pub(crate) fn check_unique(params: &[QueryParam]) -> Result<()> {
+        let mut seen = HashSet::new();
+        for param in params {
+            if !seen.insert(param.name.to_ascii_lowercase()) {
+                return Err(format_err!(
+                    "query parameter {:?} specified more than once",
+                    param.name,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for QueryParam {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.splitn(2, '=');
+        let name = parts.next().unwrap_or("");
+        let value = parts.next().ok_or_else(|| {
+            format_err!("expected query parameter {:?} to look like NAME=VALUE", s)
+        })?;
+        let valid_name = name
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(format_err!(
+                "query parameter name {:?} must start with a letter or '_', and \
+                 contain only letters, digits and '_'",
+                name,
+            ));
+        }
+        Ok(QueryParam {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        })
+    }
+}
+
+#[test]
+fn parse_query_params() {
+    let param = "start_date=2024-06-01".parse::<QueryParam>().unwrap();
+    assert_eq!(param.name(), "start_date");
+    assert_eq!(param.value(), "2024-06-01");
+    let param = "_q=a=b; DROP TABLE x".parse::<QueryParam>().unwrap();
+    assert_eq!(param.name(), "_q");
+    assert_eq!(param.value(), "a=b; DROP TABLE x");
+    let param = "empty=".parse::<QueryParam>().unwrap();
+    assert_eq!(param.value(), "");
+
+    for &s in &["", "date", "=x", "1date=x", "a-b=x", "a.b=x", " a=x"] {
+        assert!(s.parse::<QueryParam>().is_err(), "should not parse {:?}", s);
+    }
+
+    let params = ["a=1", "b=2", "A=3"]
+        .iter()
+        .map(|s| s.parse::<QueryParam>().unwrap())
+        .collect::<Vec<_>>();
+    assert!(QueryParam::check_unique(&params[..2]).is_ok());
+    assert!(QueryParam::check_unique(&params).is_err());
+}
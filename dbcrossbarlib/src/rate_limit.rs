@@ -0,0 +1,169 @@
+//! Rate limiting and batching for destinations which insert rows using an API.
+//!
+//! Some destinations (streaming inserts, message queues, search indices) need
+//! to send rows in batches through an API with per-second quotas. This module
+//! provides common `--to-arg=rate_limit.*` arguments for these destinations, so
+//! that users can configure them all the same way.
+
+use futures::executor::block_on;
+use serde::Deserialize;
+use std::{mem, time::Duration};
+use tokio::time::{timeout_at, Instant};
+
+use crate::common::*;
+use crate::driver_args::deserialize_opt_from_str;
+use crate::token_bucket::TokenBucket;
+use crate::tokio_glue::{spawn_blocking_stage, SyncStreamReader};
+
+/// The default number of rows to send in a single API call.
+const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Arguments controlling how quickly we send rows to an API.
+///
+/// Drivers should include this as a `#[serde(default)] rate_limit` field in
+/// their destination arguments, so that it can be set using
+/// `--to-arg=rate_limit.max_rows_per_second=1000`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RateLimitArguments {
+    /// The maximum number of rows to send per second. Defaults to unlimited.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    max_rows_per_second: Option<f64>,
+
+    /// The maximum number of rows to send in a single batch.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    batch_size: Option<usize>,
+
+    /// If a partial batch has been waiting for this many milliseconds, send it
+    /// without waiting for more rows.
+    #[serde(default, deserialize_with = "deserialize_opt_from_str")]
+    flush_interval_ms: Option<u64>,
+}
+
+impl RateLimitArguments {
+    /// The maximum number of rows to send in a single batch.
+    pub(crate) fn batch_size(&self) -> usize {
+        self.batch_size.unwrap_or(DEFAULT_BATCH_SIZE)
+    }
+
+    /// Parse `data` as CSV, and return a stream of row batches that will be
+    /// released no faster than our rate limit allows. The CSV header must match
+    /// the columns of `schema`, and is not included in any batch.
+    pub(crate) fn csv_row_batches(
+        &self,
+        ctx: &Context,
+        schema: &Table,
+        data: BoxStream<BytesMut>,
+    ) -> Result<BoxStream<Vec<csv::StringRecord>>> {
+        let batch_size = self.batch_size();
+        if batch_size == 0 {
+            return Err(format_err!("rate_limit.batch_size must be at least 1"));
+        }
+        let mut bucket = match self.max_rows_per_second {
+            Some(rate) if rate > 0.0 => Some(TokenBucket::new(rate, batch_size)),
+            Some(rate) => {
+                return Err(format_err!(
+                    "rate_limit.max_rows_per_second must be positive, found {}",
+                    rate,
+                ));
+            }
+            None => None,
+        };
+        let flush_interval = self.flush_interval_ms.map(Duration::from_millis);
+
+        // Parse our CSV data in a background thread.
+        let (mut row_sender, mut rows) =
+            mpsc::channel::<Result<csv::StringRecord>>(batch_size);
+        let rdr_ctx = ctx.child(o!("rate_limit" => "csv_reader"));
+        let rdr = SyncStreamReader::new(rdr_ctx.clone(), data);
+        let schema = schema.to_owned();
+        let rdr_fut = spawn_blocking_stage(move || -> Result<()> {
+            let mut rdr = csv::Reader::from_reader(rdr);
+
+            // Make sure our CSV columns match our schema.
+            let headers_match =
+                rdr.headers().map_err(Error::from).and_then(|headers| {
+                    let names = schema.columns.iter().map(|c| &c.name[..]);
+                    if headers.iter().eq(names) {
+                        Ok(())
+                    } else {
+                        Err(format_err!(
+                            "CSV columns {:?} do not match schema for {}",
+                            headers,
+                            schema.name,
+                        ))
+                    }
+                });
+            if let Err(err) = headers_match {
+                if block_on(row_sender.send(Err(err))).is_err() {
+                    debug!(rdr_ctx.log(), "row receiver was dropped");
+                }
+                return Ok(());
+            }
+
+            for row in rdr.records() {
+                let row = row.map_err(|err| format_err!("cannot read row: {}", err));
+                if block_on(row_sender.send(row)).is_err() {
+                    debug!(rdr_ctx.log(), "row receiver was dropped");
+                    break;
+                }
+            }
+            Ok(())
+        });
+        ctx.spawn_worker(rdr_fut.boxed());
+
+        // Group rows into batches and release them at our allowed rate.
+        let (mut batch_sender, batches) =
+            mpsc::channel::<Result<Vec<csv::StringRecord>>>(1);
+        let worker_ctx = ctx.child(o!("rate_limit" => "batcher"));
+        let worker = async move {
+            let mut batch = Vec::with_capacity(batch_size);
+            let mut batch_deadline = None;
+            loop {
+                // Wait for the next row, or until we need to flush a partial
+                // batch. `None` means that we timed out.
+                let next = match batch_deadline {
+                    Some(deadline) => timeout_at(deadline, rows.recv()).await.ok(),
+                    None => Some(rows.recv().await),
+                };
+                let (flush, at_end) = match next {
+                    Some(Some(Ok(row))) => {
+                        if batch.is_empty() {
+                            batch_deadline = flush_interval
+                                .map(|interval| Instant::now() + interval);
+                        }
+                        batch.push(row);
+                        (batch.len() >= batch_size, false)
+                    }
+                    Some(Some(Err(err))) => {
+                        batch_sender.send(Err(err)).await.map_send_err()?;
+                        return Ok(());
+                    }
+                    Some(None) => (true, true),
+                    None => (true, false),
+                };
+
+                // Send our batch, waiting until our rate limit allows it.
+                if flush && !batch.is_empty() {
+                    if let Some(bucket) = &mut bucket {
+                        bucket.take(batch.len()).await;
+                    }
+                    let ready =
+                        mem::replace(&mut batch, Vec::with_capacity(batch_size));
+                    batch_deadline = None;
+                    trace!(worker_ctx.log(), "sending batch of {} rows", ready.len());
+                    if batch_sender.send(Ok(ready)).await.is_err() {
+                        debug!(worker_ctx.log(), "batch receiver was dropped");
+                        return Ok(());
+                    }
+                }
+                if at_end {
+                    return Ok(());
+                }
+            }
+        };
+        ctx.spawn_worker(worker.boxed());
+
+        Ok(batches.boxed())
+    }
+}
@@ -1,16 +1,77 @@
 //! Given a stream of streams CSV data, rechunk the stream sizes.
 
 use futures::executor::block_on;
+use humanize_rs::bytes::Bytes as HumanizedBytes;
 use std::{cell::Cell, cmp::min, io, rc::Rc};
 use tokio::sync::mpsc;
 
 use crate::common::*;
 use crate::concat::concatenate_csv_streams;
-use crate::tokio_glue::{SyncStreamReader, SyncStreamWriter};
+use crate::tokio_glue::{spawn_blocking_stage, SyncStreamReader, SyncStreamWriter};
 
 /// Max buffer size for `csv::Writer`.
 const MAX_CSV_BUFFER_SIZE: usize = 8 * (1 << 10);
 
+/// Limits on how big a chunk of CSV data can get before we start a new one.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ChunkLimits {
+    /// The approximate maximum number of bytes in a chunk.
+    max_bytes: Option<usize>,
+    /// The maximum number of rows in a chunk, not counting the header.
+    max_rows: Option<usize>,
+}
+
+impl ChunkLimits {
+    /// Build limits from the `max_file_size` and `max_rows_per_file` driver
+    /// arguments. `max_file_size` may be written as "256MB", "1GiB", etc.
+    pub(crate) fn from_driver_args(
+        max_file_size: Option<&str>,
+        max_rows_per_file: Option<usize>,
+    ) -> Result<Self> {
+        let max_bytes = max_file_size
+            .map(|s| {
+                s.parse::<HumanizedBytes>().map(|b| b.size()).map_err(|e| {
+                    format_err!("cannot parse max_file_size {:?}: {}", s, e)
+                })
+            })
+            .transpose()?;
+        if max_bytes == Some(0) || max_rows_per_file == Some(0) {
+            return Err(format_err!(
+                "max_file_size and max_rows_per_file must be greater than 0"
+            ));
+        }
+        Ok(ChunkLimits {
+            max_bytes,
+            max_rows: max_rows_per_file,
+        })
+    }
+
+    /// Are there no limits at all?
+    pub(crate) fn is_unlimited(&self) -> bool {
+        self.max_bytes.is_none() && self.max_rows.is_none()
+    }
+
+    /// Should we finish a chunk containing `bytes` and `rows`?
+    pub(crate) fn is_full(&self, bytes: usize, rows: usize) -> bool {
+        self.max_bytes.map_or(false, |max| bytes >= max)
+            || self.max_rows.map_or(false, |max| rows >= max)
+    }
+}
+
+#[test]
+fn chunk_limits_from_driver_args() {
+    let limits = ChunkLimits::from_driver_args(Some("2KB"), Some(10)).unwrap();
+    assert!(!limits.is_unlimited());
+    assert!(!limits.is_full(100, 9));
+    assert!(limits.is_full(100, 10));
+    assert!(limits.is_full(5000, 1));
+    assert!(ChunkLimits::from_driver_args(None, None)
+        .unwrap()
+        .is_unlimited());
+    assert!(ChunkLimits::from_driver_args(Some("lots"), None).is_err());
+    assert!(ChunkLimits::from_driver_args(None, Some(0)).is_err());
+}
+
 /// Given a stream of streams CSV data, return another stream of CSV streams
 /// where the CSV data is approximately `chunk_size` long whenever possible.
 pub fn rechunk_csvs(
@@ -19,9 +80,56 @@ pub fn rechunk_csvs(
     streams: BoxStream<CsvStream>,
 ) -> Result<BoxStream<CsvStream>> {
     // Convert out input `BoxStream<CsvStream>` into a single, concatenated
-    // synchronous `Read` object.
+    // stream.
     let ctx = ctx.child(o!("streams_transform" => "rechunk_csvs"));
     let input_csv_stream = concatenate_csv_streams(ctx.clone(), streams)?;
+    let limits = ChunkLimits {
+        max_bytes: Some(chunk_size),
+        max_rows: None,
+    };
+    rechunk_csv_stream(ctx, limits, input_csv_stream, |chunk_id| {
+        format!("chunk_{:04}", chunk_id)
+    })
+}
+
+/// Split each stream in `streams` into chunks no larger than `limits`. The
+/// chunks of a stream named `data` will be named `data_0001`, `data_0002`,
+/// etc. If `limits` has no limits, return `streams` unchanged.
+pub(crate) fn split_csv_streams(
+    ctx: Context,
+    limits: ChunkLimits,
+    streams: BoxStream<CsvStream>,
+) -> BoxStream<CsvStream> {
+    if limits.is_unlimited() {
+        return streams;
+    }
+    let ctx = ctx.child(o!("streams_transform" => "split_csv_streams"));
+    streams
+        .and_then(move |stream| {
+            let ctx = ctx.child(o!("stream" => stream.name.clone()));
+            let name = stream.name.clone();
+            async move {
+                rechunk_csv_stream(ctx, limits, stream, move |chunk_id| {
+                    format!("{}_{:04}", name, chunk_id)
+                })
+            }
+        })
+        .try_flatten()
+        .boxed()
+}
+
+/// Split `input_csv_stream` into chunks no larger than `limits`, using
+/// `chunk_name` to name each chunk.
+fn rechunk_csv_stream<F>(
+    ctx: Context,
+    limits: ChunkLimits,
+    input_csv_stream: CsvStream,
+    chunk_name: F,
+) -> Result<BoxStream<CsvStream>>
+where
+    F: Fn(usize) -> String + Send + 'static,
+{
+    // Convert our input stream into a synchronous `Read` object.
     let csv_rdr = SyncStreamReader::new(ctx.clone(), input_csv_stream.data);
 
     // Create a channel to which we can write `CsvStream` values once we've
@@ -32,7 +140,7 @@ pub fn rechunk_csvs(
     // Run a synchronous background worker thread that parsers our sync CSV
     // `Read`er into a stream of `CsvStream`s.
     let worker_ctx = ctx.clone();
-    let worker_fut = spawn_blocking(move || -> Result<()> {
+    let worker_fut = spawn_blocking_stage(move || -> Result<()> {
         let mut rdr = csv::Reader::from_reader(csv_rdr);
         let hdr = rdr
             .byte_headers()
@@ -47,6 +155,8 @@ pub fn rechunk_csvs(
             /// Approximately how much data have we written, not counting the
             /// buffer in `wtr`?
             total_written: Rc<Cell<usize>>,
+            /// How many rows have we written, not counting the header?
+            rows_written: usize,
             /// The `CsvStream` which will output the data produced by `wtr`.
             /// Once we publish this vaue to `csv_stream_sender`, we'll set the
             /// field `csv_stream` to `None`.
@@ -68,7 +178,8 @@ pub fn rechunk_csvs(
             // and `data` is an `impl Stream<Item = BytesMut, ..>`.
             let (wtr, data) = SyncStreamWriter::pipe(worker_ctx.clone());
             let csv_stream = CsvStream {
-                name: format!("chunk_{:04}", chunk_id),
+                name: chunk_name(chunk_id),
+                metadata: StreamMetadata::default(),
                 data: data.boxed(),
             };
 
@@ -77,13 +188,17 @@ pub fn rechunk_csvs(
             let total_written = wtr.total_written();
 
             // Now, make a `csv::Writer` we can write to. We limit our buffer
-            // size so that `chunk_size` is vaguely accurate.
+            // size so that `limits.max_bytes` is vaguely accurate.
+            let buffer_size = limits
+                .max_bytes
+                .map_or(MAX_CSV_BUFFER_SIZE, |max| min(MAX_CSV_BUFFER_SIZE, max));
             let wtr = csv::WriterBuilder::default()
-                .buffer_capacity(min(MAX_CSV_BUFFER_SIZE, chunk_size))
+                .buffer_capacity(buffer_size)
                 .from_writer(wtr);
             Ok(Chunk {
                 wtr,
                 total_written,
+                rows_written: 0,
                 csv_stream: Some(csv_stream),
             })
         };
@@ -112,9 +227,10 @@ pub fn rechunk_csvs(
                 .wtr
                 .write_byte_record(&row)
                 .context("cannot write row")?;
+            chunk.rows_written += 1;
 
-            // If total written exceeds chunk size, then start a new chunk.
-            if chunk.total_written.get() >= chunk_size {
+            // If this chunk is full, then start a new chunk.
+            if limits.is_full(chunk.total_written.get(), chunk.rows_written) {
                 trace!(worker_ctx.log(), "finishing chunk");
                 chunk = new_chunk()?;
             }
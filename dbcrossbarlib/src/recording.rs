@@ -0,0 +1,276 @@
+//! Recording and replaying interactions with external services.
+//!
+//! When recording, we save each Google Cloud API request and response, plus an
+//! MD5 checksum of each CSV stream that we copy. When replaying, we answer API
+//! requests from the recording instead of the network, and we verify that our
+//! CSV streams still have the same checksums. This allows users to send us a
+//! recording of a failed run without sending us their data or credentials.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    mem,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::common::*;
+
+#[cfg(feature = "gs")]
+mod http;
+
+/// The version of our recording format.
+const RECORDING_VERSION: u32 = 1;
+
+/// Are we recording or replaying?
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RecorderMode {
+    /// Talk to real services, and record what happens.
+    Record,
+    /// Answer requests from a recording.
+    Replay,
+}
+
+/// A single HTTP request and its response.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct Interaction {
+    /// The HTTP method.
+    pub(crate) method: String,
+    /// The URL, including any query string.
+    pub(crate) url: String,
+    /// The JSON request body, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) request: Option<serde_json::Value>,
+    /// The HTTP status code of the response.
+    pub(crate) status: u16,
+    /// Did the server claim that `response` was JSON?
+    pub(crate) is_json: bool,
+    /// The response body.
+    pub(crate) response: String,
+}
+
+/// A checksum of the data in a CSV stream.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct StreamChecksum {
+    /// The name of the stream.
+    stream: String,
+    /// The MD5 checksum of the stream's data, in hexadecimal.
+    md5: String,
+    /// The length of the stream's data.
+    bytes: u64,
+    /// Have we already checked against this checksum?
+    #[serde(skip)]
+    used: bool,
+}
+
+/// The contents of a recording file.
+#[derive(Debug, Deserialize, Serialize)]
+struct Recording {
+    /// The version of our recording format.
+    version: u32,
+    /// HTTP interactions, in the order they were made.
+    interactions: Vec<Interaction>,
+    /// Checksums of the CSV streams we copied, in the order they finished.
+    checksums: Vec<StreamChecksum>,
+}
+
+/// Records or replays interactions with external services.
+#[derive(Debug)]
+pub struct Recorder {
+    /// Are we recording or replaying?
+    mode: RecorderMode,
+    /// The file we're recording to or replaying from.
+    path: PathBuf,
+    /// Our recorded data.
+    recording: Mutex<Recording>,
+}
+
+impl Recorder {
+    /// Create a recorder which will save interactions to `path`.
+    pub fn record(path: &Path) -> Self {
+        Recorder {
+            mode: RecorderMode::Record,
+            path: path.to_owned(),
+            recording: Mutex::new(Recording {
+                version: RECORDING_VERSION,
+                interactions: vec![],
+                checksums: vec![],
+            }),
+        }
+    }
+
+    /// Create a recorder which will replay interactions from `path`.
+    pub fn replay(path: &Path) -> Result<Self> {
+        let mkerr = || format!("could not read recording {}", path.display());
+        let file = File::open(path).with_context(|_| mkerr())?;
+        let recording: Recording =
+            serde_json::from_reader(BufReader::new(file)).with_context(|_| mkerr())?;
+        if recording.version != RECORDING_VERSION {
+            return Err(format_err!(
+                "{} uses recording format version {}, but we only support {}",
+                path.display(),
+                recording.version,
+                RECORDING_VERSION,
+            ));
+        }
+        Ok(Recorder {
+            mode: RecorderMode::Replay,
+            path: path.to_owned(),
+            recording: Mutex::new(recording),
+        })
+    }
+
+    /// Are we recording or replaying?
+    pub fn mode(&self) -> RecorderMode {
+        self.mode
+    }
+
+    /// Save our recording, if we're recording. This should be called even if
+    /// our command failed, because that's when a recording is most useful.
+    pub fn save(&self) -> Result<()> {
+        if self.mode == RecorderMode::Record {
+            let mkerr =
+                || format!("could not write recording {}", self.path.display());
+            let recording = self.recording.lock().expect("lock poisoned");
+            let file = File::create(&self.path).with_context(|_| mkerr())?;
+            serde_json::to_writer_pretty(BufWriter::new(file), &*recording)
+                .with_context(|_| mkerr())?;
+        }
+        Ok(())
+    }
+
+    /// Record a stream checksum, or verify it against our recording.
+    fn check_or_record_checksum(
+        &self,
+        ctx: &Context,
+        stream: &str,
+        md5: String,
+        bytes: u64,
+    ) -> Result<()> {
+        let mut recording = self.recording.lock().expect("lock poisoned");
+        match self.mode {
+            RecorderMode::Record => {
+                debug!(
+                    ctx.log(),
+                    "stream {} has MD5 {} ({} bytes)", stream, md5, bytes
+                );
+                recording.checksums.push(StreamChecksum {
+                    stream: stream.to_owned(),
+                    md5,
+                    bytes,
+                    used: false,
+                });
+                Ok(())
+            }
+            RecorderMode::Replay => {
+                let expected = recording
+                    .checksums
+                    .iter_mut()
+                    .find(|c| !c.used && c.stream == stream)
+                    .ok_or_else(|| {
+                        format_err!("recording has no checksum for stream {}", stream)
+                    })?;
+                expected.used = true;
+                if expected.md5 == md5 && expected.bytes == bytes {
+                    Ok(())
+                } else {
+                    Err(format_err!(
+                        "stream {} has MD5 {} ({} bytes), but recording has MD5 {} ({} bytes)",
+                        stream,
+                        md5,
+                        bytes,
+                        expected.md5,
+                        expected.bytes,
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// If `ctx` has a recorder, checksum the data in each of `streams`, and either
+/// record the checksums or verify them against the recording.
+pub fn checksum_csv_streams(
+    ctx: &Context,
+    streams: BoxStream<CsvStream>,
+) -> BoxStream<CsvStream> {
+    let recorder = match ctx.recorder() {
+        Some(recorder) => recorder.clone(),
+        None => return streams,
+    };
+    let ctx = ctx.clone();
+    streams
+        .map_ok(move |stream| {
+            let ctx = ctx.child(o!("stream" => stream.name.clone()));
+            let recorder = recorder.clone();
+            let name = stream.name.clone();
+
+            // Update our checksum as data passes through.
+            let state = Arc::new(Mutex::new((md5::Context::new(), 0u64)));
+            let update_state = state.clone();
+            let data = stream.data.inspect_ok(move |bytes| {
+                let mut state = update_state.lock().expect("lock poisoned");
+                state.0.consume(&bytes[..]);
+                state.1 += bytes.len() as u64;
+            });
+
+            // Once we reach the end of our data, check our checksum. This
+            // doesn't output any data unless it returns an error.
+            let finish = stream::once(async move {
+                let (md5, bytes) = {
+                    let mut state = state.lock().expect("lock poisoned");
+                    let md5 = mem::replace(&mut state.0, md5::Context::new());
+                    (format!("{:x}", md5.compute()), state.1)
+                };
+                recorder
+                    .check_or_record_checksum(&ctx, &name, md5, bytes)
+                    .map(|()| None)
+            })
+            .try_filter_map(|bytes: Option<BytesMut>| async move { Ok(bytes) });
+
+            CsvStream {
+                name: stream.name,
+                metadata: stream.metadata,
+                data: data.chain(finish).boxed(),
+            }
+        })
+        .boxed()
+}
+
+#[test]
+fn checksums_are_recorded_and_verified() {
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!(
+        "dbcrossbar-recording-{}",
+        TemporaryStorage::random_tag()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("recording.json");
+
+    // Copy `data` through `checksum_csv_streams` using `recorder`.
+    let copy = |recorder: Recorder, data: &'static [u8]| -> Result<()> {
+        let (ctx, worker_fut) = Context::create_for_test("checksums");
+        let ctx = ctx.with_recorder(Arc::new(recorder));
+        let cmd_fut = async move {
+            let input = CsvStream {
+                name: "data".to_owned(),
+                metadata: StreamMetadata::default(),
+                data: box_stream_once(Ok(BytesMut::from(data))),
+            };
+            let mut streams = checksum_csv_streams(&ctx, box_stream_once(Ok(input)));
+            let stream = streams.next().await.expect("no stream")?;
+            let output = stream.into_bytes(ctx.clone()).await?;
+            assert_eq!(output, data);
+            ctx.recorder().expect("no recorder").save()
+        };
+        run_futures_with_runtime(cmd_fut.boxed(), worker_fut)
+    };
+
+    copy(Recorder::record(&path), b"a,b\n1,2\n").unwrap();
+    copy(Recorder::replay(&path).unwrap(), b"a,b\n1,2\n").unwrap();
+    assert!(copy(Recorder::replay(&path).unwrap(), b"a,b\n1,3\n").is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
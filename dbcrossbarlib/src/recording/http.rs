@@ -0,0 +1,77 @@
+//! Recording and replaying HTTP interactions.
+
+use super::{Interaction, Recorder, RecorderMode};
+use crate::common::*;
+
+impl Interaction {
+    /// Create a new interaction.
+    pub(crate) fn new(
+        method: &str,
+        url: &str,
+        request: Option<serde_json::Value>,
+        status: u16,
+        is_json: bool,
+        response: String,
+    ) -> Self {
+        Interaction {
+            method: method.to_owned(),
+            url: url.to_owned(),
+            request,
+            status,
+            is_json,
+            response,
+        }
+    }
+}
+
+impl Recorder {
+    /// Record an HTTP interaction.
+    pub(crate) fn record_interaction(&self, interaction: Interaction) {
+        assert_eq!(self.mode, RecorderMode::Record);
+        let mut recording = self.recording.lock().expect("lock poisoned");
+        recording.interactions.push(interaction);
+    }
+
+    /// Find the recorded response to an HTTP request.
+    ///
+    /// We look for the first unused interaction with the same method, URL and
+    /// request body. If there isn't one, we fall back to the first unused
+    /// interaction with the same method, because some URLs contain randomly
+    /// generated names for temporary tables and files. Each interaction is
+    /// removed from the recording once it has been used.
+    pub(crate) fn replay_interaction(
+        &self,
+        ctx: &Context,
+        method: &str,
+        url: &str,
+        request: Option<&serde_json::Value>,
+    ) -> Result<Interaction> {
+        assert_eq!(self.mode, RecorderMode::Replay);
+        let mut recording = self.recording.lock().expect("lock poisoned");
+        let same_method = |i: &Interaction| i.method == method;
+        let index = match recording.interactions.iter().position(|i| {
+            same_method(i) && i.url == url && i.request.as_ref() == request
+        }) {
+            Some(index) => index,
+            None => {
+                let index = recording
+                    .interactions
+                    .iter()
+                    .position(same_method)
+                    .ok_or_else(|| {
+                        format_err!("no recorded response for {} {}", method, url)
+                    })?;
+                warn!(
+                    ctx.log(),
+                    "replaying {} {} using response to {}",
+                    method,
+                    url,
+                    recording.interactions[index].url,
+                );
+                index
+            }
+        };
+        trace!(ctx.log(), "replaying {} {}", method, url);
+        Ok(recording.interactions.remove(index))
+    }
+}
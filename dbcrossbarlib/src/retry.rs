@@ -0,0 +1,67 @@
+//! Helpers for retrying cloud requests which fail temporarily.
+
+use rand::{thread_rng, Rng};
+use std::{cmp::min, env, time::Duration};
+
+use crate::common::*;
+
+/// The longest we'll wait before our first retry.
+const BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// The longest we'll ever wait between retries.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// How many times should we retry a request which fails temporarily? This
+/// reads the environment variable `var`, and uses `default` if it isn't set. A
+/// value of `0` disables retries.
+pub(crate) fn max_retries_from_env(var: &str, default: u32) -> Result<u32> {
+    match env::var(var) {
+        Ok(retries) => Ok(retries
+            .parse::<u32>()
+            .with_context(|_| format!("could not parse {}={:?}", var, retries))?),
+        Err(env::VarError::NotPresent) => Ok(default),
+        Err(err) => Err(format_err!("{}: {}", var, err)),
+    }
+}
+
+/// The longest we might wait before retry number `retry`, starting from 1.
+fn max_delay_before_retry(retry: u32) -> Duration {
+    // Cap our exponent so that we can't overflow.
+    let factor = 1u32 << min(retry.saturating_sub(1), 16);
+    min(BASE_DELAY * factor, MAX_DELAY)
+}
+
+/// How long should we wait before retry number `retry`, starting from 1?
+///
+/// We use exponential backoff with "full jitter", so that many parallel
+/// streams which hit errors at the same time don't all retry at once.
+pub(crate) fn delay_before_retry(retry: u32) -> Duration {
+    let max = max_delay_before_retry(retry);
+    let max_millis = u64::try_from(max.as_millis()).unwrap_or(u64::MAX);
+    let millis = thread_rng().gen_range(0, max_millis.saturating_add(1));
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn delays_grow_exponentially_up_to_a_limit() {
+    assert_eq!(max_delay_before_retry(1), Duration::from_millis(500));
+    assert_eq!(max_delay_before_retry(2), Duration::from_secs(1));
+    assert_eq!(max_delay_before_retry(4), Duration::from_secs(4));
+    assert_eq!(max_delay_before_retry(8), MAX_DELAY);
+    assert_eq!(max_delay_before_retry(1000), MAX_DELAY);
+    for retry in 1..20 {
+        assert!(delay_before_retry(retry) <= max_delay_before_retry(retry));
+    }
+}
+
+#[test]
+fn max_retries_can_be_set_from_env() {
+    let var = "DBCROSSBAR_TEST_MAX_RETRIES";
+    env::remove_var(var);
+    assert_eq!(max_retries_from_env(var, 3).unwrap(), 3);
+    env::set_var(var, "0");
+    assert_eq!(max_retries_from_env(var, 3).unwrap(), 0);
+    env::set_var(var, "lots");
+    assert!(max_retries_from_env(var, 3).is_err());
+    env::remove_var(var);
+}
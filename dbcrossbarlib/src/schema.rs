@@ -120,6 +120,14 @@ pub enum DataType {
     /// A decimal integer (can represent currency, etc., without rounding
     /// errors).
     Decimal,
+    /// A decimal number with a fixed number of digits, like SQL `NUMERIC(10,
+    /// 2)`. Use this instead of `Decimal` when the precision matters.
+    FixedDecimal {
+        /// The total number of digits, on both sides of the decimal point.
+        precision: u32,
+        /// The number of digits after the decimal point.
+        scale: u32,
+    },
     /// 4-byte float.
     Float32,
     /// 8-byte float.
@@ -162,6 +170,7 @@ impl DataType {
             DataType::Bool
             | DataType::Date
             | DataType::Decimal
+            | DataType::FixedDecimal { .. }
             | DataType::Float32
             | DataType::Float64
             | DataType::Int16
@@ -201,6 +210,13 @@ fn data_type_serialization_examples() {
         (DataType::Bool, json!("bool")),
         (DataType::Date, json!("date")),
         (DataType::Decimal, json!("decimal")),
+        (
+            DataType::FixedDecimal {
+                precision: 10,
+                scale: 2,
+            },
+            json!({ "fixed_decimal": { "precision": 10, "scale": 2 } }),
+        ),
         (DataType::Float32, json!("float32")),
         (DataType::Float64, json!("float64")),
         (DataType::Int16, json!("int16")),
@@ -249,6 +265,10 @@ fn data_type_roundtrip() {
         DataType::Bool,
         DataType::Date,
         DataType::Decimal,
+        DataType::FixedDecimal {
+            precision: 38,
+            scale: 9,
+        },
         DataType::Float32,
         DataType::Float64,
         DataType::Int16,
@@ -0,0 +1,111 @@
+//! Dropping and reordering the columns in CSV streams.
+
+use std::str::FromStr;
+
+use crate::common::*;
+use crate::transform::spawn_sync_transform;
+
+/// A list of columns to keep, in the order we want them, parsed from a string
+/// like `id,name,email`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct SelectColumns(Vec<String>);
+
+impl SelectColumns {
+    /// The names of the columns we want, in order.
+    pub(crate) fn names(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Keep only our columns in every row of `stream`, in our order.
+    pub(crate) fn select_from_csv_stream(
+        &self,
+        ctx: &Context,
+        stream: CsvStream,
+    ) -> Result<CsvStream> {
+        let names = self.names().to_owned();
+        let data = spawn_sync_transform(
+            ctx.clone(),
+            "select_columns".to_owned(),
+            stream.data,
+            move |_ctx, rdr, wtr| select_csv_columns(&names, rdr, wtr),
+        )?;
+        Ok(CsvStream {
+            name: stream.name,
+            metadata: stream.metadata,
+            data,
+        })
+    }
+}
+
+impl FromStr for SelectColumns {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut names = Vec::<String>::new();
+        for name in s.split(',').map(|name| name.trim()) {
+            if name.is_empty() {
+                return Err(format_err!("empty column name in {:?}", s));
+            } else if names.iter().any(|n| n == name) {
+                return Err(format_err!(
+                    "column {:?} selected twice in {:?}",
+                    name,
+                    s
+                ));
+            }
+            names.push(name.to_owned());
+        }
+        Ok(SelectColumns(names))
+    }
+}
+
+/// Copy CSV data from `rdr` to `wtr`, keeping only the columns in `names`,
+/// in that order.
+fn select_csv_columns<R, W>(names: &[String], rdr: R, wtr: W) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+
+    // Figure out where to find each of our columns.
+    let header = rdr
+        .byte_headers()
+        .context("cannot read CSV header")?
+        .to_owned();
+    let indices = names
+        .iter()
+        .map(|name| {
+            header
+                .iter()
+                .position(|h| h == name.as_bytes())
+                .ok_or_else(|| format_err!("cannot find column {:?} in data", name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    wtr.write_record(names).context("cannot write CSV header")?;
+
+    let mut row = csv::ByteRecord::new();
+    let mut out = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut row).context("cannot read row")? {
+        out.clear();
+        for &idx in &indices {
+            out.push_field(&row[idx]);
+        }
+        wtr.write_byte_record(&out).context("cannot write row")?;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+#[test]
+fn select_and_reorder_columns() {
+    let select = "c, a".parse::<SelectColumns>().unwrap();
+    let mut out = vec![];
+    select_csv_columns(&select.0, &b"a,b,c\n1,2,3\n4,5,6\n"[..], &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "c,a\n3,1\n6,4\n");
+
+    let mut out = vec![];
+    assert!(select_csv_columns(&select.0, &b"a,b\n1,2\n"[..], &mut out).is_err());
+    assert!("a,,b".parse::<SelectColumns>().is_err());
+    assert!("a,b,a".parse::<SelectColumns>().is_err());
+}
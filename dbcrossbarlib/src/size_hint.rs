@@ -0,0 +1,81 @@
+//! Hints about how much data a source will produce.
+
+use std::{convert::TryFrom, fmt};
+
+/// An estimate of how much data `Locator::local_data` will return, computed
+/// before any data is read. Each field is `None` if the source can't cheaply
+/// tell us.
+///
+/// These values are only hints. They may come from table statistics or from a
+/// directory listing which changes before we read it, so they should never be
+/// relied on for correctness.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SizeHint {
+    /// The number of `CsvStream` values we expect to see.
+    pub stream_count: Option<usize>,
+    /// The approximate total size of the data, in bytes.
+    pub total_bytes: Option<u64>,
+}
+
+impl SizeHint {
+    /// A hint describing a list of streams with the specified sizes.
+    pub(crate) fn from_sizes<I>(sizes: I) -> SizeHint
+    where
+        I: IntoIterator<Item = u64>,
+    {
+        let mut stream_count = 0;
+        let mut total_bytes = 0;
+        for size in sizes {
+            stream_count += 1;
+            total_bytes += size;
+        }
+        SizeHint {
+            stream_count: Some(stream_count),
+            total_bytes: Some(total_bytes),
+        }
+    }
+
+    /// How many streams will we have after rechunking to `chunk_size` bytes?
+    pub fn stream_count_after_rechunking(&self, chunk_size: usize) -> Option<usize> {
+        let total_bytes = self.total_bytes?;
+        let chunk_size = chunk_size.max(1) as u64;
+        usize::try_from(((total_bytes + chunk_size - 1) / chunk_size).max(1)).ok()
+    }
+}
+
+impl fmt::Display for SizeHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.stream_count {
+            Some(count) => write!(f, "{} streams", count)?,
+            None => write!(f, "unknown streams")?,
+        }
+        match self.total_bytes {
+            Some(bytes) => write!(f, ", {} bytes", bytes),
+            None => write!(f, ", unknown size"),
+        }
+    }
+}
+
+#[test]
+fn from_sizes_counts_and_sums() {
+    let hint = SizeHint::from_sizes(vec![10, 20, 5]);
+    assert_eq!(hint.stream_count, Some(3));
+    assert_eq!(hint.total_bytes, Some(35));
+    assert_eq!(hint.to_string(), "3 streams, 35 bytes");
+    assert_eq!(
+        SizeHint::default().to_string(),
+        "unknown streams, unknown size"
+    );
+}
+
+#[test]
+fn stream_count_after_rechunking_rounds_up() {
+    let hint = SizeHint::from_sizes(vec![250]);
+    assert_eq!(hint.stream_count_after_rechunking(100), Some(3));
+    assert_eq!(hint.stream_count_after_rechunking(1000), Some(1));
+    assert_eq!(
+        SizeHint::from_sizes(vec![]).stream_count_after_rechunking(100),
+        Some(1)
+    );
+    assert_eq!(SizeHint::default().stream_count_after_rechunking(100), None);
+}
@@ -2,16 +2,45 @@
 
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
-use std::iter;
+use std::{
+    fmt, iter, mem,
+    sync::{Arc, Mutex},
+};
 
 use crate::common::*;
 use crate::config::Configuration;
 
+/// The prefix we use for temporary directory names, so that `clean-temp` can
+/// tell our directories apart from anything else in temporary storage.
+const TEMP_DIR_PREFIX: &str = "dbcrossbar-tmp-";
+
+/// A function which deletes a temporary directory.
+type DeleteFn = Box<dyn FnOnce(Context) -> BoxFuture<()> + Send>;
+
+/// A temporary directory which we should delete once we're done with it.
+struct TrackedTemporary {
+    /// The directory to delete.
+    location: String,
+    /// A function which deletes it.
+    delete: DeleteFn,
+}
+
+impl fmt::Debug for TrackedTemporary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackedTemporary")
+            .field("location", &self.location)
+            .finish()
+    }
+}
+
 /// Provides different types of temporary storage.
 #[derive(Clone, Debug)]
 pub struct TemporaryStorage {
     /// Various places we can store things temporarily.
     locations: Vec<String>,
+    /// Temporary directories which we should delete when we're done. This is
+    /// shared between all clones of this `TemporaryStorage`.
+    tracked: Arc<Mutex<Vec<TrackedTemporary>>>,
 }
 
 impl TemporaryStorage {
@@ -19,7 +48,10 @@ impl TemporaryStorage {
     /// of locator-like strings, such as `gs://bucket/tempdir` or
     /// `bigquery:project:dataset`.
     pub fn new(locations: Vec<String>) -> Self {
-        TemporaryStorage { locations }
+        TemporaryStorage {
+            locations,
+            tracked: Arc::new(Mutex::new(vec![])),
+        }
     }
 
     /// Like `new`, but also use temporaries from `config`.
@@ -29,7 +61,7 @@ impl TemporaryStorage {
     ) -> Result<Self> {
         // These go _after_, so that they can be overridden by values in `locations`.
         locations.extend(config.temporaries()?);
-        Ok(TemporaryStorage { locations })
+        Ok(TemporaryStorage::new(locations))
     }
 
     /// Find a location with the specified scheme.
@@ -41,6 +73,39 @@ impl TemporaryStorage {
             .map(|l| l.as_str())
     }
 
+    /// Call `delete` to delete the temporary directory `location` when
+    /// `delete_tracked` is called. Drivers should only do this for
+    /// temporaries which nobody will need once our copy has finished.
+    pub fn delete_when_done<F>(&self, location: String, delete: F)
+    where
+        F: FnOnce(Context) -> BoxFuture<()> + Send + 'static,
+    {
+        self.tracked
+            .lock()
+            .expect("lock poisoned")
+            .push(TrackedTemporary {
+                location,
+                delete: Box::new(delete),
+            });
+    }
+
+    /// Delete every temporary directory passed to `delete_when_done`. This is
+    /// best-effort: we log any errors and carry on, because a failed cleanup
+    /// shouldn't turn a successful copy into a failure. Anything left behind
+    /// can be removed later using `dbcrossbar clean-temp`.
+    pub async fn delete_tracked(&self, ctx: &Context) {
+        let tracked = mem::take(&mut *self.tracked.lock().expect("lock poisoned"));
+        for temporary in tracked {
+            debug!(ctx.log(), "deleting temporary {}", temporary.location);
+            if let Err(err) = (temporary.delete)(ctx.clone()).await {
+                warn!(
+                    ctx.log(),
+                    "could not delete temporary {}: {}", temporary.location, err,
+                );
+            }
+        }
+    }
+
     /// Generate a random alphanumeric tag for use in temporary directory names.
     pub fn random_tag() -> String {
         let mut rng = thread_rng();
@@ -49,6 +114,23 @@ impl TemporaryStorage {
             .take(10)
             .collect::<String>()
     }
+
+    /// Could `tag` have been generated by `random_tag`?
+    fn is_random_tag(tag: &str) -> bool {
+        tag.len() == 10 && tag.chars().all(|c| c.is_ascii_alphanumeric())
+    }
+
+    /// Generate a name for a new temporary directory, like
+    /// `dbcrossbar-tmp-a1B2c3D4e5`.
+    pub fn temp_dir_name() -> String {
+        format!("{}{}", TEMP_DIR_PREFIX, Self::random_tag())
+    }
+
+    /// Could `name` have been generated by `temp_dir_name`?
+    pub(crate) fn is_temp_dir_name(name: &str) -> bool {
+        name.starts_with(TEMP_DIR_PREFIX)
+            && Self::is_random_tag(&name[TEMP_DIR_PREFIX.len()..])
+    }
 }
 
 #[test]
@@ -62,7 +144,46 @@ fn find_schema() {
     assert_eq!(storage.find_scheme("gs:"), Some("gs://example/1/"));
 }
 
+#[test]
+fn delete_tracked_runs_each_deletion_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let (ctx, _worker_fut) = Context::create_for_test("delete_tracked");
+    let storage = TemporaryStorage::new(vec![]);
+    let deleted = Arc::new(AtomicUsize::new(0));
+    for i in 0..2 {
+        let deleted = deleted.clone();
+        storage
+            .clone()
+            .delete_when_done(format!("s3://example/{}/", i), move |_| {
+                async move {
+                    deleted.fetch_add(1, Ordering::SeqCst);
+                    Err(format_err!("errors are ignored"))
+                }
+                .boxed()
+            });
+    }
+    futures::executor::block_on(storage.delete_tracked(&ctx));
+    futures::executor::block_on(storage.delete_tracked(&ctx));
+    assert_eq!(deleted.load(Ordering::SeqCst), 2);
+}
+
 #[test]
 fn random_tag() {
-    assert_eq!(TemporaryStorage::random_tag().len(), 10);
+    let tag = TemporaryStorage::random_tag();
+    assert_eq!(tag.len(), 10);
+    assert!(TemporaryStorage::is_random_tag(&tag));
+    assert!(!TemporaryStorage::is_random_tag("not-a-tag!"));
+}
+
+#[test]
+fn temp_dir_name() {
+    let name = TemporaryStorage::temp_dir_name();
+    assert!(name.starts_with("dbcrossbar-tmp-"));
+    assert!(TemporaryStorage::is_temp_dir_name(&name));
+    assert!(!TemporaryStorage::is_temp_dir_name("a1B2c3D4e5"));
+    assert!(!TemporaryStorage::is_temp_dir_name("dbcrossbar-tmp-"));
+    assert!(!TemporaryStorage::is_temp_dir_name(
+        "dbcrossbar-tmp-backups"
+    ));
 }
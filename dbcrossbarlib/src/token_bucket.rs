@@ -0,0 +1,99 @@
+//! A token bucket, for limiting how fast we send rows or bytes.
+
+use std::time::Duration;
+use tokio::time::{delay_for, Instant};
+
+/// A classic token bucket. Tokens accumulate at `rate` per second, up to a
+/// maximum of `capacity`, and each row (or byte) sent consumes one token.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    /// How many tokens are added per second.
+    rate: f64,
+    /// The maximum number of tokens we can accumulate.
+    capacity: f64,
+    /// The smallest `capacity` we allow, so that we can always send a batch.
+    min_capacity: f64,
+    /// The number of tokens currently available.
+    available: f64,
+    /// When we last updated `available`.
+    last_refill: Instant,
+}
+
+/// Convert a count of rows or bytes to tokens. Counts are far smaller than
+/// 2^52, so this never loses precision in practice.
+#[allow(clippy::cast_precision_loss)]
+fn tokens(count: usize) -> f64 {
+    count as f64
+}
+
+impl TokenBucket {
+    /// Create a new, full bucket. We allow bursts of up to one second of data
+    /// or one full batch, whichever is larger.
+    pub(crate) fn new(rate: f64, batch_size: usize) -> Self {
+        let min_capacity = tokens(batch_size);
+        let capacity = rate.max(min_capacity);
+        Self {
+            rate,
+            capacity,
+            min_capacity,
+            available: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Add any tokens which have accumulated since our last refill.
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.available =
+            (self.available + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Change our rate, keeping any tokens which have already accumulated.
+    pub(crate) fn set_rate(&mut self, rate: f64) {
+        self.refill(Instant::now());
+        self.rate = rate;
+        self.capacity = rate.max(self.min_capacity);
+        self.available = self.available.min(self.capacity);
+    }
+
+    /// How long do we need to wait before we can take `count` tokens?
+    fn wait_time(&self, count: usize) -> Duration {
+        let needed = tokens(count).min(self.capacity) - self.available;
+        if needed <= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(needed / self.rate)
+        }
+    }
+
+    /// Wait until `count` tokens are available, and then take them.
+    pub(crate) async fn take(&mut self, count: usize) {
+        loop {
+            self.refill(Instant::now());
+            let wait = self.wait_time(count);
+            if wait == Duration::from_secs(0) {
+                self.available -= tokens(count);
+                return;
+            }
+            delay_for(wait).await;
+        }
+    }
+}
+
+#[test]
+fn token_bucket_refills_at_rate() {
+    let mut bucket = TokenBucket::new(100.0, 10);
+    assert_eq!(bucket.wait_time(100), Duration::from_secs(0));
+    bucket.available -= 100.0;
+    assert_eq!(bucket.wait_time(50), Duration::from_millis(500));
+
+    let later = bucket.last_refill + Duration::from_millis(250);
+    bucket.refill(later);
+    assert_eq!(bucket.wait_time(50), Duration::from_millis(250));
+
+    // We never accumulate more than `capacity` tokens.
+    let much_later = later + Duration::from_secs(60);
+    bucket.refill(much_later);
+    assert!((bucket.available - 100.0).abs() < 1e-9);
+}
@@ -7,7 +7,13 @@ use bytes::Bytes;
 use futures::{
     self, executor::block_on, stream, Sink, SinkExt, TryStream, TryStreamExt,
 };
-use std::{cmp::min, error, fmt, panic, pin::Pin, result};
+use std::{
+    cmp::min,
+    error, fmt, panic,
+    pin::Pin,
+    result,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 use tokio::{io, process::Child, sync::mpsc, task};
 
 use crate::common::*;
@@ -355,6 +361,143 @@ where
     }
 }
 
+/// Like `spawn_blocking`, but for a stage which holds its thread until a stream
+/// finishes, like a CSV transform.
+///
+/// If `RuntimeOptions::max_blocking_threads` doesn't leave room for another
+/// stage, we fail immediately. Waiting for a free thread could deadlock, because
+/// the running stages may be waiting for data from this one.
+pub(crate) async fn spawn_blocking_stage<F, T>(f: F) -> Result<T>
+where
+    F: (FnOnce() -> Result<T>) + Send + 'static,
+    T: Send + 'static,
+{
+    let permit = STAGE_LIMIT.acquire()?;
+    spawn_blocking(move || {
+        let _permit = permit;
+        f()
+    })
+    .await
+}
+
+/// How many long-running stages we allow to run at once. This is global,
+/// because `max_blocking_threads` applies to our whole runtime.
+static STAGE_LIMIT: StageLimit = StageLimit::new();
+
+/// Keeps track of how many long-running stages are using blocking threads.
+struct StageLimit {
+    /// The maximum number of stages, or 0 for no limit.
+    max: AtomicUsize,
+    /// The number of stages currently running.
+    running: AtomicUsize,
+}
+
+impl StageLimit {
+    /// Create a new limit, which does not restrict the number of stages.
+    const fn new() -> Self {
+        StageLimit {
+            max: AtomicUsize::new(0),
+            running: AtomicUsize::new(0),
+        }
+    }
+
+    /// Allow at most `max` stages, or any number if `max` is `None`.
+    fn set_max(&self, max: Option<usize>) {
+        self.max.store(max.unwrap_or(0), Ordering::SeqCst);
+    }
+
+    /// Reserve a thread for a new stage, or fail if too many are running.
+    fn acquire(&'static self) -> Result<StagePermit> {
+        let max = self.max.load(Ordering::SeqCst);
+        let running = self.running.fetch_add(1, Ordering::SeqCst);
+        let permit = StagePermit { limit: self };
+        if max != 0 && running >= max {
+            return Err(format_err!(
+                "too many CSV transforms and other CPU-heavy stages (limit {}); \
+                 use a larger --max-blocking-threads or a smaller --max-streams",
+                max,
+            ));
+        }
+        Ok(permit)
+    }
+}
+
+/// A running stage. When this is dropped, the stage's thread is released.
+struct StagePermit {
+    limit: &'static StageLimit,
+}
+
+impl Drop for StagePermit {
+    fn drop(&mut self) {
+        self.limit.running.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn stage_limit_fails_instead_of_waiting() {
+    static LIMIT: StageLimit = StageLimit::new();
+    LIMIT.set_max(Some(2));
+    let first = LIMIT.acquire().unwrap();
+    let _second = LIMIT.acquire().unwrap();
+    assert!(LIMIT.acquire().is_err());
+    drop(first);
+    let _third = LIMIT.acquire().unwrap();
+    LIMIT.set_max(None);
+    let _fourth = LIMIT.acquire().unwrap();
+}
+
+/// Options for the `tokio` runtime created by `run_futures_with_runtime`.
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeOptions {
+    /// The maximum number of threads to use for blocking work. This includes
+    /// CPU-heavy stages like CSV parsing and transforms (which we run using
+    /// `spawn_blocking` so that they don't starve async I/O), as well as local
+    /// file I/O. Defaults to `tokio`'s own default.
+    ///
+    /// Every active transform holds a thread until its stream finishes, so
+    /// this should be larger than the number of streams being copied in
+    /// parallel times the number of transforms applied to each stream. If it
+    /// isn't, the copy will fail instead of waiting for a free thread.
+    pub max_blocking_threads: Option<usize>,
+}
+
+/// The smallest value we accept for `RuntimeOptions::max_blocking_threads`.
+const MIN_BLOCKING_THREADS: usize = 4;
+
+/// How many blocking threads we keep free for short-lived work like local file
+/// I/O. The rest may be used by long-running stages.
+const RESERVED_BLOCKING_THREADS: usize = 2;
+
+impl RuntimeOptions {
+    /// Build a `tokio` runtime using these options.
+    fn build_runtime(&self) -> Result<tokio::runtime::Runtime> {
+        let mut builder = tokio::runtime::Builder::new();
+        builder.basic_scheduler().enable_all();
+        if let Some(max_blocking_threads) = self.max_blocking_threads {
+            if max_blocking_threads < MIN_BLOCKING_THREADS {
+                return Err(format_err!(
+                    "must use at least {} blocking threads",
+                    MIN_BLOCKING_THREADS,
+                ));
+            }
+            builder.max_threads(max_blocking_threads);
+        }
+        STAGE_LIMIT.set_max(
+            self.max_blocking_threads
+                .map(|max| max - RESERVED_BLOCKING_THREADS),
+        );
+        Ok(builder.build().context("unable to create a runtime")?)
+    }
+}
+
+#[test]
+fn runtime_options_reject_too_few_blocking_threads() {
+    let options = RuntimeOptions {
+        max_blocking_threads: Some(1),
+    };
+    assert!(options.build_runtime().is_err());
+}
+
 /// Create a new `tokio` runtime and use it to run `cmd_future` (which carries
 /// out whatever task we want to perform), and `worker_future` (which should
 /// have been created by `Context::create` or `Context::create_for_test`).
@@ -370,6 +513,19 @@ where
 pub fn run_futures_with_runtime(
     cmd_future: BoxFuture<()>,
     worker_future: BoxFuture<()>,
+) -> Result<()> {
+    run_futures_with_runtime_options(
+        cmd_future,
+        worker_future,
+        &RuntimeOptions::default(),
+    )
+}
+
+/// Like `run_futures_with_runtime`, but configure the runtime using `options`.
+pub fn run_futures_with_runtime_options(
+    cmd_future: BoxFuture<()>,
+    worker_future: BoxFuture<()>,
+    options: &RuntimeOptions,
 ) -> Result<()> {
     // Wait for both `cmd_fut` and `copy_fut` to finish, but bail out as soon
     // as either returns an error. This involves some pretty deep `tokio` magic:
@@ -382,8 +538,7 @@ pub fn run_futures_with_runtime(
     };
 
     // Pass `combined_fut` to our `tokio` runtime, and wait for it to finish.
-    let mut runtime =
-        tokio::runtime::Runtime::new().expect("Unable to create a runtime");
+    let mut runtime = options.build_runtime()?;
     runtime.block_on(combined_fut.boxed())?;
     Ok(())
 }
@@ -510,21 +665,6 @@ pub(crate) type IdiomaticBytesStream = Pin<
     >,
 >;
 
-/// Convert an HTTP response into a `BoxStream<BytesMut>`.
-///
-/// This is limited to a single concrete input stream type.
-pub(crate) fn http_response_stream(
-    response: reqwest::Response,
-) -> BoxStream<BytesMut> {
-    response
-        .bytes_stream()
-        // Convert `Bytes` to `BytesMut` by copying, which is slightly
-        // expensive.
-        .map_ok(|chunk| BytesMut::from(chunk.as_ref()))
-        .map_err(|err| err.into())
-        .boxed()
-}
-
 /// Convert a `BoxStream<BytesMut>` to something more idiomatic.
 pub(crate) fn idiomatic_bytes_stream(
     ctx: &Context,
@@ -1,13 +1,14 @@
 //! Tools for transforming data streams.
 
 use crate::common::*;
-use crate::tokio_glue::{SyncStreamReader, SyncStreamWriter};
+use crate::tokio_glue::{spawn_blocking_stage, SyncStreamReader, SyncStreamWriter};
 
 /// Run a synchronous transform in a separate thread.
 ///
 /// Given a synchronous function `transform` that reads data from an
 /// implementation of `Read`, transforms it, and writes it to an implementation
-/// of `Write`, spawn a background thread to run the transform.
+/// of `Write`, spawn a background thread to run the transform. This fails if
+/// `--max-blocking-threads` doesn't leave room for another thread.
 pub(crate) fn spawn_sync_transform<T>(
     ctx: Context,
     name: String,
@@ -31,7 +32,7 @@ where
     let (wtr, output) = SyncStreamWriter::pipe(wtr_ctx);
 
     let transform_ctx = ctx.clone();
-    let transform_fut = spawn_blocking(move || -> Result<()> {
+    let transform_fut = spawn_blocking_stage(move || -> Result<()> {
         transform(transform_ctx, Box::new(rdr), Box::new(wtr))
     });
     ctx.spawn_worker(transform_fut.boxed());
@@ -0,0 +1,31 @@
+//! Generating unique identifiers for our schema output drivers.
+
+use std::collections::HashSet;
+
+/// Return `name`, or `name` followed by `sep` and a numeric suffix if `name`
+/// is already in `used`. The name we return is added to `used`.
+pub(crate) fn unique_name(
+    used: &mut HashSet<String>,
+    name: String,
+    sep: &str,
+) -> String {
+    let mut candidate = name.clone();
+    let mut counter = 2;
+    while used.contains(&candidate) {
+        candidate = format!("{}{}{}", name, sep, counter);
+        counter += 1;
+    }
+    used.insert(candidate.clone());
+    candidate
+}
+
+#[test]
+fn unique_name_adds_suffixes() {
+    let mut used = HashSet::new();
+    assert_eq!(unique_name(&mut used, "a".to_owned(), "_"), "a");
+    assert_eq!(unique_name(&mut used, "a".to_owned(), "_"), "a_2");
+    assert_eq!(unique_name(&mut used, "a".to_owned(), "_"), "a_3");
+    assert_eq!(unique_name(&mut used, "a_2".to_owned(), "_"), "a_2_2");
+    assert_eq!(unique_name(&mut used, "B".to_owned(), ""), "B");
+    assert_eq!(unique_name(&mut used, "B".to_owned(), ""), "B2");
+}
@@ -0,0 +1,279 @@
+//! Checking that the values in CSV streams match our schema.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use geo_types::Geometry;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::str;
+use uuid::Uuid;
+
+use crate::common::*;
+use crate::from_csv_cell::FromCsvCell;
+use crate::schema::DataType;
+use crate::transform::spawn_sync_transform;
+
+/// Check every value in `streams` against `schema`, failing with the stream,
+/// row and column of the first value which doesn't match.
+pub(crate) fn validate_csv_streams(
+    ctx: &Context,
+    schema: &Table,
+    streams: BoxStream<CsvStream>,
+) -> BoxStream<CsvStream> {
+    let ctx = ctx.child(o!("streams_transform" => "validate_csv_streams"));
+    let schema = schema.to_owned();
+    streams
+        .map(move |stream| {
+            let stream = stream?;
+            let schema = schema.clone();
+            let name = stream.name.clone();
+            let data = spawn_sync_transform(
+                ctx.child(o!("stream" => name.clone())),
+                "validate_csv".to_owned(),
+                stream.data,
+                move |_ctx, rdr, wtr| validate_csv(&schema, &name, rdr, wtr),
+            )?;
+            Ok(CsvStream {
+                name: stream.name,
+                metadata: stream.metadata,
+                data,
+            })
+        })
+        .boxed()
+}
+
+/// Copy CSV data from `rdr` to `wtr`, checking each value against `schema`.
+fn validate_csv<R, W>(schema: &Table, stream_name: &str, rdr: R, wtr: W) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+
+    let header = rdr
+        .byte_headers()
+        .context("cannot read CSV header")?
+        .to_owned();
+    let names_match = header.len() == schema.columns.len()
+        && header
+            .iter()
+            .zip(&schema.columns)
+            .all(|(name, col)| name == col.name.as_bytes());
+    if !names_match {
+        return Err(format_err!(
+            "{}: CSV header {:?} does not match schema columns {:?}",
+            stream_name,
+            String::from_utf8_lossy(header.as_slice()),
+            schema.columns.iter().map(|c| &c.name).collect::<Vec<_>>(),
+        ));
+    }
+    wtr.write_byte_record(&header)
+        .context("cannot write CSV header")?;
+
+    let mut row = csv::ByteRecord::new();
+    let mut row_idx = 1;
+    while rdr
+        .read_byte_record(&mut row)
+        .with_context(|_| format!("{}: cannot read row {}", stream_name, row_idx))?
+    {
+        for (cell, col) in row.iter().zip(&schema.columns) {
+            let result = if cell.is_empty() {
+                if col.is_nullable {
+                    Ok(())
+                } else {
+                    Err(format_err!("found NULL in non-nullable column"))
+                }
+            } else {
+                str::from_utf8(cell)
+                    .map_err(|_| format_err!("found invalid UTF-8"))
+                    .and_then(|cell| validate_cell(&col.data_type, cell))
+            };
+            result.map_err(|err| {
+                format_err!(
+                    "{}: row {}, column {}: {}",
+                    stream_name,
+                    row_idx,
+                    col.name,
+                    err,
+                )
+            })?;
+        }
+        wtr.write_byte_record(&row).context("cannot write row")?;
+        row_idx += 1;
+    }
+    wtr.flush().context("error flushing output")?;
+    Ok(())
+}
+
+/// Make sure `cell` can be parsed as `data_type`.
+fn validate_cell(data_type: &DataType, cell: &str) -> Result<()> {
+    lazy_static! {
+        static ref DECIMAL_RE: Regex =
+            Regex::new(r"^[-+]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?:[eE][-+]?[0-9]+)?$")
+                .expect("invalid `DECIMAL_RE` in source");
+    }
+
+    match data_type {
+        // Arrays and structs are represented as JSON.
+        DataType::Array(_) | DataType::Json | DataType::Struct(_) => {
+            serde_json::Value::from_csv_cell(cell).map(|_| ())
+        }
+        DataType::Bool => bool::from_csv_cell(cell).map(|_| ()),
+        DataType::Date => NaiveDate::from_csv_cell(cell).map(|_| ()),
+        DataType::Decimal => {
+            if DECIMAL_RE.is_match(cell) {
+                Ok(())
+            } else {
+                Err(format_err!("cannot parse decimal {:?}", cell))
+            }
+        }
+        DataType::FixedDecimal { precision, scale } => {
+            if !DECIMAL_RE.is_match(cell) {
+                return Err(format_err!("cannot parse decimal {:?}", cell));
+            }
+            let (int_digits, frac_digits) = decimal_digits(cell)?;
+            let (precision, scale) = (i64::from(*precision), i64::from(*scale));
+            if int_digits <= precision - scale && frac_digits <= scale {
+                Ok(())
+            } else {
+                Err(format_err!(
+                    "decimal {:?} does not fit in NUMERIC({}, {})",
+                    cell,
+                    precision,
+                    scale,
+                ))
+            }
+        }
+        DataType::Float32 => f32::from_csv_cell(cell).map(|_| ()),
+        DataType::Float64 => f64::from_csv_cell(cell).map(|_| ()),
+        DataType::GeoJson(_) => Geometry::<f64>::from_csv_cell(cell).map(|_| ()),
+        DataType::Int16 => i16::from_csv_cell(cell).map(|_| ()),
+        DataType::Int32 => i32::from_csv_cell(cell).map(|_| ()),
+        DataType::Int64 => i64::from_csv_cell(cell).map(|_| ()),
+        DataType::Text => Ok(()),
+        DataType::TimestampWithoutTimeZone => {
+            NaiveDateTime::from_csv_cell(cell).map(|_| ())
+        }
+        DataType::TimestampWithTimeZone => {
+            DateTime::<FixedOffset>::from_csv_cell(cell).map(|_| ())
+        }
+        DataType::Uuid => Uuid::from_csv_cell(cell).map(|_| ()),
+    }
+}
+
+/// Count the digits needed before and after the decimal point to write
+/// `cell`, which must match `DECIMAL_RE`, without leading or trailing zeros.
+fn decimal_digits(cell: &str) -> Result<(i64, i64)> {
+    let mut parts = cell.splitn(2, |c| c == 'e' || c == 'E');
+    let mantissa = parts.next().unwrap_or("");
+    let exponent = match parts.next() {
+        Some(exponent) => exponent
+            .trim_start_matches('+')
+            .parse::<i64>()
+            .map_err(|_| format_err!("exponent too large in {:?}", cell))?,
+        None => 0,
+    };
+    let mantissa = mantissa.trim_start_matches(|c| c == '-' || c == '+');
+    let mut mantissa_parts = mantissa.splitn(2, '.');
+    let int_part = mantissa_parts.next().unwrap_or("");
+    let frac_part = mantissa_parts.next().unwrap_or("");
+
+    // Find our significant digits, and where the decimal point falls in them.
+    let len = |s: &str| i64::try_from(s.len()).unwrap_or(i64::MAX);
+    let digits = format!("{}{}", int_part, frac_part);
+    let significant = digits.trim_start_matches('0');
+    let point = len(int_part)
+        .saturating_add(exponent)
+        .saturating_sub(len(&digits) - len(significant));
+    let significant = significant.trim_end_matches('0');
+    if significant.is_empty() {
+        return Ok((0, 0));
+    }
+    Ok((point.max(0), len(significant).saturating_sub(point).max(0)))
+}
+
+#[test]
+fn decimal_digits_examples() {
+    let examples = &[
+        ("0", (0, 0)),
+        ("-000.000", (0, 0)),
+        ("123.45", (3, 2)),
+        ("+0012.3400", (2, 2)),
+        (".05", (0, 2)),
+        ("1.5e3", (4, 0)),
+        ("12E-4", (0, 4)),
+    ];
+    for &(cell, expected) in examples {
+        assert_eq!(decimal_digits(cell).unwrap(), expected, "{:?}", cell);
+    }
+}
+
+#[test]
+fn validate_csv_values() {
+    use crate::schema::Column;
+
+    let column = |name: &str, is_nullable, data_type| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let schema = Table {
+        name: "t".to_owned(),
+        columns: vec![
+            column("id", false, DataType::Int32),
+            column("amount", true, DataType::Decimal),
+            column("seen", true, DataType::TimestampWithTimeZone),
+            column(
+                "price",
+                true,
+                DataType::FixedDecimal {
+                    precision: 5,
+                    scale: 2,
+                },
+            ),
+        ],
+    };
+
+    let input = "id,amount,seen,price\n1,-12.50,2024-01-01T00:00:00Z,999.990\n2,,,\n";
+    let mut out = vec![];
+    validate_csv(&schema, "data", input.as_bytes(), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), input);
+
+    let bad_inputs = &[
+        (
+            "id,amount,seen,price\n1,1.5,,\nx,,,\n",
+            "data: row 2, column id",
+        ),
+        ("id,amount,seen,price\n,1.5,,\n", "data: row 1, column id"),
+        (
+            "id,amount,seen,price\n1,NaN,,\n",
+            "data: row 1, column amount",
+        ),
+        (
+            "id,amount,seen,price\n1,,yesterday,\n",
+            "data: row 1, column seen",
+        ),
+        (
+            "id,amount,seen,price\n1,,,1000\n",
+            "data: row 1, column price",
+        ),
+        (
+            "id,amount,seen,price\n1,,,0.125\n",
+            "data: row 1, column price",
+        ),
+        ("id,amount\n1,2\n", "does not match schema"),
+    ];
+    for &(input, expected) in bad_inputs {
+        let mut out = vec![];
+        let err = validate_csv(&schema, "data", input.as_bytes(), &mut out)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains(expected),
+            "{:?} should contain {:?}",
+            err,
+            expected
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Parsing geometries in Well-Known Text (WKT) format, like `POINT(-71 42)`.
+//!
+//! This file contains a [`rust-peg`][peg] grammar. We support two-dimensional
+//! geometries, plus an optional `SRID=4326;` prefix (as used by PostGIS
+//! "EWKT"), which we ignore, because the SRID is specified by the column type.
+//!
+//! [peg]: https://github.com/kevinmehall/rust-peg
+
+use geo_types::Geometry;
+
+use crate::common::*;
+
+/// Parse `wkt` as a geometry.
+pub(crate) fn parse_wkt(wkt: &str) -> Result<Geometry<f64>> {
+    wkt_grammar::wkt(wkt)
+        .map_err(|err| format_err!("cannot parse {:?} as WKT: {}", wkt, err))
+}
+
+peg::parser! {
+    grammar wkt_grammar() for str {
+        use geo_types::{
+            Coordinate, Geometry, GeometryCollection, LineString, MultiLineString,
+            MultiPoint, MultiPolygon, Point, Polygon
+        };
+
+        /// A WKT geometry, optionally preceded by an SRID.
+        pub rule wkt() -> Geometry<f64>
+            = ws()? (i("SRID=") ['0'..='9']+ ws()? ";" ws()?)? g:geometry() ws()? { g }
+
+        /// Any kind of geometry.
+        rule geometry() -> Geometry<f64>
+            = i("POINT") ws()? c:coord_list() {?
+                // We have no way to represent an empty point.
+                match c.as_slice() {
+                    [c] => Ok(Geometry::Point(Point(*c))),
+                    _ => Err("one point"),
+                }
+            }
+            / i("LINESTRING") ws()? cs:coord_list_or_empty() {
+                Geometry::LineString(LineString(cs))
+            }
+            / i("POLYGON") ws()? p:polygon_or_empty() {
+                Geometry::Polygon(p)
+            }
+            / i("MULTIPOINT") ws()? ps:multi_point() {
+                Geometry::MultiPoint(MultiPoint(ps))
+            }
+            / i("MULTILINESTRING") ws()? ls:list_or_empty(<coord_list()>) {
+                Geometry::MultiLineString(MultiLineString(
+                    ls.into_iter().map(LineString).collect(),
+                ))
+            }
+            / i("MULTIPOLYGON") ws()? ps:list_or_empty(<polygon()>) {
+                Geometry::MultiPolygon(MultiPolygon(ps))
+            }
+            / i("GEOMETRYCOLLECTION") ws()? gs:list_or_empty(<geometry()>) {
+                Geometry::GeometryCollection(GeometryCollection(gs))
+            }
+            / expected!("WKT geometry type")
+
+        /// The points in a `MULTIPOINT`, which may or may not have their own
+        /// parentheses.
+        rule multi_point() -> Vec<Point<f64>>
+            = cs:list_or_empty(<coord_list()>) {?
+                cs.into_iter()
+                    .map(|c| match c.as_slice() {
+                        [c] => Ok(Point(*c)),
+                        _ => Err("one point"),
+                    })
+                    .collect()
+            }
+            / cs:coord_list() { cs.into_iter().map(Point).collect() }
+
+        /// A polygon, which may be `EMPTY`.
+        rule polygon_or_empty() -> Polygon<f64>
+            = empty() { Polygon::new(LineString(vec![]), vec![]) }
+            / polygon()
+
+        /// A polygon, with an exterior ring and zero or more interior rings.
+        rule polygon() -> Polygon<f64>
+            = rings:list(<coord_list()>) {
+                let mut rings = rings.into_iter().map(LineString);
+                let exterior = rings.next().expect("list should not be empty");
+                Polygon::new(exterior, rings.collect())
+            }
+
+        /// A list of coordinates, which may be `EMPTY`.
+        rule coord_list_or_empty() -> Vec<Coordinate<f64>>
+            = empty() { vec![] }
+            / coord_list()
+
+        /// A parenthesized list of coordinates, like `(1 2, 3 4)`.
+        rule coord_list() -> Vec<Coordinate<f64>>
+            = list(<coord()>)
+
+        /// A single coordinate, like `1 2`.
+        rule coord() -> Coordinate<f64>
+            = x:number() ws() y:number() { Coordinate { x, y } }
+
+        /// A parenthesized list of items, or `EMPTY`.
+        rule list_or_empty<T>(item: rule<T>) -> Vec<T>
+            = empty() { vec![] }
+            / list(<item()>)
+
+        /// A non-empty, parenthesized list of items.
+        rule list<T>(item: rule<T>) -> Vec<T>
+            = "(" ws()? items:(item() ++ (ws()? "," ws()?)) ws()? ")" { items }
+
+        /// The keyword `EMPTY`.
+        rule empty() = i("EMPTY")
+
+        /// A floating point number.
+        rule number() -> f64
+            = quiet! {
+                n:$(
+                    ['-' | '+']? (['0'..='9']+ ("." ['0'..='9']*)? / "." ['0'..='9']+)
+                    (['e' | 'E'] ['-' | '+']? ['0'..='9']+)?
+                ) {? n.parse().or(Err("number")) }
+            }
+            / expected!("number")
+
+        /// One or more characters of whitespace.
+        rule ws() = quiet! { [' ' | '\t' | '\r' | '\n']+ }
+
+        /// Match a string literal, ignoring case.
+        rule i(literal: &'static str)
+            // From https://github.com/kevinmehall/rust-peg/issues/216.
+            = input:$([_]*<{literal.len()}>) {?
+                if input.eq_ignore_ascii_case(literal) {
+                    Ok(())
+                } else {
+                    Err(literal)
+                }
+            }
+    }
+}
+
+#[test]
+fn parse_wkt_geometries() {
+    use geo_types::{Coordinate, LineString, MultiPoint, Point, Polygon};
+
+    assert_eq!(
+        parse_wkt("POINT(-71 42)").unwrap(),
+        Geometry::Point(Point::new(-71.0, 42.0)),
+    );
+    assert_eq!(
+        parse_wkt("SRID=4326; point (-71.5 4.2e1)").unwrap(),
+        Geometry::Point(Point::new(-71.5, 42.0)),
+    );
+    let line = LineString(vec![
+        Coordinate { x: 0.0, y: 0.0 },
+        Coordinate { x: 1.0, y: 1.0 },
+    ]);
+    assert_eq!(
+        parse_wkt("LINESTRING (0 0, 1 1)").unwrap(),
+        Geometry::LineString(line),
+    );
+    let square = |size: f64| {
+        LineString(vec![
+            Coordinate { x: 0.0, y: 0.0 },
+            Coordinate { x: size, y: 0.0 },
+            Coordinate { x: size, y: size },
+            Coordinate { x: 0.0, y: 0.0 },
+        ])
+    };
+    assert_eq!(
+        parse_wkt("POLYGON((0 0, 4 0, 4 4, 0 0), (0 0, 1 0, 1 1, 0 0))").unwrap(),
+        Geometry::Polygon(Polygon::new(square(4.0), vec![square(1.0)])),
+    );
+    let points = MultiPoint(vec![Point::new(1.0, 2.0), Point::new(3.0, 4.0)]);
+    assert_eq!(
+        parse_wkt("MULTIPOINT((1 2), (3 4))").unwrap(),
+        Geometry::MultiPoint(points.clone()),
+    );
+    assert_eq!(
+        parse_wkt("MULTIPOINT(1 2, 3 4)").unwrap(),
+        Geometry::MultiPoint(points),
+    );
+    match parse_wkt("GEOMETRYCOLLECTION(POINT(1 2), MULTIPOLYGON EMPTY)").unwrap() {
+        Geometry::GeometryCollection(gc) => assert_eq!(gc.0.len(), 2),
+        other => panic!("unexpected geometry {:?}", other),
+    }
+
+    let invalid = &[
+        "",
+        "POINT EMPTY",
+        "POINT(1)",
+        "POINT Z(1 2 3)",
+        "CIRCLE(1 2)",
+    ];
+    for &wkt in invalid {
+        assert!(parse_wkt(wkt).is_err(), "should not parse {:?}", wkt);
+    }
+}
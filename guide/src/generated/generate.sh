@@ -10,12 +10,12 @@ function dbxb {
     ../../../target/debug/dbcrossbar --enable-unstable "$@" 2>&1
 }
 
-for c in cp count "schema conv"; do
-    dbxb $c --help | tail -n +2 > "$(echo "$c" | sed 's/ /_/g')"_help.txt
+for c in cp count doctor clean-temp "schema conv"; do
+    dbxb $c --help | tail -n +2 > "$(echo "$c" | sed 's/[ -]/_/g')"_help.txt
 done
 
 dbxb features > features.txt
 
-for d in bigml bigquery csv gs postgres redshift s3 shopify; do
+for d in abfss bigml bigquery csv db2 exec fake file gs hive https jdbc null postgres redshift s3 shopify singer-tap singer-target webdav; do
     dbxb features $d > features_$d.txt
 done
\ No newline at end of file